@@ -1915,8 +1915,19 @@ def refreshShapers():
                                 return ''
                             if chosen == 'fq_codel':
                                 return 'fq_codel'
-                            if chosen == 'cake':
+                            if chosen == 'cake' or chosen.startswith('cake:'):
                                 cake_base = base if base.startswith('cake') else 'cake diffserv4'
+                                if chosen.startswith('cake:'):
+                                    # CAKE profile: replace tin mode / ACK filter of the base
+                                    tin_modes = ('besteffort', 'diffserv3', 'diffserv4', 'diffserv8', 'precedence')
+                                    parts = cake_base.split()
+                                    for option in chosen.split(':')[1:]:
+                                        if 'ack-filter' in option:
+                                            parts = [p for p in parts if 'ack-filter' not in p]
+                                        else:
+                                            parts = [p for p in parts if p not in tin_modes]
+                                        parts.append(option)
+                                    cake_base = ' '.join(parts)
                                 return sqmFixupRate(rate, cake_base)
                             return sqmFixupRate(rate, base)
                         sqm_override = circuit['sqm'] if 'sqm' in circuit else None
@@ -122,6 +122,32 @@ def example_bulk_tc_commands():
         print("WARNING: Failed to execute TC commands in bulk")
     return success
 
+def example_bakery_batch(queues_available, stick_offset):
+    """Example of a custom CRM integration driving the Bakery without LibreQoS.py"""
+    batch = liblqos_python.BakeryBatch()
+    batch.setup_mq(queues_available, stick_offset)
+    try:
+        # Sites and circuits are checked as they are added: tc handles, class numbering,
+        # rates, IP addresses and SQM overrides. Bad input raises ValueError.
+        batch.add_site("Tower 1", "0x1:0x2", "0x3:0x2", 0x3, 100, 100, 1000, 1000)
+        batch.add_circuit(
+            "Customer 1", "Tower 1", "0x1:0x3", "0x3:0x3", 0x4,
+            5, 2, 100, 20, 0x1, 0x3, "100.64.0.10/32", sqm_override="cake:besteffort"
+        )
+    except ValueError as e:
+        print(f"WARNING: Not sending an invalid batch: {e}")
+        return False
+
+    # Compare with the last batch committed through BakeryBatch before sending anything
+    print(batch.diff())
+
+    # Sent in chunks; only committed if every chunk was accepted
+    result = batch.commit()
+    if not result.committed:
+        for error in result.errors:
+            print(f"WARNING: {error}")
+    return result.committed
+
 # Integration points in LibreQoS.py:
 # 
 # 1. Line 134 - clearPriorSettings():
@@ -148,4 +174,5 @@ if __name__ == "__main__":
     print("  - bakery_add_structural_htb_class(...)")
     print("  - bakery_add_circuit_htb_class(...)")
     print("  - bakery_add_circuit_qdisc(...)")
-    print("  - bakery_execute_tc_commands(...)")
\ No newline at end of file
+    print("  - bakery_execute_tc_commands(...)")
+    print("  - BakeryBatch (validated batch building and commit)")
\ No newline at end of file
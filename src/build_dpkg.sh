@@ -65,6 +65,8 @@ RUSTPROGS=(
   lqos_map_perf
   uisp_integration
   lqos_overrides
+  lqos
+  lqos_config
 )
 
 ####################################################
@@ -108,7 +110,10 @@ cargo build --release \
   -p lqos_map_perf \
   -p uisp_integration \
   -p lqos_python \
-  -p lqos_overrides
+  -p lqos_overrides \
+  -p lqos \
+  -p lqos_config \
+  --features lqos_config/cli
 popd > /dev/null || exit
 
 # Create the post-installation file
@@ -38,7 +38,7 @@ rustup update
 
 # Start building
 echo "Please wait while the system is compiled. Service will not be interrupted during this stage."
-PROGS="lqosd lqtop xdp_iphash_to_cpu_cmdline xdp_pping lqusers lqos_setup lqos_map_perf uisp_integration lqos_overrides"
+PROGS="lqosd lqtop xdp_iphash_to_cpu_cmdline xdp_pping lqusers lqos_setup lqos_map_perf uisp_integration lqos_overrides lqos lqos_config"
 mkdir -p bin/static
 pushd rust > /dev/null || exit
 #cargo clean
@@ -63,8 +63,14 @@ do
         fi
         popd > /dev/null || exit
     else
+      # The lqos_config tool is behind a feature so the library stays free of CLI dependencies
+      if [ $prog == "lqos_config" ]; then
+          FEATURE="-F cli"
+      else
+          FEATURE=""
+      fi
       pushd $prog > /dev/null || exit
-      cargo build $BUILD_FLAGS
+      cargo build $BUILD_FLAGS $FEATURE
       if [ $? -ne 0 ]; then
         echo "Cargo build failed. Exiting with code 1."
         exit 1
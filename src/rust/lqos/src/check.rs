@@ -0,0 +1,338 @@
+//! `lqos check`: a Nagios/Icinga-compatible health probe.
+//!
+//! Prints one status line with perfdata and exits 0 (OK), 1 (WARNING), 2 (CRITICAL) or
+//! 3 (UNKNOWN), per the monitoring plugin guidelines.
+
+use clap::Args;
+use lqos_bus::{BusRequest, BusResponse, HealthSnapshot, UrgentIssue, UrgentSeverity, bus_request};
+
+#[derive(Args)]
+pub struct CheckArgs {
+    /// Warn when any CPU core is at or above this percentage
+    #[arg(long, default_value_t = 90)]
+    cpu_warning: u32,
+
+    /// Critical when any CPU core is at or above this percentage
+    #[arg(long, default_value_t = 98)]
+    cpu_critical: u32,
+
+    /// Warn when the XDP IP map is at or above this percentage of its capacity
+    #[arg(long, default_value_t = 80)]
+    mappings_warning: u32,
+
+    /// Critical when the XDP IP map is at or above this percentage of its capacity
+    #[arg(long, default_value_t = 95)]
+    mappings_critical: u32,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+enum Status {
+    Ok,
+    Warning,
+    Critical,
+    /// The check itself could not run, so the shaper's state is unknown
+    Unknown,
+}
+
+impl Status {
+    fn label(self) -> &'static str {
+        match self {
+            Status::Ok => "OK",
+            Status::Warning => "WARNING",
+            Status::Critical => "CRITICAL",
+            Status::Unknown => "UNKNOWN",
+        }
+    }
+
+    fn exit_code(self) -> i32 {
+        match self {
+            Status::Ok => 0,
+            Status::Warning => 1,
+            Status::Critical => 2,
+            Status::Unknown => 3,
+        }
+    }
+}
+
+/// Everything the check looks at, gathered in one bus session.
+#[derive(Default)]
+struct Observations {
+    health: Option<HealthSnapshot>,
+    urgent_issues: Vec<UrgentIssue>,
+    scheduler_running: Option<bool>,
+    scheduler_error: Option<String>,
+}
+
+struct Report {
+    status: Status,
+    problems: Vec<String>,
+    perfdata: Vec<String>,
+}
+
+impl Report {
+    fn raise(&mut self, status: Status, problem: String) {
+        self.status = self.status.max(status);
+        self.problems.push(problem);
+    }
+
+    fn line(&self) -> String {
+        let summary = if self.problems.is_empty() {
+            "lqosd is healthy".to_string()
+        } else {
+            self.problems.join("; ")
+        };
+        if self.perfdata.is_empty() {
+            return format!("LIBREQOS {} - {summary}", self.status.label());
+        }
+        format!(
+            "LIBREQOS {} - {summary} | {}",
+            self.status.label(),
+            self.perfdata.join(" ")
+        )
+    }
+}
+
+pub fn requests() -> Vec<BusRequest> {
+    vec![
+        BusRequest::GetHealthSnapshot,
+        BusRequest::GetUrgentIssues,
+        BusRequest::CheckSchedulerStatus,
+    ]
+}
+
+/// Runs the check, prints its status line and returns the exit code.
+pub async fn run(args: &CheckArgs) -> i32 {
+    let responses = match bus_request(requests()).await {
+        Ok(responses) => responses,
+        Err(e) => {
+            let report = bus_failure(&e);
+            println!("{}", report.line());
+            return report.status.exit_code();
+        }
+    };
+
+    let mut observed = Observations::default();
+    for response in responses {
+        match response {
+            BusResponse::HealthSnapshot(health) => observed.health = Some(health),
+            BusResponse::UrgentIssues(issues) => observed.urgent_issues = issues,
+            BusResponse::SchedulerStatus { running, error } => {
+                observed.scheduler_running = Some(running);
+                observed.scheduler_error = error;
+            }
+            _ => {}
+        }
+    }
+
+    let report = evaluate(&observed, args);
+    println!("{}", report.line());
+    report.status.exit_code()
+}
+
+/// A bus or connection failure means nothing was measured, which the plugin guidelines
+/// report as UNKNOWN rather than as a problem with the shaper.
+fn bus_failure(error: &dyn std::fmt::Display) -> Report {
+    Report {
+        status: Status::Unknown,
+        problems: vec![format!("unable to query lqosd: {error}")],
+        perfdata: Vec::new(),
+    }
+}
+
+fn evaluate(observed: &Observations, args: &CheckArgs) -> Report {
+    let mut report = Report {
+        status: Status::Ok,
+        problems: Vec::new(),
+        perfdata: Vec::new(),
+    };
+
+    match &observed.health {
+        Some(health) => check_health(&mut report, health, args),
+        None => report.raise(
+            Status::Warning,
+            "lqosd did not report health counters (is it older than this tool?)".to_string(),
+        ),
+    }
+
+    let errors = observed
+        .urgent_issues
+        .iter()
+        .filter(|issue| issue.severity == UrgentSeverity::Error)
+        .count();
+    let warnings = observed.urgent_issues.len() - errors;
+    if let Some(first) = observed.urgent_issues.first() {
+        let status = if errors > 0 {
+            Status::Critical
+        } else {
+            Status::Warning
+        };
+        report.raise(
+            status,
+            format!(
+                "{} urgent issue(s), e.g. {}: {}",
+                observed.urgent_issues.len(),
+                first.code,
+                first.message
+            ),
+        );
+    }
+    report.perfdata.push(format!("urgent_errors={errors};;1;0"));
+    report
+        .perfdata
+        .push(format!("urgent_warnings={warnings};1;;0"));
+
+    match observed.scheduler_running {
+        Some(false) => report.raise(Status::Critical, "scheduler is not running".to_string()),
+        Some(true) => {}
+        None => report.raise(
+            Status::Warning,
+            "lqosd did not report scheduler status".to_string(),
+        ),
+    }
+    if let Some(error) = &observed.scheduler_error {
+        report.raise(Status::Warning, format!("scheduler error: {error}"));
+    }
+
+    report
+}
+
+fn check_health(report: &mut Report, health: &HealthSnapshot, args: &CheckArgs) {
+    if let Some(reason) = &health.reload_required_reason {
+        report.raise(
+            Status::Warning,
+            format!("Bakery needs a full reload: {reason}"),
+        );
+    }
+    report.perfdata.push(format!(
+        "reload_required={};1;;0;1",
+        u8::from(health.reload_required_reason.is_some())
+    ));
+
+    let capacity = health.ip_mapping_capacity;
+    match health.ip_mappings {
+        Some(mappings) if capacity > 0 => {
+            let percent = mappings as f64 * 100.0 / capacity as f64;
+            if let Some(status) = threshold(percent, args.mappings_warning, args.mappings_critical)
+            {
+                report.raise(
+                    status,
+                    format!("XDP IP map {percent:.0}% full ({mappings} of {capacity})"),
+                );
+            }
+            report.perfdata.push(format!(
+                "ip_mappings={mappings};{};{};0;{capacity}",
+                capacity * args.mappings_warning as usize / 100,
+                capacity * args.mappings_critical as usize / 100,
+            ));
+        }
+        _ => report.raise(Status::Warning, "unable to read the XDP IP map".to_string()),
+    }
+
+    if let Some(&busiest) = health.cpu_usage.iter().max() {
+        if let Some(status) = threshold(busiest as f64, args.cpu_warning, args.cpu_critical) {
+            report.raise(status, format!("busiest CPU core at {busiest}%"));
+        }
+        let average = health.cpu_usage.iter().sum::<u32>() as f64 / health.cpu_usage.len() as f64;
+        report.perfdata.push(format!(
+            "cpu_max={busiest}%;{};{};0;100",
+            args.cpu_warning, args.cpu_critical
+        ));
+        report
+            .perfdata
+            .push(format!("cpu_avg={average:.1}%;;;0;100"));
+    }
+}
+
+fn threshold(value: f64, warning: u32, critical: u32) -> Option<Status> {
+    if value >= critical as f64 {
+        Some(Status::Critical)
+    } else if value >= warning as f64 {
+        Some(Status::Warning)
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use lqos_bus::UrgentSource;
+
+    fn args() -> CheckArgs {
+        CheckArgs {
+            cpu_warning: 90,
+            cpu_critical: 98,
+            mappings_warning: 80,
+            mappings_critical: 95,
+        }
+    }
+
+    fn healthy() -> Observations {
+        Observations {
+            health: Some(HealthSnapshot {
+                reload_required_reason: None,
+                ip_mappings: Some(100),
+                ip_mapping_capacity: 1000,
+                cpu_usage: vec![10, 30],
+            }),
+            urgent_issues: Vec::new(),
+            scheduler_running: Some(true),
+            scheduler_error: None,
+        }
+    }
+
+    #[test]
+    fn healthy_shaper_is_ok_with_perfdata() {
+        let report = evaluate(&healthy(), &args());
+        assert_eq!(report.status, Status::Ok);
+        assert_eq!(
+            report.line(),
+            "LIBREQOS OK - lqosd is healthy | reload_required=0;1;;0;1 \
+             ip_mappings=100;800;950;0;1000 cpu_max=30%;90;98;0;100 cpu_avg=20.0%;;;0;100 \
+             urgent_errors=0;;1;0 urgent_warnings=0;1;;0"
+        );
+    }
+
+    #[test]
+    fn worst_problem_sets_the_status() {
+        let mut observed = healthy();
+        observed.health.as_mut().unwrap().reload_required_reason = Some("qdisc drift".into());
+        observed.health.as_mut().unwrap().cpu_usage = vec![10, 99];
+        let report = evaluate(&observed, &args());
+        assert_eq!(report.status, Status::Critical);
+        assert_eq!(report.problems.len(), 2);
+
+        let mut observed = healthy();
+        observed.health.as_mut().unwrap().ip_mappings = Some(850);
+        assert_eq!(evaluate(&observed, &args()).status, Status::Warning);
+    }
+
+    #[test]
+    fn urgent_errors_and_stopped_scheduler_are_critical() {
+        let mut observed = healthy();
+        observed.urgent_issues.push(UrgentIssue {
+            id: 1,
+            ts: 0,
+            source: UrgentSource::System,
+            severity: UrgentSeverity::Warning,
+            code: "TEST".to_string(),
+            message: "something".to_string(),
+            context: None,
+            dedupe_key: None,
+        });
+        assert_eq!(evaluate(&observed, &args()).status, Status::Warning);
+
+        observed.scheduler_running = Some(false);
+        assert_eq!(evaluate(&observed, &args()).status, Status::Critical);
+    }
+
+    #[test]
+    fn bus_errors_are_unknown() {
+        let report = bus_failure(&"No such file or directory");
+        assert_eq!(report.status.exit_code(), 3);
+        assert_eq!(
+            report.line(),
+            "LIBREQOS UNKNOWN - unable to query lqosd: No such file or directory"
+        );
+    }
+}
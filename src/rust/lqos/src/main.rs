@@ -0,0 +1,242 @@
+//! `lqos`: read-only queries of a running `lqosd`, printed as a table, JSON or CSV.
+//!
+//! Every subcommand sends one or more `BusRequest`s and prints the reply, so shell scripts
+//! and monitoring checks can read the same data as the web UI without scraping it.
+
+mod check;
+mod output;
+
+use anyhow::{Result, bail};
+use clap::{Args, Parser, Subcommand};
+use lqos_bus::{BusRequest, BusResponse, TopFlowType, bus_request};
+use output::Format;
+use serde_json::{Value, json};
+use std::time::Duration;
+
+#[derive(Parser)]
+#[command(name = "lqos", about = "Query a running lqosd")]
+struct Cli {
+    #[command(flatten)]
+    output: OutputArgs,
+
+    #[command(subcommand)]
+    command: Commands,
+}
+
+#[derive(Args)]
+struct OutputArgs {
+    /// Print JSON (one document per line with --watch)
+    #[arg(long, global = true, conflicts_with = "csv")]
+    json: bool,
+
+    /// Print CSV, with a header row
+    #[arg(long, global = true)]
+    csv: bool,
+
+    /// Repeat every N seconds until interrupted
+    #[arg(long, global = true, value_name = "N")]
+    watch: Option<u64>,
+}
+
+#[derive(Subcommand)]
+enum Commands {
+    /// Current total throughput
+    Throughput,
+    /// Hosts with the most download traffic
+    TopDownloaders {
+        /// Number of hosts
+        #[arg(short, default_value_t = 10)]
+        n: u32,
+    },
+    /// Hosts with the most upload traffic
+    TopUploaders {
+        /// Number of hosts
+        #[arg(short, default_value_t = 10)]
+        n: u32,
+    },
+    /// Hosts with the highest round-trip time
+    WorstRtt {
+        /// Number of hosts
+        #[arg(short, default_value_t = 10)]
+        n: u32,
+    },
+    /// Hosts with the most TCP retransmits
+    WorstRetransmits {
+        /// Number of hosts
+        #[arg(short, default_value_t = 10)]
+        n: u32,
+    },
+    /// Every device of every active circuit
+    Circuits,
+    /// The devices of one circuit
+    Circuit {
+        /// Circuit ID from ShapedDevices.csv
+        circuit_id: String,
+    },
+    /// Active flows to or from an IP address
+    Flows {
+        /// Local or remote IP address
+        ip: String,
+    },
+    /// The busiest flows by bytes
+    TopFlows {
+        /// Number of flows
+        #[arg(short, default_value_t = 10)]
+        n: u32,
+    },
+    /// Current CAKE statistics for one circuit's queues
+    Queue {
+        /// Circuit ID from ShapedDevices.csv
+        circuit_id: String,
+    },
+    /// Total CAKE marks and drops
+    QueueTotals,
+    /// StormGuard's current site limits
+    Stormguard,
+    /// Urgent issues awaiting attention
+    UrgentIssues,
+    /// Whether the scheduler is running, and its last error
+    Scheduler,
+    /// Per-queue RTT samples (the same data as xdp_pping)
+    Pping,
+    /// Nagios/Icinga health check: prints a status line with perfdata and exits 0 (OK),
+    /// 1 (WARNING), 2 (CRITICAL) or 3 (UNKNOWN). Ignores the output options.
+    Check(check::CheckArgs),
+}
+
+impl Commands {
+    fn requests(&self) -> Vec<BusRequest> {
+        match self {
+            Commands::Throughput => vec![BusRequest::GetCurrentThroughput],
+            Commands::TopDownloaders { n } => {
+                vec![BusRequest::GetTopNDownloaders { start: 0, end: *n }]
+            }
+            Commands::TopUploaders { n } => {
+                vec![BusRequest::GetTopNUploaders { start: 0, end: *n }]
+            }
+            Commands::WorstRtt { n } => vec![BusRequest::GetWorstRtt { start: 0, end: *n }],
+            Commands::WorstRetransmits { n } => {
+                vec![BusRequest::GetWorstRetransmits { start: 0, end: *n }]
+            }
+            Commands::Circuits => vec![BusRequest::GetAllCircuits],
+            Commands::Circuit { circuit_id } => vec![BusRequest::GetCircuitById {
+                circuit_id: circuit_id.clone(),
+            }],
+            Commands::Flows { ip } => vec![BusRequest::FlowsByIp(ip.clone())],
+            Commands::TopFlows { n } => vec![BusRequest::TopFlows {
+                flow_type: TopFlowType::Bytes,
+                n: *n,
+            }],
+            // Watching keeps tin history for the circuit; the current counters are always
+            // available
+            Commands::Queue { circuit_id } => vec![
+                BusRequest::WatchQueue(circuit_id.clone()),
+                BusRequest::GetRawQueueData(circuit_id.clone()),
+            ],
+            Commands::QueueTotals => vec![BusRequest::GetQueueStatsTotal],
+            Commands::Stormguard => vec![BusRequest::GetStormguardStats],
+            Commands::UrgentIssues => vec![BusRequest::GetUrgentIssues],
+            Commands::Scheduler => vec![BusRequest::CheckSchedulerStatus],
+            Commands::Pping => vec![BusRequest::XdpPping],
+            Commands::Check(_) => check::requests(),
+        }
+    }
+}
+
+/// Turns a reply into JSON: an array of rows, or a single object.
+fn payload(response: BusResponse) -> Result<Option<Value>> {
+    let value = match response {
+        BusResponse::Ack => return Ok(None),
+        BusResponse::Fail(e) => bail!("lqosd reported an error: {e}"),
+        BusResponse::CurrentThroughput {
+            bits_per_second,
+            packets_per_second,
+            tcp_packets_per_second,
+            udp_packets_per_second,
+            icmp_packets_per_second,
+            shaped_bits_per_second,
+        } => json!({
+            "bits_per_second": bits_per_second,
+            "packets_per_second": packets_per_second,
+            "tcp_packets_per_second": tcp_packets_per_second,
+            "udp_packets_per_second": udp_packets_per_second,
+            "icmp_packets_per_second": icmp_packets_per_second,
+            "shaped_bits_per_second": shaped_bits_per_second,
+        }),
+        BusResponse::TopDownloaders(hosts)
+        | BusResponse::TopUploaders(hosts)
+        | BusResponse::WorstRtt(hosts)
+        | BusResponse::WorstRetransmits(hosts) => serde_json::to_value(hosts)?,
+        BusResponse::CircuitData(devices) => serde_json::to_value(devices)?,
+        BusResponse::FlowsByIp(flows) | BusResponse::TopFlows(flows) => {
+            serde_json::to_value(flows)?
+        }
+        BusResponse::RawQueueData(None) => bail!("No queue data for that circuit"),
+        BusResponse::RawQueueData(Some(queue)) => json!({
+            "kind_down": queue.kind_down,
+            "kind_up": queue.kind_up,
+            "download": queue.current_download,
+            "upload": queue.current_upload,
+        }),
+        BusResponse::QueueStatsTotal(totals) => serde_json::to_value(totals)?,
+        BusResponse::StormguardStats(sites) => Value::Array(
+            sites
+                .into_iter()
+                .map(|(site, download_mbps, upload_mbps)| {
+                    json!({
+                        "site": site,
+                        "download_mbps": download_mbps,
+                        "upload_mbps": upload_mbps,
+                    })
+                })
+                .collect(),
+        ),
+        BusResponse::UrgentIssues(issues) => serde_json::to_value(issues)?,
+        BusResponse::SchedulerStatus { running, error } => {
+            json!({ "running": running, "error": error })
+        }
+        BusResponse::XdpPping(lines) => serde_json::to_value(lines)?,
+        other => bail!("Unexpected reply from lqosd: {other:?}"),
+    };
+    Ok(Some(value))
+}
+
+async fn query(command: &Commands) -> Result<Value> {
+    let mut result = None;
+    for response in bus_request(command.requests()).await? {
+        if let Some(value) = payload(response)? {
+            result = Some(value);
+        }
+    }
+    match result {
+        Some(value) => Ok(value),
+        None => bail!("lqosd sent no data"),
+    }
+}
+
+#[tokio::main(flavor = "current_thread")]
+async fn main() -> Result<()> {
+    let cli = Cli::parse();
+    if let Commands::Check(args) = &cli.command {
+        std::process::exit(check::run(args).await);
+    }
+    let format = if cli.output.json {
+        Format::Json
+    } else if cli.output.csv {
+        Format::Csv
+    } else {
+        Format::Table
+    };
+
+    let Some(seconds) = cli.output.watch else {
+        let value = query(&cli.command).await?;
+        return output::print(format, &value, false, true);
+    };
+    let mut first = true;
+    loop {
+        let value = query(&cli.command).await?;
+        output::print(format, &value, true, first)?;
+        first = false;
+        tokio::time::sleep(Duration::from_secs(seconds.max(1))).await;
+    }
+}
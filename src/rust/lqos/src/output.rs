@@ -0,0 +1,162 @@
+//! Prints query results as an aligned table, JSON or CSV.
+//!
+//! Table and CSV output flatten each row: nested objects become dotted columns
+//! (`bits_per_second.down`) and lists are written as JSON text in a single cell.
+
+use anyhow::Result;
+use serde_json::{Map, Value};
+use std::io::Write;
+
+#[derive(Clone, Copy)]
+pub enum Format {
+    Table,
+    Json,
+    Csv,
+}
+
+/// Prints `value`. With `watching`, JSON is one line per update and tables clear the
+/// screen; `first` is false for repeats, so CSV only prints its header once.
+pub fn print(format: Format, value: &Value, watching: bool, first: bool) -> Result<()> {
+    let mut stdout = std::io::stdout().lock();
+    match format {
+        Format::Json if watching => writeln!(stdout, "{}", serde_json::to_string(value)?)?,
+        Format::Json => writeln!(stdout, "{}", serde_json::to_string_pretty(value)?)?,
+        Format::Csv => {
+            let (columns, rows) = tabulate(value);
+            let mut writer = csv::Writer::from_writer(&mut stdout);
+            if first {
+                writer.write_record(&columns)?;
+            }
+            for row in rows {
+                writer.write_record(&row)?;
+            }
+            writer.flush()?;
+        }
+        Format::Table => {
+            if watching {
+                // Clear the screen and home the cursor, like watch(1)
+                write!(stdout, "\x1b[2J\x1b[H")?;
+            }
+            let (columns, rows) = tabulate(value);
+            write_table(&mut stdout, &columns, &rows)?;
+        }
+    }
+    stdout.flush()?;
+    Ok(())
+}
+
+/// Column names (in first-seen order) and the cells of each row.
+fn tabulate(value: &Value) -> (Vec<String>, Vec<Vec<String>>) {
+    let rows: Vec<Map<String, Value>> = match value {
+        Value::Array(items) => items.iter().map(flatten).collect(),
+        other => vec![flatten(other)],
+    };
+    let mut columns: Vec<String> = Vec::new();
+    for row in &rows {
+        for key in row.keys() {
+            if !columns.contains(key) {
+                columns.push(key.clone());
+            }
+        }
+    }
+    let cells = rows
+        .iter()
+        .map(|row| {
+            columns
+                .iter()
+                .map(|column| row.get(column).map(cell).unwrap_or_default())
+                .collect()
+        })
+        .collect();
+    (columns, cells)
+}
+
+fn flatten(value: &Value) -> Map<String, Value> {
+    let mut row = Map::new();
+    match value {
+        Value::Object(fields) => flatten_into(&mut row, None, fields),
+        other => {
+            row.insert("value".to_string(), other.clone());
+        }
+    }
+    row
+}
+
+fn flatten_into(row: &mut Map<String, Value>, prefix: Option<&str>, fields: &Map<String, Value>) {
+    for (key, value) in fields {
+        let name = match prefix {
+            Some(prefix) => format!("{prefix}.{key}"),
+            None => key.clone(),
+        };
+        match value {
+            Value::Object(inner) => flatten_into(row, Some(&name), inner),
+            other => {
+                row.insert(name, other.clone());
+            }
+        }
+    }
+}
+
+fn cell(value: &Value) -> String {
+    match value {
+        Value::Null => String::new(),
+        Value::String(s) => s.clone(),
+        other => other.to_string(),
+    }
+}
+
+fn write_table(out: &mut impl Write, columns: &[String], rows: &[Vec<String>]) -> Result<()> {
+    let widths: Vec<usize> = columns
+        .iter()
+        .enumerate()
+        .map(|(i, column)| {
+            rows.iter()
+                .map(|row| row[i].chars().count())
+                .chain(std::iter::once(column.chars().count()))
+                .max()
+                .unwrap_or_default()
+        })
+        .collect();
+    let line = |cells: &[String]| {
+        cells
+            .iter()
+            .zip(&widths)
+            .map(|(cell, width)| format!("{cell:<width$}"))
+            .collect::<Vec<_>>()
+            .join("  ")
+            .trim_end()
+            .to_string()
+    };
+    writeln!(out, "{}", line(columns))?;
+    for row in rows {
+        writeln!(out, "{}", line(row))?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn nested_objects_become_dotted_columns() {
+        let value = json!([
+            { "ip": "10.0.0.1", "bps": { "down": 5, "up": 1 }, "rtt": null },
+            { "ip": "10.0.0.2", "bps": { "down": 7, "up": 2 }, "tags": ["a"] },
+        ]);
+        let (columns, rows) = tabulate(&value);
+        assert_eq!(columns, ["bps.down", "bps.up", "ip", "rtt", "tags"]);
+        assert_eq!(rows[0], ["5", "1", "10.0.0.1", "", ""]);
+        assert_eq!(rows[1], ["7", "2", "10.0.0.2", "", "[\"a\"]"]);
+    }
+
+    #[test]
+    fn table_columns_are_aligned() {
+        let columns = vec!["name".to_string(), "n".to_string()];
+        let rows = vec![vec!["a".to_string(), "100".to_string()]];
+        let mut out = Vec::new();
+        write_table(&mut out, &columns, &rows).unwrap();
+        assert_eq!(String::from_utf8(out).unwrap(), "name  n\na     100\n");
+    }
+}
@@ -249,6 +249,17 @@ pub enum BakeryCommands {
         #[allocative(skip)]
         reply: Option<ReplySender<RuntimeNodeOperationSnapshot>>,
     },
+    /// Runtime TreeGuard request to move a top-level site (and its subtree) to another queue root
+    /// using lossless live migrations.
+    TreeGuardMoveTopLevelSite {
+        /// Stable Bakery site hash derived from the node name.
+        site_hash: i64,
+        /// Destination queue/root number, e.g. `1` for `Q1`.
+        target_queue: u32,
+        /// Optional synchronous reply channel reporting whether the move was started.
+        #[allocative(skip)]
+        reply: Option<ReplySender<Result<(), String>>>,
+    },
 }
 
 impl BakeryCommands {
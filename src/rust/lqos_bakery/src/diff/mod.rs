@@ -1,5 +1,160 @@
 mod circuits;
 mod sites;
 
+use crate::BakeryCommands;
+use std::collections::HashMap;
+use std::sync::Arc;
+
 pub(crate) use circuits::{CircuitDiffResult, diff_circuits};
 pub(crate) use sites::{SiteDiffResult, diff_sites};
+
+/// What committing a batch would change compared with a previously committed one, as
+/// classified by the same site and circuit diffs the Bakery runs on commit.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct BatchDiffSummary {
+    /// Why the site hierarchy must be rebuilt with a full reload, if it must
+    pub full_reload_reason: Option<String>,
+    /// Sites whose rates change in place
+    pub site_speed_changes: usize,
+    /// Circuits only in the new batch
+    pub circuits_added: usize,
+    /// Circuits only in the previous batch
+    pub circuits_removed: usize,
+    /// Circuits whose rates or SQM override change
+    pub circuits_speed_changed: usize,
+    /// Circuits whose IP addresses change
+    pub circuits_ip_changed: usize,
+    /// Circuits that move to a different parent or class handle
+    pub circuits_migrated: usize,
+}
+
+impl BatchDiffSummary {
+    /// True if the batch changes nothing.
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+/// Summarizes what `batch` changes relative to `previous`. Only `AddSite` and `AddCircuit`
+/// commands are compared.
+pub fn summarize_batch_diff(
+    previous: &[BakeryCommands],
+    batch: &[BakeryCommands],
+) -> BatchDiffSummary {
+    let mut old_sites = HashMap::new();
+    let mut old_circuits = HashMap::new();
+    for command in previous {
+        match command {
+            BakeryCommands::AddSite { site_hash, .. } => {
+                old_sites.insert(*site_hash, Arc::new(command.clone()));
+            }
+            BakeryCommands::AddCircuit { circuit_hash, .. } => {
+                old_circuits.insert(*circuit_hash, Arc::new(command.clone()));
+            }
+            _ => {}
+        }
+    }
+    let batch: Vec<Arc<BakeryCommands>> = batch
+        .iter()
+        .filter(|command| {
+            matches!(
+                command,
+                BakeryCommands::AddSite { .. } | BakeryCommands::AddCircuit { .. }
+            )
+        })
+        .cloned()
+        .map(Arc::new)
+        .collect();
+
+    let mut summary = BatchDiffSummary::default();
+    match diff_sites(&batch, &old_sites) {
+        SiteDiffResult::RebuildRequired { summary: reason } => {
+            summary.full_reload_reason = Some(reason);
+        }
+        SiteDiffResult::SpeedChanges { changes } => summary.site_speed_changes = changes.len(),
+        SiteDiffResult::NoChange => {}
+    }
+    if let CircuitDiffResult::Categorized(categories) = diff_circuits(&batch, &old_circuits) {
+        summary.circuits_added = categories.newly_added.len();
+        summary.circuits_removed = categories.removed_circuits.len();
+        summary.circuits_speed_changed = categories.speed_changed.len();
+        summary.circuits_ip_changed = categories.ip_changed.len();
+        summary.circuits_migrated = categories.migrated.len();
+    }
+    summary
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use lqos_bus::TcHandle;
+
+    fn site(site_hash: i64, download_max: f32) -> BakeryCommands {
+        BakeryCommands::AddSite {
+            site_hash,
+            parent_class_id: TcHandle::from_u32(0x10002),
+            up_parent_class_id: TcHandle::from_u32(0x20002),
+            class_minor: 3 + site_hash as u16,
+            download_bandwidth_min: 1.0,
+            upload_bandwidth_min: 1.0,
+            download_bandwidth_max: download_max,
+            upload_bandwidth_max: 100.0,
+        }
+    }
+
+    fn circuit(circuit_hash: i64, ip_addresses: &str) -> BakeryCommands {
+        BakeryCommands::AddCircuit {
+            circuit_hash,
+            circuit_name: None,
+            site_name: None,
+            parent_class_id: TcHandle::from_u32(0x10003),
+            up_parent_class_id: TcHandle::from_u32(0x20003),
+            class_minor: 0x20 + circuit_hash as u16,
+            download_bandwidth_min: 1.0,
+            upload_bandwidth_min: 1.0,
+            download_bandwidth_max: 10.0,
+            upload_bandwidth_max: 10.0,
+            class_major: 1,
+            up_class_major: 2,
+            down_qdisc_handle: None,
+            up_qdisc_handle: None,
+            ip_addresses: ip_addresses.to_string(),
+            sqm_override: None,
+        }
+    }
+
+    #[test]
+    fn counts_in_place_changes() {
+        let previous = vec![
+            BakeryCommands::StartBatch,
+            site(0, 100.0),
+            circuit(1, "192.0.2.1/32"),
+            circuit(2, "192.0.2.2/32"),
+        ];
+        let batch = vec![
+            BakeryCommands::StartBatch,
+            site(0, 200.0),
+            circuit(1, "192.0.2.10/32"),
+            circuit(3, "192.0.2.3/32"),
+        ];
+        assert_eq!(
+            summarize_batch_diff(&previous, &batch),
+            BatchDiffSummary {
+                full_reload_reason: None,
+                site_speed_changes: 1,
+                circuits_added: 1,
+                circuits_removed: 1,
+                circuits_speed_changed: 0,
+                circuits_ip_changed: 1,
+                circuits_migrated: 0,
+            }
+        );
+        assert!(summarize_batch_diff(&batch, &batch).is_empty());
+    }
+
+    #[test]
+    fn new_site_requires_full_reload() {
+        let summary = summarize_batch_diff(&[site(0, 100.0)], &[site(0, 100.0), site(1, 100.0)]);
+        assert!(summary.full_reload_reason.is_some());
+    }
+}
@@ -56,6 +56,7 @@ pub use commands::{
     RuntimeNodeOperationSnapshot as BakeryRuntimeNodeOperationSnapshot,
     RuntimeNodeOperationStatus as BakeryRuntimeNodeOperationStatus,
 };
+pub use diff::{BatchDiffSummary, summarize_batch_diff};
 use lqos_bus::{
     BusRequest, BusResponse, InsightLicenseSummary, LibreqosBusClient, TcHandle, UrgentSeverity,
     UrgentSource,
@@ -68,6 +69,7 @@ use lqos_config::{
     plan_top_level_assignments,
 };
 use qdisc_handles::MqDeviceLayout;
+pub use queue_math::{MAX_TC_RATE_MBPS, MIN_TC_RATE_MBPS};
 use serde_json::{Map, Value};
 
 const TEST_FAULT_ONCE_PATH: &str = "/tmp/lqos_bakery_fail_purpose_once.txt";
@@ -1221,6 +1223,7 @@ struct BakeryTelemetryState {
     last_apply_duration_ms: u64,
     runtime_operations: BakeryRuntimeOperationsSnapshot,
     queue_distribution: Vec<BakeryQueueDistributionSnapshot>,
+    top_level_site_queues: HashMap<i64, u32>,
     live_capacity_interfaces: Vec<BakeryLiveCapacityInterfaceSnapshot>,
     live_capacity_updated_at_unix: Option<u64>,
     preflight: Option<BakeryPreflightSnapshot>,
@@ -1335,6 +1338,7 @@ impl Default for BakeryTelemetryState {
                 latest: None,
             },
             queue_distribution: Vec::new(),
+            top_level_site_queues: HashMap::new(),
             live_capacity_interfaces: Vec::new(),
             live_capacity_updated_at_unix: None,
             preflight: None,
@@ -1589,6 +1593,15 @@ pub fn bakery_runtime_node_branch_snapshot(
         .cloned()
 }
 
+/// Returns the current queue/root number of every site TreeGuard may move between queues, keyed by
+/// Bakery site hash.
+///
+/// These are the real top-level sites; when bin packing wraps them in generated `CpueQueueN`
+/// sites, the children of those generated sites are returned instead.
+pub fn bakery_top_level_site_queues() -> HashMap<i64, u32> {
+    telemetry_state().read().top_level_site_queues.clone()
+}
+
 /// Returns the current Bakery reload-required reason, if runtime drift has frozen incremental
 /// topology mutation.
 pub fn bakery_reload_required_reason() -> Option<String> {
@@ -3003,6 +3016,7 @@ fn bakery_main(rx: Receiver<BakeryCommands>, tx: Sender<BakeryCommands>) {
     let mut virtualized_sites: HashMap<i64, VirtualizedSiteState> = HashMap::new();
     let mut runtime_node_operations: HashMap<i64, RuntimeNodeOperation> = HashMap::new();
     let mut next_runtime_operation_id: u64 = 1;
+    let mut top_level_moves: HashMap<i64, TopLevelSiteMoveState> = HashMap::new();
 
     // Mapping state
     #[derive(Clone, Hash, PartialEq, Eq, Debug)]
@@ -3699,6 +3713,7 @@ fn bakery_main(rx: Receiver<BakeryCommands>, tx: Sender<BakeryCommands>) {
                     &mut virtualized_sites,
                     &mut runtime_node_operations,
                 );
+                flush_top_level_site_move_prunes(&config, &mut top_level_moves, &migrations);
                 refresh_live_capacity_snapshot(&config, false);
                 continue;
             }
@@ -3843,6 +3858,7 @@ fn bakery_main(rx: Receiver<BakeryCommands>, tx: Sender<BakeryCommands>) {
                     &mut virtualized_sites,
                     &mut runtime_node_operations,
                 );
+                retain_unclaimed_top_level_site_moves(&mut top_level_moves, &sites);
                 let Ok(config) = lqos_config::load_config() else {
                     error!("Failed to load configuration while processing pending migrations.");
                     continue;
@@ -3891,6 +3907,7 @@ fn bakery_main(rx: Receiver<BakeryCommands>, tx: Sender<BakeryCommands>) {
                     &mut virtualized_sites,
                     &mut runtime_node_operations,
                 );
+                flush_top_level_site_move_prunes(&config, &mut top_level_moves, &migrations);
             }
             BakeryCommands::ChangeSiteSpeedLive {
                 site_hash,
@@ -4006,6 +4023,37 @@ fn bakery_main(rx: Receiver<BakeryCommands>, tx: Sender<BakeryCommands>) {
                     let _ = reply.send(result);
                 }
             }
+            BakeryCommands::TreeGuardMoveTopLevelSite {
+                site_hash,
+                target_queue,
+                reply,
+            } => {
+                let result = handle_treeguard_move_top_level_site_live(
+                    site_hash,
+                    target_queue,
+                    &mut sites,
+                    &mut circuits,
+                    &live_circuits,
+                    &mq_layout,
+                    &mut qdisc_handles,
+                    &mut migrations,
+                    &virtualized_sites,
+                    &runtime_node_operations,
+                    &mut top_level_moves,
+                );
+                if let Err(error) = &result {
+                    push_bakery_event_with_site(
+                        "top_level_site_move_rejected",
+                        "warning",
+                        Some(site_hash),
+                        error.clone(),
+                    );
+                }
+                update_queue_distribution_snapshot(&sites, &circuits);
+                if let Some(reply) = reply {
+                    let _ = reply.send(result);
+                }
+            }
         }
     }
     error!("Bakery thread exited unexpectedly.");
@@ -5607,8 +5655,11 @@ fn update_queue_distribution_snapshot(
     sites: &HashMap<i64, Arc<BakeryCommands>>,
     circuits: &HashMap<i64, Arc<BakeryCommands>>,
 ) {
-    telemetry_state().write().queue_distribution =
-        rebuild_queue_distribution_snapshot(sites, circuits);
+    let distribution = rebuild_queue_distribution_snapshot(sites, circuits);
+    let top_level_site_queues = rebalanceable_top_level_site_queues(sites);
+    let mut state = telemetry_state().write();
+    state.queue_distribution = distribution;
+    state.top_level_site_queues = top_level_site_queues;
 }
 
 fn rebuild_runtime_operations_snapshot(
@@ -5882,16 +5933,70 @@ fn site_stick_offset(site: &BakeryCommands) -> u16 {
     up_major.saturating_sub(down_major)
 }
 
-fn top_level_bin_name(queue: u32) -> String {
+/// Returns the planner bin name of a (1-based) queue, matching `LibreQoS.py`.
+pub fn top_level_bin_name(queue: u32) -> String {
     format!("CpueQueue{}", queue.saturating_sub(1))
 }
 
-fn queue_from_bin_name(name: &str) -> Option<u32> {
+/// Returns the queue named by a planner bin, the inverse of [`top_level_bin_name`].
+pub fn queue_from_bin_name(name: &str) -> Option<u32> {
     name.strip_prefix("CpueQueue")
         .and_then(|v| v.parse::<u32>().ok())
         .map(|cpu| cpu + 1)
 }
 
+/// Returns the CPU that services a (1-based) queue, given the shaping CPU list from
+/// [`lqos_config::detect_shaping_cpus`]. Queue `N` belongs to the `N`th shaping CPU, the same
+/// order the XDP TXQ map is programmed in; an empty list means every CPU shapes.
+pub fn shaping_cpu_for_queue(queue: u32, shaping_cpus: &[u32]) -> Option<u32> {
+    let index = queue.checked_sub(1)?;
+    if shaping_cpus.is_empty() {
+        return Some(index);
+    }
+    shaping_cpus.get(index as usize).copied()
+}
+
+fn generated_queue_site_hash(queue: u32) -> i64 {
+    runtime_hash_to_i64(&top_level_bin_name(queue))
+}
+
+/// Returns true for the generated `CpueQueueN` top-level sites that bin packing wraps real
+/// top-level sites in.
+fn site_is_generated_queue_bin(site_hash: i64, site: &BakeryCommands) -> bool {
+    site_is_top_level(site)
+        && current_site_queue(site)
+            .is_some_and(|queue| generated_queue_site_hash(queue) == site_hash)
+}
+
+/// Returns the queue of every site that can be moved between queue roots: real top-level sites,
+/// plus the direct children of generated queue bins when bin packing is enabled.
+fn rebalanceable_top_level_site_queues(
+    sites: &HashMap<i64, Arc<BakeryCommands>>,
+) -> HashMap<i64, u32> {
+    let children_by_parent = direct_child_sites_by_parent(sites);
+    let mut result = HashMap::new();
+    for (site_hash, site) in sites {
+        if !site_is_top_level(site.as_ref()) {
+            continue;
+        }
+        if !site_is_generated_queue_bin(*site_hash, site.as_ref()) {
+            if let Some(queue) = current_site_queue(site.as_ref()) {
+                result.insert(*site_hash, queue);
+            }
+            continue;
+        }
+        for child_hash in children_by_parent.get(site_hash).into_iter().flatten() {
+            if let Some(queue) = sites
+                .get(child_hash)
+                .and_then(|child| current_site_queue(child.as_ref()))
+            {
+                result.insert(*child_hash, queue);
+            }
+        }
+    }
+    result
+}
+
 fn planner_site_key(site_hash: i64) -> String {
     format!("site:{site_hash}")
 }
@@ -6160,7 +6265,56 @@ fn build_top_level_virtualization_plan(
         future_top_level_queue.insert(*site_hash, queue);
     }
 
-    let mut moved_top_level_roots = promoted_site_roots.clone();
+    let mut direct_circuit_queues = BTreeMap::new();
+    for circuit_hash in &direct_circuits {
+        let id = planner_circuit_key(*circuit_hash);
+        let assigned = planner
+            .assignment
+            .get(&id)
+            .or_else(|| prev_assign.get(&id))
+            .ok_or_else(|| format!("Planner did not assign direct circuit {}", circuit_hash))?;
+        let queue = queue_from_bin_name(assigned)
+            .ok_or_else(|| format!("Invalid planner queue assignment {}", assigned))?;
+        direct_circuit_queues.insert(*circuit_hash, queue);
+    }
+
+    plan_top_level_branch_rehome(
+        sites,
+        circuits,
+        config,
+        virtualized_sites,
+        stick_offset,
+        &children_by_parent,
+        &promoted_site_roots,
+        &future_top_level_queue,
+        None,
+        Some((target_site_hash, (target_down_class, target_up_class))),
+        &direct_circuit_queues,
+    )
+}
+
+/// Plans new class identities for top-level branches that are changing queue.
+///
+/// `moved_roots` are the site subtrees being re-homed, `future_top_level_queue` is the
+/// post-change queue of every top-level site, and `moved_root_parent` (if any) is an existing
+/// site that moved roots not listed in `future_top_level_queue` are attached under.
+/// `retired_site` (if any) is a site that is leaving the tree and whose direct circuits are
+/// promoted to the queue roots listed in `direct_circuit_queues`.
+#[allow(clippy::too_many_arguments)]
+fn plan_top_level_branch_rehome(
+    sites: &HashMap<i64, Arc<BakeryCommands>>,
+    circuits: &HashMap<i64, Arc<BakeryCommands>>,
+    config: Option<&Arc<Config>>,
+    virtualized_sites: &HashMap<i64, VirtualizedSiteState>,
+    stick_offset: u16,
+    children_by_parent: &HashMap<i64, Vec<i64>>,
+    moved_roots: &[i64],
+    future_top_level_queue: &HashMap<i64, u32>,
+    moved_root_parent: Option<i64>,
+    retired_site: Option<(i64, (TcHandle, TcHandle))>,
+    direct_circuit_queues: &BTreeMap<i64, u32>,
+) -> Result<TopLevelVirtualizationPlan, String> {
+    let mut moved_top_level_roots = moved_roots.to_vec();
     moved_top_level_roots.sort_unstable();
     moved_top_level_roots.dedup();
 
@@ -6168,7 +6322,7 @@ fn build_top_level_virtualization_plan(
     let mut saved_circuits = HashMap::new();
     let mut affected_site_hashes = HashSet::new();
     for site_hash in &moved_top_level_roots {
-        for hash in collect_site_subtree_hashes(*site_hash, &children_by_parent) {
+        for hash in collect_site_subtree_hashes(*site_hash, children_by_parent) {
             affected_site_hashes.insert(hash);
         }
     }
@@ -6185,7 +6339,7 @@ fn build_top_level_virtualization_plan(
             saved_circuits.insert(circuit_hash, Arc::clone(circuit));
         }
     }
-    for circuit_hash in &direct_circuits {
+    for circuit_hash in direct_circuit_queues.keys() {
         if let Some(circuit) = circuits.get(circuit_hash) {
             saved_circuits.insert(*circuit_hash, Arc::clone(circuit));
         }
@@ -6204,15 +6358,19 @@ fn build_top_level_virtualization_plan(
         if future_parent_by_site.contains_key(site_hash) {
             continue;
         }
-        let parent_hash = site_parent_hash(*site_hash, sites, &{
-            let mut class_to_site = HashMap::new();
-            for (hash, site) in sites {
-                if let Some(handles) = site_class_handles(site.as_ref()) {
-                    class_to_site.insert(handles, *hash);
+        let parent_hash = if moved_top_level_roots.contains(site_hash) {
+            moved_root_parent
+        } else {
+            site_parent_hash(*site_hash, sites, &{
+                let mut class_to_site = HashMap::new();
+                for (hash, site) in sites {
+                    if let Some(handles) = site_class_handles(site.as_ref()) {
+                        class_to_site.insert(handles, *hash);
+                    }
                 }
-            }
-            class_to_site
-        });
+                class_to_site
+            })
+        };
         future_parent_by_site.insert(*site_hash, parent_hash);
     }
 
@@ -6220,7 +6378,7 @@ fn build_top_level_virtualization_plan(
     let mut planner_site_hashes: Vec<i64> = affected_site_hashes
         .iter()
         .copied()
-        .filter(|hash| *hash != target_site_hash)
+        .filter(|hash| Some(*hash) != retired_site.map(|(site_hash, _)| site_hash))
         .collect();
     planner_site_hashes
         .sort_by_key(|hash| (site_descendant_depth(*hash, &future_parent_by_site), *hash));
@@ -6278,7 +6436,9 @@ fn build_top_level_virtualization_plan(
         else {
             continue;
         };
-        if *parent_class_id == target_down_class && *up_parent_class_id == target_up_class {
+        if retired_site
+            .is_some_and(|(_, classes)| classes == (*parent_class_id, *up_parent_class_id))
+        {
             continue;
         }
         let parent = class_to_site
@@ -6295,19 +6455,11 @@ fn build_top_level_virtualization_plan(
             .or_default()
             .push(planner_circuit_key(*circuit_hash));
     }
-    for circuit_hash in &direct_circuits {
-        let id = planner_circuit_key(*circuit_hash);
-        let assigned = planner
-            .assignment
-            .get(&id)
-            .or_else(|| prev_assign.get(&id))
-            .ok_or_else(|| format!("Planner did not assign direct circuit {}", circuit_hash))?;
-        let queue = queue_from_bin_name(assigned)
-            .ok_or_else(|| format!("Invalid planner queue assignment {}", assigned))?;
+    for (circuit_hash, queue) in direct_circuit_queues {
         circuit_ids_by_parent
             .entry(format!("root:{queue}"))
             .or_default()
-            .push(id);
+            .push(planner_circuit_key(*circuit_hash));
     }
 
     let mut planner_circuit_groups = Vec::new();
@@ -6402,6 +6554,11 @@ fn build_top_level_virtualization_plan(
                             ),
                         )
                     })
+                    .or_else(|| {
+                        sites
+                            .get(&parent_hash)
+                            .and_then(|parent| site_class_handles(parent.as_ref()))
+                    })
             })
             .unwrap_or_else(|| {
                 (
@@ -6452,7 +6609,7 @@ fn build_top_level_virtualization_plan(
             .get(&circuit_key)
             .and_then(|prev| prev.parent_node.strip_prefix("site:"))
             .and_then(|s| s.parse::<i64>().ok());
-        let future_parent_site = if direct_circuits.contains(circuit_hash) {
+        let future_parent_site = if direct_circuit_queues.contains_key(circuit_hash) {
             None
         } else {
             class_to_site
@@ -9500,6 +9657,411 @@ fn handle_treeguard_set_node_virtual_live(
     operation.snapshot()
 }
 
+/// Tracks a live top-level site move until the site's previous branch has been pruned.
+#[derive(Clone, Debug)]
+struct TopLevelSiteMoveState {
+    site_name: Option<String>,
+    from_queue: u32,
+    to_queue: u32,
+    old_sites: HashMap<i64, Arc<BakeryCommands>>,
+    moved_circuits: Vec<i64>,
+    prune_attempts: u32,
+    next_prune_attempt_unix: u64,
+}
+
+fn top_level_site_move_prune_ready(
+    state: &TopLevelSiteMoveState,
+    migrations: &HashMap<i64, Migration>,
+    now_unix: u64,
+) -> bool {
+    state.next_prune_attempt_unix <= now_unix
+        && !state
+            .moved_circuits
+            .iter()
+            .any(|circuit_hash| migrations.contains_key(circuit_hash))
+}
+
+fn site_subtree_is_runtime_virtualized(
+    subtree: &[i64],
+    virtualized_sites: &HashMap<i64, VirtualizedSiteState>,
+) -> bool {
+    subtree.iter().any(|site_hash| {
+        virtualized_sites.contains_key(site_hash)
+            || virtualized_sites.values().any(|state| {
+                state.saved_sites.contains_key(site_hash)
+                    || state.active_sites.contains_key(site_hash)
+            })
+    })
+}
+
+/// Moves a top-level site and its subtree to another queue root without a full reload.
+///
+/// New site classes are created on the target queue first, then every attached circuit is
+/// re-homed through the lossless shadow migration path. The previous branch is pruned later by
+/// `flush_top_level_site_move_prunes` once those migrations finish.
+#[allow(clippy::too_many_arguments)]
+fn handle_treeguard_move_top_level_site_live(
+    site_hash: i64,
+    target_queue: u32,
+    sites: &mut HashMap<i64, Arc<BakeryCommands>>,
+    circuits: &mut HashMap<i64, Arc<BakeryCommands>>,
+    live_circuits: &HashMap<i64, u64>,
+    mq_layout: &Option<MqDeviceLayout>,
+    qdisc_handles: &mut QdiscHandleState,
+    migrations: &mut HashMap<i64, Migration>,
+    virtualized_sites: &HashMap<i64, VirtualizedSiteState>,
+    runtime_node_operations: &HashMap<i64, RuntimeNodeOperation>,
+    top_level_moves: &mut HashMap<i64, TopLevelSiteMoveState>,
+) -> Result<(), String> {
+    if let Some(reason) = bakery_reload_required_reason() {
+        return Err(format!(
+            "TreeGuard top-level site move is unavailable until Bakery reloads: {}",
+            reason
+        ));
+    }
+    let Ok(config) = lqos_config::load_config() else {
+        return Err("Failed to load configuration".to_string());
+    };
+    let current_site_names = load_current_runtime_site_names(&config);
+    let site_name =
+        resolve_runtime_site_name(site_hash, current_site_names.as_ref(), virtualized_sites);
+    let site_label = runtime_site_label(site_hash, site_name.as_deref());
+    if let Some(reason) = live_tree_mutation_blocker_for_config(&config) {
+        return Err(format!(
+            "TreeGuard top-level site move for {} is blocked because {}.",
+            site_label, reason
+        ));
+    }
+    if top_level_moves.contains_key(&site_hash) {
+        return Err(format!(
+            "A previous move of {} is still awaiting cleanup",
+            site_label
+        ));
+    }
+    if let Some(conflicting_site_hash) = active_top_level_runtime_operation_conflict(
+        site_hash,
+        runtime_node_operations,
+        sites,
+        virtualized_sites,
+    ) {
+        return Err(format!(
+            "Top-level runtime operation for site {} is still active; not moving {}",
+            conflicting_site_hash, site_label
+        ));
+    }
+
+    let Some(site) = sites.get(&site_hash).cloned() else {
+        return Err(format!("Unknown site {}", site_label));
+    };
+    if site_is_generated_queue_bin(site_hash, site.as_ref()) {
+        return Err(format!(
+            "Site {} is a generated queue bin and cannot be moved",
+            site_label
+        ));
+    }
+    let children_by_parent = direct_child_sites_by_parent(sites);
+    let binned = children_by_parent.iter().any(|(parent_hash, children)| {
+        children.contains(&site_hash)
+            && sites
+                .get(parent_hash)
+                .is_some_and(|parent| site_is_generated_queue_bin(*parent_hash, parent.as_ref()))
+    });
+    if !binned && !site_is_top_level(site.as_ref()) {
+        return Err(format!("Site {} is not top-level", site_label));
+    }
+    let Some(from_queue) = current_site_queue(site.as_ref()) else {
+        return Err(format!(
+            "Site {} does not have a deterministic current queue assignment",
+            site_label
+        ));
+    };
+    if from_queue == target_queue {
+        return Ok(());
+    }
+    let Some(layout) = mq_layout.as_ref() else {
+        return Err("Bakery top-level site moves require MQ layout to be available".to_string());
+    };
+    if !layout.has_queue(&config.isp_interface(), target_queue) {
+        return Err(format!(
+            "Queue {} does not exist on {}",
+            target_queue,
+            config.isp_interface()
+        ));
+    }
+
+    // With bin packing the movable sites hang off generated `CpueQueueN` sites, so the move
+    // re-parents the branch under the target queue's bin instead of the queue root.
+    let target_parent = if binned {
+        let target_bin_hash = generated_queue_site_hash(target_queue);
+        if !sites.contains_key(&target_bin_hash) {
+            return Err(format!(
+                "Queue {} has no generated {} site to move {} under",
+                target_queue,
+                top_level_bin_name(target_queue),
+                site_label
+            ));
+        }
+        Some(target_bin_hash)
+    } else {
+        None
+    };
+
+    let subtree = collect_site_subtree_hashes(site_hash, &children_by_parent);
+    if site_subtree_is_runtime_virtualized(&subtree, virtualized_sites) {
+        return Err(format!(
+            "Site {} has runtime-virtualized nodes in its subtree and cannot be moved",
+            site_label
+        ));
+    }
+    let moved_circuits = collect_circuits_attached_to_sites(circuits, sites, &subtree);
+    if moved_circuits
+        .iter()
+        .any(|circuit_hash| migrations.contains_key(circuit_hash))
+    {
+        return Err(format!(
+            "Site {} still has circuit migrations in flight",
+            site_label
+        ));
+    }
+
+    let mut future_top_level_queue = HashMap::new();
+    for top_level_hash in top_level_site_hashes(sites) {
+        if let Some(queue) = sites
+            .get(&top_level_hash)
+            .and_then(|site| current_site_queue(site.as_ref()))
+        {
+            future_top_level_queue.insert(top_level_hash, queue);
+        }
+    }
+    if target_parent.is_none() {
+        future_top_level_queue.insert(site_hash, target_queue);
+    }
+
+    let plan = plan_top_level_branch_rehome(
+        sites,
+        circuits,
+        Some(&config),
+        virtualized_sites,
+        site_stick_offset(site.as_ref()),
+        &children_by_parent,
+        &[site_hash],
+        &future_top_level_queue,
+        target_parent,
+        None,
+        &BTreeMap::new(),
+    )?;
+
+    let sites_snapshot = sites.clone();
+    let circuits_snapshot = circuits.clone();
+    let qdisc_handles_snapshot = qdisc_handles.clone();
+    let migrations_snapshot = migrations.clone();
+    let result: Result<(), String> = (|| {
+        apply_site_command_update_stages(
+            &config,
+            sites,
+            &plan.active_sites,
+            &plan.site_stages,
+            "TreeGuard top-level site move",
+            true,
+        )?;
+        apply_top_level_circuit_command_updates(
+            &config,
+            sites,
+            circuits,
+            &plan.active_circuits,
+            live_circuits,
+            mq_layout,
+            qdisc_handles,
+            migrations,
+            "TreeGuard top-level site move circuit reparent",
+        )
+    })();
+
+    if let Err(error) = result {
+        *sites = sites_snapshot;
+        *circuits = circuits_snapshot;
+        *qdisc_handles = qdisc_handles_snapshot;
+        *migrations = migrations_snapshot;
+        if runtime_error_suggests_material_desync(&error) {
+            mark_reload_required(format!(
+                "Bakery detected material runtime drift while moving {} to Q{}: {}",
+                site_label, target_queue, error
+            ));
+        }
+        return Err(error);
+    }
+
+    push_bakery_event_with_site_name(
+        "top_level_site_move_started",
+        "info",
+        Some(site_hash),
+        site_name.clone(),
+        format!(
+            "Moving top-level site {} from Q{} to Q{} ({} sites, {} circuits).",
+            site_label,
+            from_queue,
+            target_queue,
+            plan.saved_sites.len(),
+            plan.saved_circuits.len()
+        ),
+    );
+    top_level_moves.insert(
+        site_hash,
+        TopLevelSiteMoveState {
+            site_name,
+            from_queue,
+            to_queue: target_queue,
+            old_sites: plan.saved_sites,
+            moved_circuits: plan.active_circuits.keys().copied().collect(),
+            prune_attempts: 0,
+            next_prune_attempt_unix: unix_now().saturating_add(RUNTIME_CUTOVER_RETRY_SECONDS),
+        },
+    );
+    Ok(())
+}
+
+/// Removes the previous branch of completed top-level site moves once their circuits have
+/// finished migrating.
+fn flush_top_level_site_move_prunes(
+    config: &Arc<Config>,
+    top_level_moves: &mut HashMap<i64, TopLevelSiteMoveState>,
+    migrations: &HashMap<i64, Migration>,
+) {
+    let now_unix = unix_now();
+    let mut ready: Vec<i64> = top_level_moves
+        .iter()
+        .filter(|(_, state)| top_level_site_move_prune_ready(state, migrations, now_unix))
+        .map(|(site_hash, _)| *site_hash)
+        .collect();
+    if ready.is_empty() {
+        return;
+    }
+    ready.sort_unstable();
+
+    let snapshots = read_live_class_snapshot(&config.isp_interface()).and_then(|down| {
+        read_live_class_snapshot(&config.internet_interface()).map(|up| (down, up))
+    });
+    let (down_snapshot, up_snapshot) = match snapshots {
+        Ok(snapshots) => snapshots,
+        Err(e) => {
+            warn!("Bakery: unable to read live classes for top-level move prune: {e}");
+            return;
+        }
+    };
+
+    for site_hash in ready {
+        let Some(state) = top_level_moves.get_mut(&site_hash) else {
+            continue;
+        };
+        let mut commands = Vec::new();
+        for old_hash in ordered_prune_site_hashes(&state.old_sites) {
+            let Some(old_site) = state.old_sites.get(&old_hash) else {
+                continue;
+            };
+            let Some((down_class, up_class)) = site_class_handles(old_site.as_ref()) else {
+                continue;
+            };
+            if let Some(prune) = site_prune_class_commands_for_observed_state(
+                config,
+                old_site.as_ref(),
+                down_snapshot.contains_key(&down_class),
+                up_snapshot.contains_key(&up_class),
+            ) {
+                commands.extend(prune);
+            }
+        }
+
+        let site_label = runtime_site_label(site_hash, state.site_name.as_deref());
+        let result = if commands.is_empty() {
+            None
+        } else {
+            Some(execute_in_memory(
+                &commands,
+                "TreeGuard top-level site move prune",
+            ))
+        };
+        match result {
+            Some(result) if !result.ok => {
+                state.prune_attempts = state.prune_attempts.saturating_add(1);
+                let summary =
+                    summarize_apply_result("TreeGuard top-level site move prune", &result);
+                if state.prune_attempts >= RUNTIME_SITE_PRUNE_MAX_ATTEMPTS {
+                    let site_name = state.site_name.clone();
+                    top_level_moves.remove(&site_hash);
+                    mark_reload_required(format!(
+                        "Bakery could not prune the previous branch of moved site {}: {}",
+                        site_label, summary
+                    ));
+                    push_bakery_event_with_site_name(
+                        "top_level_site_move_failed",
+                        "error",
+                        Some(site_hash),
+                        site_name,
+                        summary,
+                    );
+                } else {
+                    state.next_prune_attempt_unix =
+                        now_unix.saturating_add(RUNTIME_SITE_PRUNE_RETRY_SECONDS);
+                    warn!(
+                        "Bakery: prune of previous branch for moved site {} failed (attempt {}): {}",
+                        site_label, state.prune_attempts, summary
+                    );
+                }
+            }
+            _ => {
+                let summary = format!(
+                    "Top-level site {} moved from Q{} to Q{}; previous branch removed.",
+                    site_label, state.from_queue, state.to_queue
+                );
+                let site_name = state.site_name.clone();
+                top_level_moves.remove(&site_hash);
+                push_bakery_event_with_site_name(
+                    "top_level_site_move_completed",
+                    "info",
+                    Some(site_hash),
+                    site_name,
+                    summary,
+                );
+            }
+        }
+    }
+}
+
+/// Keeps pending top-level move prunes across a commit, which (when incremental) leaves the old
+/// branch classes in place. Old classes the committed tree uses again are dropped from the
+/// prune, so it never removes a live branch; moves with nothing left to prune are forgotten.
+fn retain_unclaimed_top_level_site_moves(
+    top_level_moves: &mut HashMap<i64, TopLevelSiteMoveState>,
+    sites: &HashMap<i64, Arc<BakeryCommands>>,
+) {
+    let (claimed_down, claimed_up): (HashSet<TcHandle>, HashSet<TcHandle>) = sites
+        .values()
+        .filter_map(|site| site_class_handles(site.as_ref()))
+        .unzip();
+    top_level_moves.retain(|site_hash, state| {
+        state.old_sites.retain(|_, old_site| {
+            site_class_handles(old_site.as_ref())
+                .is_none_or(|(down, up)| !claimed_down.contains(&down) && !claimed_up.contains(&up))
+        });
+        if !state.old_sites.is_empty() {
+            return true;
+        }
+        push_bakery_event_with_site_name(
+            "top_level_site_move_superseded",
+            "info",
+            Some(*site_hash),
+            state.site_name.clone(),
+            format!(
+                "Commit rebuilt the previous branch of {} (Q{} -> Q{}); nothing left to prune.",
+                runtime_site_label(*site_hash, state.site_name.as_deref()),
+                state.from_queue,
+                state.to_queue
+            ),
+        );
+        false
+    });
+}
+
 #[allow(clippy::too_many_arguments)]
 fn full_reload(
     batch: &mut Option<Vec<Arc<BakeryCommands>>>,
@@ -10732,6 +11294,236 @@ mod tests {
         );
     }
 
+    #[test]
+    fn top_level_move_plan_rehomes_subtree_onto_target_queue() {
+        let moved_site = mk_add_site(10, 0x10000, 0x20000, 0x20);
+        let other_site = mk_add_site(20, 0x20000, 0x30000, 0x21);
+        let child_site = mk_add_site(30, 0x10020, 0x20020, 0x22);
+        let circuit = Arc::new(BakeryCommands::AddCircuit {
+            circuit_hash: 40,
+            circuit_name: None,
+            site_name: None,
+            parent_class_id: TcHandle::from_u32(0x10022),
+            up_parent_class_id: TcHandle::from_u32(0x20022),
+            class_minor: 0x30,
+            download_bandwidth_min: 10.0,
+            upload_bandwidth_min: 10.0,
+            download_bandwidth_max: 100.0,
+            upload_bandwidth_max: 100.0,
+            class_major: 0x1,
+            up_class_major: 0x2,
+            down_qdisc_handle: None,
+            up_qdisc_handle: None,
+            ip_addresses: "192.0.2.40/32".to_string(),
+            sqm_override: None,
+        });
+
+        let mut sites = HashMap::new();
+        sites.insert(10, moved_site);
+        sites.insert(20, other_site);
+        sites.insert(30, child_site);
+        let mut circuits = HashMap::new();
+        circuits.insert(40, circuit);
+
+        let mut future_top_level_queue = HashMap::new();
+        future_top_level_queue.insert(10, 2);
+        future_top_level_queue.insert(20, 2);
+
+        let plan = plan_top_level_branch_rehome(
+            &sites,
+            &circuits,
+            None,
+            &HashMap::new(),
+            1,
+            &direct_child_sites_by_parent(&sites),
+            &[10],
+            &future_top_level_queue,
+            None,
+            None,
+            &BTreeMap::new(),
+        )
+        .expect("move plan should build");
+
+        assert_eq!(plan.saved_sites.len(), 2);
+        assert!(plan.saved_circuits.contains_key(&40));
+        assert!(!plan.active_sites.contains_key(&20));
+        assert_eq!(plan.site_stages, vec![vec![10], vec![30]]);
+
+        let root = plan.active_sites.get(&10).expect("moved root");
+        let BakeryCommands::AddSite {
+            parent_class_id,
+            up_parent_class_id,
+            class_minor: root_minor,
+            ..
+        } = root.command.as_ref()
+        else {
+            panic!("expected AddSite");
+        };
+        assert_eq!(*parent_class_id, TcHandle::from_u32(0x20000));
+        assert_eq!(*up_parent_class_id, TcHandle::from_u32(0x30000));
+
+        let child = plan.active_sites.get(&30).expect("moved child");
+        let BakeryCommands::AddSite {
+            parent_class_id,
+            class_minor: child_minor,
+            ..
+        } = child.command.as_ref()
+        else {
+            panic!("expected AddSite");
+        };
+        assert_eq!(parent_class_id.get_major_minor(), (0x2, *root_minor));
+
+        let moved_circuit = plan.active_circuits.get(&40).expect("moved circuit");
+        let BakeryCommands::AddCircuit {
+            parent_class_id,
+            class_major,
+            up_class_major,
+            ..
+        } = moved_circuit.command.as_ref()
+        else {
+            panic!("expected AddCircuit");
+        };
+        assert_eq!(parent_class_id.get_major_minor(), (0x2, *child_minor));
+        assert_eq!((*class_major, *up_class_major), (0x2, 0x3));
+    }
+
+    #[test]
+    fn top_level_move_plan_rehomes_binned_site_under_target_bin() {
+        let bin_one = generated_queue_site_hash(1);
+        let bin_two = generated_queue_site_hash(2);
+        let mut sites = HashMap::new();
+        sites.insert(bin_one, mk_add_site(bin_one, 0x10000, 0x20000, 0x1));
+        sites.insert(bin_two, mk_add_site(bin_two, 0x20000, 0x30000, 0x1));
+        sites.insert(10, mk_add_site(10, 0x10001, 0x20001, 0x2));
+        sites.insert(20, mk_add_site(20, 0x20001, 0x30001, 0x2));
+
+        let rebalanceable = rebalanceable_top_level_site_queues(&sites);
+        assert_eq!(rebalanceable.len(), 2);
+        assert_eq!(rebalanceable.get(&10), Some(&1));
+        assert_eq!(rebalanceable.get(&20), Some(&2));
+
+        let mut future_top_level_queue = HashMap::new();
+        future_top_level_queue.insert(bin_one, 1);
+        future_top_level_queue.insert(bin_two, 2);
+
+        let plan = plan_top_level_branch_rehome(
+            &sites,
+            &HashMap::new(),
+            None,
+            &HashMap::new(),
+            1,
+            &direct_child_sites_by_parent(&sites),
+            &[10],
+            &future_top_level_queue,
+            Some(bin_two),
+            None,
+            &BTreeMap::new(),
+        )
+        .expect("binned move plan should build");
+
+        let root = plan.active_sites.get(&10).expect("moved root");
+        let BakeryCommands::AddSite {
+            parent_class_id,
+            up_parent_class_id,
+            class_minor,
+            ..
+        } = root.command.as_ref()
+        else {
+            panic!("expected AddSite");
+        };
+        assert_eq!(*parent_class_id, TcHandle::from_u32(0x20001));
+        assert_eq!(*up_parent_class_id, TcHandle::from_u32(0x30001));
+        assert_ne!(*class_minor, 0x2);
+    }
+
+    #[test]
+    fn top_level_move_prune_waits_for_circuit_migrations() {
+        let state = TopLevelSiteMoveState {
+            site_name: None,
+            from_queue: 1,
+            to_queue: 2,
+            old_sites: HashMap::new(),
+            moved_circuits: vec![40],
+            prune_attempts: 0,
+            next_prune_attempt_unix: 100,
+        };
+        let mut migrations = HashMap::new();
+        assert!(!top_level_site_move_prune_ready(&state, &migrations, 99));
+        assert!(top_level_site_move_prune_ready(&state, &migrations, 100));
+
+        let old = mk_add_circuit(40, "192.0.2.40/32");
+        let new = Arc::new(old.as_ref().clone());
+        let sites = HashMap::new();
+        let mut circuits = HashMap::new();
+        circuits.insert(40, Arc::clone(&old));
+        assert!(queue_runtime_migration(
+            old.as_ref(),
+            &new,
+            &sites,
+            &mut circuits,
+            &HashMap::new(),
+            &mut migrations,
+            false,
+        ));
+        assert!(!top_level_site_move_prune_ready(&state, &migrations, 100));
+    }
+
+    #[test]
+    fn commit_keeps_pruning_old_branches_it_does_not_reuse() {
+        let move_state = |old_sites: HashMap<i64, Arc<BakeryCommands>>| TopLevelSiteMoveState {
+            site_name: None,
+            from_queue: 1,
+            to_queue: 2,
+            old_sites,
+            moved_circuits: Vec::new(),
+            prune_attempts: 0,
+            next_prune_attempt_unix: 0,
+        };
+        let mut top_level_moves = HashMap::new();
+        top_level_moves.insert(
+            10,
+            move_state(HashMap::from([(
+                10,
+                mk_add_site(10, 0x10000, 0x20000, 0x3),
+            )])),
+        );
+        top_level_moves.insert(
+            20,
+            move_state(HashMap::from([
+                (20, mk_add_site(20, 0x10000, 0x20000, 0x4)),
+                (21, mk_add_site(21, 0x10004, 0x20004, 0x5)),
+            ])),
+        );
+
+        // The commit put site 20 back on its old branch but left 21's old class unused.
+        let mut sites = HashMap::new();
+        sites.insert(10, mk_add_site(10, 0x20000, 0x30000, 0x3));
+        sites.insert(20, mk_add_site(20, 0x10000, 0x20000, 0x4));
+
+        retain_unclaimed_top_level_site_moves(&mut top_level_moves, &sites);
+        assert!(
+            top_level_moves[&10].old_sites.contains_key(&10),
+            "site 10's old branch is still pruned after the commit"
+        );
+        let remaining: Vec<i64> = top_level_moves[&20].old_sites.keys().copied().collect();
+        assert_eq!(remaining, vec![21], "reused classes are never pruned");
+
+        sites.insert(21, mk_add_site(21, 0x10004, 0x20004, 0x5));
+        retain_unclaimed_top_level_site_moves(&mut top_level_moves, &sites);
+        assert!(!top_level_moves.contains_key(&20));
+    }
+
+    #[test]
+    fn queues_map_to_the_shaping_cpu_list() {
+        assert_eq!(shaping_cpu_for_queue(1, &[]), Some(0));
+        assert_eq!(shaping_cpu_for_queue(3, &[]), Some(2));
+        assert_eq!(shaping_cpu_for_queue(1, &[4, 5, 6]), Some(4));
+        assert_eq!(shaping_cpu_for_queue(3, &[4, 5, 6]), Some(6));
+        assert_eq!(shaping_cpu_for_queue(4, &[4, 5, 6]), None);
+        assert_eq!(shaping_cpu_for_queue(0, &[]), None);
+        assert_eq!(queue_from_bin_name(&top_level_bin_name(3)), Some(3));
+    }
+
     #[test]
     fn non_top_level_virtualization_plan_uses_shadow_minor_unique_within_major_domain() {
         let parent_site = mk_add_site(10, 0x10003, 0x20003, 0x2002);
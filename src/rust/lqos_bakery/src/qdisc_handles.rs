@@ -48,6 +48,15 @@ impl MqDeviceLayout {
         Self { devices }
     }
 
+    /// Returns true if `queue` is one of the MQ queue majors created on `interface`.
+    pub(crate) fn has_queue(&self, interface: &str, queue: u32) -> bool {
+        u16::try_from(queue).is_ok_and(|major| {
+            self.devices
+                .get(interface)
+                .is_some_and(|majors| majors.contains(&major))
+        })
+    }
+
     /// Returns the qdisc-handle majors reserved on a single interface.
     pub(crate) fn reserved_handles(&self, interface: &str) -> HashSet<u16> {
         let mut reserved = HashSet::new();
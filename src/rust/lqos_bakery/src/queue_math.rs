@@ -30,40 +30,42 @@ pub(crate) fn format_rate_for_tc(rate: u64) -> String {
     }
 }
 
+/// Lowest rate (Mbps) passed to `tc`: 10 kbit, to avoid "0kbit" or rounding to zero.
+/// Smaller rates are clamped up to it.
+pub const MIN_TC_RATE_MBPS: f32 = 0.01;
+/// Highest rate (Mbps) passed to `tc`: 10 Tbps, an arbitrary but sane cap to avoid
+/// infinities. Larger rates are clamped down to it.
+pub const MAX_TC_RATE_MBPS: f32 = 10_000_000.0;
+
 pub(crate) fn format_rate_for_tc_f32(rate: f32) -> String {
     // Defensive formatting of a rate in Mbps for TC commands with smart unit selection.
     // Guards against NaN/Inf/negative/zero and clamps to sane bounds so `tc` always
     // receives a positive, finite value.
 
-    // Lower bound: 0.01 Mbps (10 kbit) to avoid "0kbit" or rounding to zero.
-    const MIN_RATE_MBPS: f32 = 0.01;
-    // Upper bound: 10,000,000 Mbps (10 Tbps) — arbitrary but sane cap to avoid infinities.
-    const MAX_RATE_MBPS: f32 = 10_000_000.0;
-
     let mut r = rate;
 
     // Handle NaN/Inf
     if !r.is_finite() {
         debug!(
             "format_rate_for_tc_f32: non-finite rate detected ({:?}); clamping to {} Mbps",
-            rate, MIN_RATE_MBPS
+            rate, MIN_TC_RATE_MBPS
         );
-        r = MIN_RATE_MBPS;
+        r = MIN_TC_RATE_MBPS;
     }
 
     // Clamp to bounds
-    if r < MIN_RATE_MBPS {
+    if r < MIN_TC_RATE_MBPS {
         debug!(
             "format_rate_for_tc_f32: rate below minimum ({:?}); clamping to {} Mbps",
-            r, MIN_RATE_MBPS
+            r, MIN_TC_RATE_MBPS
         );
-        r = MIN_RATE_MBPS;
-    } else if r > MAX_RATE_MBPS {
+        r = MIN_TC_RATE_MBPS;
+    } else if r > MAX_TC_RATE_MBPS {
         debug!(
             "format_rate_for_tc_f32: rate above maximum ({:?}); clamping to {} Mbps",
-            r, MAX_RATE_MBPS
+            r, MAX_TC_RATE_MBPS
         );
-        r = MAX_RATE_MBPS;
+        r = MAX_TC_RATE_MBPS;
     }
 
     // Format using thresholds
@@ -168,13 +170,28 @@ pub(crate) fn sqm_tokens_for(
             sqm_rate_fixup(rate, config)
         }
         Some("fq_codel") => vec!["fq_codel".to_string()],
-        Some("cake") => {
+        Some(token) if token == "cake" || token.starts_with("cake:") => {
             let default = &config.queues.default_sqm;
             let mut base = if default.starts_with("cake") {
                 sqm_as_vec(config)
             } else {
                 vec!["cake".to_string(), "diffserv4".to_string()]
             };
+            // CAKE profiles (e.g. `cake:besteffort:ack-filter`) replace the matching
+            // tin mode / ACK filter settings of the base CAKE configuration.
+            if let Some(options) = lqos_config::cake_profile_options(token) {
+                for option in options {
+                    let is_ack = option.contains("ack-filter");
+                    base.retain(|existing| {
+                        if is_ack {
+                            !existing.contains("ack-filter")
+                        } else {
+                            !is_cake_tin_mode(existing)
+                        }
+                    });
+                    base.push(option.to_string());
+                }
+            }
             // If RTT already specified, leave as-is; otherwise apply low-rate fixups
             let has_rtt = base.iter().any(|s| s == "rtt");
             if !has_rtt {
@@ -199,6 +216,13 @@ pub(crate) fn sqm_tokens_for(
     }
 }
 
+fn is_cake_tin_mode(token: &str) -> bool {
+    matches!(
+        token,
+        "besteffort" | "diffserv3" | "diffserv4" | "diffserv8" | "precedence"
+    )
+}
+
 pub(crate) fn effective_sqm_kind(
     rate: f32,
     config: &Arc<lqos_config::Config>,
@@ -3,26 +3,36 @@
 
 use crate::{BUS_SOCKET_PATH, BusReply, BusRequest, BusResponse, BusSession, bus::BusClientError};
 use tokio::{
-    io::{AsyncReadExt, AsyncWriteExt},
+    io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt},
     net::UnixStream,
 };
 use tracing::error;
 
 use super::protocol::{decode_reply_cbor, encode_session_cbor, read_frame, write_frame};
+use super::remote::{self, RemoteBusTarget};
 
 pub(crate) const MAGIC_NUMBER: [u8; 4] = [0x4C, 0x52, 0x45, 0x51]; // "LREQ"
 pub(crate) const MAGIC_RESPONSE: [u8; 4] = [0x4C, 0x52, 0x45, 0x50]; // "LREP"
 
+/// A connection the bus protocol can run over: the local Unix socket or a TLS stream.
+trait BusStream: AsyncRead + AsyncWrite + Unpin + Send + Sync {}
+impl<T: AsyncRead + AsyncWrite + Unpin + Send + Sync> BusStream for T {}
+
 /// A client for the libreqos bus, which connects to the bus socket and sends requests.
 /// The client is persistent by default, disconnecting when dropped.
 pub struct LibreqosBusClient {
-    stream: UnixStream,
+    stream: Box<dyn BusStream>,
     request_id: u64,
 }
 
 impl LibreqosBusClient {
-    /// Creates a new `LibreqosBusClient`.
+    /// Creates a new `LibreqosBusClient`. Connects to the remote bus described by the
+    /// `LQOS_BUS_*` environment variables if `LQOS_BUS_ADDRESS` is set (see
+    /// [`RemoteBusTarget::from_env`]), and to the local socket otherwise.
     pub async fn new() -> Result<Self, BusClientError> {
+        if let Some(target) = RemoteBusTarget::from_env() {
+            return Self::new_remote(&target).await;
+        }
         let Ok(mut stream) = UnixStream::connect(BUS_SOCKET_PATH).await else {
             return Err(BusClientError::SocketNotFound);
         };
@@ -33,14 +43,27 @@ impl LibreqosBusClient {
             BusClientError::StreamWriteError
         })?;
 
+        Self::finish_handshake(Box::new(stream), BUS_SOCKET_PATH).await
+    }
+
+    /// Creates a new `LibreqosBusClient` connected to a shaper's remote (TLS) bus.
+    pub async fn new_remote(target: &RemoteBusTarget) -> Result<Self, BusClientError> {
+        let stream = remote::connect(target).await?;
+        Self::finish_handshake(Box::new(stream), &target.address).await
+    }
+
+    async fn finish_handshake(
+        mut stream: Box<dyn BusStream>,
+        label: &str,
+    ) -> Result<Self, BusClientError> {
         // Read the response magic number
         let mut buf = [0u8; 4];
         stream.read_exact(&mut buf).await.map_err(|_| {
-            error!("Unable to read magic number from {BUS_SOCKET_PATH} stream.");
+            error!("Unable to read magic number from {label} stream.");
             BusClientError::StreamReadError
         })?;
         if buf != MAGIC_RESPONSE {
-            error!("Received invalid magic number from {BUS_SOCKET_PATH} stream.");
+            error!("Received invalid magic number from {label} stream.");
             return Err(BusClientError::StreamReadError);
         }
 
@@ -103,8 +126,6 @@ impl LibreqosBusClient {
 ///
 /// **Returns** Either an error, or a vector of `BusResponse` replies
 pub async fn bus_request(requests: Vec<BusRequest>) -> Result<Vec<BusResponse>, BusClientError> {
-    let Ok(mut client) = LibreqosBusClient::new().await else {
-        return Err(BusClientError::SocketNotFound);
-    };
+    let mut client = LibreqosBusClient::new().await?;
     client.request(requests).await
 }
@@ -4,6 +4,7 @@
 mod client;
 mod protocol;
 mod queue_data;
+mod remote;
 mod reply;
 mod request;
 pub mod response;
@@ -11,6 +12,7 @@ mod session;
 mod unix_socket_server;
 pub use client::{LibreqosBusClient, bus_request};
 pub use queue_data::*;
+pub use remote::{RemoteBusCaller, RemoteBusTarget, TlsBusServer, TlsBusServerError};
 pub use reply::BusReply;
 pub use request::{
     BakeryCapacityReportInterface, BlackboardSystem, BusRequest, TopFlowType, UrgentSeverity,
@@ -18,7 +20,8 @@ pub use request::{
 };
 #[allow(unused_imports)]
 pub use response::{
-    AsnHeatmapData, BakeryStatsSnapshot, BusResponse, CircuitHeatmapData, SiteHeatmapData,
+    AsnHeatmapData, AuditLogEntry, BakeryStatsSnapshot, BusResponse, CircuitHeatmapData, HaStatus,
+    HealthSnapshot, OverrideDiffLine, OverrideHistoryEntry, SiteHeatmapData,
     StormguardDebugDirection, StormguardDebugEntry, TreeGuardRuntimeNodeBranchSnapshot,
     TreeGuardRuntimeNodeOperationSnapshot, UrgentIssue,
 };
@@ -58,4 +61,7 @@ pub enum BusClientError {
     /// The socket connection is no longer usable.
     #[error("Stream is no longer connected")]
     StreamNotConnected,
+    /// A remote (TLS) bus connection could not be established.
+    #[error("Unable to connect to remote bus: {0}")]
+    RemoteConnectFailed(String),
 }
@@ -86,6 +86,15 @@ pub(crate) async fn write_frame<W: AsyncWrite + Unpin>(
 
 pub(crate) async fn read_frame<R: AsyncRead + Unpin>(
     reader: &mut R,
+) -> Result<(u64, Vec<u8>), BusClientError> {
+    read_frame_limited(reader, MAX_FRAME_BYTES).await
+}
+
+/// Reads a frame whose payload may be at most `max_bytes`, e.g. before the peer has
+/// authenticated.
+pub(crate) async fn read_frame_limited<R: AsyncRead + Unpin>(
+    reader: &mut R,
+    max_bytes: usize,
 ) -> Result<(u64, Vec<u8>), BusClientError> {
     let request_id = reader
         .read_u64_le()
@@ -104,10 +113,10 @@ pub(crate) async fn read_frame<R: AsyncRead + Unpin>(
         error!("Payload size exceeds usize capacity.");
         BusClientError::DecodingError
     })?;
-    if payload_len > MAX_FRAME_BYTES {
+    if payload_len > max_bytes {
         error!(
-            "Payload size {} exceeds MAX_FRAME_BYTES {}.",
-            payload_len, MAX_FRAME_BYTES
+            "Payload size {} exceeds the frame limit of {} bytes.",
+            payload_len, max_bytes
         );
         return Err(BusClientError::DecodingError);
     }
@@ -156,7 +165,7 @@ pub(crate) async fn read_frame<R: AsyncRead + Unpin>(
 mod tests {
     use super::{
         BUS_CHUNK_SIZE, MAX_FRAME_BYTES, decode_reply_cbor, decode_session_cbor, encode_reply_cbor,
-        encode_session_cbor, read_frame, write_frame,
+        encode_session_cbor, read_frame, read_frame_limited, write_frame,
     };
     use crate::{BusReply, BusRequest, BusResponse, BusSession, bus::BusClientError};
     use tokio::io::{AsyncWriteExt, duplex};
@@ -241,6 +250,21 @@ mod tests {
         assert!(matches!(result, Err(BusClientError::DecodingError)));
     }
 
+    #[tokio::test]
+    async fn limited_frame_rejects_payload_over_its_limit() {
+        let (mut client, mut server) = duplex(128 * 1024);
+        let payload = vec![0x42; 4097];
+        let write = async {
+            write_frame(&mut client, 0, &payload)
+                .await
+                .expect("write_frame");
+        };
+        let read = async { read_frame_limited(&mut server, 4096).await };
+
+        let (_, result) = tokio::join!(write, read);
+        assert!(matches!(result, Err(BusClientError::DecodingError)));
+    }
+
     #[tokio::test]
     async fn frame_multiple_back_to_back() {
         let (mut client, mut server) = duplex(256 * 1024);
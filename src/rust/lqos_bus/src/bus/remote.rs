@@ -0,0 +1,456 @@
+// SPDX-FileCopyrightText: 2025 LibreQoE support@libreqos.io
+// SPDX-License-Identifier: AGPL-3.0-or-later WITH LicenseRef-LibreQoS-Exception
+
+//! The bus over TLS, for tools running on another host.
+//!
+//! A remote session is a local session wrapped in TLS, with one extra step: after the magic
+//! number, the client sends a `BusAuth` frame (CBOR, request ID 0) carrying its token, if it has
+//! one. The server identifies the client from that token and/or the SHA-256 fingerprint of its
+//! TLS client certificate, replies with the usual magic number, and from then on exchanges
+//! `BusSession`/`BusReply` frames exactly as on the Unix socket. Requests missing from the
+//! client's allowlist are answered with `BusResponse::Fail` and never reach `lqosd`.
+
+use std::{path::Path, sync::Arc, time::Duration};
+
+use lqos_config::{RemoteBusClient, RemoteBusConfig, normalize_fingerprint};
+use rustls::{
+    ClientConfig, RootCertStore, ServerConfig,
+    pki_types::{CertificateDer, PrivateKeyDer, ServerName, pem::PemObject},
+    server::WebPkiClientVerifier,
+};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use thiserror::Error;
+use tokio::{
+    io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt},
+    net::{TcpListener, TcpStream},
+};
+use tokio_rustls::{TlsAcceptor, TlsConnector};
+use tracing::{debug, error, info, warn};
+
+use super::{
+    BusClientError,
+    client::{MAGIC_NUMBER, MAGIC_RESPONSE},
+    protocol::{
+        decode_session_cbor, encode_reply_cbor, read_frame, read_frame_limited, write_frame,
+    },
+};
+use crate::{BusReply, BusRequest, BusResponse};
+
+/// Connections that haven't finished TLS and authentication by then are dropped.
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(10);
+/// Largest `BusAuth` frame accepted; it only carries an optional token.
+const MAX_AUTH_FRAME_BYTES: usize = 4 * 1024;
+
+/// Credentials sent by a remote client after the magic number.
+#[derive(Serialize, Deserialize, Default)]
+pub(crate) struct BusAuth {
+    pub(crate) token: Option<String>,
+}
+
+/// The authenticated remote client behind a request, for auditing.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RemoteBusCaller {
+    /// The client's `name` from `[[remote_bus.clients]]`.
+    pub client: String,
+    /// Address the client connected from.
+    pub peer: String,
+}
+
+/// Where and how to reach a remote `lqosd` bus.
+#[derive(Clone, Debug, Default)]
+pub struct RemoteBusTarget {
+    /// `host:port` of the shaper's remote bus listener.
+    pub address: String,
+    /// Name to verify the server certificate against. Defaults to the host part of `address`.
+    pub server_name: Option<String>,
+    /// PEM CA bundle that signed the server certificate.
+    pub ca_file: String,
+    /// Token configured for this client on the shaper, if any.
+    pub token: Option<String>,
+    /// PEM client certificate chain, for mutual TLS.
+    pub client_cert_file: Option<String>,
+    /// PEM private key for `client_cert_file`.
+    pub client_key_file: Option<String>,
+}
+
+impl RemoteBusTarget {
+    /// Reads a target from `LQOS_BUS_ADDRESS`, `LQOS_BUS_CA`, `LQOS_BUS_TOKEN`,
+    /// `LQOS_BUS_CERT`, `LQOS_BUS_KEY` and `LQOS_BUS_SERVER_NAME`. Returns `None` (use the local
+    /// socket) unless `LQOS_BUS_ADDRESS` is set.
+    pub fn from_env() -> Option<Self> {
+        let var = |name: &str| std::env::var(name).ok().filter(|v| !v.trim().is_empty());
+        Some(Self {
+            address: var("LQOS_BUS_ADDRESS")?,
+            server_name: var("LQOS_BUS_SERVER_NAME"),
+            ca_file: var("LQOS_BUS_CA").unwrap_or_default(),
+            token: var("LQOS_BUS_TOKEN"),
+            client_cert_file: var("LQOS_BUS_CERT"),
+            client_key_file: var("LQOS_BUS_KEY"),
+        })
+    }
+
+    fn server_name(&self) -> String {
+        if let Some(name) = &self.server_name {
+            return name.clone();
+        }
+        let host = self
+            .address
+            .rsplit_once(':')
+            .map_or(self.address.as_str(), |(host, _)| host);
+        host.trim_start_matches('[')
+            .trim_end_matches(']')
+            .to_string()
+    }
+}
+
+fn load_certs(path: &str) -> Result<Vec<CertificateDer<'static>>, String> {
+    let certs = CertificateDer::pem_file_iter(Path::new(path))
+        .and_then(|iter| iter.collect::<Result<Vec<_>, _>>())
+        .map_err(|e| format!("unable to read certificates from {path}: {e}"))?;
+    if certs.is_empty() {
+        return Err(format!("no certificates in {path}"));
+    }
+    Ok(certs)
+}
+
+fn load_key(path: &str) -> Result<PrivateKeyDer<'static>, String> {
+    PrivateKeyDer::from_pem_file(Path::new(path))
+        .map_err(|e| format!("unable to read private key from {path}: {e}"))
+}
+
+fn load_roots(path: &str) -> Result<RootCertStore, String> {
+    let mut roots = RootCertStore::empty();
+    for cert in load_certs(path)? {
+        roots
+            .add(cert)
+            .map_err(|e| format!("invalid CA certificate in {path}: {e}"))?;
+    }
+    Ok(roots)
+}
+
+/// Opens a TLS connection to a remote bus and sends the magic number and credentials. The
+/// caller reads the magic response.
+pub(crate) async fn connect(
+    target: &RemoteBusTarget,
+) -> Result<tokio_rustls::client::TlsStream<TcpStream>, BusClientError> {
+    let remote_error = |e: String| {
+        error!("Remote bus {}: {e}", target.address);
+        BusClientError::RemoteConnectFailed(e)
+    };
+    if target.ca_file.is_empty() {
+        return Err(remote_error(
+            "LQOS_BUS_CA must name the CA that signed the shaper's certificate".to_string(),
+        ));
+    }
+    let _ = lqos_utils::rustls::ensure_rustls_crypto_provider();
+    let builder = ClientConfig::builder()
+        .with_root_certificates(load_roots(&target.ca_file).map_err(remote_error)?);
+    let config = match (&target.client_cert_file, &target.client_key_file) {
+        (Some(cert), Some(key)) => builder
+            .with_client_auth_cert(
+                load_certs(cert).map_err(remote_error)?,
+                load_key(key).map_err(remote_error)?,
+            )
+            .map_err(|e| remote_error(e.to_string()))?,
+        (None, None) => builder.with_no_client_auth(),
+        _ => {
+            return Err(remote_error(
+                "set both LQOS_BUS_CERT and LQOS_BUS_KEY".to_string(),
+            ));
+        }
+    };
+    let server_name =
+        ServerName::try_from(target.server_name()).map_err(|e| remote_error(e.to_string()))?;
+
+    let tcp = TcpStream::connect(&target.address)
+        .await
+        .map_err(|e| remote_error(e.to_string()))?;
+    let mut stream = TlsConnector::from(Arc::new(config))
+        .connect(server_name, tcp)
+        .await
+        .map_err(|e| remote_error(e.to_string()))?;
+
+    stream
+        .write_all(&MAGIC_NUMBER)
+        .await
+        .map_err(|_| BusClientError::StreamWriteError)?;
+    let auth = BusAuth {
+        token: target.token.clone(),
+    };
+    let auth = serde_cbor::to_vec(&auth).map_err(|_| BusClientError::EncodingError)?;
+    write_frame(&mut stream, 0, &auth).await?;
+    Ok(stream)
+}
+
+/// Serves the bus over TLS to the clients listed in `[remote_bus]`.
+pub struct TlsBusServer {
+    listen: String,
+    acceptor: TlsAcceptor,
+    clients: Arc<Vec<RemoteBusClient>>,
+}
+
+impl TlsBusServer {
+    /// Loads the certificates named in `config`.
+    pub fn new(config: &RemoteBusConfig) -> Result<Self, TlsBusServerError> {
+        let _ = lqos_utils::rustls::ensure_rustls_crypto_provider();
+        let certs = load_certs(&config.cert_file).map_err(TlsBusServerError::Tls)?;
+        let key = load_key(&config.key_file).map_err(TlsBusServerError::Tls)?;
+        let builder = ServerConfig::builder();
+        let builder = match &config.client_ca_file {
+            Some(ca) => {
+                let roots = load_roots(ca).map_err(TlsBusServerError::Tls)?;
+                // Token-only clients may connect without a certificate; clients configured
+                // with a fingerprint are held to it in `find_client`.
+                let verifier = WebPkiClientVerifier::builder(Arc::new(roots))
+                    .allow_unauthenticated()
+                    .build()
+                    .map_err(|e| TlsBusServerError::Tls(e.to_string()))?;
+                builder.with_client_cert_verifier(verifier)
+            }
+            None => builder.with_no_client_auth(),
+        };
+        let server_config = builder
+            .with_single_cert(certs, key)
+            .map_err(|e| TlsBusServerError::Tls(e.to_string()))?;
+        Ok(Self {
+            listen: config.listen.clone(),
+            acceptor: TlsAcceptor::from(Arc::new(server_config)),
+            clients: Arc::new(config.clients.clone()),
+        })
+    }
+
+    /// Accepts remote sessions and forwards permitted requests to `handle_bus_requests`,
+    /// along with the client that sent them.
+    pub async fn listen(
+        &self,
+        handle_bus_requests: fn(&RemoteBusCaller, &[BusRequest], &mut Vec<BusResponse>),
+    ) -> Result<(), TlsBusServerError> {
+        let listener = TcpListener::bind(&self.listen).await.map_err(|e| {
+            error!("Unable to bind remote bus to {}: {e}", self.listen);
+            TlsBusServerError::BindFail
+        })?;
+        info!("Remote bus listening on: {}", self.listen);
+        loop {
+            let (socket, peer) = match listener.accept().await {
+                Ok(accepted) => accepted,
+                Err(e) => {
+                    warn!("Remote bus accept failed: {e}");
+                    continue;
+                }
+            };
+            let acceptor = self.acceptor.clone();
+            let clients = self.clients.clone();
+            tokio::spawn(async move {
+                let session =
+                    tokio::time::timeout(HANDSHAKE_TIMEOUT, handshake(acceptor, socket, &clients))
+                        .await;
+                let (mut stream, client) = match session {
+                    Ok(Ok(session)) => session,
+                    Ok(Err(e)) => {
+                        warn!("Remote bus connection from {peer} rejected: {e}");
+                        return;
+                    }
+                    Err(_) => {
+                        warn!("Remote bus connection from {peer} timed out during handshake");
+                        return;
+                    }
+                };
+                info!("Remote bus client '{}' connected from {peer}", client.name);
+                let caller = RemoteBusCaller {
+                    client: client.name.clone(),
+                    peer: peer.to_string(),
+                };
+                if let Err(e) = serve(&mut stream, &client, &caller, handle_bus_requests).await {
+                    debug!("Remote bus client '{}' disconnected: {e:?}", client.name);
+                }
+            });
+        }
+    }
+}
+
+async fn handshake(
+    acceptor: TlsAcceptor,
+    socket: TcpStream,
+    clients: &[RemoteBusClient],
+) -> Result<(tokio_rustls::server::TlsStream<TcpStream>, RemoteBusClient), String> {
+    let mut stream = acceptor.accept(socket).await.map_err(|e| e.to_string())?;
+    let mut magic = [0u8; 4];
+    stream
+        .read_exact(&mut magic)
+        .await
+        .map_err(|e| e.to_string())?;
+    if magic != MAGIC_NUMBER {
+        return Err("invalid magic number".to_string());
+    }
+    let (_, auth) = read_frame_limited(&mut stream, MAX_AUTH_FRAME_BYTES)
+        .await
+        .map_err(|e| e.to_string())?;
+    let auth: BusAuth = serde_cbor::from_slice(&auth).map_err(|e| e.to_string())?;
+    let fingerprint = stream
+        .get_ref()
+        .1
+        .peer_certificates()
+        .and_then(|certs| certs.first())
+        .map(|cert| certificate_fingerprint(cert.as_ref()));
+    let client = find_client(clients, auth.token.as_deref(), fingerprint.as_deref())
+        .ok_or_else(|| "no matching [[remote_bus.clients]] entry".to_string())?
+        .clone();
+    stream
+        .write_all(&MAGIC_RESPONSE)
+        .await
+        .map_err(|e| e.to_string())?;
+    Ok((stream, client))
+}
+
+async fn serve<S: AsyncRead + AsyncWrite + Unpin>(
+    stream: &mut S,
+    client: &RemoteBusClient,
+    caller: &RemoteBusCaller,
+    handle_bus_requests: fn(&RemoteBusCaller, &[BusRequest], &mut Vec<BusResponse>),
+) -> Result<(), BusClientError> {
+    loop {
+        let (request_id, request_bytes) = read_frame(stream).await?;
+        if request_bytes.is_empty() {
+            return Ok(());
+        }
+        let session = decode_session_cbor(&request_bytes)?;
+        let mut reply = BusReply {
+            responses: Vec::with_capacity(session.requests.len()),
+        };
+        for request in &session.requests {
+            let name = request_name(request);
+            if request_allowed(&client.allow, &name) {
+                debug!("Remote bus client '{}' sent {name}", client.name);
+                handle_bus_requests(caller, std::slice::from_ref(request), &mut reply.responses);
+            } else {
+                warn!(
+                    "Remote bus client '{}' is not allowed to send {name}",
+                    client.name
+                );
+                reply.responses.push(BusResponse::Fail(format!(
+                    "{name} is not permitted for remote bus client '{}'",
+                    client.name
+                )));
+            }
+        }
+        let encoded = encode_reply_cbor(&reply)?;
+        write_frame(stream, request_id, &encoded).await?;
+    }
+}
+
+/// Lowercase hex SHA-256 of a DER certificate.
+fn certificate_fingerprint(der: &[u8]) -> String {
+    Sha256::digest(der)
+        .iter()
+        .map(|b| format!("{b:02x}"))
+        .collect()
+}
+
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0u8, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+/// The configured client matching the presented credentials. Every credential a client is
+/// configured with must match.
+fn find_client<'a>(
+    clients: &'a [RemoteBusClient],
+    token: Option<&str>,
+    fingerprint: Option<&str>,
+) -> Option<&'a RemoteBusClient> {
+    clients.iter().find(|client| {
+        if client.token.is_none() && client.certificate_sha256.is_none() {
+            return false;
+        }
+        let token_ok = match (&client.token, token) {
+            (None, _) => true,
+            (Some(expected), Some(presented)) => {
+                constant_time_eq(expected.as_bytes(), presented.as_bytes())
+            }
+            (Some(_), None) => false,
+        };
+        let cert_ok = match (&client.certificate_sha256, fingerprint) {
+            (None, _) => true,
+            (Some(expected), Some(presented)) => normalize_fingerprint(expected) == presented,
+            (Some(_), None) => false,
+        };
+        token_ok && cert_ok
+    })
+}
+
+/// The variant name of a request, e.g. `GetCurrentThroughput`.
+fn request_name(request: &BusRequest) -> String {
+    format!("{request:?}")
+        .chars()
+        .take_while(|c| c.is_ascii_alphanumeric() || *c == '_')
+        .collect()
+}
+
+fn request_allowed(allow: &[String], name: &str) -> bool {
+    allow.iter().any(|pattern| match pattern.strip_suffix('*') {
+        Some(prefix) => name.starts_with(prefix),
+        None => pattern == name,
+    })
+}
+
+/// Errors starting the remote bus listener.
+#[derive(Error, Debug)]
+pub enum TlsBusServerError {
+    /// A certificate or key could not be loaded.
+    #[error("Remote bus TLS setup failed: {0}")]
+    Tls(String),
+    /// The listen address could not be bound.
+    #[error("Cannot bind remote bus listener")]
+    BindFail,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn client(name: &str, token: Option<&str>, fingerprint: Option<&str>) -> RemoteBusClient {
+        RemoteBusClient {
+            name: name.to_string(),
+            token: token.map(str::to_string),
+            certificate_sha256: fingerprint.map(str::to_string),
+            allow: vec!["Get*".to_string(), "Ping".to_string()],
+        }
+    }
+
+    #[test]
+    fn clients_must_present_every_configured_credential() {
+        let fingerprint = certificate_fingerprint(b"not really a certificate");
+        let clients = vec![
+            client("token-only", Some("0123456789abcdef"), None),
+            client("cert-only", None, Some(&fingerprint.to_uppercase())),
+            client("both", Some("fedcba9876543210"), Some(&fingerprint)),
+        ];
+
+        let found = |token, cert| find_client(&clients, token, cert).map(|c| c.name.as_str());
+        assert_eq!(found(Some("0123456789abcdef"), None), Some("token-only"));
+        assert_eq!(found(None, Some(fingerprint.as_str())), Some("cert-only"));
+        assert_eq!(
+            found(Some("fedcba9876543210"), Some(fingerprint.as_str())),
+            Some("both")
+        );
+        assert_eq!(found(Some("fedcba9876543210"), None), None);
+        assert_eq!(found(Some("wrong token value"), None), None);
+        assert_eq!(found(None, None), None);
+    }
+
+    #[test]
+    fn allowlist_matches_names_and_prefixes() {
+        let allow = client("noc", None, None).allow;
+        assert_eq!(
+            request_name(&BusRequest::GetCurrentThroughput),
+            "GetCurrentThroughput"
+        );
+        assert!(request_allowed(&allow, &request_name(&BusRequest::Ping)));
+        assert!(request_allowed(&allow, "GetCurrentThroughput"));
+        assert!(!request_allowed(
+            &allow,
+            &request_name(&BusRequest::ClearIpFlow)
+        ));
+        assert!(request_allowed(&["*".to_string()], "ClearIpFlow"));
+    }
+}
@@ -562,6 +562,78 @@ pub enum BusRequest {
 
     /// Retrieve current Insight license summary (licensed + optional max circuits).
     GetInsightLicenseSummary,
+
+    /// List recorded versions of an overrides layer, newest first.
+    GetOverrideHistory {
+        /// Layer name: `operator`, `stormguard` or `treeguard`.
+        layer: String,
+    },
+
+    /// Diff two recorded versions of an overrides layer.
+    GetOverrideDiff {
+        /// Layer name: `operator`, `stormguard` or `treeguard`.
+        layer: String,
+        /// Older version.
+        from_version: u64,
+        /// Newer version; `None` compares against the current file.
+        to_version: Option<u64>,
+    },
+
+    /// Restore an overrides layer to a recorded version.
+    RollbackOverrideLayer {
+        /// Layer name: `operator`, `stormguard` or `treeguard`.
+        layer: String,
+        /// Version to restore.
+        version: u64,
+        /// Name recorded as the author of the rollback.
+        author: String,
+        /// Reason recorded with the rollback.
+        reason: String,
+    },
+
+    /// Query the audit log of administrative actions, newest first.
+    GetAuditLog {
+        /// Only entries at or after this Unix timestamp (seconds).
+        since_unix: Option<u64>,
+        /// Only entries made by this user.
+        user: Option<String>,
+        /// Only entries for this action (e.g. `reload_libreqos`).
+        action: Option<String>,
+        /// Maximum number of entries to return.
+        limit: usize,
+    },
+
+    /// Query active/standby high-availability status.
+    GetHaStatus,
+
+    /// List the circuit IDs excluded from RTT aggregation.
+    GetRttExcludedCircuits,
+
+    /// Exclude a circuit from (or return it to) RTT aggregation.
+    SetCircuitRttExcluded {
+        /// Circuit ID from ShapedDevices.csv
+        circuit_id: String,
+        /// Exclude (`true`) or include (`false`)
+        excluded: bool,
+    },
+
+    /// Override a circuit's maximum speed in the operator overrides layer, removing the
+    /// override again once `minutes` have passed. Reloads LibreQoS when the override is set
+    /// and when it expires; answers `Ack` only once the cap is live.
+    SetTemporaryCircuitSpeed {
+        /// Circuit ID from ShapedDevices.csv
+        circuit_id: String,
+        /// Maximum download in Mbps
+        download_mbps: f32,
+        /// Maximum upload in Mbps
+        upload_mbps: f32,
+        /// How long the override lasts
+        minutes: u32,
+    },
+
+    /// Health counters for monitoring checks: Bakery reload state, XDP map usage and CPU
+    /// load.
+    GetHealthSnapshot,
 }
 
 /// Defines the parts of the blackboard
@@ -32,6 +32,99 @@ pub struct UrgentIssue {
     pub dedupe_key: Option<String>,
 }
 
+/// One recorded version of an overrides layer.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Allocative)]
+pub struct OverrideHistoryEntry {
+    /// Version number within the layer
+    pub version: u64,
+    /// Layer name (`operator`, `stormguard`, `treeguard`)
+    pub layer: String,
+    /// Who made the change (e.g. `user:admin`, `stormguard`)
+    pub author: String,
+    /// Unix timestamp (seconds)
+    pub timestamp_unix: u64,
+    /// Why the change was made
+    pub reason: String,
+}
+
+/// One administrative action recorded in the lqosd audit log.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Allocative)]
+pub struct AuditLogEntry {
+    /// Unix timestamp (seconds)
+    pub timestamp_unix: u64,
+    /// Who performed the action (web user, `api:<token name>` or `bus`)
+    pub user: String,
+    /// Where the request came from (client IP address, or `local` for the bus)
+    pub source: String,
+    /// Action name (e.g. `reload_libreqos`, `set_node_rate_override`)
+    pub action: String,
+    /// What the action applied to (node, circuit, device, user...), if anything
+    pub target: Option<String>,
+    /// Summary of the affected state before the action, if known
+    pub before: Option<String>,
+    /// Summary of the affected state after the action, if known
+    pub after: Option<String>,
+    /// Did the action succeed?
+    pub ok: bool,
+    /// Result or error message
+    pub message: String,
+}
+
+/// Active/standby high-availability status of this shaper.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Allocative)]
+pub struct HaStatus {
+    /// Is HA configured?
+    pub enabled: bool,
+    /// Configured role (`active` or `standby`)
+    pub role: String,
+    /// Has this standby taken over?
+    pub promoted: bool,
+    /// Is the state stream to/from the peer connected?
+    pub connected: bool,
+    /// The peer's address (active) or node name (standby), once known
+    pub peer_name: Option<String>,
+    /// Unix timestamp (seconds) of the last frame sent or received
+    pub last_contact_unix: Option<u64>,
+    /// Commands in the last synchronized Bakery batch
+    pub bakery_commands: usize,
+    /// Synchronized IP mappings
+    pub ip_mappings: usize,
+    /// Synchronized TreeGuard runtime-virtualized nodes
+    pub virtualized_nodes: usize,
+    /// Synchronized StormGuard site rates
+    pub stormguard_sites: usize,
+    /// The most recent connection error, if any
+    pub last_error: Option<String>,
+}
+
+/// Shaper health counters that are not otherwise on the bus, for monitoring checks.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Allocative)]
+pub struct HealthSnapshot {
+    /// Why the Bakery needs a full reload before applying more changes, if it does
+    pub reload_required_reason: Option<String>,
+    /// Entries in the XDP IP-to-CPU/TC map, or `None` if the map could not be read
+    pub ip_mappings: Option<usize>,
+    /// Maximum entries in the XDP IP-to-CPU/TC map
+    pub ip_mapping_capacity: usize,
+    /// Usage (0-100) of each CPU core
+    pub cpu_usage: Vec<u32>,
+}
+
+/// One difference between two versions of an overrides layer.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Allocative)]
+pub struct OverrideDiffLine {
+    /// Override section (e.g. `network_adjustments`)
+    pub section: String,
+    /// Identity of the entry within the section
+    pub key: String,
+    /// `added`, `removed` or `changed`
+    pub kind: String,
+    /// JSON of the older entry, if present
+    pub before: Option<String>,
+    /// JSON of the newer entry, if present
+    pub after: Option<String>,
+}
+
 /// Summary of current Insight license state and limits.
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Allocative)]
 pub struct InsightLicenseSummary {
@@ -689,4 +782,22 @@ pub enum BusResponse {
 
     /// Latest Bakery runtime branch-state snapshot for a named TreeGuard node, if any.
     TreeGuardRuntimeNodeBranch(Option<TreeGuardRuntimeNodeBranchSnapshot>),
+
+    /// Recorded overrides layer versions, newest first
+    OverrideHistory(Vec<OverrideHistoryEntry>),
+
+    /// Differences between two overrides layer versions
+    OverrideDiff(Vec<OverrideDiffLine>),
+
+    /// Audit log entries, newest first
+    AuditLog(Vec<AuditLogEntry>),
+
+    /// High-availability status
+    HaStatus(HaStatus),
+
+    /// Circuit IDs excluded from RTT aggregation
+    RttExcludedCircuits(Vec<String>),
+
+    /// Health counters for monitoring checks
+    HealthSnapshot(HealthSnapshot),
 }
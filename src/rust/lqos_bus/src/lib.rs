@@ -25,18 +25,19 @@ pub use ip_stats::{
 };
 mod tc_handle;
 pub use bus::response::{
-    AsnHeatmapData, AsnListEntry, BakeryStatsSnapshot, CircuitCapacityRow, CircuitCount,
-    CircuitHeatmapData, CountryListEntry, DeviceCounts, ExecutiveSummaryHeader, FlowMapPoint,
-    FlowTimelineEntry, InsightLicenseSummary, NodeCapacity, ProtocolListEntry, QueueStatsTotal,
-    RetransmitSummary, SchedulerDetails, SearchResultEntry, SiteHeatmapData,
-    StormguardDebugDirection, StormguardDebugEntry, TreeGuardRuntimeNodeBranchSnapshot,
+    AsnHeatmapData, AsnListEntry, AuditLogEntry, BakeryStatsSnapshot, CircuitCapacityRow,
+    CircuitCount, CircuitHeatmapData, CountryListEntry, DeviceCounts, ExecutiveSummaryHeader,
+    FlowMapPoint, FlowTimelineEntry, HaStatus, HealthSnapshot, InsightLicenseSummary, NodeCapacity,
+    OverrideDiffLine, OverrideHistoryEntry, ProtocolListEntry, QueueStatsTotal, RetransmitSummary,
+    SchedulerDetails, SearchResultEntry, SiteHeatmapData, StormguardDebugDirection,
+    StormguardDebugEntry, TreeGuardRuntimeNodeBranchSnapshot,
     TreeGuardRuntimeNodeOperationSnapshot, UrgentIssue, WarningLevel,
 };
 pub use bus::{
     BUS_SOCKET_PATH, BakeryCapacityReportInterface, BlackboardSystem, BusClientError, BusReply,
     BusRequest, BusResponse, BusSession, CakeDiffTinTransit, CakeDiffTransit, CakeTransit,
-    LibreqosBusClient, QueueStoreTransit, TopFlowType, UnixSocketServer, UrgentSeverity,
-    UrgentSource, bus_request,
+    LibreqosBusClient, QueueStoreTransit, RemoteBusCaller, RemoteBusTarget, TlsBusServer,
+    TlsBusServerError, TopFlowType, UnixSocketServer, UrgentSeverity, UrgentSource, bus_request,
 };
 pub use tc_handle::TcHandle;
 
@@ -3,7 +3,9 @@
 
 use allocative::Allocative;
 use argon2::{Argon2, PasswordHash, PasswordHasher, PasswordVerifier, password_hash::SaltString};
-use rand_core::OsRng;
+use rand_core::{OsRng, RngCore};
+#[cfg(feature = "schema")]
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 use sha2::{Digest, Sha256};
 use std::{
@@ -16,12 +18,23 @@ use thiserror::Error;
 use tracing::{error, warn};
 use uuid::Uuid;
 
+mod totp;
+
 const AUTH_FILE_VERSION: u32 = 2;
 const LEGACY_AUTH_FILE_VERSION: u32 = 1;
 const INITIAL_AUTH_EPOCH: u64 = 1;
 const LEGACY_AUTH_FILE_NAME: &str = "webusers.toml";
 const CURRENT_AUTH_FILE_NAME: &str = "lqusers.toml";
 const LEGACY_PASSWORD_PEPPER: &str = "_LibreQosLikesPasswordsForDinner";
+const TOTP_ISSUER: &str = "LibreQoS";
+const TOTP_SECRET_BYTES: usize = 20;
+const RECOVERY_CODE_COUNT: usize = 10;
+/// Wrong second-factor codes accepted before further attempts are delayed.
+const TOTP_FREE_FAILURES: u32 = 5;
+/// Delay after the first failure past [`TOTP_FREE_FAILURES`], doubling with each one after.
+const TOTP_BASE_LOCKOUT_SECS: u64 = 30;
+/// Longest delay between second-factor attempts.
+const TOTP_MAX_LOCKOUT_SECS: u64 = 15 * 60;
 
 fn default_auth_file_version() -> u32 {
     LEGACY_AUTH_FILE_VERSION
@@ -33,6 +46,7 @@ fn default_auth_epoch() -> u64 {
 
 /// Access rights of a user
 #[derive(Clone, Copy, Debug, Deserialize, Serialize, PartialEq, Eq, Allocative)]
+#[cfg_attr(feature = "schema", derive(JsonSchema))]
 pub enum UserRole {
     /// The user may view data but not change it.
     ReadOnly,
@@ -71,6 +85,121 @@ impl Display for UserRole {
     }
 }
 
+impl UserRole {
+    /// Permissions granted by the built-in role. Read-only users keep packet capture, which
+    /// they had before permissions existed.
+    pub fn permissions(&self) -> Vec<Permission> {
+        match self {
+            UserRole::Admin => Permission::ALL.to_vec(),
+            UserRole::ReadOnly => vec![Permission::View, Permission::PacketCapture],
+        }
+    }
+}
+
+/// A single capability in the web UI, granted through a role.
+#[derive(Clone, Copy, Debug, Deserialize, Serialize, PartialEq, Eq, Hash, Allocative)]
+#[serde(rename_all = "kebab-case")]
+pub enum Permission {
+    /// See dashboards, the network tree, circuits and devices.
+    View,
+    /// Set or clear rate overrides on network.json nodes.
+    AdjustSpeeds,
+    /// Change overrides: RTT exclusions, pending changes and layer rollbacks.
+    ManageOverrides,
+    /// Trigger a LibreQoS reload.
+    Reload,
+    /// Add, update and remove web users.
+    ManageUsers,
+    /// Start packet captures and download the results.
+    PacketCapture,
+}
+
+impl Permission {
+    /// Every permission, in display order.
+    pub const ALL: [Permission; 6] = [
+        Permission::View,
+        Permission::AdjustSpeeds,
+        Permission::ManageOverrides,
+        Permission::Reload,
+        Permission::ManageUsers,
+        Permission::PacketCapture,
+    ];
+}
+
+impl Display for Permission {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Permission::View => write!(f, "view"),
+            Permission::AdjustSpeeds => write!(f, "adjust-speeds"),
+            Permission::ManageOverrides => write!(f, "manage-overrides"),
+            Permission::Reload => write!(f, "reload"),
+            Permission::ManageUsers => write!(f, "manage-users"),
+            Permission::PacketCapture => write!(f, "packet-capture"),
+        }
+    }
+}
+
+impl std::str::FromStr for Permission {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let wanted = s.trim().to_lowercase().replace('_', "-");
+        Permission::ALL
+            .into_iter()
+            .find(|p| p.to_string() == wanted)
+            .ok_or_else(|| {
+                format!(
+                    "unknown permission '{s}' (expected view, adjust-speeds, manage-overrides, reload, manage-users or packet-capture)"
+                )
+            })
+    }
+}
+
+/// A named set of permissions defined in `lqusers.toml`, e.g. for field techs or partners.
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq, Allocative)]
+pub struct RoleDefinition {
+    /// Role name, referenced by [`WebUser::custom_role`].
+    pub name: String,
+    /// What members of the role may do.
+    pub permissions: Vec<Permission>,
+}
+
+/// Effective rights of a user: their permissions and, optionally, the `network.json`
+/// subtrees they are limited to.
+#[derive(Clone, Debug, PartialEq, Eq, Default)]
+pub struct UserAccess {
+    /// Granted permissions.
+    pub permissions: Vec<Permission>,
+    /// Names of `network.json` nodes the user may see, including everything below them.
+    /// Empty means the whole network.
+    pub scope: Vec<String>,
+}
+
+impl UserAccess {
+    /// Unscoped access with the permissions of a built-in role.
+    pub fn for_role(role: UserRole) -> Self {
+        Self {
+            permissions: role.permissions(),
+            scope: Vec::new(),
+        }
+    }
+
+    /// Is `permission` granted?
+    pub fn allows(&self, permission: Permission) -> bool {
+        self.permissions.contains(&permission)
+    }
+
+    /// Is the user limited to part of the network?
+    pub fn is_scoped(&self) -> bool {
+        !self.scope.is_empty()
+    }
+
+    /// Every permission over the whole network, i.e. equivalent to an administrator.
+    pub fn is_full_admin(&self) -> bool {
+        !self.is_scoped() && Permission::ALL.iter().all(|p| self.allows(*p))
+    }
+}
+
 /// A user of the web UI.
 #[derive(Clone, Debug, Deserialize, Serialize, Allocative)]
 pub struct WebUser {
@@ -81,6 +210,135 @@ pub struct WebUser {
     pub password_hash: String,
     /// The user's role.
     pub role: UserRole,
+    /// Two-factor enrollment, if the user has started or completed one.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub totp: Option<TotpEnrollment>,
+    /// Custom role whose permissions replace those of `role`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub custom_role: Option<String>,
+    /// `network.json` nodes this user is limited to. Empty means the whole network.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub scope: Vec<String>,
+}
+
+impl WebUser {
+    /// Does this user have a confirmed second factor?
+    pub fn has_totp(&self) -> bool {
+        self.totp.as_ref().is_some_and(|totp| totp.confirmed)
+    }
+
+    /// Copy without password hash or TOTP material, for display in the web UI.
+    pub fn redacted(&self) -> Self {
+        let mut user = self.clone();
+        user.password_hash.clear();
+        if let Some(totp) = &mut user.totp {
+            totp.secret.clear();
+            totp.recovery_code_hashes.clear();
+        }
+        user
+    }
+}
+
+/// A user's TOTP (authenticator app) enrollment.
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq, Allocative)]
+pub struct TotpEnrollment {
+    /// Base32 shared secret.
+    pub secret: String,
+    /// True once the user has entered a valid code. Unconfirmed enrollments are not enforced.
+    #[serde(default)]
+    pub confirmed: bool,
+    /// SHA-256 hashes of the unused recovery codes.
+    #[serde(default)]
+    pub recovery_code_hashes: Vec<String>,
+    /// Last accepted time step, so a code cannot be used twice.
+    #[serde(default)]
+    pub last_used_step: u64,
+    /// Wrong codes entered since the last accepted one.
+    #[serde(default)]
+    pub failed_attempts: u32,
+    /// Unix time before which no code is checked, after repeated wrong codes.
+    #[serde(default)]
+    pub locked_until: u64,
+}
+
+/// Details needed to add a new enrollment to an authenticator app.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TotpProvisioning {
+    /// Base32 secret for manual entry.
+    pub secret: String,
+    /// `otpauth://` URI to render as a QR code.
+    pub uri: String,
+}
+
+/// Permission granted to an API token.
+#[derive(Clone, Copy, Debug, Deserialize, Serialize, PartialEq, Eq, Allocative)]
+#[serde(rename_all = "snake_case")]
+pub enum ApiTokenScope {
+    /// Read circuits, sites, devices, overrides, statistics and urgent issues.
+    Read,
+    /// Create, update and delete shaped devices.
+    Devices,
+    /// Import overrides.
+    Overrides,
+    /// Trigger a LibreQoS reload.
+    Reload,
+}
+
+impl Display for ApiTokenScope {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ApiTokenScope::Read => write!(f, "read"),
+            ApiTokenScope::Devices => write!(f, "devices"),
+            ApiTokenScope::Overrides => write!(f, "overrides"),
+            ApiTokenScope::Reload => write!(f, "reload"),
+        }
+    }
+}
+
+impl std::str::FromStr for ApiTokenScope {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "read" => Ok(ApiTokenScope::Read),
+            "devices" => Ok(ApiTokenScope::Devices),
+            "overrides" => Ok(ApiTokenScope::Overrides),
+            "reload" => Ok(ApiTokenScope::Reload),
+            other => Err(format!(
+                "unknown scope '{other}' (expected read, devices, overrides or reload)"
+            )),
+        }
+    }
+}
+
+/// A long-lived API token. Only a SHA-256 hash of the secret is stored.
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq, Allocative)]
+pub struct ApiToken {
+    /// Public identifier, embedded in the token string.
+    pub id: String,
+    /// Operator-supplied label, e.g. the integrating system's name.
+    pub name: String,
+    /// Hex SHA-256 of the secret part of the token.
+    pub secret_hash: String,
+    /// What the token may do.
+    pub scopes: Vec<ApiTokenScope>,
+    /// Unix timestamp (seconds) when the token was issued.
+    pub created_unix: u64,
+    /// Optional Unix timestamp (seconds) after which the token is rejected.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub expires_unix: Option<u64>,
+}
+
+impl ApiToken {
+    /// Does the token grant `scope`?
+    pub fn has_scope(&self, scope: ApiTokenScope) -> bool {
+        self.scopes.contains(&scope)
+    }
+
+    /// Has the token passed its expiry time?
+    pub fn is_expired(&self, now_unix: u64) -> bool {
+        self.expires_unix.is_some_and(|expires| now_unix >= expires)
+    }
 }
 
 /// Result of authenticating a single user.
@@ -113,6 +371,16 @@ pub struct WebUsers {
     allow_unauthenticated_to_view: bool,
     #[serde(default)]
     users: Vec<WebUser>,
+    #[serde(default)]
+    api_tokens: Vec<ApiToken>,
+    #[serde(default)]
+    totp_required_roles: Vec<UserRole>,
+    #[serde(default)]
+    totp_required_custom_roles: Vec<String>,
+    #[serde(default)]
+    totp_required_permissions: Vec<Permission>,
+    #[serde(default)]
+    roles: Vec<RoleDefinition>,
 }
 
 impl Default for WebUsers {
@@ -122,6 +390,11 @@ impl Default for WebUsers {
             auth_epoch: INITIAL_AUTH_EPOCH,
             allow_unauthenticated_to_view: false,
             users: Vec::new(),
+            api_tokens: Vec::new(),
+            totp_required_roles: Vec::new(),
+            totp_required_custom_roles: Vec::new(),
+            totp_required_permissions: Vec::new(),
+            roles: Vec::new(),
         }
     }
 }
@@ -336,6 +609,9 @@ impl WebUsers {
                 username: username.to_string(),
                 password_hash,
                 role,
+                totp: None,
+                custom_role: None,
+                scope: Vec::new(),
             };
             self.users.push(new_user);
         }
@@ -386,10 +662,27 @@ impl WebUsers {
     /// Attempt a login with the specified username and password. If the login
     /// succeeds, returns the authenticated user details and transparently
     /// upgrades legacy password hashes to Argon2id.
+    ///
+    /// Users with a confirmed second factor are refused with
+    /// [`AuthenticationError::TotpRequired`]; use
+    /// [`WebUsers::authenticate_with_second_factor`] for them.
     pub fn authenticate(
         &mut self,
         username: &str,
         password: &str,
+    ) -> Result<AuthenticatedUser, AuthenticationError> {
+        self.authenticate_with_second_factor(username, password, None)
+    }
+
+    /// Attempt a login with username, password and (when the user has enrolled) a TOTP or
+    /// recovery code. Recovery codes are consumed on use. After repeated wrong codes the
+    /// user's second factor is locked for a growing delay, reported as
+    /// [`AuthenticationError::TotpLockedOut`].
+    pub fn authenticate_with_second_factor(
+        &mut self,
+        username: &str,
+        password: &str,
+        code: Option<&str>,
     ) -> Result<AuthenticatedUser, AuthenticationError> {
         let Some(index) = self.users.iter().position(|u| u.username == username) else {
             return Err(AuthenticationError::InvalidLogin);
@@ -400,30 +693,320 @@ impl WebUsers {
             return Err(AuthenticationError::InvalidLogin);
         }
 
+        let mut needs_save = false;
+        let role = self.users[index].role;
+        let totp_required = self.totp_required_for_user(&self.users[index]);
+        match self.users[index].totp.as_mut().filter(|t| t.confirmed) {
+            Some(enrollment) => {
+                let code = code
+                    .filter(|c| !c.trim().is_empty())
+                    .ok_or(AuthenticationError::TotpRequired)?;
+                let attempt = attempt_second_factor(enrollment, code, unix_now());
+                if matches!(attempt, Err(AuthenticationError::InvalidTotp)) {
+                    // Keep the failure count
+                    self.save_to_disk()?;
+                }
+                attempt?;
+                needs_save = true;
+            }
+            None if totp_required => return Err(AuthenticationError::TotpEnrollmentRequired),
+            None => {}
+        }
+
         let mut password_upgraded = false;
         if verification.needs_rehash {
             self.users[index].password_hash = Self::hash_password(password)?;
             self.bump_auth_epoch();
-            self.save_to_disk()?;
+            needs_save = true;
             password_upgraded = true;
         }
+        if needs_save {
+            self.save_to_disk()?;
+        }
 
         Ok(AuthenticatedUser {
             username: self.users[index].username.clone(),
-            role: self.users[index].role,
+            role,
             auth_epoch: self.auth_epoch,
             password_upgraded,
         })
     }
 
+    /// Starts (or restarts) TOTP enrollment for a user and saves `lqusers.toml`. The
+    /// enrollment is enforced only after [`WebUsers::confirm_totp_enrollment`].
+    pub fn begin_totp_enrollment(
+        &mut self,
+        username: &str,
+    ) -> Result<TotpProvisioning, AuthenticationError> {
+        let user = self
+            .users
+            .iter_mut()
+            .find(|u| u.username == username)
+            .ok_or(AuthenticationError::UserNotFound)?;
+        if user.has_totp() {
+            return Err(AuthenticationError::TotpAlreadyEnrolled);
+        }
+        let mut secret_bytes = [0u8; TOTP_SECRET_BYTES];
+        OsRng.fill_bytes(&mut secret_bytes);
+        let secret = totp::base32_encode(&secret_bytes);
+        user.totp = Some(TotpEnrollment {
+            secret: secret.clone(),
+            confirmed: false,
+            recovery_code_hashes: Vec::new(),
+            last_used_step: 0,
+            failed_attempts: 0,
+            locked_until: 0,
+        });
+        self.save_to_disk()?;
+        Ok(TotpProvisioning {
+            uri: totp::provisioning_uri(TOTP_ISSUER, username, &secret),
+            secret,
+        })
+    }
+
+    /// Confirms a pending enrollment with a code from the authenticator app and saves
+    /// `lqusers.toml`. Returns the one-time recovery codes, which are only available here.
+    pub fn confirm_totp_enrollment(
+        &mut self,
+        username: &str,
+        code: &str,
+    ) -> Result<Vec<String>, AuthenticationError> {
+        let user = self
+            .users
+            .iter_mut()
+            .find(|u| u.username == username)
+            .ok_or(AuthenticationError::UserNotFound)?;
+        let enrollment = user
+            .totp
+            .as_mut()
+            .filter(|t| !t.confirmed)
+            .ok_or(AuthenticationError::TotpNotEnrolled)?;
+        let recovery_codes = confirm_enrollment(enrollment, code, unix_now())?;
+        self.bump_auth_epoch();
+        self.save_to_disk()?;
+        Ok(recovery_codes)
+    }
+
+    /// Removes a user's second factor (e.g. a lost phone) and saves `lqusers.toml`. Existing
+    /// sessions are revoked.
+    pub fn reset_totp(&mut self, username: &str) -> Result<(), AuthenticationError> {
+        let user = self
+            .users
+            .iter_mut()
+            .find(|u| u.username == username)
+            .ok_or(AuthenticationError::UserNotFound)?;
+        if user.totp.take().is_none() {
+            return Err(AuthenticationError::TotpNotEnrolled);
+        }
+        self.bump_auth_epoch();
+        self.save_to_disk()?;
+        Ok(())
+    }
+
+    /// Sets whether users with `role` must have a second factor to log in, and saves
+    /// `lqusers.toml`.
+    pub fn set_totp_required(
+        &mut self,
+        role: UserRole,
+        required: bool,
+    ) -> Result<(), AuthenticationError> {
+        self.totp_required_roles.retain(|r| *r != role);
+        if required {
+            self.totp_required_roles.push(role);
+        }
+        self.bump_auth_epoch();
+        self.save_to_disk()?;
+        Ok(())
+    }
+
+    /// Sets whether members of the custom role `name` must have a second factor to log in,
+    /// and saves `lqusers.toml`.
+    pub fn set_totp_required_for_custom_role(
+        &mut self,
+        name: &str,
+        required: bool,
+    ) -> Result<(), AuthenticationError> {
+        if required && !self.roles.iter().any(|r| r.name == name) {
+            return Err(AuthenticationError::RoleNotFound);
+        }
+        self.totp_required_custom_roles.retain(|r| r != name);
+        if required {
+            self.totp_required_custom_roles.push(name.to_string());
+        }
+        self.bump_auth_epoch();
+        self.save_to_disk()?;
+        Ok(())
+    }
+
+    /// Sets whether users granted `permission`, through any role, must have a second factor
+    /// to log in, and saves `lqusers.toml`.
+    pub fn set_totp_required_for_permission(
+        &mut self,
+        permission: Permission,
+        required: bool,
+    ) -> Result<(), AuthenticationError> {
+        self.totp_required_permissions.retain(|p| *p != permission);
+        if required {
+            self.totp_required_permissions.push(permission);
+        }
+        self.bump_auth_epoch();
+        self.save_to_disk()?;
+        Ok(())
+    }
+
+    /// Must `user` use a second factor, because of their built-in role, their custom role or
+    /// any permission they hold?
+    pub fn totp_required_for_user(&self, user: &WebUser) -> bool {
+        if self.totp_required_roles.contains(&user.role)
+            || user
+                .custom_role
+                .as_ref()
+                .is_some_and(|name| self.totp_required_custom_roles.contains(name))
+        {
+            return true;
+        }
+        self.access_for_user(&user.username).is_some_and(|access| {
+            self.totp_required_permissions
+                .iter()
+                .any(|p| access.allows(*p))
+        })
+    }
+
+    /// Creates or replaces a custom role and saves `lqusers.toml`. Existing sessions are
+    /// revoked so members pick up the new permissions.
+    pub fn define_role(
+        &mut self,
+        name: &str,
+        permissions: &[Permission],
+    ) -> Result<(), AuthenticationError> {
+        let name = name.trim();
+        if name.is_empty()
+            || matches!(
+                name.to_lowercase().as_str(),
+                "admin" | "read-only" | "readonly"
+            )
+        {
+            return Err(AuthenticationError::InvalidRoleName);
+        }
+        let mut unique = Vec::new();
+        for permission in permissions {
+            if !unique.contains(permission) {
+                unique.push(*permission);
+            }
+        }
+        match self.roles.iter_mut().find(|r| r.name == name) {
+            Some(role) => role.permissions = unique,
+            None => self.roles.push(RoleDefinition {
+                name: name.to_string(),
+                permissions: unique,
+            }),
+        }
+        self.bump_auth_epoch();
+        self.save_to_disk()?;
+        Ok(())
+    }
+
+    /// Removes a custom role that no user references, and saves `lqusers.toml`.
+    pub fn remove_role(&mut self, name: &str) -> Result<(), AuthenticationError> {
+        if self
+            .users
+            .iter()
+            .any(|u| u.custom_role.as_deref() == Some(name))
+        {
+            return Err(AuthenticationError::RoleInUse);
+        }
+        let old_len = self.roles.len();
+        self.roles.retain(|r| r.name != name);
+        if old_len == self.roles.len() {
+            return Err(AuthenticationError::RoleNotFound);
+        }
+        self.totp_required_custom_roles.retain(|r| r != name);
+        self.bump_auth_epoch();
+        self.save_to_disk()?;
+        Ok(())
+    }
+
+    /// Return the custom role definitions.
+    pub fn get_roles(&self) -> Vec<RoleDefinition> {
+        self.roles.clone()
+    }
+
+    /// Assigns a custom role (or `None` for the built-in role only) and a `network.json`
+    /// scope to a user, and saves `lqusers.toml`. Existing sessions are revoked.
+    pub fn set_user_access(
+        &mut self,
+        username: &str,
+        custom_role: Option<&str>,
+        scope: &[String],
+    ) -> Result<(), AuthenticationError> {
+        if let Some(role) = custom_role
+            && !self.roles.iter().any(|r| r.name == role)
+        {
+            return Err(AuthenticationError::RoleNotFound);
+        }
+        let user = self
+            .users
+            .iter_mut()
+            .find(|u| u.username == username)
+            .ok_or(AuthenticationError::UserNotFound)?;
+        user.custom_role = custom_role.map(str::to_string);
+        user.scope = scope
+            .iter()
+            .map(|node| node.trim().to_string())
+            .filter(|node| !node.is_empty())
+            .collect();
+        self.bump_auth_epoch();
+        self.save_to_disk()?;
+        Ok(())
+    }
+
+    /// Effective permissions and scope of a user, or `None` if the user does not exist.
+    /// A user whose custom role has been deleted from the file falls back to no permissions.
+    pub fn access_for_user(&self, username: &str) -> Option<UserAccess> {
+        let user = self.users.iter().find(|u| u.username == username)?;
+        let permissions = match &user.custom_role {
+            Some(name) => self
+                .roles
+                .iter()
+                .find(|r| &r.name == name)
+                .map(|r| r.permissions.clone())
+                .unwrap_or_default(),
+            None => user.role.permissions(),
+        };
+        Some(UserAccess {
+            permissions,
+            scope: user.scope.clone(),
+        })
+    }
+
     /// Dump all users to the console.
     pub fn print_users(&self) -> Result<(), AuthenticationError> {
         self.users.iter().for_each(|u| {
-            println!("{:<40} {:<10}", u.username, u.role.to_string());
+            let two_factor = match &u.totp {
+                Some(totp) if totp.confirmed => "2fa",
+                Some(_) => "2fa-pending",
+                None if self.totp_required_for_user(u) => "2fa-required",
+                None => "",
+            };
+            let role = u.custom_role.clone().unwrap_or_else(|| u.role.to_string());
+            let scope = if u.scope.is_empty() {
+                String::new()
+            } else {
+                format!("scope: {}", u.scope.join(", "))
+            };
+            println!("{:<40} {:<16} {two_factor:<12} {scope}", u.username, role);
         });
         Ok(())
     }
 
+    /// Dump the custom roles to the console.
+    pub fn print_roles(&self) {
+        for role in &self.roles {
+            let permissions: Vec<String> = role.permissions.iter().map(|p| p.to_string()).collect();
+            println!("{:<24} {}", role.name, permissions.join(", "));
+        }
+    }
+
     /// Return a list of user objects
     pub fn get_users(&self) -> Vec<WebUser> {
         self.users.clone()
@@ -443,6 +1026,202 @@ impl WebUsers {
     pub fn do_we_allow_anonymous(&self) -> bool {
         self.allow_unauthenticated_to_view
     }
+
+    fn hash_api_secret(secret: &str) -> String {
+        let mut sha256 = Sha256::new();
+        sha256.update(secret.as_bytes());
+        format!("{:x}", sha256.finalize())
+    }
+
+    fn issue_api_token(
+        &mut self,
+        name: &str,
+        scopes: &[ApiTokenScope],
+        expires_unix: Option<u64>,
+        now_unix: u64,
+    ) -> (ApiToken, String) {
+        let id = Uuid::new_v4().simple().to_string()[..12].to_string();
+        let secret = format!("{}{}", Uuid::new_v4().simple(), Uuid::new_v4().simple());
+        let mut unique_scopes = Vec::new();
+        for scope in scopes {
+            if !unique_scopes.contains(scope) {
+                unique_scopes.push(*scope);
+            }
+        }
+        let token = ApiToken {
+            id: id.clone(),
+            name: name.to_string(),
+            secret_hash: Self::hash_api_secret(&secret),
+            scopes: unique_scopes,
+            created_unix: now_unix,
+            expires_unix,
+        };
+        self.api_tokens.push(token.clone());
+        (token, format!("{API_TOKEN_PREFIX}{id}_{secret}"))
+    }
+
+    /// Creates an API token and saves `lqusers.toml`.
+    ///
+    /// Returns the stored token and the full token string. The token string is only available
+    /// here; it cannot be recovered later.
+    pub fn create_api_token(
+        &mut self,
+        name: &str,
+        scopes: &[ApiTokenScope],
+        expires_unix: Option<u64>,
+    ) -> Result<(ApiToken, String), AuthenticationError> {
+        let now = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or(0);
+        let issued = self.issue_api_token(name, scopes, expires_unix, now);
+        self.save_to_disk()?;
+        Ok(issued)
+    }
+
+    /// Revokes an API token by id and saves `lqusers.toml`.
+    pub fn revoke_api_token(&mut self, id: &str) -> Result<(), AuthenticationError> {
+        let old_len = self.api_tokens.len();
+        self.api_tokens.retain(|token| token.id != id);
+        if old_len == self.api_tokens.len() {
+            return Err(AuthenticationError::ApiTokenNotFound);
+        }
+        self.save_to_disk()?;
+        Ok(())
+    }
+
+    /// Return the stored API tokens (hashes only).
+    pub fn get_api_tokens(&self) -> Vec<ApiToken> {
+        self.api_tokens.clone()
+    }
+
+    /// Looks up the token matching a presented `lqos_<id>_<secret>` string. Unknown, malformed
+    /// and expired tokens return `None`.
+    pub fn verify_api_token(&self, presented: &str, now_unix: u64) -> Option<ApiToken> {
+        verify_api_token_in(&self.api_tokens, presented, now_unix)
+    }
+}
+
+const API_TOKEN_PREFIX: &str = "lqos_";
+
+fn unix_now() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+fn normalize_recovery_code(code: &str) -> String {
+    code.chars()
+        .filter(|c| c.is_ascii_alphanumeric())
+        .map(|c| c.to_ascii_lowercase())
+        .collect()
+}
+
+fn hash_recovery_code(code: &str) -> String {
+    let mut sha256 = Sha256::new();
+    sha256.update(normalize_recovery_code(code).as_bytes());
+    format!("{:x}", sha256.finalize())
+}
+
+fn totp_key(enrollment: &TotpEnrollment) -> Option<Vec<u8>> {
+    totp::base32_decode(&enrollment.secret).filter(|key| !key.is_empty())
+}
+
+/// Checks a TOTP or recovery code, recording the used step or consuming the recovery code.
+fn verify_second_factor(enrollment: &mut TotpEnrollment, code: &str, now_unix: u64) -> bool {
+    if let Some(key) = totp_key(enrollment)
+        && let Some(step) = totp::matching_step(&key, code, now_unix)
+        && step > enrollment.last_used_step
+    {
+        enrollment.last_used_step = step;
+        return true;
+    }
+    let hash = hash_recovery_code(code);
+    let before = enrollment.recovery_code_hashes.len();
+    enrollment.recovery_code_hashes.retain(|h| *h != hash);
+    enrollment.recovery_code_hashes.len() != before
+}
+
+/// Checks a second-factor code like [`verify_second_factor`], counting wrong codes and
+/// refusing to check any more for a doubling delay once [`TOTP_FREE_FAILURES`] have been
+/// used up, so the six digits can't be guessed by trying them all.
+fn attempt_second_factor(
+    enrollment: &mut TotpEnrollment,
+    code: &str,
+    now_unix: u64,
+) -> Result<(), AuthenticationError> {
+    if enrollment.locked_until > now_unix {
+        return Err(AuthenticationError::TotpLockedOut {
+            retry_after_secs: enrollment.locked_until - now_unix,
+        });
+    }
+    if verify_second_factor(enrollment, code, now_unix) {
+        enrollment.failed_attempts = 0;
+        enrollment.locked_until = 0;
+        return Ok(());
+    }
+    enrollment.failed_attempts = enrollment.failed_attempts.saturating_add(1);
+    let excess = enrollment
+        .failed_attempts
+        .saturating_sub(TOTP_FREE_FAILURES);
+    if excess > 0 {
+        let delay = TOTP_BASE_LOCKOUT_SECS
+            .saturating_mul(1 << (excess - 1).min(16))
+            .min(TOTP_MAX_LOCKOUT_SECS);
+        enrollment.locked_until = now_unix + delay;
+    }
+    Err(AuthenticationError::InvalidTotp)
+}
+
+/// Confirms an enrollment with a TOTP code and issues fresh recovery codes.
+fn confirm_enrollment(
+    enrollment: &mut TotpEnrollment,
+    code: &str,
+    now_unix: u64,
+) -> Result<Vec<String>, AuthenticationError> {
+    let key = totp_key(enrollment).ok_or(AuthenticationError::TotpNotEnrolled)?;
+    let step = totp::matching_step(&key, code, now_unix).ok_or(AuthenticationError::InvalidTotp)?;
+    let recovery_codes: Vec<String> = (0..RECOVERY_CODE_COUNT)
+        .map(|_| {
+            let mut bytes = [0u8; 5];
+            OsRng.fill_bytes(&mut bytes);
+            let hex: String = bytes.iter().map(|b| format!("{b:02x}")).collect();
+            format!("{}-{}", &hex[..5], &hex[5..])
+        })
+        .collect();
+    enrollment.confirmed = true;
+    enrollment.last_used_step = step;
+    enrollment.recovery_code_hashes = recovery_codes
+        .iter()
+        .map(|c| hash_recovery_code(c))
+        .collect();
+    Ok(recovery_codes)
+}
+
+/// Looks up a presented token in a token list. See [`WebUsers::verify_api_token`].
+pub fn verify_api_token_in(
+    tokens: &[ApiToken],
+    presented: &str,
+    now_unix: u64,
+) -> Option<ApiToken> {
+    let (id, secret) = presented
+        .trim()
+        .strip_prefix(API_TOKEN_PREFIX)?
+        .split_once('_')?;
+    let token = tokens.iter().find(|token| token.id == id)?;
+    let presented_hash = WebUsers::hash_api_secret(secret);
+    // Compare every byte so the time taken does not depend on where the hashes differ.
+    let matches = presented_hash.len() == token.secret_hash.len()
+        && presented_hash
+            .bytes()
+            .zip(token.secret_hash.bytes())
+            .fold(0u8, |acc, (a, b)| acc | (a ^ b))
+            == 0;
+    if !matches || token.is_expired(now_unix) {
+        return None;
+    }
+    Some(token.clone())
 }
 
 /// Errors that can occur while managing web-UI authentication.
@@ -474,4 +1253,230 @@ pub enum AuthenticationError {
     /// Username/password did not match.
     #[error("Invalid Login")]
     InvalidLogin,
+    /// Attempted to revoke an API token that does not exist.
+    #[error("API token not found")]
+    ApiTokenNotFound,
+    /// The user has two-factor authentication enabled and no code was supplied.
+    #[error("Two-factor code required")]
+    TotpRequired,
+    /// The two-factor or recovery code was wrong or already used.
+    #[error("Invalid two-factor code")]
+    InvalidTotp,
+    /// The user's role requires two-factor authentication but the user has not enrolled.
+    #[error("Two-factor enrollment required")]
+    TotpEnrollmentRequired,
+    /// Too many wrong two-factor codes; no code is checked until the delay has passed.
+    #[error("Too many wrong two-factor codes; try again in {retry_after_secs} seconds")]
+    TotpLockedOut {
+        /// Seconds until the next code is checked.
+        retry_after_secs: u64,
+    },
+    /// The user already has a confirmed second factor; reset it first.
+    #[error("Two-factor authentication is already enrolled")]
+    TotpAlreadyEnrolled,
+    /// No (pending) two-factor enrollment exists for the user.
+    #[error("Two-factor authentication is not enrolled")]
+    TotpNotEnrolled,
+    /// The referenced custom role is not defined.
+    #[error("Role not found")]
+    RoleNotFound,
+    /// A custom role cannot be removed while users are assigned to it.
+    #[error("Role is assigned to one or more users")]
+    RoleInUse,
+    /// Custom role names may not be empty or shadow the built-in roles.
+    #[error("Invalid role name")]
+    InvalidRoleName,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn api_tokens_verify_by_secret_and_expiry() {
+        let mut users = WebUsers::default();
+        let (token, presented) =
+            users.issue_api_token("billing", &[ApiTokenScope::Read], Some(2_000), 1_000);
+        assert!(presented.starts_with("lqos_"));
+
+        let verified = users
+            .verify_api_token(&presented, 1_500)
+            .expect("valid token");
+        assert_eq!(verified, token);
+        assert!(verified.has_scope(ApiTokenScope::Read));
+        assert!(!verified.has_scope(ApiTokenScope::Reload));
+
+        assert!(users.verify_api_token(&presented, 2_000).is_none());
+        let last = if presented.ends_with('0') { '1' } else { '0' };
+        let tampered = format!("{}{last}", &presented[..presented.len() - 1]);
+        assert!(users.verify_api_token(&tampered, 1_500).is_none());
+        assert!(users.verify_api_token("lqos_nope_secret", 1_500).is_none());
+        assert!(users.verify_api_token("not-a-token", 1_500).is_none());
+    }
+
+    #[test]
+    fn totp_codes_are_single_use_and_recovery_codes_are_consumed() {
+        // RFC 6238 test key; code 287082 is valid at t = 59.
+        let mut enrollment = TotpEnrollment {
+            secret: totp::base32_encode(b"12345678901234567890"),
+            confirmed: false,
+            recovery_code_hashes: Vec::new(),
+            last_used_step: 0,
+            failed_attempts: 0,
+            locked_until: 0,
+        };
+        assert!(matches!(
+            confirm_enrollment(&mut enrollment, "000000", 59),
+            Err(AuthenticationError::InvalidTotp)
+        ));
+        let recovery = confirm_enrollment(&mut enrollment, "287082", 59).expect("confirm");
+        assert!(enrollment.confirmed);
+        assert_eq!(recovery.len(), RECOVERY_CODE_COUNT);
+
+        // The confirming code cannot be replayed.
+        assert!(!verify_second_factor(&mut enrollment, "287082", 60));
+
+        let code = recovery[0].to_uppercase();
+        assert!(verify_second_factor(&mut enrollment, &code, 60));
+        assert!(!verify_second_factor(&mut enrollment, &code, 60));
+        assert_eq!(
+            enrollment.recovery_code_hashes.len(),
+            RECOVERY_CODE_COUNT - 1
+        );
+
+        let user = WebUser {
+            username: "noc".to_string(),
+            password_hash: "hash".to_string(),
+            role: UserRole::Admin,
+            totp: Some(enrollment),
+            custom_role: None,
+            scope: Vec::new(),
+        };
+        let redacted = user.redacted();
+        assert!(redacted.has_totp());
+        assert!(redacted.password_hash.is_empty());
+        assert!(redacted.totp.expect("totp").secret.is_empty());
+    }
+
+    #[test]
+    fn repeated_wrong_totp_codes_lock_the_second_factor() {
+        // RFC 6238 test key; code 287082 is valid at t = 59.
+        let mut enrollment = TotpEnrollment {
+            secret: totp::base32_encode(b"12345678901234567890"),
+            confirmed: true,
+            recovery_code_hashes: Vec::new(),
+            last_used_step: 0,
+            failed_attempts: 0,
+            locked_until: 0,
+        };
+        for _ in 0..TOTP_FREE_FAILURES {
+            assert!(matches!(
+                attempt_second_factor(&mut enrollment, "wrong", 40),
+                Err(AuthenticationError::InvalidTotp)
+            ));
+        }
+        assert_eq!(enrollment.locked_until, 0, "the first failures are free");
+
+        assert!(matches!(
+            attempt_second_factor(&mut enrollment, "wrong", 40),
+            Err(AuthenticationError::InvalidTotp)
+        ));
+        assert_eq!(enrollment.locked_until, 40 + TOTP_BASE_LOCKOUT_SECS);
+        // Even the right code is refused while locked.
+        assert!(matches!(
+            attempt_second_factor(&mut enrollment, "287082", 59),
+            Err(AuthenticationError::TotpLockedOut {
+                retry_after_secs: 11
+            })
+        ));
+
+        assert!(matches!(
+            attempt_second_factor(&mut enrollment, "wrong", 70),
+            Err(AuthenticationError::InvalidTotp)
+        ));
+        assert_eq!(
+            enrollment.locked_until,
+            70 + 2 * TOTP_BASE_LOCKOUT_SECS,
+            "the delay doubles"
+        );
+
+        enrollment.locked_until = 0;
+        assert!(attempt_second_factor(&mut enrollment, "287082", 59).is_ok());
+        assert_eq!(enrollment.failed_attempts, 0);
+    }
+
+    #[test]
+    fn totp_can_be_required_by_custom_role_or_permission() {
+        let mut users = WebUsers::default();
+        users.roles.push(RoleDefinition {
+            name: "field-tech".to_string(),
+            permissions: vec![Permission::View, Permission::AdjustSpeeds],
+        });
+        users.users.push(WebUser {
+            username: "tech".to_string(),
+            password_hash: "hash".to_string(),
+            role: UserRole::ReadOnly,
+            totp: None,
+            custom_role: Some("field-tech".to_string()),
+            scope: Vec::new(),
+        });
+        let tech = users.users[0].clone();
+        assert!(!users.totp_required_for_user(&tech));
+
+        users
+            .totp_required_custom_roles
+            .push("field-tech".to_string());
+        assert!(users.totp_required_for_user(&tech));
+
+        users.totp_required_custom_roles.clear();
+        users
+            .totp_required_permissions
+            .push(Permission::ManageUsers);
+        assert!(!users.totp_required_for_user(&tech));
+        users
+            .totp_required_permissions
+            .push(Permission::AdjustSpeeds);
+        assert!(users.totp_required_for_user(&tech));
+    }
+
+    #[test]
+    fn custom_roles_replace_builtin_permissions() {
+        let mut users = WebUsers::default();
+        users.users.push(WebUser {
+            username: "tech".to_string(),
+            password_hash: "hash".to_string(),
+            role: UserRole::ReadOnly,
+            totp: None,
+            custom_role: None,
+            scope: Vec::new(),
+        });
+        let access = users.access_for_user("tech").expect("user");
+        assert!(access.allows(Permission::View));
+        assert!(!access.allows(Permission::AdjustSpeeds));
+        assert!(UserAccess::for_role(UserRole::Admin).is_full_admin());
+
+        users.roles.push(RoleDefinition {
+            name: "field-tech".to_string(),
+            permissions: vec![Permission::View, Permission::AdjustSpeeds],
+        });
+        users.users[0].custom_role = Some("field-tech".to_string());
+        users.users[0].scope = vec!["Tower 7".to_string()];
+        let access = users.access_for_user("tech").expect("user");
+        assert!(access.allows(Permission::AdjustSpeeds));
+        assert!(!access.allows(Permission::PacketCapture));
+        assert!(access.is_scoped());
+        assert!(!access.is_full_admin());
+
+        // A dangling role grants nothing rather than falling back to the built-in role.
+        users.roles.clear();
+        let access = users.access_for_user("tech").expect("user");
+        assert!(access.permissions.is_empty());
+        assert!(users.access_for_user("nobody").is_none());
+
+        assert_eq!(
+            "manage_overrides".parse::<Permission>(),
+            Ok(Permission::ManageOverrides)
+        );
+        assert!("root".parse::<Permission>().is_err());
+    }
 }
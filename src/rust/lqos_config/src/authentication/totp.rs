@@ -0,0 +1,140 @@
+//! RFC 6238 time-based one-time passwords (HMAC-SHA1, 30 second steps, 6 digits), which is
+//! the variant every common authenticator app supports.
+
+use hmac::{Hmac, Mac};
+use sha1::Sha1;
+
+/// Length of a time step in seconds.
+pub(crate) const STEP_SECS: u64 = 30;
+const DIGITS: u32 = 6;
+/// Steps either side of "now" that are still accepted, to tolerate clock drift.
+const ALLOWED_DRIFT_STEPS: u64 = 1;
+const BASE32_ALPHABET: &[u8; 32] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZ234567";
+
+/// RFC 4648 base32 without padding, as used in `otpauth://` URIs.
+pub(crate) fn base32_encode(bytes: &[u8]) -> String {
+    let mut out = String::with_capacity(bytes.len().div_ceil(5) * 8);
+    let mut buffer: u32 = 0;
+    let mut bits = 0;
+    for byte in bytes {
+        buffer = (buffer << 8) | u32::from(*byte);
+        bits += 8;
+        while bits >= 5 {
+            bits -= 5;
+            out.push(BASE32_ALPHABET[((buffer >> bits) & 0x1f) as usize] as char);
+        }
+    }
+    if bits > 0 {
+        out.push(BASE32_ALPHABET[((buffer << (5 - bits)) & 0x1f) as usize] as char);
+    }
+    out
+}
+
+/// Decodes base32, ignoring case, spaces and padding. Returns `None` for other characters.
+pub(crate) fn base32_decode(text: &str) -> Option<Vec<u8>> {
+    let mut out = Vec::with_capacity(text.len() * 5 / 8);
+    let mut buffer: u32 = 0;
+    let mut bits = 0;
+    for c in text.bytes() {
+        if c == b'=' || c == b' ' {
+            continue;
+        }
+        let value = BASE32_ALPHABET
+            .iter()
+            .position(|a| *a == c.to_ascii_uppercase())? as u32;
+        buffer = (buffer << 5) | value;
+        bits += 5;
+        if bits >= 8 {
+            bits -= 8;
+            out.push(((buffer >> bits) & 0xff) as u8);
+        }
+    }
+    Some(out)
+}
+
+/// HOTP value (RFC 4226) for `counter`.
+fn hotp(key: &[u8], counter: u64) -> u32 {
+    let Ok(mut mac) = Hmac::<Sha1>::new_from_slice(key) else {
+        // HMAC accepts keys of any length, so this cannot happen.
+        return u32::MAX;
+    };
+    mac.update(&counter.to_be_bytes());
+    let digest = mac.finalize().into_bytes();
+    let offset = (digest[digest.len() - 1] & 0x0f) as usize;
+    let code = u32::from_be_bytes([
+        digest[offset] & 0x7f,
+        digest[offset + 1],
+        digest[offset + 2],
+        digest[offset + 3],
+    ]);
+    code % 10u32.pow(DIGITS)
+}
+
+/// Finds the time step within the allowed drift whose code matches `code`.
+pub(crate) fn matching_step(key: &[u8], code: &str, now_unix: u64) -> Option<u64> {
+    let code = code.trim();
+    if code.len() != DIGITS as usize || !code.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    let wanted: u32 = code.parse().ok()?;
+    let current = now_unix / STEP_SECS;
+    (current.saturating_sub(ALLOWED_DRIFT_STEPS)..=current + ALLOWED_DRIFT_STEPS)
+        .find(|step| hotp(key, *step) == wanted)
+}
+
+/// Percent-encodes a label or parameter for an `otpauth://` URI.
+fn uri_encode(value: &str) -> String {
+    let mut out = String::with_capacity(value.len());
+    for byte in value.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => {
+                out.push(byte as char)
+            }
+            _ => out.push_str(&format!("%{byte:02X}")),
+        }
+    }
+    out
+}
+
+/// `otpauth://` provisioning URI, suitable for rendering as a QR code.
+pub(crate) fn provisioning_uri(issuer: &str, username: &str, secret_base32: &str) -> String {
+    format!(
+        "otpauth://totp/{}:{}?secret={secret_base32}&issuer={}&algorithm=SHA1&digits={DIGITS}&period={STEP_SECS}",
+        uri_encode(issuer),
+        uri_encode(username),
+        uri_encode(issuer),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn matches_rfc6238_vectors() {
+        // RFC 6238 appendix B, SHA1, truncated to 6 digits.
+        let key = b"12345678901234567890";
+        assert_eq!(matching_step(key, "287082", 59), Some(1));
+        assert_eq!(
+            matching_step(key, "081804", 1_111_111_109),
+            Some(37_037_036)
+        );
+        assert_eq!(
+            matching_step(key, "050471", 1_111_111_111),
+            Some(37_037_037)
+        );
+        // One step of drift is tolerated, two are not.
+        assert_eq!(matching_step(key, "287082", 59 + STEP_SECS), Some(1));
+        assert_eq!(matching_step(key, "287082", 59 + 2 * STEP_SECS), None);
+        assert_eq!(matching_step(key, "28708", 59), None);
+    }
+
+    #[test]
+    fn base32_round_trips() {
+        let key = b"12345678901234567890";
+        let encoded = base32_encode(key);
+        assert_eq!(encoded, "GEZDGNBVGY3TQOJQGEZDGNBVGY3TQOJQ");
+        assert_eq!(base32_decode(&encoded.to_lowercase()), Some(key.to_vec()));
+        assert_eq!(base32_decode("not base32!"), None);
+    }
+}
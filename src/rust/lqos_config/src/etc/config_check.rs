@@ -0,0 +1,362 @@
+//! Section-by-section checks of `/etc/lqos.conf`. Unlike [`Config::validate`], which stops at
+//! the first problem, this collects everything wrong with a file so an operator can fix it in
+//! one pass, and warns about settings that are valid but unlikely to do what was intended.
+
+use super::Config;
+use serde::Serialize;
+
+/// How serious a configuration problem is.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ConfigIssueSeverity {
+    /// LibreQoS will refuse to load the file, or the feature cannot work.
+    Error,
+    /// The file loads, but the setting is probably a mistake.
+    Warning,
+}
+
+/// A problem found in one section of the configuration.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ConfigIssue {
+    /// The top-level TOML section, e.g. `stormguard`
+    pub section: String,
+    /// How serious the problem is
+    pub severity: ConfigIssueSeverity,
+    /// What is wrong
+    pub message: String,
+}
+
+struct Issues(Vec<ConfigIssue>);
+
+impl Issues {
+    fn push(&mut self, section: &str, severity: ConfigIssueSeverity, message: impl Into<String>) {
+        self.0.push(ConfigIssue {
+            section: section.to_string(),
+            severity,
+            message: message.into(),
+        });
+    }
+
+    fn error(&mut self, section: &str, message: impl Into<String>) {
+        self.push(section, ConfigIssueSeverity::Error, message);
+    }
+
+    fn warning(&mut self, section: &str, message: impl Into<String>) {
+        self.push(section, ConfigIssueSeverity::Warning, message);
+    }
+
+    fn check(&mut self, section: &str, result: Result<(), String>) {
+        if let Err(message) = result {
+            self.error(section, message);
+        }
+    }
+
+    /// Flags empty credentials of an enabled integration
+    fn require(&mut self, section: &str, enabled: bool, fields: &[(&str, &str)]) {
+        if !enabled {
+            return;
+        }
+        for (name, value) in fields {
+            if value.trim().is_empty() {
+                self.error(
+                    section,
+                    format!("{section}.{name} must be set when the integration is enabled"),
+                );
+            }
+        }
+    }
+}
+
+/// Parses a configuration file and checks every section of it.
+///
+/// `interface_exists` reports whether a network interface is present; pass `None` to skip
+/// interface checks, e.g. when checking a file copied from another shaper. Fails only if
+/// the file cannot be parsed at all.
+pub fn check_config(
+    raw: &str,
+    interface_exists: Option<&dyn Fn(&str) -> bool>,
+) -> Result<Vec<ConfigIssue>, String> {
+    let config = Config::parse_unvalidated(raw)?;
+    Ok(check_sections(&config, interface_exists))
+}
+
+fn check_sections(
+    config: &Config,
+    interface_exists: Option<&dyn Fn(&str) -> bool>,
+) -> Vec<ConfigIssue> {
+    let mut issues = Issues(Vec::new());
+
+    issues.check("general", config.validate_general());
+    check_interfaces(config, interface_exists, &mut issues);
+    check_integrations(config, &mut issues);
+
+    if let Some(stormguard) = &config.stormguard {
+        issues.check("stormguard", stormguard.validate());
+        check_stormguard_coherence(stormguard, &mut issues);
+    }
+    issues.check("treeguard", config.treeguard.validate());
+    if config.treeguard.enabled {
+        check_treeguard_coherence(&config.treeguard, &mut issues);
+    }
+
+    issues.check("change_control", config.change_control.validate());
+    issues.check("oidc", config.oidc.validate());
+    issues.check("notifications", config.notifications.validate());
+    issues.check("alerting", config.alerting.validate());
+    issues.check("federation", config.federation.validate());
+    issues.check("high_availability", config.high_availability.validate());
+    issues.check("remote_bus", config.remote_bus.validate());
+
+    issues.0
+}
+
+fn check_interfaces(
+    config: &Config,
+    interface_exists: Option<&dyn Fn(&str) -> bool>,
+    issues: &mut Issues,
+) {
+    let mut interfaces = Vec::new();
+    match (&config.bridge, &config.single_interface) {
+        (Some(bridge), None) => {
+            if bridge.to_internet == bridge.to_network {
+                issues.error(
+                    "bridge",
+                    "bridge.to_internet and bridge.to_network must be different interfaces",
+                );
+            }
+            interfaces.push(("bridge", "to_internet", &bridge.to_internet));
+            interfaces.push(("bridge", "to_network", &bridge.to_network));
+        }
+        (None, Some(single)) => {
+            if single.internet_vlan == single.network_vlan {
+                issues.error(
+                    "single_interface",
+                    "single_interface.internet_vlan and single_interface.network_vlan must differ",
+                );
+            }
+            interfaces.push(("single_interface", "interface", &single.interface));
+        }
+        (None, None) => issues.error(
+            "general",
+            "Either a [bridge] or a [single_interface] section is required",
+        ),
+        // Reported by `validate_general`
+        (Some(_), Some(_)) => {}
+    }
+
+    for (section, key, name) in interfaces {
+        if name.trim().is_empty() {
+            issues.error(section, format!("{section}.{key} must be set"));
+        } else if let Some(exists) = interface_exists
+            && !exists(name)
+        {
+            issues.error(
+                section,
+                format!("{section}.{key}: interface {name} does not exist on this system"),
+            );
+        }
+    }
+}
+
+fn check_integrations(config: &Config, issues: &mut Issues) {
+    let splynx = &config.splynx_integration;
+    issues.require(
+        "splynx_integration",
+        splynx.enable_splynx,
+        &[
+            ("api_key", splynx.api_key.as_str()),
+            ("api_secret", splynx.api_secret.as_str()),
+            ("url", splynx.url.as_str()),
+        ],
+    );
+
+    let uisp = &config.uisp_integration;
+    issues.require(
+        "uisp_integration",
+        uisp.enable_uisp,
+        &[("token", uisp.token.as_str()), ("url", uisp.url.as_str())],
+    );
+
+    let powercode = &config.powercode_integration;
+    issues.require(
+        "powercode_integration",
+        powercode.enable_powercode,
+        &[
+            ("powercode_api_key", powercode.powercode_api_key.as_str()),
+            ("powercode_api_url", powercode.powercode_api_url.as_str()),
+        ],
+    );
+
+    let sonar = &config.sonar_integration;
+    issues.require(
+        "sonar_integration",
+        sonar.enable_sonar,
+        &[
+            ("sonar_api_url", sonar.sonar_api_url.as_str()),
+            ("sonar_api_key", sonar.sonar_api_key.as_str()),
+        ],
+    );
+
+    if let Some(netzur) = &config.netzur_integration {
+        issues.require(
+            "netzur_integration",
+            netzur.enable_netzur,
+            &[
+                ("api_key", netzur.api_key.as_str()),
+                ("api_url", netzur.api_url.as_str()),
+            ],
+        );
+    }
+
+    if let Some(visp) = &config.visp_integration {
+        issues.require(
+            "visp_integration",
+            visp.enable_visp,
+            &[
+                ("client_id", visp.client_id.as_str()),
+                ("client_secret", visp.client_secret.as_str()),
+                ("username", visp.username.as_str()),
+                ("password", visp.password.as_str()),
+                ("token_url", visp.token_url.as_str()),
+                ("graphql_url", visp.graphql_url.as_str()),
+            ],
+        );
+    }
+
+    if let Some(wispgate) = &config.wispgate_integration {
+        issues.require(
+            "wispgate_integration",
+            wispgate.enable_wispgate,
+            &[
+                ("wispgate_api_token", wispgate.wispgate_api_token.as_str()),
+                ("wispgate_api_url", wispgate.wispgate_api_url.as_str()),
+            ],
+        );
+    }
+
+    if let Some(influxdb) = &config.influxdb {
+        issues.require(
+            "influxdb",
+            influxdb.enable_influxdb,
+            &[
+                ("url", influxdb.url.as_str()),
+                ("bucket", influxdb.bucket.as_str()),
+                ("org", influxdb.org.as_str()),
+                ("token", influxdb.token.as_str()),
+            ],
+        );
+    }
+}
+
+/// Settings that pass [`StormguardConfig::validate`](super::StormguardConfig::validate) but
+/// contradict each other.
+fn check_stormguard_coherence(stormguard: &super::StormguardConfig, issues: &mut Issues) {
+    if stormguard.increase_fast_multiplier < stormguard.increase_multiplier {
+        issues.warning(
+            "stormguard",
+            format!(
+                "stormguard.increase_fast_multiplier ({}) is below stormguard.increase_multiplier ({}), so fast increases are slower than normal ones",
+                stormguard.increase_fast_multiplier, stormguard.increase_multiplier
+            ),
+        );
+    }
+    if stormguard.decrease_fast_multiplier > stormguard.decrease_multiplier {
+        issues.warning(
+            "stormguard",
+            format!(
+                "stormguard.decrease_fast_multiplier ({}) is above stormguard.decrease_multiplier ({}), so fast decreases are gentler than normal ones",
+                stormguard.decrease_fast_multiplier, stormguard.decrease_multiplier
+            ),
+        );
+    }
+    for site in &stormguard.targets {
+        if stormguard.exclude_sites.contains(site) {
+            issues.warning(
+                "stormguard",
+                format!("Site {site} is in both stormguard.targets and stormguard.exclude_sites"),
+            );
+        }
+    }
+}
+
+/// Settings that pass [`TreeguardConfig::validate`](super::TreeguardConfig::validate) but
+/// leave an enabled feature with nothing to do, or make it flap.
+fn check_treeguard_coherence(treeguard: &super::TreeguardConfig, issues: &mut Issues) {
+    let links = &treeguard.links;
+    if links.enabled {
+        if links.unvirtualize_util_pct <= links.idle_util_pct {
+            issues.warning(
+                "treeguard",
+                format!(
+                    "treeguard.links.unvirtualize_util_pct ({}) should be above treeguard.links.idle_util_pct ({}), or links may flap between virtualized and unvirtualized",
+                    links.unvirtualize_util_pct, links.idle_util_pct
+                ),
+            );
+        }
+        if !links.all_nodes && links.nodes.is_empty() {
+            issues.warning(
+                "treeguard",
+                "treeguard.links is enabled with all_nodes = false and no nodes listed",
+            );
+        }
+    }
+
+    let circuits = &treeguard.circuits;
+    if circuits.enabled && !circuits.all_circuits && circuits.circuits.is_empty() {
+        issues.warning(
+            "treeguard",
+            "treeguard.circuits is enabled with all_circuits = false and no circuits listed",
+        );
+    }
+
+    if treeguard.adaptive_sqm.enabled && treeguard.adaptive_sqm.profiles.is_empty() {
+        issues.warning(
+            "treeguard",
+            "treeguard.adaptive_sqm is enabled but has no profiles to try",
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn messages(issues: &[ConfigIssue]) -> Vec<(&str, ConfigIssueSeverity)> {
+        issues
+            .iter()
+            .map(|issue| (issue.section.as_str(), issue.severity))
+            .collect()
+    }
+
+    #[test]
+    fn example_config_is_clean() {
+        let issues =
+            check_config(include_str!("v15/example.toml"), None).expect("Cannot read example");
+        assert!(issues.is_empty(), "{issues:?}");
+    }
+
+    #[test]
+    fn collects_problems_from_every_section() {
+        let mut config = Config::load_from_string(include_str!("v15/example.toml"))
+            .expect("Cannot read example");
+        config.uisp_integration.enable_uisp = true;
+        config.uisp_integration.url = "https://uisp.example".to_string();
+        config.queues.default_sqm.clear();
+        let mut stormguard = crate::StormguardConfig::default();
+        stormguard.increase_fast_multiplier = 1.05;
+        config.stormguard = Some(stormguard);
+
+        let no_interfaces: &dyn Fn(&str) -> bool = &|_| false;
+        let issues = check_sections(&config, Some(no_interfaces));
+        assert_eq!(
+            messages(&issues),
+            vec![
+                ("general", ConfigIssueSeverity::Error),
+                ("bridge", ConfigIssueSeverity::Error),
+                ("bridge", ConfigIssueSeverity::Error),
+                ("uisp_integration", ConfigIssueSeverity::Error),
+                ("stormguard", ConfigIssueSeverity::Warning),
+            ]
+        );
+    }
+}
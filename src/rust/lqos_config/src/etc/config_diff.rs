@@ -0,0 +1,246 @@
+//! Key-by-key comparison of two versions of `/etc/lqos.conf`, so migrations can report
+//! what they changed rather than just rewriting the file.
+
+use serde::Serialize;
+use toml_edit::{ArrayOfTables, DocumentMut, Item, Value};
+
+/// One setting that differs between two configuration files.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "change", rename_all = "snake_case")]
+pub enum ConfigChange {
+    /// The setting only exists in the newer file.
+    Added {
+        /// Dotted path, e.g. `bridge.to_internet`
+        path: String,
+        /// TOML rendering of the new value
+        value: String,
+    },
+    /// The setting only exists in the older file.
+    Removed {
+        /// Dotted path, e.g. `bridge.to_internet`
+        path: String,
+        /// TOML rendering of the old value
+        value: String,
+    },
+    /// The setting exists in both files with different values.
+    Changed {
+        /// Dotted path, e.g. `bridge.to_internet`
+        path: String,
+        /// TOML rendering of the old value
+        from: String,
+        /// TOML rendering of the new value
+        to: String,
+    },
+}
+
+impl ConfigChange {
+    /// The dotted path of the setting.
+    pub fn path(&self) -> &str {
+        match self {
+            Self::Added { path, .. } | Self::Removed { path, .. } | Self::Changed { path, .. } => {
+                path
+            }
+        }
+    }
+}
+
+impl std::fmt::Display for ConfigChange {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Added { path, value } => write!(f, "+ {path} = {value}"),
+            Self::Removed { path, value } => write!(f, "- {path} = {value}"),
+            Self::Changed { path, from, to } => write!(f, "~ {path}: {from} -> {to}"),
+        }
+    }
+}
+
+/// Lists every leaf setting that was added, removed or changed between `before` and `after`.
+/// Formatting and comments are ignored; tables are compared key by key, and arrays of tables
+/// entry by entry.
+pub fn diff_config_documents(before: &DocumentMut, after: &DocumentMut) -> Vec<ConfigChange> {
+    let mut changes = Vec::new();
+    diff_items(
+        "",
+        Some(before.as_item()),
+        Some(after.as_item()),
+        &mut changes,
+    );
+    changes
+}
+
+fn diff_items(
+    path: &str,
+    before: Option<&Item>,
+    after: Option<&Item>,
+    out: &mut Vec<ConfigChange>,
+) {
+    let before_table = before.and_then(Item::as_table_like);
+    let after_table = after.and_then(Item::as_table_like);
+    let both_absent_or_tables =
+        (before.is_none() || before_table.is_some()) && (after.is_none() || after_table.is_some());
+    if both_absent_or_tables {
+        let mut keys: Vec<&str> = Vec::new();
+        for table in [before_table, after_table].into_iter().flatten() {
+            for (key, _) in table.iter() {
+                if !keys.contains(&key) {
+                    keys.push(key);
+                }
+            }
+        }
+        for key in keys {
+            diff_items(
+                &join(path, key),
+                before_table.and_then(|t| t.get(key)),
+                after_table.and_then(|t| t.get(key)),
+                out,
+            );
+        }
+        return;
+    }
+
+    let before_tables = before.and_then(Item::as_array_of_tables);
+    let after_tables = after.and_then(Item::as_array_of_tables);
+    if (before.is_none() || before_tables.is_some()) && (after.is_none() || after_tables.is_some())
+    {
+        let count = |tables: Option<&ArrayOfTables>| tables.map_or(0, ArrayOfTables::len);
+        for index in 0..count(before_tables).max(count(after_tables)) {
+            let entry = |tables: Option<&ArrayOfTables>| {
+                tables
+                    .and_then(|tables| tables.get(index))
+                    .map(|table| Item::Table(table.clone()))
+            };
+            diff_items(
+                &format!("{path}[{index}]"),
+                entry(before_tables).as_ref(),
+                entry(after_tables).as_ref(),
+                out,
+            );
+        }
+        return;
+    }
+
+    let path = path.to_string();
+    match (before.map(render), after.map(render)) {
+        (Some(from), Some(to)) if from != to => out.push(ConfigChange::Changed { path, from, to }),
+        (Some(value), None) => out.push(ConfigChange::Removed { path, value }),
+        (None, Some(value)) => out.push(ConfigChange::Added { path, value }),
+        _ => {}
+    }
+}
+
+fn join(path: &str, key: &str) -> String {
+    if path.is_empty() {
+        key.to_string()
+    } else {
+        format!("{path}.{key}")
+    }
+}
+
+/// Renders an item as a single line, without its comments or original spacing.
+fn render(item: &Item) -> String {
+    match item {
+        Item::Value(value) => render_value(value),
+        Item::ArrayOfTables(tables) => {
+            let rendered: Vec<String> = tables
+                .iter()
+                .map(|table| render_value(&Value::InlineTable(table.clone().into_inline_table())))
+                .collect();
+            format!("[{}]", rendered.join(", "))
+        }
+        Item::Table(table) => render_value(&Value::InlineTable(table.clone().into_inline_table())),
+        Item::None => String::new(),
+    }
+}
+
+fn render_value(value: &Value) -> String {
+    let mut value = value.clone();
+    strip_formatting(&mut value);
+    value.to_string()
+}
+
+fn strip_formatting(value: &mut Value) {
+    value.decor_mut().clear();
+    match value {
+        Value::Array(array) => {
+            array.iter_mut().for_each(strip_formatting);
+            array.fmt();
+        }
+        Value::InlineTable(table) => {
+            table
+                .iter_mut()
+                .for_each(|(_, value)| strip_formatting(value));
+            table.fmt();
+        }
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn doc(raw: &str) -> DocumentMut {
+        raw.parse().expect("test TOML should parse")
+    }
+
+    #[test]
+    fn reports_added_removed_and_changed_leaves() {
+        let before = doc(r#"
+node_name = "old"
+packet_capture_time = 10 # seconds
+
+[bridge]
+use_xdp_bridge = true
+to_internet = "eth0"
+"#);
+        let after = doc(r#"
+version = "1.5"
+node_name = "new"
+packet_capture_time   =   10
+
+[bridge]
+to_internet = "eth0"
+"#);
+        assert_eq!(
+            diff_config_documents(&before, &after),
+            vec![
+                ConfigChange::Changed {
+                    path: "node_name".to_string(),
+                    from: "\"old\"".to_string(),
+                    to: "\"new\"".to_string(),
+                },
+                ConfigChange::Removed {
+                    path: "bridge.use_xdp_bridge".to_string(),
+                    value: "true".to_string(),
+                },
+                ConfigChange::Added {
+                    path: "version".to_string(),
+                    value: "\"1.5\"".to_string(),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn ignores_array_formatting_and_walks_tables_of_tables() {
+        let before = doc(r#"
+[ip_ranges]
+allow_subnets = ["10.0.0.0/8","100.64.0.0/10"]
+
+[[notifications.sinks]]
+type = "webhook"
+url = "https://a.example"
+"#);
+        let after = doc(r#"
+[ip_ranges]
+allow_subnets = [ "10.0.0.0/8", "100.64.0.0/10" ]
+
+[[notifications.sinks]]
+type = "webhook"
+url = "https://b.example"
+"#);
+        let changes = diff_config_documents(&before, &after);
+        assert_eq!(changes.len(), 1);
+        assert_eq!(changes[0].path(), "notifications.sinks[0].url");
+    }
+}
@@ -30,10 +30,16 @@ pub enum MigrationError {
     ImpossibleError,
 }
 
-pub fn migrate_if_needed(config_location: &str) -> Result<(), MigrationError> {
-    debug!("Checking config file version");
-    let raw = std::fs::read_to_string(config_location).map_err(MigrationError::ReadError)?;
+/// What [`migrate_if_needed`] has to do to a configuration file
+enum MigrationPlan {
+    UpToDate,
+    /// A 1.5 file that is only missing its `version` key; holds the fixed file
+    AddVersion(String),
+    /// A 1.4 file, rebuilt from `/etc/lqos.conf` and `ispConfig.py`
+    From14(Config),
+}
 
+fn plan_migration(raw: &str) -> Result<MigrationPlan, MigrationError> {
     let doc = raw
         .parse::<DocumentMut>()
         .map_err(MigrationError::ParseError)?;
@@ -49,18 +55,18 @@ pub fn migrate_if_needed(config_location: &str) -> Result<(), MigrationError> {
             == "1.5"
         {
             debug!("Configuration file is already at version 1.5, no migration needed");
-            return Ok(());
+            Ok(MigrationPlan::UpToDate)
         } else {
             error!(
                 "Configuration file is at version {}, but this version of lqos only supports version 1.5",
                 version.as_str().ok_or(MigrationError::InvalidVersion)?
             );
-            return Err(MigrationError::UnknownVersion(
+            Err(MigrationError::UnknownVersion(
                 version
                     .as_str()
                     .ok_or(MigrationError::InvalidVersion)?
                     .to_string(),
-            ));
+            ))
         }
     } else {
         // If the file otherwise looks like a v1.5 config but is missing the `version` key,
@@ -72,37 +78,84 @@ pub fn migrate_if_needed(config_location: &str) -> Result<(), MigrationError> {
         let mut with_version = doc.clone();
         with_version.insert("version", toml_edit::value("1.5"));
         if Config::load_from_string(&with_version.to_string()).is_ok() {
+            return Ok(MigrationPlan::AddVersion(with_version.to_string()));
+        }
+
+        info!("No version found in configuration file, assuming 1.4x and migration is needed");
+        Ok(MigrationPlan::From14(migrate_14_to_15()?))
+    }
+}
+
+pub fn migrate_if_needed(config_location: &str) -> Result<(), MigrationError> {
+    debug!("Checking config file version");
+    let raw = std::fs::read_to_string(config_location).map_err(MigrationError::ReadError)?;
+
+    match plan_migration(&raw)? {
+        MigrationPlan::UpToDate => {}
+        MigrationPlan::AddVersion(with_version) => {
             let backup_path = format!("{config_location}.backup_noversion");
             std::fs::copy(config_location, &backup_path).map_err(MigrationError::ReadError)?;
-            std::fs::write(config_location, with_version.to_string())
-                .map_err(MigrationError::ReadError)?;
+            std::fs::write(config_location, with_version).map_err(MigrationError::ReadError)?;
             info!(
                 "Added missing `version = \"1.5\"` to {config_location}; backup written to {backup_path}"
             );
-            return Ok(());
         }
+        MigrationPlan::From14(new_config) => {
+            // Back up the old configuration
+            let backup_path = format!("{config_location}.backup14");
+            std::fs::rename(config_location, &backup_path).map_err(MigrationError::ReadError)?;
 
-        info!("No version found in configuration file, assuming 1.4x and migration is needed");
-        let new_config = migrate_14_to_15()?;
-        // Back up the old configuration
-        let backup_path = format!("{config_location}.backup14");
-        std::fs::rename(config_location, &backup_path).map_err(MigrationError::ReadError)?;
-
-        // Rename the old Python configuration
-        let from = Path::new(new_config.lqos_directory.as_str()).join("ispConfig.py");
-        let to = Path::new(new_config.lqos_directory.as_str()).join("ispConfig.py.backup14");
+            // Rename the old Python configuration
+            let from = Path::new(new_config.lqos_directory.as_str()).join("ispConfig.py");
+            let to = Path::new(new_config.lqos_directory.as_str()).join("ispConfig.py.backup14");
 
-        std::fs::rename(from, to).map_err(MigrationError::ReadError)?;
+            std::fs::rename(from, to).map_err(MigrationError::ReadError)?;
 
-        // Save the configuration
-        let raw =
-            toml::to_string_pretty(&new_config).map_err(|_| MigrationError::SerializeError)?;
-        std::fs::write(config_location, raw).map_err(MigrationError::ReadError)?;
+            // Save the configuration
+            let raw =
+                toml::to_string_pretty(&new_config).map_err(|_| MigrationError::SerializeError)?;
+            std::fs::write(config_location, raw).map_err(MigrationError::ReadError)?;
+        }
     }
 
     Ok(())
 }
 
+/// A migration worked out in memory, for review before it is applied
+pub struct MigrationPreview {
+    /// What each migration step does, in order
+    pub steps: Vec<String>,
+    /// The file as it is now
+    pub original: String,
+    /// The file as the migration would leave it
+    pub migrated: String,
+}
+
+/// Works out what [`migrate_if_needed`] would write, without touching any files.
+pub fn preview_migration(config_location: &str) -> Result<MigrationPreview, MigrationError> {
+    let original = std::fs::read_to_string(config_location).map_err(MigrationError::ReadError)?;
+    let (steps, migrated) = match plan_migration(&original)? {
+        MigrationPlan::UpToDate => (Vec::new(), original.clone()),
+        MigrationPlan::AddVersion(with_version) => (
+            vec![format!(
+                "Add the missing `version = \"1.5\"` (backup: {config_location}.backup_noversion)"
+            )],
+            with_version,
+        ),
+        MigrationPlan::From14(new_config) => (
+            vec![format!(
+                "Rebuild the 1.4 configuration and ispConfig.py as version 1.5 (backups: {config_location}.backup14, ispConfig.py.backup14)"
+            )],
+            toml::to_string_pretty(&new_config).map_err(|_| MigrationError::SerializeError)?,
+        ),
+    };
+    Ok(MigrationPreview {
+        steps,
+        original,
+        migrated,
+    })
+}
+
 fn migrate_14_to_15() -> Result<Config, MigrationError> {
     // Load the 1.4 config file
     let old_config = EtcLqos::load().map_err(MigrationError::LoadError)?;
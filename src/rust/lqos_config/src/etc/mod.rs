@@ -1,10 +1,15 @@
 //! Manages the `/etc/lqos.conf` file.
 
+mod config_check;
+mod config_diff;
 mod etclqos_migration;
 
+pub use self::migration::MigrationPreview;
 use self::migration::migrate_if_needed;
 pub use self::v15::Config;
 use arc_swap::ArcSwap;
+pub use config_check::{ConfigIssue, ConfigIssueSeverity, check_config};
+pub use config_diff::{ConfigChange, diff_config_documents};
 pub use etclqos_migration::*;
 use once_cell::sync::Lazy;
 use std::path::Path;
@@ -19,9 +24,14 @@ mod python_migration;
 pub mod test_data;
 mod v15;
 pub use v15::{
-    BridgeConfig, LazyQueueMode, QueueMode, RttThresholds, SingleInterfaceConfig, StormguardConfig,
-    StormguardStrategy, TreeguardCircuitsConfig, TreeguardConfig, TreeguardCpuConfig,
-    TreeguardCpuMode, TreeguardLinksConfig, TreeguardQooConfig, Tunables,
+    AlertComparison, AlertDirection, AlertMetric, AlertRule, AlertScope, AlertingConfig,
+    BridgeConfig, ChangeControlConfig, FederationConfig, FederationPeer, HaRole,
+    HighAvailabilityConfig, LazyQueueMode, NOTIFICATION_SOURCES, NotificationSeverity,
+    NotificationSink, NotificationTarget, NotificationsConfig, OidcConfig, QueueMode,
+    RemoteBusClient, RemoteBusConfig, RttThresholds, SingleInterfaceConfig, SmtpSecurity,
+    StormguardConfig, StormguardStrategy, SyslogProtocol, TreeguardAdaptiveSqmConfig,
+    TreeguardCircuitsConfig, TreeguardConfig, TreeguardCpuConfig, TreeguardCpuMode,
+    TreeguardLinksConfig, TreeguardQooConfig, TreeguardRebalanceConfig, Tunables,
 };
 
 static CONFIG: Lazy<ArcSwap<Option<Arc<Config>>>> = Lazy::new(|| ArcSwap::from_pointee(None));
@@ -67,39 +77,10 @@ fn maybe_migrate_treeguard_cpu_mode(
     config_location: &str,
     raw: String,
 ) -> Result<String, LibreQoSConfigError> {
-    let mut doc: DocumentMut = raw.parse().map_err(|e| LibreQoSConfigError::ParseError {
-        path: config_location.to_string(),
-        details: format!("Error parsing config: {e}"),
-    })?;
-
-    let mode_item = doc
-        .get("treeguard")
-        .and_then(|section| section.as_table_like())
-        .and_then(|treeguard| treeguard.get("cpu"))
-        .and_then(|cpu| cpu.as_table_like())
-        .and_then(|cpu| cpu.get("mode"))
-        .and_then(|mode| mode.as_str());
-    if mode_item != Some("traffic_rtt_only") {
-        return Ok(raw);
-    }
-
-    let stamp_path = treeguard_cpu_mode_migration_stamp_path(config_location);
-    if Path::new(&stamp_path).exists() {
-        return Ok(raw);
-    }
-
-    let Some(cpu_table) = doc
-        .get_mut("treeguard")
-        .and_then(|section| section.as_table_like_mut())
-        .and_then(|treeguard| treeguard.get_mut("cpu"))
-        .and_then(|cpu| cpu.as_table_like_mut())
-    else {
+    let Some(migrated) = treeguard_cpu_mode_migration(config_location, &raw)? else {
         return Ok(raw);
     };
 
-    cpu_table.insert("mode", value("cpu_aware"));
-    let migrated = doc.to_string();
-
     let config_path = Path::new(config_location);
     if config_path.exists() {
         let backup_path = format!("{config_location}.treeguard_cpu_mode_backup");
@@ -113,6 +94,7 @@ fn maybe_migrate_treeguard_cpu_mode(
         path: config_location.to_string(),
         source: e,
     })?;
+    let stamp_path = treeguard_cpu_mode_migration_stamp_path(config_location);
     std::fs::write(&stamp_path, b"cpu_aware\n").map_err(|e| LibreQoSConfigError::CannotWrite {
         path: stamp_path,
         source: e,
@@ -127,15 +109,59 @@ fn maybe_migrate_treeguard_cpu_mode(
     Ok(migrated)
 }
 
-fn actually_load_from_disk() -> Result<Arc<Config>, LibreQoSConfigError> {
-    let config_location = if let Ok(lqos_config) = std::env::var("LQOS_CONFIG") {
+/// The configuration with TreeGuard's `traffic_rtt_only` CPU mode replaced by `cpu_aware`, if
+/// it needs it. This only happens once, so operators can switch back deliberately.
+fn treeguard_cpu_mode_migration(
+    config_location: &str,
+    raw: &str,
+) -> Result<Option<String>, LibreQoSConfigError> {
+    let mut doc: DocumentMut = raw.parse().map_err(|e| LibreQoSConfigError::ParseError {
+        path: config_location.to_string(),
+        details: format!("Error parsing config: {e}"),
+    })?;
+
+    let mode_item = doc
+        .get("treeguard")
+        .and_then(|section| section.as_table_like())
+        .and_then(|treeguard| treeguard.get("cpu"))
+        .and_then(|cpu| cpu.as_table_like())
+        .and_then(|cpu| cpu.get("mode"))
+        .and_then(|mode| mode.as_str());
+    if mode_item != Some("traffic_rtt_only") {
+        return Ok(None);
+    }
+
+    let stamp_path = treeguard_cpu_mode_migration_stamp_path(config_location);
+    if Path::new(&stamp_path).exists() {
+        return Ok(None);
+    }
+
+    let Some(cpu_table) = doc
+        .get_mut("treeguard")
+        .and_then(|section| section.as_table_like_mut())
+        .and_then(|treeguard| treeguard.get_mut("cpu"))
+        .and_then(|cpu| cpu.as_table_like_mut())
+    else {
+        return Ok(None);
+    };
+
+    cpu_table.insert("mode", value("cpu_aware"));
+    Ok(Some(doc.to_string()))
+}
+
+fn default_config_location() -> String {
+    if let Ok(lqos_config) = std::env::var("LQOS_CONFIG") {
         info!("Overriding lqos.conf location from environment variable.");
         lqos_config
     } else {
         "/etc/lqos.conf".to_string()
-    };
+    }
+}
 
-    debug!("Loading configuration file {config_location}");
+/// Applies the migrations that loading the configuration would, and returns the migrated
+/// file. `config_location` defaults to `/etc/lqos.conf`, or `LQOS_CONFIG` if set.
+pub fn migrate_config_file(config_location: Option<&str>) -> Result<String, LibreQoSConfigError> {
+    let config_location = config_location.map_or_else(default_config_location, str::to_string);
     migrate_if_needed(&config_location).map_err(|e| {
         error!("Unable to migrate configuration: {:?}", e);
         match &e {
@@ -167,7 +193,39 @@ fn actually_load_from_disk() -> Result<Arc<Config>, LibreQoSConfigError> {
         }
     })?;
 
-    let raw = maybe_migrate_treeguard_cpu_mode(&config_location, raw)?;
+    maybe_migrate_treeguard_cpu_mode(&config_location, raw)
+}
+
+/// Works out what [`migrate_config_file`] would change, without writing anything.
+pub fn preview_config_migration(
+    config_location: Option<&str>,
+) -> Result<MigrationPreview, LibreQoSConfigError> {
+    let config_location = config_location.map_or_else(default_config_location, str::to_string);
+    let mut preview = migration::preview_migration(&config_location).map_err(|e| match &e {
+        migration::MigrationError::ReadError(io) if io.kind() == std::io::ErrorKind::NotFound => {
+            LibreQoSConfigError::NotFound {
+                path: config_location.clone(),
+            }
+        }
+        _ => LibreQoSConfigError::MigrationFailed {
+            path: config_location.clone(),
+            details: e.to_string(),
+        },
+    })?;
+
+    if let Some(migrated) = treeguard_cpu_mode_migration(&config_location, &preview.migrated)? {
+        preview.steps.push(format!(
+            "Switch TreeGuard from the retired traffic_rtt_only CPU mode to cpu_aware (backup: {config_location}.treeguard_cpu_mode_backup)"
+        ));
+        preview.migrated = migrated;
+    }
+    Ok(preview)
+}
+
+fn actually_load_from_disk() -> Result<Arc<Config>, LibreQoSConfigError> {
+    let config_location = default_config_location();
+    debug!("Loading configuration file {config_location}");
+    let raw = migrate_config_file(Some(&config_location))?;
 
     let mut final_config = Config::load_from_string(&raw).map_err(|e| {
         error!("Unable to parse {config_location}");
@@ -210,6 +268,12 @@ pub fn enable_long_term_stats(license_key: String) -> Result<(), LibreQoSConfigE
     Ok(())
 }*/
 
+/// JSON schema of `/etc/lqos.conf`, for editor autocompletion (e.g. with Taplo).
+#[cfg(feature = "schema")]
+pub fn config_json_schema() -> serde_json::Value {
+    schemars::schema_for!(Config).to_value()
+}
+
 /// Update the configuration on disk
 pub fn update_config(new_config: &Config) -> Result<(), LibreQoSConfigError> {
     debug!("Updating stored configuration");
@@ -286,3 +350,40 @@ pub enum LibreQoSConfigError {
         source: std::io::Error,
     },
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn preview_lists_migrations_without_writing() {
+        let path = std::env::temp_dir().join(format!("lqos_preview_{}.conf", std::process::id()));
+        let raw = include_str!("v15/example.toml")
+            .replacen("version = \"1.5\"\n", "", 1)
+            .replace("mode = \"cpu_aware\"", "mode = \"traffic_rtt_only\"");
+        std::fs::write(&path, &raw).expect("Cannot write test config");
+
+        let preview = preview_config_migration(path.to_str());
+        let on_disk = std::fs::read_to_string(&path).expect("Cannot read test config");
+        let _ = std::fs::remove_file(&path);
+        let preview = preview.expect("Cannot preview migration");
+
+        assert_eq!(on_disk, raw);
+        assert_eq!(preview.steps.len(), 2);
+        let parse = |raw: &str| raw.parse::<DocumentMut>().expect("Cannot parse TOML");
+        assert_eq!(
+            diff_config_documents(&parse(&preview.original), &parse(&preview.migrated)),
+            vec![
+                ConfigChange::Changed {
+                    path: "treeguard.cpu.mode".to_string(),
+                    from: "\"traffic_rtt_only\"".to_string(),
+                    to: "\"cpu_aware\"".to_string(),
+                },
+                ConfigChange::Added {
+                    path: "version".to_string(),
+                    value: "\"1.5\"".to_string(),
+                },
+            ]
+        );
+    }
+}
@@ -0,0 +1,222 @@
+//! Threshold alerting rules evaluated by `lqosd` over node and circuit metrics.
+
+use allocative::Allocative;
+#[cfg(feature = "schema")]
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+
+use super::NotificationSeverity;
+
+fn default_true() -> bool {
+    true
+}
+
+fn default_evaluation_interval_seconds() -> u64 {
+    10
+}
+
+fn default_hold_down_seconds() -> u64 {
+    300
+}
+
+fn default_recovery_seconds() -> u64 {
+    300
+}
+
+/// Alerting rule settings.
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq, Allocative)]
+#[cfg_attr(feature = "schema", derive(JsonSchema))]
+#[serde(default)]
+pub struct AlertingConfig {
+    /// Master switch for rule evaluation.
+    #[serde(default = "default_true")]
+    pub enabled: bool,
+
+    /// How often rules are evaluated, in seconds.
+    #[serde(default = "default_evaluation_interval_seconds")]
+    pub evaluation_interval_seconds: u64,
+
+    /// Rules, each a `[[alerting.rules]]` table.
+    #[serde(default)]
+    pub rules: Vec<AlertRule>,
+}
+
+impl Default for AlertingConfig {
+    fn default() -> Self {
+        Self {
+            enabled: default_true(),
+            evaluation_interval_seconds: default_evaluation_interval_seconds(),
+            rules: Vec::new(),
+        }
+    }
+}
+
+/// What a rule is evaluated against.
+#[derive(Clone, Copy, Serialize, Deserialize, Debug, PartialEq, Eq, Allocative)]
+#[cfg_attr(feature = "schema", derive(JsonSchema))]
+#[serde(rename_all = "snake_case")]
+pub enum AlertScope {
+    /// Sites, APs and other `network.json` nodes.
+    Node,
+    /// Circuits from `ShapedDevices.csv`.
+    Circuit,
+}
+
+/// The measured value a rule compares with its threshold.
+#[derive(Clone, Copy, Serialize, Deserialize, Debug, PartialEq, Eq, Allocative)]
+#[cfg_attr(feature = "schema", derive(JsonSchema))]
+#[serde(rename_all = "snake_case")]
+pub enum AlertMetric {
+    /// Current throughput in Mbps.
+    ThroughputMbps,
+    /// Current throughput as a percentage of the node's or circuit's configured maximum.
+    UtilizationPct,
+    /// Median RTT in milliseconds.
+    RttP50Ms,
+    /// 95th-percentile RTT in milliseconds. Nodes only.
+    RttP95Ms,
+    /// TCP retransmits as a percentage of TCP packets.
+    RetransmitPct,
+    /// CAKE drops in the most recent queue sample. Nodes only.
+    CakeDrops,
+    /// CAKE ECN marks in the most recent queue sample. Nodes only.
+    CakeMarks,
+}
+
+impl AlertMetric {
+    /// The metric's name as written in the configuration file.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            AlertMetric::ThroughputMbps => "throughput_mbps",
+            AlertMetric::UtilizationPct => "utilization_pct",
+            AlertMetric::RttP50Ms => "rtt_p50_ms",
+            AlertMetric::RttP95Ms => "rtt_p95_ms",
+            AlertMetric::RetransmitPct => "retransmit_pct",
+            AlertMetric::CakeDrops => "cake_drops",
+            AlertMetric::CakeMarks => "cake_marks",
+        }
+    }
+
+    /// Whether the metric is available for `scope`.
+    pub fn supports(self, scope: AlertScope) -> bool {
+        match self {
+            AlertMetric::RttP95Ms | AlertMetric::CakeDrops | AlertMetric::CakeMarks => {
+                scope == AlertScope::Node
+            }
+            _ => true,
+        }
+    }
+}
+
+/// Which traffic direction a rule looks at.
+#[derive(Clone, Copy, Serialize, Deserialize, Debug, PartialEq, Eq, Allocative)]
+#[cfg_attr(feature = "schema", derive(JsonSchema))]
+#[serde(rename_all = "snake_case")]
+pub enum AlertDirection {
+    /// Download only.
+    Down,
+    /// Upload only.
+    Up,
+    /// Either direction crossing the threshold is enough.
+    Either,
+}
+
+/// Whether a rule fires above or below its threshold.
+#[derive(Clone, Copy, Serialize, Deserialize, Debug, PartialEq, Eq, Allocative)]
+#[cfg_attr(feature = "schema", derive(JsonSchema))]
+#[serde(rename_all = "snake_case")]
+pub enum AlertComparison {
+    /// Fires while the value is greater than the threshold.
+    Above,
+    /// Fires while the value is less than the threshold.
+    Below,
+}
+
+/// One alerting rule.
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq, Allocative)]
+#[cfg_attr(feature = "schema", derive(JsonSchema))]
+pub struct AlertRule {
+    /// Unique name, shown in alerts.
+    pub name: String,
+
+    /// Set to `false` to keep the rule configured but inactive.
+    #[serde(default = "default_true")]
+    pub enabled: bool,
+
+    /// Nodes or circuits.
+    pub scope: AlertScope,
+
+    /// Node names, or circuit IDs/names, to watch. Empty watches all of them.
+    #[serde(default)]
+    pub targets: Vec<String>,
+
+    /// Value compared with `threshold`.
+    pub metric: AlertMetric,
+
+    /// Traffic direction.
+    #[serde(default = "default_direction")]
+    pub direction: AlertDirection,
+
+    /// Fire above or below `threshold`.
+    #[serde(default = "default_comparison")]
+    pub comparison: AlertComparison,
+
+    /// Threshold, in the metric's unit.
+    pub threshold: f64,
+
+    /// The condition must hold for this long before the alert fires.
+    #[serde(default = "default_hold_down_seconds")]
+    pub hold_down_seconds: u64,
+
+    /// The condition must stay clear for this long before the alert resolves.
+    #[serde(default = "default_recovery_seconds")]
+    pub recovery_seconds: u64,
+
+    /// Severity of the urgent issue raised when the alert fires.
+    #[serde(default = "default_severity")]
+    pub severity: NotificationSeverity,
+}
+
+fn default_direction() -> AlertDirection {
+    AlertDirection::Either
+}
+
+fn default_comparison() -> AlertComparison {
+    AlertComparison::Above
+}
+
+fn default_severity() -> NotificationSeverity {
+    NotificationSeverity::Warning
+}
+
+impl AlertingConfig {
+    /// Validates alerting settings.
+    pub fn validate(&self) -> Result<(), String> {
+        if !(1..=3600).contains(&self.evaluation_interval_seconds) {
+            return Err("alerting.evaluation_interval_seconds must be between 1 and 3600".into());
+        }
+        let mut names = HashSet::new();
+        for rule in &self.rules {
+            let name = rule.name.trim();
+            if name.is_empty() {
+                return Err("alerting.rules entries need a name".to_string());
+            }
+            if !names.insert(name) {
+                return Err(format!("alerting.rules name '{name}' is used twice"));
+            }
+            if !rule.metric.supports(rule.scope) {
+                return Err(format!(
+                    "alerting rule '{name}': metric {} is only available for node rules",
+                    rule.metric.as_str()
+                ));
+            }
+            if !rule.threshold.is_finite() || rule.threshold < 0.0 {
+                return Err(format!(
+                    "alerting rule '{name}': threshold must be a non-negative number"
+                ));
+            }
+        }
+        Ok(())
+    }
+}
@@ -3,10 +3,13 @@
 //! section, but not both.
 
 use allocative::Allocative;
+#[cfg(feature = "schema")]
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
 /// Represents a two-interface bridge configuration.
 #[derive(Clone, Serialize, Deserialize, Debug, PartialEq, Allocative)]
+#[cfg_attr(feature = "schema", derive(JsonSchema))]
 pub struct BridgeConfig {
     /// Use the XDP-accelerated bridge?
     pub use_xdp_bridge: bool,
@@ -30,6 +33,7 @@ impl Default for BridgeConfig {
 
 /// Represents a single-interface bridge
 #[derive(Clone, Serialize, Deserialize, Debug, PartialEq, Allocative)]
+#[cfg_attr(feature = "schema", derive(JsonSchema))]
 pub struct SingleInterfaceConfig {
     /// The name of the interface
     pub interface: String,
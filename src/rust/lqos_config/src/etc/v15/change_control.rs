@@ -0,0 +1,72 @@
+//! Change-control settings for automated override writers (StormGuard and TreeGuard).
+
+use allocative::Allocative;
+#[cfg(feature = "schema")]
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+fn default_false() -> bool {
+    false
+}
+
+fn default_auto_approve_max_change_pct() -> f32 {
+    0.0
+}
+
+/// Controls whether persisted changes proposed by automated actors are applied
+/// immediately or held in a pending queue until an administrator approves them.
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq, Allocative)]
+#[cfg_attr(feature = "schema", derive(JsonSchema))]
+#[serde(default)]
+pub struct ChangeControlConfig {
+    /// When true, persisted override changes from StormGuard and TreeGuard are queued
+    /// for approval instead of being written directly.
+    #[serde(default = "default_false")]
+    pub require_approval: bool,
+
+    /// Sites whose proposed changes are always approved automatically.
+    #[serde(default)]
+    pub auto_approve_sites: Vec<String>,
+
+    /// Bandwidth changes whose magnitude (percent of the configured rate) is at or
+    /// below this value are approved automatically. `0` disables the rule.
+    #[serde(default = "default_auto_approve_max_change_pct")]
+    pub auto_approve_max_change_pct: f32,
+
+    /// Automatically approve SQM (qdisc) override changes.
+    #[serde(default = "default_false")]
+    pub auto_approve_sqm: bool,
+}
+
+impl Default for ChangeControlConfig {
+    fn default() -> Self {
+        Self {
+            require_approval: default_false(),
+            auto_approve_sites: Vec::new(),
+            auto_approve_max_change_pct: default_auto_approve_max_change_pct(),
+            auto_approve_sqm: default_false(),
+        }
+    }
+}
+
+impl ChangeControlConfig {
+    /// Validates change-control settings.
+    pub fn validate(&self) -> Result<(), String> {
+        let pct = self.auto_approve_max_change_pct;
+        if !pct.is_finite() || !(0.0..=100.0).contains(&pct) {
+            return Err(
+                "change_control.auto_approve_max_change_pct must be between 0 and 100".to_string(),
+            );
+        }
+        if self
+            .auto_approve_sites
+            .iter()
+            .any(|site| site.trim().is_empty())
+        {
+            return Err(
+                "change_control.auto_approve_sites may not contain empty names".to_string(),
+            );
+        }
+        Ok(())
+    }
+}
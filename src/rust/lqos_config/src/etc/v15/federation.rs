@@ -0,0 +1,135 @@
+//! Federation: one node manager showing several shapers' dashboards, trees and circuits.
+
+use allocative::Allocative;
+#[cfg(feature = "schema")]
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+
+fn default_true() -> bool {
+    true
+}
+
+fn default_poll_interval_seconds() -> u64 {
+    10
+}
+
+fn default_request_timeout_seconds() -> u64 {
+    5
+}
+
+/// Federation settings.
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq, Allocative)]
+#[cfg_attr(feature = "schema", derive(JsonSchema))]
+#[serde(default)]
+pub struct FederationConfig {
+    /// Poll peers and show the federation view.
+    pub enabled: bool,
+
+    /// How often each peer is polled, in seconds.
+    #[serde(default = "default_poll_interval_seconds")]
+    pub poll_interval_seconds: u64,
+
+    /// Timeout for each request to a peer, in seconds.
+    #[serde(default = "default_request_timeout_seconds")]
+    pub request_timeout_seconds: u64,
+
+    /// Peer shapers, each a `[[federation.peers]]` table.
+    #[serde(default)]
+    pub peers: Vec<FederationPeer>,
+}
+
+impl Default for FederationConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            poll_interval_seconds: default_poll_interval_seconds(),
+            request_timeout_seconds: default_request_timeout_seconds(),
+            peers: Vec::new(),
+        }
+    }
+}
+
+/// A peer `lqosd`, reached through its `/api/v1` REST API.
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq, Allocative)]
+#[cfg_attr(feature = "schema", derive(JsonSchema))]
+pub struct FederationPeer {
+    /// Display name, unique within the federation.
+    pub name: String,
+
+    /// Base URL of the peer's node manager, e.g. `https://pop-east.example.net:9123`.
+    pub url: String,
+
+    /// API token issued on the peer with `lqusers token add` (the `read` scope is enough).
+    pub api_token: String,
+
+    /// Verify the peer's TLS certificate.
+    #[serde(default = "default_true")]
+    pub verify_tls: bool,
+
+    /// Extra PEM CA certificate to trust for this peer, e.g. for a private CA.
+    #[serde(default)]
+    pub ca_certificate: Option<String>,
+}
+
+impl FederationConfig {
+    /// Validates federation settings.
+    pub fn validate(&self) -> Result<(), String> {
+        if !(1..=3600).contains(&self.poll_interval_seconds) {
+            return Err("federation.poll_interval_seconds must be between 1 and 3600".to_string());
+        }
+        if !(1..=60).contains(&self.request_timeout_seconds) {
+            return Err("federation.request_timeout_seconds must be between 1 and 60".to_string());
+        }
+        let mut names = HashSet::new();
+        for peer in &self.peers {
+            let name = peer.name.trim();
+            if name.is_empty() {
+                return Err("federation.peers entries need a name".to_string());
+            }
+            if !names.insert(name) {
+                return Err(format!("federation.peers name '{name}' is used twice"));
+            }
+            if !https_or_loopback(&peer.url) {
+                return Err(format!(
+                    "federation peer '{name}': url must use https:// (http:// is allowed only for localhost)"
+                ));
+            }
+            if peer.api_token.trim().is_empty() {
+                return Err(format!("federation peer '{name}': api_token is required"));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Peers and identity providers must be reached over HTTPS, except on the loopback interface.
+pub(super) fn https_or_loopback(url: &str) -> bool {
+    if url.starts_with("https://") {
+        return true;
+    }
+    let Some(rest) = url.strip_prefix("http://") else {
+        return false;
+    };
+    let host = rest.split(['/', '?']).next().unwrap_or_default();
+    let host = host
+        .strip_prefix('[')
+        .and_then(|h| h.split_once(']').map(|(h, _)| h))
+        .unwrap_or_else(|| host.rsplit_once(':').map_or(host, |(h, _)| h));
+    matches!(host, "localhost" | "127.0.0.1" | "::1")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn peer_urls_require_https_except_loopback() {
+        assert!(https_or_loopback("https://pop-east.example.net:9123"));
+        assert!(https_or_loopback("http://127.0.0.1:9123"));
+        assert!(https_or_loopback("http://localhost/"));
+        assert!(https_or_loopback("http://[::1]:9123"));
+        assert!(!https_or_loopback("http://pop-east.example.net:9123"));
+        assert!(!https_or_loopback("ftp://127.0.0.1"));
+    }
+}
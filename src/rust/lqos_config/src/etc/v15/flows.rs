@@ -3,9 +3,12 @@
 //! You can enable them by adding a `[flows]` section to your configuration file.
 
 use allocative::Allocative;
+#[cfg(feature = "schema")]
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
 #[derive(Clone, Serialize, Deserialize, Debug, PartialEq, Allocative)]
+#[cfg_attr(feature = "schema", derive(JsonSchema))]
 pub struct FlowConfig {
     pub flow_timeout_seconds: u64,
     pub netflow_enabled: bool,
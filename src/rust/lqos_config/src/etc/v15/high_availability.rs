@@ -0,0 +1,167 @@
+//! Active/standby shaper pairs: the active shaper streams its shaping state to a warm standby.
+
+use allocative::Allocative;
+#[cfg(feature = "schema")]
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::net::SocketAddr;
+
+fn default_true() -> bool {
+    true
+}
+
+fn default_listen() -> String {
+    "0.0.0.0:9124".to_string()
+}
+
+fn default_heartbeat_interval_seconds() -> u64 {
+    1
+}
+
+fn default_failover_timeout_seconds() -> u64 {
+    10
+}
+
+/// This shaper's place in an active/standby pair.
+#[derive(Clone, Copy, Serialize, Deserialize, Debug, PartialEq, Eq, Allocative)]
+#[cfg_attr(feature = "schema", derive(JsonSchema))]
+#[serde(rename_all = "snake_case")]
+pub enum HaRole {
+    /// Shapes traffic and streams its state to the standby.
+    Active,
+    /// Mirrors the active shaper's state and takes over if it goes silent.
+    Standby,
+}
+
+/// High-availability settings.
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq, Allocative)]
+#[cfg_attr(feature = "schema", derive(JsonSchema))]
+#[serde(default)]
+pub struct HighAvailabilityConfig {
+    /// Run as one half of an active/standby pair.
+    pub enabled: bool,
+
+    /// Configured role. Changing `standby` to `active` promotes the standby immediately.
+    pub role: HaRole,
+
+    /// Address the standby listens on for the active shaper's state stream.
+    #[serde(default = "default_listen")]
+    pub listen: String,
+
+    /// The standby's `host:port`, used by the active shaper.
+    #[serde(default)]
+    pub peer: String,
+
+    /// Secret shared by both shapers; every frame is signed with it.
+    #[serde(default)]
+    pub shared_secret: String,
+
+    /// How often the active shaper sends a heartbeat, in seconds.
+    #[serde(default = "default_heartbeat_interval_seconds")]
+    pub heartbeat_interval_seconds: u64,
+
+    /// How long the standby waits without hearing from the active shaper before taking over.
+    #[serde(default = "default_failover_timeout_seconds")]
+    pub failover_timeout_seconds: u64,
+
+    /// Take over automatically after `failover_timeout_seconds`. When `false`, the standby only
+    /// raises an urgent issue and waits for `role` to be changed to `active`.
+    #[serde(default = "default_true")]
+    pub auto_failover: bool,
+}
+
+impl Default for HighAvailabilityConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            role: HaRole::Active,
+            listen: default_listen(),
+            peer: String::new(),
+            shared_secret: String::new(),
+            heartbeat_interval_seconds: default_heartbeat_interval_seconds(),
+            failover_timeout_seconds: default_failover_timeout_seconds(),
+            auto_failover: default_true(),
+        }
+    }
+}
+
+impl HighAvailabilityConfig {
+    /// Validates high-availability settings.
+    pub fn validate(&self) -> Result<(), String> {
+        if !self.enabled {
+            return Ok(());
+        }
+        if self.shared_secret.trim().len() < 16 {
+            return Err("high_availability.shared_secret must be at least 16 characters".into());
+        }
+        if !(1..=60).contains(&self.heartbeat_interval_seconds) {
+            return Err(
+                "high_availability.heartbeat_interval_seconds must be between 1 and 60".into(),
+            );
+        }
+        if self.failover_timeout_seconds < self.heartbeat_interval_seconds * 3 {
+            return Err(
+                "high_availability.failover_timeout_seconds must be at least three heartbeat intervals"
+                    .into(),
+            );
+        }
+        match self.role {
+            HaRole::Active => {
+                if !valid_host_port(&self.peer) {
+                    return Err("high_availability.peer must be the standby's host:port".into());
+                }
+            }
+            HaRole::Standby => {
+                if self.listen.parse::<SocketAddr>().is_err() {
+                    return Err(format!(
+                        "high_availability.listen '{}' is not an address:port",
+                        self.listen
+                    ));
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Accepts `host:port`, `ip:port` and `[ipv6]:port`.
+fn valid_host_port(value: &str) -> bool {
+    let Some((host, port)) = value.trim().rsplit_once(':') else {
+        return false;
+    };
+    !host.is_empty() && port.parse::<u16>().is_ok_and(|port| port != 0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn enabled(role: HaRole) -> HighAvailabilityConfig {
+        HighAvailabilityConfig {
+            enabled: true,
+            role,
+            peer: "standby.example.net:9124".to_string(),
+            shared_secret: "correct horse battery staple".to_string(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn validates_role_specific_settings() {
+        assert!(HighAvailabilityConfig::default().validate().is_ok());
+        assert!(enabled(HaRole::Active).validate().is_ok());
+        assert!(enabled(HaRole::Standby).validate().is_ok());
+
+        let mut no_peer = enabled(HaRole::Active);
+        no_peer.peer = "standby.example.net".to_string();
+        assert!(no_peer.validate().is_err());
+
+        let mut weak = enabled(HaRole::Standby);
+        weak.shared_secret = "short".to_string();
+        assert!(weak.validate().is_err());
+
+        let mut hasty = enabled(HaRole::Standby);
+        hasty.failover_timeout_seconds = 2;
+        assert!(hasty.validate().is_err());
+    }
+}
@@ -1,7 +1,10 @@
 use allocative::Allocative;
+#[cfg(feature = "schema")]
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
 #[derive(Clone, Serialize, Deserialize, Debug, PartialEq, Allocative)]
+#[cfg_attr(feature = "schema", derive(JsonSchema))]
 pub struct InfluxDbConfig {
     pub enable_influxdb: bool,
     pub url: String,
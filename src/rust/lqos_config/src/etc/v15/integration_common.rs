@@ -1,9 +1,12 @@
 //! Common integration variables, shared between integrations
 
 use allocative::Allocative;
+#[cfg(feature = "schema")]
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
 #[derive(Clone, Serialize, Deserialize, Debug, PartialEq, Allocative)]
+#[cfg_attr(feature = "schema", derive(JsonSchema))]
 pub struct IntegrationConfig {
     /// Replace names with addresses?
     pub circuit_name_as_address: bool,
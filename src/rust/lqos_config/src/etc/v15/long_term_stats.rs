@@ -1,9 +1,12 @@
 //! Defines configuration for the LTS project
 
 use allocative::Allocative;
+#[cfg(feature = "schema")]
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Allocative)]
+#[cfg_attr(feature = "schema", derive(JsonSchema))]
 pub struct LongTermStats {
     /// Should we store long-term stats at all?
     pub gather_stats: bool,
@@ -3,15 +3,22 @@
 mod top_config;
 pub use top_config::Config;
 pub use top_config::RttThresholds;
+mod alerting;
 mod bridge;
+mod change_control;
+mod federation;
 mod flows;
+mod high_availability;
 pub mod influxdb;
 mod integration_common;
 mod ip_ranges;
 mod long_term_stats;
 mod netzur_integration;
+mod notifications;
+mod oidc;
 mod powercode_integration;
 mod queues;
+mod remote_bus;
 mod sonar_integration;
 mod splynx_integration;
 mod stormguard;
@@ -21,12 +28,24 @@ mod uisp_integration;
 mod visp_integration;
 mod wispgate;
 
+pub use alerting::{
+    AlertComparison, AlertDirection, AlertMetric, AlertRule, AlertScope, AlertingConfig,
+};
 pub use bridge::*;
+pub use change_control::ChangeControlConfig;
+pub use federation::{FederationConfig, FederationPeer};
+pub use high_availability::{HaRole, HighAvailabilityConfig};
 pub use long_term_stats::LongTermStats;
+pub use notifications::{
+    NOTIFICATION_SOURCES, NotificationSeverity, NotificationSink, NotificationTarget,
+    NotificationsConfig, SmtpSecurity, SyslogProtocol,
+};
+pub use oidc::OidcConfig;
 pub use queues::{LazyQueueMode, QueueMode};
+pub use remote_bus::{RemoteBusClient, RemoteBusConfig, normalize_fingerprint};
 pub use stormguard::{StormguardConfig, StormguardStrategy};
 pub use treeguard::{
-    TreeguardCircuitsConfig, TreeguardConfig, TreeguardCpuConfig, TreeguardCpuMode,
-    TreeguardLinksConfig, TreeguardQooConfig,
+    TreeguardAdaptiveSqmConfig, TreeguardCircuitsConfig, TreeguardConfig, TreeguardCpuConfig,
+    TreeguardCpuMode, TreeguardLinksConfig, TreeguardQooConfig, TreeguardRebalanceConfig,
 };
 pub use tuning::Tunables;
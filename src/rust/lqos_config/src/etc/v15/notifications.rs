@@ -0,0 +1,258 @@
+//! Notification sinks for urgent issues, and persistence of the urgent-issue list.
+
+use allocative::Allocative;
+#[cfg(feature = "schema")]
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+
+/// Urgent-issue sources a sink can filter on.
+pub const NOTIFICATION_SOURCES: [&str; 4] = ["scheduler", "libreqos", "api", "system"];
+
+fn default_true() -> bool {
+    true
+}
+
+fn default_min_interval_seconds() -> u64 {
+    900
+}
+
+fn default_smtp_port() -> u16 {
+    587
+}
+
+fn default_syslog_facility() -> u8 {
+    16 // local0
+}
+
+/// Urgent-issue notification settings.
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq, Allocative)]
+#[cfg_attr(feature = "schema", derive(JsonSchema))]
+#[serde(default)]
+pub struct NotificationsConfig {
+    /// Keep the urgent-issue list in `<lqos_directory>/urgent_issues.json` so that it
+    /// survives a restart of `lqosd`.
+    #[serde(default = "default_true")]
+    pub persist_urgent_issues: bool,
+
+    /// Where urgent issues are sent. Each sink is a `[[notifications.sinks]]` table.
+    #[serde(default)]
+    pub sinks: Vec<NotificationSink>,
+}
+
+impl Default for NotificationsConfig {
+    fn default() -> Self {
+        Self {
+            persist_urgent_issues: default_true(),
+            sinks: Vec::new(),
+        }
+    }
+}
+
+/// Lowest severity an issue must have to be sent to a sink.
+#[derive(
+    Clone, Copy, Serialize, Deserialize, Debug, PartialEq, Eq, PartialOrd, Ord, Allocative,
+)]
+#[cfg_attr(feature = "schema", derive(JsonSchema))]
+#[serde(rename_all = "snake_case")]
+pub enum NotificationSeverity {
+    /// Warnings and errors.
+    Warning,
+    /// Errors only.
+    Error,
+}
+
+/// How a connection to the SMTP server is secured.
+#[derive(Clone, Copy, Serialize, Deserialize, Debug, PartialEq, Eq, Allocative)]
+#[cfg_attr(feature = "schema", derive(JsonSchema))]
+#[serde(rename_all = "snake_case")]
+pub enum SmtpSecurity {
+    /// Plain connection upgraded with STARTTLS (usually port 587).
+    Starttls,
+    /// Implicit TLS (usually port 465).
+    Tls,
+    /// No encryption. Only for a relay on the local host or a trusted network.
+    None,
+}
+
+/// Transport used to reach a syslog collector.
+#[derive(Clone, Copy, Serialize, Deserialize, Debug, PartialEq, Eq, Allocative)]
+#[cfg_attr(feature = "schema", derive(JsonSchema))]
+#[serde(rename_all = "snake_case")]
+pub enum SyslogProtocol {
+    /// One datagram per message.
+    Udp,
+    /// Octet-counted framing (RFC 6587).
+    Tcp,
+}
+
+/// What kind of endpoint a sink delivers to, and how to reach it.
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq, Allocative)]
+#[cfg_attr(feature = "schema", derive(JsonSchema))]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum NotificationTarget {
+    /// POSTs the issue as JSON to `url`.
+    Webhook {
+        /// Endpoint URL.
+        url: String,
+    },
+    /// Slack (or Mattermost/Rocket.Chat) incoming webhook.
+    Slack {
+        /// Incoming webhook URL.
+        url: String,
+    },
+    /// Microsoft Teams incoming webhook.
+    Teams {
+        /// Incoming webhook URL.
+        url: String,
+    },
+    /// Matrix room via a hookshot-style generic webhook.
+    Matrix {
+        /// Generic webhook URL.
+        url: String,
+    },
+    /// Email through an SMTP relay.
+    Email {
+        /// SMTP server host name.
+        smtp_server: String,
+        /// SMTP server port.
+        #[serde(default = "default_smtp_port")]
+        smtp_port: u16,
+        /// Connection security.
+        #[serde(default = "default_smtp_security")]
+        security: SmtpSecurity,
+        /// Login user name. Leave empty for unauthenticated relays.
+        #[serde(default)]
+        username: String,
+        /// Login password.
+        #[serde(default)]
+        password: String,
+        /// Sender address.
+        from: String,
+        /// Recipient addresses.
+        to: Vec<String>,
+    },
+    /// RFC 5424 syslog.
+    Syslog {
+        /// Collector address, `host:port`.
+        address: String,
+        /// Transport.
+        #[serde(default = "default_syslog_protocol")]
+        protocol: SyslogProtocol,
+        /// Syslog facility number (16 = local0).
+        #[serde(default = "default_syslog_facility")]
+        facility: u8,
+    },
+}
+
+fn default_smtp_security() -> SmtpSecurity {
+    SmtpSecurity::Starttls
+}
+
+fn default_syslog_protocol() -> SyslogProtocol {
+    SyslogProtocol::Udp
+}
+
+/// One destination for urgent-issue notifications.
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq, Allocative)]
+#[cfg_attr(feature = "schema", derive(JsonSchema))]
+pub struct NotificationSink {
+    /// Unique name, used in logs and for rate limiting.
+    pub name: String,
+
+    /// Set to `false` to keep the sink configured but silent.
+    #[serde(default = "default_true")]
+    pub enabled: bool,
+
+    /// Lowest severity sent to this sink.
+    #[serde(default = "default_min_severity")]
+    pub min_severity: NotificationSeverity,
+
+    /// Only send issues from these sources (see [`NOTIFICATION_SOURCES`]). Empty sends all.
+    #[serde(default)]
+    pub sources: Vec<String>,
+
+    /// An issue with the same dedupe key is sent to this sink at most once per interval.
+    #[serde(default = "default_min_interval_seconds")]
+    pub min_interval_seconds: u64,
+
+    /// Endpoint type and settings.
+    #[serde(flatten)]
+    pub target: NotificationTarget,
+}
+
+fn default_min_severity() -> NotificationSeverity {
+    NotificationSeverity::Warning
+}
+
+impl NotificationsConfig {
+    /// Validates notification settings.
+    pub fn validate(&self) -> Result<(), String> {
+        let mut names = HashSet::new();
+        for sink in &self.sinks {
+            let name = sink.name.trim();
+            if name.is_empty() {
+                return Err("notifications.sinks entries need a name".to_string());
+            }
+            if !names.insert(name) {
+                return Err(format!("notifications.sinks name '{name}' is used twice"));
+            }
+            if let Some(source) = sink
+                .sources
+                .iter()
+                .find(|s| !NOTIFICATION_SOURCES.contains(&s.to_ascii_lowercase().as_str()))
+            {
+                return Err(format!(
+                    "notifications sink '{name}': unknown source '{source}' (expected one of {})",
+                    NOTIFICATION_SOURCES.join(", ")
+                ));
+            }
+            sink.target
+                .validate()
+                .map_err(|e| format!("notifications sink '{name}': {e}"))?;
+        }
+        Ok(())
+    }
+}
+
+impl NotificationTarget {
+    fn validate(&self) -> Result<(), String> {
+        match self {
+            NotificationTarget::Webhook { url }
+            | NotificationTarget::Slack { url }
+            | NotificationTarget::Teams { url }
+            | NotificationTarget::Matrix { url } => {
+                if !(url.starts_with("https://") || url.starts_with("http://")) {
+                    return Err("url must start with http:// or https://".to_string());
+                }
+            }
+            NotificationTarget::Email {
+                smtp_server,
+                from,
+                to,
+                ..
+            } => {
+                if smtp_server.trim().is_empty() {
+                    return Err("smtp_server is required".to_string());
+                }
+                if !from.contains('@') || to.is_empty() || to.iter().any(|t| !t.contains('@')) {
+                    return Err("from and to must be email addresses".to_string());
+                }
+            }
+            NotificationTarget::Syslog {
+                address, facility, ..
+            } => {
+                if address
+                    .rsplit_once(':')
+                    .is_none_or(|(host, port)| host.is_empty() || port.parse::<u16>().is_err())
+                {
+                    return Err("address must be host:port".to_string());
+                }
+                if *facility > 23 {
+                    return Err("facility must be between 0 and 23".to_string());
+                }
+            }
+        }
+        Ok(())
+    }
+}
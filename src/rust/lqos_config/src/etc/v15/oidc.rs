@@ -0,0 +1,164 @@
+//! OpenID Connect single sign-on settings for the node manager.
+
+use super::federation::https_or_loopback;
+use crate::authentication::UserRole;
+use allocative::Allocative;
+#[cfg(feature = "schema")]
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+fn default_false() -> bool {
+    false
+}
+
+fn default_scopes() -> Vec<String> {
+    vec![
+        "openid".to_string(),
+        "profile".to_string(),
+        "email".to_string(),
+    ]
+}
+
+fn default_username_claim() -> String {
+    "preferred_username".to_string()
+}
+
+fn default_role_claim() -> String {
+    "groups".to_string()
+}
+
+fn default_button_label() -> String {
+    "Sign in with SSO".to_string()
+}
+
+/// OIDC authorization-code login for the web UI. Local users in `lqusers.toml` keep working
+/// alongside SSO as break-glass accounts.
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq, Allocative)]
+#[cfg_attr(feature = "schema", derive(JsonSchema))]
+#[serde(default)]
+pub struct OidcConfig {
+    /// Offer SSO on the login page.
+    #[serde(default = "default_false")]
+    pub enabled: bool,
+
+    /// Issuer URL, e.g. `https://keycloak.example.com/realms/isp`. Endpoints are read from
+    /// `<issuer>/.well-known/openid-configuration`.
+    #[serde(default)]
+    pub issuer: String,
+
+    /// Client id registered with the identity provider.
+    #[serde(default)]
+    pub client_id: String,
+
+    /// Client secret. Leave empty for public clients (PKCE is always used).
+    #[serde(default)]
+    pub client_secret: String,
+
+    /// Callback URL registered with the identity provider, ending in `/oidc/callback`.
+    #[serde(default)]
+    pub redirect_url: String,
+
+    /// Scopes to request. `openid` is always sent.
+    #[serde(default = "default_scopes")]
+    pub scopes: Vec<String>,
+
+    /// ID token claim used as the displayed username.
+    #[serde(default = "default_username_claim")]
+    pub username_claim: String,
+
+    /// ID token claim (string or list of strings) matched against the role values below.
+    #[serde(default = "default_role_claim")]
+    pub role_claim: String,
+
+    /// Claim values that grant [`UserRole::Admin`].
+    #[serde(default)]
+    pub admin_values: Vec<String>,
+
+    /// Claim values that grant [`UserRole::ReadOnly`].
+    #[serde(default)]
+    pub read_only_values: Vec<String>,
+
+    /// Role for users matching neither list. `None` rejects them.
+    #[serde(default)]
+    pub default_role: Option<UserRole>,
+
+    /// Text of the login-page button.
+    #[serde(default = "default_button_label")]
+    pub button_label: String,
+}
+
+impl Default for OidcConfig {
+    fn default() -> Self {
+        Self {
+            enabled: default_false(),
+            issuer: String::new(),
+            client_id: String::new(),
+            client_secret: String::new(),
+            redirect_url: String::new(),
+            scopes: default_scopes(),
+            username_claim: default_username_claim(),
+            role_claim: default_role_claim(),
+            admin_values: Vec::new(),
+            read_only_values: Vec::new(),
+            default_role: None,
+            button_label: default_button_label(),
+        }
+    }
+}
+
+impl OidcConfig {
+    /// Maps the values of the role claim to a role. Admin matches win over read-only ones.
+    pub fn role_for_claim_values(&self, values: &[String]) -> Option<UserRole> {
+        let matches = |wanted: &[String]| values.iter().any(|value| wanted.contains(value));
+        if matches(&self.admin_values) {
+            Some(UserRole::Admin)
+        } else if matches(&self.read_only_values) {
+            Some(UserRole::ReadOnly)
+        } else {
+            self.default_role
+        }
+    }
+
+    /// Validates OIDC settings. Disabled configurations are not checked.
+    pub fn validate(&self) -> Result<(), String> {
+        if !self.enabled {
+            return Ok(());
+        }
+        if !https_or_loopback(&self.issuer) {
+            return Err(
+                "oidc.issuer must be an https:// URL (http:// is only allowed for loopback)"
+                    .to_string(),
+            );
+        }
+        if self.client_id.trim().is_empty() {
+            return Err("oidc.client_id is required when oidc is enabled".to_string());
+        }
+        if !(self.redirect_url.starts_with("https://") || self.redirect_url.starts_with("http://"))
+        {
+            return Err("oidc.redirect_url must be an http(s) URL".to_string());
+        }
+        if self.username_claim.trim().is_empty() || self.role_claim.trim().is_empty() {
+            return Err("oidc.username_claim and oidc.role_claim may not be empty".to_string());
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn issuer_requires_https_except_loopback() {
+        let config = |issuer: &str| OidcConfig {
+            enabled: true,
+            issuer: issuer.to_string(),
+            client_id: "libreqos".to_string(),
+            redirect_url: "https://shaper.example.com/oidc/callback".to_string(),
+            ..OidcConfig::default()
+        };
+        assert!(config("https://idp.example.com").validate().is_ok());
+        assert!(config("http://127.0.0.1:8080").validate().is_ok());
+        assert!(config("http://idp.example.com").validate().is_err());
+    }
+}
@@ -1,11 +1,14 @@
 //! Queue Generation definitions (originally from ispConfig.py)
 
 use allocative::Allocative;
+#[cfg(feature = "schema")]
+use schemars::JsonSchema;
 use serde::ser::SerializeStruct;
 use serde::{Deserialize, Deserializer, Serialize, Serializer};
 
 /// Queue application mode.
 #[derive(Clone, Copy, Serialize, Deserialize, Debug, PartialEq, Eq, Default, Allocative)]
+#[cfg_attr(feature = "schema", derive(JsonSchema))]
 #[serde(rename_all = "snake_case")]
 pub enum QueueMode {
     /// Apply LibreQoS queueing and shaping.
@@ -98,7 +101,20 @@ impl Serialize for QueueConfig {
     }
 }
 
+#[cfg(feature = "schema")]
+impl JsonSchema for QueueConfig {
+    fn schema_name() -> std::borrow::Cow<'static, str> {
+        "QueueConfig".into()
+    }
+
+    fn json_schema(generator: &mut schemars::SchemaGenerator) -> schemars::Schema {
+        // Describe what is accepted, including the legacy `monitor_only` flag
+        QueueConfigCompat::json_schema(generator)
+    }
+}
+
 #[derive(Deserialize)]
+#[cfg_attr(feature = "schema", derive(JsonSchema))]
 #[serde(default)]
 struct QueueConfigCompat {
     default_sqm: String,
@@ -120,6 +136,7 @@ struct QueueConfigCompat {
 
 /// Lazy queue creation modes
 #[derive(Clone, Serialize, Deserialize, Debug, PartialEq, Default, Allocative)]
+#[cfg_attr(feature = "schema", derive(JsonSchema))]
 pub enum LazyQueueMode {
     /// No lazy queue creation
     #[default]
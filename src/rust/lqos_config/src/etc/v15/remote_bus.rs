@@ -0,0 +1,196 @@
+//! Bus access over TLS for tools running on another host.
+
+use allocative::Allocative;
+#[cfg(feature = "schema")]
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::net::SocketAddr;
+
+fn default_listen() -> String {
+    "0.0.0.0:9125".to_string()
+}
+
+/// Remote bus listener settings.
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq, Allocative)]
+#[cfg_attr(feature = "schema", derive(JsonSchema))]
+#[serde(default)]
+pub struct RemoteBusConfig {
+    /// Accept bus sessions over TLS, in addition to the local Unix socket.
+    pub enabled: bool,
+
+    /// Address and port to listen on.
+    #[serde(default = "default_listen")]
+    pub listen: String,
+
+    /// PEM certificate chain presented to clients.
+    #[serde(default)]
+    pub cert_file: String,
+
+    /// PEM private key for `cert_file`.
+    #[serde(default)]
+    pub key_file: String,
+
+    /// PEM CA bundle used to verify client certificates (mutual TLS). Clients with a
+    /// `certificate_sha256` must present a certificate signed by it.
+    #[serde(default)]
+    pub client_ca_file: Option<String>,
+
+    /// Permitted clients, each a `[[remote_bus.clients]]` table.
+    #[serde(default)]
+    pub clients: Vec<RemoteBusClient>,
+}
+
+impl Default for RemoteBusConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            listen: default_listen(),
+            cert_file: String::new(),
+            key_file: String::new(),
+            client_ca_file: None,
+            clients: Vec::new(),
+        }
+    }
+}
+
+/// A remote bus client. When both `token` and `certificate_sha256` are set, the client must
+/// present both.
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq, Allocative)]
+#[cfg_attr(feature = "schema", derive(JsonSchema))]
+pub struct RemoteBusClient {
+    /// Name used in logs.
+    pub name: String,
+
+    /// Shared token the client sends after the TLS handshake.
+    #[serde(default)]
+    pub token: Option<String>,
+
+    /// SHA-256 fingerprint of the client's certificate, in hex (colons optional).
+    #[serde(default)]
+    pub certificate_sha256: Option<String>,
+
+    /// Bus request names this client may send, e.g. `"GetCurrentThroughput"`. A trailing `*`
+    /// matches a prefix (`"Get*"`), and `"*"` allows everything.
+    #[serde(default)]
+    pub allow: Vec<String>,
+}
+
+impl RemoteBusConfig {
+    /// Validates remote bus settings.
+    pub fn validate(&self) -> Result<(), String> {
+        if !self.enabled {
+            return Ok(());
+        }
+        if self.listen.parse::<SocketAddr>().is_err() {
+            return Err(format!(
+                "remote_bus.listen '{}' is not an address:port",
+                self.listen
+            ));
+        }
+        if self.cert_file.trim().is_empty() || self.key_file.trim().is_empty() {
+            return Err("remote_bus.cert_file and remote_bus.key_file are required".to_string());
+        }
+        if self.clients.is_empty() {
+            return Err("remote_bus needs at least one [[remote_bus.clients]] entry".to_string());
+        }
+        let mut names = HashSet::new();
+        for client in &self.clients {
+            let name = client.name.trim();
+            if name.is_empty() {
+                return Err("remote_bus.clients entries need a name".to_string());
+            }
+            if !names.insert(name) {
+                return Err(format!("remote_bus.clients name '{name}' is used twice"));
+            }
+            if client.token.is_none() && client.certificate_sha256.is_none() {
+                return Err(format!(
+                    "remote_bus client '{name}': set a token, a certificate_sha256, or both"
+                ));
+            }
+            if client.token.as_ref().is_some_and(|t| t.trim().len() < 16) {
+                return Err(format!(
+                    "remote_bus client '{name}': token must be at least 16 characters"
+                ));
+            }
+            if let Some(fingerprint) = &client.certificate_sha256 {
+                if self.client_ca_file.is_none() {
+                    return Err(format!(
+                        "remote_bus client '{name}': certificate_sha256 requires remote_bus.client_ca_file"
+                    ));
+                }
+                if normalize_fingerprint(fingerprint).len() != 64 {
+                    return Err(format!(
+                        "remote_bus client '{name}': certificate_sha256 must be 64 hex digits"
+                    ));
+                }
+            }
+            if client.allow.is_empty() {
+                return Err(format!(
+                    "remote_bus client '{name}': allow must list at least one request"
+                ));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Lowercase hex with separators removed, or an empty string if `value` isn't hex.
+pub fn normalize_fingerprint(value: &str) -> String {
+    let hex: String = value
+        .chars()
+        .filter(|c| !matches!(c, ':' | ' '))
+        .collect::<String>()
+        .to_ascii_lowercase();
+    if hex.chars().all(|c| c.is_ascii_hexdigit()) {
+        hex
+    } else {
+        String::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn client(token: Option<&str>, certificate_sha256: Option<&str>) -> RemoteBusClient {
+        RemoteBusClient {
+            name: "noc".to_string(),
+            token: token.map(str::to_string),
+            certificate_sha256: certificate_sha256.map(str::to_string),
+            allow: vec!["Get*".to_string()],
+        }
+    }
+
+    fn enabled(clients: Vec<RemoteBusClient>) -> RemoteBusConfig {
+        RemoteBusConfig {
+            enabled: true,
+            cert_file: "/etc/lqos/bus.crt".to_string(),
+            key_file: "/etc/lqos/bus.key".to_string(),
+            clients,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn validates_clients() {
+        assert!(RemoteBusConfig::default().validate().is_ok());
+        assert!(
+            enabled(vec![client(Some("0123456789abcdef0123"), None)])
+                .validate()
+                .is_ok()
+        );
+        assert!(enabled(vec![client(None, None)]).validate().is_err());
+        assert!(
+            enabled(vec![client(Some("short"), None)])
+                .validate()
+                .is_err()
+        );
+
+        let fingerprint = "AB:".repeat(31) + "AB";
+        let mut mtls = enabled(vec![client(None, Some(&fingerprint))]);
+        assert!(mtls.validate().is_err(), "needs client_ca_file");
+        mtls.client_ca_file = Some("/etc/lqos/clients-ca.pem".to_string());
+        assert!(mtls.validate().is_ok());
+    }
+}
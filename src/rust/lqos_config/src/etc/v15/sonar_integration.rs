@@ -1,7 +1,10 @@
 use allocative::Allocative;
+#[cfg(feature = "schema")]
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
 #[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Allocative, Default)]
+#[cfg_attr(feature = "schema", derive(JsonSchema))]
 pub struct SonarRecurringServiceRate {
     pub enabled: bool,
     pub service_name: String,
@@ -10,6 +13,7 @@ pub struct SonarRecurringServiceRate {
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Allocative)]
+#[cfg_attr(feature = "schema", derive(JsonSchema))]
 pub struct SonarIntegration {
     pub enable_sonar: bool,
     pub sonar_api_url: String,
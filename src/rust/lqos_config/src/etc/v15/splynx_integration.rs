@@ -1,7 +1,10 @@
 use allocative::Allocative;
+#[cfg(feature = "schema")]
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
 #[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Allocative)]
+#[cfg_attr(feature = "schema", derive(JsonSchema))]
 pub struct SplynxIntegration {
     #[serde(alias = "enable_spylnx")]
     pub enable_splynx: bool,
@@ -1,6 +1,8 @@
 //! StormGuard definitions (originally from ispConfig.py)
 
 use allocative::Allocative;
+#[cfg(feature = "schema")]
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
 fn default_false() -> bool {
@@ -97,6 +99,7 @@ fn default_active_ping_timeout_seconds() -> f32 {
 
 /// StormGuard evaluation strategy.
 #[derive(Clone, Copy, Serialize, Deserialize, Debug, PartialEq, Eq, Allocative)]
+#[cfg_attr(feature = "schema", derive(JsonSchema))]
 #[serde(rename_all = "snake_case")]
 pub enum StormguardStrategy {
     /// Legacy score-based evaluation.
@@ -109,6 +112,7 @@ pub enum StormguardStrategy {
 
 /// Configuration for the StormGuard module (auto-rate).
 #[derive(Clone, Serialize, Deserialize, Debug, PartialEq, Allocative)]
+#[cfg_attr(feature = "schema", derive(JsonSchema))]
 #[serde(default)]
 pub struct StormguardConfig {
     /// Whether StormGuard is enabled.
@@ -1,9 +1,18 @@
 //! Top-level configuration file for LibreQoS.
 
 use super::tuning::Tunables;
+use crate::etc::v15::alerting;
+use crate::etc::v15::change_control;
+use crate::etc::v15::federation;
+use crate::etc::v15::high_availability;
+use crate::etc::v15::notifications;
+use crate::etc::v15::oidc;
+use crate::etc::v15::remote_bus;
 use crate::etc::v15::stormguard;
 use crate::etc::v15::treeguard;
 use allocative::Allocative;
+#[cfg(feature = "schema")]
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 use sha2::Digest;
 use sha2::digest::Update;
@@ -33,6 +42,7 @@ fn default_rtt_red_ms() -> u32 {
 /// - `yellow_ms`: this point is yellow
 /// - `red_ms`: values at/above this are red
 #[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Allocative)]
+#[cfg_attr(feature = "schema", derive(JsonSchema))]
 pub struct RttThresholds {
     /// RTT at/below this value (milliseconds) is colored green.
     #[serde(default = "default_rtt_green_ms")]
@@ -57,6 +67,7 @@ impl Default for RttThresholds {
 
 /// Top-level configuration file for LibreQoS.
 #[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Allocative)]
+#[cfg_attr(feature = "schema", derive(JsonSchema))]
 pub struct Config {
     /// Version number for the configuration file.
     /// This will be set to "1.5". Versioning will make
@@ -158,6 +169,34 @@ pub struct Config {
     #[serde(default)]
     pub treeguard: treeguard::TreeguardConfig,
 
+    /// Approval queue for persisted changes proposed by StormGuard and TreeGuard.
+    #[serde(default)]
+    pub change_control: change_control::ChangeControlConfig,
+
+    /// OpenID Connect single sign-on for the web UI.
+    #[serde(default)]
+    pub oidc: oidc::OidcConfig,
+
+    /// Urgent-issue notification sinks and persistence.
+    #[serde(default)]
+    pub notifications: notifications::NotificationsConfig,
+
+    /// Threshold alerting rules over node and circuit metrics.
+    #[serde(default)]
+    pub alerting: alerting::AlertingConfig,
+
+    /// Peer shapers shown in this node manager's federation view.
+    #[serde(default)]
+    pub federation: federation::FederationConfig,
+
+    /// Active/standby pairing with another shaper.
+    #[serde(default)]
+    pub high_availability: high_availability::HighAvailabilityConfig,
+
+    /// Bus access over TLS for remote tools.
+    #[serde(default)]
+    pub remote_bus: remote_bus::RemoteBusConfig,
+
     /// Disable ICMP Ping Monitoring for Devices in the hosts view
     pub disable_icmp_ping: Option<bool>,
 
@@ -196,6 +235,23 @@ impl Config {
 
     /// Test is a configuration is valid.
     pub fn validate(&self) -> Result<(), String> {
+        self.validate_general()?;
+        if let Some(stormguard) = &self.stormguard {
+            stormguard.validate()?;
+        }
+        self.treeguard.validate()?;
+        self.change_control.validate()?;
+        self.oidc.validate()?;
+        self.notifications.validate()?;
+        self.alerting.validate()?;
+        self.federation.validate()?;
+        self.high_availability.validate()?;
+        self.remote_bus.validate()?;
+        Ok(())
+    }
+
+    /// Checks the top-level settings, leaving out the per-feature sections.
+    pub(crate) fn validate_general(&self) -> Result<(), String> {
         if self.bridge.is_some() && self.single_interface.is_some() {
             return Err(
                 "Configuration file may not contain both a bridge and a single-interface section."
@@ -225,22 +281,21 @@ impl Config {
         if self.queues.default_sqm.trim().is_empty() {
             return Err("default_sqm cannot be empty. Please specify a qdisc type (e.g., 'cake diffserv4' or 'fq_codel')".to_string());
         }
-        if let Some(stormguard) = &self.stormguard {
-            stormguard.validate()?;
-        }
-        self.treeguard.validate()?;
         Ok(())
     }
 
-    /// Loads a config file from a string (used for testing only)
-    #[allow(dead_code)]
+    /// Loads a config file from a string, e.g. in tests or from a support dump
     pub fn load_from_string(s: &str) -> Result<Self, String> {
-        let normalized = normalize_splynx_compat_keys(s)?;
-        let config: Config =
-            toml::from_str(&normalized).map_err(|e| format!("Error parsing config: {}", e))?;
+        let config = Self::parse_unvalidated(s)?;
         config.validate()?;
         Ok(config)
     }
+
+    /// Parses a config file without validating its values
+    pub(crate) fn parse_unvalidated(s: &str) -> Result<Self, String> {
+        let normalized = normalize_splynx_compat_keys(s)?;
+        toml::from_str(&normalized).map_err(|e| format!("Error parsing config: {}", e))
+    }
 }
 
 /// Normalizes historical misspellings of Splynx keys in the TOML configuration.
@@ -317,6 +372,13 @@ impl Default for Config {
             webserver_listen: None,
             stormguard: None,
             treeguard: treeguard::TreeguardConfig::default(),
+            change_control: change_control::ChangeControlConfig::default(),
+            oidc: oidc::OidcConfig::default(),
+            notifications: notifications::NotificationsConfig::default(),
+            alerting: alerting::AlertingConfig::default(),
+            federation: federation::FederationConfig::default(),
+            high_availability: high_availability::HighAvailabilityConfig::default(),
+            remote_bus: remote_bus::RemoteBusConfig::default(),
             disable_icmp_ping: Some(false),
             exclude_efficiency_cores: true,
             enable_circuit_heatmaps: true,
@@ -518,6 +580,9 @@ mod test {
         assert!(cfg.treeguard.links.top_level_auto_virtualize);
         assert!(cfg.treeguard.circuits.enabled);
         assert!(cfg.treeguard.circuits.all_circuits);
+        assert!(!cfg.treeguard.rebalance.enabled);
+        assert!(!cfg.treeguard.adaptive_sqm.enabled);
+        assert_eq!(cfg.treeguard.adaptive_sqm.profiles.len(), 4);
     }
 
     #[test]
@@ -531,6 +596,190 @@ mod test {
         assert!(config.treeguard.circuits.all_circuits);
     }
 
+    #[test]
+    fn change_control_defaults_to_direct_writes() {
+        let stripped = remove_sections(include_str!("example.toml"), &["change_control"]);
+        let config = Config::load_from_string(&stripped)
+            .expect("Config without change_control should still deserialize");
+        assert!(!config.change_control.require_approval);
+        assert!(config.change_control.auto_approve_sites.is_empty());
+
+        let mut cfg = Config::default();
+        cfg.change_control.auto_approve_max_change_pct = 150.0;
+        assert!(cfg.validate().is_err());
+    }
+
+    #[test]
+    fn oidc_defaults_to_disabled_and_maps_roles() {
+        let stripped = remove_sections(include_str!("example.toml"), &["oidc"]);
+        let config = Config::load_from_string(&stripped)
+            .expect("Config without oidc should still deserialize");
+        assert!(!config.oidc.enabled);
+
+        let mut cfg = Config::default();
+        cfg.oidc.enabled = true;
+        assert!(cfg.validate().is_err());
+        cfg.oidc.issuer = "https://idp.example.com/realms/isp".to_string();
+        cfg.oidc.client_id = "libreqos".to_string();
+        cfg.oidc.redirect_url = "https://shaper.example.com/oidc/callback".to_string();
+        assert!(cfg.validate().is_ok());
+
+        cfg.oidc.admin_values = vec!["noc-admins".to_string()];
+        cfg.oidc.read_only_values = vec!["noc".to_string()];
+        let role = |values: &[&str]| {
+            let values: Vec<String> = values.iter().map(|v| v.to_string()).collect();
+            cfg.oidc.role_for_claim_values(&values)
+        };
+        assert_eq!(role(&["noc", "noc-admins"]), Some(crate::UserRole::Admin));
+        assert_eq!(role(&["noc"]), Some(crate::UserRole::ReadOnly));
+        assert_eq!(role(&["sales"]), None);
+    }
+
+    #[test]
+    fn notifications_parse_sinks_and_validate() {
+        let stripped = remove_sections(include_str!("example.toml"), &["notifications"]);
+        let config = Config::load_from_string(&stripped)
+            .expect("Config without notifications should still deserialize");
+        assert!(config.notifications.persist_urgent_issues);
+        assert!(config.notifications.sinks.is_empty());
+
+        let with_sinks = format!(
+            "{stripped}\n{}",
+            r#"
+[notifications]
+persist_urgent_issues = false
+
+[[notifications.sinks]]
+name = "noc-slack"
+type = "slack"
+url = "https://hooks.slack.com/services/T000/B000/XXXX"
+min_severity = "error"
+
+[[notifications.sinks]]
+name = "syslog"
+type = "syslog"
+address = "192.0.2.10:514"
+sources = ["scheduler", "system"]
+"#
+        );
+        let config = Config::load_from_string(&with_sinks).expect("Sinks should deserialize");
+        assert!(!config.notifications.persist_urgent_issues);
+        assert_eq!(config.notifications.sinks.len(), 2);
+        assert_eq!(
+            config.notifications.sinks[0].min_severity,
+            notifications::NotificationSeverity::Error
+        );
+        assert_eq!(config.notifications.sinks[1].min_interval_seconds, 900);
+        assert!(matches!(
+            config.notifications.sinks[1].target,
+            notifications::NotificationTarget::Syslog { facility: 16, .. }
+        ));
+        assert!(config.notifications.validate().is_ok());
+
+        let mut cfg = config.clone();
+        cfg.notifications.sinks[1].name = "noc-slack".to_string();
+        assert!(cfg.notifications.validate().is_err());
+        let mut cfg = config.clone();
+        cfg.notifications.sinks[1].sources = vec!["weather".to_string()];
+        assert!(cfg.notifications.validate().is_err());
+        let mut cfg = config;
+        cfg.notifications.sinks[0].target = notifications::NotificationTarget::Webhook {
+            url: "ftp://example.com".to_string(),
+        };
+        assert!(cfg.notifications.validate().is_err());
+    }
+
+    #[test]
+    fn alerting_rules_parse_and_validate() {
+        let stripped = remove_sections(include_str!("example.toml"), &["alerting"]);
+        let config = Config::load_from_string(&stripped)
+            .expect("Config without alerting should still deserialize");
+        assert!(config.alerting.enabled);
+        assert!(config.alerting.rules.is_empty());
+
+        let with_rules = format!(
+            "{stripped}\n{}",
+            r#"
+[[alerting.rules]]
+name = "site-rtt"
+scope = "node"
+targets = ["Tower 1"]
+metric = "rtt_p95_ms"
+threshold = 80.0
+hold_down_seconds = 600
+
+[[alerting.rules]]
+name = "backhaul-full"
+scope = "node"
+metric = "utilization_pct"
+direction = "down"
+threshold = 95.0
+severity = "error"
+"#
+        );
+        let config = Config::load_from_string(&with_rules).expect("Rules should deserialize");
+        assert_eq!(config.alerting.rules.len(), 2);
+        let rtt = &config.alerting.rules[0];
+        assert_eq!(rtt.direction, alerting::AlertDirection::Either);
+        assert_eq!(rtt.comparison, alerting::AlertComparison::Above);
+        assert_eq!(rtt.recovery_seconds, 300);
+        assert!(config.alerting.validate().is_ok());
+
+        let mut cfg = config.clone();
+        cfg.alerting.rules[0].scope = alerting::AlertScope::Circuit;
+        assert!(cfg.alerting.validate().is_err());
+        let mut cfg = config;
+        cfg.alerting.rules[1].name = "site-rtt".to_string();
+        assert!(cfg.alerting.validate().is_err());
+    }
+
+    #[test]
+    fn federation_defaults_to_disabled_and_validates_peers() {
+        let stripped = remove_sections(include_str!("example.toml"), &["federation"]);
+        let config = Config::load_from_string(&stripped)
+            .expect("Config without federation should still deserialize");
+        assert!(!config.federation.enabled);
+
+        let mut cfg = Config::default();
+        cfg.federation.enabled = true;
+        cfg.federation.peers.push(federation::FederationPeer {
+            name: "pop-east".to_string(),
+            url: "https://pop-east.example.net:9123".to_string(),
+            api_token: "lqt_example".to_string(),
+            verify_tls: true,
+            ca_certificate: None,
+        });
+        assert!(cfg.federation.validate().is_ok());
+
+        cfg.federation.peers[0].url = "http://pop-east.example.net:9123".to_string();
+        assert!(cfg.federation.validate().is_err());
+        cfg.federation.peers[0].url = "https://pop-east.example.net:9123".to_string();
+        cfg.federation.peers[0].api_token.clear();
+        assert!(cfg.federation.validate().is_err());
+    }
+
+    #[test]
+    fn high_availability_defaults_to_disabled() {
+        let stripped = remove_sections(include_str!("example.toml"), &["high_availability"]);
+        let config = Config::load_from_string(&stripped)
+            .expect("Config without high_availability should still deserialize");
+        assert!(!config.high_availability.enabled);
+        assert_eq!(
+            config.high_availability.role,
+            high_availability::HaRole::Active
+        );
+        assert!(config.high_availability.validate().is_ok());
+    }
+
+    #[test]
+    fn remote_bus_defaults_to_disabled() {
+        let stripped = remove_sections(include_str!("example.toml"), &["remote_bus"]);
+        let config = Config::load_from_string(&stripped)
+            .expect("Config without remote_bus should still deserialize");
+        assert!(!config.remote_bus.enabled);
+        assert!(config.remote_bus.clients.is_empty());
+    }
+
     #[test]
     fn load_example_without_stormguard_section_deserializes() {
         let stripped = remove_sections(include_str!("example.toml"), &["stormguard"]);
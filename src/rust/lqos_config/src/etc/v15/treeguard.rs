@@ -1,6 +1,8 @@
 //! TreeGuard configuration definitions.
 
 use allocative::Allocative;
+#[cfg(feature = "schema")]
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
 fn default_enabled() -> bool {
@@ -71,8 +73,74 @@ fn default_min_score() -> f32 {
     70.0
 }
 
+fn default_rebalance_imbalance_pct() -> u8 {
+    25
+}
+
+fn default_rebalance_min_hot_cpu_pct() -> u8 {
+    50
+}
+
+fn default_rebalance_sustain_minutes() -> u32 {
+    10
+}
+
+fn default_rebalance_hysteresis_pct() -> f32 {
+    3.0
+}
+
+fn default_rebalance_site_cooldown_minutes() -> u32 {
+    60
+}
+
+fn default_rebalance_max_moves_per_run() -> u32 {
+    1
+}
+
+fn default_rebalance_min_minutes_between_runs() -> u32 {
+    30
+}
+
+fn default_adaptive_sqm_profiles() -> Vec<String> {
+    vec![
+        "cake:diffserv4".to_string(),
+        "cake:besteffort".to_string(),
+        "cake:diffserv4:ack-filter".to_string(),
+        "cake:besteffort:ack-filter".to_string(),
+    ]
+}
+
+fn default_adaptive_sqm_poor_qoo_score() -> f32 {
+    60.0
+}
+
+fn default_adaptive_sqm_min_util_pct() -> f32 {
+    50.0
+}
+
+fn default_adaptive_sqm_sustain_minutes() -> u32 {
+    5
+}
+
+fn default_adaptive_sqm_evaluation_minutes() -> u32 {
+    15
+}
+
+fn default_adaptive_sqm_min_improvement() -> f32 {
+    5.0
+}
+
+fn default_adaptive_sqm_retry_cooldown_minutes() -> u32 {
+    1440
+}
+
+fn default_adaptive_sqm_max_active_experiments() -> u32 {
+    8
+}
+
 /// CPU modes supported by TreeGuard.
 #[derive(Clone, Copy, Serialize, Deserialize, Debug, PartialEq, Eq, Default, Allocative)]
+#[cfg_attr(feature = "schema", derive(JsonSchema))]
 #[serde(rename_all = "snake_case")]
 pub enum TreeguardCpuMode {
     /// Consider CPU thresholds when making decisions.
@@ -84,6 +152,7 @@ pub enum TreeguardCpuMode {
 
 /// Top-level TreeGuard configuration.
 #[derive(Clone, Serialize, Deserialize, Debug, PartialEq, Allocative)]
+#[cfg_attr(feature = "schema", derive(JsonSchema))]
 #[serde(default)]
 pub struct TreeguardConfig {
     /// Enables TreeGuard globally.
@@ -103,6 +172,10 @@ pub struct TreeguardConfig {
     pub circuits: TreeguardCircuitsConfig,
     /// QoO guardrail settings.
     pub qoo: TreeguardQooConfig,
+    /// Top-level site CPU rebalancing settings.
+    pub rebalance: TreeguardRebalanceConfig,
+    /// Per-circuit adaptive SQM profile settings.
+    pub adaptive_sqm: TreeguardAdaptiveSqmConfig,
 }
 
 impl Default for TreeguardConfig {
@@ -115,12 +188,15 @@ impl Default for TreeguardConfig {
             links: TreeguardLinksConfig::default(),
             circuits: TreeguardCircuitsConfig::default(),
             qoo: TreeguardQooConfig::default(),
+            rebalance: TreeguardRebalanceConfig::default(),
+            adaptive_sqm: TreeguardAdaptiveSqmConfig::default(),
         }
     }
 }
 
 /// TreeGuard CPU guardrails.
 #[derive(Clone, Serialize, Deserialize, Debug, PartialEq, Allocative)]
+#[cfg_attr(feature = "schema", derive(JsonSchema))]
 #[serde(default)]
 pub struct TreeguardCpuConfig {
     /// How CPU participates in decision making.
@@ -145,6 +221,7 @@ impl Default for TreeguardCpuConfig {
 
 /// TreeGuard node virtualization settings.
 #[derive(Clone, Serialize, Deserialize, Debug, PartialEq, Allocative)]
+#[cfg_attr(feature = "schema", derive(JsonSchema))]
 #[serde(default)]
 pub struct TreeguardLinksConfig {
     /// Enables TreeGuard management of node virtualization.
@@ -205,6 +282,7 @@ impl Default for TreeguardLinksConfig {
 
 /// TreeGuard circuit management settings.
 #[derive(Clone, Serialize, Deserialize, Debug, PartialEq, Allocative)]
+#[cfg_attr(feature = "schema", derive(JsonSchema))]
 #[serde(default)]
 pub struct TreeguardCircuitsConfig {
     /// Enables TreeGuard circuit management.
@@ -265,6 +343,7 @@ impl Default for TreeguardCircuitsConfig {
 
 /// TreeGuard QoO protection settings.
 #[derive(Clone, Serialize, Deserialize, Debug, PartialEq, Allocative)]
+#[cfg_attr(feature = "schema", derive(JsonSchema))]
 #[serde(default)]
 pub struct TreeguardQooConfig {
     /// Enables QoO protection.
@@ -284,6 +363,110 @@ impl Default for TreeguardQooConfig {
     }
 }
 
+/// TreeGuard top-level site rebalancing settings.
+///
+/// When enabled, TreeGuard watches per-CPU softirq load and moves top-level
+/// sites between MQ queues (live, via Bakery migrations) once an imbalance has
+/// persisted long enough.
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq, Allocative)]
+#[cfg_attr(feature = "schema", derive(JsonSchema))]
+#[serde(default)]
+pub struct TreeguardRebalanceConfig {
+    /// Enables automatic rebalancing of top-level sites.
+    #[serde(default = "default_false")]
+    pub enabled: bool,
+    /// Minimum spread (max - min) in per-CPU softirq percent to count as imbalanced.
+    #[serde(default = "default_rebalance_imbalance_pct")]
+    pub imbalance_pct: u8,
+    /// The busiest shaping CPU must be at least this busy before rebalancing.
+    #[serde(default = "default_rebalance_min_hot_cpu_pct")]
+    pub min_hot_cpu_pct: u8,
+    /// How long the imbalance must persist before a replan is attempted.
+    #[serde(default = "default_rebalance_sustain_minutes")]
+    pub sustain_minutes: u32,
+    /// Planner hysteresis: a move must improve queue balance by at least this percent.
+    #[serde(default = "default_rebalance_hysteresis_pct")]
+    pub hysteresis_pct: f32,
+    /// Minimum time before the same site may be moved again.
+    #[serde(default = "default_rebalance_site_cooldown_minutes")]
+    pub site_cooldown_minutes: u32,
+    /// Maximum number of top-level sites moved by one replan.
+    #[serde(default = "default_rebalance_max_moves_per_run")]
+    pub max_moves_per_run: u32,
+    /// Minimum time between replans.
+    #[serde(default = "default_rebalance_min_minutes_between_runs")]
+    pub min_minutes_between_runs: u32,
+}
+
+impl Default for TreeguardRebalanceConfig {
+    fn default() -> Self {
+        Self {
+            enabled: default_false(),
+            imbalance_pct: default_rebalance_imbalance_pct(),
+            min_hot_cpu_pct: default_rebalance_min_hot_cpu_pct(),
+            sustain_minutes: default_rebalance_sustain_minutes(),
+            hysteresis_pct: default_rebalance_hysteresis_pct(),
+            site_cooldown_minutes: default_rebalance_site_cooldown_minutes(),
+            max_moves_per_run: default_rebalance_max_moves_per_run(),
+            min_minutes_between_runs: default_rebalance_min_minutes_between_runs(),
+        }
+    }
+}
+
+/// TreeGuard per-circuit adaptive SQM settings.
+///
+/// When enabled, TreeGuard watches each CAKE circuit's QoO score while it is
+/// under load. If quality stays poor, it live-applies the next untried CAKE
+/// profile, measures QoO again, and keeps the profile only if it helped.
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq, Allocative)]
+#[cfg_attr(feature = "schema", derive(JsonSchema))]
+#[serde(default)]
+pub struct TreeguardAdaptiveSqmConfig {
+    /// Enables adaptive SQM profile experiments.
+    #[serde(default = "default_false")]
+    pub enabled: bool,
+    /// Candidate CAKE profiles, tried in order (e.g. `cake:besteffort:ack-filter`).
+    #[serde(default = "default_adaptive_sqm_profiles")]
+    pub profiles: Vec<String>,
+    /// QoO score below which a loaded circuit is considered poor.
+    #[serde(default = "default_adaptive_sqm_poor_qoo_score")]
+    pub poor_qoo_score: f32,
+    /// Utilization (percent of the circuit ceiling) required for QoO to count.
+    #[serde(default = "default_adaptive_sqm_min_util_pct")]
+    pub min_util_pct: f32,
+    /// How long QoO must stay poor under load before an experiment starts.
+    #[serde(default = "default_adaptive_sqm_sustain_minutes")]
+    pub sustain_minutes: u32,
+    /// How long a profile runs before it is judged.
+    #[serde(default = "default_adaptive_sqm_evaluation_minutes")]
+    pub evaluation_minutes: u32,
+    /// Minimum QoO gain over the baseline required to keep a profile.
+    #[serde(default = "default_adaptive_sqm_min_improvement")]
+    pub min_improvement: f32,
+    /// Wait after a reverted experiment before trying again on the same circuit.
+    #[serde(default = "default_adaptive_sqm_retry_cooldown_minutes")]
+    pub retry_cooldown_minutes: u32,
+    /// Maximum number of circuits experimenting at the same time.
+    #[serde(default = "default_adaptive_sqm_max_active_experiments")]
+    pub max_active_experiments: u32,
+}
+
+impl Default for TreeguardAdaptiveSqmConfig {
+    fn default() -> Self {
+        Self {
+            enabled: default_false(),
+            profiles: default_adaptive_sqm_profiles(),
+            poor_qoo_score: default_adaptive_sqm_poor_qoo_score(),
+            min_util_pct: default_adaptive_sqm_min_util_pct(),
+            sustain_minutes: default_adaptive_sqm_sustain_minutes(),
+            evaluation_minutes: default_adaptive_sqm_evaluation_minutes(),
+            min_improvement: default_adaptive_sqm_min_improvement(),
+            retry_cooldown_minutes: default_adaptive_sqm_retry_cooldown_minutes(),
+            max_active_experiments: default_adaptive_sqm_max_active_experiments(),
+        }
+    }
+}
+
 impl TreeguardConfig {
     /// Validates TreeGuard configuration values and cross-field relationships.
     pub fn validate(&self) -> Result<(), String> {
@@ -301,6 +484,8 @@ impl TreeguardConfig {
         self.links.validate()?;
         self.circuits.validate()?;
         self.qoo.validate()?;
+        self.rebalance.validate()?;
+        self.adaptive_sqm.validate()?;
 
         Ok(())
     }
@@ -341,6 +526,44 @@ impl TreeguardQooConfig {
     }
 }
 
+impl TreeguardRebalanceConfig {
+    fn validate(&self) -> Result<(), String> {
+        validate_percent_u8("treeguard.rebalance.imbalance_pct", self.imbalance_pct)?;
+        validate_percent_u8("treeguard.rebalance.min_hot_cpu_pct", self.min_hot_cpu_pct)?;
+        validate_percent_f32("treeguard.rebalance.hysteresis_pct", self.hysteresis_pct)?;
+        validate_non_zero(
+            "treeguard.rebalance.max_moves_per_run",
+            self.max_moves_per_run as u64,
+        )?;
+        Ok(())
+    }
+}
+
+impl TreeguardAdaptiveSqmConfig {
+    fn validate(&self) -> Result<(), String> {
+        if let Some(bad) = self
+            .profiles
+            .iter()
+            .find(|p| crate::cake_profile_options(p).is_none())
+        {
+            return Err(format!(
+                "treeguard.adaptive_sqm.profiles contains invalid profile '{bad}' (expected e.g. 'cake:besteffort:ack-filter')"
+            ));
+        }
+        validate_percent_f32("treeguard.adaptive_sqm.poor_qoo_score", self.poor_qoo_score)?;
+        validate_percent_f32("treeguard.adaptive_sqm.min_util_pct", self.min_util_pct)?;
+        validate_percent_f32(
+            "treeguard.adaptive_sqm.min_improvement",
+            self.min_improvement,
+        )?;
+        validate_non_zero(
+            "treeguard.adaptive_sqm.evaluation_minutes",
+            self.evaluation_minutes as u64,
+        )?;
+        Ok(())
+    }
+}
+
 fn validate_percent_u8(name: &str, value: u8) -> Result<(), String> {
     if value > 100 {
         return Err(format!("{name} must be between 0 and 100"));
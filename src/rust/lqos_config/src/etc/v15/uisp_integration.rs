@@ -1,7 +1,10 @@
 use allocative::Allocative;
+#[cfg(feature = "schema")]
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
 #[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Allocative)]
+#[cfg_attr(feature = "schema", derive(JsonSchema))]
 pub struct UispIntegration {
     pub enable_uisp: bool,
     pub token: String,
@@ -31,6 +34,7 @@ fn default_ignore_calculated_capacity() -> bool {
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Allocative)]
+#[cfg_attr(feature = "schema", derive(JsonSchema))]
 pub struct ExceptionCpe {
     pub cpe: String,
     pub parent: String,
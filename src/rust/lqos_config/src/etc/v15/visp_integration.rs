@@ -1,4 +1,6 @@
 use allocative::Allocative;
+#[cfg(feature = "schema")]
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
 fn default_token_url() -> String {
@@ -39,6 +41,7 @@ fn default_service_type_allowlist() -> Vec<String> {
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Allocative)]
+#[cfg_attr(feature = "schema", derive(JsonSchema))]
 pub struct VispIntegration {
     pub enable_visp: bool,
 
@@ -1,7 +1,10 @@
 use allocative::Allocative;
+#[cfg(feature = "schema")]
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
 #[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Default, Allocative)]
+#[cfg_attr(feature = "schema", derive(JsonSchema))]
 pub struct WispGateIntegration {
     pub enable_wispgate: bool,
     pub wispgate_api_token: String,
@@ -17,26 +17,38 @@ mod program_control;
 mod qoo_profiles;
 mod shaped_devices;
 
-pub use authentication::{AuthenticatedUser, UserRole, WebUser, WebUsers};
+pub use authentication::{
+    ApiToken, ApiTokenScope, AuthenticatedUser, AuthenticationError, Permission, RoleDefinition,
+    TotpEnrollment, TotpProvisioning, UserAccess, UserRole, WebUser, WebUsers, verify_api_token_in,
+};
 pub use circuit_ethernet_metadata::{
     CIRCUIT_ETHERNET_METADATA_FILENAME, CircuitEthernetMetadata, CircuitEthernetMetadataFile,
 };
 pub use cpu_topology::{
     CpuListParseError, ShapingCpuDetection, ShapingCpuSource, detect_shaping_cpus,
 };
+#[cfg(feature = "schema")]
+pub use etc::config_json_schema;
 pub use etc::{
-    BridgeConfig, Config, LazyQueueMode, QueueMode, RttThresholds, SingleInterfaceConfig,
-    StormguardConfig, StormguardStrategy, TreeguardCircuitsConfig, TreeguardConfig,
-    TreeguardCpuConfig, TreeguardCpuMode, TreeguardLinksConfig, TreeguardQooConfig, Tunables,
-    clear_cached_config, disable_xdp_bridge, enable_long_term_stats, load_config,
+    AlertComparison, AlertDirection, AlertMetric, AlertRule, AlertScope, AlertingConfig,
+    BridgeConfig, ChangeControlConfig, Config, ConfigChange, ConfigIssue, ConfigIssueSeverity,
+    FederationConfig, FederationPeer, HaRole, HighAvailabilityConfig, LazyQueueMode,
+    MigrationPreview, NOTIFICATION_SOURCES, NotificationSeverity, NotificationSink,
+    NotificationTarget, NotificationsConfig, OidcConfig, QueueMode, RemoteBusClient,
+    RemoteBusConfig, RttThresholds, SingleInterfaceConfig, SmtpSecurity, StormguardConfig,
+    StormguardStrategy, SyslogProtocol, TreeguardAdaptiveSqmConfig, TreeguardCircuitsConfig,
+    TreeguardConfig, TreeguardCpuConfig, TreeguardCpuMode, TreeguardLinksConfig,
+    TreeguardQooConfig, TreeguardRebalanceConfig, Tunables, check_config, clear_cached_config,
+    diff_config_documents, disable_xdp_bridge, enable_long_term_stats, load_config,
+    migrate_config_file, normalize_fingerprint, preview_config_migration,
     treeguard_cpu_mode_migration_notice, update_config,
 };
 pub use network_json::{NetworkJson, NetworkJsonNode, NetworkJsonTransport};
 pub use planner::{
     CircuitIdentityAssignment, CircuitIdentityGroupInput, ClassIdentityPlannerConstraints,
-    ClassIdentityPlannerOutput, PlannerCircuitIdentityState, PlannerMinorReservations,
-    PlannerSiteIdentityState, SiteIdentityAssignment, SiteIdentityInput, TopLevelPlannerItem,
-    TopLevelPlannerMode, TopLevelPlannerOutput, TopLevelPlannerParams,
+    ClassIdentityPlannerOutput, FIRST_CLASS_MINOR, PlannerCircuitIdentityState,
+    PlannerMinorReservations, PlannerSiteIdentityState, SiteIdentityAssignment, SiteIdentityInput,
+    TopLevelPlannerItem, TopLevelPlannerMode, TopLevelPlannerOutput, TopLevelPlannerParams,
     build_class_identity_reservations, plan_class_identities,
     plan_class_identities_with_constraints, plan_top_level_assignments,
 };
@@ -45,7 +57,10 @@ pub use qoo_profiles::{
     DEFAULT_QOO_PROFILE_ID, QooProfileInfo, QooProfilesError, active_qoo_profile,
     list_qoo_profiles, load_qoo_profiles_file,
 };
-pub use shaped_devices::{ConfigShapedDevices, ShapedDevice};
+pub use shaped_devices::{
+    CAKE_PROFILE_OPTIONS, ConfigShapedDevices, ShapedDevice, cake_profile_options,
+    is_valid_sqm_direction_token,
+};
 
 /// Used as a constant in determining buffer preallocation
 pub const SUPPORTED_CUSTOMERS: usize = 100_000;
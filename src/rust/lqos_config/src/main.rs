@@ -0,0 +1,232 @@
+//! `lqos_config`: review configuration migrations, check `/etc/lqos.conf` section by section,
+//! and export a JSON schema for editors.
+
+use clap::{Parser, Subcommand};
+use lqos_config::{
+    ConfigChange, ConfigIssue, ConfigIssueSeverity, check_config, config_json_schema,
+    diff_config_documents, migrate_config_file, preview_config_migration,
+};
+use serde::Serialize;
+use std::io::{BufRead, IsTerminal, Write};
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+use toml_edit::DocumentMut;
+
+#[derive(Parser)]
+#[command(name = "lqos_config")]
+#[command(
+    about = "Migrate, check and describe the LibreQoS configuration",
+    version
+)]
+struct Cli {
+    /// Configuration file (defaults to $LQOS_CONFIG, then /etc/lqos.conf)
+    #[arg(long, global = true)]
+    config: Option<PathBuf>,
+
+    /// Print JSON instead of text
+    #[arg(long, global = true)]
+    json: bool,
+
+    #[command(subcommand)]
+    command: Commands,
+}
+
+#[derive(Subcommand)]
+enum Commands {
+    /// Show what upgrading the configuration file changes, and optionally apply it
+    Migrate {
+        /// Write the migrated file (the original is backed up first)
+        #[arg(long)]
+        apply: bool,
+        /// Apply without asking for confirmation
+        #[arg(long, requires = "apply")]
+        yes: bool,
+    },
+    /// Check every section of the configuration file
+    Validate {
+        /// Don't check that the configured interfaces exist, e.g. for a file from another shaper
+        #[arg(long)]
+        skip_interfaces: bool,
+    },
+    /// Print a JSON schema of the configuration file, for editor autocompletion
+    Schema {
+        /// Write to this file instead of stdout
+        #[arg(long)]
+        output: Option<PathBuf>,
+    },
+}
+
+#[derive(Serialize)]
+struct MigrationReport<'a> {
+    config: &'a str,
+    steps: Vec<String>,
+    changes: Vec<ConfigChange>,
+    issues: Vec<ConfigIssue>,
+    applied: bool,
+}
+
+#[derive(Serialize)]
+struct ValidationReport<'a> {
+    config: &'a str,
+    issues: Vec<ConfigIssue>,
+}
+
+fn main() -> ExitCode {
+    let cli = Cli::parse();
+    let config = cli
+        .config
+        .map(|path| path.display().to_string())
+        .or_else(|| std::env::var("LQOS_CONFIG").ok())
+        .unwrap_or_else(|| "/etc/lqos.conf".to_string());
+
+    let result = match cli.command {
+        Commands::Migrate { apply, yes } => migrate(&config, cli.json, apply, yes),
+        Commands::Validate { skip_interfaces } => validate(&config, cli.json, skip_interfaces),
+        Commands::Schema { output } => schema(output.as_deref()),
+    };
+    match result {
+        Ok(code) => code,
+        Err(e) => {
+            eprintln!("{e}");
+            ExitCode::from(2)
+        }
+    }
+}
+
+fn interface_exists(name: &str) -> bool {
+    Path::new("/sys/class/net").join(name).exists()
+}
+
+/// 1 if any check failed, 0 if there were only warnings
+fn exit_code(issues: &[ConfigIssue]) -> ExitCode {
+    if issues
+        .iter()
+        .any(|issue| issue.severity == ConfigIssueSeverity::Error)
+    {
+        ExitCode::FAILURE
+    } else {
+        ExitCode::SUCCESS
+    }
+}
+
+fn migrate(config: &str, json: bool, apply: bool, yes: bool) -> Result<ExitCode, String> {
+    let preview = preview_config_migration(Some(config)).map_err(|e| e.to_string())?;
+    let parse = |raw: &str| {
+        raw.parse::<DocumentMut>()
+            .map_err(|e| format!("Unable to parse {config}: {e}"))
+    };
+    let changes = diff_config_documents(&parse(&preview.original)?, &parse(&preview.migrated)?);
+    let issues = check_config(&preview.migrated, Some(&interface_exists))?;
+
+    let mut report = MigrationReport {
+        config,
+        steps: preview.steps,
+        changes,
+        issues,
+        applied: false,
+    };
+    if !json {
+        print_migration(&report);
+    }
+
+    if apply && !report.steps.is_empty() {
+        if !yes && !confirm(json)? {
+            println!("Not applied.");
+            return Ok(ExitCode::FAILURE);
+        }
+        migrate_config_file(Some(config)).map_err(|e| e.to_string())?;
+        report.applied = true;
+        if !json {
+            println!("Migrated {config}.");
+        }
+    }
+
+    if json {
+        print_json(&report)?;
+    }
+    Ok(exit_code(&report.issues))
+}
+
+fn print_migration(report: &MigrationReport) {
+    if report.steps.is_empty() {
+        println!("{} is up to date; no migration needed.", report.config);
+    } else {
+        println!("Migrating {} will:", report.config);
+        for (index, step) in report.steps.iter().enumerate() {
+            println!("  {}. {step}", index + 1);
+        }
+        println!();
+        println!("Changes ({}):", report.changes.len());
+        for change in &report.changes {
+            println!("  {change}");
+        }
+    }
+    println!();
+    print_issues(&report.issues);
+}
+
+/// Asks on the terminal whether to go ahead. Without a terminal there is nobody to ask.
+fn confirm(json: bool) -> Result<bool, String> {
+    if json || !std::io::stdin().is_terminal() {
+        return Err("Refusing to apply without a terminal to confirm on; pass --yes".to_string());
+    }
+    print!("Apply these changes? [y/N] ");
+    std::io::stdout().flush().map_err(|e| e.to_string())?;
+    let mut answer = String::new();
+    std::io::stdin()
+        .lock()
+        .read_line(&mut answer)
+        .map_err(|e| e.to_string())?;
+    Ok(matches!(answer.trim(), "y" | "Y" | "yes"))
+}
+
+fn validate(config: &str, json: bool, skip_interfaces: bool) -> Result<ExitCode, String> {
+    let raw =
+        std::fs::read_to_string(config).map_err(|e| format!("Unable to read {config}: {e}"))?;
+    let interfaces: Option<&dyn Fn(&str) -> bool> = if skip_interfaces {
+        None
+    } else {
+        Some(&interface_exists)
+    };
+    let issues = check_config(&raw, interfaces).map_err(|e| {
+        format!("{e}\nIf {config} is from an older version, run `lqos_config migrate` first.")
+    })?;
+
+    let report = ValidationReport { config, issues };
+    if json {
+        print_json(&report)?;
+    } else {
+        print_issues(&report.issues);
+    }
+    Ok(exit_code(&report.issues))
+}
+
+fn print_issues(issues: &[ConfigIssue]) {
+    if issues.is_empty() {
+        println!("All configuration checks passed.");
+        return;
+    }
+    for issue in issues {
+        let severity = match issue.severity {
+            ConfigIssueSeverity::Error => "error",
+            ConfigIssueSeverity::Warning => "warning",
+        };
+        println!("{severity:<8}[{}] {}", issue.section, issue.message);
+    }
+}
+
+fn schema(output: Option<&Path>) -> Result<ExitCode, String> {
+    let schema = serde_json::to_string_pretty(&config_json_schema()).map_err(|e| e.to_string())?;
+    match output {
+        Some(path) => std::fs::write(path, schema + "\n")
+            .map_err(|e| format!("Unable to write {}: {e}", path.display()))?,
+        None => println!("{schema}"),
+    }
+    Ok(ExitCode::SUCCESS)
+}
+
+fn print_json(report: &impl Serialize) -> Result<(), String> {
+    let json = serde_json::to_string_pretty(report).map_err(|e| e.to_string())?;
+    println!("{json}");
+    Ok(())
+}
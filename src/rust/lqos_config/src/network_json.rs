@@ -76,6 +76,17 @@ impl NetworkJson {
 
     /// Attempt to load network.json from disk
     pub fn load() -> Result<Self, NetworkJsonError> {
+        if !Self::exists() {
+            return Err(NetworkJsonError::FileNotFound);
+        }
+        let path = Self::path()?;
+        let raw = fs::read_to_string(path).map_err(|_| NetworkJsonError::ConfigLoadError)?;
+        Self::load_from_string(&raw)
+    }
+
+    /// Parse network.json contents that did not come from disk, such as a copy in a
+    /// support dump.
+    pub fn load_from_string(raw: &str) -> Result<Self, NetworkJsonError> {
         let mut nodes = vec![NetworkJsonNode {
             name: "Root".to_string(),
             id: None,
@@ -99,13 +110,8 @@ impl NetworkJson {
             heatmap: None,
             qoq_heatmap: None,
         }];
-        if !Self::exists() {
-            return Err(NetworkJsonError::FileNotFound);
-        }
-        let path = Self::path()?;
-        let raw = fs::read_to_string(path).map_err(|_| NetworkJsonError::ConfigLoadError)?;
         let json: Value =
-            serde_json::from_str(&raw).map_err(|_| NetworkJsonError::ConfigLoadError)?;
+            serde_json::from_str(raw).map_err(|_| NetworkJsonError::ConfigLoadError)?;
 
         // Start reading from the top. We are at the root node.
         let parents = vec![0];
@@ -165,6 +165,10 @@ pub struct ClassIdentityPlannerOutput {
     pub last_used_minor_by_queue: BTreeMap<u32, u32>,
 }
 
+/// Lowest class minor the planner hands out. Minors 1 and 2 of every queue belong to the
+/// queue's own root and default classes.
+pub const FIRST_CLASS_MINOR: u32 = 3;
+
 /// Reserved minor numbers keyed by shaping queue.
 pub type PlannerMinorReservations = BTreeMap<u32, BTreeSet<u32>>;
 
@@ -194,7 +198,7 @@ fn sanitize_weight(weight: f64) -> f64 {
 }
 
 fn next_free_minor(start_minor: u32, reserved: &BTreeSet<u32>) -> u32 {
-    let mut candidate = start_minor.max(3);
+    let mut candidate = start_minor.max(FIRST_CLASS_MINOR);
     while reserved.contains(&candidate) {
         candidate += 1;
     }
@@ -439,7 +443,7 @@ pub fn plan_class_identities_with_constraints(
     for (queue, reserved) in &reserved_site_minors {
         let next_minor = next_site_minor_by_queue
             .entry(*queue)
-            .or_insert(constraints.site_minor_start.max(3));
+            .or_insert(constraints.site_minor_start.max(FIRST_CLASS_MINOR));
         if let Some(last_reserved) = reserved.iter().next_back().copied() {
             *next_minor = (*next_minor).max(last_reserved.saturating_add(1));
         }
@@ -449,7 +453,7 @@ pub fn plan_class_identities_with_constraints(
         let reserved = reserved_site_minors.entry(site.queue).or_default();
         let next_minor = next_site_minor_by_queue
             .entry(site.queue)
-            .or_insert(constraints.site_minor_start.max(3));
+            .or_insert(constraints.site_minor_start.max(FIRST_CLASS_MINOR));
         let reuse_minor = previous_sites.get(&site.site_key).and_then(|stored| {
             (stored.queue == site.queue
                 && stored.parent_path == site.parent_path
@@ -500,10 +504,18 @@ pub fn plan_class_identities_with_constraints(
         let start = next_site_minor_by_queue
             .get(queue)
             .copied()
-            .unwrap_or_else(|| next_free_minor(constraints.circuit_minor_start.max(3), reserved));
+            .unwrap_or_else(|| {
+                next_free_minor(
+                    constraints.circuit_minor_start.max(FIRST_CLASS_MINOR),
+                    reserved,
+                )
+            });
         next_circuit_minor_by_queue.insert(
             *queue,
-            next_free_minor(start.max(constraints.circuit_minor_start.max(3)), reserved),
+            next_free_minor(
+                start.max(constraints.circuit_minor_start.max(FIRST_CLASS_MINOR)),
+                reserved,
+            ),
         );
     }
 
@@ -513,7 +525,12 @@ pub fn plan_class_identities_with_constraints(
         let reserved = reserved_circuit_minors.entry(group.queue).or_default();
         let next_minor = next_circuit_minor_by_queue
             .entry(group.queue)
-            .or_insert_with(|| next_free_minor(constraints.circuit_minor_start.max(3), reserved));
+            .or_insert_with(|| {
+                next_free_minor(
+                    constraints.circuit_minor_start.max(FIRST_CLASS_MINOR),
+                    reserved,
+                )
+            });
         for circuit_id in &group.circuit_ids {
             let reuse_minor = previous_circuits.get(circuit_id).and_then(|stored| {
                 (stored.queue == group.queue
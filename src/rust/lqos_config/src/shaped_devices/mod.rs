@@ -1,10 +1,12 @@
 mod serializable;
 mod shaped_device;
+mod sqm_token;
 
 use csv::{QuoteStyle, ReaderBuilder, WriterBuilder};
 use lqos_utils::XdpIpAddress;
 use serializable::SerializableShapedDevice;
 pub use shaped_device::ShapedDevice;
+pub use sqm_token::{CAKE_PROFILE_OPTIONS, cake_profile_options, is_valid_sqm_direction_token};
 use std::net::IpAddr;
 use std::path::{Path, PathBuf};
 use thiserror::Error;
@@ -128,7 +130,13 @@ impl ConfigShapedDevices {
         }
         debug!("Loading ShapedDevices.csv from {:?}", final_path);
         let raw_bytes = std::fs::read(&final_path).map_err(|_| ShapedDevicesError::OpenFail)?;
-        let utf8_bytes = ConfigShapedDevices::handle_encodings(&raw_bytes);
+        Self::load_from_bytes(&raw_bytes)
+    }
+
+    /// Parses `ShapedDevices.csv` contents that did not come from disk, such as a copy in
+    /// a support dump.
+    pub fn load_from_bytes(raw_bytes: &[u8]) -> Result<Self, ShapedDevicesError> {
+        let utf8_bytes = ConfigShapedDevices::handle_encodings(raw_bytes);
 
         let mut reader = ReaderBuilder::new()
             .comment(Some(b'#'))
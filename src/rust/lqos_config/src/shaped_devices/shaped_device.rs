@@ -195,11 +195,12 @@ impl ShapedDevice {
                     let up = parts.next().unwrap_or("").trim();
 
                     // Validate each side if present
-                    let valid =
-                        |s: &str| -> bool { matches!(s, "" | "cake" | "fq_codel" | "none") };
+                    let valid = |s: &str| -> bool {
+                        s.is_empty() || super::sqm_token::is_valid_sqm_direction_token(s)
+                    };
                     if !valid(down) || !valid(up) {
                         return Err(ShapedDevicesError::CsvEntryParseError(format!(
-                            "Invalid directional sqm override '{token}'. Allowed: 'cake', 'fq_codel', 'none', or 'down_sqm/up_sqm' (e.g. 'cake/fq_codel', '/none')"
+                            "Invalid directional sqm override '{token}'. Allowed: 'cake', 'cake:<options>', 'fq_codel', 'none', or 'down_sqm/up_sqm' (e.g. 'cake/fq_codel', '/none')"
                         )));
                     }
 
@@ -208,10 +209,12 @@ impl ShapedDevice {
                 } else {
                     // Single token applies to both directions when used
                     match token.as_str() {
-                        "cake" | "fq_codel" | "none" => device.sqm_override = Some(token),
+                        valid if super::sqm_token::is_valid_sqm_direction_token(valid) => {
+                            device.sqm_override = Some(token)
+                        }
                         other => {
                             return Err(ShapedDevicesError::CsvEntryParseError(format!(
-                                "Invalid sqm override '{other}'. Allowed values: 'cake', 'fq_codel', 'none', or 'down_sqm/up_sqm' (e.g. 'cake/fq_codel', '/none')"
+                                "Invalid sqm override '{other}'. Allowed values: 'cake', 'cake:<options>' (e.g. 'cake:besteffort:ack-filter'), 'fq_codel', 'none', or 'down_sqm/up_sqm' (e.g. 'cake/fq_codel', '/none')"
                             )));
                         }
                     }
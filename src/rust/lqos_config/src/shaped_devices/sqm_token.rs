@@ -0,0 +1,71 @@
+//! Validation of per-circuit SQM override tokens.
+//!
+//! A direction token is `cake`, `fq_codel`, `none`, or a CAKE profile of the form
+//! `cake:<option>[:<option>]`, e.g. `cake:besteffort:ack-filter`.
+
+/// Options accepted after `cake:` in a CAKE profile token.
+pub const CAKE_PROFILE_OPTIONS: &[&str] = &[
+    "besteffort",
+    "diffserv3",
+    "diffserv4",
+    "diffserv8",
+    "ack-filter",
+    "no-ack-filter",
+];
+
+/// Returns the options of a `cake:<option>[:<option>]` profile token, or `None` if the token is
+/// not a valid CAKE profile.
+///
+/// A profile may name at most one tin mode and at most one ACK filter setting.
+pub fn cake_profile_options(token: &str) -> Option<Vec<&str>> {
+    let options: Vec<&str> = token.strip_prefix("cake:")?.split(':').collect();
+    if options
+        .iter()
+        .any(|option| !CAKE_PROFILE_OPTIONS.contains(option))
+    {
+        return None;
+    }
+    let tin_modes = options
+        .iter()
+        .filter(|option| !option.contains("ack-filter"))
+        .count();
+    let ack_modes = options.len() - tin_modes;
+    if tin_modes > 1 || ack_modes > 1 {
+        return None;
+    }
+    Some(options)
+}
+
+/// Returns true if `token` is a valid (lowercase, trimmed) single-direction SQM override token.
+pub fn is_valid_sqm_direction_token(token: &str) -> bool {
+    matches!(token, "cake" | "fq_codel" | "none") || cake_profile_options(token).is_some()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn accepts_plain_and_profile_tokens() {
+        assert!(is_valid_sqm_direction_token("cake"));
+        assert!(is_valid_sqm_direction_token("fq_codel"));
+        assert!(is_valid_sqm_direction_token("none"));
+        assert!(is_valid_sqm_direction_token("cake:besteffort"));
+        assert!(is_valid_sqm_direction_token("cake:diffserv4:ack-filter"));
+        assert_eq!(
+            cake_profile_options("cake:ack-filter:besteffort"),
+            Some(vec!["ack-filter", "besteffort"])
+        );
+    }
+
+    #[test]
+    fn rejects_unknown_or_conflicting_profile_options() {
+        assert!(!is_valid_sqm_direction_token("cake:"));
+        assert!(!is_valid_sqm_direction_token("cake:rtt"));
+        assert!(!is_valid_sqm_direction_token("cake:besteffort:diffserv4"));
+        assert!(!is_valid_sqm_direction_token(
+            "cake:ack-filter:no-ack-filter"
+        ));
+        assert!(!is_valid_sqm_direction_token("fq_codel:besteffort"));
+    }
+}
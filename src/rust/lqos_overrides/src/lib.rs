@@ -3,6 +3,9 @@
 
 mod overrides_file;
 pub use overrides_file::{
-    CircuitAdjustment, NetworkAdjustment, OverrideFile, OverrideLayer, OverrideStore,
-    UispOverrides, UispRouteOverride,
+    BulkFormat, ChangeAuthor, ChangeProposal, CircuitAdjustment, ConflictKind, DiffKind,
+    HistoryEntry, ImportConflict, ImportIssue, ImportMode, ImportReport, NetworkAdjustment,
+    OverrideDiffEntry, OverrideFile, OverrideLayer, OverrideStore, PendingChange, ProposedChange,
+    SaveContext, SubmitOutcome, UispOverrides, UispRouteOverride, diff_override_files,
+    export_override_file, normalize_sqm_override, plan_import, proposal_requires_approval,
 };
@@ -1,15 +1,23 @@
+use std::io::Read;
 use std::net::{Ipv4Addr, Ipv6Addr};
+use std::path::{Path, PathBuf};
 
 use anyhow::{Result, anyhow};
-use clap::{Args, Parser, Subcommand};
+use clap::{Args, Parser, Subcommand, ValueEnum};
 
 use lqos_config::ShapedDevice;
-use lqos_overrides::{CircuitAdjustment, NetworkAdjustment, OverrideFile};
+use lqos_overrides::{
+    BulkFormat, ChangeAuthor, CircuitAdjustment, ImportMode, NetworkAdjustment, OverrideFile,
+    OverrideLayer, OverrideStore, SaveContext, normalize_sqm_override, plan_import,
+};
 
 #[derive(Parser, Debug)]
 #[command(name = "lqos_overrides")]
 #[command(about = "Manage LibreQoS overrides", version, author)]
 struct Cli {
+    /// Reason recorded in the override history for changes made by this command
+    #[arg(long, global = true)]
+    reason: Option<String>,
     #[command(subcommand)]
     command: Commands,
 }
@@ -36,6 +44,176 @@ enum Commands {
         #[command(subcommand)]
         command: UispCommand,
     },
+    /// Inspect, diff and roll back recorded versions of an overrides layer
+    History {
+        #[command(subcommand)]
+        command: HistoryCommand,
+    },
+    /// Review changes proposed by StormGuard/TreeGuard that are awaiting approval
+    Pending {
+        #[command(subcommand)]
+        command: PendingCommand,
+    },
+    /// Export all overrides in a layer as JSON or CSV
+    Export {
+        #[arg(long, value_enum, default_value = "operator")]
+        layer: LayerArg,
+        #[arg(long, value_enum, default_value = "json")]
+        format: FormatArg,
+        /// Write to this file instead of stdout
+        #[arg(long)]
+        output: Option<PathBuf>,
+    },
+    /// Import overrides into a layer from JSON or CSV
+    Import(ImportArgs),
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug)]
+enum FormatArg {
+    Json,
+    Csv,
+}
+
+impl From<FormatArg> for BulkFormat {
+    fn from(format: FormatArg) -> Self {
+        match format {
+            FormatArg::Json => BulkFormat::Json,
+            FormatArg::Csv => BulkFormat::Csv,
+        }
+    }
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug)]
+enum ModeArg {
+    /// Add or update imported entries, keep everything else
+    Merge,
+    /// Make the layer contain exactly the imported entries
+    Replace,
+}
+
+impl From<ModeArg> for ImportMode {
+    fn from(mode: ModeArg) -> Self {
+        match mode {
+            ModeArg::Merge => ImportMode::Merge,
+            ModeArg::Replace => ImportMode::Replace,
+        }
+    }
+}
+
+#[derive(Args, Debug)]
+struct ImportArgs {
+    /// File to import, or `-` for stdin
+    #[arg(long)]
+    input: String,
+    #[arg(long, value_enum, default_value = "operator")]
+    layer: LayerArg,
+    /// Input format; inferred from the file extension when omitted
+    #[arg(long, value_enum)]
+    format: Option<FormatArg>,
+    #[arg(long, value_enum, default_value = "merge")]
+    mode: ModeArg,
+    /// Report issues, conflicts and changes without writing anything
+    #[arg(long)]
+    validate_only: bool,
+    /// Allow a merge to replace existing entries that differ from the import
+    #[arg(long)]
+    overwrite: bool,
+}
+
+impl ImportArgs {
+    fn bulk_format(&self) -> Result<BulkFormat> {
+        if let Some(format) = self.format {
+            return Ok(format.into());
+        }
+        match Path::new(&self.input)
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .map(str::to_ascii_lowercase)
+            .as_deref()
+        {
+            Some("json") => Ok(BulkFormat::Json),
+            Some("csv") => Ok(BulkFormat::Csv),
+            _ => Err(anyhow!(
+                "Unable to infer the format of '{}'; pass --format json|csv",
+                self.input
+            )),
+        }
+    }
+
+    fn read_input(&self) -> Result<String> {
+        if self.input == "-" {
+            let mut buffer = String::new();
+            std::io::stdin().read_to_string(&mut buffer)?;
+            Ok(buffer)
+        } else {
+            Ok(std::fs::read_to_string(&self.input)?)
+        }
+    }
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug)]
+enum LayerArg {
+    Operator,
+    Stormguard,
+    Treeguard,
+}
+
+impl From<LayerArg> for OverrideLayer {
+    fn from(layer: LayerArg) -> Self {
+        match layer {
+            LayerArg::Operator => OverrideLayer::Operator,
+            LayerArg::Stormguard => OverrideLayer::Stormguard,
+            LayerArg::Treeguard => OverrideLayer::Treeguard,
+        }
+    }
+}
+
+#[derive(Subcommand, Debug)]
+enum HistoryCommand {
+    /// List recorded versions, newest first
+    List {
+        #[arg(long, value_enum, default_value = "operator")]
+        layer: LayerArg,
+    },
+    /// Print the full contents of a recorded version
+    Show {
+        #[arg(long, value_enum, default_value = "operator")]
+        layer: LayerArg,
+        #[arg(long)]
+        version: u64,
+    },
+    /// Diff two versions (omit --to to compare against the current file)
+    Diff {
+        #[arg(long, value_enum, default_value = "operator")]
+        layer: LayerArg,
+        #[arg(long)]
+        from: u64,
+        #[arg(long)]
+        to: Option<u64>,
+    },
+    /// Restore a layer to a recorded version
+    Rollback {
+        #[arg(long, value_enum, default_value = "operator")]
+        layer: LayerArg,
+        #[arg(long)]
+        version: u64,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+enum PendingCommand {
+    /// List changes awaiting approval
+    List,
+    /// Approve a pending change and write it to its overrides layer
+    Approve {
+        #[arg(long)]
+        id: u64,
+    },
+    /// Reject (discard) a pending change
+    Reject {
+        #[arg(long)]
+        id: u64,
+    },
 }
 
 #[derive(Subcommand, Debug)]
@@ -184,6 +362,21 @@ struct AddArgs {
     sqm_override: String,
 }
 
+/// Attributes CLI changes to the invoking (or sudo-ing) user and records the command line.
+fn cli_save_context(reason: Option<&str>) -> SaveContext {
+    let name = std::env::var("SUDO_USER")
+        .or_else(|_| std::env::var("USER"))
+        .unwrap_or_else(|_| "cli".to_string());
+    let reason = match reason {
+        Some(reason) => reason.to_string(),
+        None => {
+            let args: Vec<String> = std::env::args().skip(1).collect();
+            format!("lqos_overrides {}", args.join(" "))
+        }
+    };
+    SaveContext::new(ChangeAuthor::User { name }, reason)
+}
+
 fn parse_ipv4(s: &str) -> Result<(Ipv4Addr, u32)> {
     if let Some((ip, prefix)) = s.split_once('/') {
         Ok((ip.parse()?, prefix.parse()?))
@@ -245,34 +438,6 @@ impl AddArgs {
     }
 }
 
-fn normalize_sqm_override(raw: &str) -> Result<Option<String>> {
-    let trimmed = raw.trim();
-    if trimmed.is_empty() {
-        return Ok(None);
-    }
-
-    let token = trimmed.to_ascii_lowercase();
-    if token.contains('/') {
-        let mut parts = token.splitn(2, '/');
-        let down = parts.next().unwrap_or("").trim();
-        let up = parts.next().unwrap_or("").trim();
-        let valid = |s: &str| matches!(s, "" | "cake" | "fq_codel" | "none");
-        if !valid(down) || !valid(up) {
-            return Err(anyhow!(
-                "invalid directional sqm override '{token}'. Allowed: 'cake', 'fq_codel', 'none', or 'down_sqm/up_sqm' (e.g. 'cake/fq_codel', '/none')"
-            ));
-        }
-        return Ok(Some(format!("{down}/{up}")));
-    }
-
-    match token.as_str() {
-        "cake" | "fq_codel" | "none" => Ok(Some(token)),
-        _ => Err(anyhow!(
-            "invalid sqm override '{token}'. Allowed values: 'cake', 'fq_codel', 'none', or 'down_sqm/up_sqm' (e.g. 'cake/fq_codel', '/none')"
-        )),
-    }
-}
-
 #[derive(Args, Debug, Default)]
 struct AddCircuitSpeedArgs {
     #[arg(long)]
@@ -318,6 +483,7 @@ fn main() -> Result<()> {
 
     // Load overrides file at start
     let mut overrides = OverrideFile::load()?;
+    let ctx = cli_save_context(cli.reason.as_deref());
 
     match cli.command {
         Commands::PersistentDevices { command: cmd } => match cmd {
@@ -325,7 +491,7 @@ fn main() -> Result<()> {
                 let device = args.into_device()?;
                 let changed = overrides.add_persistent_shaped_device_return_changed(device);
                 if changed {
-                    overrides.save()?;
+                    overrides.save(&ctx)?;
                     println!("Added device; overrides saved.");
                 } else {
                     println!("No changes (device already present).");
@@ -335,7 +501,7 @@ fn main() -> Result<()> {
                 let removed =
                     overrides.remove_persistent_shaped_device_by_circuit_count(&circuit_id);
                 if removed > 0 {
-                    overrides.save()?;
+                    overrides.save(&ctx)?;
                     println!("Removed {removed} device(s) by circuit_id; overrides saved.");
                 } else {
                     println!("No devices matched circuit_id {circuit_id}.");
@@ -344,7 +510,7 @@ fn main() -> Result<()> {
             PersistentDevicesCommand::DeleteDeviceId { device_id } => {
                 let removed = overrides.remove_persistent_shaped_device_by_device_count(&device_id);
                 if removed > 0 {
-                    overrides.save()?;
+                    overrides.save(&ctx)?;
                     println!("Removed {removed} device(s) by device_id; overrides saved.");
                 } else {
                     println!("No devices matched device_id {device_id}.");
@@ -365,7 +531,7 @@ fn main() -> Result<()> {
                     max_upload_bandwidth: args.max_upload_bandwidth,
                 };
                 overrides.add_circuit_adjustment(adj);
-                overrides.save()?;
+                overrides.save(&ctx)?;
                 println!("Added circuit speed adjustment; overrides saved.");
             }
             AdjustmentsCommand::AddDeviceSpeed(args) => {
@@ -377,19 +543,19 @@ fn main() -> Result<()> {
                     max_upload_bandwidth: args.max_upload_bandwidth,
                 };
                 overrides.add_circuit_adjustment(adj);
-                overrides.save()?;
+                overrides.save(&ctx)?;
                 println!("Added device speed adjustment; overrides saved.");
             }
             AdjustmentsCommand::AddRemoveCircuit { circuit_id } => {
                 let adj = CircuitAdjustment::RemoveCircuit { circuit_id };
                 overrides.add_circuit_adjustment(adj);
-                overrides.save()?;
+                overrides.save(&ctx)?;
                 println!("Added remove-circuit adjustment; overrides saved.");
             }
             AdjustmentsCommand::AddRemoveDevice { device_id } => {
                 let adj = CircuitAdjustment::RemoveDevice { device_id };
                 overrides.add_circuit_adjustment(adj);
-                overrides.save()?;
+                overrides.save(&ctx)?;
                 println!("Added remove-device adjustment; overrides saved.");
             }
             AdjustmentsCommand::AddReparentCircuit {
@@ -401,13 +567,13 @@ fn main() -> Result<()> {
                     parent_node,
                 };
                 overrides.add_circuit_adjustment(adj);
-                overrides.save()?;
+                overrides.save(&ctx)?;
                 println!("Added reparent-circuit adjustment; overrides saved.");
             }
             AdjustmentsCommand::DeleteIndex { index } => {
                 let ok = overrides.remove_circuit_adjustment_by_index(index);
                 if ok {
-                    overrides.save()?;
+                    overrides.save(&ctx)?;
                     println!("Removed adjustment at index {index}; overrides saved.");
                 } else {
                     println!("No adjustment at index {index}.");
@@ -427,7 +593,7 @@ fn main() -> Result<()> {
                     upload_bandwidth_mbps: args.upload_bandwidth_mbps,
                 };
                 overrides.add_network_adjustment(adj);
-                overrides.save()?;
+                overrides.save(&ctx)?;
                 println!("Added site speed adjustment; overrides saved.");
             }
             NetworkAdjustmentsCommand::SetVirtual {
@@ -435,13 +601,13 @@ fn main() -> Result<()> {
                 virtual_node,
             } => {
                 overrides.set_network_node_virtual(node_name, virtual_node);
-                overrides.save()?;
+                overrides.save(&ctx)?;
                 println!("Set node virtual flag; overrides saved.");
             }
             NetworkAdjustmentsCommand::DeleteVirtual { node_name } => {
                 let removed = overrides.remove_network_node_virtual_by_name_count(&node_name);
                 if removed > 0 {
-                    overrides.save()?;
+                    overrides.save(&ctx)?;
                     println!(
                         "Removed {removed} virtual override(s) for node '{node_name}'; overrides saved."
                     );
@@ -452,7 +618,7 @@ fn main() -> Result<()> {
             NetworkAdjustmentsCommand::DeleteIndex { index } => {
                 let ok = overrides.remove_network_adjustment_by_index(index);
                 if ok {
-                    overrides.save()?;
+                    overrides.save(&ctx)?;
                     println!("Removed network adjustment at index {index}; overrides saved.");
                 } else {
                     println!("No network adjustment at index {index}.");
@@ -470,13 +636,13 @@ fn main() -> Result<()> {
                 up,
             } => {
                 overrides.set_uisp_bandwidth_override(site_name, down, up);
-                overrides.save()?;
+                overrides.save(&ctx)?;
                 println!("Set UISP bandwidth override; overrides saved.");
             }
             UispCommand::BandwidthRemove { site_name } => {
                 let removed = overrides.remove_uisp_bandwidth_override(&site_name);
                 if removed {
-                    overrides.save()?;
+                    overrides.save(&ctx)?;
                     println!("Removed UISP bandwidth override for {site_name}; overrides saved.");
                 } else {
                     println!("No UISP bandwidth override found for {site_name}.");
@@ -498,13 +664,13 @@ fn main() -> Result<()> {
                 cost,
             } => {
                 overrides.add_uisp_route_override(from_site, to_site, cost);
-                overrides.save()?;
+                overrides.save(&ctx)?;
                 println!("Added UISP route override; overrides saved.");
             }
             UispCommand::RouteRemoveIndex { index } => {
                 let removed = overrides.remove_uisp_route_by_index(index);
                 if removed {
-                    overrides.save()?;
+                    overrides.save(&ctx)?;
                     println!("Removed UISP route override at index {index}; overrides saved.");
                 } else {
                     println!("No UISP route override at index {index}.");
@@ -518,6 +684,104 @@ fn main() -> Result<()> {
                 }
             }
         },
+        Commands::History { command: cmd } => match cmd {
+            HistoryCommand::List { layer } => {
+                let list = OverrideStore::list_history(layer.into())?;
+                println!("{}", serde_json::to_string_pretty(&list)?);
+            }
+            HistoryCommand::Show { layer, version } => {
+                let contents = OverrideStore::load_history_version(layer.into(), version)?;
+                println!("{}", serde_json::to_string_pretty(&contents)?);
+            }
+            HistoryCommand::Diff { layer, from, to } => {
+                let diff = OverrideStore::diff_history_versions(layer.into(), from, to)?;
+                println!("{}", serde_json::to_string_pretty(&diff)?);
+            }
+            HistoryCommand::Rollback { layer, version } => {
+                let entry = OverrideStore::rollback_layer(layer.into(), version, &ctx)?;
+                println!(
+                    "Rolled back to version {version}; recorded as version {}.",
+                    entry.version
+                );
+            }
+        },
+        Commands::Pending { command: cmd } => match cmd {
+            PendingCommand::List => {
+                let list = OverrideStore::load_pending_changes()?;
+                println!("{}", serde_json::to_string_pretty(&list)?);
+            }
+            PendingCommand::Approve { id } => {
+                let (change, changed) = OverrideStore::approve_pending_change(id)?;
+                if changed {
+                    println!(
+                        "Approved change {id} ({}); overrides saved.",
+                        change.summary
+                    );
+                } else {
+                    println!(
+                        "Approved change {id} ({}); overrides already matched.",
+                        change.summary
+                    );
+                }
+            }
+            PendingCommand::Reject { id } => {
+                let change = OverrideStore::reject_pending_change(id)?;
+                println!("Rejected change {id} ({}).", change.summary);
+            }
+        },
+        Commands::Export {
+            layer,
+            format,
+            output,
+        } => {
+            let exported = OverrideStore::export_layer(layer.into(), format.into())?;
+            match output {
+                Some(path) => {
+                    std::fs::write(&path, exported.as_bytes())?;
+                    eprintln!("Exported overrides to {}.", path.display());
+                }
+                None => print!("{exported}"),
+            }
+        }
+        Commands::Import(args) => {
+            let format = args.bulk_format()?;
+            let input = args.read_input()?;
+            let layer: OverrideLayer = args.layer.into();
+            let report = if args.validate_only {
+                let existing = OverrideStore::load_layer(layer)?;
+                plan_import(&existing, &input, format, args.mode.into())
+            } else {
+                OverrideStore::import_layer(
+                    layer,
+                    &input,
+                    format,
+                    args.mode.into(),
+                    args.overwrite,
+                    &ctx,
+                )?
+            };
+            println!("{}", serde_json::to_string_pretty(&report)?);
+            if report.is_blocked(args.overwrite) {
+                return Err(anyhow!(
+                    "Import refused: {} issue(s), {} conflict(s). Conflicts with existing entries can be accepted with --overwrite.",
+                    report.issues.len(),
+                    report.conflicts.len()
+                ));
+            }
+            if args.validate_only {
+                eprintln!(
+                    "Validation passed: {} change(s) would be applied.",
+                    report.changes.len()
+                );
+            } else if report.applied {
+                eprintln!(
+                    "Imported {} change(s); overrides saved.",
+                    report.changes.len()
+                );
+            } else {
+                eprintln!("No changes (layer already matches the import).");
+            }
+        }
     }
 
     Ok(())
@@ -3,13 +3,27 @@ use std::{
     path::{Path, PathBuf},
 };
 
-use anyhow::Result;
+use anyhow::{Result, anyhow};
 use lqos_config::ShapedDevice;
 use serde::{Deserialize, Serialize};
 
 use crate::overrides_file::file_lock::FileLock;
 
+mod bulk;
+mod change_queue;
 mod file_lock;
+mod history;
+
+pub use bulk::{
+    BulkFormat, ConflictKind, ImportConflict, ImportIssue, ImportMode, ImportReport,
+    export_override_file, plan_import,
+};
+pub use change_queue::{
+    ChangeProposal, PendingChange, ProposedChange, SubmitOutcome, proposal_requires_approval,
+};
+pub use history::{
+    ChangeAuthor, DiffKind, HistoryEntry, OverrideDiffEntry, SaveContext, diff_override_files,
+};
 
 const OPERATOR_OVERRIDES_FILE: &str = "lqos_overrides.json";
 const STORMGUARD_OVERRIDES_FILE: &str = "lqos_overrides.stormguard.json";
@@ -17,7 +31,8 @@ const TREEGUARD_OVERRIDES_FILE: &str = "lqos_overrides.treeguard.json";
 const LEGACY_AUTOPILOT_OVERRIDES_FILE: &str = "lqos_overrides.autopilot.json";
 
 /// Selects which overrides file to load/save.
-#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
 pub enum OverrideLayer {
     /// Operator-owned overrides (`lqos_overrides.json`).
     Operator,
@@ -135,7 +150,7 @@ pub struct UispRouteOverride {
 }
 
 /// The serialized contents of a single LibreQoS overrides file.
-#[derive(Serialize, Deserialize, Default)]
+#[derive(Serialize, Deserialize, Default, Clone)]
 pub struct OverrideFile {
     /// Devices that will be persisted into ShapedDevices.csv by the scheduler. Useful
     /// for adding persistent "catch all", or API-controlled new devices that are somehow detached
@@ -174,6 +189,25 @@ fn treeguard_read_path(config: &lqos_config::Config) -> PathBuf {
     if legacy.exists() { legacy } else { canonical }
 }
 
+fn layer_read_path(config: &lqos_config::Config, layer: OverrideLayer) -> PathBuf {
+    match layer {
+        OverrideLayer::Treeguard => treeguard_read_path(config),
+        OverrideLayer::Operator | OverrideLayer::Stormguard => overrides_path(config, layer),
+    }
+}
+
+/// Loads a layer without taking the lock. The operator file is created if missing; adaptive
+/// layers default to empty.
+fn load_layer_unlocked(config: &lqos_config::Config, layer: OverrideLayer) -> Result<OverrideFile> {
+    let path = layer_read_path(config, layer);
+    if layer == OverrideLayer::Operator {
+        ensure_exists_default(&path)?;
+    } else if !path.exists() {
+        return Ok(OverrideFile::default());
+    }
+    load_from_path(&path)
+}
+
 fn load_from_path(path: &Path) -> Result<OverrideFile> {
     let raw = read_to_string(path)?;
     let as_json = serde_json::from_str(&raw)?;
@@ -273,6 +307,36 @@ fn persistent_device_carries_sqm(device: &ShapedDevice) -> bool {
         .is_some_and(|sqm| !sqm.trim().is_empty())
 }
 
+/// Normalizes a per-device SQM override token (`cake`, `cake:<options>`, `fq_codel`, `none` or
+/// `down_sqm/up_sqm`). Empty input means "no override" and yields `None`.
+pub fn normalize_sqm_override(raw: &str) -> Result<Option<String>> {
+    let trimmed = raw.trim();
+    if trimmed.is_empty() {
+        return Ok(None);
+    }
+
+    let token = trimmed.to_ascii_lowercase();
+    if token.contains('/') {
+        let mut parts = token.splitn(2, '/');
+        let down = parts.next().unwrap_or("").trim();
+        let up = parts.next().unwrap_or("").trim();
+        let valid = |s: &str| s.is_empty() || lqos_config::is_valid_sqm_direction_token(s);
+        if !valid(down) || !valid(up) {
+            return Err(anyhow!(
+                "invalid directional sqm override '{token}'. Allowed: 'cake', 'cake:<options>', 'fq_codel', 'none', or 'down_sqm/up_sqm' (e.g. 'cake/fq_codel', '/none')"
+            ));
+        }
+        return Ok(Some(format!("{down}/{up}")));
+    }
+
+    match token.as_str() {
+        valid if lqos_config::is_valid_sqm_direction_token(valid) => Ok(Some(token)),
+        _ => Err(anyhow!(
+            "invalid sqm override '{token}'. Allowed values: 'cake', 'cake:<options>' (e.g. 'cake:besteffort:ack-filter'), 'fq_codel', 'none', or 'down_sqm/up_sqm' (e.g. 'cake/fq_codel', '/none')"
+        )),
+    }
+}
+
 fn legacy_sqm_adjustments_from_devices(devices: &[ShapedDevice]) -> Vec<CircuitAdjustment> {
     devices
         .iter()
@@ -515,12 +579,11 @@ impl OverrideFile {
         Ok(as_json)
     }
 
-    /// Saves this value to the operator-owned overrides file.
-    pub fn save(&self) -> Result<()> {
+    /// Saves this value to the operator-owned overrides file and records it in the layer history.
+    pub fn save(&self, ctx: &SaveContext) -> Result<()> {
         let lock = FileLock::new()?;
         let config = lqos_config::load_config()?;
-        let path = overrides_path(&config, OverrideLayer::Operator);
-        save_to_path(&path, self)?;
+        history::save_layer_recorded(&config, OverrideLayer::Operator, self, ctx)?;
         drop(lock); // Explicitly drop for clarity. RAII does it anyway.
         Ok(())
     }
@@ -892,40 +955,95 @@ impl OverrideStore {
     pub fn load_layer(layer: OverrideLayer) -> Result<OverrideFile> {
         let lock = FileLock::new()?;
         let config = lqos_config::load_config()?;
-        let path = match layer {
-            OverrideLayer::Operator => overrides_path(&config, layer),
-            OverrideLayer::Stormguard => overrides_path(&config, layer),
-            OverrideLayer::Treeguard => treeguard_read_path(&config),
-        };
-        let overrides = match layer {
-            OverrideLayer::Operator => {
-                ensure_exists_default(&path)?;
-                load_from_path(&path)?
-            }
-            OverrideLayer::Stormguard | OverrideLayer::Treeguard => {
-                if !path.exists() {
-                    OverrideFile::default()
-                } else {
-                    load_from_path(&path)?
-                }
-            }
-        };
+        let overrides = load_layer_unlocked(&config, layer)?;
         drop(lock);
         Ok(overrides)
     }
 
-    /// Saves a single overrides layer.
+    /// Saves a single overrides layer and records the new contents in the layer history.
     ///
-    /// Side effects: acquires the global overrides lock and writes the selected overrides file.
-    pub fn save_layer(layer: OverrideLayer, overrides: &OverrideFile) -> Result<()> {
+    /// Side effects: acquires the global overrides lock, writes the selected overrides file and
+    /// a snapshot under `lqos_overrides.history/`.
+    pub fn save_layer(
+        layer: OverrideLayer,
+        overrides: &OverrideFile,
+        ctx: &SaveContext,
+    ) -> Result<()> {
         let lock = FileLock::new()?;
         let config = lqos_config::load_config()?;
-        let path = overrides_path(&config, layer);
-        save_to_path(&path, overrides)?;
+        history::save_layer_recorded(&config, layer, overrides, ctx)?;
         drop(lock);
         Ok(())
     }
 
+    /// Lists recorded versions of a layer, newest first.
+    ///
+    /// Side effects: acquires the global overrides lock.
+    pub fn list_history(layer: OverrideLayer) -> Result<Vec<HistoryEntry>> {
+        let lock = FileLock::new()?;
+        let config = lqos_config::load_config()?;
+        let entries = history::list_history(&config, layer)?;
+        drop(lock);
+        Ok(entries)
+    }
+
+    /// Loads the contents of a recorded layer version.
+    ///
+    /// Side effects: acquires the global overrides lock.
+    pub fn load_history_version(layer: OverrideLayer, version: u64) -> Result<OverrideFile> {
+        let lock = FileLock::new()?;
+        let config = lqos_config::load_config()?;
+        let overrides = history::load_version(&config, layer, version)?;
+        drop(lock);
+        Ok(overrides)
+    }
+
+    /// Diffs two recorded versions of a layer. `to_version` of `None` compares against the
+    /// layer's current contents.
+    ///
+    /// Side effects: acquires the global overrides lock.
+    pub fn diff_history_versions(
+        layer: OverrideLayer,
+        from_version: u64,
+        to_version: Option<u64>,
+    ) -> Result<Vec<OverrideDiffEntry>> {
+        let lock = FileLock::new()?;
+        let config = lqos_config::load_config()?;
+        let before = history::load_version(&config, layer, from_version)?;
+        let after = match to_version {
+            Some(version) => history::load_version(&config, layer, version)?,
+            None => load_layer_unlocked(&config, layer)?,
+        };
+        drop(lock);
+        Ok(diff_override_files(&before, &after))
+    }
+
+    /// Restores a layer to a recorded version. The rollback itself is recorded as a new version.
+    ///
+    /// Side effects: acquires the global overrides lock, writes the layer and a history snapshot.
+    pub fn rollback_layer(
+        layer: OverrideLayer,
+        version: u64,
+        ctx: &SaveContext,
+    ) -> Result<HistoryEntry> {
+        let lock = FileLock::new()?;
+        let config = lqos_config::load_config()?;
+        let restored = history::load_version(&config, layer, version)?;
+        let reason = if ctx.reason.trim().is_empty() {
+            format!("Rollback to version {version}")
+        } else {
+            format!("Rollback to version {version}: {}", ctx.reason)
+        };
+        let entry = history::save_layer_recorded(
+            &config,
+            layer,
+            &restored,
+            &SaveContext::new(ctx.author.clone(), reason),
+        )?;
+        drop(lock);
+        Ok(entry)
+    }
+
     /// Loads the effective overrides view used during shaping.
     ///
     /// When adaptive layers are disabled, this is equivalent to loading the operator layer only.
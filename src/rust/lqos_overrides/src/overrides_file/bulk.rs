@@ -0,0 +1,1053 @@
+//! Bulk import/export of an overrides layer as JSON or CSV.
+//!
+//! JSON uses the override file format itself. CSV flattens every section into one table with a
+//! `record_type` column, so a provisioning system can emit only the columns it needs.
+
+use std::{
+    collections::{BTreeMap, HashMap},
+    net::{Ipv4Addr, Ipv6Addr},
+};
+
+use anyhow::Result;
+use lqos_config::ShapedDevice;
+use serde::{Deserialize, Serialize};
+
+use super::{
+    CircuitAdjustment, NetworkAdjustment, OverrideFile, OverrideLayer, UispRouteOverride,
+    file_lock::FileLock,
+    history::{
+        OverrideDiffEntry, SaveContext, circuit_adjustment_key, diff_override_files,
+        network_adjustment_key, save_layer_recorded, uisp_route_key,
+    },
+    load_layer_unlocked, normalize_sqm_override,
+};
+
+/// Serialization format for bulk import/export.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum BulkFormat {
+    /// The override file JSON format.
+    Json,
+    /// One flat table with a `record_type` column.
+    Csv,
+}
+
+/// How imported entries are combined with the current layer.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ImportMode {
+    /// Add or update the imported entries and keep everything else.
+    Merge,
+    /// Make the layer contain exactly the imported entries.
+    Replace,
+}
+
+/// An imported entry that could not be parsed or failed validation.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct ImportIssue {
+    /// Where the entry came from (`line 4`, `persistent_devices[2]`, ...).
+    pub location: String,
+    /// What is wrong with it.
+    pub message: String,
+}
+
+/// Why an imported entry conflicts with other data.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ConflictKind {
+    /// The import contains the same entry twice with different contents.
+    DuplicateInImport,
+    /// A merge would replace an existing entry with different contents.
+    ExistingEntry,
+    /// A persistent device uses an address or subnet already assigned to another device.
+    AddressOverlap,
+}
+
+/// A conflict detected while planning an import.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct ImportConflict {
+    /// Kind of conflict.
+    pub kind: ConflictKind,
+    /// Override section (`persistent_devices`, `circuit_adjustments`, ...).
+    pub section: String,
+    /// Identity of the entry within the section.
+    pub key: String,
+    /// Where the imported entry came from.
+    pub location: String,
+    /// Human-readable explanation.
+    pub message: String,
+}
+
+/// Outcome of planning (and possibly applying) a bulk import.
+#[derive(Serialize, Clone)]
+pub struct ImportReport {
+    /// Mode the import was planned with.
+    pub mode: ImportMode,
+    /// Number of entries read from the input.
+    pub entries: usize,
+    /// Parse and validation problems. Any issue blocks the import.
+    pub issues: Vec<ImportIssue>,
+    /// Conflicts found in the input or against the current layer.
+    pub conflicts: Vec<ImportConflict>,
+    /// Changes the import makes to the layer.
+    pub changes: Vec<OverrideDiffEntry>,
+    /// Whether the result was written to the layer.
+    pub applied: bool,
+    #[serde(skip)]
+    result: OverrideFile,
+}
+
+impl ImportReport {
+    /// Returns true if the import must not be applied. Conflicts with existing entries only
+    /// block when `allow_overwrite` is false.
+    pub fn is_blocked(&self, allow_overwrite: bool) -> bool {
+        !self.issues.is_empty()
+            || self
+                .conflicts
+                .iter()
+                .any(|c| c.kind != ConflictKind::ExistingEntry || !allow_overwrite)
+    }
+
+    /// The layer contents the import would produce.
+    pub fn result(&self) -> &OverrideFile {
+        &self.result
+    }
+}
+
+#[derive(Clone)]
+enum BulkItem {
+    Device(Box<ShapedDevice>),
+    Circuit(CircuitAdjustment),
+    Network(NetworkAdjustment),
+    RttExclusion(String),
+    UispBandwidth(String, (f32, f32)),
+    UispRoute(UispRouteOverride),
+}
+
+impl BulkItem {
+    fn section(&self) -> &'static str {
+        match self {
+            BulkItem::Device(_) => "persistent_devices",
+            BulkItem::Circuit(_) => "circuit_adjustments",
+            BulkItem::Network(_) => "network_adjustments",
+            BulkItem::RttExclusion(_) => "rtt_excluded_circuits",
+            BulkItem::UispBandwidth(..) => "uisp.bandwidth_overrides",
+            BulkItem::UispRoute(_) => "uisp.route_overrides",
+        }
+    }
+
+    /// Matches the keys used by [`diff_override_files`].
+    fn key(&self) -> String {
+        match self {
+            BulkItem::Device(device) => device.device_id.clone(),
+            BulkItem::Circuit(adj) => circuit_adjustment_key(adj),
+            BulkItem::Network(adj) => network_adjustment_key(adj),
+            BulkItem::RttExclusion(circuit_id) => circuit_id.clone(),
+            BulkItem::UispBandwidth(site, _) => site.clone(),
+            BulkItem::UispRoute(route) => uisp_route_key(route),
+        }
+    }
+
+    fn value(&self) -> serde_json::Value {
+        let value = match self {
+            BulkItem::Device(device) => serde_json::to_value(device),
+            BulkItem::Circuit(adj) => serde_json::to_value(adj),
+            BulkItem::Network(adj) => serde_json::to_value(adj),
+            BulkItem::RttExclusion(circuit_id) => serde_json::to_value(circuit_id),
+            BulkItem::UispBandwidth(_, rates) => serde_json::to_value(rates),
+            BulkItem::UispRoute(route) => serde_json::to_value(route),
+        };
+        value.unwrap_or(serde_json::Value::Null)
+    }
+}
+
+struct LocatedItem {
+    location: String,
+    item: BulkItem,
+}
+
+fn file_items(file: &OverrideFile) -> Vec<LocatedItem> {
+    let mut out = Vec::new();
+    let mut push = |location: String, item: BulkItem| out.push(LocatedItem { location, item });
+    for (i, device) in file.persistent_devices.iter().enumerate() {
+        push(
+            format!("persistent_devices[{i}]"),
+            BulkItem::Device(Box::new(device.clone())),
+        );
+    }
+    for (i, adj) in file.circuit_adjustments.iter().enumerate() {
+        push(
+            format!("circuit_adjustments[{i}]"),
+            BulkItem::Circuit(adj.clone()),
+        );
+    }
+    for (i, adj) in file.network_adjustments.iter().enumerate() {
+        push(
+            format!("network_adjustments[{i}]"),
+            BulkItem::Network(adj.clone()),
+        );
+    }
+    for (i, circuit_id) in file.rtt_excluded_circuits.iter().enumerate() {
+        push(
+            format!("rtt_excluded_circuits[{i}]"),
+            BulkItem::RttExclusion(circuit_id.clone()),
+        );
+    }
+    if let Some(uisp) = &file.uisp {
+        let mut sites: Vec<_> = uisp.bandwidth_overrides.iter().collect();
+        sites.sort_by(|a, b| a.0.cmp(b.0));
+        for (site, rates) in sites {
+            push(
+                format!("uisp.bandwidth_overrides[{site}]"),
+                BulkItem::UispBandwidth(site.clone(), *rates),
+            );
+        }
+        for (i, route) in uisp.route_overrides.iter().enumerate() {
+            push(
+                format!("uisp.route_overrides[{i}]"),
+                BulkItem::UispRoute(route.clone()),
+            );
+        }
+    }
+    out
+}
+
+/// Replaces the first entry with the same key (dropping any later duplicates) or appends.
+fn upsert_keyed<T>(list: &mut Vec<T>, item: T, key: impl Fn(&T) -> String) {
+    let wanted = key(&item);
+    match list.iter().position(|existing| key(existing) == wanted) {
+        Some(first) => {
+            list[first] = item;
+            let mut index = 0;
+            list.retain(|existing| {
+                let keep = index <= first || key(existing) != wanted;
+                index += 1;
+                keep
+            });
+        }
+        None => list.push(item),
+    }
+}
+
+fn apply_item(file: &mut OverrideFile, item: BulkItem) {
+    match item {
+        BulkItem::Device(device) => upsert_keyed(&mut file.persistent_devices, *device, |d| {
+            d.device_id.clone()
+        }),
+        BulkItem::Circuit(adj) => {
+            upsert_keyed(&mut file.circuit_adjustments, adj, circuit_adjustment_key)
+        }
+        BulkItem::Network(adj) => {
+            upsert_keyed(&mut file.network_adjustments, adj, network_adjustment_key)
+        }
+        BulkItem::RttExclusion(circuit_id) => {
+            if !file.rtt_excluded_circuits.contains(&circuit_id) {
+                file.rtt_excluded_circuits.push(circuit_id);
+            }
+        }
+        BulkItem::UispBandwidth(site, rates) => {
+            file.uisp
+                .get_or_insert_with(Default::default)
+                .bandwidth_overrides
+                .insert(site, rates);
+        }
+        BulkItem::UispRoute(route) => upsert_keyed(
+            &mut file
+                .uisp
+                .get_or_insert_with(Default::default)
+                .route_overrides,
+            route,
+            uisp_route_key,
+        ),
+    }
+}
+
+fn check_rate(name: &str, rate: Option<f32>, out: &mut Vec<String>) {
+    if let Some(rate) = rate
+        && (!rate.is_finite() || rate < 0.01)
+    {
+        out.push(format!("{name} must be at least 0.01 Mbps (got {rate})"));
+    }
+}
+
+fn check_not_empty(name: &str, value: &str, out: &mut Vec<String>) {
+    if value.trim().is_empty() {
+        out.push(format!("{name} must not be empty"));
+    }
+}
+
+fn check_sqm(sqm: Option<&str>, out: &mut Vec<String>) {
+    if let Some(sqm) = sqm
+        && let Err(e) = normalize_sqm_override(sqm)
+    {
+        out.push(e.to_string());
+    }
+}
+
+fn validate_item(item: &BulkItem) -> Vec<String> {
+    let mut out = Vec::new();
+    match item {
+        BulkItem::Device(device) => {
+            check_not_empty("circuit_id", &device.circuit_id, &mut out);
+            check_not_empty("device_id", &device.device_id, &mut out);
+            check_rate(
+                "download_min_mbps",
+                Some(device.download_min_mbps),
+                &mut out,
+            );
+            check_rate("upload_min_mbps", Some(device.upload_min_mbps), &mut out);
+            check_rate(
+                "download_max_mbps",
+                Some(device.download_max_mbps),
+                &mut out,
+            );
+            check_rate("upload_max_mbps", Some(device.upload_max_mbps), &mut out);
+            if device.ipv4.iter().any(|(_, prefix)| *prefix > 32)
+                || device.ipv6.iter().any(|(_, prefix)| *prefix > 128)
+            {
+                out.push("address prefix length out of range".to_string());
+            }
+            check_sqm(device.sqm_override.as_deref(), &mut out);
+        }
+        BulkItem::Circuit(adj) => match adj {
+            CircuitAdjustment::CircuitAdjustSpeed {
+                circuit_id: id,
+                min_download_bandwidth,
+                max_download_bandwidth,
+                min_upload_bandwidth,
+                max_upload_bandwidth,
+            }
+            | CircuitAdjustment::DeviceAdjustSpeed {
+                device_id: id,
+                min_download_bandwidth,
+                max_download_bandwidth,
+                min_upload_bandwidth,
+                max_upload_bandwidth,
+            } => {
+                check_not_empty("id", id, &mut out);
+                let rates = [
+                    ("download_min_mbps", *min_download_bandwidth),
+                    ("download_max_mbps", *max_download_bandwidth),
+                    ("upload_min_mbps", *min_upload_bandwidth),
+                    ("upload_max_mbps", *max_upload_bandwidth),
+                ];
+                if rates.iter().all(|(_, rate)| rate.is_none()) {
+                    out.push("at least one rate must be set".to_string());
+                }
+                for (name, rate) in rates {
+                    check_rate(name, rate, &mut out);
+                }
+            }
+            CircuitAdjustment::DeviceAdjustSqm {
+                device_id,
+                sqm_override,
+            } => {
+                check_not_empty("device_id", device_id, &mut out);
+                check_sqm(sqm_override.as_deref(), &mut out);
+            }
+            CircuitAdjustment::RemoveCircuit { circuit_id } => {
+                check_not_empty("circuit_id", circuit_id, &mut out);
+            }
+            CircuitAdjustment::RemoveDevice { device_id } => {
+                check_not_empty("device_id", device_id, &mut out);
+            }
+            CircuitAdjustment::ReparentCircuit {
+                circuit_id,
+                parent_node,
+            } => {
+                check_not_empty("circuit_id", circuit_id, &mut out);
+                check_not_empty("parent_node", parent_node, &mut out);
+            }
+        },
+        BulkItem::Network(adj) => match adj {
+            NetworkAdjustment::AdjustSiteSpeed {
+                site_name,
+                download_bandwidth_mbps,
+                upload_bandwidth_mbps,
+                ..
+            } => {
+                check_not_empty("site_name", site_name, &mut out);
+                if download_bandwidth_mbps.is_none() && upload_bandwidth_mbps.is_none() {
+                    out.push("at least one rate must be set".to_string());
+                }
+                check_rate("download_max_mbps", *download_bandwidth_mbps, &mut out);
+                check_rate("upload_max_mbps", *upload_bandwidth_mbps, &mut out);
+            }
+            NetworkAdjustment::SetNodeVirtual { node_name, .. } => {
+                check_not_empty("site_name", node_name, &mut out);
+            }
+        },
+        BulkItem::RttExclusion(circuit_id) => check_not_empty("circuit_id", circuit_id, &mut out),
+        BulkItem::UispBandwidth(site, (down, up)) => {
+            check_not_empty("site_name", site, &mut out);
+            check_rate("download_max_mbps", Some(*down), &mut out);
+            check_rate("upload_max_mbps", Some(*up), &mut out);
+        }
+        BulkItem::UispRoute(route) => {
+            check_not_empty("from_site", &route.from_site, &mut out);
+            check_not_empty("to_site", &route.to_site, &mut out);
+        }
+    }
+    out
+}
+
+/// Reports persistent devices that share an exact address or subnet, where at least one of
+/// them came from the import.
+fn address_overlaps(
+    result: &OverrideFile,
+    imported: &HashMap<String, String>,
+    out: &mut Vec<ImportConflict>,
+) {
+    let mut owners: BTreeMap<String, &str> = BTreeMap::new();
+    for device in &result.persistent_devices {
+        let addresses = device
+            .ipv4
+            .iter()
+            .map(|(ip, prefix)| format!("{ip}/{prefix}"))
+            .chain(
+                device
+                    .ipv6
+                    .iter()
+                    .map(|(ip, prefix)| format!("{ip}/{prefix}")),
+            );
+        for address in addresses {
+            match owners.get(address.as_str()) {
+                Some(owner) if *owner != device.device_id => {
+                    let location = imported
+                        .get(&device.device_id)
+                        .or_else(|| imported.get(*owner));
+                    if let Some(location) = location {
+                        out.push(ImportConflict {
+                            kind: ConflictKind::AddressOverlap,
+                            section: "persistent_devices".to_string(),
+                            key: device.device_id.clone(),
+                            location: location.clone(),
+                            message: format!("{address} is also assigned to device '{owner}'"),
+                        });
+                    }
+                }
+                Some(_) => {}
+                None => {
+                    owners.insert(address, &device.device_id);
+                }
+            }
+        }
+    }
+}
+
+/// Plans importing `input` into a layer whose current contents are `existing`.
+///
+/// The report lists parse/validation issues, conflicts (duplicates within the input, differing
+/// existing entries in merge mode, overlapping device addresses) and the resulting changes.
+pub fn plan_import(
+    existing: &OverrideFile,
+    input: &str,
+    format: BulkFormat,
+    mode: ImportMode,
+) -> ImportReport {
+    let (items, mut issues) = match format {
+        BulkFormat::Json => parse_json(input),
+        BulkFormat::Csv => parse_csv(input),
+    };
+    for located in &items {
+        for message in validate_item(&located.item) {
+            issues.push(ImportIssue {
+                location: located.location.clone(),
+                message,
+            });
+        }
+    }
+
+    let mut conflicts = Vec::new();
+    let mut seen: HashMap<(&'static str, String), (String, serde_json::Value)> = HashMap::new();
+    for located in &items {
+        let id = (located.item.section(), located.item.key());
+        let value = located.item.value();
+        match seen.get(&id) {
+            Some((first_location, first_value)) if *first_value != value => {
+                conflicts.push(ImportConflict {
+                    kind: ConflictKind::DuplicateInImport,
+                    section: id.0.to_string(),
+                    key: id.1.clone(),
+                    location: located.location.clone(),
+                    message: format!("differs from the entry at {first_location}"),
+                });
+            }
+            Some(_) => {}
+            None => {
+                seen.insert(id, (located.location.clone(), value));
+            }
+        }
+    }
+
+    if mode == ImportMode::Merge {
+        let current: HashMap<(&'static str, String), serde_json::Value> = file_items(existing)
+            .into_iter()
+            .map(|located| {
+                (
+                    (located.item.section(), located.item.key()),
+                    located.item.value(),
+                )
+            })
+            .collect();
+        for located in &items {
+            let id = (located.item.section(), located.item.key());
+            if let Some(old) = current.get(&id)
+                && *old != located.item.value()
+            {
+                conflicts.push(ImportConflict {
+                    kind: ConflictKind::ExistingEntry,
+                    section: id.0.to_string(),
+                    key: id.1,
+                    location: located.location.clone(),
+                    message: "would replace an existing entry with different contents".to_string(),
+                });
+            }
+        }
+    }
+
+    let mut result = match mode {
+        ImportMode::Merge => existing.clone(),
+        ImportMode::Replace => OverrideFile::default(),
+    };
+    let mut imported_devices = HashMap::new();
+    for located in &items {
+        if let BulkItem::Device(device) = &located.item {
+            imported_devices
+                .entry(device.device_id.clone())
+                .or_insert_with(|| located.location.clone());
+        }
+        apply_item(&mut result, located.item.clone());
+    }
+    address_overlaps(&result, &imported_devices, &mut conflicts);
+
+    ImportReport {
+        mode,
+        entries: items.len(),
+        issues,
+        conflicts,
+        changes: diff_override_files(existing, &result),
+        applied: false,
+        result,
+    }
+}
+
+fn parse_json(input: &str) -> (Vec<LocatedItem>, Vec<ImportIssue>) {
+    match serde_json::from_str::<OverrideFile>(input) {
+        Ok(file) => (file_items(&file), Vec::new()),
+        Err(e) => (
+            Vec::new(),
+            vec![ImportIssue {
+                location: format!("line {}", e.line()),
+                message: format!("invalid overrides JSON: {e}"),
+            }],
+        ),
+    }
+}
+
+/// One CSV row. Columns that a record type does not use are left empty.
+#[derive(Serialize, Deserialize, Default, Debug)]
+#[serde(default)]
+struct BulkCsvRow {
+    record_type: String,
+    circuit_id: String,
+    circuit_name: String,
+    device_id: String,
+    device_name: String,
+    parent_node: String,
+    node_id: String,
+    site_name: String,
+    mac: String,
+    ipv4: String,
+    ipv6: String,
+    download_min_mbps: Option<f32>,
+    upload_min_mbps: Option<f32>,
+    download_max_mbps: Option<f32>,
+    upload_max_mbps: Option<f32>,
+    sqm_override: String,
+    comment: String,
+    #[serde(rename = "virtual")]
+    virtual_node: Option<bool>,
+    from_site: String,
+    to_site: String,
+    cost: Option<u32>,
+}
+
+fn non_empty(value: &str) -> Option<String> {
+    let trimmed = value.trim();
+    (!trimmed.is_empty()).then(|| trimmed.to_string())
+}
+
+fn required<T>(name: &str, value: Option<T>) -> std::result::Result<T, String> {
+    value.ok_or_else(|| format!("{name} is required"))
+}
+
+fn split_addresses(list: &str) -> impl Iterator<Item = &str> {
+    list.split([',', ';', ' '])
+        .map(str::trim)
+        .filter(|s| !s.is_empty())
+}
+
+fn parse_address<T: std::str::FromStr>(
+    raw: &str,
+    default_prefix: u32,
+) -> std::result::Result<(T, u32), String> {
+    let (ip, prefix) = match raw.split_once('/') {
+        Some((ip, prefix)) => (
+            ip,
+            prefix
+                .parse()
+                .map_err(|_| format!("invalid prefix length in '{raw}'"))?,
+        ),
+        None => (raw, default_prefix),
+    };
+    let ip = ip.parse().map_err(|_| format!("invalid address '{raw}'"))?;
+    Ok((ip, prefix))
+}
+
+fn format_addresses<T: std::fmt::Display>(list: &[(T, u32)], host_prefix: u32) -> String {
+    list.iter()
+        .map(|(ip, prefix)| {
+            if *prefix == host_prefix {
+                ip.to_string()
+            } else {
+                format!("{ip}/{prefix}")
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+fn row_to_item(row: BulkCsvRow) -> std::result::Result<BulkItem, String> {
+    let item = match row.record_type.trim().to_ascii_lowercase().as_str() {
+        "persistent_device" => BulkItem::Device(Box::new(ShapedDevice {
+            ipv4: split_addresses(&row.ipv4)
+                .map(|raw| parse_address::<Ipv4Addr>(raw, 32))
+                .collect::<std::result::Result<_, _>>()?,
+            ipv6: split_addresses(&row.ipv6)
+                .map(|raw| parse_address::<Ipv6Addr>(raw, 128))
+                .collect::<std::result::Result<_, _>>()?,
+            download_min_mbps: required("download_min_mbps", row.download_min_mbps)?,
+            upload_min_mbps: required("upload_min_mbps", row.upload_min_mbps)?,
+            download_max_mbps: required("download_max_mbps", row.download_max_mbps)?,
+            upload_max_mbps: required("upload_max_mbps", row.upload_max_mbps)?,
+            sqm_override: normalize_sqm_override(&row.sqm_override).map_err(|e| e.to_string())?,
+            circuit_id: row.circuit_id,
+            circuit_name: row.circuit_name,
+            device_id: row.device_id,
+            device_name: row.device_name,
+            parent_node: row.parent_node,
+            mac: row.mac,
+            comment: row.comment,
+            ..ShapedDevice::default()
+        })),
+        "circuit_adjust_speed" => BulkItem::Circuit(CircuitAdjustment::CircuitAdjustSpeed {
+            circuit_id: row.circuit_id,
+            min_download_bandwidth: row.download_min_mbps,
+            max_download_bandwidth: row.download_max_mbps,
+            min_upload_bandwidth: row.upload_min_mbps,
+            max_upload_bandwidth: row.upload_max_mbps,
+        }),
+        "device_adjust_speed" => BulkItem::Circuit(CircuitAdjustment::DeviceAdjustSpeed {
+            device_id: row.device_id,
+            min_download_bandwidth: row.download_min_mbps,
+            max_download_bandwidth: row.download_max_mbps,
+            min_upload_bandwidth: row.upload_min_mbps,
+            max_upload_bandwidth: row.upload_max_mbps,
+        }),
+        "device_adjust_sqm" => BulkItem::Circuit(CircuitAdjustment::DeviceAdjustSqm {
+            device_id: row.device_id,
+            sqm_override: non_empty(&row.sqm_override),
+        }),
+        "remove_circuit" => BulkItem::Circuit(CircuitAdjustment::RemoveCircuit {
+            circuit_id: row.circuit_id,
+        }),
+        "remove_device" => BulkItem::Circuit(CircuitAdjustment::RemoveDevice {
+            device_id: row.device_id,
+        }),
+        "reparent_circuit" => BulkItem::Circuit(CircuitAdjustment::ReparentCircuit {
+            circuit_id: row.circuit_id,
+            parent_node: row.parent_node,
+        }),
+        "adjust_site_speed" => BulkItem::Network(NetworkAdjustment::AdjustSiteSpeed {
+            node_id: non_empty(&row.node_id),
+            site_name: row.site_name,
+            download_bandwidth_mbps: row.download_max_mbps,
+            upload_bandwidth_mbps: row.upload_max_mbps,
+        }),
+        "set_node_virtual" => BulkItem::Network(NetworkAdjustment::SetNodeVirtual {
+            node_name: row.site_name,
+            virtual_node: required("virtual", row.virtual_node)?,
+        }),
+        "rtt_exclusion" => BulkItem::RttExclusion(row.circuit_id),
+        "uisp_bandwidth" => BulkItem::UispBandwidth(
+            row.site_name,
+            (
+                required("download_max_mbps", row.download_max_mbps)?,
+                required("upload_max_mbps", row.upload_max_mbps)?,
+            ),
+        ),
+        "uisp_route" => BulkItem::UispRoute(UispRouteOverride {
+            from_site: row.from_site,
+            to_site: row.to_site,
+            cost: required("cost", row.cost)?,
+        }),
+        "" => return Err("record_type is required".to_string()),
+        other => return Err(format!("unknown record_type '{other}'")),
+    };
+    Ok(item)
+}
+
+fn item_to_row(item: &BulkItem) -> BulkCsvRow {
+    match item {
+        BulkItem::Device(device) => BulkCsvRow {
+            record_type: "persistent_device".to_string(),
+            circuit_id: device.circuit_id.clone(),
+            circuit_name: device.circuit_name.clone(),
+            device_id: device.device_id.clone(),
+            device_name: device.device_name.clone(),
+            parent_node: device.parent_node.clone(),
+            mac: device.mac.clone(),
+            ipv4: format_addresses(&device.ipv4, 32),
+            ipv6: format_addresses(&device.ipv6, 128),
+            download_min_mbps: Some(device.download_min_mbps),
+            upload_min_mbps: Some(device.upload_min_mbps),
+            download_max_mbps: Some(device.download_max_mbps),
+            upload_max_mbps: Some(device.upload_max_mbps),
+            sqm_override: device.sqm_override.clone().unwrap_or_default(),
+            comment: device.comment.clone(),
+            ..BulkCsvRow::default()
+        },
+        BulkItem::Circuit(adj) => match adj {
+            CircuitAdjustment::CircuitAdjustSpeed {
+                circuit_id,
+                min_download_bandwidth,
+                max_download_bandwidth,
+                min_upload_bandwidth,
+                max_upload_bandwidth,
+            } => BulkCsvRow {
+                record_type: "circuit_adjust_speed".to_string(),
+                circuit_id: circuit_id.clone(),
+                download_min_mbps: *min_download_bandwidth,
+                download_max_mbps: *max_download_bandwidth,
+                upload_min_mbps: *min_upload_bandwidth,
+                upload_max_mbps: *max_upload_bandwidth,
+                ..BulkCsvRow::default()
+            },
+            CircuitAdjustment::DeviceAdjustSpeed {
+                device_id,
+                min_download_bandwidth,
+                max_download_bandwidth,
+                min_upload_bandwidth,
+                max_upload_bandwidth,
+            } => BulkCsvRow {
+                record_type: "device_adjust_speed".to_string(),
+                device_id: device_id.clone(),
+                download_min_mbps: *min_download_bandwidth,
+                download_max_mbps: *max_download_bandwidth,
+                upload_min_mbps: *min_upload_bandwidth,
+                upload_max_mbps: *max_upload_bandwidth,
+                ..BulkCsvRow::default()
+            },
+            CircuitAdjustment::DeviceAdjustSqm {
+                device_id,
+                sqm_override,
+            } => BulkCsvRow {
+                record_type: "device_adjust_sqm".to_string(),
+                device_id: device_id.clone(),
+                sqm_override: sqm_override.clone().unwrap_or_default(),
+                ..BulkCsvRow::default()
+            },
+            CircuitAdjustment::RemoveCircuit { circuit_id } => BulkCsvRow {
+                record_type: "remove_circuit".to_string(),
+                circuit_id: circuit_id.clone(),
+                ..BulkCsvRow::default()
+            },
+            CircuitAdjustment::RemoveDevice { device_id } => BulkCsvRow {
+                record_type: "remove_device".to_string(),
+                device_id: device_id.clone(),
+                ..BulkCsvRow::default()
+            },
+            CircuitAdjustment::ReparentCircuit {
+                circuit_id,
+                parent_node,
+            } => BulkCsvRow {
+                record_type: "reparent_circuit".to_string(),
+                circuit_id: circuit_id.clone(),
+                parent_node: parent_node.clone(),
+                ..BulkCsvRow::default()
+            },
+        },
+        BulkItem::Network(adj) => match adj {
+            NetworkAdjustment::AdjustSiteSpeed {
+                node_id,
+                site_name,
+                download_bandwidth_mbps,
+                upload_bandwidth_mbps,
+            } => BulkCsvRow {
+                record_type: "adjust_site_speed".to_string(),
+                node_id: node_id.clone().unwrap_or_default(),
+                site_name: site_name.clone(),
+                download_max_mbps: *download_bandwidth_mbps,
+                upload_max_mbps: *upload_bandwidth_mbps,
+                ..BulkCsvRow::default()
+            },
+            NetworkAdjustment::SetNodeVirtual {
+                node_name,
+                virtual_node,
+            } => BulkCsvRow {
+                record_type: "set_node_virtual".to_string(),
+                site_name: node_name.clone(),
+                virtual_node: Some(*virtual_node),
+                ..BulkCsvRow::default()
+            },
+        },
+        BulkItem::RttExclusion(circuit_id) => BulkCsvRow {
+            record_type: "rtt_exclusion".to_string(),
+            circuit_id: circuit_id.clone(),
+            ..BulkCsvRow::default()
+        },
+        BulkItem::UispBandwidth(site, (down, up)) => BulkCsvRow {
+            record_type: "uisp_bandwidth".to_string(),
+            site_name: site.clone(),
+            download_max_mbps: Some(*down),
+            upload_max_mbps: Some(*up),
+            ..BulkCsvRow::default()
+        },
+        BulkItem::UispRoute(route) => BulkCsvRow {
+            record_type: "uisp_route".to_string(),
+            from_site: route.from_site.clone(),
+            to_site: route.to_site.clone(),
+            cost: Some(route.cost),
+            ..BulkCsvRow::default()
+        },
+    }
+}
+
+fn parse_csv(input: &str) -> (Vec<LocatedItem>, Vec<ImportIssue>) {
+    let mut items = Vec::new();
+    let mut issues = Vec::new();
+    let mut reader = csv::ReaderBuilder::new()
+        .trim(csv::Trim::All)
+        .flexible(true)
+        .from_reader(input.as_bytes());
+    let headers = match reader.headers() {
+        Ok(headers) if headers.iter().any(|h| h == "record_type") => headers.clone(),
+        Ok(_) => {
+            issues.push(ImportIssue {
+                location: "line 1".to_string(),
+                message: "CSV header must include a record_type column".to_string(),
+            });
+            return (items, issues);
+        }
+        Err(e) => {
+            issues.push(ImportIssue {
+                location: "line 1".to_string(),
+                message: format!("unreadable CSV header: {e}"),
+            });
+            return (items, issues);
+        }
+    };
+
+    for record in reader.records() {
+        let record = match record {
+            Ok(record) => record,
+            Err(e) => {
+                let line = e.position().map_or(0, |p| p.line());
+                issues.push(ImportIssue {
+                    location: format!("line {line}"),
+                    message: format!("unreadable CSV row: {e}"),
+                });
+                continue;
+            }
+        };
+        let location = format!("line {}", record.position().map_or(0, |p| p.line()));
+        let parsed = record
+            .deserialize::<BulkCsvRow>(Some(&headers))
+            .map_err(|e| format!("invalid value: {e}"))
+            .and_then(row_to_item);
+        match parsed {
+            Ok(item) => items.push(LocatedItem { location, item }),
+            Err(message) => issues.push(ImportIssue { location, message }),
+        }
+    }
+    (items, issues)
+}
+
+/// Serializes an overrides file for bulk editing. Output of either format can be fed back to
+/// [`plan_import`] unchanged.
+pub fn export_override_file(file: &OverrideFile, format: BulkFormat) -> Result<String> {
+    match format {
+        BulkFormat::Json => Ok(serde_json::to_string_pretty(file)?),
+        BulkFormat::Csv => {
+            let mut writer = csv::Writer::from_writer(Vec::new());
+            let items = file_items(file);
+            if items.is_empty() {
+                // Headers are only emitted alongside a row; keep just the header line.
+                writer.serialize(BulkCsvRow::default())?;
+                let header_only = String::from_utf8(writer.into_inner()?)?;
+                return Ok(header_only.lines().next().unwrap_or_default().to_string() + "\n");
+            }
+            for located in &items {
+                writer.serialize(item_to_row(&located.item))?;
+            }
+            Ok(String::from_utf8(writer.into_inner()?)?)
+        }
+    }
+}
+
+impl super::OverrideStore {
+    /// Serializes a layer for bulk editing.
+    ///
+    /// Side effects: acquires the global overrides lock; may create an empty operator file.
+    pub fn export_layer(layer: OverrideLayer, format: BulkFormat) -> Result<String> {
+        let lock = FileLock::new()?;
+        let config = lqos_config::load_config()?;
+        let overrides = load_layer_unlocked(&config, layer)?;
+        drop(lock);
+        export_override_file(&overrides, format)
+    }
+
+    /// Plans an import against the current layer and, unless it is blocked (see
+    /// [`ImportReport::is_blocked`]) or changes nothing, writes the result as a recorded version.
+    ///
+    /// Side effects: acquires the global overrides lock and may write the layer and its history.
+    pub fn import_layer(
+        layer: OverrideLayer,
+        input: &str,
+        format: BulkFormat,
+        mode: ImportMode,
+        allow_overwrite: bool,
+        ctx: &SaveContext,
+    ) -> Result<ImportReport> {
+        let lock = FileLock::new()?;
+        let config = lqos_config::load_config()?;
+        let existing = load_layer_unlocked(&config, layer)?;
+        let mut report = plan_import(&existing, input, format, mode);
+        if !report.is_blocked(allow_overwrite) && !report.changes.is_empty() {
+            save_layer_recorded(&config, layer, &report.result, ctx)?;
+            report.applied = true;
+        }
+        drop(lock);
+        Ok(report)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample() -> OverrideFile {
+        let mut file = OverrideFile::default();
+        file.add_persistent_shaped_device_return_changed(ShapedDevice {
+            circuit_id: "c1".to_string(),
+            circuit_name: "Circuit 1".to_string(),
+            device_id: "d1".to_string(),
+            device_name: "Device 1".to_string(),
+            parent_node: "Tower1".to_string(),
+            ipv4: vec![
+                ("100.64.0.2".parse().unwrap(), 32),
+                ("10.0.0.0".parse().unwrap(), 24),
+            ],
+            ipv6: vec![("2001:db8::".parse().unwrap(), 64)],
+            download_min_mbps: 5.0,
+            upload_min_mbps: 1.0,
+            download_max_mbps: 100.0,
+            upload_max_mbps: 20.5,
+            comment: "imported, with comma".to_string(),
+            sqm_override: Some("cake/fq_codel".to_string()),
+            ..ShapedDevice::default()
+        });
+        file.add_circuit_adjustment(CircuitAdjustment::CircuitAdjustSpeed {
+            circuit_id: "c2".to_string(),
+            min_download_bandwidth: None,
+            max_download_bandwidth: Some(50.0),
+            min_upload_bandwidth: None,
+            max_upload_bandwidth: None,
+        });
+        file.add_circuit_adjustment(CircuitAdjustment::ReparentCircuit {
+            circuit_id: "c3".to_string(),
+            parent_node: "Tower2".to_string(),
+        });
+        file.set_site_bandwidth_override(
+            Some("node-7".to_string()),
+            "Tower1".to_string(),
+            Some(900.0),
+            Some(300.0),
+        );
+        file.set_network_node_virtual("AP1".to_string(), true);
+        file.set_circuit_rtt_excluded_return_changed("c9", true);
+        file.set_uisp_bandwidth_override("SiteA".to_string(), 200.0, 40.0);
+        file.add_uisp_route_override("SiteA".to_string(), "SiteB".to_string(), 10);
+        file
+    }
+
+    #[test]
+    fn csv_and_json_exports_round_trip() {
+        let original = sample();
+        for format in [BulkFormat::Csv, BulkFormat::Json] {
+            let exported = export_override_file(&original, format).unwrap();
+            let report = plan_import(
+                &OverrideFile::default(),
+                &exported,
+                format,
+                ImportMode::Replace,
+            );
+            assert!(report.issues.is_empty(), "{format:?}: {:?}", report.issues);
+            assert!(report.conflicts.is_empty(), "{format:?}");
+            assert_eq!(report.entries, 8);
+            assert!(diff_override_files(&original, report.result()).is_empty());
+        }
+    }
+
+    #[test]
+    fn merge_reports_issues_and_conflicts() {
+        let existing = sample();
+        let csv = "\
+record_type,circuit_id,device_id,site_name,download_max_mbps,upload_max_mbps,ipv4,download_min_mbps,upload_min_mbps
+circuit_adjust_speed,c2,,,75,,,,
+circuit_adjust_speed,c4,,,10,,,,
+circuit_adjust_speed,c4,,,20,,,,
+adjust_site_speed,,,Tower3,-5,,,,
+persistent_device,c5,d5,,10,10,100.64.0.2,1,1
+mystery,,,,,,,,
+";
+        let report = plan_import(&existing, csv, BulkFormat::Csv, ImportMode::Merge);
+        let issues: Vec<&str> = report.issues.iter().map(|i| i.location.as_str()).collect();
+        assert_eq!(issues, vec!["line 7", "line 5"]);
+
+        let conflicts: Vec<(ConflictKind, &str)> = report
+            .conflicts
+            .iter()
+            .map(|c| (c.kind, c.key.as_str()))
+            .collect();
+        assert_eq!(
+            conflicts,
+            vec![
+                (ConflictKind::DuplicateInImport, "circuit_speed:c4"),
+                (ConflictKind::ExistingEntry, "circuit_speed:c2"),
+                (ConflictKind::AddressOverlap, "d5"),
+            ]
+        );
+        assert!(report.is_blocked(true));
+    }
+
+    #[test]
+    fn replace_removes_entries_missing_from_input() {
+        let existing = sample();
+        let csv = "record_type,circuit_id\nrtt_exclusion,c9\n";
+        let report = plan_import(&existing, csv, BulkFormat::Csv, ImportMode::Replace);
+        assert!(!report.is_blocked(false));
+        assert!(
+            report
+                .changes
+                .iter()
+                .all(|change| change.kind == crate::DiffKind::Removed)
+        );
+        assert_eq!(report.changes.len(), 7);
+        assert_eq!(
+            report.result().rtt_excluded_circuits,
+            vec!["c9".to_string()]
+        );
+    }
+}
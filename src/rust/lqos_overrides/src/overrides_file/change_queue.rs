@@ -0,0 +1,503 @@
+//! Change-control queue for overrides proposed by automated actors.
+//!
+//! When `[change_control].require_approval` is enabled, persisted changes from StormGuard and
+//! TreeGuard are written to `lqos_overrides.pending.json` instead of their override layer, and
+//! only reach the layer once an administrator approves them.
+
+use std::{
+    path::{Path, PathBuf},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use anyhow::{Result, anyhow};
+use lqos_config::ChangeControlConfig;
+use serde::{Deserialize, Serialize};
+
+use super::{
+    OverrideFile, OverrideLayer,
+    file_lock::FileLock,
+    history::{ChangeAuthor, SaveContext, save_layer_recorded},
+    load_layer_unlocked,
+};
+
+const PENDING_CHANGES_FILE: &str = "lqos_overrides.pending.json";
+
+/// A persisted override change proposed by an automated actor.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ProposedChange {
+    /// Sets (or, when both rates are `None`, removes) a site bandwidth override.
+    SetSiteBandwidth {
+        /// Site name to update.
+        site_name: String,
+        /// Replacement download bandwidth in Mbps.
+        download_bandwidth_mbps: Option<f32>,
+        /// Replacement upload bandwidth in Mbps.
+        upload_bandwidth_mbps: Option<f32>,
+    },
+    /// Sets an SQM override token for a group of devices.
+    SetDeviceSqm {
+        /// Device identifiers to update.
+        device_ids: Vec<String>,
+        /// SQM override token to apply.
+        sqm_override: String,
+    },
+    /// Removes SQM overrides for a group of devices.
+    ClearDeviceSqm {
+        /// Device identifiers to clear.
+        device_ids: Vec<String>,
+    },
+    /// Removes a node-virtualization override.
+    ClearNodeVirtual {
+        /// Node name to clear.
+        node_name: String,
+    },
+}
+
+impl ProposedChange {
+    /// Applies the change to an overrides file. Returns true if the file changed.
+    pub fn apply_to(&self, overrides: &mut OverrideFile) -> bool {
+        match self {
+            ProposedChange::SetSiteBandwidth {
+                site_name,
+                download_bandwidth_mbps: None,
+                upload_bandwidth_mbps: None,
+            } => overrides.remove_site_bandwidth_override_count(None, site_name) > 0,
+            ProposedChange::SetSiteBandwidth {
+                site_name,
+                download_bandwidth_mbps,
+                upload_bandwidth_mbps,
+            } => overrides.set_site_bandwidth_override(
+                None,
+                site_name.clone(),
+                *download_bandwidth_mbps,
+                *upload_bandwidth_mbps,
+            ),
+            ProposedChange::SetDeviceSqm {
+                device_ids,
+                sqm_override,
+            } => {
+                let mut changed = false;
+                for device_id in device_ids {
+                    if overrides.set_device_sqm_override_return_changed(
+                        device_id.clone(),
+                        Some(sqm_override.clone()),
+                    ) {
+                        changed = true;
+                    }
+                    if overrides.remove_persistent_shaped_device_by_device_count(device_id) > 0 {
+                        changed = true;
+                    }
+                }
+                changed
+            }
+            ProposedChange::ClearDeviceSqm { device_ids } => {
+                let mut changed = false;
+                for device_id in device_ids {
+                    if overrides.remove_device_sqm_override_by_device_count(device_id) > 0 {
+                        changed = true;
+                    }
+                    if overrides.remove_persistent_shaped_device_by_device_count(device_id) > 0 {
+                        changed = true;
+                    }
+                }
+                changed
+            }
+            ProposedChange::ClearNodeVirtual { node_name } => {
+                overrides.remove_network_node_virtual_by_name_count(node_name) > 0
+            }
+        }
+    }
+
+    /// True for changes that only touch SQM (qdisc) selection.
+    pub fn is_sqm(&self) -> bool {
+        matches!(
+            self,
+            ProposedChange::SetDeviceSqm { .. } | ProposedChange::ClearDeviceSqm { .. }
+        )
+    }
+
+    /// Key identifying the object this change targets. A newer proposal for the same target
+    /// replaces any older pending one.
+    fn target_key(&self) -> String {
+        match self {
+            ProposedChange::SetSiteBandwidth { site_name, .. } => format!("site:{site_name}"),
+            ProposedChange::SetDeviceSqm { device_ids, .. }
+            | ProposedChange::ClearDeviceSqm { device_ids } => {
+                let mut ids = device_ids.clone();
+                ids.sort();
+                format!("devices:{}", ids.join(","))
+            }
+            ProposedChange::ClearNodeVirtual { node_name } => format!("node:{node_name}"),
+        }
+    }
+}
+
+/// A change submitted by an automated actor, before it is applied or queued.
+#[derive(Clone, Debug)]
+pub struct ChangeProposal {
+    /// Layer the change will be written to.
+    pub layer: OverrideLayer,
+    /// Site the change affects, used by `auto_approve_sites`.
+    pub site: Option<String>,
+    /// Human-readable description shown to approvers.
+    pub summary: String,
+    /// Size of a bandwidth change in percent of the configured rate, used by
+    /// `auto_approve_max_change_pct`.
+    pub magnitude_pct: Option<f32>,
+    /// The change itself.
+    pub change: ProposedChange,
+}
+
+/// A change waiting for administrator approval.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct PendingChange {
+    /// Queue-unique identifier.
+    pub id: u64,
+    /// Layer the change will be written to once approved.
+    pub layer: OverrideLayer,
+    /// Unix timestamp (seconds) when the change was queued.
+    pub created_unix: u64,
+    /// Site the change affects, if known.
+    #[serde(default)]
+    pub site: Option<String>,
+    /// Human-readable description.
+    pub summary: String,
+    /// Size of a bandwidth change in percent of the configured rate, if applicable.
+    #[serde(default)]
+    pub magnitude_pct: Option<f32>,
+    /// The change to apply.
+    pub change: ProposedChange,
+}
+
+/// Result of submitting a [`ChangeProposal`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SubmitOutcome {
+    /// The change was written straight to its layer (`changed` is false for no-ops).
+    Applied {
+        /// True if the override file changed.
+        changed: bool,
+    },
+    /// The change is waiting in the pending queue under this id.
+    Queued {
+        /// Pending change identifier.
+        id: u64,
+    },
+}
+
+#[derive(Serialize, Deserialize, Default)]
+struct PendingChangeQueue {
+    #[serde(default)]
+    next_id: u64,
+    #[serde(default)]
+    changes: Vec<PendingChange>,
+}
+
+impl PendingChangeQueue {
+    /// Queues a proposal, replacing older pending changes for the same target.
+    /// An identical pending change is kept as-is so repeated proposals keep their id.
+    fn enqueue(&mut self, proposal: &ChangeProposal, now_unix: u64) -> u64 {
+        if let Some(existing) = self
+            .changes
+            .iter()
+            .find(|c| c.layer == proposal.layer && c.change == proposal.change)
+        {
+            return existing.id;
+        }
+        self.drop_target(proposal.layer, &proposal.change);
+        self.next_id = self.next_id.saturating_add(1);
+        let id = self.next_id;
+        self.changes.push(PendingChange {
+            id,
+            layer: proposal.layer,
+            created_unix: now_unix,
+            site: proposal.site.clone(),
+            summary: proposal.summary.clone(),
+            magnitude_pct: proposal.magnitude_pct,
+            change: proposal.change.clone(),
+        });
+        id
+    }
+
+    fn drop_target(&mut self, layer: OverrideLayer, change: &ProposedChange) -> bool {
+        let key = change.target_key();
+        let before = self.changes.len();
+        self.changes
+            .retain(|c| c.layer != layer || c.change.target_key() != key);
+        self.changes.len() != before
+    }
+
+    fn take(&mut self, id: u64) -> Option<PendingChange> {
+        let index = self.changes.iter().position(|c| c.id == id)?;
+        Some(self.changes.remove(index))
+    }
+}
+
+/// Returns true when a proposal must wait for administrator approval.
+pub fn proposal_requires_approval(cfg: &ChangeControlConfig, proposal: &ChangeProposal) -> bool {
+    if !cfg.require_approval {
+        return false;
+    }
+    if let Some(site) = &proposal.site
+        && cfg.auto_approve_sites.iter().any(|s| s == site)
+    {
+        return false;
+    }
+    if cfg.auto_approve_max_change_pct > 0.0
+        && let Some(pct) = proposal.magnitude_pct
+        && pct.abs() <= cfg.auto_approve_max_change_pct
+    {
+        return false;
+    }
+    if cfg.auto_approve_sqm && proposal.change.is_sqm() {
+        return false;
+    }
+    true
+}
+
+fn pending_path(config: &lqos_config::Config) -> PathBuf {
+    Path::new(&config.lqos_directory).join(PENDING_CHANGES_FILE)
+}
+
+fn load_queue(path: &Path) -> Result<PendingChangeQueue> {
+    if !path.exists() {
+        return Ok(PendingChangeQueue::default());
+    }
+    let raw = std::fs::read_to_string(path)?;
+    Ok(serde_json::from_str(&raw)?)
+}
+
+fn save_queue(path: &Path, queue: &PendingChangeQueue) -> Result<()> {
+    let as_json = serde_json::to_string_pretty(queue)?;
+    std::fs::write(path, as_json.as_bytes())?;
+    Ok(())
+}
+
+fn layer_author(layer: OverrideLayer) -> ChangeAuthor {
+    match layer {
+        OverrideLayer::Stormguard => ChangeAuthor::Stormguard,
+        OverrideLayer::Treeguard => ChangeAuthor::Treeguard,
+        OverrideLayer::Operator => ChangeAuthor::Unknown,
+    }
+}
+
+fn now_unix() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+impl super::OverrideStore {
+    /// Submits a batch of proposed changes from an automated actor.
+    ///
+    /// Changes that do not require approval (see [`proposal_requires_approval`]) are written to
+    /// their layer. The rest are placed in the pending queue, unless they would not alter the
+    /// layer at all. Either way, any older pending change for the same target is superseded.
+    ///
+    /// Side effects: acquires the global overrides lock and may write override layers and
+    /// `lqos_overrides.pending.json`.
+    pub fn submit_changes(proposals: &[ChangeProposal]) -> Result<Vec<SubmitOutcome>> {
+        if proposals.is_empty() {
+            return Ok(Vec::new());
+        }
+        let lock = FileLock::new()?;
+        let config = lqos_config::load_config()?;
+        let queue_path = pending_path(&config);
+        let mut queue = load_queue(&queue_path)?;
+        let mut queue_changed = false;
+        let mut layers: Vec<(OverrideLayer, OverrideFile, Vec<&str>)> = Vec::new();
+        let mut outcomes = Vec::with_capacity(proposals.len());
+
+        for proposal in proposals {
+            let slot = match layers.iter().position(|(l, _, _)| *l == proposal.layer) {
+                Some(slot) => slot,
+                None => {
+                    let file = load_layer_unlocked(&config, proposal.layer)?;
+                    layers.push((proposal.layer, file, Vec::new()));
+                    layers.len() - 1
+                }
+            };
+
+            if proposal_requires_approval(&config.change_control, proposal) {
+                // Probe against a scratch copy so no-op proposals never reach the queue.
+                let mut scratch = layers[slot].1.clone();
+                if !proposal.change.apply_to(&mut scratch) {
+                    queue_changed |= queue.drop_target(proposal.layer, &proposal.change);
+                    outcomes.push(SubmitOutcome::Applied { changed: false });
+                    continue;
+                }
+                let next_id_before = queue.next_id;
+                let id = queue.enqueue(proposal, now_unix());
+                queue_changed |= queue.next_id != next_id_before;
+                outcomes.push(SubmitOutcome::Queued { id });
+            } else {
+                let changed = proposal.change.apply_to(&mut layers[slot].1);
+                if changed {
+                    layers[slot].2.push(&proposal.summary);
+                }
+                queue_changed |= queue.drop_target(proposal.layer, &proposal.change);
+                outcomes.push(SubmitOutcome::Applied { changed });
+            }
+        }
+
+        for (layer, file, summaries) in &layers {
+            if !summaries.is_empty() {
+                let ctx = SaveContext::new(layer_author(*layer), summaries.join("; "));
+                save_layer_recorded(&config, *layer, file, &ctx)?;
+            }
+        }
+        if queue_changed {
+            save_queue(&queue_path, &queue)?;
+        }
+        drop(lock);
+        Ok(outcomes)
+    }
+
+    /// Submits a single proposed change. See [`OverrideStore::submit_changes`].
+    ///
+    /// Side effects: acquires the global overrides lock and may write override files.
+    pub fn submit_change(proposal: ChangeProposal) -> Result<SubmitOutcome> {
+        Self::submit_changes(std::slice::from_ref(&proposal))?
+            .pop()
+            .ok_or_else(|| anyhow!("No outcome returned for submitted change"))
+    }
+
+    /// Lists changes waiting for approval, oldest first.
+    ///
+    /// Side effects: acquires the global overrides lock.
+    pub fn load_pending_changes() -> Result<Vec<PendingChange>> {
+        let lock = FileLock::new()?;
+        let config = lqos_config::load_config()?;
+        let queue = load_queue(&pending_path(&config))?;
+        drop(lock);
+        Ok(queue.changes)
+    }
+
+    /// Approves a pending change, writing it to its layer.
+    ///
+    /// Returns the approved change and whether the layer file changed.
+    ///
+    /// Side effects: acquires the global overrides lock and writes the layer and pending files.
+    pub fn approve_pending_change(id: u64) -> Result<(PendingChange, bool)> {
+        let lock = FileLock::new()?;
+        let config = lqos_config::load_config()?;
+        let queue_path = pending_path(&config);
+        let mut queue = load_queue(&queue_path)?;
+        let pending = queue
+            .take(id)
+            .ok_or_else(|| anyhow!("No pending change with id {id}"))?;
+        let mut overrides = load_layer_unlocked(&config, pending.layer)?;
+        let changed = pending.change.apply_to(&mut overrides);
+        if changed {
+            let ctx = SaveContext::new(
+                layer_author(pending.layer),
+                format!("Approved pending change {id}: {}", pending.summary),
+            );
+            save_layer_recorded(&config, pending.layer, &overrides, &ctx)?;
+        }
+        save_queue(&queue_path, &queue)?;
+        drop(lock);
+        Ok((pending, changed))
+    }
+
+    /// Rejects (discards) a pending change.
+    ///
+    /// Side effects: acquires the global overrides lock and writes the pending file.
+    pub fn reject_pending_change(id: u64) -> Result<PendingChange> {
+        let lock = FileLock::new()?;
+        let config = lqos_config::load_config()?;
+        let queue_path = pending_path(&config);
+        let mut queue = load_queue(&queue_path)?;
+        let pending = queue
+            .take(id)
+            .ok_or_else(|| anyhow!("No pending change with id {id}"))?;
+        save_queue(&queue_path, &queue)?;
+        drop(lock);
+        Ok(pending)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn site_proposal(site: &str, down: Option<f32>, pct: Option<f32>) -> ChangeProposal {
+        ChangeProposal {
+            layer: OverrideLayer::Stormguard,
+            site: Some(site.to_string()),
+            summary: format!("Set {site}"),
+            magnitude_pct: pct,
+            change: ProposedChange::SetSiteBandwidth {
+                site_name: site.to_string(),
+                download_bandwidth_mbps: down,
+                upload_bandwidth_mbps: None,
+            },
+        }
+    }
+
+    #[test]
+    fn auto_approve_rules_bypass_the_queue() {
+        let mut cfg = ChangeControlConfig::default();
+        let proposal = site_proposal("Tower1", Some(80.0), Some(20.0));
+        assert!(!proposal_requires_approval(&cfg, &proposal));
+
+        cfg.require_approval = true;
+        assert!(proposal_requires_approval(&cfg, &proposal));
+
+        cfg.auto_approve_max_change_pct = 25.0;
+        assert!(!proposal_requires_approval(&cfg, &proposal));
+        cfg.auto_approve_max_change_pct = 10.0;
+        assert!(proposal_requires_approval(&cfg, &proposal));
+
+        cfg.auto_approve_sites = vec!["Tower1".to_string()];
+        assert!(!proposal_requires_approval(&cfg, &proposal));
+
+        let sqm = ChangeProposal {
+            layer: OverrideLayer::Treeguard,
+            site: None,
+            summary: "sqm".to_string(),
+            magnitude_pct: None,
+            change: ProposedChange::ClearDeviceSqm {
+                device_ids: vec!["d1".to_string()],
+            },
+        };
+        assert!(proposal_requires_approval(&cfg, &sqm));
+        cfg.auto_approve_sqm = true;
+        assert!(!proposal_requires_approval(&cfg, &sqm));
+    }
+
+    #[test]
+    fn newer_proposal_supersedes_pending_change_for_same_target() {
+        let mut queue = PendingChangeQueue::default();
+        let first = queue.enqueue(&site_proposal("Tower1", Some(80.0), None), 10);
+        let again = queue.enqueue(&site_proposal("Tower1", Some(80.0), None), 20);
+        assert_eq!(first, again);
+        assert_eq!(queue.changes[0].created_unix, 10);
+
+        let second = queue.enqueue(&site_proposal("Tower1", Some(60.0), None), 30);
+        assert_ne!(first, second);
+        queue.enqueue(&site_proposal("Tower2", Some(60.0), None), 30);
+        assert_eq!(queue.changes.len(), 2);
+        assert!(queue.take(first).is_none());
+        assert!(queue.take(second).is_some());
+    }
+
+    #[test]
+    fn device_changes_share_a_target_regardless_of_order() {
+        let set = ProposedChange::SetDeviceSqm {
+            device_ids: vec!["b".to_string(), "a".to_string()],
+            sqm_override: "fq_codel".to_string(),
+        };
+        let clear = ProposedChange::ClearDeviceSqm {
+            device_ids: vec!["a".to_string(), "b".to_string()],
+        };
+        assert_eq!(set.target_key(), clear.target_key());
+
+        let mut overrides = OverrideFile::default();
+        assert!(set.apply_to(&mut overrides));
+        assert!(!set.apply_to(&mut overrides));
+        assert!(clear.apply_to(&mut overrides));
+        assert!(overrides.circuit_adjustments().is_empty());
+    }
+}
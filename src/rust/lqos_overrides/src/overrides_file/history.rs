@@ -0,0 +1,467 @@
+//! Versioned history for override layers.
+//!
+//! Every save writes a snapshot of the new layer contents to
+//! `lqos_overrides.history/<layer>/<version>.json`, together with who made the change and why.
+//! Snapshots can be listed, diffed against each other, and restored.
+
+use std::{
+    collections::BTreeMap,
+    fmt,
+    path::{Path, PathBuf},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use anyhow::{Result, anyhow};
+use serde::{Deserialize, Serialize};
+
+use super::{
+    CircuitAdjustment, NetworkAdjustment, OverrideFile, OverrideLayer, UispRouteOverride,
+    circuit_adjustment_merge_key, layer_read_path, load_from_path, overrides_path, save_to_path,
+    site_speed_key,
+};
+
+const HISTORY_DIR: &str = "lqos_overrides.history";
+/// Oldest snapshots beyond this count are pruned per layer.
+const MAX_HISTORY_VERSIONS: usize = 200;
+
+/// Who made a change to an overrides layer.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum ChangeAuthor {
+    /// A person, via the WebUI, CLI or API.
+    User {
+        /// User or session name.
+        name: String,
+    },
+    /// The StormGuard actor.
+    Stormguard,
+    /// The TreeGuard actor.
+    Treeguard,
+    /// A CRM/NMS integration or provisioning system.
+    Integration {
+        /// Integration name.
+        name: String,
+    },
+    /// Origin not recorded (for example, the baseline snapshot of a pre-existing file).
+    Unknown,
+}
+
+impl fmt::Display for ChangeAuthor {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ChangeAuthor::User { name } => write!(f, "user:{name}"),
+            ChangeAuthor::Stormguard => write!(f, "stormguard"),
+            ChangeAuthor::Treeguard => write!(f, "treeguard"),
+            ChangeAuthor::Integration { name } => write!(f, "integration:{name}"),
+            ChangeAuthor::Unknown => write!(f, "unknown"),
+        }
+    }
+}
+
+/// Attribution recorded alongside a layer save.
+#[derive(Clone, Debug)]
+pub struct SaveContext {
+    /// Who made the change.
+    pub author: ChangeAuthor,
+    /// Why the change was made.
+    pub reason: String,
+}
+
+impl SaveContext {
+    /// Creates a save context.
+    pub fn new(author: ChangeAuthor, reason: impl Into<String>) -> Self {
+        Self {
+            author,
+            reason: reason.into(),
+        }
+    }
+}
+
+/// Metadata for one recorded version of an overrides layer.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct HistoryEntry {
+    /// Monotonic version number within the layer.
+    pub version: u64,
+    /// Layer the snapshot belongs to.
+    pub layer: OverrideLayer,
+    /// Who made the change.
+    pub author: ChangeAuthor,
+    /// Unix timestamp (seconds) of the save.
+    pub timestamp_unix: u64,
+    /// Why the change was made.
+    pub reason: String,
+}
+
+#[derive(Serialize, Deserialize)]
+struct HistorySnapshot {
+    entry: HistoryEntry,
+    overrides: OverrideFile,
+}
+
+/// How an entry differs between two versions.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum DiffKind {
+    /// Present only in the newer version.
+    Added,
+    /// Present only in the older version.
+    Removed,
+    /// Present in both with different contents.
+    Changed,
+}
+
+/// One difference between two versions of an overrides layer.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct OverrideDiffEntry {
+    /// Override section (`persistent_devices`, `circuit_adjustments`, ...).
+    pub section: String,
+    /// Identity of the entry within the section.
+    pub key: String,
+    /// Kind of difference.
+    pub kind: DiffKind,
+    /// Entry in the older version, if present.
+    pub before: Option<serde_json::Value>,
+    /// Entry in the newer version, if present.
+    pub after: Option<serde_json::Value>,
+}
+
+fn layer_dir_name(layer: OverrideLayer) -> &'static str {
+    match layer {
+        OverrideLayer::Operator => "operator",
+        OverrideLayer::Stormguard => "stormguard",
+        OverrideLayer::Treeguard => "treeguard",
+    }
+}
+
+fn history_dir(config: &lqos_config::Config, layer: OverrideLayer) -> PathBuf {
+    Path::new(&config.lqos_directory)
+        .join(HISTORY_DIR)
+        .join(layer_dir_name(layer))
+}
+
+fn snapshot_path(dir: &Path, version: u64) -> PathBuf {
+    dir.join(format!("{version:010}.json"))
+}
+
+fn list_versions(dir: &Path) -> Result<Vec<u64>> {
+    if !dir.exists() {
+        return Ok(Vec::new());
+    }
+    let mut versions: Vec<u64> = std::fs::read_dir(dir)?
+        .filter_map(|entry| entry.ok())
+        .filter_map(|entry| {
+            let name = entry.file_name();
+            name.to_str()?.strip_suffix(".json")?.parse().ok()
+        })
+        .collect();
+    versions.sort_unstable();
+    Ok(versions)
+}
+
+fn read_snapshot(dir: &Path, version: u64) -> Result<HistorySnapshot> {
+    let path = snapshot_path(dir, version);
+    if !path.exists() {
+        return Err(anyhow!("Version {version} not found in override history"));
+    }
+    let raw = std::fs::read_to_string(path)?;
+    Ok(serde_json::from_str(&raw)?)
+}
+
+fn write_snapshot(dir: &Path, snapshot: &HistorySnapshot) -> Result<()> {
+    std::fs::create_dir_all(dir)?;
+    let as_json = serde_json::to_string_pretty(snapshot)?;
+    std::fs::write(
+        snapshot_path(dir, snapshot.entry.version),
+        as_json.as_bytes(),
+    )?;
+    Ok(())
+}
+
+fn now_unix() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+/// Writes a layer and records the new contents as a history version.
+///
+/// The first recorded save of a layer also captures the pre-existing file as a baseline
+/// version, so the state before any tracked change can be restored. Callers must hold the
+/// global overrides lock.
+pub(super) fn save_layer_recorded(
+    config: &lqos_config::Config,
+    layer: OverrideLayer,
+    overrides: &OverrideFile,
+    ctx: &SaveContext,
+) -> Result<HistoryEntry> {
+    let dir = history_dir(config, layer);
+    let versions = list_versions(&dir)?;
+    let mut next_version = versions.last().copied().unwrap_or(0) + 1;
+
+    if versions.is_empty() {
+        let existing = layer_read_path(config, layer);
+        if existing.exists() {
+            write_snapshot(
+                &dir,
+                &HistorySnapshot {
+                    entry: HistoryEntry {
+                        version: next_version,
+                        layer,
+                        author: ChangeAuthor::Unknown,
+                        timestamp_unix: now_unix(),
+                        reason: "Baseline before first recorded change".to_string(),
+                    },
+                    overrides: load_from_path(&existing)?,
+                },
+            )?;
+            next_version += 1;
+        }
+    }
+
+    let entry = HistoryEntry {
+        version: next_version,
+        layer,
+        author: ctx.author.clone(),
+        timestamp_unix: now_unix(),
+        reason: ctx.reason.clone(),
+    };
+    write_snapshot(
+        &dir,
+        &HistorySnapshot {
+            entry: entry.clone(),
+            overrides: overrides.clone(),
+        },
+    )?;
+    save_to_path(&overrides_path(config, layer), overrides)?;
+
+    let versions = list_versions(&dir)?;
+    if versions.len() > MAX_HISTORY_VERSIONS {
+        for version in &versions[..versions.len() - MAX_HISTORY_VERSIONS] {
+            let _ = std::fs::remove_file(snapshot_path(&dir, *version));
+        }
+    }
+    Ok(entry)
+}
+
+/// Lists recorded versions of a layer, newest first. Callers must hold the overrides lock.
+pub(super) fn list_history(
+    config: &lqos_config::Config,
+    layer: OverrideLayer,
+) -> Result<Vec<HistoryEntry>> {
+    let dir = history_dir(config, layer);
+    let mut entries = Vec::new();
+    for version in list_versions(&dir)?.into_iter().rev() {
+        entries.push(read_snapshot(&dir, version)?.entry);
+    }
+    Ok(entries)
+}
+
+/// Loads the contents of a recorded version. Callers must hold the overrides lock.
+pub(super) fn load_version(
+    config: &lqos_config::Config,
+    layer: OverrideLayer,
+    version: u64,
+) -> Result<OverrideFile> {
+    Ok(read_snapshot(&history_dir(config, layer), version)?.overrides)
+}
+
+fn diff_keyed<T: Serialize>(
+    section: &str,
+    before: &[T],
+    after: &[T],
+    key: impl Fn(&T) -> String,
+    out: &mut Vec<OverrideDiffEntry>,
+) {
+    let to_map = |items: &[T]| -> BTreeMap<String, serde_json::Value> {
+        items
+            .iter()
+            .map(|item| {
+                (
+                    key(item),
+                    serde_json::to_value(item).unwrap_or(serde_json::Value::Null),
+                )
+            })
+            .collect()
+    };
+    let before = to_map(before);
+    let after = to_map(after);
+
+    for (key, old) in &before {
+        match after.get(key) {
+            None => out.push(OverrideDiffEntry {
+                section: section.to_string(),
+                key: key.clone(),
+                kind: DiffKind::Removed,
+                before: Some(old.clone()),
+                after: None,
+            }),
+            Some(new) if new != old => out.push(OverrideDiffEntry {
+                section: section.to_string(),
+                key: key.clone(),
+                kind: DiffKind::Changed,
+                before: Some(old.clone()),
+                after: Some(new.clone()),
+            }),
+            Some(_) => {}
+        }
+    }
+    for (key, new) in &after {
+        if !before.contains_key(key) {
+            out.push(OverrideDiffEntry {
+                section: section.to_string(),
+                key: key.clone(),
+                kind: DiffKind::Added,
+                before: None,
+                after: Some(new.clone()),
+            });
+        }
+    }
+}
+
+pub(super) fn circuit_adjustment_key(adj: &CircuitAdjustment) -> String {
+    let (kind, id) = circuit_adjustment_merge_key(adj);
+    format!("{kind}:{id}")
+}
+
+pub(super) fn uisp_route_key(route: &UispRouteOverride) -> String {
+    format!("{}->{}", route.from_site, route.to_site)
+}
+
+pub(super) fn network_adjustment_key(adj: &NetworkAdjustment) -> String {
+    match adj {
+        NetworkAdjustment::AdjustSiteSpeed {
+            node_id, site_name, ..
+        } => format!(
+            "site_speed:{}",
+            site_speed_key(node_id.as_deref(), site_name)
+        ),
+        NetworkAdjustment::SetNodeVirtual { node_name, .. } => format!("node_virtual:{node_name}"),
+    }
+}
+
+/// Compares two versions of an overrides file entry-by-entry.
+///
+/// Entries are matched by identity (device ID, circuit ID, site, ...) so an edited entry is
+/// reported as `changed` rather than as a removal plus an addition.
+pub fn diff_override_files(before: &OverrideFile, after: &OverrideFile) -> Vec<OverrideDiffEntry> {
+    let mut out = Vec::new();
+    diff_keyed(
+        "persistent_devices",
+        &before.persistent_devices,
+        &after.persistent_devices,
+        |d| d.device_id.clone(),
+        &mut out,
+    );
+    diff_keyed(
+        "circuit_adjustments",
+        &before.circuit_adjustments,
+        &after.circuit_adjustments,
+        circuit_adjustment_key,
+        &mut out,
+    );
+    diff_keyed(
+        "network_adjustments",
+        &before.network_adjustments,
+        &after.network_adjustments,
+        network_adjustment_key,
+        &mut out,
+    );
+    diff_keyed(
+        "rtt_excluded_circuits",
+        &before.rtt_excluded_circuits,
+        &after.rtt_excluded_circuits,
+        Clone::clone,
+        &mut out,
+    );
+
+    let uisp_bandwidth = |file: &OverrideFile| -> Vec<(String, (f32, f32))> {
+        file.uisp
+            .as_ref()
+            .map(|u| {
+                u.bandwidth_overrides
+                    .iter()
+                    .map(|(site, rates)| (site.clone(), *rates))
+                    .collect()
+            })
+            .unwrap_or_default()
+    };
+    diff_keyed(
+        "uisp.bandwidth_overrides",
+        &uisp_bandwidth(before),
+        &uisp_bandwidth(after),
+        |(site, _)| site.clone(),
+        &mut out,
+    );
+    let no_routes = Vec::new();
+    diff_keyed(
+        "uisp.route_overrides",
+        before
+            .uisp
+            .as_ref()
+            .map_or(&no_routes, |u| &u.route_overrides),
+        after
+            .uisp
+            .as_ref()
+            .map_or(&no_routes, |u| &u.route_overrides),
+        uisp_route_key,
+        &mut out,
+    );
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use lqos_config::ShapedDevice;
+
+    #[test]
+    fn diff_matches_entries_by_identity() {
+        let mut before = OverrideFile::default();
+        before.set_site_bandwidth_override(None, "Tower1".to_string(), Some(100.0), None);
+        before.set_network_node_virtual("AP1".to_string(), true);
+        before.add_persistent_shaped_device_return_changed(ShapedDevice {
+            device_id: "d1".to_string(),
+            ..ShapedDevice::default()
+        });
+
+        let mut after = OverrideFile::default();
+        after.set_site_bandwidth_override(None, "Tower1".to_string(), Some(80.0), None);
+        after.add_persistent_shaped_device_return_changed(ShapedDevice {
+            device_id: "d1".to_string(),
+            ..ShapedDevice::default()
+        });
+        after.set_circuit_rtt_excluded_return_changed("c9", true);
+
+        let diff = diff_override_files(&before, &after);
+        let summary: Vec<(&str, &str, DiffKind)> = diff
+            .iter()
+            .map(|d| (d.section.as_str(), d.key.as_str(), d.kind))
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                ("network_adjustments", "node_virtual:AP1", DiffKind::Removed),
+                (
+                    "network_adjustments",
+                    "site_speed:name:Tower1",
+                    DiffKind::Changed
+                ),
+                ("rtt_excluded_circuits", "c9", DiffKind::Added),
+            ]
+        );
+        assert!(diff_override_files(&after, &after).is_empty());
+    }
+
+    #[test]
+    fn author_display_is_stable() {
+        assert_eq!(ChangeAuthor::Stormguard.to_string(), "stormguard");
+        assert_eq!(
+            ChangeAuthor::User {
+                name: "admin".to_string()
+            }
+            .to_string(),
+            "user:admin"
+        );
+    }
+}
@@ -0,0 +1,775 @@
+//! `BakeryBatch`: a checked way for custom integrations to drive the Bakery without
+//! `LibreQoS.py`.
+//!
+//! Unlike [`Bakery`](crate::Bakery), sites and circuits are validated as they are added (tc
+//! handles, class numbering and rates), the batch is sent in chunks over one bus connection,
+//! and the commit is only sent once every chunk has been accepted, so a failed submission never
+//! leaves the Bakery applying half a batch. The last committed batch is saved next to the other
+//! runtime files so the next commit can report what it changes.
+
+use crate::BakeryCommands;
+use lqos_bakery::{BatchDiffSummary, MAX_TC_RATE_MBPS, MIN_TC_RATE_MBPS, summarize_batch_diff};
+use lqos_bus::{BusRequest, BusResponse, LibreqosBusClient, TcHandle};
+use lqos_config::{FIRST_CLASS_MINOR, is_valid_sqm_direction_token};
+use pyo3::exceptions::{PyOSError, PyValueError};
+use pyo3::prelude::*;
+use std::collections::{HashMap, HashSet};
+use std::net::IpAddr;
+use std::path::{Path, PathBuf};
+
+/// The last batch committed through `BakeryBatch`, relative to `lqos_directory`.
+const LAST_COMMIT_FILE: &str = "bakery_batch_last_commit.cbor";
+
+/// Commands per bus request, matching `Bakery.commit()`.
+const DEFAULT_CHUNK_SIZE: usize = 1024;
+
+fn parse_handle(owner: &str, field: &str, handle: &str) -> Result<TcHandle, String> {
+    TcHandle::from_string(handle)
+        .map_err(|_| format!("{owner}: {field} {handle:?} is not a valid tc handle"))
+}
+
+fn check_minor(owner: &str, class_minor: u16) -> Result<(), String> {
+    if u32::from(class_minor) < FIRST_CLASS_MINOR {
+        return Err(format!(
+            "{owner}: class_minor {class_minor:#x} is reserved; minors start at {FIRST_CLASS_MINOR:#x}"
+        ));
+    }
+    Ok(())
+}
+
+/// Rates outside the range the Bakery passes to `tc` would be silently clamped, so they are
+/// rejected instead.
+fn check_rates(owner: &str, download: (f32, f32), upload: (f32, f32)) -> Result<(), String> {
+    for (direction, (min, max)) in [("download", download), ("upload", upload)] {
+        for (bound, rate) in [("min", min), ("max", max)] {
+            if !(MIN_TC_RATE_MBPS..=MAX_TC_RATE_MBPS).contains(&rate) {
+                return Err(format!(
+                    "{owner}: {direction}_bandwidth_{bound} {rate} Mbps is outside {MIN_TC_RATE_MBPS}-{MAX_TC_RATE_MBPS} Mbps"
+                ));
+            }
+        }
+        if min > max {
+            return Err(format!(
+                "{owner}: {direction}_bandwidth_min ({min}) is above {direction}_bandwidth_max ({max})"
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// Checks a comma-separated list of addresses, each optionally with a `/prefix`.
+fn check_ip_addresses(owner: &str, ip_addresses: &str) -> Result<(), String> {
+    for entry in ip_addresses.split(',').map(str::trim) {
+        if entry.is_empty() {
+            continue;
+        }
+        let (address, prefix) = match entry.split_once('/') {
+            Some((address, prefix)) => (address, Some(prefix)),
+            None => (entry, None),
+        };
+        let Ok(address) = address.parse::<IpAddr>() else {
+            return Err(format!("{owner}: {entry:?} is not an IP address"));
+        };
+        let max_prefix = if address.is_ipv4() { 32 } else { 128 };
+        if let Some(prefix) = prefix
+            && !prefix
+                .parse::<u8>()
+                .is_ok_and(|prefix| prefix <= max_prefix)
+        {
+            return Err(format!("{owner}: {entry:?} has an invalid prefix length"));
+        }
+    }
+    Ok(())
+}
+
+/// Normalizes an SQM override (`token` or `down/up`), rejecting unknown tokens.
+fn check_sqm_override(owner: &str, sqm_override: Option<&str>) -> Result<Option<String>, String> {
+    let Some(sqm_override) = sqm_override.map(str::trim).filter(|s| !s.is_empty()) else {
+        return Ok(None);
+    };
+    let normalized = sqm_override.to_lowercase();
+    for token in normalized.split('/').map(str::trim) {
+        if !token.is_empty() && !is_valid_sqm_direction_token(token) {
+            return Err(format!(
+                "{owner}: sqm_override {sqm_override:?} contains unknown token {token:?}"
+            ));
+        }
+    }
+    Ok(Some(normalized))
+}
+
+fn class_label((major, minor): (u16, u16)) -> String {
+    format!("{major:x}:{minor:x}")
+}
+
+/// The validated contents of a batch, kept apart from the Python class so it can be tested
+/// without an interpreter.
+#[derive(Default)]
+struct BatchBuilder {
+    mq_setup: Option<BakeryCommands>,
+    commands: Vec<BakeryCommands>,
+    sites: HashSet<i64>,
+    circuits: HashSet<i64>,
+    down_classes: HashMap<(u16, u16), String>,
+    up_classes: HashMap<(u16, u16), String>,
+}
+
+impl BatchBuilder {
+    fn setup_mq(&mut self, queues_available: usize, stick_offset: usize) -> Result<(), String> {
+        if queues_available == 0 {
+            return Err("setup_mq: queues_available must be at least 1".to_string());
+        }
+        self.mq_setup = Some(BakeryCommands::MqSetup {
+            queues_available,
+            stick_offset,
+        });
+        Ok(())
+    }
+
+    /// Reserves the download and upload classes for `owner`, failing if either is taken.
+    fn claim_classes(
+        &mut self,
+        owner: &str,
+        down: (u16, u16),
+        up: (u16, u16),
+    ) -> Result<(), String> {
+        for (direction, classes, class) in [
+            ("download", &self.down_classes, down),
+            ("upload", &self.up_classes, up),
+        ] {
+            if let Some(existing) = classes.get(&class) {
+                return Err(format!(
+                    "{owner}: {direction} class {} is already used by {existing}",
+                    class_label(class)
+                ));
+            }
+        }
+        self.down_classes.insert(down, owner.to_string());
+        self.up_classes.insert(up, owner.to_string());
+        Ok(())
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn add_site(
+        &mut self,
+        site_name: &str,
+        parent_class_id: &str,
+        up_parent_class_id: &str,
+        class_minor: u16,
+        download_bandwidth_min: f32,
+        upload_bandwidth_min: f32,
+        download_bandwidth_max: f32,
+        upload_bandwidth_max: f32,
+    ) -> Result<(), String> {
+        let owner = format!("site {site_name:?}");
+        if site_name.trim().is_empty() {
+            return Err("site_name must not be empty".to_string());
+        }
+        let site_hash = lqos_utils::hash_to_i64(site_name);
+        if self.sites.contains(&site_hash) {
+            return Err(format!("{owner} was already added"));
+        }
+        let parent = parse_handle(&owner, "parent_class_id", parent_class_id)?;
+        let up_parent = parse_handle(&owner, "up_parent_class_id", up_parent_class_id)?;
+        check_minor(&owner, class_minor)?;
+        check_rates(
+            &owner,
+            (download_bandwidth_min, download_bandwidth_max),
+            (upload_bandwidth_min, upload_bandwidth_max),
+        )?;
+        self.claim_classes(
+            &owner,
+            (parent.get_major_minor().0, class_minor),
+            (up_parent.get_major_minor().0, class_minor),
+        )?;
+
+        self.sites.insert(site_hash);
+        self.commands.push(BakeryCommands::AddSite {
+            site_hash,
+            parent_class_id: parent,
+            up_parent_class_id: up_parent,
+            class_minor,
+            download_bandwidth_min,
+            upload_bandwidth_min,
+            download_bandwidth_max,
+            upload_bandwidth_max,
+        });
+        Ok(())
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn add_circuit(
+        &mut self,
+        circuit_name: &str,
+        site_name: Option<String>,
+        parent_class_id: &str,
+        up_parent_class_id: &str,
+        class_minor: u16,
+        download_bandwidth_min: f32,
+        upload_bandwidth_min: f32,
+        download_bandwidth_max: f32,
+        upload_bandwidth_max: f32,
+        class_major: u16,
+        up_class_major: u16,
+        ip_addresses: &str,
+        sqm_override: Option<&str>,
+    ) -> Result<(), String> {
+        let owner = format!("circuit {circuit_name:?}");
+        if circuit_name.trim().is_empty() {
+            return Err("circuit_name must not be empty".to_string());
+        }
+        let circuit_hash = lqos_utils::hash_to_i64(circuit_name);
+        if self.circuits.contains(&circuit_hash) {
+            return Err(format!("{owner} was already added"));
+        }
+        let parent = parse_handle(&owner, "parent_class_id", parent_class_id)?;
+        let up_parent = parse_handle(&owner, "up_parent_class_id", up_parent_class_id)?;
+        for (field, handle, major) in [
+            ("parent_class_id", parent, class_major),
+            ("up_parent_class_id", up_parent, up_class_major),
+        ] {
+            let parent_major = handle.get_major_minor().0;
+            if parent_major != major {
+                return Err(format!(
+                    "{owner}: {field} {} is in queue {parent_major:x}, but the circuit's class major is {major:x}",
+                    handle.as_tc_string()
+                ));
+            }
+        }
+        check_minor(&owner, class_minor)?;
+        check_rates(
+            &owner,
+            (download_bandwidth_min, download_bandwidth_max),
+            (upload_bandwidth_min, upload_bandwidth_max),
+        )?;
+        check_ip_addresses(&owner, ip_addresses)?;
+        let sqm_override = check_sqm_override(&owner, sqm_override)?;
+        self.claim_classes(
+            &owner,
+            (class_major, class_minor),
+            (up_class_major, class_minor),
+        )?;
+
+        self.circuits.insert(circuit_hash);
+        self.commands.push(BakeryCommands::AddCircuit {
+            circuit_hash,
+            circuit_name: Some(circuit_name.to_string()),
+            site_name,
+            parent_class_id: parent,
+            up_parent_class_id: up_parent,
+            class_minor,
+            download_bandwidth_min,
+            upload_bandwidth_min,
+            download_bandwidth_max,
+            upload_bandwidth_max,
+            class_major,
+            up_class_major,
+            ip_addresses: ip_addresses.to_string(),
+            sqm_override,
+        });
+        Ok(())
+    }
+
+    /// The batch as it is sent: start, queue setup, then sites and circuits. The commit is
+    /// sent separately.
+    fn batch(&self) -> Result<Vec<BakeryCommands>, String> {
+        let Some(mq_setup) = &self.mq_setup else {
+            return Err("setup_mq() must be called before committing".to_string());
+        };
+        if self.commands.is_empty() {
+            return Err(
+                "The batch has no sites or circuits; committing it would remove all shaping"
+                    .to_string(),
+            );
+        }
+        let mut batch = Vec::with_capacity(self.commands.len() + 2);
+        batch.push(BakeryCommands::StartBatch);
+        batch.push(mq_setup.clone());
+        batch.extend(self.commands.iter().cloned());
+        Ok(batch)
+    }
+}
+
+fn last_commit_path() -> Option<PathBuf> {
+    let config = lqos_config::load_config().ok()?;
+    Some(Path::new(&config.lqos_directory).join(LAST_COMMIT_FILE))
+}
+
+fn load_last_commit(path: &Path) -> Option<Vec<BakeryCommands>> {
+    let bytes = std::fs::read(path).ok()?;
+    serde_cbor::from_slice(&bytes).ok()
+}
+
+fn runtime_commands(commands: &[BakeryCommands]) -> Vec<lqos_bakery::BakeryCommands> {
+    commands
+        .iter()
+        .map(BakeryCommands::as_runtime_command)
+        .collect()
+}
+
+/// What a batch changes compared with the last batch committed through `BakeryBatch`.
+#[pyclass]
+#[derive(Clone)]
+pub struct BakeryDiffSummary {
+    /// False if no earlier commit was found, in which case everything counts as added.
+    #[pyo3(get)]
+    pub baseline_found: bool,
+    /// Why the Bakery will rebuild the site hierarchy with a full reload, if it will.
+    #[pyo3(get)]
+    pub full_reload_reason: Option<String>,
+    /// Sites whose rates change in place.
+    #[pyo3(get)]
+    pub site_speed_changes: usize,
+    /// Circuits that are new in this batch.
+    #[pyo3(get)]
+    pub circuits_added: usize,
+    /// Circuits that this batch removes.
+    #[pyo3(get)]
+    pub circuits_removed: usize,
+    /// Circuits whose rates or SQM override change.
+    #[pyo3(get)]
+    pub circuits_speed_changed: usize,
+    /// Circuits whose IP addresses change.
+    #[pyo3(get)]
+    pub circuits_ip_changed: usize,
+    /// Circuits that move to a different parent or class.
+    #[pyo3(get)]
+    pub circuits_migrated: usize,
+}
+
+impl BakeryDiffSummary {
+    fn new(baseline_found: bool, summary: BatchDiffSummary) -> Self {
+        Self {
+            baseline_found,
+            full_reload_reason: summary.full_reload_reason,
+            site_speed_changes: summary.site_speed_changes,
+            circuits_added: summary.circuits_added,
+            circuits_removed: summary.circuits_removed,
+            circuits_speed_changed: summary.circuits_speed_changed,
+            circuits_ip_changed: summary.circuits_ip_changed,
+            circuits_migrated: summary.circuits_migrated,
+        }
+    }
+}
+
+#[pymethods]
+impl BakeryDiffSummary {
+    fn __repr__(&self) -> String {
+        format!(
+            "BakeryDiffSummary(full_reload={}, site_speed_changes={}, circuits_added={}, circuits_removed={}, circuits_speed_changed={}, circuits_ip_changed={}, circuits_migrated={})",
+            if self.full_reload_reason.is_some() {
+                "True"
+            } else {
+                "False"
+            },
+            self.site_speed_changes,
+            self.circuits_added,
+            self.circuits_removed,
+            self.circuits_speed_changed,
+            self.circuits_ip_changed,
+            self.circuits_migrated
+        )
+    }
+}
+
+/// The outcome of `BakeryBatch.commit()`.
+#[pyclass]
+pub struct BakeryCommitResult {
+    /// True if every chunk was accepted and `lqosd` acknowledged the commit.
+    #[pyo3(get)]
+    pub committed: bool,
+    /// Bus requests sent, including the final commit.
+    #[pyo3(get)]
+    pub chunks_sent: usize,
+    /// Bakery commands sent, including the batch start and commit.
+    #[pyo3(get)]
+    pub commands_sent: usize,
+    /// Problems reported by `lqosd` or the bus; empty if the commit succeeded.
+    #[pyo3(get)]
+    pub errors: Vec<String>,
+    /// What the batch changes compared with the previous commit.
+    #[pyo3(get)]
+    pub diff: BakeryDiffSummary,
+}
+
+#[pymethods]
+impl BakeryCommitResult {
+    fn __repr__(&self) -> String {
+        format!(
+            "BakeryCommitResult(committed={}, chunks_sent={}, commands_sent={}, errors={})",
+            if self.committed { "True" } else { "False" },
+            self.chunks_sent,
+            self.commands_sent,
+            self.errors.len()
+        )
+    }
+}
+
+struct SendOutcome {
+    committed: bool,
+    chunks_sent: usize,
+    commands_sent: usize,
+    errors: Vec<String>,
+}
+
+fn collect_failures(responses: &[BusResponse], errors: &mut Vec<String>) {
+    for response in responses {
+        if let BusResponse::Fail(message) = response {
+            errors.push(message.clone());
+        }
+    }
+}
+
+/// Sends the batch in chunks, then the commit only if nothing was rejected.
+async fn send_batch(batch: &[BakeryCommands], chunk_size: usize) -> SendOutcome {
+    let mut outcome = SendOutcome {
+        committed: false,
+        chunks_sent: 0,
+        commands_sent: 0,
+        errors: Vec::new(),
+    };
+    let mut bus = match LibreqosBusClient::new().await {
+        Ok(bus) => bus,
+        Err(e) => {
+            outcome
+                .errors
+                .push(format!("Unable to connect to lqosd: {e}"));
+            return outcome;
+        }
+    };
+
+    for chunk in batch.chunks(chunk_size) {
+        let requests: Vec<BusRequest> = chunk.iter().map(BakeryCommands::as_bus_request).collect();
+        match bus.request(requests).await {
+            Ok(responses) => collect_failures(&responses, &mut outcome.errors),
+            Err(e) => outcome.errors.push(format!("Bus request failed: {e}")),
+        }
+        outcome.chunks_sent += 1;
+        outcome.commands_sent += chunk.len();
+        if !outcome.errors.is_empty() {
+            outcome.errors.push(
+                "The batch was not committed; the Bakery keeps its current queues".to_string(),
+            );
+            return outcome;
+        }
+    }
+
+    match bus.request(vec![BusRequest::BakeryCommit]).await {
+        Ok(responses) => {
+            collect_failures(&responses, &mut outcome.errors);
+            outcome.committed =
+                outcome.errors.is_empty() && matches!(responses.first(), Some(BusResponse::Ack));
+            if !outcome.committed && outcome.errors.is_empty() {
+                outcome
+                    .errors
+                    .push("lqosd did not acknowledge the commit".to_string());
+            }
+        }
+        Err(e) => outcome.errors.push(format!("Bus request failed: {e}")),
+    }
+    outcome.chunks_sent += 1;
+    outcome.commands_sent += 1;
+    outcome
+}
+
+/// Builds a Bakery batch, validating each site and circuit as it is added, and submits it.
+///
+/// ```python
+/// batch = BakeryBatch()
+/// batch.setup_mq(queues_available, stick_offset)
+/// batch.add_site("Tower 1", "0x1:0x2", "0x3:0x2", 0x3, 100, 100, 1000, 1000)
+/// batch.add_circuit("Customer 1", "Tower 1", "0x1:0x3", "0x3:0x3", 0x4,
+///                   5, 2, 100, 20, 0x1, 0x3, "100.64.0.10/32")
+/// result = batch.commit()
+/// ```
+///
+/// `add_site`, `add_circuit` and `setup_mq` raise `ValueError` for invalid input and leave the
+/// batch unchanged.
+#[pyclass]
+pub struct BakeryBatch {
+    builder: BatchBuilder,
+    chunk_size: usize,
+}
+
+#[pymethods]
+impl BakeryBatch {
+    #[new]
+    #[pyo3(signature = (chunk_size=DEFAULT_CHUNK_SIZE))]
+    /// Creates an empty batch, sent `chunk_size` commands per bus request.
+    pub fn new(chunk_size: usize) -> PyResult<Self> {
+        if chunk_size == 0 {
+            return Err(PyValueError::new_err("chunk_size must be at least 1"));
+        }
+        Ok(Self {
+            builder: BatchBuilder::default(),
+            chunk_size,
+        })
+    }
+
+    /// Sets the multi-queue layout. Required before committing.
+    pub fn setup_mq(&mut self, queues_available: usize, stick_offset: usize) -> PyResult<()> {
+        self.builder
+            .setup_mq(queues_available, stick_offset)
+            .map_err(PyValueError::new_err)
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    /// Adds a site: an HTB class `parent major:class_minor` in each direction. Rates are in Mbps.
+    pub fn add_site(
+        &mut self,
+        site_name: String,
+        parent_class_id: String,
+        up_parent_class_id: String,
+        class_minor: u16,
+        download_bandwidth_min: f32,
+        upload_bandwidth_min: f32,
+        download_bandwidth_max: f32,
+        upload_bandwidth_max: f32,
+    ) -> PyResult<()> {
+        self.builder
+            .add_site(
+                &site_name,
+                &parent_class_id,
+                &up_parent_class_id,
+                class_minor,
+                download_bandwidth_min,
+                upload_bandwidth_min,
+                download_bandwidth_max,
+                upload_bandwidth_max,
+            )
+            .map_err(PyValueError::new_err)
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    #[pyo3(signature = (
+        circuit_name,
+        site_name,
+        parent_class_id,
+        up_parent_class_id,
+        class_minor,
+        download_bandwidth_min,
+        upload_bandwidth_min,
+        download_bandwidth_max,
+        upload_bandwidth_max,
+        class_major,
+        up_class_major,
+        ip_addresses,
+        sqm_override=None
+    ))]
+    /// Adds a circuit: a class `class_major:class_minor` (and `up_class_major:class_minor`)
+    /// under the given parents. `ip_addresses` is comma-separated; `sqm_override` is a token
+    /// such as "cake", "fq_codel" or "cake:besteffort", or "down/up". Rates are in Mbps.
+    pub fn add_circuit(
+        &mut self,
+        circuit_name: String,
+        site_name: Option<String>,
+        parent_class_id: String,
+        up_parent_class_id: String,
+        class_minor: u16,
+        download_bandwidth_min: f32,
+        upload_bandwidth_min: f32,
+        download_bandwidth_max: f32,
+        upload_bandwidth_max: f32,
+        class_major: u16,
+        up_class_major: u16,
+        ip_addresses: String,
+        sqm_override: Option<String>,
+    ) -> PyResult<()> {
+        self.builder
+            .add_circuit(
+                &circuit_name,
+                site_name,
+                &parent_class_id,
+                &up_parent_class_id,
+                class_minor,
+                download_bandwidth_min,
+                upload_bandwidth_min,
+                download_bandwidth_max,
+                upload_bandwidth_max,
+                class_major,
+                up_class_major,
+                &ip_addresses,
+                sqm_override.as_deref(),
+            )
+            .map_err(PyValueError::new_err)
+    }
+
+    /// Number of sites added.
+    #[getter]
+    pub fn site_count(&self) -> usize {
+        self.builder.sites.len()
+    }
+
+    /// Number of circuits added.
+    #[getter]
+    pub fn circuit_count(&self) -> usize {
+        self.builder.circuits.len()
+    }
+
+    fn __len__(&self) -> usize {
+        self.builder.commands.len()
+    }
+
+    /// Compares the batch with the last one committed through `BakeryBatch`, without sending
+    /// anything.
+    pub fn diff(&self) -> PyResult<BakeryDiffSummary> {
+        let batch = self.builder.batch().map_err(PyValueError::new_err)?;
+        Ok(self.diff_against_last_commit(&batch))
+    }
+
+    /// Sends the batch to `lqosd` and commits it. Raises `ValueError` if the batch is
+    /// incomplete; bus and Bakery failures are reported in the result.
+    pub fn commit(&self, py: Python) -> PyResult<BakeryCommitResult> {
+        let batch = self.builder.batch().map_err(PyValueError::new_err)?;
+        let diff = self.diff_against_last_commit(&batch);
+        let chunk_size = self.chunk_size;
+
+        let outcome = py.allow_threads(|| {
+            tokio::runtime::Builder::new_current_thread()
+                .enable_all()
+                .build()
+                .map(|runtime| runtime.block_on(send_batch(&batch, chunk_size)))
+        });
+        let outcome = outcome.map_err(|e| PyOSError::new_err(e.to_string()))?;
+
+        if outcome.committed
+            && let Some(path) = last_commit_path()
+        {
+            let saved = serde_cbor::to_vec(&batch)
+                .map_err(|e| e.to_string())
+                .and_then(|bytes| std::fs::write(&path, bytes).map_err(|e| e.to_string()));
+            if let Err(e) = saved {
+                eprintln!(
+                    "Unable to save the committed batch to {}: {e}",
+                    path.display()
+                );
+            }
+        }
+
+        Ok(BakeryCommitResult {
+            committed: outcome.committed,
+            chunks_sent: outcome.chunks_sent,
+            commands_sent: outcome.commands_sent,
+            errors: outcome.errors,
+            diff,
+        })
+    }
+}
+
+impl BakeryBatch {
+    fn diff_against_last_commit(&self, batch: &[BakeryCommands]) -> BakeryDiffSummary {
+        let previous = last_commit_path().and_then(|path| load_last_commit(&path));
+        let summary = summarize_batch_diff(
+            &runtime_commands(previous.as_deref().unwrap_or_default()),
+            &runtime_commands(batch),
+        );
+        BakeryDiffSummary::new(previous.is_some(), summary)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn builder_with_site() -> BatchBuilder {
+        let mut builder = BatchBuilder::default();
+        builder
+            .add_site(
+                "Tower 1", "0x1:0x2", "0x3:0x2", 0x3, 10.0, 10.0, 500.0, 500.0,
+            )
+            .expect("valid site");
+        builder
+    }
+
+    fn add_circuit(
+        builder: &mut BatchBuilder,
+        name: &str,
+        class_minor: u16,
+        class_major: u16,
+        ip_addresses: &str,
+    ) -> Result<(), String> {
+        builder.add_circuit(
+            name,
+            Some("Tower 1".to_string()),
+            "0x1:0x3",
+            "0x3:0x3",
+            class_minor,
+            1.0,
+            1.0,
+            100.0,
+            20.0,
+            class_major,
+            0x3,
+            ip_addresses,
+            Some("CAKE/fq_codel"),
+        )
+    }
+
+    #[test]
+    fn accepts_a_valid_batch() {
+        let mut builder = builder_with_site();
+        add_circuit(
+            &mut builder,
+            "Customer 1",
+            0x4,
+            0x1,
+            "100.64.0.1, 2001:db8::/64",
+        )
+        .expect("valid circuit");
+        assert!(builder.batch().is_err(), "setup_mq is required");
+        builder.setup_mq(4, 2).expect("valid mq");
+
+        let batch = builder.batch().expect("complete batch");
+        assert_eq!(batch.len(), 4);
+        assert!(matches!(batch[0], BakeryCommands::StartBatch));
+        assert!(matches!(batch[1], BakeryCommands::MqSetup { .. }));
+        let BakeryCommands::AddCircuit { sqm_override, .. } = &batch[3] else {
+            panic!("expected a circuit");
+        };
+        assert_eq!(sqm_override.as_deref(), Some("cake/fq_codel"));
+    }
+
+    #[test]
+    fn rejects_invalid_entries_without_changing_the_batch() {
+        let mut builder = builder_with_site();
+        let rejected = [
+            // Same class as the site
+            add_circuit(&mut builder, "Customer 1", 0x3, 0x1, ""),
+            // Reserved minor
+            add_circuit(&mut builder, "Customer 2", 0x2, 0x1, ""),
+            // Class major does not match the parent's queue
+            add_circuit(&mut builder, "Customer 3", 0x4, 0x2, ""),
+            add_circuit(&mut builder, "Customer 4", 0x4, 0x1, "100.64.0.1/33"),
+            add_circuit(&mut builder, "Customer 5", 0x4, 0x1, "not-an-ip"),
+            builder.add_site("Tower 1", "0x1:0x2", "0x3:0x2", 0x9, 1.0, 1.0, 5.0, 5.0),
+            builder.add_site("Tower 2", "1:2", "bogus", 0x9, 1.0, 1.0, 5.0, 5.0),
+            builder.add_site("Tower 3", "1:2", "3:2", 0x9, 10.0, 1.0, 5.0, 5.0),
+            builder.add_site("Tower 4", "1:2", "3:2", 0x9, 0.0, 1.0, 5.0, 5.0),
+            builder.add_site("Tower 5", "1:2", "3:2", 0x9, f32::NAN, 1.0, 5.0, 5.0),
+        ];
+        for (index, result) in rejected.iter().enumerate() {
+            assert!(result.is_err(), "entry {index} should be rejected");
+        }
+        assert!(
+            builder
+                .add_circuit(
+                    "Customer 6",
+                    None,
+                    "0x1:0x3",
+                    "0x3:0x3",
+                    0x4,
+                    1.0,
+                    1.0,
+                    10.0,
+                    10.0,
+                    0x1,
+                    0x3,
+                    "",
+                    Some("cake:rtt"),
+                )
+                .is_err()
+        );
+        assert_eq!(builder.commands.len(), 1);
+        assert_eq!(builder.down_classes.len(), 1);
+    }
+}
@@ -24,7 +24,9 @@ use anyhow::{Error, Result};
 use blocking::{run_query, run_query_wait_for_bus};
 use lqos_bakery::estimate_full_reload_auto_qdisc_budget;
 use sysinfo::System;
+mod bakery_batch;
 mod device_weights;
+mod streaming;
 use base64::Engine as _;
 use base64::engine::general_purpose::STANDARD as BASE64_STANDARD;
 use serde::{Deserialize, Serialize};
@@ -1001,6 +1003,25 @@ fn liblqos_python(m: &Bound<'_, PyModule>) -> PyResult<()> {
     m.add_function(wrap_pyfunction!(plan_class_identities, m)?)?;
 
     m.add_class::<Bakery>()?;
+    m.add_class::<bakery_batch::BakeryBatch>()?;
+    m.add_class::<bakery_batch::BakeryCommitResult>()?;
+    m.add_class::<bakery_batch::BakeryDiffSummary>()?;
+
+    // Typed bus data and async streaming subscriptions
+    m.add_class::<streaming::ThroughputTick>()?;
+    m.add_class::<streaming::CircuitMetrics>()?;
+    m.add_class::<streaming::UrgentIssue>()?;
+    m.add_class::<streaming::HealthSnapshot>()?;
+    m.add_class::<streaming::BakeryEvent>()?;
+    m.add_class::<streaming::Subscription>()?;
+    m.add_function(wrap_pyfunction!(streaming::get_current_throughput, m)?)?;
+    m.add_function(wrap_pyfunction!(streaming::get_circuit_metrics, m)?)?;
+    m.add_function(wrap_pyfunction!(streaming::get_urgent_issues, m)?)?;
+    m.add_function(wrap_pyfunction!(streaming::get_health_snapshot, m)?)?;
+    m.add_function(wrap_pyfunction!(streaming::subscribe_throughput, m)?)?;
+    m.add_function(wrap_pyfunction!(streaming::subscribe_circuits, m)?)?;
+    m.add_function(wrap_pyfunction!(streaming::subscribe_urgent_issues, m)?)?;
+    m.add_function(wrap_pyfunction!(streaming::subscribe_bakery_events, m)?)?;
     Ok(())
 }
 
@@ -2676,8 +2697,8 @@ fn calculate_shaping_runtime_hash() -> PyResult<i64> {
 
 ////////////////////////////// The Bakery class //////////////////////////////
 
-#[derive(Clone)]
-enum BakeryCommands {
+#[derive(Clone, Serialize, Deserialize)]
+pub(crate) enum BakeryCommands {
     StartBatch,
     Commit,
     MqSetup {
@@ -2713,7 +2734,71 @@ enum BakeryCommands {
 }
 
 impl BakeryCommands {
-    fn as_runtime_command(&self) -> lqos_bakery::BakeryCommands {
+    pub(crate) fn as_bus_request(&self) -> BusRequest {
+        match self {
+            BakeryCommands::StartBatch => BusRequest::BakeryStart,
+            BakeryCommands::Commit => BusRequest::BakeryCommit,
+            BakeryCommands::MqSetup {
+                queues_available,
+                stick_offset,
+            } => BusRequest::BakeryMqSetup {
+                queues_available: *queues_available,
+                stick_offset: *stick_offset,
+            },
+            BakeryCommands::AddSite {
+                site_hash,
+                parent_class_id,
+                up_parent_class_id,
+                class_minor,
+                download_bandwidth_min,
+                upload_bandwidth_min,
+                download_bandwidth_max,
+                upload_bandwidth_max,
+            } => BusRequest::BakeryAddSite {
+                site_hash: *site_hash,
+                parent_class_id: *parent_class_id,
+                up_parent_class_id: *up_parent_class_id,
+                class_minor: *class_minor,
+                download_bandwidth_min: *download_bandwidth_min,
+                upload_bandwidth_min: *upload_bandwidth_min,
+                download_bandwidth_max: *download_bandwidth_max,
+                upload_bandwidth_max: *upload_bandwidth_max,
+            },
+            BakeryCommands::AddCircuit {
+                circuit_hash,
+                circuit_name,
+                site_name,
+                parent_class_id,
+                up_parent_class_id,
+                class_minor,
+                download_bandwidth_min,
+                upload_bandwidth_min,
+                download_bandwidth_max,
+                upload_bandwidth_max,
+                class_major,
+                up_class_major,
+                ip_addresses,
+                sqm_override,
+            } => BusRequest::BakeryAddCircuit {
+                circuit_hash: *circuit_hash,
+                circuit_name: circuit_name.clone(),
+                site_name: site_name.clone(),
+                parent_class_id: *parent_class_id,
+                up_parent_class_id: *up_parent_class_id,
+                class_minor: *class_minor,
+                download_bandwidth_min: *download_bandwidth_min,
+                upload_bandwidth_min: *upload_bandwidth_min,
+                download_bandwidth_max: *download_bandwidth_max,
+                upload_bandwidth_max: *upload_bandwidth_max,
+                class_major: *class_major,
+                up_class_major: *up_class_major,
+                ip_addresses: ip_addresses.clone(),
+                sqm_override: sqm_override.clone(),
+            },
+        }
+    }
+
+    pub(crate) fn as_runtime_command(&self) -> lqos_bakery::BakeryCommands {
         match self {
             BakeryCommands::StartBatch => lqos_bakery::BakeryCommands::StartBatch,
             BakeryCommands::Commit => lqos_bakery::BakeryCommands::CommitBatch,
@@ -2818,80 +2903,7 @@ impl Bakery {
                     };
                     let chunks = queue.chunks(1024);
                     for chunk in chunks {
-                        let mut requests = Vec::new();
-                        for msg in chunk {
-                            match msg {
-                                BakeryCommands::StartBatch => {
-                                    requests.push(BusRequest::BakeryStart)
-                                }
-                                BakeryCommands::Commit => requests.push(BusRequest::BakeryCommit),
-                                BakeryCommands::MqSetup {
-                                    queues_available,
-                                    stick_offset,
-                                } => {
-                                    requests.push(BusRequest::BakeryMqSetup {
-                                        queues_available: *queues_available,
-                                        stick_offset: *stick_offset,
-                                    });
-                                }
-                                BakeryCommands::AddSite {
-                                    site_hash,
-                                    parent_class_id,
-                                    up_parent_class_id,
-                                    class_minor,
-                                    download_bandwidth_min,
-                                    upload_bandwidth_min,
-                                    download_bandwidth_max,
-                                    upload_bandwidth_max,
-                                } => {
-                                    let command = BusRequest::BakeryAddSite {
-                                        site_hash: *site_hash,
-                                        parent_class_id: *parent_class_id,
-                                        up_parent_class_id: *up_parent_class_id,
-                                        class_minor: *class_minor,
-                                        download_bandwidth_min: *download_bandwidth_min,
-                                        upload_bandwidth_min: *upload_bandwidth_min,
-                                        download_bandwidth_max: *download_bandwidth_max,
-                                        upload_bandwidth_max: *upload_bandwidth_max,
-                                    };
-                                    requests.push(command);
-                                }
-                                BakeryCommands::AddCircuit {
-                                    circuit_hash,
-                                    circuit_name,
-                                    site_name,
-                                    parent_class_id,
-                                    up_parent_class_id,
-                                    class_minor,
-                                    download_bandwidth_min,
-                                    upload_bandwidth_min,
-                                    download_bandwidth_max,
-                                    upload_bandwidth_max,
-                                    class_major,
-                                    up_class_major,
-                                    ip_addresses,
-                                    sqm_override,
-                                } => {
-                                    let command = BusRequest::BakeryAddCircuit {
-                                        circuit_hash: *circuit_hash,
-                                        circuit_name: circuit_name.clone(),
-                                        site_name: site_name.clone(),
-                                        parent_class_id: *parent_class_id,
-                                        up_parent_class_id: *up_parent_class_id,
-                                        class_minor: *class_minor,
-                                        download_bandwidth_min: *download_bandwidth_min,
-                                        upload_bandwidth_min: *upload_bandwidth_min,
-                                        download_bandwidth_max: *download_bandwidth_max,
-                                        upload_bandwidth_max: *upload_bandwidth_max,
-                                        class_major: *class_major,
-                                        up_class_major: *up_class_major,
-                                        ip_addresses: ip_addresses.clone(),
-                                        sqm_override: sqm_override.clone(),
-                                    };
-                                    requests.push(command);
-                                }
-                            }
-                        }
+                        let requests = chunk.iter().map(BakeryCommands::as_bus_request).collect();
                         if let Err(e) = bus.request(requests).await {
                             eprintln!("Failed to send batch commands: {}", e);
                         } else {
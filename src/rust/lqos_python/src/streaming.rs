@@ -0,0 +1,843 @@
+//! Live data from `lqosd` for Python: typed snapshots of bus responses, and async iterators
+//! that poll the bus on an interval and yield new data as it arrives.
+//!
+//! The bus has no push subscriptions, so each subscription keeps one bus connection open and
+//! polls it. Subscriptions run on the `pyo3-async-runtimes` tokio runtime, so they can be used
+//! with `async for` from any asyncio event loop:
+//!
+//! ```python
+//! async for tick in liblqos_python.subscribe_throughput(1.0):
+//!     print(tick.bits_per_second)
+//! ```
+
+use crate::blocking::run_query;
+use lqos_bus::{BusRequest, BusResponse, LibreqosBusClient, UrgentSeverity, UrgentSource};
+use lqos_utils::units::DownUpOrder;
+use pyo3::exceptions::{PyOSError, PyStopAsyncIteration, PyValueError};
+use pyo3::prelude::*;
+use std::collections::{BTreeMap, HashSet, VecDeque};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tokio::sync::Mutex;
+use tokio::time::Instant;
+
+fn down_up<T: Copy>(value: &DownUpOrder<T>) -> (T, T) {
+    (value.down, value.up)
+}
+
+fn unix_now() -> f64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs_f64())
+        .unwrap_or(0.0)
+}
+
+fn py_option<T: std::fmt::Display>(value: &Option<T>) -> String {
+    value
+        .as_ref()
+        .map_or_else(|| "None".to_string(), ToString::to_string)
+}
+
+/// Shaper-wide throughput at one point in time. Rates are `(down, up)` tuples.
+#[pyclass]
+#[derive(Clone, Debug, PartialEq)]
+pub struct ThroughputTick {
+    /// Unix time (seconds) the sample was taken.
+    #[pyo3(get)]
+    pub timestamp: f64,
+    /// Bits per second.
+    #[pyo3(get)]
+    pub bits_per_second: (u64, u64),
+    /// Packets per second.
+    #[pyo3(get)]
+    pub packets_per_second: (u64, u64),
+    /// TCP packets per second.
+    #[pyo3(get)]
+    pub tcp_packets_per_second: (u64, u64),
+    /// UDP packets per second.
+    #[pyo3(get)]
+    pub udp_packets_per_second: (u64, u64),
+    /// ICMP packets per second.
+    #[pyo3(get)]
+    pub icmp_packets_per_second: (u64, u64),
+    /// Bits per second that passed through a shaped circuit.
+    #[pyo3(get)]
+    pub shaped_bits_per_second: (u64, u64),
+}
+
+#[pymethods]
+impl ThroughputTick {
+    fn __repr__(&self) -> String {
+        format!(
+            "ThroughputTick(timestamp={}, bits_per_second={:?}, packets_per_second={:?}, shaped_bits_per_second={:?})",
+            self.timestamp,
+            self.bits_per_second,
+            self.packets_per_second,
+            self.shaped_bits_per_second
+        )
+    }
+}
+
+impl ThroughputTick {
+    fn from_response(response: &BusResponse, timestamp: f64) -> Option<Self> {
+        let BusResponse::CurrentThroughput {
+            bits_per_second,
+            packets_per_second,
+            tcp_packets_per_second,
+            udp_packets_per_second,
+            icmp_packets_per_second,
+            shaped_bits_per_second,
+        } = response
+        else {
+            return None;
+        };
+        Some(Self {
+            timestamp,
+            bits_per_second: down_up(bits_per_second),
+            packets_per_second: down_up(packets_per_second),
+            tcp_packets_per_second: down_up(tcp_packets_per_second),
+            udp_packets_per_second: down_up(udp_packets_per_second),
+            icmp_packets_per_second: down_up(icmp_packets_per_second),
+            shaped_bits_per_second: down_up(shaped_bits_per_second),
+        })
+    }
+}
+
+/// Current metrics for one circuit, summed over its hosts. Rates are `(down, up)` tuples.
+#[pyclass]
+#[derive(Clone, Debug, PartialEq)]
+pub struct CircuitMetrics {
+    /// Circuit ID from `ShapedDevices.csv`.
+    #[pyo3(get)]
+    pub circuit_id: String,
+    /// Circuit name, or an empty string if it has none.
+    #[pyo3(get)]
+    pub circuit_name: String,
+    /// Parent node, or an empty string if the circuit is not in the network tree.
+    #[pyo3(get)]
+    pub parent_node: String,
+    /// Number of distinct devices seen with traffic.
+    #[pyo3(get)]
+    pub device_count: usize,
+    /// Number of IP addresses seen with traffic.
+    #[pyo3(get)]
+    pub host_count: usize,
+    /// Bytes per second.
+    #[pyo3(get)]
+    pub bytes_per_second: (u64, u64),
+    /// Plan rate in Mbps.
+    #[pyo3(get)]
+    pub plan_mbps: (f32, f32),
+    /// Median of the hosts' median round-trip times in milliseconds, if any host had enough
+    /// traffic to measure.
+    #[pyo3(get)]
+    pub median_latency_ms: Option<f32>,
+    /// Quality of Outcome score (0-100) of the worst host, if known.
+    #[pyo3(get)]
+    pub qoo: (Option<f32>, Option<f32>),
+}
+
+#[pymethods]
+impl CircuitMetrics {
+    fn __repr__(&self) -> String {
+        format!(
+            "CircuitMetrics(circuit_id={:?}, circuit_name={:?}, bytes_per_second={:?}, median_latency_ms={})",
+            self.circuit_id,
+            self.circuit_name,
+            self.bytes_per_second,
+            py_option(&self.median_latency_ms)
+        )
+    }
+}
+
+fn worst_qoo(current: Option<f32>, host: Option<f32>) -> Option<f32> {
+    match (current, host) {
+        (Some(a), Some(b)) => Some(a.min(b)),
+        (a, b) => a.or(b),
+    }
+}
+
+/// Groups per-host circuit data into one entry per circuit, optionally keeping only the
+/// circuits in `filter`. Hosts that are not mapped to a circuit are skipped.
+fn circuit_metrics(
+    hosts: &[lqos_bus::Circuit],
+    filter: Option<&HashSet<String>>,
+) -> Vec<CircuitMetrics> {
+    struct Accumulator {
+        metrics: CircuitMetrics,
+        devices: HashSet<String>,
+        latencies: Vec<f32>,
+    }
+
+    let mut circuits: BTreeMap<&str, Accumulator> = BTreeMap::new();
+    for host in hosts {
+        let Some(circuit_id) = host.circuit_id.as_deref() else {
+            continue;
+        };
+        if let Some(filter) = filter
+            && !filter.contains(circuit_id)
+        {
+            continue;
+        }
+        let entry = circuits.entry(circuit_id).or_insert_with(|| Accumulator {
+            metrics: CircuitMetrics {
+                circuit_id: circuit_id.to_string(),
+                circuit_name: host.circuit_name.clone().unwrap_or_default(),
+                parent_node: host.parent_node.clone().unwrap_or_default(),
+                device_count: 0,
+                host_count: 0,
+                bytes_per_second: (0, 0),
+                plan_mbps: down_up(&host.plan),
+                median_latency_ms: None,
+                qoo: (None, None),
+            },
+            devices: HashSet::new(),
+            latencies: Vec::new(),
+        });
+        let metrics = &mut entry.metrics;
+        metrics.host_count += 1;
+        metrics.bytes_per_second.0 += host.bytes_per_second.down;
+        metrics.bytes_per_second.1 += host.bytes_per_second.up;
+        metrics.qoo = (
+            worst_qoo(metrics.qoo.0, host.qoo.down),
+            worst_qoo(metrics.qoo.1, host.qoo.up),
+        );
+        if let Some(device_id) = &host.device_id {
+            entry.devices.insert(device_id.clone());
+        }
+        if let Some(latency) = host.median_latency {
+            entry.latencies.push(latency);
+        }
+    }
+
+    circuits
+        .into_values()
+        .map(|mut entry| {
+            entry.metrics.device_count = entry.devices.len();
+            entry.latencies.sort_by(f32::total_cmp);
+            entry.metrics.median_latency_ms =
+                entry.latencies.get(entry.latencies.len() / 2).copied();
+            entry.metrics
+        })
+        .collect()
+}
+
+/// An urgent issue raised on the shaper, as shown in the Node Manager UI.
+#[pyclass]
+#[derive(Clone, Debug, PartialEq)]
+pub struct UrgentIssue {
+    /// Unique identifier.
+    #[pyo3(get)]
+    pub id: u64,
+    /// Unix time (seconds) the issue was raised.
+    #[pyo3(get)]
+    pub timestamp: u64,
+    /// Component that raised it: "Scheduler", "LibreQoS", "API" or "System".
+    #[pyo3(get)]
+    pub source: String,
+    /// "Error" or "Warning".
+    #[pyo3(get)]
+    pub severity: String,
+    /// Machine-readable code, e.g. "TC_U16_OVERFLOW".
+    #[pyo3(get)]
+    pub code: String,
+    /// Human-readable description.
+    #[pyo3(get)]
+    pub message: String,
+    /// Optional JSON string with extra details.
+    #[pyo3(get)]
+    pub context: Option<String>,
+    /// Optional key used to deduplicate repeats.
+    #[pyo3(get)]
+    pub dedupe_key: Option<String>,
+}
+
+#[pymethods]
+impl UrgentIssue {
+    fn __repr__(&self) -> String {
+        format!(
+            "UrgentIssue(id={}, severity={:?}, source={:?}, code={:?}, message={:?})",
+            self.id, self.severity, self.source, self.code, self.message
+        )
+    }
+}
+
+impl From<&lqos_bus::UrgentIssue> for UrgentIssue {
+    fn from(issue: &lqos_bus::UrgentIssue) -> Self {
+        let source = match issue.source {
+            UrgentSource::Scheduler => "Scheduler",
+            UrgentSource::LibreQoS => "LibreQoS",
+            UrgentSource::API => "API",
+            UrgentSource::System => "System",
+        };
+        let severity = match issue.severity {
+            UrgentSeverity::Error => "Error",
+            UrgentSeverity::Warning => "Warning",
+        };
+        Self {
+            id: issue.id,
+            timestamp: issue.ts,
+            source: source.to_string(),
+            severity: severity.to_string(),
+            code: issue.code.clone(),
+            message: issue.message.clone(),
+            context: issue.context.clone(),
+            dedupe_key: issue.dedupe_key.clone(),
+        }
+    }
+}
+
+/// Shaper health counters used by monitoring checks.
+#[pyclass]
+#[derive(Clone, Debug, PartialEq)]
+pub struct HealthSnapshot {
+    /// Why the Bakery needs a full reload before applying more changes, if it does.
+    #[pyo3(get)]
+    pub reload_required_reason: Option<String>,
+    /// Entries in the XDP IP-to-CPU/TC map, or `None` if the map could not be read.
+    #[pyo3(get)]
+    pub ip_mappings: Option<usize>,
+    /// Maximum entries in the XDP IP-to-CPU/TC map.
+    #[pyo3(get)]
+    pub ip_mapping_capacity: usize,
+    /// Usage (0-100) of each CPU core.
+    #[pyo3(get)]
+    pub cpu_usage: Vec<u32>,
+}
+
+#[pymethods]
+impl HealthSnapshot {
+    fn __repr__(&self) -> String {
+        format!(
+            "HealthSnapshot(reload_required_reason={}, ip_mappings={}, ip_mapping_capacity={})",
+            self.reload_required_reason
+                .as_ref()
+                .map_or_else(|| "None".to_string(), |reason| format!("{reason:?}")),
+            py_option(&self.ip_mappings),
+            self.ip_mapping_capacity
+        )
+    }
+}
+
+impl From<&lqos_bus::HealthSnapshot> for HealthSnapshot {
+    fn from(snapshot: &lqos_bus::HealthSnapshot) -> Self {
+        Self {
+            reload_required_reason: snapshot.reload_required_reason.clone(),
+            ip_mappings: snapshot.ip_mappings,
+            ip_mapping_capacity: snapshot.ip_mapping_capacity,
+            cpu_usage: snapshot.cpu_usage.clone(),
+        }
+    }
+}
+
+/// A change in the Bakery's state, detected between two polls.
+///
+/// `kind` is one of:
+/// - "snapshot": the state when the subscription started
+/// - "circuits_changed": the number of active circuits changed; `detail` is "old -> new"
+/// - "reload_started" / "reload_finished": a full reload batch began or completed
+/// - "reload_required": the Bakery needs a full reload; `detail` says why
+/// - "reload_cleared": a full reload is no longer required
+#[pyclass]
+#[derive(Clone, Debug, PartialEq)]
+pub struct BakeryEvent {
+    /// Unix time (seconds) the change was seen.
+    #[pyo3(get)]
+    pub timestamp: f64,
+    /// What changed.
+    #[pyo3(get)]
+    pub kind: String,
+    /// Circuits the Bakery is tracking after the change.
+    #[pyo3(get)]
+    pub active_circuits: usize,
+    /// Extra information, depending on `kind`.
+    #[pyo3(get)]
+    pub detail: Option<String>,
+}
+
+#[pymethods]
+impl BakeryEvent {
+    fn __repr__(&self) -> String {
+        format!(
+            "BakeryEvent(kind={:?}, active_circuits={}, detail={})",
+            self.kind,
+            self.active_circuits,
+            self.detail
+                .as_ref()
+                .map_or_else(|| "None".to_string(), |detail| format!("{detail:?}"))
+        )
+    }
+}
+
+/// What a Bakery subscription compares between polls.
+#[derive(Clone, Debug, Default, PartialEq)]
+struct BakeryState {
+    active_circuits: usize,
+    reload_in_progress: bool,
+    reload_required_reason: Option<String>,
+}
+
+impl BakeryState {
+    fn from_responses(responses: &[BusResponse]) -> Self {
+        let mut state = Self::default();
+        for response in responses {
+            match response {
+                BusResponse::BakeryActiveCircuits(count) => state.active_circuits = *count,
+                BusResponse::HealthSnapshot(health) => {
+                    state.reload_required_reason = health.reload_required_reason.clone();
+                }
+                BusResponse::ExecutiveSummaryHeader(header) => {
+                    state.reload_in_progress = header.bakery_reload_in_progress;
+                }
+                _ => {}
+            }
+        }
+        state
+    }
+}
+
+fn bakery_events(
+    previous: Option<&BakeryState>,
+    current: &BakeryState,
+    timestamp: f64,
+) -> Vec<BakeryEvent> {
+    let event = |kind: &str, detail: Option<String>| BakeryEvent {
+        timestamp,
+        kind: kind.to_string(),
+        active_circuits: current.active_circuits,
+        detail,
+    };
+    let Some(previous) = previous else {
+        return vec![event("snapshot", current.reload_required_reason.clone())];
+    };
+
+    let mut events = Vec::new();
+    match (previous.reload_in_progress, current.reload_in_progress) {
+        (false, true) => events.push(event("reload_started", None)),
+        (true, false) => events.push(event("reload_finished", None)),
+        _ => {}
+    }
+    if previous.reload_required_reason != current.reload_required_reason {
+        match &current.reload_required_reason {
+            Some(reason) => events.push(event("reload_required", Some(reason.clone()))),
+            None => events.push(event("reload_cleared", None)),
+        }
+    }
+    if previous.active_circuits != current.active_circuits {
+        events.push(event(
+            "circuits_changed",
+            Some(format!(
+                "{} -> {}",
+                previous.active_circuits, current.active_circuits
+            )),
+        ));
+    }
+    events
+}
+
+/// Returns the shaper's current throughput, or `None` if `lqosd` can't be reached.
+#[pyfunction]
+pub fn get_current_throughput() -> PyResult<Option<ThroughputTick>> {
+    let Ok(responses) = run_query(vec![BusRequest::GetCurrentThroughput]) else {
+        return Ok(None);
+    };
+    let timestamp = unix_now();
+    Ok(responses
+        .iter()
+        .find_map(|response| ThroughputTick::from_response(response, timestamp)))
+}
+
+/// Returns current metrics for every circuit with traffic, or only for `circuit_ids`.
+#[pyfunction]
+#[pyo3(signature = (circuit_ids=None))]
+pub fn get_circuit_metrics(circuit_ids: Option<Vec<String>>) -> PyResult<Vec<CircuitMetrics>> {
+    let filter = circuit_ids.map(|ids| ids.into_iter().collect::<HashSet<_>>());
+    let Ok(responses) = run_query(vec![BusRequest::GetAllCircuits]) else {
+        return Ok(Vec::new());
+    };
+    for response in responses {
+        if let BusResponse::CircuitData(hosts) = response {
+            return Ok(circuit_metrics(&hosts, filter.as_ref()));
+        }
+    }
+    Ok(Vec::new())
+}
+
+/// Returns the urgent issues currently raised on the shaper.
+#[pyfunction]
+pub fn get_urgent_issues() -> PyResult<Vec<UrgentIssue>> {
+    let Ok(responses) = run_query(vec![BusRequest::GetUrgentIssues]) else {
+        return Ok(Vec::new());
+    };
+    for response in responses {
+        if let BusResponse::UrgentIssues(issues) = response {
+            return Ok(issues.iter().map(UrgentIssue::from).collect());
+        }
+    }
+    Ok(Vec::new())
+}
+
+/// Returns the shaper's health counters, or `None` if `lqosd` can't be reached.
+#[pyfunction]
+pub fn get_health_snapshot() -> PyResult<Option<HealthSnapshot>> {
+    let Ok(responses) = run_query(vec![BusRequest::GetHealthSnapshot]) else {
+        return Ok(None);
+    };
+    Ok(responses.iter().find_map(|response| match response {
+        BusResponse::HealthSnapshot(snapshot) => Some(HealthSnapshot::from(snapshot)),
+        _ => None,
+    }))
+}
+
+enum StreamKind {
+    Throughput,
+    Circuits(Option<HashSet<String>>),
+    UrgentIssues { include_existing: bool },
+    Bakery,
+}
+
+impl StreamKind {
+    fn requests(&self) -> Vec<BusRequest> {
+        match self {
+            Self::Throughput => vec![BusRequest::GetCurrentThroughput],
+            Self::Circuits(_) => vec![BusRequest::GetAllCircuits],
+            Self::UrgentIssues { .. } => vec![BusRequest::GetUrgentIssues],
+            Self::Bakery => vec![
+                BusRequest::GetBakeryStats,
+                BusRequest::GetHealthSnapshot,
+                BusRequest::GetExecutiveSummaryHeader,
+            ],
+        }
+    }
+}
+
+enum StreamItem {
+    Throughput(ThroughputTick),
+    Circuits(Vec<CircuitMetrics>),
+    UrgentIssue(UrgentIssue),
+    Bakery(BakeryEvent),
+}
+
+impl StreamItem {
+    fn into_object(self, py: Python<'_>) -> PyResult<PyObject> {
+        Ok(match self {
+            Self::Throughput(tick) => Py::new(py, tick)?.into_any(),
+            Self::Circuits(circuits) => circuits.into_pyobject(py)?.into_any().unbind(),
+            Self::UrgentIssue(issue) => Py::new(py, issue)?.into_any(),
+            Self::Bakery(event) => Py::new(py, event)?.into_any(),
+        })
+    }
+}
+
+#[derive(Default)]
+struct StreamState {
+    client: Option<LibreqosBusClient>,
+    next_poll: Option<Instant>,
+    pending: VecDeque<StreamItem>,
+    seen_issues: Option<HashSet<u64>>,
+    bakery: Option<BakeryState>,
+}
+
+struct Stream {
+    kind: StreamKind,
+    interval: Duration,
+    closed: AtomicBool,
+    state: Mutex<StreamState>,
+}
+
+impl Stream {
+    async fn next(&self) -> PyResult<StreamItem> {
+        let mut state = self.state.lock().await;
+        loop {
+            if self.closed.load(Ordering::Relaxed) {
+                state.client = None;
+                return Err(PyStopAsyncIteration::new_err(()));
+            }
+            if let Some(item) = state.pending.pop_front() {
+                return Ok(item);
+            }
+            if let Some(next_poll) = state.next_poll {
+                tokio::time::sleep_until(next_poll).await;
+            }
+            state.next_poll = Some(Instant::now() + self.interval);
+            let responses = Self::poll(&mut state, self.kind.requests()).await?;
+            self.collect(&mut state, &responses);
+        }
+    }
+
+    /// Sends one poll, connecting first if needed. A failed connection is dropped so the
+    /// next poll reconnects, e.g. after `lqosd` restarts.
+    async fn poll(
+        state: &mut StreamState,
+        requests: Vec<BusRequest>,
+    ) -> PyResult<Vec<BusResponse>> {
+        let mut client = match state.client.take() {
+            Some(client) => client,
+            None => LibreqosBusClient::new()
+                .await
+                .map_err(|e| PyOSError::new_err(format!("Unable to connect to lqosd: {e}")))?,
+        };
+        let responses = client
+            .request(requests)
+            .await
+            .map_err(|e| PyOSError::new_err(format!("lqosd bus request failed: {e}")))?;
+        state.client = Some(client);
+        Ok(responses)
+    }
+
+    fn collect(&self, state: &mut StreamState, responses: &[BusResponse]) {
+        let timestamp = unix_now();
+        match &self.kind {
+            StreamKind::Throughput => {
+                if let Some(tick) = responses
+                    .iter()
+                    .find_map(|response| ThroughputTick::from_response(response, timestamp))
+                {
+                    state.pending.push_back(StreamItem::Throughput(tick));
+                }
+            }
+            StreamKind::Circuits(filter) => {
+                for response in responses {
+                    if let BusResponse::CircuitData(hosts) = response {
+                        state
+                            .pending
+                            .push_back(StreamItem::Circuits(circuit_metrics(
+                                hosts,
+                                filter.as_ref(),
+                            )));
+                    }
+                }
+            }
+            StreamKind::UrgentIssues { include_existing } => {
+                for response in responses {
+                    if let BusResponse::UrgentIssues(issues) = response {
+                        let current: HashSet<u64> = issues.iter().map(|issue| issue.id).collect();
+                        let seen = state.seen_issues.replace(current);
+                        for issue in issues {
+                            let is_new = match &seen {
+                                Some(seen) => !seen.contains(&issue.id),
+                                None => *include_existing,
+                            };
+                            if is_new {
+                                state
+                                    .pending
+                                    .push_back(StreamItem::UrgentIssue(UrgentIssue::from(issue)));
+                            }
+                        }
+                    }
+                }
+            }
+            StreamKind::Bakery => {
+                let current = BakeryState::from_responses(responses);
+                for event in bakery_events(state.bakery.as_ref(), &current, timestamp) {
+                    state.pending.push_back(StreamItem::Bakery(event));
+                }
+                state.bakery = Some(current);
+            }
+        }
+    }
+}
+
+/// An async iterator over live data from `lqosd`, created by one of the `subscribe_*`
+/// functions. Each subscription holds its own bus connection. If a poll fails, the
+/// iteration raises `OSError`; iterating again reconnects. Call `close()` to end it.
+#[pyclass]
+pub struct Subscription {
+    stream: Arc<Stream>,
+}
+
+impl Subscription {
+    fn new(kind: StreamKind, interval_seconds: f64) -> PyResult<Self> {
+        if !interval_seconds.is_finite() || interval_seconds <= 0.0 {
+            return Err(PyValueError::new_err(
+                "interval_seconds must be a positive number",
+            ));
+        }
+        Ok(Self {
+            stream: Arc::new(Stream {
+                kind,
+                interval: Duration::from_secs_f64(interval_seconds),
+                closed: AtomicBool::new(false),
+                state: Mutex::new(StreamState::default()),
+            }),
+        })
+    }
+}
+
+#[pymethods]
+impl Subscription {
+    fn __aiter__(slf: PyRef<'_, Self>) -> PyRef<'_, Self> {
+        slf
+    }
+
+    fn __anext__<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyAny>> {
+        let stream = self.stream.clone();
+        pyo3_async_runtimes::tokio::future_into_py(py, async move {
+            let item = stream.next().await?;
+            Python::with_gil(|py| item.into_object(py))
+        })
+    }
+
+    /// Stops the subscription. A pending `__anext__` finishes its current poll first.
+    fn close(&self) {
+        self.stream.closed.store(true, Ordering::Relaxed);
+    }
+
+    /// Whether `close()` has been called.
+    #[getter]
+    fn closed(&self) -> bool {
+        self.stream.closed.load(Ordering::Relaxed)
+    }
+
+    /// Seconds between polls of `lqosd`.
+    #[getter]
+    fn interval_seconds(&self) -> f64 {
+        self.stream.interval.as_secs_f64()
+    }
+}
+
+/// Subscribes to shaper-wide throughput, yielding a `ThroughputTick` every
+/// `interval_seconds`.
+#[pyfunction]
+#[pyo3(signature = (interval_seconds=1.0))]
+pub fn subscribe_throughput(interval_seconds: f64) -> PyResult<Subscription> {
+    Subscription::new(StreamKind::Throughput, interval_seconds)
+}
+
+/// Subscribes to circuit metrics, yielding a list of `CircuitMetrics` every
+/// `interval_seconds`. Pass `circuit_ids` to only follow those circuits.
+#[pyfunction]
+#[pyo3(signature = (interval_seconds=1.0, circuit_ids=None))]
+pub fn subscribe_circuits(
+    interval_seconds: f64,
+    circuit_ids: Option<Vec<String>>,
+) -> PyResult<Subscription> {
+    let filter = circuit_ids.map(|ids| ids.into_iter().collect());
+    Subscription::new(StreamKind::Circuits(filter), interval_seconds)
+}
+
+/// Subscribes to urgent issues, yielding each `UrgentIssue` once, when it is first seen.
+/// Issues already raised when the subscription starts are skipped unless
+/// `include_existing` is true.
+#[pyfunction]
+#[pyo3(signature = (interval_seconds=5.0, include_existing=false))]
+pub fn subscribe_urgent_issues(
+    interval_seconds: f64,
+    include_existing: bool,
+) -> PyResult<Subscription> {
+    Subscription::new(
+        StreamKind::UrgentIssues { include_existing },
+        interval_seconds,
+    )
+}
+
+/// Subscribes to Bakery state changes, yielding a `BakeryEvent` for each change seen
+/// between polls, starting with a "snapshot" of the current state.
+#[pyfunction]
+#[pyo3(signature = (interval_seconds=1.0))]
+pub fn subscribe_bakery_events(interval_seconds: f64) -> PyResult<Subscription> {
+    Subscription::new(StreamKind::Bakery, interval_seconds)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn host(
+        circuit_id: &str,
+        device_id: &str,
+        down: u64,
+        latency: Option<f32>,
+    ) -> lqos_bus::Circuit {
+        lqos_bus::Circuit {
+            ip: "100.64.0.1".parse().expect("valid IP"),
+            bytes_per_second: DownUpOrder::new(down, down / 10),
+            median_latency: latency,
+            rtt_current_p50_nanos: DownUpOrder::new(None, None),
+            rtt_current_p95_nanos: DownUpOrder::new(None, None),
+            rtt_total_p50_nanos: DownUpOrder::new(None, None),
+            rtt_total_p95_nanos: DownUpOrder::new(None, None),
+            qoo: DownUpOrder::new(latency.map(|l| 100.0 - l), None),
+            tcp_retransmit_sample: DownUpOrder::default(),
+            circuit_id: Some(circuit_id.to_string()),
+            device_id: Some(device_id.to_string()),
+            parent_node: Some("Tower 1".to_string()),
+            circuit_name: Some(format!("Customer {circuit_id}")),
+            device_name: None,
+            plan: DownUpOrder::new(100.0, 20.0),
+            last_seen_nanos: 0,
+        }
+    }
+
+    #[test]
+    fn circuit_metrics_sums_hosts_per_circuit() {
+        let hosts = vec![
+            host("a", "a-1", 1_000, Some(10.0)),
+            host("a", "a-1", 2_000, Some(30.0)),
+            host("a", "a-2", 3_000, Some(20.0)),
+            host("b", "b-1", 500, None),
+        ];
+        let metrics = circuit_metrics(&hosts, None);
+        assert_eq!(metrics.len(), 2);
+        let a = &metrics[0];
+        assert_eq!(a.circuit_id, "a");
+        assert_eq!(a.host_count, 3);
+        assert_eq!(a.device_count, 2);
+        assert_eq!(a.bytes_per_second, (6_000, 600));
+        assert_eq!(a.median_latency_ms, Some(20.0));
+        assert_eq!(a.qoo, (Some(70.0), None));
+        assert_eq!(metrics[1].median_latency_ms, None);
+
+        let only_b: HashSet<String> = ["b".to_string()].into();
+        let filtered = circuit_metrics(&hosts, Some(&only_b));
+        assert_eq!(filtered.len(), 1);
+        assert_eq!(filtered[0].circuit_id, "b");
+    }
+
+    #[test]
+    fn bakery_events_report_each_change() {
+        let idle = BakeryState {
+            active_circuits: 10,
+            reload_in_progress: false,
+            reload_required_reason: None,
+        };
+        let kinds = |events: Vec<BakeryEvent>| {
+            events
+                .into_iter()
+                .map(|event| (event.kind, event.detail))
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(
+            kinds(bakery_events(None, &idle, 0.0)),
+            vec![("snapshot".to_string(), None)]
+        );
+        assert!(bakery_events(Some(&idle), &idle, 0.0).is_empty());
+
+        let reloading = BakeryState {
+            active_circuits: 12,
+            reload_in_progress: true,
+            reload_required_reason: Some("qdisc budget exceeded".to_string()),
+        };
+        assert_eq!(
+            kinds(bakery_events(Some(&idle), &reloading, 0.0)),
+            vec![
+                ("reload_started".to_string(), None),
+                (
+                    "reload_required".to_string(),
+                    Some("qdisc budget exceeded".to_string())
+                ),
+                ("circuits_changed".to_string(), Some("10 -> 12".to_string())),
+            ]
+        );
+        assert_eq!(
+            kinds(bakery_events(Some(&reloading), &idle, 0.0)),
+            vec![
+                ("reload_finished".to_string(), None),
+                ("reload_cleared".to_string(), None),
+                ("circuits_changed".to_string(), Some("12 -> 10".to_string())),
+            ]
+        );
+    }
+}
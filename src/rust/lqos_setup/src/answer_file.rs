@@ -0,0 +1,525 @@
+//! Headless setup from a TOML or YAML answer file, for provisioning shapers with Ansible and
+//! similar tools. Runs the same preflight checks and writes the same files as the console
+//! UI, and reports the outcome as JSON on stdout.
+
+use crate::config_builder::{BridgeMode, ConfigBuilder};
+use crate::preflight;
+use ip_network::IpNetwork;
+use lqos_config::{UserRole, WebUsers};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+/// Nothing was written: the answer file or preflight checks failed
+const EXIT_INVALID: i32 = 1;
+/// Setup failed part-way through writing
+const EXIT_WRITE_FAILED: i32 = 2;
+
+#[derive(Deserialize, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct AnswerFile {
+    /// Defaults to the existing node name, or "LibreQoS"
+    #[serde(default)]
+    pub node_name: Option<String>,
+    pub mode: Mode,
+    pub interfaces: Interfaces,
+    pub bandwidth: Bandwidth,
+    /// Subnets to shape. Defaults to the existing list, or the private ranges.
+    #[serde(default)]
+    pub ip_ranges: Option<Vec<String>>,
+    /// First WebUI user, created as (or updated to) an admin
+    #[serde(default)]
+    pub admin: Option<Admin>,
+}
+
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum Mode {
+    LinuxBridge,
+    XdpBridge,
+    SingleInterface,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct Interfaces {
+    pub to_internet: String,
+    /// Bridge modes only
+    #[serde(default)]
+    pub to_network: Option<String>,
+    /// Single interface mode only
+    #[serde(default)]
+    pub internet_vlan: u32,
+    /// Single interface mode only
+    #[serde(default)]
+    pub network_vlan: u32,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct Bandwidth {
+    pub to_internet_mbps: u64,
+    pub to_network_mbps: u64,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct Admin {
+    pub username: String,
+    /// Give either `password` or `password_file`
+    #[serde(default)]
+    pub password: Option<String>,
+    #[serde(default)]
+    pub password_file: Option<String>,
+}
+
+/// A machine-readable problem. `field` is the dotted answer-file path, or empty.
+#[derive(Serialize, Debug, PartialEq)]
+pub struct SetupError {
+    pub field: String,
+    pub code: &'static str,
+    pub message: String,
+}
+
+impl SetupError {
+    fn new(field: &str, code: &'static str, message: impl Into<String>) -> Self {
+        Self {
+            field: field.to_string(),
+            code,
+            message: message.into(),
+        }
+    }
+}
+
+#[derive(Serialize, Debug, Default)]
+struct Report {
+    success: bool,
+    dry_run: bool,
+    errors: Vec<SetupError>,
+    actions: Vec<String>,
+}
+
+/// Validated answers, ready to write
+pub struct Plan {
+    pub config: ConfigBuilder,
+    /// Username and password
+    pub admin: Option<(String, String)>,
+}
+
+impl AnswerFile {
+    /// Parses an answer file: YAML if the extension is `.yaml` or `.yml`, TOML otherwise.
+    pub fn load(path: &Path) -> Result<Self, SetupError> {
+        let text = std::fs::read_to_string(path).map_err(|e| {
+            SetupError::new("", "unreadable", format!("Unable to read {path:?}: {e}"))
+        })?;
+        let yaml = path
+            .extension()
+            .is_some_and(|ext| ext == "yaml" || ext == "yml");
+        Self::parse(&text, yaml)
+    }
+
+    pub fn parse(text: &str, yaml: bool) -> Result<Self, SetupError> {
+        if yaml {
+            serde_norway::from_str(text).map_err(|e| SetupError::new("", "parse", e.to_string()))
+        } else {
+            toml::from_str(text).map_err(|e| SetupError::new("", "parse", e.to_string()))
+        }
+    }
+
+    /// Checks every answer against the machine, reporting all problems rather than the
+    /// first. `base` supplies defaults for anything the file leaves out.
+    pub fn validate(
+        &self,
+        base: ConfigBuilder,
+        compatible: &[String],
+    ) -> Result<Plan, Vec<SetupError>> {
+        let mut errors = Vec::new();
+        let mut config = base;
+
+        let interface = |field: &str, name: &str, errors: &mut Vec<SetupError>| {
+            if name.is_empty() {
+                errors.push(SetupError::new(
+                    field,
+                    "required",
+                    "An interface is required",
+                ));
+            } else if !compatible.iter().any(|c| c == name) {
+                errors.push(SetupError::new(
+                    field,
+                    "interface_unsupported",
+                    format!(
+                        "{name} is not a compatible interface (found: {})",
+                        compatible.join(", ")
+                    ),
+                ));
+            }
+        };
+
+        let ifaces = &self.interfaces;
+        interface("interfaces.to_internet", &ifaces.to_internet, &mut errors);
+        match self.mode {
+            Mode::LinuxBridge | Mode::XdpBridge => {
+                match &ifaces.to_network {
+                    None => errors.push(SetupError::new(
+                        "interfaces.to_network",
+                        "required",
+                        "Bridge modes need a network-facing interface",
+                    )),
+                    Some(to_network) if *to_network == ifaces.to_internet => {
+                        errors.push(SetupError::new(
+                            "interfaces.to_network",
+                            "same_interface",
+                            "to_network must differ from to_internet; use single_interface mode for one interface",
+                        ))
+                    }
+                    Some(to_network) => {
+                        interface("interfaces.to_network", to_network, &mut errors)
+                    }
+                }
+                for (field, vlan) in [
+                    ("interfaces.internet_vlan", ifaces.internet_vlan),
+                    ("interfaces.network_vlan", ifaces.network_vlan),
+                ] {
+                    if vlan != 0 {
+                        errors.push(SetupError::new(
+                            field,
+                            "not_applicable",
+                            "VLANs only apply in single_interface mode",
+                        ));
+                    }
+                }
+            }
+            Mode::SingleInterface => {
+                if ifaces.to_network.is_some() {
+                    errors.push(SetupError::new(
+                        "interfaces.to_network",
+                        "not_applicable",
+                        "single_interface mode uses only to_internet",
+                    ));
+                }
+                for (field, vlan) in [
+                    ("interfaces.internet_vlan", ifaces.internet_vlan),
+                    ("interfaces.network_vlan", ifaces.network_vlan),
+                ] {
+                    if vlan > 4094 {
+                        errors.push(SetupError::new(
+                            field,
+                            "out_of_range",
+                            format!("VLAN {vlan} is outside 0-4094"),
+                        ));
+                    }
+                }
+                if ifaces.internet_vlan == ifaces.network_vlan {
+                    errors.push(SetupError::new(
+                        "interfaces.network_vlan",
+                        "same_vlan",
+                        "The internet and network VLANs must differ",
+                    ));
+                }
+            }
+        }
+        config.bridge_mode = match self.mode {
+            Mode::LinuxBridge => BridgeMode::Linux,
+            Mode::XdpBridge => BridgeMode::XDP,
+            Mode::SingleInterface => BridgeMode::Single,
+        };
+        config.to_internet = ifaces.to_internet.clone();
+        config.to_network = ifaces.to_network.clone().unwrap_or_default();
+        config.internet_vlan = ifaces.internet_vlan;
+        config.network_vlan = ifaces.network_vlan;
+
+        for (field, mbps) in [
+            (
+                "bandwidth.to_internet_mbps",
+                self.bandwidth.to_internet_mbps,
+            ),
+            ("bandwidth.to_network_mbps", self.bandwidth.to_network_mbps),
+        ] {
+            if mbps == 0 {
+                errors.push(SetupError::new(
+                    field,
+                    "out_of_range",
+                    "Bandwidth must be at least 1 Mbps",
+                ));
+            }
+        }
+        config.mbps_to_internet = self.bandwidth.to_internet_mbps;
+        config.mbps_to_network = self.bandwidth.to_network_mbps;
+
+        if let Some(ranges) = &self.ip_ranges {
+            if ranges.is_empty() {
+                errors.push(SetupError::new(
+                    "ip_ranges",
+                    "required",
+                    "At least one IP range is needed, or nothing will be shaped",
+                ));
+            }
+            for (i, range) in ranges.iter().enumerate() {
+                if range.parse::<IpNetwork>().is_err() {
+                    errors.push(SetupError::new(
+                        &format!("ip_ranges[{i}]"),
+                        "invalid_ip_range",
+                        format!("{range} is not in CIDR notation, e.g. 192.168.0.0/16"),
+                    ));
+                }
+            }
+            config.allow_subnets = ranges.clone();
+        }
+
+        if let Some(node_name) = &self.node_name {
+            if node_name.trim().is_empty() {
+                errors.push(SetupError::new(
+                    "node_name",
+                    "required",
+                    "The node name can't be blank",
+                ));
+            }
+            config.node_name = node_name.clone();
+        }
+
+        let admin = match &self.admin {
+            Some(admin) => match admin.credentials() {
+                Ok(credentials) => Some(credentials),
+                Err(e) => {
+                    errors.push(e);
+                    None
+                }
+            },
+            None => None,
+        };
+
+        if errors.is_empty() {
+            Ok(Plan { config, admin })
+        } else {
+            Err(errors)
+        }
+    }
+}
+
+impl Admin {
+    fn credentials(&self) -> Result<(String, String), SetupError> {
+        if self.username.trim().is_empty() {
+            return Err(SetupError::new(
+                "admin.username",
+                "required",
+                "A username is required",
+            ));
+        }
+        let password = match (&self.password, &self.password_file) {
+            (Some(password), None) => password.clone(),
+            (None, Some(path)) => std::fs::read_to_string(path)
+                .map(|password| password.trim_end_matches(['\r', '\n']).to_string())
+                .map_err(|e| {
+                    SetupError::new(
+                        "admin.password_file",
+                        "unreadable",
+                        format!("Unable to read {path}: {e}"),
+                    )
+                })?,
+            _ => {
+                return Err(SetupError::new(
+                    "admin.password",
+                    "required",
+                    "Give exactly one of password or password_file",
+                ));
+            }
+        };
+        if password.is_empty() {
+            return Err(SetupError::new(
+                "admin.password",
+                "required",
+                "The password can't be blank",
+            ));
+        }
+        Ok((self.username.clone(), password))
+    }
+}
+
+/// Runs headless setup, prints the JSON report and returns the process exit code.
+pub fn run(path: &Path, dry_run: bool) -> i32 {
+    let mut report = Report {
+        dry_run,
+        ..Default::default()
+    };
+    let code = match setup(path, dry_run, &mut report) {
+        Ok(()) => {
+            report.success = true;
+            0
+        }
+        Err((code, errors)) => {
+            report.errors = errors;
+            code
+        }
+    };
+    println!(
+        "{}",
+        serde_json::to_string_pretty(&report).unwrap_or_default()
+    );
+    code
+}
+
+fn setup(path: &Path, dry_run: bool, report: &mut Report) -> Result<(), (i32, Vec<SetupError>)> {
+    let answers = AnswerFile::load(path).map_err(|e| (EXIT_INVALID, vec![e]))?;
+    let compatible = preflight::compatible_interfaces().map_err(|e| {
+        (
+            EXIT_INVALID,
+            vec![SetupError::new("", "preflight", e.to_string())],
+        )
+    })?;
+    let plan = answers
+        .validate(ConfigBuilder::new(), &compatible)
+        .map_err(|errors| (EXIT_INVALID, errors))?;
+
+    if dry_run {
+        report
+            .actions
+            .push("Answers are valid; nothing written".to_string());
+        return Ok(());
+    }
+
+    let write_failed = |e: String| {
+        (
+            EXIT_WRITE_FAILED,
+            vec![SetupError::new("", "write_failed", e)],
+        )
+    };
+    let actions = &mut report.actions;
+    let mut config = crate::load_or_backup_config(actions);
+    plan.config.apply_to(&mut config);
+    lqos_config::update_config(&config)
+        .map_err(|e| write_failed(format!("Unable to write configuration: {e:?}")))?;
+    actions.push("Configuration updated".to_string());
+
+    crate::write_default_files(&config, actions)
+        .map_err(|e| write_failed(format!("Unable to write default files: {e}")))?;
+
+    if let Some((username, password)) = plan.admin {
+        let mut users = WebUsers::load_or_create()
+            .map_err(|e| write_failed(format!("Failed to load web users: {e}")))?;
+        users
+            .add_or_update_user(&username, &password, UserRole::Admin)
+            .map_err(|e| write_failed(format!("Failed to add user: {e}")))?;
+        actions.push(format!("Admin user {username} saved"));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn base() -> ConfigBuilder {
+        ConfigBuilder {
+            bridge_mode: BridgeMode::Linux,
+            to_internet: String::new(),
+            to_network: String::new(),
+            internet_vlan: 0,
+            network_vlan: 0,
+            mbps_to_internet: 1_000,
+            mbps_to_network: 1_000,
+            allow_subnets: vec!["10.0.0.0/8".to_string()],
+            node_name: "LibreQoS".to_string(),
+        }
+    }
+
+    fn compatible() -> Vec<String> {
+        vec!["eth0".to_string(), "eth1".to_string()]
+    }
+
+    #[test]
+    fn toml_and_yaml_answers_agree() {
+        let toml = r#"
+mode = "xdp_bridge"
+node_name = "shaper-1"
+ip_ranges = ["100.64.0.0/10"]
+
+[interfaces]
+to_internet = "eth0"
+to_network = "eth1"
+
+[bandwidth]
+to_internet_mbps = 2000
+to_network_mbps = 1000
+
+[admin]
+username = "admin"
+password = "hunter2"
+"#;
+        let yaml = r#"
+mode: xdp_bridge
+node_name: shaper-1
+ip_ranges: ["100.64.0.0/10"]
+interfaces:
+  to_internet: eth0
+  to_network: eth1
+bandwidth:
+  to_internet_mbps: 2000
+  to_network_mbps: 1000
+admin:
+  username: admin
+  password: hunter2
+"#;
+        for (text, is_yaml) in [(toml, false), (yaml, true)] {
+            let answers = AnswerFile::parse(text, is_yaml).unwrap();
+            let plan = answers.validate(base(), &compatible()).unwrap();
+            assert_eq!(plan.config.bridge_mode, BridgeMode::XDP);
+            assert_eq!(plan.config.to_network, "eth1");
+            assert_eq!(plan.config.mbps_to_internet, 2000);
+            assert_eq!(plan.config.allow_subnets, vec!["100.64.0.0/10"]);
+            assert_eq!(plan.config.node_name, "shaper-1");
+            assert_eq!(
+                plan.admin,
+                Some(("admin".to_string(), "hunter2".to_string()))
+            );
+        }
+    }
+
+    #[test]
+    fn every_problem_is_reported() {
+        let toml = r#"
+mode = "linux_bridge"
+ip_ranges = ["100.64.0.0/10", "not-a-range"]
+
+[interfaces]
+to_internet = "eth9"
+to_network = "eth9"
+network_vlan = 20
+
+[bandwidth]
+to_internet_mbps = 0
+to_network_mbps = 1000
+
+[admin]
+username = "admin"
+"#;
+        let answers = AnswerFile::parse(toml, false).unwrap();
+        let errors = answers.validate(base(), &compatible()).err().unwrap();
+        let found: Vec<(&str, &str)> = errors.iter().map(|e| (e.field.as_str(), e.code)).collect();
+        assert_eq!(
+            found,
+            vec![
+                ("interfaces.to_internet", "interface_unsupported"),
+                ("interfaces.to_network", "same_interface"),
+                ("interfaces.network_vlan", "not_applicable"),
+                ("bandwidth.to_internet_mbps", "out_of_range"),
+                ("ip_ranges[1]", "invalid_ip_range"),
+                ("admin.password", "required"),
+            ]
+        );
+    }
+
+    #[test]
+    fn unknown_keys_are_rejected() {
+        let toml = r#"
+mode = "single_interface"
+[interfaces]
+to_internet = "eth0"
+to_netwrok = "eth1"
+[bandwidth]
+to_internet_mbps = 100
+to_network_mbps = 100
+"#;
+        let error = AnswerFile::parse(toml, false).unwrap_err();
+        assert_eq!(error.code, "parse");
+        assert!(error.message.contains("to_netwrok"));
+    }
+}
@@ -82,4 +82,40 @@ impl ConfigBuilder {
             }
         }
     }
+
+    /// Copies the setup choices into a configuration, leaving everything else alone.
+    pub fn apply_to(&self, config: &mut lqos_config::Config) {
+        config.node_name = self.node_name.clone();
+        config.queues.downlink_bandwidth_mbps = self.mbps_to_internet;
+        config.queues.uplink_bandwidth_mbps = self.mbps_to_network;
+        config.queues.generated_pn_download_mbps = self.mbps_to_internet;
+        config.queues.generated_pn_upload_mbps = self.mbps_to_network;
+        match self.bridge_mode {
+            BridgeMode::Linux => {
+                config.single_interface = None;
+                config.bridge = Some(lqos_config::BridgeConfig {
+                    use_xdp_bridge: false,
+                    to_internet: self.to_internet.clone(),
+                    to_network: self.to_network.clone(),
+                });
+            }
+            BridgeMode::XDP => {
+                config.single_interface = None;
+                config.bridge = Some(lqos_config::BridgeConfig {
+                    use_xdp_bridge: true,
+                    to_internet: self.to_internet.clone(),
+                    to_network: self.to_network.clone(),
+                });
+            }
+            BridgeMode::Single => {
+                config.single_interface = Some(lqos_config::SingleInterfaceConfig {
+                    interface: self.to_internet.clone(),
+                    internet_vlan: self.internet_vlan,
+                    network_vlan: self.network_vlan,
+                });
+                config.bridge = None;
+            }
+        }
+        config.ip_ranges.allow_subnets = self.allow_subnets.clone();
+    }
 }
@@ -1,3 +1,4 @@
+mod answer_file;
 mod bandwidth;
 mod bridge_mode;
 mod config_builder;
@@ -6,9 +7,10 @@ mod ip_range;
 mod preflight;
 mod webusers;
 
-use std::path::Path;
+use std::path::{Path, PathBuf};
 
 use bandwidth::bandwidth_view;
+use clap::Parser;
 use config_builder::CURRENT_CONFIG;
 use cursive::{
     Rect, Vec2, View,
@@ -41,7 +43,24 @@ fn shaped_devices_exists() -> bool {
     path.exists()
 }
 
+#[derive(Parser)]
+#[command(about = "LibreQoS Setup", long_about = None)]
+struct Cli {
+    /// Configure without the console UI, from a TOML or YAML answer file. Prints a JSON
+    /// report; exits 1 if the answers are invalid (nothing written) or 2 if writing failed.
+    #[arg(long)]
+    answers: Option<PathBuf>,
+    /// Check the answer file against this machine without writing anything
+    #[arg(long, requires = "answers")]
+    dry_run: bool,
+}
+
 fn main() {
+    let cli = Cli::parse();
+    if let Some(answers) = cli.answers {
+        std::process::exit(answer_file::run(&answers, cli.dry_run));
+    }
+
     preflight::preflight();
     let mut ui = cursive::default();
     ui.add_global_callback('q', |s| s.quit());
@@ -193,85 +212,43 @@ or Cancel to exit and investigate.",
     continue_finalize(ui);
 }
 
-fn continue_finalize(ui: &mut cursive::Cursive) {
-    let mut event_log = Vec::new();
-
-    // Update/Create the config file.
-    let mut config = if let Ok(config) = lqos_config::load_config() {
+/// Loads the existing configuration, or starts from defaults. An existing file that can't be
+/// loaded is backed up first.
+fn load_or_backup_config(event_log: &mut Vec<String>) -> lqos_config::Config {
+    if let Ok(config) = lqos_config::load_config() {
         event_log.push("Loaded existing configuration".to_string());
-        (*config).clone()
-    } else {
-        // If the file exists but couldn't be read, ensure we also log that a
-        // backup was attempted (final safeguard; may already have been done
-        // before showing the warning dialog).
-        if Path::new("/etc/lqos.conf").exists() {
-            let backup_path = "/etc/lqos.conf.setupbackup";
-            match std::fs::copy("/etc/lqos.conf", backup_path) {
-                Ok(_) => event_log.push(format!(
-                    "Existing /etc/lqos.conf could not be loaded. Backup saved to {}.",
-                    backup_path
-                )),
-                Err(e) => event_log.push(format!(
-                    "Existing /etc/lqos.conf could not be loaded. Backup attempt failed: {:?}.",
-                    e
-                )),
-            }
-        }
-        event_log.push("Creating new configuration".to_string());
-        lqos_config::Config::default()
-    };
+        return (*config).clone();
+    }
 
-    let new_config = CURRENT_CONFIG.lock();
-    config.node_name = new_config.node_name.clone();
-    config.queues.downlink_bandwidth_mbps = new_config.mbps_to_internet;
-    config.queues.uplink_bandwidth_mbps = new_config.mbps_to_network;
-    config.queues.generated_pn_download_mbps = new_config.mbps_to_internet;
-    config.queues.generated_pn_upload_mbps = new_config.mbps_to_network;
-    match new_config.bridge_mode {
-        config_builder::BridgeMode::Linux => {
-            config.single_interface = None;
-            config.bridge = Some(lqos_config::BridgeConfig {
-                use_xdp_bridge: false,
-                to_internet: new_config.to_internet.clone(),
-                to_network: new_config.to_network.clone(),
-            });
-        }
-        config_builder::BridgeMode::XDP => {
-            config.single_interface = None;
-            config.bridge = Some(lqos_config::BridgeConfig {
-                use_xdp_bridge: true,
-                to_internet: new_config.to_internet.clone(),
-                to_network: new_config.to_network.clone(),
-            });
-        }
-        config_builder::BridgeMode::Single => {
-            config.single_interface = Some(lqos_config::SingleInterfaceConfig {
-                interface: new_config.to_internet.clone(),
-                internet_vlan: new_config.internet_vlan,
-                network_vlan: new_config.network_vlan,
-            });
-            config.bridge = None;
+    // If the file exists but couldn't be read, ensure we also log that a
+    // backup was attempted (final safeguard; may already have been done
+    // before showing the warning dialog).
+    if Path::new("/etc/lqos.conf").exists() {
+        let backup_path = "/etc/lqos.conf.setupbackup";
+        match std::fs::copy("/etc/lqos.conf", backup_path) {
+            Ok(_) => event_log.push(format!(
+                "Existing /etc/lqos.conf could not be loaded. Backup saved to {}.",
+                backup_path
+            )),
+            Err(e) => event_log.push(format!(
+                "Existing /etc/lqos.conf could not be loaded. Backup attempt failed: {:?}.",
+                e
+            )),
         }
     }
-    config.ip_ranges.allow_subnets = new_config.allow_subnets.clone();
-    if let Err(e) = lqos_config::update_config(&config) {
-        event_log.push(format!("ERROR: Unable to write configuration: {e:?}"));
-        let msg = format!("ERROR: Unable to write configuration: {e:?}");
-        ui.add_layer(
-            Dialog::around(TextView::new(msg))
-                .title("Error")
-                .button("OK", |s| {
-                    s.pop_layer();
-                }),
-        );
-        return;
-    }
-    event_log.push("Configuration updated".to_string());
+    event_log.push("Creating new configuration".to_string());
+    lqos_config::Config::default()
+}
 
+/// Writes example network.json and ShapedDevices.csv files, unless they already exist.
+fn write_default_files(
+    config: &lqos_config::Config,
+    event_log: &mut Vec<String>,
+) -> std::io::Result<()> {
     // Does network.json exist?
     if !network_json_exists() {
         let path = Path::new(&config.lqos_directory).join("network.json");
-        std::fs::write(path, DEFAULT_NETWORK_JSON).expect("Unable to write file");
+        std::fs::write(path, DEFAULT_NETWORK_JSON)?;
         event_log.push("Network.json created.".to_string());
     } else {
         event_log.push("Network.json already exists - not updated.".to_string());
@@ -280,11 +257,35 @@ fn continue_finalize(ui: &mut cursive::Cursive) {
     // Does ShapedDevices.csv exist?
     if !shaped_devices_exists() {
         let path = Path::new(&config.lqos_directory).join("ShapedDevices.csv");
-        std::fs::write(path, DEFAULT_SHAPED_DEVICES).expect("Unable to write file");
+        std::fs::write(path, DEFAULT_SHAPED_DEVICES)?;
         event_log.push("ShapedDevices.csv created.".to_string());
     } else {
         event_log.push("ShapedDevices.csv already exists - not updated.".to_string());
     }
+    Ok(())
+}
+
+fn continue_finalize(ui: &mut cursive::Cursive) {
+    let mut event_log = Vec::new();
+
+    // Update/Create the config file.
+    let mut config = load_or_backup_config(&mut event_log);
+    CURRENT_CONFIG.lock().apply_to(&mut config);
+    if let Err(e) = lqos_config::update_config(&config) {
+        event_log.push(format!("ERROR: Unable to write configuration: {e:?}"));
+        let msg = format!("ERROR: Unable to write configuration: {e:?}");
+        ui.add_layer(
+            Dialog::around(TextView::new(msg))
+                .title("Error")
+                .button("OK", |s| {
+                    s.pop_layer();
+                }),
+        );
+        return;
+    }
+    event_log.push("Configuration updated".to_string());
+
+    write_default_files(&config, &mut event_log).expect("Unable to write file");
 
     // Display final report
     use cursive::views::{Dialog, LinearLayout, TextView};
@@ -2,9 +2,22 @@ use cursive::views::{Dialog, LinearLayout, TextView};
 
 use crate::interfaces;
 
-pub fn preflight() {
-    let interfaces = interfaces::get_interfaces().unwrap();
+/// The interfaces LibreQoS can shape on. Fails if there are none.
+pub fn compatible_interfaces() -> anyhow::Result<Vec<String>> {
+    let mut interfaces = interfaces::get_interfaces()?;
+    // getifaddrs lists an interface once per address
+    interfaces.sort();
+    interfaces.dedup();
     if interfaces.is_empty() {
+        anyhow::bail!(
+            "No compatible network interfaces found. LQOS requires at least one network interface that supports XDP and multiple queues."
+        );
+    }
+    Ok(interfaces)
+}
+
+pub fn preflight() {
+    if compatible_interfaces().is_err() {
         let mut ui = cursive::default();
         ui.add_layer(
             Dialog::new()
@@ -1,7 +1,9 @@
 use anyhow::{Result, anyhow};
 use lqos_bakery::BakeryCommands;
 use lqos_config::{ConfigShapedDevices, ShapedDevice};
-use lqos_overrides::{OverrideFile, OverrideLayer, OverrideStore};
+use lqos_overrides::{
+    ChangeProposal, OverrideFile, OverrideLayer, OverrideStore, ProposedChange, SubmitOutcome,
+};
 use lqos_queue_tracker::QUEUE_STRUCTURE;
 use lqos_utils::hash_to_i64;
 use std::collections::{BTreeMap, HashMap, HashSet};
@@ -9,6 +11,7 @@ use std::collections::{BTreeMap, HashMap, HashSet};
 pub enum CircuitFallbackOutcome {
     Applied { persisted: bool },
     Cleared { persisted: bool },
+    HeldForApproval { id: u64 },
     DryRun { action: String },
     Skipped { reason: String },
 }
@@ -17,6 +20,8 @@ pub struct SiteOverrideUpdate {
     pub site_name: String,
     pub download_bandwidth_mbps: Option<f32>,
     pub upload_bandwidth_mbps: Option<f32>,
+    /// Largest deviation from the site's configured rate, in percent.
+    pub change_pct: f32,
 }
 
 #[derive(Clone)]
@@ -25,43 +30,50 @@ pub struct PersistedCircuitFallback {
     pub devices: Vec<ShapedDevice>,
 }
 
-pub fn apply_site_override_updates(updates: &[SiteOverrideUpdate]) -> Result<bool> {
+/// Persists StormGuard site rate overrides through the change-control queue.
+///
+/// Returns the names of sites whose updates are held for approval. Those rates must not be
+/// applied live until the change is approved.
+pub fn apply_site_override_updates(updates: &[SiteOverrideUpdate]) -> Result<HashSet<String>> {
     if updates.is_empty() {
-        return Ok(false);
+        return Ok(HashSet::new());
     }
 
-    let mut overrides = OverrideStore::load_layer(OverrideLayer::Stormguard)?;
-    let mut changed = false;
-
-    for update in updates {
-        let desired = (update.download_bandwidth_mbps, update.upload_bandwidth_mbps);
-        if desired == (None, None) {
-            let removed = overrides.remove_site_bandwidth_override_count(None, &update.site_name);
-            if removed > 0 {
-                changed = true;
-            }
-            continue;
-        }
-
-        if current_site_override(&overrides, &update.site_name) == Some(desired) {
-            continue;
-        }
-
-        overrides.set_site_bandwidth_override(
-            None,
-            update.site_name.clone(),
-            update.download_bandwidth_mbps,
-            update.upload_bandwidth_mbps,
-        );
-        changed = true;
-    }
+    let proposals: Vec<ChangeProposal> = updates
+        .iter()
+        .map(|update| ChangeProposal {
+            layer: OverrideLayer::Stormguard,
+            site: Some(update.site_name.clone()),
+            summary: site_update_summary(update),
+            magnitude_pct: Some(update.change_pct),
+            change: ProposedChange::SetSiteBandwidth {
+                site_name: update.site_name.clone(),
+                download_bandwidth_mbps: update.download_bandwidth_mbps,
+                upload_bandwidth_mbps: update.upload_bandwidth_mbps,
+            },
+        })
+        .collect();
 
-    if !changed {
-        return Ok(false);
-    }
+    let outcomes = OverrideStore::submit_changes(&proposals)?;
+    Ok(updates
+        .iter()
+        .zip(outcomes)
+        .filter(|(_, outcome)| matches!(outcome, SubmitOutcome::Queued { .. }))
+        .map(|(update, _)| update.site_name.clone())
+        .collect())
+}
 
-    OverrideStore::save_layer(OverrideLayer::Stormguard, &overrides)?;
-    Ok(true)
+fn site_update_summary(update: &SiteOverrideUpdate) -> String {
+    let rate = |mbps: Option<f32>| match mbps {
+        Some(mbps) => format!("{mbps:.1} Mbps"),
+        None => "configured".to_string(),
+    };
+    format!(
+        "StormGuard: set site '{}' to {} down / {} up",
+        update.site_name,
+        rate(update.download_bandwidth_mbps),
+        rate(update.upload_bandwidth_mbps)
+    )
 }
 
 pub fn apply_circuit_fallback(
@@ -89,7 +101,12 @@ pub fn apply_circuit_fallback(
     }
 
     let persisted = if persist {
-        set_devices_sqm_override(&devices, sqm_override)?
+        match set_devices_sqm_override(circuit_id, &devices, sqm_override)? {
+            SubmitOutcome::Queued { id } => {
+                return Ok(CircuitFallbackOutcome::HeldForApproval { id });
+            }
+            SubmitOutcome::Applied { changed } => changed,
+        }
     } else {
         false
     };
@@ -124,12 +141,10 @@ pub fn clear_circuit_fallback(
         });
     }
 
-    let device_ids: Vec<String> = fallback
-        .devices
-        .iter()
-        .map(|d| d.device_id.clone())
-        .collect();
-    let persisted = clear_device_overrides(&device_ids)?;
+    let persisted = match clear_device_overrides(circuit_id, &fallback.devices)? {
+        SubmitOutcome::Queued { id } => return Ok(CircuitFallbackOutcome::HeldForApproval { id }),
+        SubmitOutcome::Applied { changed } => changed,
+    };
     apply_circuit_sqm_override_live(circuit_id, &fallback.devices, None, bakery_sender)?;
     Ok(CircuitFallbackOutcome::Cleared { persisted })
 }
@@ -140,24 +155,6 @@ pub fn load_persisted_circuit_fallbacks() -> Result<HashMap<String, PersistedCir
     Ok(group_circuit_fallbacks(&overrides, &current_devices))
 }
 
-fn current_site_override(
-    overrides: &OverrideFile,
-    site_name: &str,
-) -> Option<(Option<f32>, Option<f32>)> {
-    overrides
-        .network_adjustments()
-        .iter()
-        .find_map(|adj| match adj {
-            lqos_overrides::NetworkAdjustment::AdjustSiteSpeed {
-                node_id: _,
-                site_name: current,
-                download_bandwidth_mbps,
-                upload_bandwidth_mbps,
-            } if current == site_name => Some((*download_bandwidth_mbps, *upload_bandwidth_mbps)),
-            _ => None,
-        })
-}
-
 fn load_devices_for_circuit(circuit_id: &str) -> Result<Vec<ShapedDevice>> {
     let shaped_devices = ConfigShapedDevices::load()?;
     Ok(shaped_devices
@@ -209,46 +206,33 @@ fn has_sqm_override_for_device_ids(overrides: &OverrideFile, device_ids: &HashSe
     })
 }
 
-fn set_devices_sqm_override(base_devices: &[ShapedDevice], sqm_override: &str) -> Result<bool> {
-    let mut overrides = OverrideStore::load_layer(OverrideLayer::Stormguard)?;
-    let mut changed = false;
-    for base_device in base_devices {
-        if overrides.set_device_sqm_override_return_changed(
-            base_device.device_id.clone(),
-            Some(sqm_override.to_string()),
-        ) {
-            changed = true;
-        }
-        if overrides.remove_persistent_shaped_device_by_device_count(&base_device.device_id) > 0 {
-            changed = true;
-        }
-    }
-
-    if !changed {
-        return Ok(false);
-    }
-
-    OverrideStore::save_layer(OverrideLayer::Stormguard, &overrides)?;
-    Ok(true)
+fn set_devices_sqm_override(
+    circuit_id: &str,
+    base_devices: &[ShapedDevice],
+    sqm_override: &str,
+) -> Result<SubmitOutcome> {
+    OverrideStore::submit_change(ChangeProposal {
+        layer: OverrideLayer::Stormguard,
+        site: base_devices.first().map(|d| d.parent_node.clone()),
+        summary: format!("StormGuard: set SQM '{sqm_override}' on circuit '{circuit_id}'"),
+        magnitude_pct: None,
+        change: ProposedChange::SetDeviceSqm {
+            device_ids: base_devices.iter().map(|d| d.device_id.clone()).collect(),
+            sqm_override: sqm_override.to_string(),
+        },
+    })
 }
 
-fn clear_device_overrides(device_ids: &[String]) -> Result<bool> {
-    let mut overrides = OverrideStore::load_layer(OverrideLayer::Stormguard)?;
-    let mut removed_any = false;
-    for device_id in device_ids {
-        let removed_adjustments = overrides.remove_device_sqm_override_by_device_count(device_id);
-        let removed_devices = overrides.remove_persistent_shaped_device_by_device_count(device_id);
-        if removed_adjustments > 0 || removed_devices > 0 {
-            removed_any = true;
-        }
-    }
-
-    if !removed_any {
-        return Ok(false);
-    }
-
-    OverrideStore::save_layer(OverrideLayer::Stormguard, &overrides)?;
-    Ok(true)
+fn clear_device_overrides(circuit_id: &str, devices: &[ShapedDevice]) -> Result<SubmitOutcome> {
+    OverrideStore::submit_change(ChangeProposal {
+        layer: OverrideLayer::Stormguard,
+        site: devices.first().map(|d| d.parent_node.clone()),
+        summary: format!("StormGuard: clear SQM fallback on circuit '{circuit_id}'"),
+        magnitude_pct: None,
+        change: ProposedChange::ClearDeviceSqm {
+            device_ids: devices.iter().map(|d| d.device_id.clone()).collect(),
+        },
+    })
 }
 
 fn apply_circuit_sqm_override_live(
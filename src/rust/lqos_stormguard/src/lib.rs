@@ -14,6 +14,7 @@ use lqos_bus::StormguardDebugEntry;
 use lqos_config::NetworkJsonTransport;
 use lqos_queue_tracker::QUEUE_STRUCTURE_CHANGED_STORMGUARD;
 use parking_lot::Mutex;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::time::Duration;
 use tracing::{debug, info};
 
@@ -33,6 +34,24 @@ pub static STORMGUARD_STATS: Mutex<Vec<(String, u64, u64)>> = Mutex::new(Vec::ne
 /// Debug snapshots of StormGuard evaluation state
 pub static STORMGUARD_DEBUG: Mutex<Vec<StormguardDebugEntry>> = Mutex::new(Vec::new());
 
+/// While set, StormGuard makes no adjustments (for example on an HA standby). Clearing it
+/// reloads the configuration and replays persisted adjustments.
+pub static STORMGUARD_PAUSED: AtomicBool = AtomicBool::new(false);
+
+/// Set after an approved change lands in the StormGuard override layer. StormGuard reloads
+/// its configuration and replays persisted adjustments, which applies the change live.
+pub static STORMGUARD_RELOAD_REQUESTED: AtomicBool = AtomicBool::new(false);
+
+/// Site rates `(site, download Mbps, upload Mbps)` to start from on the next configuration load,
+/// instead of the persisted overrides. Consumed by that load.
+static SEED_SITE_RATES: Mutex<Vec<(String, u64, u64)>> = Mutex::new(Vec::new());
+
+/// Makes StormGuard resume from these site rates (for example, those synchronized from an HA
+/// peer) the next time it loads its configuration. Rates are clamped to each site's range.
+pub fn seed_site_rates(rates: Vec<(String, u64, u64)>) {
+    *SEED_SITE_RATES.lock() = rates;
+}
+
 /// Launches the StormGuard component. Will exit if there's
 /// nothing to do.
 pub async fn start_stormguard(
@@ -56,11 +75,21 @@ pub async fn start_stormguard(
     loop {
         interval.tick().await;
 
+        if STORMGUARD_PAUSED.load(Ordering::Relaxed) {
+            if site_state_tracker.take().is_some() {
+                info!("StormGuard paused");
+                config = None;
+                STORMGUARD_DEBUG.lock().clear();
+            }
+            continue;
+        }
+
         // Check if queue structure has changed or if we need initial configuration
         let queue_structure_changed =
-            QUEUE_STRUCTURE_CHANGED_STORMGUARD.swap(false, std::sync::atomic::Ordering::Relaxed);
+            QUEUE_STRUCTURE_CHANGED_STORMGUARD.swap(false, Ordering::Relaxed);
+        let reload_requested = STORMGUARD_RELOAD_REQUESTED.swap(false, Ordering::Relaxed);
 
-        if config.is_none() || queue_structure_changed {
+        if config.is_none() || queue_structure_changed || reload_requested {
             // Try to (re)configure StormGuard
             match config::configure() {
                 Ok(new_config) => {
@@ -74,6 +103,7 @@ pub async fn start_stormguard(
                             log_sender = datalog::start_datalog(&new_config).ok();
                         }
                         let mut tracker = site_state::SiteStateTracker::from_config(&new_config);
+                        tracker.seed_site_rates(&std::mem::take(&mut *SEED_SITE_RATES.lock()));
                         tracker.replay_persisted_adjustments(&new_config, bakery.clone());
                         site_state_tracker = Some(tracker);
                         config = Some(new_config);
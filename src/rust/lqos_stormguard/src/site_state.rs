@@ -33,6 +33,17 @@ pub struct SiteStateTracker {
     active_circuit_fallbacks: HashSet<String>,
 }
 
+/// A site rate change waiting for its change-control outcome before it reaches the queue.
+struct SiteRateChange {
+    site: String,
+    direction: RecommendationDirection,
+    interface_name: String,
+    class_handle: TcHandle,
+    previous_rate: u64,
+    new_rate: u64,
+    summary: String,
+}
+
 struct CircuitQueueRecommendationContext<'a> {
     active_circuit_fallbacks: &'a mut HashSet<String>,
     site: &'a mut SiteState,
@@ -102,6 +113,25 @@ impl SiteStateTracker {
         }
     }
 
+    /// Starts sites from previously observed rates, clamped to each site's configured range.
+    /// [`Self::replay_persisted_adjustments`] then applies them to the queues.
+    pub fn seed_site_rates(&mut self, rates: &[(String, u64, u64)]) {
+        for (name, download, upload) in rates {
+            let Some(site) = self.sites.get_mut(name) else {
+                continue;
+            };
+            for (direction, rate) in [
+                (RecommendationDirection::Download, *download),
+                (RecommendationDirection::Upload, *upload),
+            ] {
+                let rate = rate
+                    .max(Self::minimum_rate(&site.config, direction))
+                    .min(Self::planned_rate(&site.config, direction));
+                Self::set_site_rate(site, direction, rate);
+            }
+        }
+    }
+
     pub fn replay_persisted_adjustments(
         &mut self,
         config: &StormguardConfig,
@@ -456,6 +486,7 @@ impl SiteStateTracker {
             return;
         };
         let mut pending_site_updates: HashSet<String> = HashSet::new();
+        let mut rate_changes: Vec<SiteRateChange> = Vec::new();
 
         for (recommendation, summary) in recommendations {
             // Find the Site Object
@@ -560,26 +591,19 @@ impl SiteStateTracker {
                 continue;
             }
 
-            // Apply to the site
+            // Apply to the site. The queue itself only changes once change control accepts
+            // the new rate, below.
             Self::set_site_rate(site, recommendation.direction, new_rate);
-
-            // Actually make the change
-            Self::apply_dependents(
-                &site.config,
-                recommendation.direction,
-                new_rate,
-                config,
-                &interface_name,
-                bakery_sender.clone(),
-            );
-            Self::apply_htb_change(
-                config,
-                &interface_name,
+            pending_site_updates.insert(site.config.name.clone());
+            rate_changes.push(SiteRateChange {
+                site: recommendation.site.clone(),
+                direction: recommendation.direction,
+                interface_name,
                 class_handle,
+                previous_rate: current_rate as u64,
                 new_rate,
-                bakery_sender.clone(),
-            );
-            pending_site_updates.insert(site.config.name.clone());
+                summary,
+            });
 
             // Finish Up by entering cooldown
             debug!("Recommendation applied: entering cooldown");
@@ -589,17 +613,11 @@ impl SiteStateTracker {
                 cooldown_secs,
                 recommendation.action,
             );
-
-            // Report
-            let _ = log_sender.send(LogCommand::SpeedChange {
-                site: recommendation.site.clone(),
-                download: site.queue_download_mbps,
-                upload: site.queue_upload_mbps,
-                state: summary,
-            });
         }
 
-        if !pending_site_updates.is_empty() {
+        let held_sites = if pending_site_updates.is_empty() {
+            HashSet::new()
+        } else {
             let updates: Vec<SiteOverrideUpdate> = pending_site_updates
                 .into_iter()
                 .filter_map(|site_name| {
@@ -608,9 +626,51 @@ impl SiteStateTracker {
                         .map(Self::site_override_update_from_state)
                 })
                 .collect();
-            if let Err(e) = apply_site_override_updates(&updates) {
+            apply_site_override_updates(&updates).unwrap_or_else(|e| {
                 warn!("Failed to batch StormGuard site override updates: {}", e);
-            }
+                HashSet::new()
+            })
+        };
+
+        for change in rate_changes {
+            let Some(site) = self.sites.get_mut(&change.site) else {
+                continue;
+            };
+            let state = if held_sites.contains(&change.site) {
+                // Approval will write the override layer, and the reload that follows
+                // applies it to the queue.
+                Self::set_site_rate(site, change.direction, change.previous_rate);
+                info!(
+                    "StormGuard {} change for {} is held for approval",
+                    change.direction, change.site
+                );
+                format!("{}; held_for_approval", change.summary)
+            } else {
+                Self::apply_dependents(
+                    &site.config,
+                    change.direction,
+                    change.new_rate,
+                    config,
+                    &change.interface_name,
+                    bakery_sender.clone(),
+                );
+                Self::apply_htb_change(
+                    config,
+                    &change.interface_name,
+                    change.class_handle,
+                    change.new_rate,
+                    bakery_sender.clone(),
+                );
+                change.summary
+            };
+
+            // Report
+            let _ = log_sender.send(LogCommand::SpeedChange {
+                site: change.site,
+                download: site.queue_download_mbps,
+                upload: site.queue_upload_mbps,
+                state,
+            });
         }
     }
 
@@ -703,6 +763,13 @@ impl SiteStateTracker {
                     true,
                 )
             }
+            CircuitFallbackOutcome::HeldForApproval { id } => {
+                info!(
+                    "StormGuard circuit fallback change for {} ({}) is held for approval as change {id}",
+                    recommendation.site, circuit_id
+                );
+                (format!("circuit_fallback=held_for_approval id={id}"), true)
+            }
             CircuitFallbackOutcome::DryRun { action } => {
                 info!(
                     "StormGuard dry-run circuit fallback for {} ({}): {}",
@@ -833,12 +900,22 @@ impl SiteStateTracker {
     }
 
     fn site_override_update_from_state(site: &SiteState) -> SiteOverrideUpdate {
+        let deviation_pct = |current: u64, configured: u64| {
+            if configured == 0 {
+                0.0
+            } else {
+                (current.abs_diff(configured) as f32 / configured as f32) * 100.0
+            }
+        };
         SiteOverrideUpdate {
             site_name: site.config.name.clone(),
             download_bandwidth_mbps: (site.queue_download_mbps != site.config.max_download_mbps)
                 .then_some(site.queue_download_mbps as f32),
             upload_bandwidth_mbps: (site.queue_upload_mbps != site.config.max_upload_mbps)
                 .then_some(site.queue_upload_mbps as f32),
+            change_pct: deviation_pct(site.queue_download_mbps, site.config.max_download_mbps).max(
+                deviation_pct(site.queue_upload_mbps, site.config.max_upload_mbps),
+            ),
         }
     }
 
@@ -1007,6 +1084,18 @@ mod tests {
         }
     }
 
+    #[test]
+    fn seeded_rates_are_clamped_to_the_site_range() {
+        let mut tracker = SiteStateTracker {
+            sites: HashMap::from([("Site A".to_string(), site_state(100, 50, 100, 50))]),
+            active_circuit_fallbacks: HashSet::new(),
+        };
+        tracker.seed_site_rates(&[("Site A".to_string(), 60, 5), ("Unknown".to_string(), 1, 1)]);
+        let site = &tracker.sites["Site A"];
+        assert_eq!(site.queue_download_mbps, 60);
+        assert_eq!(site.queue_upload_mbps, 10);
+    }
+
     #[test]
     fn site_override_update_omits_baseline_rates() {
         let site = site_state(100, 50, 100, 50);
@@ -1021,6 +1110,7 @@ mod tests {
         let update = SiteStateTracker::site_override_update_from_state(&site);
         assert_eq!(update.download_bandwidth_mbps, Some(75.0));
         assert_eq!(update.upload_bandwidth_mbps, None);
+        assert_eq!(update.change_pct, 25.0);
     }
 
     #[test]
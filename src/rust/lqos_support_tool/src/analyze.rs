@@ -0,0 +1,488 @@
+//! Offline analysis of a support dump: replays the sanity checks against the files inside
+//! the dump, scans the logs for known issues and ranks the results for triage.
+
+use crate::sanity_checks::run_checks;
+use crate::sanity_checks::source::DumpSource;
+use crate::support_info::{Section, SupportDump};
+use std::fmt::Write;
+
+/// How urgently a finding needs attention. Sorts most severe first.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Severity {
+    Critical,
+    Warning,
+    Info,
+}
+
+impl Severity {
+    pub fn label(self) -> &'static str {
+        match self {
+            Severity::Critical => "Critical",
+            Severity::Warning => "Warning",
+            Severity::Info => "Info",
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct Finding {
+    pub severity: Severity,
+    pub title: String,
+    pub detail: String,
+    /// Where the finding came from: a sanity check, or the dump entry a signature matched
+    pub source: String,
+    /// A line from the dump supporting the finding
+    pub evidence: Option<String>,
+}
+
+#[derive(Debug)]
+pub struct AnalysisReport {
+    pub sender: String,
+    pub comment: String,
+    pub checks_run: usize,
+    pub checks_passed: usize,
+    /// Ordered by severity, most severe first
+    pub findings: Vec<Finding>,
+}
+
+/// A log message that points at a known cause
+struct KnownIssue {
+    patterns: &'static [&'static str],
+    severity: Severity,
+    title: &'static str,
+    advice: &'static str,
+}
+
+const KNOWN_ISSUES: &[KnownIssue] = &[
+    KnownIssue {
+        patterns: &[
+            "Unable to load the XDP/TC kernel",
+            "Unable to open LibreQoS XDP/TC Kernel",
+            "Unable to attach to interface",
+            "Unable to attach TC",
+        ],
+        severity: Severity::Critical,
+        title: "XDP/TC programs failed to load or attach",
+        advice: "Check the NIC driver supports XDP, the kernel is new enough, and no other XDP program holds the interfaces.",
+    },
+    KnownIssue {
+        patterns: &["Unknown interface:"],
+        severity: Severity::Critical,
+        title: "A configured interface does not exist",
+        advice: "Interface names in /etc/lqos.conf must match `ip link`; they often change after a NIC or kernel upgrade.",
+    },
+    KnownIssue {
+        patterns: &["You need to be root"],
+        severity: Severity::Critical,
+        title: "lqosd was not started as root",
+        advice: "lqosd needs root to load eBPF programs; run it from the systemd unit.",
+    },
+    KnownIssue {
+        patterns: &["panicked at"],
+        severity: Severity::Critical,
+        title: "A LibreQoS service panicked",
+        advice: "The evidence line names the source location; include it when escalating.",
+    },
+    KnownIssue {
+        patterns: &["Out of memory", "oom-kill", "oom_reaper"],
+        severity: Severity::Critical,
+        title: "The kernel ran out of memory",
+        advice: "Check RAM against the subscriber count and look for a runaway process.",
+    },
+    KnownIssue {
+        patterns: &["No space left on device"],
+        severity: Severity::Critical,
+        title: "A disk is full",
+        advice: "Free space (journals and long-term stats are the usual culprits) and restart the services.",
+    },
+    KnownIssue {
+        patterns: &["Address already in use"],
+        severity: Severity::Warning,
+        title: "A service could not bind its port",
+        advice: "Another process, often a second copy of the service, is using the port.",
+    },
+    KnownIssue {
+        patterns: &[
+            "Error reading CSV record",
+            "CSV decode error",
+            "DEVICE DECODE",
+        ],
+        severity: Severity::Warning,
+        title: "ShapedDevices.csv was rejected",
+        advice: "A malformed row stops the whole file loading; the evidence line gives its position.",
+    },
+    KnownIssue {
+        patterns: &["RTNETLINK answers: File exists"],
+        severity: Severity::Warning,
+        title: "Queue setup collided with existing qdiscs or classes",
+        advice: "Usually two schedulers running at once, or stale queues; a full reload clears it.",
+    },
+    KnownIssue {
+        patterns: &["Failed with result"],
+        severity: Severity::Warning,
+        title: "A systemd service failed",
+        advice: "See the service's journal entry for the reason it exited.",
+    },
+    KnownIssue {
+        patterns: &["queueStructure.json does not exist yet"],
+        severity: Severity::Info,
+        title: "Queues have never been built",
+        advice: "The scheduler has not run LibreQoS.py yet; check lqos_scheduler is enabled.",
+    },
+];
+
+/// Only logs and service status are scanned for signatures; configuration files would
+/// match on subscriber names and comments.
+fn is_log_entry(name: &str) -> bool {
+    name.starts_with("Journal") || name.starts_with("SystemCtl")
+}
+
+/// Longest evidence line kept, in characters
+const MAX_EVIDENCE: usize = 300;
+
+/// Analyzes a support dump without touching the local system.
+pub fn analyze_dump(dump: &SupportDump) -> AnalysisReport {
+    let results = run_checks(&DumpSource::new(dump));
+    let mut findings = Vec::new();
+
+    // Failed checks, grouped by name so a thousand bad parents are one finding
+    let mut failed: Vec<(&str, Vec<&str>)> = Vec::new();
+    for check in results.iter().filter(|check| !check.success) {
+        match failed.iter_mut().find(|(name, _)| *name == check.name) {
+            Some((_, comments)) => comments.push(&check.comments),
+            None => failed.push((&check.name, vec![&check.comments])),
+        }
+    }
+    for (name, comments) in failed.iter() {
+        // A file the sender chose not to include isn't missing from their system
+        if let Some(section) = checked_section(name)
+            && dump.manifest.omitted_sections.contains(&section)
+        {
+            findings.push(Finding {
+                severity: Severity::Info,
+                title: name.to_string(),
+                detail: format!("Not checked: the {section:?} section was left out of the dump"),
+                source: "Sanity check (replayed from dump)".to_string(),
+                evidence: None,
+            });
+            continue;
+        }
+        let detail = if comments.len() == 1 {
+            comments[0].to_string()
+        } else {
+            format!("{} failures, e.g. {}", comments.len(), comments[0])
+        };
+        findings.push(Finding {
+            severity: check_severity(name),
+            title: name.to_string(),
+            detail,
+            source: "Sanity check (replayed from dump)".to_string(),
+            evidence: None,
+        });
+    }
+
+    // Failures seen on the customer's system that the dump can't reproduce, such as
+    // queue counts
+    for check in dump
+        .sanity_checks
+        .results
+        .iter()
+        .filter(|check| !check.success)
+    {
+        let replayed = failed.iter().any(|(name, _)| *name == check.name);
+        let duplicate = findings
+            .iter()
+            .any(|f| f.title == check.name && f.detail == check.comments);
+        if replayed || duplicate {
+            continue;
+        }
+        findings.push(Finding {
+            severity: check_severity(&check.name),
+            title: check.name.clone(),
+            detail: check.comments.clone(),
+            source: "Sanity check (recorded when gathered)".to_string(),
+            evidence: None,
+        });
+    }
+
+    for entry in dump
+        .entries
+        .iter()
+        .filter(|entry| is_log_entry(&entry.name))
+    {
+        for issue in KNOWN_ISSUES {
+            let mut matches = entry.contents.lines().filter(|line| {
+                let line = line.to_lowercase();
+                issue
+                    .patterns
+                    .iter()
+                    .any(|pattern| line.contains(&pattern.to_lowercase()))
+            });
+            let Some(first) = matches.next() else {
+                continue;
+            };
+            let count = 1 + matches.count();
+            findings.push(Finding {
+                severity: issue.severity,
+                title: issue.title.to_string(),
+                detail: format!("{} ({count} matching line(s))", issue.advice),
+                source: entry.name.clone(),
+                evidence: Some(first.trim().chars().take(MAX_EVIDENCE).collect()),
+            });
+        }
+    }
+
+    // Stable, so findings of equal severity keep check order then log order
+    findings.sort_by_key(|finding| finding.severity);
+
+    AnalysisReport {
+        sender: dump.sender.clone(),
+        comment: dump.comment.clone(),
+        checks_run: results.len(),
+        checks_passed: results.iter().filter(|check| check.success).count(),
+        findings,
+    }
+}
+
+/// The dump section a file check reads
+fn checked_section(name: &str) -> Option<Section> {
+    if name.starts_with("Config File") {
+        Some(Section::Config)
+    } else if name.starts_with("network.json") {
+        Some(Section::NetworkJson)
+    } else if name.starts_with("ShapedDevices.csv") {
+        Some(Section::ShapedDevices)
+    } else {
+        None
+    }
+}
+
+/// Problems that leave the shaper partly working are warnings; the rest stop it outright.
+fn check_severity(name: &str) -> Severity {
+    if name.starts_with("Queue Check") || name == "Shaped Device Invalid Parent" {
+        Severity::Warning
+    } else {
+        Severity::Critical
+    }
+}
+
+impl AnalysisReport {
+    fn count(&self, severity: Severity) -> usize {
+        self.findings
+            .iter()
+            .filter(|finding| finding.severity == severity)
+            .count()
+    }
+
+    fn summary(&self) -> String {
+        format!(
+            "{} critical, {} warning, {} info. {} of {} replayed sanity checks passed.",
+            self.count(Severity::Critical),
+            self.count(Severity::Warning),
+            self.count(Severity::Info),
+            self.checks_passed,
+            self.checks_run
+        )
+    }
+
+    pub fn to_markdown(&self) -> String {
+        let mut md = String::new();
+        let _ = writeln!(md, "# LibreQoS Support Dump Analysis\n");
+        let _ = writeln!(md, "- **Sent by:** {}", self.sender);
+        let _ = writeln!(md, "- **Comments:** {}", self.comment);
+        let _ = writeln!(md, "- **Summary:** {}\n", self.summary());
+        if self.findings.is_empty() {
+            let _ = writeln!(md, "No problems found.");
+        }
+        for (i, finding) in self.findings.iter().enumerate() {
+            let _ = writeln!(
+                md,
+                "## {}. [{}] {}\n",
+                i + 1,
+                finding.severity.label(),
+                finding.title
+            );
+            let _ = writeln!(md, "{}\n", finding.detail);
+            let _ = writeln!(md, "*Source:* {}\n", finding.source);
+            if let Some(evidence) = &finding.evidence {
+                let _ = writeln!(md, "```text\n{evidence}\n```\n");
+            }
+        }
+        md
+    }
+
+    pub fn to_html(&self) -> String {
+        let mut html = String::new();
+        html += "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n";
+        html += "<title>LibreQoS Support Dump Analysis</title>\n<style>\n";
+        html += "body { font-family: sans-serif; margin: 2em; }\n";
+        html += "table { border-collapse: collapse; width: 100%; }\n";
+        html += "td, th { border: 1px solid #ccc; padding: 0.4em; text-align: left; vertical-align: top; }\n";
+        html += ".Critical { background: #f8d7da; }\n.Warning { background: #fff3cd; }\n.Info { background: #d1ecf1; }\n";
+        html += "</style>\n</head>\n<body>\n<h1>LibreQoS Support Dump Analysis</h1>\n";
+        let _ = writeln!(
+            html,
+            "<p><b>Sent by:</b> {}<br><b>Comments:</b> {}<br><b>Summary:</b> {}</p>",
+            escape_html(&self.sender),
+            escape_html(&self.comment),
+            escape_html(&self.summary())
+        );
+        if self.findings.is_empty() {
+            html += "<p>No problems found.</p>\n";
+        } else {
+            html +=
+                "<table>\n<tr><th>#</th><th>Severity</th><th>Finding</th><th>Source</th></tr>\n";
+            for (i, finding) in self.findings.iter().enumerate() {
+                let evidence = finding
+                    .evidence
+                    .as_ref()
+                    .map(|evidence| format!("<pre>{}</pre>", escape_html(evidence)))
+                    .unwrap_or_default();
+                let _ = writeln!(
+                    html,
+                    "<tr class=\"{severity}\"><td>{}</td><td>{severity}</td><td><b>{}</b><br>{}{evidence}</td><td>{}</td></tr>",
+                    i + 1,
+                    escape_html(&finding.title),
+                    escape_html(&finding.detail),
+                    escape_html(&finding.source),
+                    severity = finding.severity.label(),
+                );
+            }
+            html += "</table>\n";
+        }
+        html += "</body>\n</html>\n";
+        html
+    }
+}
+
+fn escape_html(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sanity_checks::SanityCheck;
+    use crate::support_info::DumpEntry;
+
+    fn entry(name: &str, contents: &str) -> DumpEntry {
+        DumpEntry {
+            name: name.to_string(),
+            filename: None,
+            contents: contents.to_string(),
+        }
+    }
+
+    #[test]
+    fn missing_config_is_critical_and_ranked_first() {
+        let mut dump = SupportDump::default();
+        dump.entries.push(entry(
+            "Journal (lqos_scheduler)",
+            "Oct 01 RTNETLINK answers: File exists\nOct 01 RTNETLINK answers: File exists",
+        ));
+        let report = analyze_dump(&dump);
+
+        assert_eq!(report.findings[0].severity, Severity::Critical);
+        assert!(
+            report
+                .findings
+                .iter()
+                .any(|f| f.title == "Config File Exists")
+        );
+        let rtnetlink = report
+            .findings
+            .iter()
+            .find(|f| f.source == "Journal (lqos_scheduler)")
+            .unwrap();
+        assert_eq!(rtnetlink.severity, Severity::Warning);
+        assert!(rtnetlink.detail.contains("2 matching line(s)"));
+        assert!(
+            report
+                .findings
+                .windows(2)
+                .all(|pair| pair[0].severity <= pair[1].severity)
+        );
+    }
+
+    #[test]
+    fn signatures_match_case_insensitively_in_logs_only() {
+        let mut dump = SupportDump::default();
+        dump.entries.push(entry(
+            "Journal (lqosd)",
+            "thread 'main' PANICKED AT src/main.rs:10:5",
+        ));
+        dump.entries
+            .push(entry("Config File: network.json", "{\"panicked at\": {}}"));
+        let report = analyze_dump(&dump);
+        let panics: Vec<&Finding> = report
+            .findings
+            .iter()
+            .filter(|f| f.title == "A LibreQoS service panicked")
+            .collect();
+        assert_eq!(panics.len(), 1);
+        assert_eq!(panics[0].source, "Journal (lqosd)");
+        assert_eq!(
+            panics[0].evidence.as_deref(),
+            Some("thread 'main' PANICKED AT src/main.rs:10:5")
+        );
+    }
+
+    #[test]
+    fn gather_time_failures_are_kept() {
+        let mut dump = SupportDump::default();
+        dump.sanity_checks.results.push(SanityCheck {
+            name: "Queue Check (Internet Interface)".to_string(),
+            success: false,
+            comments: "eth0 does not provide multiple RX and TX queues".to_string(),
+        });
+        let report = analyze_dump(&dump);
+        let queues = report
+            .findings
+            .iter()
+            .find(|f| f.title == "Queue Check (Internet Interface)")
+            .unwrap();
+        assert_eq!(queues.severity, Severity::Warning);
+        assert_eq!(queues.source, "Sanity check (recorded when gathered)");
+    }
+
+    #[test]
+    fn omitted_sections_are_not_reported_missing() {
+        let mut dump = SupportDump::default();
+        dump.manifest.omitted_sections = vec![Section::ShapedDevices];
+        let report = analyze_dump(&dump);
+        let shaped = report
+            .findings
+            .iter()
+            .find(|f| f.title == "ShapedDevices.csv exists")
+            .unwrap();
+        assert_eq!(shaped.severity, Severity::Info);
+        let net_json = report
+            .findings
+            .iter()
+            .find(|f| f.title == "network.json exists")
+            .unwrap();
+        assert_eq!(net_json.severity, Severity::Critical);
+    }
+
+    #[test]
+    fn html_is_escaped() {
+        let dump = SupportDump {
+            sender: "<script>alert('x')</script>".to_string(),
+            ..Default::default()
+        };
+        let html = analyze_dump(&dump).to_html();
+        assert!(!html.contains("<script>"));
+        assert!(html.contains("&lt;script&gt;alert(&#39;x&#39;)&lt;/script&gt;"));
+    }
+}
@@ -1,15 +1,20 @@
 //! Provides a support library for the support tool system.
+mod analyze;
 pub mod console;
+mod redaction;
 mod sanity_checks;
 mod support_info;
 
 use crate::console::{error, success};
+pub use analyze::{AnalysisReport, Finding, Severity, analyze_dump};
+pub use redaction::{Redaction, RedactionManifest, RedactionProfile};
 pub use sanity_checks::SanityChecks;
 pub use sanity_checks::run_sanity_checks;
 use std::io::Write;
 use std::net::TcpStream;
 pub use support_info::SupportDump;
 pub use support_info::gather_all_support_info;
+pub use support_info::{GatherOptions, Section};
 
 const REMOTE_SYSTEM: &str = "stats.libreqos.io:9200";
 
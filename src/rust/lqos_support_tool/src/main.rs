@@ -1,10 +1,13 @@
 //! Provides the start of a text-mode support tool for LibreQoS. It will double as
 //! a library (see `lib.rs`) to provide similar functionality from the GUI.
 
-use clap::{Parser, Subcommand};
+use clap::{Args, Parser, Subcommand};
 use colored::Colorize;
 use lqos_config::load_config;
-use lqos_support_tool::{SupportDump, gather_all_support_info, run_sanity_checks};
+use lqos_support_tool::{
+    GatherOptions, RedactionManifest, RedactionProfile, Section, SupportDump, analyze_dump,
+    gather_all_support_info, run_sanity_checks,
+};
 use std::path::Path;
 use std::time::{SystemTime, UNIX_EPOCH};
 
@@ -20,9 +23,9 @@ enum Commands {
     /// Sanity Checks your Configuration against your hardware
     Sanity,
     /// Gather Support Info and Save it to /tmp
-    Gather,
+    Gather(GatherArgs),
     /// Gather Support Info and Send it to the LibreQoS Team. Note that Insight users and donors get priority, we don't guarantee that we'll help anyone else. Please make sure you've tried Zulip first ( https://chat.libreqos.io/ )
-    Submit,
+    Submit(GatherArgs),
     /// Summarize the contents of a support dump
     Summarize {
         /// The filename to read
@@ -35,6 +38,40 @@ enum Commands {
         /// The target directory
         target: String,
     },
+    /// Replay the sanity checks against a support dump and report ranked findings
+    Analyze {
+        /// The filename to read
+        filename: String,
+        /// Write an HTML report to this file
+        #[arg(long)]
+        html: Option<String>,
+        /// Write a markdown report to this file
+        #[arg(long)]
+        markdown: Option<String>,
+    },
+}
+
+#[derive(Args)]
+struct GatherArgs {
+    /// How much to redact before the dump leaves this machine
+    #[arg(long, value_enum, default_value_t = RedactionProfile::Standard)]
+    redact: RedactionProfile,
+    /// Only gather these sections, comma separated (default: all)
+    #[arg(long, value_enum, value_delimiter = ',')]
+    sections: Vec<Section>,
+}
+
+impl GatherArgs {
+    fn options(&self) -> GatherOptions {
+        let mut options = GatherOptions {
+            profile: self.redact,
+            ..Default::default()
+        };
+        if !self.sections.is_empty() {
+            options.sections = self.sections.clone();
+        }
+        options
+    }
 }
 
 fn read_line() -> String {
@@ -76,12 +113,12 @@ fn get_comments() -> String {
     read_line()
 }
 
-fn gather_dump() {
+fn gather_dump(args: &GatherArgs) {
     let name = get_name();
     let lts_key = get_lts_key();
     let comments = get_comments();
 
-    let dump = gather_all_support_info(&name, &comments, &lts_key).unwrap();
+    let dump = gather_all_support_info(&name, &comments, &lts_key, &args.options()).unwrap();
     let timestamp = SystemTime::now()
         .duration_since(UNIX_EPOCH)
         .unwrap()
@@ -102,6 +139,8 @@ fn summarize(filename: &str) {
             println!("Sent by: {}", decoded.sender);
             println!("Comments: {}", decoded.comment);
             println!("LTS Key: {}", decoded.lts_key);
+            print!("{}", describe_manifest(&decoded.manifest));
+            println!();
 
             println!("{:50} {:10} {}", "Sanity Check", "Success?", "Comment");
             for entry in decoded.sanity_checks.results.iter() {
@@ -124,6 +163,23 @@ fn summarize(filename: &str) {
     }
 }
 
+fn describe_manifest(manifest: &RedactionManifest) -> String {
+    let Some(profile) = manifest.profile else {
+        return "Redaction: unknown (gathered by an older support tool)\n".to_string();
+    };
+    let mut text = format!("Redaction: {profile:?}\n");
+    if !manifest.omitted_sections.is_empty() {
+        text += &format!("Omitted sections: {:?}\n", manifest.omitted_sections);
+    }
+    for redaction in manifest.redactions.iter() {
+        text += &format!(
+            "Redacted {} {} from {}\n",
+            redaction.count, redaction.what, redaction.entry
+        );
+    }
+    text
+}
+
 fn sanity_checks() {
     if let Err(e) = run_sanity_checks(true) {
         println!("Sanity Check Failed: {e:?}");
@@ -169,6 +225,10 @@ fn expand(filename: &str, target: &str) {
         let sanity_path = out_path.join("sanity_checks.txt");
         std::fs::write(sanity_path, sanity.as_bytes()).unwrap();
 
+        // Save what was redacted
+        let manifest_path = out_path.join("redaction_manifest.txt");
+        std::fs::write(manifest_path, describe_manifest(&decoded.manifest)).unwrap();
+
         // Save the files
         for (idx, dump) in decoded.entries.iter().enumerate() {
             let trimmed = dump
@@ -186,14 +246,41 @@ fn expand(filename: &str, target: &str) {
     println!("Expanded data written to {}", target);
 }
 
-fn submit() {
+fn analyze(filename: &str, html: Option<String>, markdown: Option<String>) {
+    let path = Path::new(filename);
+    if !path.exists() {
+        println!("Dump not found at {filename}");
+        return;
+    }
+    let bytes = std::fs::read(&path).unwrap();
+    let Ok(decoded) = SupportDump::from_bytes(&bytes) else {
+        println!("Dump did not decode");
+        return;
+    };
+    let report = analyze_dump(&decoded);
+
+    if html.is_none() && markdown.is_none() {
+        print!("{}", report.to_markdown());
+        return;
+    }
+    if let Some(target) = html {
+        std::fs::write(&target, report.to_html()).unwrap();
+        lqos_support_tool::console::success(&format!("HTML report written to {target}"));
+    }
+    if let Some(target) = markdown {
+        std::fs::write(&target, report.to_markdown()).unwrap();
+        lqos_support_tool::console::success(&format!("Markdown report written to {target}"));
+    }
+}
+
+fn submit(args: &GatherArgs) {
     // Get header
     let name = get_name();
     let lts_key = get_lts_key();
     let comments = get_comments();
 
     // Get the data
-    let dump = gather_all_support_info(&name, &comments, &lts_key).unwrap();
+    let dump = gather_all_support_info(&name, &comments, &lts_key, &args.options()).unwrap();
 
     // Send it
     lqos_support_tool::submit_to_network(dump);
@@ -204,10 +291,15 @@ fn main() {
 
     match cli.command {
         Some(Commands::Sanity) => sanity_checks(),
-        Some(Commands::Gather) => gather_dump(),
+        Some(Commands::Gather(args)) => gather_dump(&args),
         Some(Commands::Summarize { filename }) => summarize(&filename),
         Some(Commands::Expand { filename, target }) => expand(&filename, &target),
-        Some(Commands::Submit) => submit(),
+        Some(Commands::Submit(args)) => submit(&args),
+        Some(Commands::Analyze {
+            filename,
+            html,
+            markdown,
+        }) => analyze(&filename, html, markdown),
         _ => {}
     }
 }
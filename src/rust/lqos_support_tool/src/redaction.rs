@@ -0,0 +1,590 @@
+//! Redacts support dumps before they leave the shaper, and records what was removed.
+//!
+//! Subscriber names and IP addresses are replaced with pseudonyms derived from a random
+//! per-dump salt, so the same subscriber reads the same everywhere in one dump but can't be
+//! looked up. Pseudonymous addresses come from ranges that never appear on real networks
+//! (240.0.0.0/4 and 2001:db8::/32), and rewritten files still parse.
+
+use crate::sanity_checks::SanityChecks;
+use crate::support_info::{DumpEntry, Section};
+use clap::ValueEnum;
+use rand_core::{OsRng, RngCore};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+
+/// Replaces secret values in `/etc/lqos.conf`
+const REDACTED: &str = "REDACTED";
+
+/// Replaces MAC addresses
+const REDACTED_MAC: &str = "[mac]";
+
+#[derive(ValueEnum, Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum RedactionProfile {
+    /// Include everything verbatim
+    None,
+    /// Strip secrets from lqos.conf and drop MAC addresses
+    #[default]
+    Standard,
+    /// Standard, plus pseudonymize subscriber names and IP addresses
+    Strict,
+}
+
+impl RedactionProfile {
+    fn strips_secrets(self) -> bool {
+        self != RedactionProfile::None
+    }
+
+    fn drops_macs(self) -> bool {
+        self != RedactionProfile::None
+    }
+
+    fn hashes_subscribers(self) -> bool {
+        self == RedactionProfile::Strict
+    }
+}
+
+/// What was left out of, or removed from, a support dump. Dumps from older tools decode
+/// with an empty manifest and no profile.
+#[derive(Debug, Serialize, Deserialize, Default)]
+pub struct RedactionManifest {
+    pub profile: Option<RedactionProfile>,
+    pub omitted_sections: Vec<Section>,
+    pub redactions: Vec<Redaction>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Default, PartialEq)]
+pub struct Redaction {
+    /// The dump entry, or "Sanity Checks"
+    pub entry: String,
+    pub what: String,
+    pub count: usize,
+}
+
+pub(crate) struct Redactor {
+    profile: RedactionProfile,
+    salt: [u8; 16],
+    /// Original subscriber names and their pseudonyms, from ShapedDevices.csv
+    names: HashMap<String, String>,
+    /// Why subscriber names could not be learned, if they couldn't
+    names_unavailable: Option<String>,
+    redactions: Vec<Redaction>,
+}
+
+impl Redactor {
+    pub fn new(profile: RedactionProfile) -> Self {
+        let mut salt = [0u8; 16];
+        OsRng.fill_bytes(&mut salt);
+        Self {
+            profile,
+            salt,
+            names: HashMap::new(),
+            names_unavailable: None,
+            redactions: Vec::new(),
+        }
+    }
+
+    /// Does this profile need the subscriber names from ShapedDevices.csv?
+    pub fn needs_subscriber_names(&self) -> bool {
+        self.profile.hashes_subscribers()
+    }
+
+    /// Learns subscriber names from the contents of ShapedDevices.csv, so they can be
+    /// replaced in every entry. Call before redacting any entry, whether or not the file
+    /// itself is part of the dump.
+    pub fn learn_subscriber_names(&mut self, shaped_devices: anyhow::Result<String>) {
+        let learned = shaped_devices.and_then(|text| self.pseudonymize_shaped_devices(&text));
+        if let Err(e) = learned {
+            self.names_unavailable = Some(e.to_string());
+        }
+    }
+
+    /// Redacts one gathered entry in place.
+    pub fn redact_entry(&mut self, entry: &mut DumpEntry) {
+        if entry.name == "LibreQoS Config File" && self.profile.strips_secrets() {
+            let (text, count) = strip_config_secrets(&entry.contents);
+            entry.contents = text;
+            self.record(&entry.name, "secrets", count);
+        }
+
+        let subscriber_data = entry.name == "Config File: ShapedDevices.csv"
+            || entry.name.starts_with("Journal")
+            || entry.name.starts_with("SystemCtl Status (");
+        let hash_ips = subscriber_data && self.profile.hashes_subscribers();
+
+        if entry.name == "Config File: ShapedDevices.csv" && hash_ips {
+            match self.pseudonymize_shaped_devices(&entry.contents) {
+                Ok((text, names, comments)) => {
+                    entry.contents = text;
+                    self.record(&entry.name, "subscriber names", names);
+                    self.record(&entry.name, "comments", comments);
+                }
+                Err(e) => {
+                    // Better to send nothing than names we failed to find
+                    entry.contents = String::new();
+                    self.record(
+                        &entry.name,
+                        &format!("entire file (could not be parsed for redaction: {e})"),
+                        1,
+                    );
+                    return;
+                }
+            }
+        }
+
+        if self.profile.hashes_subscribers() && entry.name != "Config File: ShapedDevices.csv" {
+            if let Some(reason) = self.names_unavailable.clone()
+                && subscriber_data
+            {
+                // Without the names there is no way to find them in logs
+                entry.contents = String::new();
+                self.record(
+                    &entry.name,
+                    &format!("entire entry (subscriber names could not be loaded: {reason})"),
+                    1,
+                );
+                return;
+            }
+            let (text, names) = self.replace_names(&entry.contents);
+            entry.contents = text;
+            self.record(&entry.name, "subscriber names", names);
+        }
+
+        if self.profile.drops_macs() || hash_ips {
+            let (text, macs, ips) = self.scrub_text(&entry.contents, hash_ips);
+            entry.contents = text;
+            self.record(&entry.name, "MAC addresses", macs);
+            self.record(&entry.name, "IP addresses", ips);
+        }
+    }
+
+    /// Redacts sanity check comments, which quote device names and addresses.
+    pub fn redact_sanity_checks(&mut self, checks: &mut SanityChecks) {
+        if !self.profile.hashes_subscribers() {
+            return;
+        }
+        let (mut names, mut ips) = (0, 0);
+        for check in checks.results.iter_mut() {
+            let (text, found) = self.replace_names(&check.comments);
+            names += found;
+            let (text, _, found) = self.scrub_text(&text, true);
+            check.comments = text;
+            ips += found;
+        }
+        self.record("Sanity Checks", "subscriber names", names);
+        self.record("Sanity Checks", "IP addresses", ips);
+    }
+
+    pub fn finish(self, omitted_sections: Vec<Section>) -> RedactionManifest {
+        RedactionManifest {
+            profile: Some(self.profile),
+            omitted_sections,
+            redactions: self.redactions,
+        }
+    }
+
+    fn record(&mut self, entry: &str, what: &str, count: usize) {
+        if count > 0 {
+            self.redactions.push(Redaction {
+                entry: entry.to_string(),
+                what: what.to_string(),
+                count,
+            });
+        }
+    }
+
+    fn digest(&self, kind: &str, value: &str) -> [u8; 32] {
+        let mut hasher = Sha256::new();
+        hasher.update(self.salt);
+        hasher.update(kind.as_bytes());
+        hasher.update(value.as_bytes());
+        let mut digest = [0u8; 32];
+        digest.copy_from_slice(&hasher.finalize());
+        digest
+    }
+
+    fn pseudonym(&mut self, kind: &str, name: &str) -> String {
+        let digest = self.digest(kind, name);
+        let pseudonym = format!(
+            "{kind}-{:02x}{:02x}{:02x}{:02x}",
+            digest[0], digest[1], digest[2], digest[3]
+        );
+        self.names.insert(name.to_string(), pseudonym.clone());
+        pseudonym
+    }
+
+    fn pseudonym_ip(&self, ip: IpAddr) -> IpAddr {
+        let d = self.digest("ip", &ip.to_string());
+        match ip {
+            IpAddr::V4(_) => IpAddr::V4(Ipv4Addr::new(240 | (d[0] & 0x0f), d[1], d[2], d[3])),
+            IpAddr::V6(_) => IpAddr::V6(Ipv6Addr::new(
+                0x2001,
+                0x0db8,
+                u16::from_be_bytes([d[0], d[1]]),
+                u16::from_be_bytes([d[2], d[3]]),
+                u16::from_be_bytes([d[4], d[5]]),
+                u16::from_be_bytes([d[6], d[7]]),
+                u16::from_be_bytes([d[8], d[9]]),
+                u16::from_be_bytes([d[10], d[11]]),
+            )),
+        }
+    }
+
+    /// Replaces known subscriber names in free text, longest first so a name inside a longer
+    /// one doesn't split it. Returns the text and how many names were replaced.
+    fn replace_names(&self, text: &str) -> (String, usize) {
+        let mut names: Vec<(&String, &String)> = self
+            .names
+            .iter()
+            // Very short names would match inside unrelated words
+            .filter(|(original, _)| original.len() >= 3)
+            .collect();
+        names.sort_by(|a, b| b.0.len().cmp(&a.0.len()).then_with(|| a.0.cmp(b.0)));
+        let mut text = text.to_string();
+        let mut count = 0;
+        for (original, pseudonym) in names {
+            let found = text.matches(original.as_str()).count();
+            if found > 0 {
+                text = text.replace(original.as_str(), pseudonym);
+                count += found;
+            }
+        }
+        (text, count)
+    }
+
+    /// Rewrites ShapedDevices.csv with pseudonymous circuit and device names and no
+    /// comments. `#` comment lines are dropped, since they may hold anything.
+    fn pseudonymize_shaped_devices(
+        &mut self,
+        text: &str,
+    ) -> anyhow::Result<(String, usize, usize)> {
+        let mut reader = csv::ReaderBuilder::new()
+            .comment(Some(b'#'))
+            .flexible(true)
+            .from_reader(text.as_bytes());
+        let mut writer = csv::WriterBuilder::new()
+            .flexible(true)
+            .from_writer(Vec::new());
+        writer.write_record(reader.headers()?)?;
+
+        let (mut names, mut comments) = (0, 0);
+        for record in reader.records() {
+            let mut fields: Vec<String> = record?.iter().map(str::to_string).collect();
+            for (column, kind) in [(1, "circuit"), (3, "device")] {
+                if let Some(field) = fields.get_mut(column)
+                    && !field.trim().is_empty()
+                {
+                    *field = self.pseudonym(kind, field.trim());
+                    names += 1;
+                }
+            }
+            if let Some(comment) = fields.get_mut(12)
+                && !comment.trim().is_empty()
+            {
+                comment.clear();
+                comments += 1;
+            }
+            writer.write_record(&fields)?;
+        }
+
+        let text = String::from_utf8(writer.into_inner()?)?;
+        Ok((text, names, comments))
+    }
+
+    /// Replaces MAC addresses (if the profile drops them) and, if `hash_ips`, IP addresses
+    /// in free text. Returns the text and how many of each were replaced.
+    fn scrub_text(&self, text: &str, hash_ips: bool) -> (String, usize, usize) {
+        let mut out = String::with_capacity(text.len());
+        let (mut macs, mut ips) = (0, 0);
+        let mut run_start = None;
+        for (i, c) in text
+            .char_indices()
+            .chain(std::iter::once((text.len(), ' ')))
+        {
+            if c.is_ascii_hexdigit() || c == ':' || c == '.' {
+                run_start.get_or_insert(i);
+                continue;
+            }
+            if let Some(start) = run_start.take() {
+                let run = &text[start..i];
+                if self.profile.drops_macs() && is_mac(run) {
+                    out.push_str(REDACTED_MAC);
+                    macs += 1;
+                } else if let Some(replaced) = hash_ips.then(|| self.replace_ip(run)).flatten() {
+                    out.push_str(&replaced);
+                    ips += 1;
+                } else {
+                    out.push_str(run);
+                }
+            }
+            if i < text.len() {
+                out.push(c);
+            }
+        }
+        (out, macs, ips)
+    }
+
+    /// A run of address characters with its address replaced, allowing for a trailing
+    /// full stop or an IPv4 port
+    fn replace_ip(&self, run: &str) -> Option<String> {
+        let map = |ip: IpAddr| {
+            if ip.is_loopback() || ip.is_unspecified() {
+                None
+            } else {
+                Some(self.pseudonym_ip(ip).to_string())
+            }
+        };
+        if let Ok(ip) = run.parse::<IpAddr>() {
+            return map(ip);
+        }
+        let trimmed = run.trim_end_matches('.');
+        if let Ok(ip) = trimmed.parse::<IpAddr>() {
+            return map(ip).map(|ip| format!("{ip}{}", &run[trimmed.len()..]));
+        }
+        let (host, port) = run.rsplit_once(':')?;
+        let ip = host.parse::<Ipv4Addr>().ok()?;
+        map(IpAddr::V4(ip)).map(|ip| format!("{ip}:{port}"))
+    }
+}
+
+fn is_mac(run: &str) -> bool {
+    let parts: Vec<&str> = run.split(':').collect();
+    parts.len() == 6
+        && parts
+            .iter()
+            .all(|part| part.len() == 2 && part.chars().all(|c| c.is_ascii_hexdigit()))
+}
+
+/// Keys whose values are credentials. Notification sink URLs embed webhook tokens.
+fn is_secret_key(key: &str, section: &str) -> bool {
+    let key = key.to_lowercase();
+    ["key", "secret", "token", "password", "community"]
+        .iter()
+        .any(|suffix| key.ends_with(suffix))
+        || (section.starts_with("notifications") && key == "url")
+}
+
+/// Replaces non-empty secret string values in a TOML config, including those in inline
+/// tables, keeping everything else byte-for-byte so the file still loads.
+fn strip_config_secrets(text: &str) -> (String, usize) {
+    let mut out = String::with_capacity(text.len());
+    let mut section = String::new();
+    let mut count = 0;
+    for line in text.split_inclusive('\n') {
+        let trimmed = line.trim();
+        if trimmed.starts_with('[') && !trimmed.contains('=') {
+            section = trimmed.trim_matches(|c| c == '[' || c == ']').to_string();
+        }
+        let (line, redacted) = strip_line_secrets(line, &section);
+        out += &line;
+        count += redacted;
+    }
+    (out, count)
+}
+
+fn strip_line_secrets(line: &str, section: &str) -> (String, usize) {
+    let bytes = line.as_bytes();
+    let mut out = String::with_capacity(line.len());
+    let mut count = 0;
+    let mut i = 0;
+    let mut copied = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'#' => break,
+            b'"' | b'\'' => i = string_end(bytes, i),
+            b'=' => {
+                let key_end = line[..i].trim_end().len();
+                let key_start = line[..key_end]
+                    .rfind(|c: char| !(c.is_ascii_alphanumeric() || c == '_' || c == '-'))
+                    .map_or(0, |p| p + 1);
+                let key = &line[key_start..key_end];
+                let value_start = i + 1 + (line[i + 1..].len() - line[i + 1..].trim_start().len());
+                if value_start < bytes.len()
+                    && matches!(bytes[value_start], b'"' | b'\'')
+                    && is_secret_key(key, section)
+                {
+                    let value_end = string_end(bytes, value_start);
+                    // Leave empty values alone, so an unset secret reads as unset
+                    if value_end - value_start > 2 {
+                        let quote = bytes[value_start] as char;
+                        out += &line[copied..value_start];
+                        out.push(quote);
+                        out += REDACTED;
+                        out.push(quote);
+                        copied = value_end;
+                        count += 1;
+                    }
+                    i = value_end;
+                } else {
+                    i += 1;
+                }
+            }
+            _ => i += 1,
+        }
+    }
+    out += &line[copied..];
+    (out, count)
+}
+
+/// The index just past the string starting at `start`
+fn string_end(bytes: &[u8], start: usize) -> usize {
+    let quote = bytes[start];
+    let mut i = start + 1;
+    while i < bytes.len() {
+        if bytes[i] == b'\\' && quote == b'"' {
+            i += 2;
+            continue;
+        }
+        if bytes[i] == quote {
+            return i + 1;
+        }
+        i += 1;
+    }
+    bytes.len()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(name: &str, contents: &str) -> DumpEntry {
+        DumpEntry {
+            name: name.to_string(),
+            filename: None,
+            contents: contents.to_string(),
+        }
+    }
+
+    #[test]
+    fn config_secrets_are_stripped() {
+        let config = "\
+[long_term_stats]
+license_key = \"abc-123\" # from your email
+[splynx_integration]
+api_key = \"\"
+api_secret = 's3cr3t'
+url = \"https://splynx.example.com\"
+[remote_bus]
+key_file = \"/etc/lqos/key.pem\"
+clients = [{ name = \"noc\", token = \"t0k=n\", allow = [\"read\"] }]
+[notifications]
+sinks = [{ name = \"slack\", target = { type = \"slack\", url = \"https://hooks.slack.com/x\" } }]
+";
+        let (text, count) = strip_config_secrets(config);
+        assert_eq!(count, 4);
+        assert!(text.contains("license_key = \"REDACTED\" # from your email"));
+        assert!(text.contains("api_key = \"\""));
+        assert!(text.contains("api_secret = 'REDACTED'"));
+        assert!(text.contains("url = \"https://splynx.example.com\""));
+        assert!(text.contains("key_file = \"/etc/lqos/key.pem\""));
+        assert!(text.contains("token = \"REDACTED\", allow = [\"read\"]"));
+        assert!(text.contains("url = \"REDACTED\""));
+        assert!(!text.contains("t0k=n") && !text.contains("hooks.slack.com"));
+    }
+
+    #[test]
+    fn standard_profile_drops_macs_but_keeps_addresses() {
+        let mut redactor = Redactor::new(RedactionProfile::Standard);
+        let mut link = entry(
+            "Journal (lqosd)",
+            "link/ether 52:54:00:12:34:56 at 10:23:45 from 100.64.0.1",
+        );
+        redactor.redact_entry(&mut link);
+        assert_eq!(
+            link.contents,
+            "link/ether [mac] at 10:23:45 from 100.64.0.1"
+        );
+    }
+
+    #[test]
+    fn strict_profile_pseudonymizes_consistently() {
+        let mut redactor = Redactor::new(RedactionProfile::Strict);
+        let mut csv = entry(
+            "Config File: ShapedDevices.csv",
+            "\
+Circuit ID,Circuit Name,Device ID,Device Name,Parent Node,MAC,IPv4,IPv6,Download Min Mbps,Upload Min Mbps,Download Max Mbps,Upload Max Mbps,Comment
+# Jane's house
+1,\"Jane Doe, 1 Main St\",1,Jane Router,AP_A,52:54:00:12:34:56,\"100.64.0.1/30, 100.64.0.8\",fdd7:b724:0:100::/56,1,1,100,20,VIP
+",
+        );
+        let mut journal = entry(
+            "Journal (lqosd)",
+            "Mapped 100.64.0.8 to 1:5. Listening on 127.0.0.1:9123 and 100.64.0.1:80",
+        );
+        redactor.redact_entry(&mut csv);
+        redactor.redact_entry(&mut journal);
+        let mut checks = SanityChecks::default();
+        checks.results.push(crate::sanity_checks::SanityCheck {
+            name: "Shaped Device Invalid Parent".to_string(),
+            success: false,
+            comments: "Device Jane Router/1 is parented to AP_B - which does not exist".to_string(),
+        });
+        redactor.redact_sanity_checks(&mut checks);
+
+        for secret in ["Jane", "Main St", "VIP", "100.64.0", "52:54:00", "fdd7"] {
+            assert!(
+                !csv.contents.contains(secret),
+                "{secret} in {}",
+                csv.contents
+            );
+            assert!(!journal.contents.contains(secret));
+            assert!(!checks.results[0].comments.contains(secret));
+        }
+        let router = redactor.names["Jane Router"].clone();
+        assert!(checks.results[0].comments.contains(&router));
+        assert!(csv.contents.contains(&router));
+        assert!(journal.contents.contains("127.0.0.1:9123"));
+
+        // The same address gets the same pseudonym in every entry
+        let pseudonym = redactor
+            .pseudonym_ip("100.64.0.8".parse().unwrap())
+            .to_string();
+        assert!(pseudonym.starts_with("24") || pseudonym.starts_with("25"));
+        assert!(csv.contents.contains(&pseudonym));
+        assert!(journal.contents.contains(&format!("Mapped {pseudonym} to")));
+
+        // The rewritten file still loads
+        let devices =
+            lqos_config::ConfigShapedDevices::load_from_bytes(csv.contents.as_bytes()).unwrap();
+        assert_eq!(devices.devices.len(), 1);
+        assert_eq!(devices.devices[0].ipv4.len(), 2);
+
+        let manifest = redactor.finish(Vec::new());
+        assert!(manifest.redactions.contains(&Redaction {
+            entry: "Config File: ShapedDevices.csv".to_string(),
+            what: "subscriber names".to_string(),
+            count: 2,
+        }));
+    }
+
+    #[test]
+    fn strict_profile_replaces_names_in_journals() {
+        let mut redactor = Redactor::new(RedactionProfile::Strict);
+        redactor.learn_subscriber_names(Ok("\
+Circuit ID,Circuit Name,Device ID,Device Name,Parent Node,MAC,IPv4,IPv6,Download Min Mbps,Upload Min Mbps,Download Max Mbps,Upload Max Mbps,Comment
+7,Acme Bakery,7,Acme Bakery AP,AP_A,,100.64.1.1,,1,1,100,20,
+"
+        .to_string()));
+        let mut journal = entry(
+            "Journal (lqosd)",
+            "TreeGuard: circuit 'Acme Bakery' (7) moved to fq_codel; device Acme Bakery AP idle",
+        );
+        redactor.redact_entry(&mut journal);
+
+        assert!(!journal.contents.contains("Acme"), "{}", journal.contents);
+        let circuit = redactor.names["Acme Bakery"].clone();
+        let device = redactor.names["Acme Bakery AP"].clone();
+        assert!(journal.contents.contains(&format!("circuit '{circuit}'")));
+        assert!(journal.contents.contains(&format!("device {device} idle")));
+    }
+
+    #[test]
+    fn strict_profile_withholds_logs_when_names_are_unknown() {
+        let mut redactor = Redactor::new(RedactionProfile::Strict);
+        redactor.learn_subscriber_names(Err(anyhow::anyhow!("ShapedDevices.csv is missing")));
+        let mut journal = entry("Journal (lqosd)", "circuit 'Acme Bakery' moved");
+        redactor.redact_entry(&mut journal);
+        assert!(journal.contents.is_empty());
+    }
+}
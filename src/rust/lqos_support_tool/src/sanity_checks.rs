@@ -4,9 +4,11 @@ mod interfaces;
 mod net_json;
 mod queues;
 mod shaped_devices;
+pub(crate) mod source;
 
 use crate::console::{error, success};
 use serde::{Deserialize, Serialize};
+use source::{CheckSource, LiveSystem};
 
 #[derive(Debug, Serialize, Deserialize, Default)]
 pub struct SanityChecks {
@@ -24,21 +26,7 @@ pub fn run_sanity_checks(echo: bool) -> anyhow::Result<SanityChecks> {
     if echo {
         println!("Running Sanity Checks");
     }
-    let mut results = Vec::new();
-
-    // Run the checks
-    config_sane::config_exists(&mut results);
-    config_sane::can_load_config(&mut results);
-    interfaces::interfaces_exist(&mut results);
-    queues::sanity_check_queues(&mut results);
-    bridge::check_interface_status(&mut results);
-    bridge::check_bridge(&mut results);
-    net_json::check_net_json_exists(&mut results);
-    net_json::can_we_load_net_json(&mut results);
-    net_json::can_we_parse_net_json(&mut results);
-    shaped_devices::shaped_devices_exists(&mut results);
-    shaped_devices::can_we_read_shaped_devices(&mut results);
-    shaped_devices::parent_check(&mut results);
+    let results = run_checks(&LiveSystem);
 
     // Did any fail?
     let mut any_errors = false;
@@ -63,3 +51,21 @@ pub fn run_sanity_checks(echo: bool) -> anyhow::Result<SanityChecks> {
 
     Ok(SanityChecks { results })
 }
+
+/// Runs every check against a source: the live system, or a support dump being analyzed.
+pub(crate) fn run_checks(source: &dyn CheckSource) -> Vec<SanityCheck> {
+    let mut results = Vec::new();
+    config_sane::config_exists(source, &mut results);
+    config_sane::can_load_config(source, &mut results);
+    interfaces::interfaces_exist(source, &mut results);
+    queues::sanity_check_queues(source, &mut results);
+    bridge::check_interface_status(source, &mut results);
+    bridge::check_bridge(source, &mut results);
+    net_json::check_net_json_exists(source, &mut results);
+    net_json::can_we_load_net_json(source, &mut results);
+    net_json::can_we_parse_net_json(source, &mut results);
+    shaped_devices::shaped_devices_exists(source, &mut results);
+    shaped_devices::can_we_read_shaped_devices(source, &mut results);
+    shaped_devices::parent_check(source, &mut results);
+    results
+}
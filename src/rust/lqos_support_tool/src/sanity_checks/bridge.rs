@@ -1,10 +1,9 @@
 use crate::sanity_checks::SanityCheck;
-use lqos_config::load_config;
-use std::process::Command;
+use crate::sanity_checks::source::{CheckSource, IpLinkInterface};
 
-pub fn check_bridge(results: &mut Vec<SanityCheck>) {
-    if let Ok(cfg) = load_config() {
-        if let Ok(interfaces) = get_interfaces_from_ip_link() {
+pub fn check_bridge(source: &dyn CheckSource, results: &mut Vec<SanityCheck>) {
+    if let Ok(cfg) = source.config() {
+        if let Some(interfaces) = source.interfaces() {
             // On a stick mode is bridge-free
             if cfg.on_a_stick_mode() {
                 results.push(SanityCheck {
@@ -58,9 +57,9 @@ pub fn check_bridge(results: &mut Vec<SanityCheck>) {
     }
 }
 
-pub fn check_interface_status(results: &mut Vec<SanityCheck>) {
-    if let Ok(cfg) = load_config() {
-        if let Ok(interfaces) = get_interfaces_from_ip_link() {
+pub fn check_interface_status(source: &dyn CheckSource, results: &mut Vec<SanityCheck>) {
+    if let Ok(cfg) = source.config() {
+        if let Some(interfaces) = source.interfaces() {
             if let Some(stick) = &cfg.single_interface {
                 if let Some(iface) = interfaces.iter().find(|i| i.name == stick.interface) {
                     results.push(SanityCheck {
@@ -97,34 +96,3 @@ pub fn check_interface_status(results: &mut Vec<SanityCheck>) {
         }
     }
 }
-
-#[derive(Debug)]
-struct IpLinkInterface {
-    pub name: String,
-    pub operational_state: String,
-    pub link_type: String,
-    pub master: Option<String>,
-}
-
-fn get_interfaces_from_ip_link() -> anyhow::Result<Vec<IpLinkInterface>> {
-    let output = Command::new("/sbin/ip").args(["-j", "link"]).output()?;
-    let output = String::from_utf8(output.stdout)?;
-    let output_json = serde_json::from_str::<serde_json::Value>(&output)?;
-
-    let mut interfaces = Vec::new();
-    for interface in output_json.as_array().unwrap() {
-        let name = interface["ifname"].as_str().unwrap().to_string();
-        let operstate = interface["operstate"].as_str().unwrap().to_string();
-        let link_type = interface["link_type"].as_str().unwrap().to_string();
-        let master = interface["master"].as_str().map(|s| s.to_string());
-
-        interfaces.push(IpLinkInterface {
-            name,
-            operational_state: operstate,
-            link_type,
-            master,
-        });
-    }
-
-    Ok(interfaces)
-}
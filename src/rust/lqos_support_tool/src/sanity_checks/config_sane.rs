@@ -1,14 +1,12 @@
 use crate::sanity_checks::SanityCheck;
-use lqos_config::load_config;
-use std::path::Path;
+use crate::sanity_checks::source::CheckSource;
 
-pub fn config_exists(results: &mut Vec<SanityCheck>) {
-    let path = Path::new("/etc/lqos.conf");
+pub fn config_exists(source: &dyn CheckSource, results: &mut Vec<SanityCheck>) {
     let mut result = SanityCheck {
         name: "Config File Exists".to_string(),
         ..Default::default()
     };
-    if path.exists() {
+    if source.config_text().is_some() {
         result.success = true;
     } else {
         result.success = false;
@@ -18,17 +16,17 @@ pub fn config_exists(results: &mut Vec<SanityCheck>) {
     results.push(result);
 }
 
-pub fn can_load_config(results: &mut Vec<SanityCheck>) {
+pub fn can_load_config(source: &dyn CheckSource, results: &mut Vec<SanityCheck>) {
     let mut result = SanityCheck {
         name: "Config File Can Be Loaded".to_string(),
         ..Default::default()
     };
-    let cfg = load_config();
-    if cfg.is_ok() {
-        result.success = true;
-    } else {
-        result.success = false;
-        result.comments = "Configuration file could not be loaded".to_string();
+    match source.config() {
+        Ok(_) => result.success = true,
+        Err(e) => {
+            result.success = false;
+            result.comments = format!("Configuration file could not be loaded: {e}");
+        }
     }
     results.push(result);
 }
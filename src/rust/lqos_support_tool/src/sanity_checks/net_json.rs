@@ -1,53 +1,53 @@
 use crate::sanity_checks::SanityCheck;
-use lqos_config::{NetworkJson, load_config};
+use crate::sanity_checks::source::CheckSource;
+use lqos_config::NetworkJson;
 use serde_json::Value;
-use std::path::Path;
 
-pub fn check_net_json_exists(results: &mut Vec<SanityCheck>) {
-    let path = NetworkJson::path().unwrap();
-    if path.exists() {
-        results.push(SanityCheck {
-            name: "network.json exists".to_string(),
-            success: true,
-            comments: "".to_string(),
-        });
-    } else {
-        results.push(SanityCheck {
-            name: "network.json exists".to_string(),
-            success: false,
-            comments: format!("File not found at {:?}", path),
-        });
+pub fn check_net_json_exists(source: &dyn CheckSource, results: &mut Vec<SanityCheck>) {
+    match source.network_json() {
+        Ok(_) => {
+            results.push(SanityCheck {
+                name: "network.json exists".to_string(),
+                success: true,
+                comments: "".to_string(),
+            });
+        }
+        Err(e) => {
+            results.push(SanityCheck {
+                name: "network.json exists".to_string(),
+                success: false,
+                comments: e,
+            });
+        }
     }
 }
 
-pub fn can_we_load_net_json(results: &mut Vec<SanityCheck>) {
-    if let Ok(cfg) = load_config() {
-        let path = Path::new(&cfg.lqos_directory).join("network.json");
-        if path.exists() {
-            if let Ok(str) = std::fs::read_to_string(path) {
-                match serde_json::from_str::<Value>(&str) {
-                    Ok(_json) => {
-                        results.push(SanityCheck {
-                            name: "network.json is parseable JSON".to_string(),
-                            success: true,
-                            comments: "".to_string(),
-                        });
-                    }
-                    Err(e) => {
-                        results.push(SanityCheck {
-                            name: "network.json is parseable JSON".to_string(),
-                            success: false,
-                            comments: format!("{e:?}"),
-                        });
-                    }
-                }
+pub fn can_we_load_net_json(source: &dyn CheckSource, results: &mut Vec<SanityCheck>) {
+    if let Ok(str) = source.network_json() {
+        match serde_json::from_str::<Value>(&str) {
+            Ok(_json) => {
+                results.push(SanityCheck {
+                    name: "network.json is parseable JSON".to_string(),
+                    success: true,
+                    comments: "".to_string(),
+                });
+            }
+            Err(e) => {
+                results.push(SanityCheck {
+                    name: "network.json is parseable JSON".to_string(),
+                    success: false,
+                    comments: format!("{e:?}"),
+                });
             }
         }
     }
 }
 
-pub fn can_we_parse_net_json(results: &mut Vec<SanityCheck>) {
-    match lqos_config::NetworkJson::load() {
+pub fn can_we_parse_net_json(source: &dyn CheckSource, results: &mut Vec<SanityCheck>) {
+    let Ok(raw) = source.network_json() else {
+        return;
+    };
+    match NetworkJson::load_from_string(&raw) {
         Ok(_json) => {
             results.push(SanityCheck {
                 name: "network.json is valid JSON".to_string(),
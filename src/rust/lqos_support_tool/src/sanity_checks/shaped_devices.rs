@@ -1,25 +1,31 @@
 use crate::sanity_checks::SanityCheck;
-use lqos_config::ConfigShapedDevices;
+use crate::sanity_checks::source::CheckSource;
+use lqos_config::{ConfigShapedDevices, NetworkJson};
 
-pub fn shaped_devices_exists(results: &mut Vec<SanityCheck>) {
-    let path = ConfigShapedDevices::path().unwrap();
-    if path.exists() {
-        results.push(SanityCheck {
-            name: "ShapedDevices.csv exists".to_string(),
-            success: true,
-            comments: "".to_string(),
-        });
-    } else {
-        results.push(SanityCheck {
-            name: "ShapedDevices.csv exists".to_string(),
-            success: false,
-            comments: format!("File not found at {:?}", path),
-        });
+pub fn shaped_devices_exists(source: &dyn CheckSource, results: &mut Vec<SanityCheck>) {
+    match source.shaped_devices() {
+        Ok(_) => {
+            results.push(SanityCheck {
+                name: "ShapedDevices.csv exists".to_string(),
+                success: true,
+                comments: "".to_string(),
+            });
+        }
+        Err(e) => {
+            results.push(SanityCheck {
+                name: "ShapedDevices.csv exists".to_string(),
+                success: false,
+                comments: e,
+            });
+        }
     }
 }
 
-pub fn can_we_read_shaped_devices(results: &mut Vec<SanityCheck>) {
-    match lqos_config::ConfigShapedDevices::load() {
+pub fn can_we_read_shaped_devices(source: &dyn CheckSource, results: &mut Vec<SanityCheck>) {
+    let Ok(raw) = source.shaped_devices() else {
+        return;
+    };
+    match ConfigShapedDevices::load_from_bytes(&raw) {
         Ok(sd) => {
             results.push(SanityCheck {
                 name: "ShapedDevices.csv Loads?".to_string(),
@@ -37,8 +43,11 @@ pub fn can_we_read_shaped_devices(results: &mut Vec<SanityCheck>) {
     }
 }
 
-pub fn parent_check(results: &mut Vec<SanityCheck>) {
-    if let Ok(net_json) = lqos_config::NetworkJson::load() {
+pub fn parent_check(source: &dyn CheckSource, results: &mut Vec<SanityCheck>) {
+    let Ok(raw) = source.network_json() else {
+        return;
+    };
+    if let Ok(net_json) = NetworkJson::load_from_string(&raw) {
         if net_json.get_nodes_when_ready().len() < 2 {
             results.push(SanityCheck {
                 name: "Flat Network - Skipping Parent Check".to_string(),
@@ -48,7 +57,10 @@ pub fn parent_check(results: &mut Vec<SanityCheck>) {
             return;
         }
 
-        if let Ok(shaped_devices) = lqos_config::ConfigShapedDevices::load() {
+        let Ok(raw) = source.shaped_devices() else {
+            return;
+        };
+        if let Ok(shaped_devices) = ConfigShapedDevices::load_from_bytes(&raw) {
             for sd in shaped_devices.devices.iter() {
                 if !net_json
                     .get_nodes_when_ready()
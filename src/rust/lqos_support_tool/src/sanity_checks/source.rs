@@ -0,0 +1,286 @@
+//! Where the sanity checks read the system from: this machine, or a support dump.
+
+use crate::support_info::SupportDump;
+use anyhow::Error;
+use lqos_config::{Config, ConfigShapedDevices, NetworkJson, load_config};
+use std::ffi::CString;
+use std::path::Path;
+use std::process::Command;
+use std::sync::Arc;
+
+/// Dump entry names, as written by the `SupportInfo` gatherers
+const CONFIG_ENTRY: &str = "LibreQoS Config File";
+const NETWORK_JSON_ENTRY: &str = "Config File: network.json";
+const SHAPED_DEVICES_ENTRY: &str = "Config File: ShapedDevices.csv";
+const IP_LINK_ENTRY: &str = "IP Link Information";
+
+pub(crate) trait CheckSource {
+    /// The raw contents of `/etc/lqos.conf`
+    fn config_text(&self) -> Option<String>;
+
+    /// The parsed configuration
+    fn config(&self) -> Result<Arc<Config>, String>;
+
+    /// The raw contents of network.json, or why it is unavailable
+    fn network_json(&self) -> Result<String, String>;
+
+    /// The raw contents of ShapedDevices.csv, or why it is unavailable
+    fn shaped_devices(&self) -> Result<Vec<u8>, String>;
+
+    /// Network interfaces, if known
+    fn interfaces(&self) -> Option<Vec<IpLinkInterface>>;
+
+    /// Whether an interface exists, if known
+    fn interface_exists(&self, interface: &str) -> Option<bool>;
+
+    /// RX and TX queue counts of an interface, if known
+    fn queue_counts(&self, interface: &str) -> Option<(i32, i32)>;
+}
+
+#[derive(Debug, PartialEq)]
+pub(crate) struct IpLinkInterface {
+    pub name: String,
+    pub operational_state: String,
+    pub link_type: String,
+    pub master: Option<String>,
+}
+
+/// The machine the support tool is running on
+pub(crate) struct LiveSystem;
+
+impl CheckSource for LiveSystem {
+    fn config_text(&self) -> Option<String> {
+        std::fs::read_to_string("/etc/lqos.conf").ok()
+    }
+
+    fn config(&self) -> Result<Arc<Config>, String> {
+        load_config().map_err(|e| e.to_string())
+    }
+
+    fn network_json(&self) -> Result<String, String> {
+        let path = NetworkJson::path().map_err(|e| e.to_string())?;
+        if !path.exists() {
+            return Err(format!("File not found at {:?}", path));
+        }
+        std::fs::read_to_string(&path).map_err(|e| format!("{e:?}"))
+    }
+
+    fn shaped_devices(&self) -> Result<Vec<u8>, String> {
+        let path = ConfigShapedDevices::path().map_err(|e| e.to_string())?;
+        if !path.exists() {
+            return Err(format!("File not found at {:?}", path));
+        }
+        std::fs::read(&path).map_err(|e| format!("{e:?}"))
+    }
+
+    fn interfaces(&self) -> Option<Vec<IpLinkInterface>> {
+        get_interfaces_from_ip_link().ok()
+    }
+
+    fn interface_exists(&self, interface: &str) -> Option<bool> {
+        Some(interface_name_to_index(interface).is_ok())
+    }
+
+    fn queue_counts(&self, interface: &str) -> Option<(i32, i32)> {
+        Some(check_queues(interface))
+    }
+}
+
+/// A support dump, checked offline. Queue counts are not gathered, so queue checks are
+/// skipped; interfaces come from the `ip link` output.
+pub(crate) struct DumpSource<'a> {
+    dump: &'a SupportDump,
+}
+
+impl<'a> DumpSource<'a> {
+    pub fn new(dump: &'a SupportDump) -> Self {
+        Self { dump }
+    }
+
+    /// An entry's contents; gatherers that failed leave their entry empty
+    fn entry(&self, name: &str) -> Option<&'a str> {
+        self.dump
+            .entries
+            .iter()
+            .find(|entry| entry.name == name)
+            .map(|entry| entry.contents.as_str())
+            .filter(|contents| !contents.trim().is_empty())
+    }
+}
+
+impl CheckSource for DumpSource<'_> {
+    fn config_text(&self) -> Option<String> {
+        self.entry(CONFIG_ENTRY).map(str::to_string)
+    }
+
+    fn config(&self) -> Result<Arc<Config>, String> {
+        let text = self
+            .entry(CONFIG_ENTRY)
+            .ok_or_else(|| "/etc/lqos.conf is not in the support dump".to_string())?;
+        Config::load_from_string(text).map(Arc::new)
+    }
+
+    fn network_json(&self) -> Result<String, String> {
+        self.entry(NETWORK_JSON_ENTRY)
+            .map(str::to_string)
+            .ok_or_else(|| "network.json is not in the support dump".to_string())
+    }
+
+    fn shaped_devices(&self) -> Result<Vec<u8>, String> {
+        self.entry(SHAPED_DEVICES_ENTRY)
+            .map(|text| text.as_bytes().to_vec())
+            .ok_or_else(|| "ShapedDevices.csv is not in the support dump".to_string())
+    }
+
+    fn interfaces(&self) -> Option<Vec<IpLinkInterface>> {
+        self.entry(IP_LINK_ENTRY).map(parse_ip_link_text)
+    }
+
+    fn interface_exists(&self, interface: &str) -> Option<bool> {
+        self.interfaces()
+            .map(|interfaces| interfaces.iter().any(|i| i.name == interface))
+    }
+
+    fn queue_counts(&self, _interface: &str) -> Option<(i32, i32)> {
+        None
+    }
+}
+
+fn interface_name_to_index(interface_name: &str) -> anyhow::Result<u32> {
+    use nix::libc::if_nametoindex;
+    let if_name = CString::new(interface_name)?;
+    let index = unsafe { if_nametoindex(if_name.as_ptr()) };
+    if index == 0 {
+        Err(Error::msg(format!("Unknown interface: {interface_name}")))
+    } else {
+        Ok(index)
+    }
+}
+
+fn check_queues(interface: &str) -> (i32, i32) {
+    let path = format!("/sys/class/net/{interface}/queues/");
+    let sys_path = Path::new(&path);
+    if !sys_path.exists() {
+        return (0, 0);
+    }
+
+    let mut counts = (0, 0);
+    let paths = std::fs::read_dir(sys_path).unwrap();
+    for path in paths {
+        if let Ok(path) = &path {
+            if path.path().is_dir() {
+                if let Some(filename) = path.path().file_name() {
+                    if let Some(filename) = filename.to_str() {
+                        if filename.starts_with("rx-") {
+                            counts.0 += 1;
+                        } else if filename.starts_with("tx-") {
+                            counts.1 += 1;
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    counts
+}
+
+fn get_interfaces_from_ip_link() -> anyhow::Result<Vec<IpLinkInterface>> {
+    let output = Command::new("/sbin/ip").args(["-j", "link"]).output()?;
+    let output = String::from_utf8(output.stdout)?;
+    let output_json = serde_json::from_str::<serde_json::Value>(&output)?;
+
+    let mut interfaces = Vec::new();
+    for interface in output_json.as_array().unwrap() {
+        let name = interface["ifname"].as_str().unwrap().to_string();
+        let operstate = interface["operstate"].as_str().unwrap().to_string();
+        let link_type = interface["link_type"].as_str().unwrap().to_string();
+        let master = interface["master"].as_str().map(|s| s.to_string());
+
+        interfaces.push(IpLinkInterface {
+            name,
+            operational_state: operstate,
+            link_type,
+            master,
+        });
+    }
+
+    Ok(interfaces)
+}
+
+/// Parses plain `ip link` output, as stored in support dumps:
+///
+/// ```text
+/// 2: eth0: <BROADCAST,MULTICAST,UP,LOWER_UP> mtu 1500 qdisc mq master br0 state UP ...
+///     link/ether 52:54:00:12:34:56 brd ff:ff:ff:ff:ff:ff
+/// ```
+fn parse_ip_link_text(text: &str) -> Vec<IpLinkInterface> {
+    let mut interfaces: Vec<IpLinkInterface> = Vec::new();
+    for line in text.lines() {
+        if line.starts_with(char::is_whitespace) {
+            let Some(link) = line.trim().strip_prefix("link/") else {
+                continue;
+            };
+            if let Some(interface) = interfaces.last_mut() {
+                interface.link_type = link
+                    .split_whitespace()
+                    .next()
+                    .unwrap_or_default()
+                    .to_string();
+            }
+            continue;
+        }
+
+        let mut parts = line.splitn(3, ": ");
+        let (Some(_index), Some(name), Some(rest)) = (parts.next(), parts.next(), parts.next())
+        else {
+            continue;
+        };
+        let words: Vec<&str> = rest.split_whitespace().collect();
+        let after = |key: &str| {
+            words
+                .iter()
+                .position(|word| *word == key)
+                .and_then(|i| words.get(i + 1))
+                .map(|word| word.to_string())
+        };
+        interfaces.push(IpLinkInterface {
+            // Virtual interfaces are listed as `veth0@if2`
+            name: name.split('@').next().unwrap_or(name).to_string(),
+            operational_state: after("state").unwrap_or_default(),
+            link_type: String::new(),
+            master: after("master"),
+        });
+    }
+    interfaces
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_plain_ip_link_output() {
+        let text = "\
+1: lo: <LOOPBACK,UP,LOWER_UP> mtu 65536 qdisc noqueue state UNKNOWN mode DEFAULT group default qlen 1000
+    link/loopback 00:00:00:00:00:00 brd 00:00:00:00:00:00
+2: eth0: <BROADCAST,MULTICAST,UP,LOWER_UP> mtu 1500 qdisc mq master br0 state UP mode DEFAULT group default qlen 1000
+    link/ether 52:54:00:12:34:56 brd ff:ff:ff:ff:ff:ff
+3: veth0@if2: <BROADCAST,MULTICAST> mtu 1500 qdisc noop state DOWN mode DEFAULT group default qlen 1000
+    link/ether 52:54:00:12:34:57 brd ff:ff:ff:ff:ff:ff
+";
+        let interfaces = parse_ip_link_text(text);
+        assert_eq!(interfaces.len(), 3);
+        assert_eq!(
+            interfaces[1],
+            IpLinkInterface {
+                name: "eth0".to_string(),
+                operational_state: "UP".to_string(),
+                link_type: "ether".to_string(),
+                master: Some("br0".to_string()),
+            }
+        );
+        assert_eq!(interfaces[2].name, "veth0");
+        assert_eq!(interfaces[2].master, None);
+    }
+}
@@ -1,5 +1,7 @@
 use crate::console::error;
+use crate::redaction::{RedactionManifest, RedactionProfile, Redactor};
 use crate::sanity_checks::{SanityChecks, run_sanity_checks};
+use clap::ValueEnum;
 use colored::Colorize;
 use serde::{Deserialize, Serialize};
 
@@ -27,6 +29,8 @@ pub struct SupportDump {
     pub lts_key: String,
     pub sanity_checks: SanityChecks,
     pub entries: Vec<DumpEntry>,
+    #[serde(default)]
+    pub manifest: RedactionManifest,
 }
 
 #[derive(Debug, Serialize, Deserialize, Default)]
@@ -50,29 +54,114 @@ impl SupportDump {
     }
 }
 
+/// Groups of support information that can be left out of a dump
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Section {
+    /// /etc/lqos.conf
+    Config,
+    /// `ip link` and `ip addr`
+    Interfaces,
+    /// Kernel and distribution versions
+    System,
+    /// systemd service status
+    Services,
+    /// Service journals
+    Journals,
+    /// ShapedDevices.csv
+    ShapedDevices,
+    /// network.json
+    NetworkJson,
+}
+
+impl Section {
+    pub const ALL: [Section; 7] = [
+        Section::Config,
+        Section::Interfaces,
+        Section::System,
+        Section::Services,
+        Section::Journals,
+        Section::ShapedDevices,
+        Section::NetworkJson,
+    ];
+}
+
+/// What to gather, and how to redact it
+#[derive(Debug, Clone)]
+pub struct GatherOptions {
+    pub profile: RedactionProfile,
+    pub sections: Vec<Section>,
+}
+
+impl Default for GatherOptions {
+    fn default() -> Self {
+        Self {
+            profile: RedactionProfile::default(),
+            sections: Section::ALL.to_vec(),
+        }
+    }
+}
+
 pub fn gather_all_support_info(
     sender: &str,
     comments: &str,
     lts_key: &str,
+    options: &GatherOptions,
 ) -> anyhow::Result<SupportDump> {
-    let sanity_checks = run_sanity_checks(false)?;
-
-    let mut data_targets: Vec<Box<dyn SupportInfo>> = vec![
-        lqos_config::LqosConfig::boxed(),
-        ip_link::IpLink::boxed(),
-        ip_addr::IpAddr::boxed(),
-        kernel_info::KernelInfo::boxed(),
-        distro_name::DistroName::boxed(),
-        systemctl_services::SystemCtlServices::boxed(),
-        systemctl_service_single::SystemCtlService::boxed("lqosd"),
-        systemctl_service_single::SystemCtlService::boxed("lqos_node_manager"),
-        systemctl_service_single::SystemCtlService::boxed("lqos_scheduler"),
-        task_journal::TaskJournal::boxed("lqosd"),
-        task_journal::TaskJournal::boxed("lqos_node_manager"),
-        task_journal::TaskJournal::boxed("lqos_scheduler"),
-        service_config::ServiceConfig::boxed("ShapedDevices.csv"),
-        service_config::ServiceConfig::boxed("network.json"),
+    // Subscriber names can turn up in any entry, so learn them before gathering anything.
+    let mut redactor = Redactor::new(options.profile);
+    if redactor.needs_subscriber_names() {
+        let mut shaped_devices = service_config::ServiceConfig::boxed("ShapedDevices.csv");
+        let text = shaped_devices.gather().map(|_| shaped_devices.get_string());
+        redactor.learn_subscriber_names(text);
+    }
+
+    let mut sanity_checks = run_sanity_checks(false)?;
+
+    let all_targets: Vec<(Section, Box<dyn SupportInfo>)> = vec![
+        (Section::Config, lqos_config::LqosConfig::boxed()),
+        (Section::Interfaces, ip_link::IpLink::boxed()),
+        (Section::Interfaces, ip_addr::IpAddr::boxed()),
+        (Section::System, kernel_info::KernelInfo::boxed()),
+        (Section::System, distro_name::DistroName::boxed()),
+        (
+            Section::Services,
+            systemctl_services::SystemCtlServices::boxed(),
+        ),
+        (
+            Section::Services,
+            systemctl_service_single::SystemCtlService::boxed("lqosd"),
+        ),
+        (
+            Section::Services,
+            systemctl_service_single::SystemCtlService::boxed("lqos_node_manager"),
+        ),
+        (
+            Section::Services,
+            systemctl_service_single::SystemCtlService::boxed("lqos_scheduler"),
+        ),
+        (Section::Journals, task_journal::TaskJournal::boxed("lqosd")),
+        (
+            Section::Journals,
+            task_journal::TaskJournal::boxed("lqos_node_manager"),
+        ),
+        (
+            Section::Journals,
+            task_journal::TaskJournal::boxed("lqos_scheduler"),
+        ),
+        (
+            Section::ShapedDevices,
+            service_config::ServiceConfig::boxed("ShapedDevices.csv"),
+        ),
+        (
+            Section::NetworkJson,
+            service_config::ServiceConfig::boxed("network.json"),
+        ),
     ];
+    let mut data_targets: Vec<Box<dyn SupportInfo>> = all_targets
+        .into_iter()
+        .filter(|(section, _)| options.sections.contains(section))
+        .map(|(_, target)| target)
+        .collect();
 
     for target in data_targets.iter_mut() {
         println!("{} : {}", "TASK-GATHER".cyan(), target.get_name().yellow());
@@ -81,22 +170,30 @@ pub fn gather_all_support_info(
         }
     }
 
-    let mut dump = SupportDump {
-        sender: sender.to_string(),
-        comment: comments.to_string(),
-        lts_key: lts_key.to_string(),
-        sanity_checks,
-        entries: Vec::new(),
-    };
-
+    let mut entries = Vec::new();
     for target in data_targets.iter() {
-        let entry = DumpEntry {
+        let mut entry = DumpEntry {
             name: target.get_name(),
             filename: target.get_filename(),
             contents: target.get_string(),
         };
-        dump.entries.push(entry);
+        redactor.redact_entry(&mut entry);
+        entries.push(entry);
     }
+    redactor.redact_sanity_checks(&mut sanity_checks);
+    let omitted = Section::ALL
+        .into_iter()
+        .filter(|section| !options.sections.contains(section))
+        .collect();
+
+    let dump = SupportDump {
+        sender: sender.to_string(),
+        comment: comments.to_string(),
+        lts_key: lts_key.to_string(),
+        sanity_checks,
+        entries,
+        manifest: redactor.finish(omitted),
+    };
     //println!("{dump:#?}");
 
     Ok(dump)
@@ -0,0 +1,499 @@
+//! Threshold alerting rules over node and circuit metrics.
+//!
+//! A background thread evaluates the `[alerting]` rules every `evaluation_interval_seconds`
+//! against `NETWORK_JSON` node metrics (throughput, utilization, RTT, retransmits and CAKE
+//! queue stats) and the circuit-live snapshot. A condition must hold for `hold_down_seconds`
+//! before the rule fires, which raises an `ALERT_RULE` urgent issue (and so reaches any
+//! notification sinks). While the alert stays active the issue is refreshed so it does not
+//! expire. Once the condition has been clear for `recovery_seconds` the issue is removed
+//! and sinks receive an `ALERT_RULE_RESOLVED` notice. Missing data counts as clear.
+
+use std::collections::HashMap;
+use std::time::Duration;
+
+use lqos_bus::{UrgentSeverity, UrgentSource};
+use lqos_config::{
+    AlertComparison, AlertDirection, AlertMetric, AlertRule, AlertScope, NetworkJsonNode,
+    NotificationSeverity,
+};
+use lqos_utils::rtt::{FlowbeeEffectiveDirection, RttBucket};
+use lqos_utils::units::DownUpOrder;
+use lqos_utils::unix_time::unix_now;
+use serde_json::json;
+use tracing::{info, warn};
+
+use crate::shaped_devices_tracker::NETWORK_JSON;
+use crate::shaped_devices_tracker::circuit_live::{CircuitLiveRollup, fresh_circuit_live_snapshot};
+use crate::urgent;
+
+const ALERT_CODE: &str = "ALERT_RULE";
+/// Firing alerts are re-submitted this often, well inside the urgent-issue dedupe window.
+const REFRESH_SECONDS: u64 = 60;
+
+/// Starts the rule evaluation thread.
+pub fn start_alerting() {
+    let spawned = std::thread::Builder::new()
+        .name("alerting".to_string())
+        .spawn(|| {
+            let mut states: HashMap<(String, String), AlertState> = HashMap::new();
+            loop {
+                let interval = match lqos_config::load_config() {
+                    Ok(config) => {
+                        let rules: &[AlertRule] = if config.alerting.enabled {
+                            &config.alerting.rules
+                        } else {
+                            &[]
+                        };
+                        evaluate_rules(rules, &mut states, unix_now().unwrap_or_default());
+                        config.alerting.evaluation_interval_seconds.max(1)
+                    }
+                    Err(e) => {
+                        warn!("Unable to load config for alerting rules: {e:?}");
+                        60
+                    }
+                };
+                std::thread::sleep(Duration::from_secs(interval));
+            }
+        });
+    if let Err(e) = spawned {
+        warn!("Unable to start alerting thread: {e}");
+    }
+}
+
+/// One node or circuit's metric values for a tick, in each direction.
+struct Sample {
+    key: String,
+    label: String,
+    aliases: Vec<String>,
+    value: DownUpOrder<Option<f64>>,
+}
+
+fn evaluate_rules(
+    rules: &[AlertRule],
+    states: &mut HashMap<(String, String), AlertState>,
+    now: u64,
+) {
+    // Resolve anything left over from rules that were removed or disabled.
+    states.retain(|(rule_name, key), state| {
+        let active = rules.iter().any(|r| r.enabled && &r.name == rule_name);
+        if !active && state.firing {
+            raise(Transition::Resolve, rule_name, key, key, None, None, state);
+        }
+        active
+    });
+
+    for rule in rules.iter().filter(|r| r.enabled) {
+        let samples = match rule.scope {
+            AlertScope::Node => node_samples(rule.metric),
+            AlertScope::Circuit => circuit_samples(rule.metric),
+        };
+        let mut seen = Vec::new();
+        for sample in samples {
+            if !rule.targets.is_empty() && !sample.aliases.iter().any(|a| rule.targets.contains(a))
+            {
+                continue;
+            }
+            let value = pick_value(sample.value, rule.direction, rule.comparison);
+            let breaching = value.is_some_and(|v| breaches(v, rule.comparison, rule.threshold));
+            let state_key = (rule.name.clone(), sample.key.clone());
+            if !breaching && !states.contains_key(&state_key) {
+                continue;
+            }
+            let state = states.entry(state_key).or_default();
+            if value.is_some() {
+                state.last_value = value;
+            }
+            let transition = state.step(breaching, now, rule);
+            raise(
+                transition,
+                &rule.name,
+                &sample.key,
+                &sample.label,
+                Some(rule),
+                value,
+                state,
+            );
+            seen.push(sample.key);
+        }
+
+        // Nodes or circuits that disappeared (or fell out of the target list) count as clear.
+        for ((rule_name, key), state) in states.iter_mut() {
+            if rule_name != &rule.name || seen.contains(key) {
+                continue;
+            }
+            let transition = state.step(false, now, rule);
+            raise(transition, rule_name, key, key, Some(rule), None, state);
+        }
+    }
+
+    states.retain(|_, state| state.firing || state.breach_since.is_some());
+}
+
+/// Pushes a state change into the urgent-issue system.
+fn raise(
+    transition: Transition,
+    rule_name: &str,
+    key: &str,
+    label: &str,
+    rule: Option<&AlertRule>,
+    value: Option<f64>,
+    state: &AlertState,
+) {
+    if matches!(transition, Transition::None) {
+        return;
+    }
+    let dedupe_key = format!("alert:{rule_name}:{key}");
+    let severity = rule
+        .map(|r| r.severity)
+        .or(state.severity)
+        .unwrap_or(NotificationSeverity::Warning);
+    let severity = match severity {
+        NotificationSeverity::Error => UrgentSeverity::Error,
+        NotificationSeverity::Warning => UrgentSeverity::Warning,
+    };
+    let context = rule.map(|rule| {
+        json!({
+            "rule": rule.name,
+            "scope": rule.scope,
+            "target": key,
+            "metric": rule.metric,
+            "direction": rule.direction,
+            "comparison": rule.comparison,
+            "threshold": rule.threshold,
+            "value": value.or(state.last_value),
+        })
+        .to_string()
+    });
+    match transition {
+        Transition::None => {}
+        Transition::Fire | Transition::Refresh => {
+            let Some(rule) = rule else {
+                return;
+            };
+            let message = format!(
+                "{label}: {} is {:.1}, {} {} for at least {}s (rule '{}')",
+                rule.metric.as_str(),
+                value.or(state.last_value).unwrap_or_default(),
+                comparison_word(rule.comparison),
+                rule.threshold,
+                rule.hold_down_seconds,
+                rule.name,
+            );
+            if matches!(transition, Transition::Fire) {
+                info!("Alert fired: {message}");
+            }
+            urgent::submit(
+                UrgentSource::System,
+                severity,
+                ALERT_CODE.to_string(),
+                message,
+                context,
+                Some(dedupe_key),
+            );
+        }
+        Transition::Resolve => {
+            let message = format!("{label}: alert rule '{rule_name}' has recovered");
+            info!("Alert resolved: {message}");
+            urgent::resolve(
+                UrgentSource::System,
+                severity,
+                ALERT_CODE,
+                message,
+                context,
+                &dedupe_key,
+            );
+        }
+    }
+}
+
+fn comparison_word(comparison: AlertComparison) -> &'static str {
+    match comparison {
+        AlertComparison::Above => "above",
+        AlertComparison::Below => "below",
+    }
+}
+
+fn breaches(value: f64, comparison: AlertComparison, threshold: f64) -> bool {
+    match comparison {
+        AlertComparison::Above => value > threshold,
+        AlertComparison::Below => value < threshold,
+    }
+}
+
+/// Reduces a down/up pair to the value the rule should compare. For `either`, this is the
+/// direction closest to breaching.
+fn pick_value(
+    value: DownUpOrder<Option<f64>>,
+    direction: AlertDirection,
+    comparison: AlertComparison,
+) -> Option<f64> {
+    match direction {
+        AlertDirection::Down => value.down,
+        AlertDirection::Up => value.up,
+        AlertDirection::Either => match (value.down, value.up, comparison) {
+            (Some(d), Some(u), AlertComparison::Above) => Some(d.max(u)),
+            (Some(d), Some(u), AlertComparison::Below) => Some(d.min(u)),
+            (d, u, _) => d.or(u),
+        },
+    }
+}
+
+enum Transition {
+    None,
+    Fire,
+    Refresh,
+    Resolve,
+}
+
+/// Hold-down and recovery tracking for one rule and node/circuit.
+#[derive(Default)]
+struct AlertState {
+    breach_since: Option<u64>,
+    clear_since: Option<u64>,
+    firing: bool,
+    last_submitted: u64,
+    last_value: Option<f64>,
+    severity: Option<NotificationSeverity>,
+}
+
+impl AlertState {
+    fn step(&mut self, breaching: bool, now: u64, rule: &AlertRule) -> Transition {
+        self.severity = Some(rule.severity);
+        if breaching {
+            self.clear_since = None;
+            let since = *self.breach_since.get_or_insert(now);
+            if !self.firing && now.saturating_sub(since) >= rule.hold_down_seconds {
+                self.firing = true;
+                self.last_submitted = now;
+                return Transition::Fire;
+            }
+            if self.firing && now.saturating_sub(self.last_submitted) >= REFRESH_SECONDS {
+                self.last_submitted = now;
+                return Transition::Refresh;
+            }
+            return Transition::None;
+        }
+
+        self.breach_since = None;
+        if !self.firing {
+            return Transition::None;
+        }
+        let since = *self.clear_since.get_or_insert(now);
+        if now.saturating_sub(since) >= rule.recovery_seconds {
+            self.firing = false;
+            self.clear_since = None;
+            return Transition::Resolve;
+        }
+        Transition::None
+    }
+}
+
+fn node_samples(metric: AlertMetric) -> Vec<Sample> {
+    let reader = NETWORK_JSON.read();
+    reader
+        .get_nodes_when_ready()
+        .iter()
+        .filter(|node| node.name != "Root")
+        .map(|node| Sample {
+            key: node.name.clone(),
+            label: format!("Node '{}'", node.name),
+            aliases: vec![node.name.clone()],
+            value: node_metric(node, metric),
+        })
+        .collect()
+}
+
+fn node_metric(node: &NetworkJsonNode, metric: AlertMetric) -> DownUpOrder<Option<f64>> {
+    let mbps = DownUpOrder {
+        down: node.current_throughput.get_down() as f64 * 8.0 / 1_000_000.0,
+        up: node.current_throughput.get_up() as f64 * 8.0 / 1_000_000.0,
+    };
+    let count = |v: &DownUpOrder<u64>| DownUpOrder {
+        down: Some(v.get_down() as f64),
+        up: Some(v.get_up() as f64),
+    };
+    match metric {
+        AlertMetric::ThroughputMbps => DownUpOrder {
+            down: Some(mbps.down),
+            up: Some(mbps.up),
+        },
+        AlertMetric::UtilizationPct => DownUpOrder {
+            down: utilization(mbps.down, node.max_throughput.0),
+            up: utilization(mbps.up, node.max_throughput.1),
+        },
+        AlertMetric::RttP50Ms | AlertMetric::RttP95Ms => {
+            let percentile = if metric == AlertMetric::RttP50Ms {
+                50
+            } else {
+                95
+            };
+            let rtt_ms = |direction| {
+                node.rtt_buffer
+                    .percentile(RttBucket::Current, direction, percentile)
+                    .map(|rtt| rtt.as_millis())
+            };
+            DownUpOrder {
+                down: rtt_ms(FlowbeeEffectiveDirection::Download),
+                up: rtt_ms(FlowbeeEffectiveDirection::Upload),
+            }
+        }
+        AlertMetric::RetransmitPct => DownUpOrder {
+            down: retransmit_pct(
+                node.current_tcp_retransmits.get_down(),
+                node.current_tcp_retransmit_packets.get_down(),
+            ),
+            up: retransmit_pct(
+                node.current_tcp_retransmits.get_up(),
+                node.current_tcp_retransmit_packets.get_up(),
+            ),
+        },
+        AlertMetric::CakeDrops => count(&node.current_drops),
+        AlertMetric::CakeMarks => count(&node.current_marks),
+    }
+}
+
+fn circuit_samples(metric: AlertMetric) -> Vec<Sample> {
+    let snapshot = fresh_circuit_live_snapshot();
+    snapshot
+        .by_circuit_id
+        .values()
+        .map(|circuit| Sample {
+            key: circuit.circuit_id.clone(),
+            label: format!("Circuit '{}'", circuit.circuit_name),
+            aliases: vec![circuit.circuit_id.clone(), circuit.circuit_name.clone()],
+            value: circuit_metric(circuit, metric),
+        })
+        .collect()
+}
+
+fn circuit_metric(circuit: &CircuitLiveRollup, metric: AlertMetric) -> DownUpOrder<Option<f64>> {
+    let mbps = DownUpOrder {
+        down: circuit.bytes_per_second.down as f64 * 8.0 / 1_000_000.0,
+        up: circuit.bytes_per_second.up as f64 * 8.0 / 1_000_000.0,
+    };
+    match metric {
+        AlertMetric::ThroughputMbps => DownUpOrder {
+            down: Some(mbps.down),
+            up: Some(mbps.up),
+        },
+        AlertMetric::UtilizationPct => DownUpOrder {
+            down: utilization(mbps.down, circuit.plan_mbps.down as f64),
+            up: utilization(mbps.up, circuit.plan_mbps.up as f64),
+        },
+        AlertMetric::RttP50Ms => DownUpOrder {
+            down: circuit
+                .rtt_current_p50_nanos
+                .down
+                .map(|n| n as f64 / 1_000_000.0),
+            up: circuit
+                .rtt_current_p50_nanos
+                .up
+                .map(|n| n as f64 / 1_000_000.0),
+        },
+        AlertMetric::RetransmitPct => DownUpOrder {
+            down: circuit.tcp_retransmit_sample.down.percent_0_to_100(),
+            up: circuit.tcp_retransmit_sample.up.percent_0_to_100(),
+        },
+        // Rejected by config validation.
+        AlertMetric::RttP95Ms | AlertMetric::CakeDrops | AlertMetric::CakeMarks => DownUpOrder {
+            down: None,
+            up: None,
+        },
+    }
+}
+
+fn utilization(mbps: f64, max_mbps: f64) -> Option<f64> {
+    (max_mbps > 0.0).then(|| mbps / max_mbps * 100.0)
+}
+
+fn retransmit_pct(retransmits: u64, packets: u64) -> Option<f64> {
+    (packets > 0).then(|| retransmits as f64 / packets as f64 * 100.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rule(hold_down_seconds: u64, recovery_seconds: u64) -> AlertRule {
+        AlertRule {
+            name: "site-rtt".to_string(),
+            enabled: true,
+            scope: AlertScope::Node,
+            targets: Vec::new(),
+            metric: AlertMetric::RttP95Ms,
+            direction: AlertDirection::Either,
+            comparison: AlertComparison::Above,
+            threshold: 80.0,
+            hold_down_seconds,
+            recovery_seconds,
+            severity: NotificationSeverity::Warning,
+        }
+    }
+
+    #[test]
+    fn fires_after_hold_down_and_resolves_after_recovery() {
+        let rule = rule(600, 300);
+        let mut state = AlertState::default();
+
+        assert!(matches!(state.step(true, 1000, &rule), Transition::None));
+        assert!(matches!(state.step(true, 1599, &rule), Transition::None));
+        assert!(matches!(state.step(true, 1600, &rule), Transition::Fire));
+        assert!(matches!(state.step(true, 1610, &rule), Transition::None));
+        assert!(matches!(
+            state.step(true, 1600 + REFRESH_SECONDS, &rule),
+            Transition::Refresh
+        ));
+
+        // A brief dip does not resolve; the recovery timer restarts on the next breach.
+        assert!(matches!(state.step(false, 1700, &rule), Transition::None));
+        assert!(matches!(state.step(true, 1710, &rule), Transition::None));
+        assert!(matches!(state.step(false, 1720, &rule), Transition::None));
+        assert!(matches!(state.step(false, 2019, &rule), Transition::None));
+        assert!(matches!(
+            state.step(false, 2020, &rule),
+            Transition::Resolve
+        ));
+        assert!(!state.firing);
+    }
+
+    #[test]
+    fn interrupted_breach_restarts_hold_down() {
+        let rule = rule(600, 0);
+        let mut state = AlertState::default();
+        state.step(true, 1000, &rule);
+        state.step(false, 1500, &rule);
+        assert!(matches!(state.step(true, 1700, &rule), Transition::None));
+        assert!(matches!(state.step(true, 2300, &rule), Transition::Fire));
+    }
+
+    #[test]
+    fn either_direction_picks_worst_value() {
+        let value = DownUpOrder {
+            down: Some(40.0),
+            up: Some(90.0),
+        };
+        let either = AlertDirection::Either;
+        assert_eq!(
+            pick_value(value, either, AlertComparison::Above),
+            Some(90.0)
+        );
+        assert_eq!(
+            pick_value(value, either, AlertComparison::Below),
+            Some(40.0)
+        );
+        assert_eq!(
+            pick_value(value, AlertDirection::Down, AlertComparison::Above),
+            Some(40.0)
+        );
+        let partial = DownUpOrder {
+            down: None,
+            up: Some(12.0),
+        };
+        assert_eq!(
+            pick_value(partial, either, AlertComparison::Above),
+            Some(12.0)
+        );
+        assert_eq!(utilization(95.0, 0.0), None);
+        assert_eq!(retransmit_pct(5, 100), Some(5.0));
+    }
+}
@@ -0,0 +1,388 @@
+//! Append-only audit log of administrative actions.
+//!
+//! Reloads, configuration saves, rate overrides, shaped-device and user edits, urgent-issue
+//! clears and TreeGuard toggles are recorded as one JSON object per line in
+//! `<lqos_directory>/audit.jsonl`, whether they arrive over the WebUI, the `/api/v1` REST
+//! API or the local bus. When the file grows past [`MAX_FILE_BYTES`] it is rotated to
+//! `audit.jsonl.1` (and older files shuffled up), keeping [`ROTATED_FILES`] generations.
+//! The live file can be tailed directly by a SIEM agent.
+
+use std::fmt::Display;
+use std::fs::{File, OpenOptions};
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+
+use lqos_bus::{AuditLogEntry, BusResponse};
+use lqos_utils::unix_time::unix_now;
+use parking_lot::Mutex;
+use serde::Serialize;
+use serde_json::Value;
+use tracing::warn;
+
+const AUDIT_FILE: &str = "audit.jsonl";
+/// The live file is rotated once it reaches this size.
+const MAX_FILE_BYTES: u64 = 10 * 1024 * 1024;
+/// Number of rotated files kept alongside the live file.
+const ROTATED_FILES: usize = 5;
+/// Before/after summaries and messages longer than this are truncated.
+const MAX_SUMMARY_CHARS: usize = 1024;
+/// Upper bound on entries returned by a single query.
+pub const MAX_QUERY_LIMIT: usize = 5000;
+
+static WRITE_LOCK: Mutex<()> = Mutex::new(());
+
+/// Who performed an action, and from where.
+#[derive(Clone, Debug)]
+pub struct AuditActor {
+    user: String,
+    source: String,
+}
+
+impl AuditActor {
+    /// A node manager user, connecting from `source`.
+    pub fn web(user: &str, source: &str) -> Self {
+        Self {
+            user: user.to_string(),
+            source: source.to_string(),
+        }
+    }
+
+    /// An `/api/v1` bearer token, identified by its name.
+    pub fn api_token(name: &str, source: &str) -> Self {
+        Self {
+            user: format!("api:{name}"),
+            source: source.to_string(),
+        }
+    }
+
+    /// A local process talking to lqosd over the bus socket.
+    pub fn bus() -> Self {
+        Self {
+            user: "bus".to_string(),
+            source: "local".to_string(),
+        }
+    }
+
+    /// A `[[remote_bus.clients]]` entry, identified by its name, connecting from `peer`.
+    pub fn remote_bus(client: &str, peer: &str) -> Self {
+        Self {
+            user: format!("bus:{client}"),
+            source: peer.to_string(),
+        }
+    }
+
+    /// The recorded user name, e.g. `bus` or `api:<token name>`.
+    pub fn user(&self) -> &str {
+        &self.user
+    }
+}
+
+/// An action about to be recorded. Build it up, then call [`AuditEvent::record`].
+#[derive(Debug)]
+pub struct AuditEvent {
+    action: &'static str,
+    target: Option<String>,
+    before: Option<String>,
+    after: Option<String>,
+}
+
+impl AuditEvent {
+    /// Starts an event for `action` (a short `snake_case` name).
+    pub fn new(action: &'static str) -> Self {
+        Self {
+            action,
+            target: None,
+            before: None,
+            after: None,
+        }
+    }
+
+    /// What the action applied to.
+    pub fn target(mut self, target: impl Into<String>) -> Self {
+        self.target = Some(target.into());
+        self
+    }
+
+    /// The affected state before the action, serialized as JSON.
+    pub fn before<T: Serialize>(mut self, value: &T) -> Self {
+        self.before = Some(summarize(value));
+        self
+    }
+
+    /// The affected state after the action, serialized as JSON.
+    pub fn after<T: Serialize>(mut self, value: &T) -> Self {
+        self.after = Some(summarize(value));
+        self
+    }
+
+    /// Appends the event to the audit log. Failures to write are logged, not returned:
+    /// an unwritable audit log must not block the action itself.
+    pub fn record(self, actor: &AuditActor, ok: bool, message: impl Into<String>) {
+        let entry = AuditLogEntry {
+            timestamp_unix: unix_now().unwrap_or_default(),
+            user: actor.user.clone(),
+            source: actor.source.clone(),
+            action: self.action.to_string(),
+            target: self.target,
+            before: self.before,
+            after: self.after,
+            ok,
+            message: truncate(message.into()),
+        };
+        let Some(path) = audit_path() else {
+            return;
+        };
+        let _guard = WRITE_LOCK.lock();
+        if let Err(e) = append_entry(&path, &entry, MAX_FILE_BYTES, ROTATED_FILES) {
+            warn!("Unable to write audit log entry to {path:?}: {e}");
+        }
+    }
+
+    /// Records the outcome of an action that returns a `Result`.
+    pub fn record_result<T, E: Display>(self, actor: &AuditActor, result: &Result<T, E>) {
+        match result {
+            Ok(_) => self.record(actor, true, "Ok"),
+            Err(e) => self.record(actor, false, e.to_string()),
+        }
+    }
+
+    /// Records a bus request from its response: `Fail` responses are failures.
+    pub fn record_bus(self, actor: &AuditActor, response: &BusResponse) {
+        let (ok, message) = match response {
+            BusResponse::Fail(message) => (false, message.clone()),
+            BusResponse::ReloadLibreQoS(message) => (true, message.clone()),
+            _ => (true, "Ok".to_string()),
+        };
+        self.record(actor, ok, message);
+    }
+}
+
+/// Filters for [`query`].
+#[derive(Clone, Debug, Default)]
+pub struct AuditQuery {
+    /// Only entries at or after this Unix timestamp (seconds).
+    pub since_unix: Option<u64>,
+    /// Only entries made by this user.
+    pub user: Option<String>,
+    /// Only entries for this action.
+    pub action: Option<String>,
+    /// Maximum number of entries to return; clamped to [`MAX_QUERY_LIMIT`].
+    pub limit: usize,
+}
+
+impl AuditQuery {
+    fn matches(&self, entry: &AuditLogEntry) -> bool {
+        self.since_unix.is_none_or(|t| entry.timestamp_unix >= t)
+            && self.user.as_deref().is_none_or(|u| entry.user == u)
+            && self.action.as_deref().is_none_or(|a| entry.action == a)
+    }
+}
+
+/// Returns matching entries from the live and rotated files, newest first.
+pub fn query(filter: &AuditQuery) -> Vec<AuditLogEntry> {
+    let Some(path) = audit_path() else {
+        return Vec::new();
+    };
+    read_entries(&path, ROTATED_FILES, filter)
+}
+
+/// Returns matching entries as JSON lines, oldest first, for import into a SIEM.
+pub fn export_jsonl(filter: &AuditQuery) -> String {
+    let mut lines = String::new();
+    for entry in query(filter).iter().rev() {
+        if let Ok(line) = serde_json::to_string(entry) {
+            lines.push_str(&line);
+            lines.push('\n');
+        }
+    }
+    lines
+}
+
+/// Names the top-level fields that differ between two serializable values, for summarizing
+/// changes whose contents should not be logged.
+pub fn changed_sections<T: Serialize>(before: &T, after: &T) -> Vec<String> {
+    let (Ok(Value::Object(before)), Ok(Value::Object(after))) =
+        (serde_json::to_value(before), serde_json::to_value(after))
+    else {
+        return Vec::new();
+    };
+    let mut changed: Vec<String> = before
+        .keys()
+        .chain(after.keys())
+        .filter(|key| before.get(*key) != after.get(*key))
+        .cloned()
+        .collect();
+    changed.sort();
+    changed.dedup();
+    changed
+}
+
+/// Serializes `value` as compact JSON, truncated to [`MAX_SUMMARY_CHARS`].
+fn summarize<T: Serialize>(value: &T) -> String {
+    truncate(serde_json::to_string(value).unwrap_or_default())
+}
+
+fn truncate(text: String) -> String {
+    if text.chars().count() <= MAX_SUMMARY_CHARS {
+        return text;
+    }
+    let mut truncated: String = text.chars().take(MAX_SUMMARY_CHARS).collect();
+    truncated.push('…');
+    truncated
+}
+
+fn audit_path() -> Option<PathBuf> {
+    match lqos_config::load_config() {
+        Ok(config) => Some(Path::new(&config.lqos_directory).join(AUDIT_FILE)),
+        Err(e) => {
+            warn!("Unable to load config to locate the audit log: {e}");
+            None
+        }
+    }
+}
+
+fn rotated_path(path: &Path, generation: usize) -> PathBuf {
+    let mut name = path.as_os_str().to_owned();
+    name.push(format!(".{generation}"));
+    PathBuf::from(name)
+}
+
+fn append_entry(
+    path: &Path,
+    entry: &AuditLogEntry,
+    max_bytes: u64,
+    rotated_files: usize,
+) -> std::io::Result<()> {
+    let mut line = serde_json::to_string(entry)?;
+    line.push('\n');
+
+    let current = std::fs::metadata(path).map(|m| m.len()).unwrap_or(0);
+    if current > 0 && current + line.len() as u64 > max_bytes {
+        rotate(path, rotated_files)?;
+    }
+
+    let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+    file.write_all(line.as_bytes())?;
+    file.flush()
+}
+
+/// Shifts `audit.jsonl.N` to `.N+1` (dropping the oldest) and moves the live file to `.1`.
+fn rotate(path: &Path, rotated_files: usize) -> std::io::Result<()> {
+    if rotated_files == 0 {
+        return std::fs::remove_file(path);
+    }
+    let oldest = rotated_path(path, rotated_files);
+    if oldest.exists() {
+        std::fs::remove_file(&oldest)?;
+    }
+    for generation in (1..rotated_files).rev() {
+        let from = rotated_path(path, generation);
+        if from.exists() {
+            std::fs::rename(&from, rotated_path(path, generation + 1))?;
+        }
+    }
+    std::fs::rename(path, rotated_path(path, 1))
+}
+
+fn read_entries(path: &Path, rotated_files: usize, filter: &AuditQuery) -> Vec<AuditLogEntry> {
+    let limit = filter.limit.clamp(1, MAX_QUERY_LIMIT);
+    let mut result = Vec::new();
+    // Newest file first; lines within a file are oldest first.
+    let files = std::iter::once(path.to_path_buf())
+        .chain((1..=rotated_files).map(|generation| rotated_path(path, generation)));
+    for file in files {
+        let Ok(handle) = File::open(&file) else {
+            continue;
+        };
+        let mut entries: Vec<AuditLogEntry> = BufReader::new(handle)
+            .lines()
+            .map_while(Result::ok)
+            .filter_map(|line| serde_json::from_str(&line).ok())
+            .filter(|entry| filter.matches(entry))
+            .collect();
+        entries.reverse();
+        result.extend(entries.into_iter().take(limit - result.len()));
+        if result.len() >= limit {
+            break;
+        }
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(ts: u64, user: &str, action: &str) -> AuditLogEntry {
+        AuditLogEntry {
+            timestamp_unix: ts,
+            user: user.to_string(),
+            source: "192.0.2.1".to_string(),
+            action: action.to_string(),
+            target: None,
+            before: None,
+            after: None,
+            ok: true,
+            message: "Ok".to_string(),
+        }
+    }
+
+    #[test]
+    fn rotates_and_queries_newest_first() {
+        let dir = std::env::temp_dir().join(format!("lqosd-audit-test-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).expect("create temp dir");
+        let path = dir.join(AUDIT_FILE);
+
+        // Each entry is ~150 bytes, so a 400 byte limit rotates every other write.
+        for ts in 1..=8 {
+            let action = if ts % 2 == 0 {
+                "reload_libreqos"
+            } else {
+                "add_user"
+            };
+            append_entry(&path, &entry(ts, "admin", action), 400, 2).expect("append");
+        }
+        assert!(rotated_path(&path, 2).exists());
+        assert!(!rotated_path(&path, 3).exists());
+
+        let all = read_entries(
+            &path,
+            2,
+            &AuditQuery {
+                limit: 100,
+                ..Default::default()
+            },
+        );
+        let stamps: Vec<u64> = all.iter().map(|e| e.timestamp_unix).collect();
+        assert_eq!(stamps, vec![8, 7, 6, 5, 4, 3]);
+
+        let reloads = read_entries(
+            &path,
+            2,
+            &AuditQuery {
+                since_unix: Some(5),
+                action: Some("reload_libreqos".to_string()),
+                limit: 1,
+                ..Default::default()
+            },
+        );
+        assert_eq!(reloads.len(), 1);
+        assert_eq!(reloads[0].timestamp_unix, 8);
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn changed_sections_lists_differing_fields() {
+        let before = serde_json::json!({ "bridge": { "use_xdp_bridge": true }, "node_id": "a" });
+        let after = serde_json::json!({ "bridge": { "use_xdp_bridge": false }, "node_id": "a", "queues": {} });
+        assert_eq!(changed_sections(&before, &after), vec!["bridge", "queues"]);
+    }
+
+    #[test]
+    fn long_summaries_are_truncated() {
+        let summary = summarize(&"x".repeat(MAX_SUMMARY_CHARS * 2));
+        assert_eq!(summary.chars().count(), MAX_SUMMARY_CHARS + 1);
+        assert!(summary.ends_with('…'));
+    }
+}
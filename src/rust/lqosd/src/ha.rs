@@ -0,0 +1,628 @@
+//! Active/standby high availability.
+//!
+//! The active shaper connects to the standby's `listen` address and streams its shaping state:
+//! the three override layers, the last committed Bakery batch (plus the latest live speed of
+//! each site changed since), the XDP IP mappings, StormGuard's site rates and TreeGuard's
+//! runtime-virtualized nodes. Each is sent when it changes, with heartbeats in between, over
+//! frames signed with the shared secret (see [`wire`]).
+//!
+//! The standby applies what it receives, so its queues and mappings match the active shaper's,
+//! and keeps StormGuard and TreeGuard paused. It takes over when its configured role is changed
+//! to `active`, or (with `auto_failover`) when the active shaper has been silent for
+//! `failover_timeout_seconds` after first contact and no unauthenticated peer has been talking to
+//! it meanwhile (that peer might be the active shaper with a mismatched secret, so taking over
+//! could leave two shapers active). Taking over resumes StormGuard from the
+//! synchronized site rates, hands the synchronized virtualized nodes to TreeGuard and raises an
+//! `HA_TAKEOVER` urgent issue.
+
+mod sync;
+mod wire;
+
+use std::io::{BufReader, Read, Write};
+use std::net::{TcpListener, TcpStream, ToSocketAddrs};
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
+use std::time::{Duration, Instant};
+
+use lqos_bus::{BusRequest, BusResponse, HaStatus, IpMapping, UrgentSeverity, UrgentSource};
+use lqos_config::{HaRole, HighAvailabilityConfig};
+use lqos_overrides::{ChangeAuthor, OverrideFile, OverrideLayer, OverrideStore, SaveContext};
+use lqos_stormguard::{STORMGUARD_PAUSED, STORMGUARD_STATS};
+use lqos_utils::unix_time::unix_now;
+use once_cell::sync::Lazy;
+use parking_lot::Mutex;
+use rand::RngCore;
+use serde_json::json;
+use tracing::{info, warn};
+
+use crate::ip_mapping::list_mapped_ips;
+use crate::treeguard::actor::{adopt_runtime_virtualized_nodes, runtime_virtualized_nodes};
+use crate::urgent;
+use sync::{BatchRecorder, LocalState, SentState, mapping_plan};
+use wire::{
+    CHALLENGE_BYTES, FrameCodec, HaMessage, MAX_HELLO_BYTES, PROTOCOL_VERSION, SyncedLayer,
+};
+
+/// How often the active shaper checks its state for changes.
+const STATE_CHECK_INTERVAL: Duration = Duration::from_secs(5);
+/// Delay between connection attempts from the active shaper.
+const RECONNECT_DELAY: Duration = Duration::from_secs(5);
+/// How long the standby waits for a new connection to authenticate.
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(10);
+/// Connections the standby will authenticate at once; further ones are closed immediately.
+const MAX_HANDSHAKES: usize = 16;
+const LAYERS: [OverrideLayer; 3] = [
+    OverrideLayer::Operator,
+    OverrideLayer::Stormguard,
+    OverrideLayer::Treeguard,
+];
+
+static HA_ENABLED: AtomicBool = AtomicBool::new(false);
+static STANDBY: AtomicBool = AtomicBool::new(false);
+static PROMOTED: AtomicBool = AtomicBool::new(false);
+/// Connections accepted by the standby that have not authenticated yet.
+static HANDSHAKES: AtomicUsize = AtomicUsize::new(0);
+/// When a peer last connected without authenticating (timed out, wrong secret or refused).
+static LAST_UNVERIFIED_PEER: AtomicU64 = AtomicU64::new(0);
+/// Identifies the newest authenticated connection; older ones stop applying state.
+static RECEIVER_GENERATION: AtomicU64 = AtomicU64::new(0);
+static BATCHES: Lazy<Mutex<BatchRecorder>> = Lazy::new(|| Mutex::new(BatchRecorder::default()));
+static LINK: Lazy<Mutex<LinkStatus>> = Lazy::new(|| Mutex::new(LinkStatus::default()));
+/// TreeGuard's virtualized nodes as last received from the active shaper.
+static SYNCED_VIRTUALIZED: Lazy<Mutex<Vec<String>>> = Lazy::new(|| Mutex::new(Vec::new()));
+/// StormGuard's site rates as last received from the active shaper.
+static SYNCED_STORMGUARD: Lazy<Mutex<Vec<(String, u64, u64)>>> =
+    Lazy::new(|| Mutex::new(Vec::new()));
+
+#[derive(Default)]
+struct LinkStatus {
+    connected: bool,
+    peer_name: Option<String>,
+    last_contact: Option<u64>,
+    bakery_commands: usize,
+    ip_mappings: usize,
+    virtualized_nodes: usize,
+    stormguard_sites: usize,
+    last_error: Option<String>,
+}
+
+fn now() -> u64 {
+    unix_now().unwrap_or_default()
+}
+
+fn record_error(error: String) {
+    warn!("HA: {error}");
+    let mut link = LINK.lock();
+    link.connected = false;
+    link.last_error = Some(error);
+}
+
+/// Starts the HA threads for the configured role. Must run before StormGuard starts, so a
+/// standby never makes adjustments of its own.
+pub fn start_ha() {
+    let config = match lqos_config::load_config() {
+        Ok(config) => config,
+        Err(e) => {
+            warn!("Unable to load config for high availability: {e:?}");
+            return;
+        }
+    };
+    let ha = config.high_availability.clone();
+    if !ha.enabled {
+        return;
+    }
+    HA_ENABLED.store(true, Ordering::Relaxed);
+    match ha.role {
+        HaRole::Active => {
+            info!(
+                "HA: running as the active shaper, streaming state to {}",
+                ha.peer
+            );
+            spawn("ha_sender", run_sender);
+        }
+        HaRole::Standby => {
+            info!(
+                "HA: running as the standby shaper, listening on {}",
+                ha.listen
+            );
+            STANDBY.store(true, Ordering::Relaxed);
+            STORMGUARD_PAUSED.store(true, Ordering::Relaxed);
+            let listen_config = ha.clone();
+            spawn("ha_listener", move || run_listener(listen_config));
+            spawn("ha_monitor", move || run_monitor(ha));
+        }
+    }
+}
+
+fn spawn(name: &str, f: impl FnOnce() + Send + 'static) {
+    if let Err(e) = std::thread::Builder::new().name(name.to_string()).spawn(f) {
+        warn!("Unable to start {name} thread: {e}");
+    }
+}
+
+fn is_standby() -> bool {
+    STANDBY.load(Ordering::Relaxed) && !PROMOTED.load(Ordering::Relaxed)
+}
+
+/// Why automatic adjustments are paused on this shaper, if they are.
+pub(crate) fn standby_pause_reason() -> Option<String> {
+    is_standby().then(|| "this shaper is the HA standby".to_string())
+}
+
+/// Records Bakery batches passing through the bus so they can be sent to the standby.
+pub(crate) fn observe_bus_request(request: &BusRequest) {
+    if HA_ENABLED.load(Ordering::Relaxed) {
+        BATCHES.lock().observe(request);
+    }
+}
+
+/// Current HA state for the bus.
+pub(crate) fn ha_status() -> HaStatus {
+    let role = lqos_config::load_config()
+        .map(|c| c.high_availability.role)
+        .unwrap_or(HaRole::Active);
+    let link = LINK.lock();
+    HaStatus {
+        enabled: HA_ENABLED.load(Ordering::Relaxed),
+        role: match role {
+            HaRole::Active => "active".to_string(),
+            HaRole::Standby => "standby".to_string(),
+        },
+        promoted: PROMOTED.load(Ordering::Relaxed),
+        connected: link.connected,
+        peer_name: link.peer_name.clone(),
+        last_contact_unix: link.last_contact,
+        bakery_commands: link.bakery_commands,
+        ip_mappings: link.ip_mappings,
+        virtualized_nodes: link.virtualized_nodes,
+        stormguard_sites: link.stormguard_sites,
+        last_error: link.last_error.clone(),
+    }
+}
+
+// Active side
+
+/// Streams state to the standby whenever this shaper is configured as active. A promoted
+/// standby runs this too, and starts streaming once its role is changed to `active`.
+fn run_sender() {
+    loop {
+        match lqos_config::load_config() {
+            Ok(config)
+                if config.high_availability.enabled
+                    && config.high_availability.role == HaRole::Active =>
+            {
+                let ha = &config.high_availability;
+                if let Err(e) = stream_to_standby(ha, &config.node_id, &config.node_name) {
+                    record_error(e);
+                }
+            }
+            Ok(_) => {}
+            Err(e) => warn!("Unable to load config for high availability: {e:?}"),
+        }
+        LINK.lock().connected = false;
+        std::thread::sleep(RECONNECT_DELAY);
+    }
+}
+
+fn stream_to_standby(
+    ha: &HighAvailabilityConfig,
+    node_id: &str,
+    node_name: &str,
+) -> Result<(), String> {
+    let address = ha
+        .peer
+        .to_socket_addrs()
+        .map_err(|e| format!("unable to resolve {}: {e}", ha.peer))?
+        .next()
+        .ok_or_else(|| format!("{} has no address", ha.peer))?;
+    let mut stream = TcpStream::connect_timeout(&address, RECONNECT_DELAY)
+        .map_err(|e| format!("unable to connect to {}: {e}", ha.peer))?;
+    stream
+        .set_read_timeout(Some(RECONNECT_DELAY))
+        .map_err(|e| e.to_string())?;
+    let _ = stream.set_nodelay(true);
+
+    let mut challenge = [0u8; CHALLENGE_BYTES];
+    stream
+        .read_exact(&mut challenge)
+        .map_err(|e| format!("no challenge from {}: {e}", ha.peer))?;
+    let mut codec = FrameCodec::new(&ha.shared_secret, challenge);
+    codec.write(
+        &mut stream,
+        &HaMessage::Hello {
+            protocol: PROTOCOL_VERSION,
+            node_id: node_id.to_string(),
+            node_name: node_name.to_string(),
+        },
+    )?;
+    info!("HA: connected to standby {}", ha.peer);
+    {
+        let mut link = LINK.lock();
+        link.connected = true;
+        link.peer_name = Some(ha.peer.clone());
+        link.last_error = None;
+    }
+
+    let heartbeat = Duration::from_secs(ha.heartbeat_interval_seconds);
+    let mut sent = SentState::default();
+    let mut last_check: Option<Instant> = None;
+    loop {
+        if last_check.is_none_or(|t| t.elapsed() >= STATE_CHECK_INTERVAL) {
+            // Reconnect with the new settings if the HA section changed.
+            let current = lqos_config::load_config().map_err(|e| format!("{e:?}"))?;
+            if current.high_availability != *ha {
+                return Ok(());
+            }
+            let local = local_state();
+            {
+                let mut link = LINK.lock();
+                link.bakery_commands = local.batch.len();
+                link.ip_mappings = local.mappings.len();
+                link.virtualized_nodes = local.treeguard.len();
+                link.stormguard_sites = local.stormguard.len();
+            }
+            for message in sent.changes(local) {
+                codec.write(&mut stream, &message)?;
+            }
+            last_check = Some(Instant::now());
+        }
+        codec.write(&mut stream, &HaMessage::Heartbeat)?;
+        LINK.lock().last_contact = Some(now());
+        std::thread::sleep(heartbeat);
+    }
+}
+
+fn local_state() -> LocalState {
+    let mut layers = Vec::new();
+    for layer in LAYERS {
+        match OverrideStore::load_layer(layer).map(|file| serde_json::to_string(&file)) {
+            Ok(Ok(json)) => layers.push(SyncedLayer { layer, json }),
+            Ok(Err(e)) => warn!("HA: unable to serialize {layer:?} overrides: {e}"),
+            Err(e) => warn!("HA: unable to load {layer:?} overrides: {e:?}"),
+        }
+    }
+    let ((batch_generation, batch), (live_generation, live_speeds)) = {
+        let batches = BATCHES.lock();
+        (batches.committed(), batches.live_speeds())
+    };
+    LocalState {
+        layers,
+        batch_generation,
+        batch,
+        live_generation,
+        live_speeds,
+        mappings: current_mappings(),
+        stormguard: STORMGUARD_STATS.lock().clone(),
+        treeguard: runtime_virtualized_nodes(),
+    }
+}
+
+fn current_mappings() -> Vec<IpMapping> {
+    match list_mapped_ips() {
+        BusResponse::MappedIps(mappings) => mappings,
+        _ => Vec::new(),
+    }
+}
+
+// Standby side
+
+fn run_listener(ha: HighAvailabilityConfig) {
+    let listener = match TcpListener::bind(&ha.listen) {
+        Ok(listener) => listener,
+        Err(e) => {
+            record_error(format!("unable to listen on {}: {e}", ha.listen));
+            return;
+        }
+    };
+    for stream in listener.incoming() {
+        let stream = match stream {
+            Ok(stream) => stream,
+            Err(e) => {
+                warn!("HA: failed to accept connection: {e}");
+                continue;
+            }
+        };
+        let from = stream
+            .peer_addr()
+            .map(|a| a.to_string())
+            .unwrap_or_default();
+        if PROMOTED.load(Ordering::Relaxed) {
+            urgent::submit(
+                UrgentSource::System,
+                UrgentSeverity::Error,
+                "HA_PEER_STILL_ACTIVE".to_string(),
+                format!(
+                    "This shaper has taken over, but {from} is still trying to stream state as the active shaper. Make sure only one shaper is shaping traffic."
+                ),
+                None,
+                Some("ha_peer_still_active".to_string()),
+            );
+            continue;
+        }
+        let Some(pending) = PendingHandshake::start() else {
+            LAST_UNVERIFIED_PEER.store(now(), Ordering::Relaxed);
+            warn!("HA: too many unauthenticated connections; closing {from}");
+            continue;
+        };
+        let ha = ha.clone();
+        spawn("ha_receiver", move || {
+            handle_connection(stream, pending, &from, &ha)
+        });
+    }
+}
+
+/// Counts a connection in [`HANDSHAKES`] until it authenticates or is dropped.
+struct PendingHandshake;
+
+impl PendingHandshake {
+    fn start() -> Option<Self> {
+        HANDSHAKES
+            .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |n| {
+                (n < MAX_HANDSHAKES).then_some(n + 1)
+            })
+            .ok()
+            .map(|_| Self)
+    }
+}
+
+impl Drop for PendingHandshake {
+    fn drop(&mut self) {
+        HANDSHAKES.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+fn handle_connection(
+    stream: TcpStream,
+    pending: PendingHandshake,
+    from: &str,
+    ha: &HighAvailabilityConfig,
+) {
+    let (codec, reader, node_name) = match authenticate(stream, ha) {
+        Ok(session) => session,
+        Err(e) => {
+            LAST_UNVERIFIED_PEER.store(now(), Ordering::Relaxed);
+            warn!("HA: rejected connection from {from}: {e}");
+            return;
+        }
+    };
+    drop(pending);
+
+    let generation = RECEIVER_GENERATION.fetch_add(1, Ordering::Relaxed) + 1;
+    info!("HA: receiving state from active shaper {node_name}");
+    {
+        let mut link = LINK.lock();
+        link.connected = true;
+        link.peer_name = Some(node_name);
+        link.last_contact = Some(now());
+        link.last_error = None;
+    }
+
+    let result = receive_from_active(codec, reader, generation);
+    if RECEIVER_GENERATION.load(Ordering::Relaxed) == generation {
+        if let Err(e) = result {
+            record_error(format!("connection from {from} ended: {e}"));
+        }
+        LINK.lock().connected = false;
+    } else {
+        info!("HA: connection from {from} was replaced by a newer one");
+    }
+}
+
+/// Sends the challenge and reads the peer's `Hello`, under a short timeout and a small size
+/// limit. Returns the verified codec, the reader and the peer's node name.
+fn authenticate(
+    mut stream: TcpStream,
+    ha: &HighAvailabilityConfig,
+) -> Result<(FrameCodec, BufReader<TcpStream>, String), String> {
+    stream
+        .set_read_timeout(Some(HANDSHAKE_TIMEOUT))
+        .map_err(|e| e.to_string())?;
+    stream
+        .set_write_timeout(Some(HANDSHAKE_TIMEOUT))
+        .map_err(|e| e.to_string())?;
+    let mut challenge = [0u8; CHALLENGE_BYTES];
+    rand::thread_rng().fill_bytes(&mut challenge);
+    stream.write_all(&challenge).map_err(|e| e.to_string())?;
+    let mut codec = FrameCodec::new(&ha.shared_secret, challenge);
+    let mut reader = BufReader::new(stream);
+
+    let HaMessage::Hello {
+        protocol,
+        node_name,
+        ..
+    } = codec.read_bounded(&mut reader, MAX_HELLO_BYTES)?
+    else {
+        return Err("peer did not introduce itself".to_string());
+    };
+    if protocol != PROTOCOL_VERSION {
+        return Err(format!(
+            "peer speaks HA protocol {protocol}, this shaper speaks {PROTOCOL_VERSION}"
+        ));
+    }
+    reader
+        .get_ref()
+        .set_read_timeout(Some(Duration::from_secs(ha.failover_timeout_seconds)))
+        .map_err(|e| e.to_string())?;
+    Ok((codec, reader, node_name))
+}
+
+fn receive_from_active(
+    mut codec: FrameCodec,
+    mut reader: BufReader<TcpStream>,
+    generation: u64,
+) -> Result<(), String> {
+    loop {
+        let message = codec.read(&mut reader)?;
+        if PROMOTED.load(Ordering::Relaxed) {
+            return Err("this shaper has taken over; ignoring the former active shaper".into());
+        }
+        if RECEIVER_GENERATION.load(Ordering::Relaxed) != generation {
+            return Ok(());
+        }
+        LINK.lock().last_contact = Some(now());
+        apply(message);
+    }
+}
+
+/// Why automatic failover should wait even though no authenticated frame has arrived: an
+/// unauthenticated peer is connected, or was within `window_seconds`.
+fn unverified_peer_activity(window_seconds: u64) -> Option<String> {
+    let pending = HANDSHAKES.load(Ordering::Relaxed);
+    if pending > 0 {
+        return Some(format!(
+            "{pending} connection(s) have not authenticated yet"
+        ));
+    }
+    let last = LAST_UNVERIFIED_PEER.load(Ordering::Relaxed);
+    (last > 0 && now().saturating_sub(last) < window_seconds).then(|| {
+        "a peer failed to authenticate recently (check shared_secret on both shapers)".to_string()
+    })
+}
+
+fn apply(message: HaMessage) {
+    match message {
+        HaMessage::Hello { .. } | HaMessage::Heartbeat => {}
+        HaMessage::OverrideLayers(layers) => {
+            let peer = LINK.lock().peer_name.clone().unwrap_or_default();
+            for synced in layers {
+                if let Err(e) = apply_layer(&synced, &peer) {
+                    warn!("HA: unable to apply {:?} overrides: {e}", synced.layer);
+                }
+            }
+        }
+        HaMessage::BakeryBatch(batch) => {
+            info!("HA: replaying Bakery batch of {} commands", batch.len());
+            replay(&batch);
+            LINK.lock().bakery_commands = batch.len();
+        }
+        HaMessage::LiveSiteSpeeds(speeds) => replay(&speeds),
+        HaMessage::IpMappings(mappings) => {
+            replay(&mapping_plan(&current_mappings(), &mappings));
+            LINK.lock().ip_mappings = mappings.len();
+        }
+        HaMessage::Stormguard(rates) => {
+            LINK.lock().stormguard_sites = rates.len();
+            *SYNCED_STORMGUARD.lock() = rates;
+        }
+        HaMessage::Treeguard(nodes) => {
+            LINK.lock().virtualized_nodes = nodes.len();
+            *SYNCED_VIRTUALIZED.lock() = nodes;
+        }
+    }
+}
+
+fn apply_layer(synced: &SyncedLayer, peer: &str) -> Result<(), String> {
+    let incoming: OverrideFile = serde_json::from_str(&synced.json).map_err(|e| e.to_string())?;
+    let local = OverrideStore::load_layer(synced.layer).map_err(|e| format!("{e:?}"))?;
+    if serde_json::to_string(&local).map_err(|e| e.to_string())? == synced.json {
+        return Ok(());
+    }
+    let ctx = SaveContext::new(
+        ChangeAuthor::Integration {
+            name: "ha-sync".to_string(),
+        },
+        format!("Synchronized from HA peer {peer}"),
+    );
+    OverrideStore::save_layer(synced.layer, &incoming, &ctx).map_err(|e| format!("{e:?}"))
+}
+
+fn replay(requests: &[BusRequest]) {
+    let mut responses = Vec::with_capacity(requests.len());
+    crate::handle_bus_requests(requests, &mut responses);
+    let failures: Vec<&String> = responses
+        .iter()
+        .filter_map(|r| match r {
+            BusResponse::Fail(e) => Some(e),
+            _ => None,
+        })
+        .collect();
+    if let Some(first) = failures.first() {
+        warn!(
+            "HA: {} of {} synchronized commands failed, first: {first}",
+            failures.len(),
+            requests.len()
+        );
+    }
+}
+
+fn run_monitor(ha: HighAvailabilityConfig) {
+    let mut warned = false;
+    loop {
+        std::thread::sleep(Duration::from_secs(1));
+        if let Ok(config) = lqos_config::load_config()
+            && config.high_availability.role == HaRole::Active
+        {
+            promote("its configured role was changed to active".to_string());
+            return;
+        }
+        let Some(last_contact) = LINK.lock().last_contact else {
+            continue;
+        };
+        let silent = now().saturating_sub(last_contact);
+        if silent < ha.failover_timeout_seconds {
+            warned = false;
+            continue;
+        }
+        if ha.auto_failover {
+            match unverified_peer_activity(ha.failover_timeout_seconds) {
+                None => {
+                    promote(format!(
+                        "the active shaper has been silent for {silent} seconds"
+                    ));
+                    return;
+                }
+                Some(activity) => {
+                    if !warned {
+                        warned = true;
+                        urgent::submit(
+                            UrgentSource::System,
+                            UrgentSeverity::Warning,
+                            "HA_PEER_UNVERIFIED".to_string(),
+                            format!(
+                                "The active shaper has been silent for {silent} seconds, but {activity}. Automatic failover is on hold; set high_availability.role to \"active\" on this shaper to take over."
+                            ),
+                            None,
+                            Some("ha_peer_unverified".to_string()),
+                        );
+                    }
+                    continue;
+                }
+            }
+        }
+        if !warned {
+            warned = true;
+            urgent::submit(
+                UrgentSource::System,
+                UrgentSeverity::Warning,
+                "HA_PEER_SILENT".to_string(),
+                format!(
+                    "The active shaper has been silent for {silent} seconds. Automatic failover is off; set high_availability.role to \"active\" on this shaper to take over."
+                ),
+                None,
+                Some("ha_peer_silent".to_string()),
+            );
+        }
+    }
+}
+
+fn promote(reason: String) {
+    if PROMOTED.swap(true, Ordering::Relaxed) {
+        return;
+    }
+    warn!("HA: taking over shaping because {reason}");
+    lqos_stormguard::seed_site_rates(SYNCED_STORMGUARD.lock().clone());
+    STORMGUARD_PAUSED.store(false, Ordering::Relaxed);
+    spawn("ha_sender", run_sender);
+    let nodes = SYNCED_VIRTUALIZED.lock().clone();
+    if !nodes.is_empty() && !adopt_runtime_virtualized_nodes(nodes) {
+        warn!("HA: TreeGuard is not running; synchronized virtualized nodes were not adopted");
+    }
+    let peer = LINK.lock().peer_name.clone();
+    urgent::submit(
+        UrgentSource::System,
+        UrgentSeverity::Error,
+        "HA_TAKEOVER".to_string(),
+        format!("This standby shaper has taken over shaping because {reason}."),
+        peer.map(|peer| json!({ "peer": peer }).to_string()),
+        Some("ha_takeover".to_string()),
+    );
+}
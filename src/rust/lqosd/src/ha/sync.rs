@@ -0,0 +1,308 @@
+//! Tracking what the active shaper has to send, and turning received state into bus requests.
+
+use std::collections::BTreeMap;
+use std::sync::Arc;
+
+use lqos_bus::{BusRequest, IpMapping};
+
+use super::wire::{HaMessage, SyncedLayer};
+
+/// Records Bakery batches as they pass through the bus, keeping the last committed one and the
+/// latest live speed change for each site made since.
+#[derive(Default)]
+pub(super) struct BatchRecorder {
+    staging: Option<Vec<BusRequest>>,
+    committed: Arc<Vec<BusRequest>>,
+    generation: u64,
+    live_speeds: BTreeMap<i64, BusRequest>,
+    live_generation: u64,
+}
+
+impl BatchRecorder {
+    pub(super) fn observe(&mut self, request: &BusRequest) {
+        match request {
+            BusRequest::BakeryStart => {
+                self.staging = Some(vec![request.clone()]);
+            }
+            BusRequest::BakeryMqSetup { .. }
+            | BusRequest::BakeryAddSite { .. }
+            | BusRequest::BakeryAddCircuit { .. } => {
+                if let Some(staging) = self.staging.as_mut() {
+                    staging.push(request.clone());
+                }
+            }
+            BusRequest::BakeryCommit => {
+                if let Some(mut staging) = self.staging.take() {
+                    staging.push(request.clone());
+                    self.committed = Arc::new(staging);
+                    self.generation += 1;
+                    // The new batch carries the planned rates again.
+                    self.live_speeds.clear();
+                    self.live_generation += 1;
+                }
+            }
+            BusRequest::BakeryChangeSiteSpeedLive { site_hash, .. } => {
+                // Live changes only make sense on top of a committed batch.
+                if !self.committed.is_empty() && self.live_speeds.get(site_hash) != Some(request) {
+                    self.live_speeds.insert(*site_hash, request.clone());
+                    self.live_generation += 1;
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// The last committed batch and a counter that changes whenever it does.
+    pub(super) fn committed(&self) -> (u64, Arc<Vec<BusRequest>>) {
+        (self.generation, self.committed.clone())
+    }
+
+    /// The latest live speed change per site since the last commit, and a counter that
+    /// changes whenever they do.
+    pub(super) fn live_speeds(&self) -> (u64, Vec<BusRequest>) {
+        (
+            self.live_generation,
+            self.live_speeds.values().cloned().collect(),
+        )
+    }
+}
+
+/// A snapshot of the active shaper's state.
+pub(super) struct LocalState {
+    pub(super) layers: Vec<SyncedLayer>,
+    pub(super) batch_generation: u64,
+    pub(super) batch: Arc<Vec<BusRequest>>,
+    pub(super) live_generation: u64,
+    pub(super) live_speeds: Vec<BusRequest>,
+    pub(super) mappings: Vec<IpMapping>,
+    pub(super) stormguard: Vec<(String, u64, u64)>,
+    pub(super) treeguard: Vec<String>,
+}
+
+/// What has already been sent on the current connection.
+#[derive(Default)]
+pub(super) struct SentState {
+    layers: Option<Vec<SyncedLayer>>,
+    batch_generation: Option<u64>,
+    live_generation: Option<u64>,
+    mappings: Option<Vec<IpMapping>>,
+    stormguard: Option<Vec<(String, u64, u64)>>,
+    treeguard: Option<Vec<String>>,
+}
+
+impl SentState {
+    /// Returns the messages needed to bring the standby up to date, and records them as sent.
+    /// Layers go first so that a replayed batch already sees the matching overrides.
+    pub(super) fn changes(&mut self, local: LocalState) -> Vec<HaMessage> {
+        let mut messages = Vec::new();
+        if self.layers.as_ref() != Some(&local.layers) {
+            messages.push(HaMessage::OverrideLayers(local.layers.clone()));
+            self.layers = Some(local.layers);
+        }
+        if self.batch_generation != Some(local.batch_generation) && !local.batch.is_empty() {
+            messages.push(HaMessage::BakeryBatch(local.batch.as_ref().clone()));
+            self.batch_generation = Some(local.batch_generation);
+        }
+        if self.live_generation != Some(local.live_generation) && !local.live_speeds.is_empty() {
+            messages.push(HaMessage::LiveSiteSpeeds(local.live_speeds));
+            self.live_generation = Some(local.live_generation);
+        }
+        if self.mappings.as_ref() != Some(&local.mappings) {
+            messages.push(HaMessage::IpMappings(local.mappings.clone()));
+            self.mappings = Some(local.mappings);
+        }
+        if self.stormguard.as_ref() != Some(&local.stormguard) {
+            messages.push(HaMessage::Stormguard(local.stormguard.clone()));
+            self.stormguard = Some(local.stormguard);
+        }
+        if self.treeguard.as_ref() != Some(&local.treeguard) {
+            messages.push(HaMessage::Treeguard(local.treeguard.clone()));
+            self.treeguard = Some(local.treeguard);
+        }
+        messages
+    }
+}
+
+/// The CIDR string to map for an entry returned by `ListIpFlow`. IPv4 prefixes are stored
+/// offset by 96 (as IPv4-mapped IPv6).
+pub(super) fn mapping_cidr(mapping: &IpMapping) -> String {
+    let prefix = if mapping.ip_address.contains(':') {
+        mapping.prefix_length
+    } else {
+        mapping.prefix_length.saturating_sub(96)
+    };
+    format!("{}/{prefix}", mapping.ip_address)
+}
+
+/// Bus requests that turn the `current` IP mappings into `desired`. Entries without circuit
+/// or device ids cannot be re-created through the bus and are skipped.
+pub(super) fn mapping_plan(current: &[IpMapping], desired: &[IpMapping]) -> Vec<BusRequest> {
+    let key = |m: &IpMapping| (m.ip_address.clone(), m.prefix_length);
+    let desired: Vec<&IpMapping> = desired
+        .iter()
+        .filter(|m| m.circuit_id != 0 && m.device_id != 0)
+        .collect();
+    let removals = current
+        .iter()
+        .any(|c| !desired.iter().any(|d| key(d) == key(c)));
+
+    let mut plan = Vec::new();
+    if removals {
+        // Single mappings can't be deleted by CIDR, so start again from an empty table.
+        plan.push(BusRequest::ClearIpFlow);
+    }
+    for mapping in desired {
+        if !removals && current.contains(mapping) {
+            continue;
+        }
+        plan.push(BusRequest::MapIpToFlow {
+            ip_address: mapping_cidr(mapping),
+            tc_handle: mapping.tc_handle,
+            cpu: mapping.cpu,
+            circuit_id: mapping.circuit_id,
+            device_id: mapping.device_id,
+            upload: false,
+        });
+    }
+    if !plan.is_empty() {
+        plan.push(BusRequest::ClearHotCache);
+    }
+    plan
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn mapping(ip: &str, prefix: u32, circuit_id: u64) -> IpMapping {
+        IpMapping {
+            ip_address: ip.to_string(),
+            prefix_length: prefix,
+            tc_handle: lqos_bus::TcHandle::from_string("1:10").unwrap(),
+            cpu: 1,
+            circuit_id,
+            device_id: circuit_id,
+        }
+    }
+
+    fn local(batch_generation: u64, batch: Vec<BusRequest>) -> LocalState {
+        LocalState {
+            layers: Vec::new(),
+            batch_generation,
+            batch: Arc::new(batch),
+            live_generation: 0,
+            live_speeds: Vec::new(),
+            mappings: vec![mapping("10.0.0.1", 128, 1)],
+            stormguard: Vec::new(),
+            treeguard: vec!["Tower A".to_string()],
+        }
+    }
+
+    fn live_speed(site_hash: i64, download_max: f32) -> BusRequest {
+        BusRequest::BakeryChangeSiteSpeedLive {
+            site_hash,
+            download_bandwidth_min: 10.0,
+            upload_bandwidth_min: 10.0,
+            download_bandwidth_max: download_max,
+            upload_bandwidth_max: 100.0,
+        }
+    }
+
+    #[test]
+    fn recorder_keeps_last_committed_batch_and_live_changes() {
+        let mut recorder = BatchRecorder::default();
+        recorder.observe(&BusRequest::BakeryStart);
+        recorder.observe(&BusRequest::Ping);
+        assert!(recorder.committed().1.is_empty(), "uncommitted batch");
+        recorder.observe(&live_speed(42, 100.0));
+        assert!(
+            recorder.live_speeds().1.is_empty(),
+            "no batch to apply it to"
+        );
+        recorder.observe(&BusRequest::BakeryCommit);
+        let (_, batch) = recorder.committed();
+        assert_eq!(
+            batch.as_slice(),
+            &[BusRequest::BakeryStart, BusRequest::BakeryCommit]
+        );
+
+        let (live_generation, _) = recorder.live_speeds();
+        recorder.observe(&live_speed(42, 100.0));
+        let (next_live_generation, live) = recorder.live_speeds();
+        assert_ne!(live_generation, next_live_generation);
+        assert_eq!(live, vec![live_speed(42, 100.0)]);
+
+        recorder.observe(&BusRequest::BakeryStart);
+        assert_eq!(
+            recorder.committed().1.len(),
+            2,
+            "in-progress batch doesn't replace it"
+        );
+        recorder.observe(&BusRequest::BakeryCommit);
+        assert!(
+            recorder.live_speeds().1.is_empty(),
+            "cleared by the new batch"
+        );
+    }
+
+    #[test]
+    fn repeated_live_changes_keep_only_the_latest_per_site() {
+        let mut recorder = BatchRecorder::default();
+        recorder.observe(&BusRequest::BakeryStart);
+        recorder.observe(&BusRequest::BakeryCommit);
+        let (batch_generation, _) = recorder.committed();
+
+        for step in 0..100 {
+            recorder.observe(&live_speed(1, 50.0 + step as f32));
+            recorder.observe(&live_speed(2, 80.0));
+        }
+        let (generation, batch) = recorder.committed();
+        assert_eq!(
+            generation, batch_generation,
+            "live changes don't resend the batch"
+        );
+        assert_eq!(batch.len(), 2);
+        let (live_generation, live) = recorder.live_speeds();
+        assert_eq!(live, vec![live_speed(1, 149.0), live_speed(2, 80.0)]);
+
+        recorder.observe(&live_speed(2, 80.0));
+        assert_eq!(recorder.live_speeds().0, live_generation, "unchanged speed");
+    }
+
+    #[test]
+    fn sent_state_only_resends_changes() {
+        let mut sent = SentState::default();
+        assert_eq!(
+            sent.changes(local(0, Vec::new())).len(),
+            4,
+            "all but the empty batch"
+        );
+        assert!(sent.changes(local(0, Vec::new())).is_empty());
+        let changes = sent.changes(local(1, vec![BusRequest::BakeryStart]));
+        assert_eq!(
+            changes,
+            vec![HaMessage::BakeryBatch(vec![BusRequest::BakeryStart])]
+        );
+    }
+
+    #[test]
+    fn mapping_plan_adds_changes_and_rebuilds_on_removal() {
+        let a = mapping("10.0.0.1", 128, 1);
+        let b = mapping("10.0.1.0", 120, 2);
+        let unsynced = mapping("10.0.2.1", 128, 0);
+
+        assert!(mapping_plan(&[a.clone()], &[a.clone()]).is_empty());
+
+        let plan = mapping_plan(&[a.clone()], &[a.clone(), b.clone(), unsynced]);
+        assert_eq!(plan.len(), 2);
+        assert!(matches!(
+            &plan[0],
+            BusRequest::MapIpToFlow { ip_address, .. } if ip_address == "10.0.1.0/24"
+        ));
+        assert_eq!(plan[1], BusRequest::ClearHotCache);
+
+        let plan = mapping_plan(&[a, b.clone()], &[b]);
+        assert_eq!(plan.first(), Some(&BusRequest::ClearIpFlow));
+        assert_eq!(plan.len(), 3);
+    }
+}
@@ -0,0 +1,248 @@
+//! Frames exchanged between HA peers.
+//!
+//! On connect, the standby sends a random 32-byte challenge. Every frame the active shaper sends
+//! after that is `len: u32 BE | seq: u64 BE | CBOR payload | HMAC-SHA256 tag`, with the tag taken
+//! over `challenge | seq | payload` using the shared secret. Sequence numbers start at zero on
+//! each connection, so frames cannot be replayed, reordered or carried over from another
+//! connection. The first frame must be a small `Hello`, so an unauthenticated peer cannot make
+//! the standby read or allocate a large payload.
+
+use std::io::{Read, Write};
+
+use hmac::{Hmac, Mac};
+use lqos_bus::{BusRequest, IpMapping};
+use lqos_overrides::OverrideLayer;
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+
+type HmacSha256 = Hmac<Sha256>;
+
+/// Bumped when [`HaMessage`] changes incompatibly.
+pub(super) const PROTOCOL_VERSION: u32 = 2;
+
+/// Length of the per-connection challenge.
+pub(super) const CHALLENGE_BYTES: usize = 32;
+
+/// Largest `Hello` payload the standby reads before the peer has proven it knows the secret.
+pub(super) const MAX_HELLO_BYTES: usize = 4 * 1024;
+
+const TAG_BYTES: usize = 32;
+/// Matches the bus frame limit, which already bounds the Bakery batches synchronized here.
+const MAX_PAYLOAD_BYTES: usize = 64 * 1024 * 1024;
+
+/// One override layer, as the JSON it is stored in.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub(super) struct SyncedLayer {
+    pub(super) layer: OverrideLayer,
+    pub(super) json: String,
+}
+
+/// Messages sent from the active shaper to the standby.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub(super) enum HaMessage {
+    /// First message on every connection.
+    Hello {
+        protocol: u32,
+        node_id: String,
+        node_name: String,
+    },
+    /// Sent when nothing else has changed.
+    Heartbeat,
+    /// All three override layers.
+    OverrideLayers(Vec<SyncedLayer>),
+    /// The last committed Bakery batch, `BakeryStart` through `BakeryCommit`.
+    BakeryBatch(Vec<BusRequest>),
+    /// The latest `BakeryChangeSiteSpeedLive` for each site changed since that batch.
+    LiveSiteSpeeds(Vec<BusRequest>),
+    /// Every XDP IP mapping.
+    IpMappings(Vec<IpMapping>),
+    /// StormGuard's current `(site, download Mbps, upload Mbps)` rates.
+    Stormguard(Vec<(String, u64, u64)>),
+    /// Nodes TreeGuard has runtime-virtualized.
+    Treeguard(Vec<String>),
+}
+
+/// Signs (active side) or verifies (standby side) the frames of one connection.
+pub(super) struct FrameCodec {
+    secret: Vec<u8>,
+    challenge: [u8; CHALLENGE_BYTES],
+    next_seq: u64,
+}
+
+impl FrameCodec {
+    pub(super) fn new(secret: &str, challenge: [u8; CHALLENGE_BYTES]) -> Self {
+        Self {
+            secret: secret.as_bytes().to_vec(),
+            challenge,
+            next_seq: 0,
+        }
+    }
+
+    fn mac(&self, seq: u64, payload: &[u8]) -> Result<HmacSha256, String> {
+        let mut mac = HmacSha256::new_from_slice(&self.secret).map_err(|e| e.to_string())?;
+        mac.update(&self.challenge);
+        mac.update(&seq.to_be_bytes());
+        mac.update(payload);
+        Ok(mac)
+    }
+
+    /// Encodes and signs the next frame.
+    pub(super) fn encode(&mut self, message: &HaMessage) -> Result<Vec<u8>, String> {
+        let payload = serde_cbor::to_vec(message).map_err(|e| e.to_string())?;
+        if payload.len() > MAX_PAYLOAD_BYTES {
+            return Err(format!("HA frame of {} bytes is too large", payload.len()));
+        }
+        let seq = self.next_seq;
+        let tag = self.mac(seq, &payload)?.finalize().into_bytes();
+        self.next_seq += 1;
+
+        let mut frame = Vec::with_capacity(12 + payload.len() + TAG_BYTES);
+        frame.extend_from_slice(&(payload.len() as u32).to_be_bytes());
+        frame.extend_from_slice(&seq.to_be_bytes());
+        frame.extend_from_slice(&payload);
+        frame.extend_from_slice(&tag);
+        Ok(frame)
+    }
+
+    /// Encodes, signs and writes the next frame.
+    pub(super) fn write<W: Write>(
+        &mut self,
+        writer: &mut W,
+        message: &HaMessage,
+    ) -> Result<(), String> {
+        let frame = self.encode(message)?;
+        writer.write_all(&frame).map_err(|e| e.to_string())?;
+        writer.flush().map_err(|e| e.to_string())
+    }
+
+    /// Reads the next frame, rejecting it unless it is signed and in sequence.
+    pub(super) fn read<R: Read>(&mut self, reader: &mut R) -> Result<HaMessage, String> {
+        self.read_bounded(reader, MAX_PAYLOAD_BYTES)
+    }
+
+    /// Reads the next frame, refusing payloads over `max_payload` bytes. The payload buffer
+    /// grows as bytes arrive, so a peer cannot reserve memory with the length prefix alone.
+    pub(super) fn read_bounded<R: Read>(
+        &mut self,
+        reader: &mut R,
+        max_payload: usize,
+    ) -> Result<HaMessage, String> {
+        let mut header = [0u8; 12];
+        reader.read_exact(&mut header).map_err(|e| e.to_string())?;
+        let len = u32::from_be_bytes([header[0], header[1], header[2], header[3]]) as usize;
+        let mut seq_bytes = [0u8; 8];
+        seq_bytes.copy_from_slice(&header[4..]);
+        let seq = u64::from_be_bytes(seq_bytes);
+        if len > max_payload {
+            return Err(format!("HA frame of {len} bytes is too large"));
+        }
+        let mut payload = Vec::new();
+        reader
+            .by_ref()
+            .take(len as u64)
+            .read_to_end(&mut payload)
+            .map_err(|e| e.to_string())?;
+        if payload.len() != len {
+            return Err("HA frame truncated".to_string());
+        }
+        let mut tag = [0u8; TAG_BYTES];
+        reader.read_exact(&mut tag).map_err(|e| e.to_string())?;
+
+        if self.mac(seq, &payload)?.verify_slice(&tag).is_err() {
+            return Err("HA frame signature mismatch (check shared_secret)".to_string());
+        }
+        if seq != self.next_seq {
+            return Err(format!(
+                "HA frame out of sequence: expected {}, got {seq}",
+                self.next_seq
+            ));
+        }
+        self.next_seq += 1;
+        serde_cbor::from_slice(&payload).map_err(|e| e.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SECRET: &str = "correct horse battery staple";
+
+    fn hello() -> HaMessage {
+        HaMessage::Hello {
+            protocol: PROTOCOL_VERSION,
+            node_id: "abc".to_string(),
+            node_name: "shaper-a".to_string(),
+        }
+    }
+
+    #[test]
+    fn frames_round_trip_in_order() {
+        let mut sender = FrameCodec::new(SECRET, [7; CHALLENGE_BYTES]);
+        let mut receiver = FrameCodec::new(SECRET, [7; CHALLENGE_BYTES]);
+        let mut wire = Vec::new();
+        sender.write(&mut wire, &hello()).unwrap();
+        sender
+            .write(
+                &mut wire,
+                &HaMessage::BakeryBatch(vec![BusRequest::BakeryStart]),
+            )
+            .unwrap();
+        sender.write(&mut wire, &HaMessage::Heartbeat).unwrap();
+
+        let mut reader = wire.as_slice();
+        assert_eq!(receiver.read(&mut reader).unwrap(), hello());
+        assert_eq!(
+            receiver.read(&mut reader).unwrap(),
+            HaMessage::BakeryBatch(vec![BusRequest::BakeryStart])
+        );
+        assert_eq!(receiver.read(&mut reader).unwrap(), HaMessage::Heartbeat);
+        assert!(receiver.read(&mut reader).is_err(), "stream is exhausted");
+    }
+
+    #[test]
+    fn rejects_wrong_secret_tampering_and_replay() {
+        let mut sender = FrameCodec::new(SECRET, [1; CHALLENGE_BYTES]);
+        let frame = sender.encode(&hello()).unwrap();
+
+        let mut wrong_secret = FrameCodec::new("another secret entirely", [1; CHALLENGE_BYTES]);
+        assert!(wrong_secret.read(&mut frame.as_slice()).is_err());
+
+        let mut other_connection = FrameCodec::new(SECRET, [2; CHALLENGE_BYTES]);
+        assert!(other_connection.read(&mut frame.as_slice()).is_err());
+
+        let mut tampered = frame.clone();
+        tampered[14] ^= 0xff;
+        let mut receiver = FrameCodec::new(SECRET, [1; CHALLENGE_BYTES]);
+        assert!(receiver.read(&mut tampered.as_slice()).is_err());
+
+        let mut receiver = FrameCodec::new(SECRET, [1; CHALLENGE_BYTES]);
+        assert!(receiver.read(&mut frame.as_slice()).is_ok());
+        assert!(
+            receiver.read(&mut frame.as_slice()).is_err(),
+            "replayed frame"
+        );
+    }
+
+    #[test]
+    fn hello_reads_refuse_large_frames_before_reading_them() {
+        let mut sender = FrameCodec::new(SECRET, [3; CHALLENGE_BYTES]);
+        let frame = sender
+            .encode(&HaMessage::Treeguard(vec!["node".repeat(2048)]))
+            .unwrap();
+        let mut receiver = FrameCodec::new(SECRET, [3; CHALLENGE_BYTES]);
+        let error = receiver
+            .read_bounded(&mut &frame[..12], MAX_HELLO_BYTES)
+            .unwrap_err();
+        assert!(error.contains("too large"), "{error}");
+
+        // A length prefix with no payload behind it fails without a matching allocation.
+        let mut header = (MAX_PAYLOAD_BYTES as u32).to_be_bytes().to_vec();
+        header.extend_from_slice(&0u64.to_be_bytes());
+        let mut receiver = FrameCodec::new(SECRET, [3; CHALLENGE_BYTES]);
+        assert_eq!(
+            receiver.read(&mut header.as_slice()).unwrap_err(),
+            "HA frame truncated"
+        );
+    }
+}
@@ -3,13 +3,17 @@
 
 #![deny(clippy::unwrap_used)]
 
+mod alerting;
+mod audit;
 mod blackboard;
 mod file_lock;
+mod ha;
 mod ip_mapping;
 #[cfg(feature = "equinix_tests")]
 mod lqos_daht_test;
 pub mod lts2_sys;
 mod node_manager;
+mod notifications;
 mod preflight_checks;
 mod program_control;
 mod remote_commands;
@@ -19,6 +23,7 @@ mod shaped_devices_tracker;
 mod stats;
 mod stick;
 mod system_stats;
+mod temporary_overrides;
 mod throughput_tracker;
 mod tool_status;
 mod treeguard;
@@ -31,6 +36,7 @@ mod version_checks;
 use std::io::Write;
 use std::net::IpAddr;
 
+use crate::audit::{AuditActor, AuditEvent};
 use crate::ip_mapping::clear_hot_cache;
 use crate::{
     file_lock::FileLock,
@@ -39,8 +45,8 @@ use crate::{
 };
 use anyhow::Result;
 use lqos_bus::{
-    BusRequest, BusResponse, InsightLicenseSummary, TreeGuardRuntimeNodeBranchSnapshot,
-    TreeGuardRuntimeNodeOperationSnapshot, UnixSocketServer,
+    BusRequest, BusResponse, InsightLicenseSummary, RemoteBusCaller,
+    TreeGuardRuntimeNodeBranchSnapshot, TreeGuardRuntimeNodeOperationSnapshot, UnixSocketServer,
 };
 use lqos_heimdall::{n_second_packet_dump, perf_interface::heimdall_handle_events, start_heimdall};
 use lqos_queue_tracker::{
@@ -235,6 +241,11 @@ fn main() -> Result<()> {
     }
     blackboard::start_blackboard();
     start_remote_commands();
+    urgent::restore();
+    notifications::start_notifications();
+    alerting::start_alerting();
+    ha::start_ha();
+    temporary_overrides::start_expiry();
     let flow_tx = setup_netflow_tracker()?;
     let _ = throughput_tracker::flow_data::setup_flow_analysis();
     start_heimdall()?;
@@ -365,6 +376,21 @@ fn main() -> Result<()> {
                     warn!("Webserver disabled by configuration");
                 }
 
+                // Optional remote (TLS) bus listener
+                if config.remote_bus.enabled {
+                    match lqos_bus::TlsBusServer::new(&config.remote_bus) {
+                        Ok(remote_bus) => {
+                            tokio::spawn(async move {
+                                if let Err(e) = remote_bus.listen(handle_remote_bus_requests).await
+                                {
+                                    error!("Remote bus stopped: {e:?}");
+                                }
+                            });
+                        }
+                        Err(e) => error!("Remote bus not started: {e}"),
+                    }
+                }
+
                 // Main bus listen loop
                 if let Err(e) = server.listen(handle_bus_requests, bus_rx).await {
                     error!("Bus stopped: {e:?}");
@@ -407,9 +433,28 @@ fn memory_debug() {
 fn memory_debug() {}
 
 fn handle_bus_requests(requests: &[BusRequest], responses: &mut Vec<BusResponse>) {
+    handle_bus_requests_as(&AuditActor::bus(), requests, responses);
+}
+
+fn handle_remote_bus_requests(
+    caller: &RemoteBusCaller,
+    requests: &[BusRequest],
+    responses: &mut Vec<BusResponse>,
+) {
+    let actor = AuditActor::remote_bus(&caller.client, &caller.peer);
+    handle_bus_requests_as(&actor, requests, responses);
+}
+
+/// Answers bus requests, recording administrative ones in the audit log as `actor`.
+fn handle_bus_requests_as(
+    actor: &AuditActor,
+    requests: &[BusRequest],
+    responses: &mut Vec<BusResponse>,
+) {
     for req in requests.iter() {
         //println!("Request: {:?}", req);
         BUS_REQUESTS.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+        ha::observe_bus_request(req);
         responses.push(match req {
             BusRequest::Ping => BusResponse::Ack,
             BusRequest::GetCurrentThroughput => throughput_tracker::current_throughput(),
@@ -492,7 +537,11 @@ fn handle_bus_requests(requests: &[BusRequest], responses: &mut Vec<BusResponse>
             BusRequest::RttHistogram => throughput_tracker::rtt_histogram::<50>(),
             BusRequest::HostCounts => throughput_tracker::host_counts(),
             BusRequest::AllUnknownIps => throughput_tracker::all_unknown_ips(),
-            BusRequest::ReloadLibreQoS => program_control::reload_libre_qos(),
+            BusRequest::ReloadLibreQoS => {
+                let response = program_control::reload_libre_qos();
+                AuditEvent::new("reload_libreqos").record_bus(actor, &response);
+                response
+            }
             BusRequest::GetRawQueueData(circuit_id) => get_raw_circuit_data(circuit_id),
             BusRequest::WatchQueue(circuit_id) => {
                 add_watched_queue(circuit_id);
@@ -500,13 +549,7 @@ fn handle_bus_requests(requests: &[BusRequest], responses: &mut Vec<BusResponse>
             }
             BusRequest::UpdateLqosDTuning(..) => tuning::tune_lqosd_from_bus(req),
             BusRequest::UpdateLqosdConfig(config) => {
-                let result = lqos_config::update_config(config);
-                if result.is_err() {
-                    error!("Error updating config: {:?}", result);
-                }
-                if let Ok(cfg) = lqos_config::load_config() {
-                    let _ = stick::recompute_stick_offset(&cfg);
-                }
+                let _ = program_control::update_lqosd_config(config, actor);
                 BusResponse::Ack
             }
             BusRequest::InvalidateAuthCache => {
@@ -776,16 +819,113 @@ fn handle_bus_requests(requests: &[BusRequest], responses: &mut Vec<BusResponse>
                 );
                 BusResponse::Ack
             },
+            BusRequest::GetOverrideHistory { layer } => {
+                match crate::node_manager::local_api::override_history::override_history(layer) {
+                    Ok(entries) => BusResponse::OverrideHistory(entries),
+                    Err(err) => BusResponse::Fail(err),
+                }
+            }
+            BusRequest::GetOverrideDiff {
+                layer,
+                from_version,
+                to_version,
+            } => match crate::node_manager::local_api::override_history::override_diff(
+                layer,
+                *from_version,
+                *to_version,
+            ) {
+                Ok(diff) => BusResponse::OverrideDiff(diff),
+                Err(err) => BusResponse::Fail(err),
+            },
+            BusRequest::GetAuditLog {
+                since_unix,
+                user,
+                action,
+                limit,
+            } => BusResponse::AuditLog(audit::query(&audit::AuditQuery {
+                since_unix: *since_unix,
+                user: user.clone(),
+                action: action.clone(),
+                limit: *limit,
+            })),
+            BusRequest::GetHaStatus => BusResponse::HaStatus(ha::ha_status()),
+            BusRequest::GetRttExcludedCircuits => match rtt_exclusions::excluded_circuit_ids() {
+                Ok(ids) => BusResponse::RttExcludedCircuits(ids),
+                Err(e) => BusResponse::Fail(e.to_string()),
+            },
+            BusRequest::SetCircuitRttExcluded {
+                circuit_id,
+                excluded,
+            } => {
+                let response =
+                    match rtt_exclusions::set_excluded_circuit_id(circuit_id, *excluded, actor.user()) {
+                        Ok(_) => BusResponse::Ack,
+                        Err(e) => BusResponse::Fail(e.to_string()),
+                    };
+                AuditEvent::new("set_circuit_rtt_excluded")
+                    .target(circuit_id.as_str())
+                    .after(excluded)
+                    .record_bus(actor, &response);
+                response
+            }
+            BusRequest::SetTemporaryCircuitSpeed {
+                circuit_id,
+                download_mbps,
+                upload_mbps,
+                minutes,
+            } => {
+                let response = match temporary_overrides::set_circuit_speed(
+                    circuit_id,
+                    *download_mbps,
+                    *upload_mbps,
+                    *minutes,
+                ) {
+                    Ok(()) => BusResponse::Ack,
+                    Err(e) => BusResponse::Fail(e),
+                };
+                AuditEvent::new("set_temporary_circuit_speed")
+                    .target(circuit_id.as_str())
+                    .after(&format!("{download_mbps}/{upload_mbps} Mbps for {minutes} minutes"))
+                    .record_bus(actor, &response);
+                response
+            }
+            BusRequest::GetHealthSnapshot => BusResponse::HealthSnapshot(health_snapshot_data()),
+            BusRequest::RollbackOverrideLayer {
+                layer,
+                version,
+                author,
+                reason,
+            } => {
+                let response =
+                    match crate::node_manager::local_api::override_history::rollback_override_layer(
+                        layer, *version, author, reason,
+                    ) {
+                        Ok(entry) => BusResponse::OverrideHistory(vec![entry]),
+                        Err(err) => BusResponse::Fail(err),
+                    };
+                AuditEvent::new("rollback_override_layer")
+                    .target(format!("{layer} v{version}"))
+                    .after(&format!("author {author}: {reason}"))
+                    .record_bus(actor, &response);
+                response
+            }
             BusRequest::TreeGuardSetNodeVirtual {
                 node_name,
                 virtualized,
-            } => match crate::treeguard::bakery::submit_node_virtualization_live(
-                node_name,
-                *virtualized,
-            ) {
-                Ok(()) => BusResponse::Ack,
-                Err(err) => BusResponse::Fail(err.to_string()),
-            },
+            } => {
+                let response = match crate::treeguard::bakery::submit_node_virtualization_live(
+                    node_name,
+                    *virtualized,
+                ) {
+                    Ok(()) => BusResponse::Ack,
+                    Err(err) => BusResponse::Fail(err.to_string()),
+                };
+                AuditEvent::new("treeguard_set_node_virtual")
+                    .target(node_name.as_str())
+                    .after(virtualized)
+                    .record_bus(actor, &response);
+                response
+            }
             BusRequest::TreeGuardGetNodeVirtualStatus { node_name } => {
                 let snapshot = crate::treeguard::bakery::node_virtualization_operation_status(
                     node_name,
@@ -898,11 +1038,22 @@ fn handle_bus_requests(requests: &[BusRequest], responses: &mut Vec<BusResponse>
                 BusResponse::UrgentIssues(list)
             }
             BusRequest::ClearUrgentIssue(id) => {
-                urgent::clear(*id);
+                let before = urgent::get(*id);
+                let cleared = urgent::clear(*id);
+                let mut event = AuditEvent::new("clear_urgent_issue").target(id.to_string());
+                if let Some(issue) = &before {
+                    event = event.before(issue);
+                }
+                let message = if cleared { "Ok" } else { "Not found" };
+                event.record(actor, cleared, message);
                 BusResponse::Ack
             }
             BusRequest::ClearAllUrgentIssues => {
+                let cleared = urgent::list().len();
                 urgent::clear_all();
+                AuditEvent::new("clear_all_urgent_issues")
+                    .before(&cleared)
+                    .record_bus(actor, &BusResponse::Ack);
                 BusResponse::Ack
             }
             BusRequest::GetGlobalWarnings => {
@@ -1086,6 +1237,25 @@ fn queue_stats_total_data() -> lqos_bus::QueueStatsTotal {
     }
 }
 
+fn health_snapshot_data() -> lqos_bus::HealthSnapshot {
+    use crate::system_stats::{CPU_USAGE, NUM_CPUS};
+    let cpus = NUM_CPUS
+        .load(std::sync::atomic::Ordering::Relaxed)
+        .min(CPU_USAGE.len());
+    lqos_bus::HealthSnapshot {
+        reload_required_reason: lqos_bakery::bakery_reload_required_reason(),
+        // Walks the whole map; fine for a check that runs every few minutes
+        ip_mappings: lqos_sys::list_mapped_ips()
+            .ok()
+            .map(|mappings| mappings.len()),
+        ip_mapping_capacity: lqos_sys::ip_mapping_capacity(),
+        cpu_usage: CPU_USAGE[..cpus]
+            .iter()
+            .map(|usage| usage.load(std::sync::atomic::Ordering::Relaxed))
+            .collect(),
+    }
+}
+
 fn circuit_capacity_data() -> Vec<lqos_bus::CircuitCapacityRow> {
     use crate::shaped_devices_tracker::SHAPED_DEVICES;
     use crate::throughput_tracker::THROUGHPUT_TRACKER;
@@ -1,4 +1,6 @@
+mod api_v1;
 mod auth;
+pub(crate) mod federation;
 pub(crate) mod local_api;
 mod run;
 mod shaper_queries_actor;
@@ -0,0 +1,469 @@
+//! Stable, versioned REST API for OSS/BSS integrations.
+//!
+//! Unlike the WebSocket protocol used by the UI, routes under `/api/v1` are a supported
+//! contract. Requests authenticate with `Authorization: Bearer <token>`, where tokens are
+//! issued with `lqusers token add` and carry scopes (see [`ApiTokenScope`]). The OpenAPI
+//! document at `/api/v1/openapi.json` is public.
+
+mod openapi;
+
+use crate::audit::{AuditActor, AuditEvent};
+use crate::node_manager::auth::{LoginResult, api_token_from_bearer, client_source};
+use crate::node_manager::local_api::{
+    circuit, circuit_live, config, directories, network_tree_lite, override_history,
+    reload_libreqos, shaped_device_api, urgent,
+};
+use axum::extract::{ConnectInfo, Path, Query, Request};
+use axum::http::{HeaderMap, StatusCode, header};
+use axum::middleware::Next;
+use axum::response::{IntoResponse, Response};
+use axum::routing::{get, post};
+use axum::{Extension, Json, Router};
+use lqos_bus::{BusReply, BusRequest, BusResponse};
+use lqos_config::{ApiToken, ApiTokenScope, ShapedDevice};
+use lqos_overrides::{BulkFormat, ChangeAuthor, ImportMode, OverrideStore, SaveContext};
+use lqos_utils::units::DownUpOrder;
+use serde::{Deserialize, Serialize};
+use std::net::SocketAddr;
+use tokio::sync::mpsc::Sender;
+use tower_http::cors::CorsLayer;
+
+type BusSender = Sender<(tokio::sync::oneshot::Sender<BusReply>, BusRequest)>;
+
+/// JSON error body returned by every `/api/v1` route.
+struct ApiError {
+    status: StatusCode,
+    message: String,
+}
+
+impl ApiError {
+    fn new(status: StatusCode, message: impl Into<String>) -> Self {
+        Self {
+            status,
+            message: message.into(),
+        }
+    }
+
+    fn not_found(what: &str) -> Self {
+        Self::new(StatusCode::NOT_FOUND, format!("{what} not found"))
+    }
+
+    /// Maps the string errors returned by the shared `local_api` helpers.
+    fn from_helper(message: String) -> Self {
+        let status = match message.as_str() {
+            "Not found" => StatusCode::NOT_FOUND,
+            _ => StatusCode::BAD_REQUEST,
+        };
+        Self::new(status, message)
+    }
+}
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        (
+            self.status,
+            Json(serde_json::json!({ "error": self.message })),
+        )
+            .into_response()
+    }
+}
+
+type ApiResult<T> = Result<Json<T>, ApiError>;
+
+fn require_scope(token: &ApiToken, scope: ApiTokenScope) -> Result<(), ApiError> {
+    if token.has_scope(scope) {
+        Ok(())
+    } else {
+        Err(ApiError::new(
+            StatusCode::FORBIDDEN,
+            format!("Token lacks the '{scope}' scope"),
+        ))
+    }
+}
+
+/// Attributes a write made with `token` in the audit log.
+fn audit_actor(token: &ApiToken, peer: SocketAddr, headers: &HeaderMap) -> AuditActor {
+    AuditActor::api_token(&token.name, &client_source(peer, headers))
+}
+
+/// Resolves the bearer token and stores it in the request extensions.
+async fn token_layer(mut req: Request, next: Next) -> Response {
+    let presented = req
+        .headers()
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "));
+    let Some(token) = presented.and_then(api_token_from_bearer) else {
+        return ApiError::new(StatusCode::UNAUTHORIZED, "Missing or invalid API token")
+            .into_response();
+    };
+    req.extensions_mut().insert(token);
+    next.run(req).await
+}
+
+/// Builds the `/api/v1` router.
+pub fn api_v1_router(bus_tx: BusSender) -> Router {
+    let authenticated = Router::new()
+        .route("/circuits", get(list_circuits))
+        .route("/circuits/:circuit_id", get(get_circuit))
+        .route("/circuits/:circuit_id/live", get(get_circuit_live))
+        .route("/sites", get(list_sites))
+        .route(
+            "/shaped-devices",
+            get(list_shaped_devices).post(create_shaped_device),
+        )
+        .route(
+            "/shaped-devices/:device_id",
+            get(get_shaped_device)
+                .put(update_shaped_device)
+                .delete(delete_shaped_device),
+        )
+        .route("/overrides/:layer", get(export_overrides))
+        .route("/overrides/:layer/import", post(import_overrides))
+        .route("/overrides/:layer/history", get(list_override_history))
+        .route("/throughput", get(current_throughput))
+        .route("/rtt/histogram", get(rtt_histogram))
+        .route("/urgent", get(list_urgent))
+        .route("/reload", post(reload))
+        .layer(Extension(bus_tx))
+        .route_layer(axum::middleware::from_fn(token_layer));
+
+    // Only the public OpenAPI document may be read cross-origin.
+    Router::new()
+        .route(
+            "/openapi.json",
+            get(openapi_document).layer(CorsLayer::permissive()),
+        )
+        .merge(authenticated)
+}
+
+async fn openapi_document() -> Json<serde_json::Value> {
+    Json(openapi::openapi_document())
+}
+
+#[derive(Deserialize)]
+struct PageQuery {
+    page: Option<usize>,
+    page_size: Option<usize>,
+    search: Option<String>,
+}
+
+async fn list_circuits(
+    Extension(token): Extension<ApiToken>,
+    Query(query): Query<PageQuery>,
+) -> ApiResult<directories::CircuitDirectoryPage> {
+    require_scope(&token, ApiTokenScope::Read)?;
+    Ok(Json(directories::circuit_directory_page(
+        directories::CircuitDirectoryQuery {
+            page: query.page,
+            page_size: query.page_size,
+            search: query.search,
+        },
+    )))
+}
+
+async fn get_circuit(
+    Extension(token): Extension<ApiToken>,
+    Path(circuit_id): Path<String>,
+) -> ApiResult<circuit::CircuitByIdData> {
+    require_scope(&token, ApiTokenScope::Read)?;
+    circuit::circuit_by_id_data(&circuit_id)
+        .map(Json)
+        .ok_or_else(|| ApiError::not_found("Circuit"))
+}
+
+async fn get_circuit_live(
+    Extension(token): Extension<ApiToken>,
+    Path(circuit_id): Path<String>,
+) -> ApiResult<circuit_live::CircuitLiveMetrics> {
+    require_scope(&token, ApiTokenScope::Read)?;
+    let query = circuit_live::CircuitMetricsQuery {
+        circuit_ids: vec![circuit_id],
+    };
+    circuit_live::circuit_live_metrics(&query)
+        .into_iter()
+        .next()
+        .map(Json)
+        .ok_or_else(|| ApiError::not_found("Live data for circuit"))
+}
+
+/// One node of the network hierarchy, tagged with its tree index.
+#[derive(Serialize, Deserialize)]
+pub(crate) struct ApiSite {
+    pub(crate) index: usize,
+    #[serde(flatten)]
+    pub(crate) node: network_tree_lite::NetworkTreeLiteNode,
+}
+
+async fn list_sites(Extension(token): Extension<ApiToken>) -> ApiResult<Vec<ApiSite>> {
+    require_scope(&token, ApiTokenScope::Read)?;
+    Ok(Json(
+        network_tree_lite::network_tree_lite_data()
+            .into_iter()
+            .map(|(index, node)| ApiSite { index, node })
+            .collect(),
+    ))
+}
+
+async fn list_shaped_devices(
+    Extension(token): Extension<ApiToken>,
+) -> ApiResult<Vec<ShapedDevice>> {
+    require_scope(&token, ApiTokenScope::Read)?;
+    Ok(Json(shaped_device_api::all_shaped_devices_data()))
+}
+
+async fn get_shaped_device(
+    Extension(token): Extension<ApiToken>,
+    Path(device_id): Path<String>,
+) -> ApiResult<ShapedDevice> {
+    require_scope(&token, ApiTokenScope::Read)?;
+    config::get_shaped_device_data(LoginResult::Admin, device_id)
+        .map_err(|status| ApiError::new(status, "Unable to read shaped devices"))?
+        .map(Json)
+        .ok_or_else(|| ApiError::not_found("Shaped device"))
+}
+
+async fn create_shaped_device(
+    Extension(token): Extension<ApiToken>,
+    ConnectInfo(peer): ConnectInfo<SocketAddr>,
+    headers: HeaderMap,
+    Json(device): Json<ShapedDevice>,
+) -> Result<(StatusCode, Json<ShapedDevice>), ApiError> {
+    require_scope(&token, ApiTokenScope::Devices)?;
+    let device_id = device.device_id.clone();
+    let event = AuditEvent::new("create_shaped_device")
+        .target(device_id.as_str())
+        .after(&device);
+    let result = config::create_shaped_device_data(LoginResult::Admin, device);
+    event.record_result(&audit_actor(&token, peer, &headers), &result);
+    let created = result.map_err(ApiError::from_helper)?;
+    tracing::info!(token = %token.id, device_id = %device_id, "API created shaped device");
+    Ok((StatusCode::CREATED, Json(created)))
+}
+
+async fn update_shaped_device(
+    Extension(token): Extension<ApiToken>,
+    ConnectInfo(peer): ConnectInfo<SocketAddr>,
+    headers: HeaderMap,
+    Path(device_id): Path<String>,
+    Json(device): Json<ShapedDevice>,
+) -> ApiResult<ShapedDevice> {
+    require_scope(&token, ApiTokenScope::Devices)?;
+    let event =
+        config::shaped_device_audit_event("update_shaped_device", &device_id).after(&device);
+    let result = config::update_shaped_device_data(LoginResult::Admin, device_id.clone(), device);
+    event.record_result(&audit_actor(&token, peer, &headers), &result);
+    let updated = result.map_err(ApiError::from_helper)?;
+    tracing::info!(token = %token.id, device_id = %device_id, "API updated shaped device");
+    Ok(Json(updated))
+}
+
+async fn delete_shaped_device(
+    Extension(token): Extension<ApiToken>,
+    ConnectInfo(peer): ConnectInfo<SocketAddr>,
+    headers: HeaderMap,
+    Path(device_id): Path<String>,
+) -> Result<StatusCode, ApiError> {
+    require_scope(&token, ApiTokenScope::Devices)?;
+    let event = config::shaped_device_audit_event("delete_shaped_device", &device_id);
+    let result = config::delete_shaped_device_data(LoginResult::Admin, device_id.clone());
+    event.record_result(&audit_actor(&token, peer, &headers), &result);
+    result.map_err(ApiError::from_helper)?;
+    tracing::info!(token = %token.id, device_id = %device_id, "API deleted shaped device");
+    Ok(StatusCode::NO_CONTENT)
+}
+
+#[derive(Deserialize)]
+struct ExportQuery {
+    format: Option<BulkFormat>,
+}
+
+async fn export_overrides(
+    Extension(token): Extension<ApiToken>,
+    Path(layer): Path<String>,
+    Query(query): Query<ExportQuery>,
+) -> Result<Response, ApiError> {
+    require_scope(&token, ApiTokenScope::Read)?;
+    let layer = override_history::parse_layer(&layer)
+        .map_err(|e| ApiError::new(StatusCode::NOT_FOUND, e))?;
+    let format = query.format.unwrap_or(BulkFormat::Json);
+    let body = OverrideStore::export_layer(layer, format)
+        .map_err(|e| ApiError::new(StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    let content_type = match format {
+        BulkFormat::Json => "application/json",
+        BulkFormat::Csv => "text/csv",
+    };
+    Ok(([(header::CONTENT_TYPE, content_type)], body).into_response())
+}
+
+#[derive(Deserialize)]
+struct ImportQuery {
+    format: Option<BulkFormat>,
+    mode: Option<ImportMode>,
+    #[serde(default)]
+    validate_only: bool,
+    #[serde(default)]
+    overwrite: bool,
+    reason: Option<String>,
+}
+
+async fn import_overrides(
+    Extension(token): Extension<ApiToken>,
+    ConnectInfo(peer): ConnectInfo<SocketAddr>,
+    headers: HeaderMap,
+    Path(layer): Path<String>,
+    Query(query): Query<ImportQuery>,
+    body: String,
+) -> Result<Response, ApiError> {
+    require_scope(&token, ApiTokenScope::Overrides)?;
+    let layer_name = layer.clone();
+    let layer = override_history::parse_layer(&layer)
+        .map_err(|e| ApiError::new(StatusCode::NOT_FOUND, e))?;
+    let format = query.format.unwrap_or(BulkFormat::Json);
+    let mode = query.mode.unwrap_or(ImportMode::Merge);
+    let internal =
+        |e: anyhow::Error| ApiError::new(StatusCode::INTERNAL_SERVER_ERROR, e.to_string());
+
+    let report = if query.validate_only {
+        let existing = OverrideStore::load_layer(layer).map_err(internal)?;
+        lqos_overrides::plan_import(&existing, &body, format, mode)
+    } else {
+        let ctx = SaveContext::new(
+            ChangeAuthor::Integration {
+                name: token.name.clone(),
+            },
+            query
+                .reason
+                .unwrap_or_else(|| format!("API import with token {}", token.id)),
+        );
+        let result = OverrideStore::import_layer(layer, &body, format, mode, query.overwrite, &ctx);
+        let actor = audit_actor(&token, peer, &headers);
+        let event = AuditEvent::new("import_overrides").target(layer_name.as_str());
+        match &result {
+            Ok(report) if report.is_blocked(query.overwrite) => {
+                event.after(report).record(&actor, false, "Not applied")
+            }
+            Ok(report) => event.after(report).record(&actor, true, "Ok"),
+            Err(e) => event.record(&actor, false, e.to_string()),
+        }
+        result.map_err(internal)?
+    };
+    let status = if report.is_blocked(query.overwrite) {
+        StatusCode::UNPROCESSABLE_ENTITY
+    } else {
+        StatusCode::OK
+    };
+    Ok((status, Json(report)).into_response())
+}
+
+async fn list_override_history(
+    Extension(token): Extension<ApiToken>,
+    Path(layer): Path<String>,
+) -> ApiResult<Vec<lqos_bus::OverrideHistoryEntry>> {
+    require_scope(&token, ApiTokenScope::Read)?;
+    override_history::override_history(&layer)
+        .map(Json)
+        .map_err(|e| ApiError::new(StatusCode::NOT_FOUND, e))
+}
+
+async fn bus_query(bus_tx: &BusSender, request: BusRequest) -> Result<Vec<BusResponse>, ApiError> {
+    let unavailable = || ApiError::new(StatusCode::SERVICE_UNAVAILABLE, "lqosd bus unavailable");
+    let (tx, rx) = tokio::sync::oneshot::channel::<BusReply>();
+    bus_tx
+        .send((tx, request))
+        .await
+        .map_err(|_| unavailable())?;
+    Ok(rx.await.map_err(|_| unavailable())?.responses)
+}
+
+/// Current shaper-wide throughput.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub(crate) struct ApiThroughput {
+    pub(crate) bits_per_second: DownUpOrder<u64>,
+    pub(crate) packets_per_second: DownUpOrder<u64>,
+    pub(crate) tcp_packets_per_second: DownUpOrder<u64>,
+    pub(crate) udp_packets_per_second: DownUpOrder<u64>,
+    pub(crate) icmp_packets_per_second: DownUpOrder<u64>,
+    pub(crate) shaped_bits_per_second: DownUpOrder<u64>,
+}
+
+impl ApiThroughput {
+    /// Extracts throughput from a `CurrentThroughput` bus response.
+    pub(crate) fn from_response(response: BusResponse) -> Option<Self> {
+        match response {
+            BusResponse::CurrentThroughput {
+                bits_per_second,
+                packets_per_second,
+                tcp_packets_per_second,
+                udp_packets_per_second,
+                icmp_packets_per_second,
+                shaped_bits_per_second,
+            } => Some(Self {
+                bits_per_second,
+                packets_per_second,
+                tcp_packets_per_second,
+                udp_packets_per_second,
+                icmp_packets_per_second,
+                shaped_bits_per_second,
+            }),
+            _ => None,
+        }
+    }
+}
+
+async fn current_throughput(
+    Extension(token): Extension<ApiToken>,
+    Extension(bus_tx): Extension<BusSender>,
+) -> ApiResult<ApiThroughput> {
+    require_scope(&token, ApiTokenScope::Read)?;
+    for response in bus_query(&bus_tx, BusRequest::GetCurrentThroughput).await? {
+        if let Some(throughput) = ApiThroughput::from_response(response) {
+            return Ok(Json(throughput));
+        }
+    }
+    Err(ApiError::new(
+        StatusCode::SERVICE_UNAVAILABLE,
+        "Throughput unavailable",
+    ))
+}
+
+async fn rtt_histogram(
+    Extension(token): Extension<ApiToken>,
+    Extension(bus_tx): Extension<BusSender>,
+) -> ApiResult<Vec<u32>> {
+    require_scope(&token, ApiTokenScope::Read)?;
+    for response in bus_query(&bus_tx, BusRequest::RttHistogram).await? {
+        if let BusResponse::RttHistogram(buckets) = response {
+            return Ok(Json(buckets));
+        }
+    }
+    Err(ApiError::new(
+        StatusCode::SERVICE_UNAVAILABLE,
+        "RTT histogram unavailable",
+    ))
+}
+
+async fn list_urgent(Extension(token): Extension<ApiToken>) -> ApiResult<urgent::UrgentList> {
+    require_scope(&token, ApiTokenScope::Read)?;
+    Ok(Json(urgent::urgent_list_data()))
+}
+
+#[derive(Serialize)]
+struct ReloadResult {
+    message: String,
+}
+
+async fn reload(
+    Extension(token): Extension<ApiToken>,
+    ConnectInfo(peer): ConnectInfo<SocketAddr>,
+    headers: HeaderMap,
+) -> ApiResult<ReloadResult> {
+    require_scope(&token, ApiTokenScope::Reload)?;
+    tracing::info!(token = %token.id, "API requested LibreQoS reload");
+    let message = reload_libreqos::reload_libreqos_with_login(
+        LoginResult::Admin,
+        &audit_actor(&token, peer, &headers),
+    )
+    .await;
+    Ok(Json(ReloadResult { message }))
+}
@@ -0,0 +1,374 @@
+//! OpenAPI 3 description of the `/api/v1` routes.
+//!
+//! The document is built from [`OPERATIONS`], so adding a route means adding one entry here.
+
+use serde_json::{Map, Value, json};
+
+/// Where an operation parameter is read from.
+#[derive(Clone, Copy)]
+enum ParamIn {
+    Path,
+    Query,
+}
+
+struct Param {
+    name: &'static str,
+    location: ParamIn,
+    schema: &'static str,
+    description: &'static str,
+}
+
+struct Operation {
+    method: &'static str,
+    path: &'static str,
+    summary: &'static str,
+    /// Required token scope, or `None` for public operations.
+    scope: Option<&'static str>,
+    params: &'static [Param],
+    /// Request body content type, if the operation takes one.
+    body: Option<&'static str>,
+    /// Success status code.
+    success: &'static str,
+}
+
+const DEVICE_ID: Param = Param {
+    name: "device_id",
+    location: ParamIn::Path,
+    schema: "string",
+    description: "Shaped device identifier",
+};
+
+const CIRCUIT_ID: Param = Param {
+    name: "circuit_id",
+    location: ParamIn::Path,
+    schema: "string",
+    description: "Circuit identifier",
+};
+
+const LAYER: Param = Param {
+    name: "layer",
+    location: ParamIn::Path,
+    schema: "string",
+    description: "Overrides layer: operator, stormguard or treeguard",
+};
+
+const FORMAT: Param = Param {
+    name: "format",
+    location: ParamIn::Query,
+    schema: "string",
+    description: "json (default) or csv",
+};
+
+const OPERATIONS: &[Operation] = &[
+    Operation {
+        method: "get",
+        path: "/circuits",
+        summary: "Page through circuits",
+        scope: Some("read"),
+        params: &[
+            Param {
+                name: "page",
+                location: ParamIn::Query,
+                schema: "integer",
+                description: "Zero-based page number",
+            },
+            Param {
+                name: "page_size",
+                location: ParamIn::Query,
+                schema: "integer",
+                description: "Rows per page (at most 250)",
+            },
+            Param {
+                name: "search",
+                location: ParamIn::Query,
+                schema: "string",
+                description: "Filter by circuit id, name or parent node",
+            },
+        ],
+        body: None,
+        success: "200",
+    },
+    Operation {
+        method: "get",
+        path: "/circuits/{circuit_id}",
+        summary: "Circuit and its devices",
+        scope: Some("read"),
+        params: &[CIRCUIT_ID],
+        body: None,
+        success: "200",
+    },
+    Operation {
+        method: "get",
+        path: "/circuits/{circuit_id}/live",
+        summary: "Live throughput, RTT, QoO and retransmits for a circuit",
+        scope: Some("read"),
+        params: &[CIRCUIT_ID],
+        body: None,
+        success: "200",
+    },
+    Operation {
+        method: "get",
+        path: "/sites",
+        summary: "Network hierarchy nodes with current throughput",
+        scope: Some("read"),
+        params: &[],
+        body: None,
+        success: "200",
+    },
+    Operation {
+        method: "get",
+        path: "/shaped-devices",
+        summary: "List shaped devices",
+        scope: Some("read"),
+        params: &[],
+        body: None,
+        success: "200",
+    },
+    Operation {
+        method: "post",
+        path: "/shaped-devices",
+        summary: "Create a shaped device",
+        scope: Some("devices"),
+        params: &[],
+        body: Some("application/json"),
+        success: "201",
+    },
+    Operation {
+        method: "get",
+        path: "/shaped-devices/{device_id}",
+        summary: "Get a shaped device",
+        scope: Some("read"),
+        params: &[DEVICE_ID],
+        body: None,
+        success: "200",
+    },
+    Operation {
+        method: "put",
+        path: "/shaped-devices/{device_id}",
+        summary: "Replace a shaped device",
+        scope: Some("devices"),
+        params: &[DEVICE_ID],
+        body: Some("application/json"),
+        success: "200",
+    },
+    Operation {
+        method: "delete",
+        path: "/shaped-devices/{device_id}",
+        summary: "Delete a shaped device",
+        scope: Some("devices"),
+        params: &[DEVICE_ID],
+        body: None,
+        success: "204",
+    },
+    Operation {
+        method: "get",
+        path: "/overrides/{layer}",
+        summary: "Export an overrides layer",
+        scope: Some("read"),
+        params: &[LAYER, FORMAT],
+        body: None,
+        success: "200",
+    },
+    Operation {
+        method: "post",
+        path: "/overrides/{layer}/import",
+        summary: "Import entries into an overrides layer (422 when blocked)",
+        scope: Some("overrides"),
+        params: &[
+            LAYER,
+            FORMAT,
+            Param {
+                name: "mode",
+                location: ParamIn::Query,
+                schema: "string",
+                description: "merge (default) or replace",
+            },
+            Param {
+                name: "validate_only",
+                location: ParamIn::Query,
+                schema: "boolean",
+                description: "Report what would change without writing",
+            },
+            Param {
+                name: "overwrite",
+                location: ParamIn::Query,
+                schema: "boolean",
+                description: "Allow replacing existing entries that differ",
+            },
+            Param {
+                name: "reason",
+                location: ParamIn::Query,
+                schema: "string",
+                description: "Reason recorded in the layer history",
+            },
+        ],
+        body: Some("text/plain"),
+        success: "200",
+    },
+    Operation {
+        method: "get",
+        path: "/overrides/{layer}/history",
+        summary: "Recorded versions of an overrides layer, newest first",
+        scope: Some("read"),
+        params: &[LAYER],
+        body: None,
+        success: "200",
+    },
+    Operation {
+        method: "get",
+        path: "/throughput",
+        summary: "Current shaper-wide throughput",
+        scope: Some("read"),
+        params: &[],
+        body: None,
+        success: "200",
+    },
+    Operation {
+        method: "get",
+        path: "/rtt/histogram",
+        summary: "Current RTT histogram buckets",
+        scope: Some("read"),
+        params: &[],
+        body: None,
+        success: "200",
+    },
+    Operation {
+        method: "get",
+        path: "/urgent",
+        summary: "Active urgent issues",
+        scope: Some("read"),
+        params: &[],
+        body: None,
+        success: "200",
+    },
+    Operation {
+        method: "post",
+        path: "/reload",
+        summary: "Reload LibreQoS",
+        scope: Some("reload"),
+        params: &[],
+        body: None,
+        success: "200",
+    },
+    Operation {
+        method: "get",
+        path: "/openapi.json",
+        summary: "This document",
+        scope: None,
+        params: &[],
+        body: None,
+        success: "200",
+    },
+];
+
+fn operation_json(op: &Operation) -> Value {
+    let params: Vec<Value> = op
+        .params
+        .iter()
+        .map(|p| {
+            json!({
+                "name": p.name,
+                "in": match p.location {
+                    ParamIn::Path => "path",
+                    ParamIn::Query => "query",
+                },
+                "required": matches!(p.location, ParamIn::Path),
+                "description": p.description,
+                "schema": { "type": p.schema },
+            })
+        })
+        .collect();
+
+    let mut responses = Map::new();
+    responses.insert(op.success.to_string(), json!({ "description": "Success" }));
+    let error = json!({
+        "description": "Error",
+        "content": { "application/json": { "schema": { "$ref": "#/components/schemas/Error" } } },
+    });
+    if let Some(scope) = op.scope {
+        responses.insert(
+            "401".to_string(),
+            json!({ "description": "Missing or invalid API token" }),
+        );
+        responses.insert(
+            "403".to_string(),
+            json!({ "description": format!("Token lacks the '{scope}' scope") }),
+        );
+    }
+    responses.insert("default".to_string(), error);
+
+    let mut out = json!({
+        "summary": op.summary,
+        "operationId": format!("{}{}", op.method, op.path.replace(['/', '{', '}', '-', '.'], "_")),
+        "parameters": params,
+        "responses": responses,
+    });
+    match op.scope {
+        Some(scope) => {
+            out["security"] = json!([{ "apiToken": [] }]);
+            out["x-required-scope"] = json!(scope);
+        }
+        None => out["security"] = json!([]),
+    }
+    if let Some(content_type) = op.body {
+        out["requestBody"] = json!({
+            "required": true,
+            "content": { content_type: { "schema": {} } },
+        });
+    }
+    out
+}
+
+/// Builds the OpenAPI document served at `/api/v1/openapi.json`.
+pub fn openapi_document() -> Value {
+    let mut paths = Map::new();
+    for op in OPERATIONS {
+        let entry = paths
+            .entry(op.path.to_string())
+            .or_insert_with(|| Value::Object(Map::new()));
+        entry[op.method] = operation_json(op);
+    }
+
+    json!({
+        "openapi": "3.0.3",
+        "info": {
+            "title": "LibreQoS Node Manager API",
+            "version": "1",
+            "description": "Stable REST API for OSS/BSS integration. Issue tokens with `lqusers token add`.",
+        },
+        "servers": [{ "url": "/api/v1" }],
+        "paths": paths,
+        "components": {
+            "securitySchemes": {
+                "apiToken": { "type": "http", "scheme": "bearer" },
+            },
+            "schemas": {
+                "Error": {
+                    "type": "object",
+                    "properties": { "error": { "type": "string" } },
+                    "required": ["error"],
+                },
+            },
+        },
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn every_operation_is_documented_once() {
+        let doc = openapi_document();
+        let mut count = 0;
+        for methods in doc["paths"].as_object().expect("paths").values() {
+            count += methods.as_object().expect("methods").len();
+        }
+        assert_eq!(count, OPERATIONS.len());
+        assert_eq!(
+            doc["paths"]["/shaped-devices/{device_id}"]["delete"]["x-required-scope"],
+            "devices"
+        );
+        assert_eq!(doc["paths"]["/openapi.json"]["get"]["security"], json!([]));
+    }
+}
@@ -1,27 +1,38 @@
 //! Provides authentication for the Node Manager.
 
 use axum::Json;
-use axum::http::StatusCode;
+use axum::http::{HeaderMap, StatusCode};
 use axum::response::{Html, IntoResponse, Redirect, Response};
 use axum_extra::extract::CookieJar;
 use axum_extra::extract::cookie::{Cookie, SameSite};
 use base64::Engine;
 use base64::engine::general_purpose::URL_SAFE_NO_PAD;
 use hmac::{Hmac, Mac};
-use lqos_config::{AuthenticatedUser, UserRole, WebUsers, load_config};
+use lqos_config::{
+    ApiToken, AuthenticatedUser, AuthenticationError, UserAccess, UserRole, WebUsers, load_config,
+    verify_api_token_in,
+};
 use once_cell::sync::Lazy;
 use parking_lot::Mutex;
 use rand::RngCore;
 use serde::{Deserialize, Serialize};
 use sha2::Sha256;
+use std::collections::HashMap;
 use std::fs::OpenOptions;
 use std::io::Write;
+use std::net::SocketAddr;
 use std::path::{Path, PathBuf};
 use std::sync::atomic::AtomicU64;
 use std::sync::atomic::Ordering::Relaxed;
 use std::time::{SystemTime, UNIX_EPOCH};
 use tracing::{error, warn};
 
+mod access;
+mod oidc;
+
+pub use access::{ScopeFilter, SessionAccess};
+pub use oidc::{oidc_callback, oidc_login, oidc_status};
+
 const COOKIE_NAME: &str = "User-Token";
 const SESSION_TOKEN_VERSION: &str = "v1";
 const SESSION_DURATION_SECS: u64 = 60 * 60 * 24 * 30;
@@ -48,6 +59,8 @@ struct AuthSnapshot {
     bootstrap_state: AuthBootstrapState,
     auth_epoch: u64,
     allow_anonymous: bool,
+    api_tokens: Vec<ApiToken>,
+    user_access: HashMap<String, UserAccess>,
 }
 
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
@@ -58,10 +71,22 @@ enum AuthBootstrapState {
     CorruptUsersFile,
 }
 
+/// How a session was established. SSO sessions never pick up the access of a local
+/// account that happens to share their username.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum SessionOrigin {
+    #[default]
+    Local,
+    Sso,
+}
+
 #[derive(Clone, Debug, Serialize, Deserialize)]
 struct SessionClaims {
     sub: String,
     role: UserRole,
+    #[serde(default)]
+    origin: SessionOrigin,
     auth_epoch: u64,
     iat: u64,
     exp: u64,
@@ -80,10 +105,12 @@ pub struct LoginResponse {
 struct SessionUser {
     username: String,
     role: UserRole,
+    origin: SessionOrigin,
 }
 
 static AUTH_SNAPSHOT: Lazy<Mutex<Option<CachedAuthSnapshot>>> = Lazy::new(|| Mutex::new(None));
 static SESSION_KEY: Lazy<Mutex<Option<Vec<u8>>>> = Lazy::new(|| Mutex::new(None));
+static LOGIN_LOCK: Mutex<()> = Mutex::new(());
 pub static FIRST_LOAD: AtomicU64 = AtomicU64::new(0);
 
 fn record_first_login_timestamp_if_needed() {
@@ -161,6 +188,8 @@ fn auth_snapshot() -> AuthSnapshot {
                 bootstrap_state: AuthBootstrapState::CorruptUsersFile,
                 auth_epoch: 0,
                 allow_anonymous: false,
+                api_tokens: Vec::new(),
+                user_access: HashMap::new(),
             };
         }
     };
@@ -177,6 +206,8 @@ fn auth_snapshot() -> AuthSnapshot {
             bootstrap_state: AuthBootstrapState::MissingUsersFile,
             auth_epoch: 0,
             allow_anonymous: false,
+            api_tokens: Vec::new(),
+            user_access: HashMap::new(),
         },
         Some(_) => match WebUsers::load_or_create() {
             Ok(users) => AuthSnapshot {
@@ -187,6 +218,16 @@ fn auth_snapshot() -> AuthSnapshot {
                 },
                 auth_epoch: users.auth_epoch(),
                 allow_anonymous: users.do_we_allow_anonymous(),
+                api_tokens: users.get_api_tokens(),
+                user_access: users
+                    .get_users()
+                    .iter()
+                    .filter_map(|u| {
+                        users
+                            .access_for_user(&u.username)
+                            .map(|access| (u.username.clone(), access))
+                    })
+                    .collect(),
             },
             Err(e) => {
                 warn!("Unable to load auth state: {e}");
@@ -194,6 +235,8 @@ fn auth_snapshot() -> AuthSnapshot {
                     bootstrap_state: AuthBootstrapState::CorruptUsersFile,
                     auth_epoch: 0,
                     allow_anonymous: false,
+                    api_tokens: Vec::new(),
+                    user_access: HashMap::new(),
                 }
             }
         },
@@ -269,11 +312,16 @@ fn build_session_cookie(token: String) -> Cookie<'static> {
     cookie
 }
 
-fn build_signed_session(key: &[u8], user: &AuthenticatedUser) -> Result<String, StatusCode> {
+fn build_signed_session(
+    key: &[u8],
+    user: &AuthenticatedUser,
+    origin: SessionOrigin,
+) -> Result<String, StatusCode> {
     let now = now_unix_secs();
     let claims = SessionClaims {
         sub: user.username.clone(),
         role: user.role,
+        origin,
         auth_epoch: user.auth_epoch,
         iat: now,
         exp: now.saturating_add(SESSION_DURATION_SECS),
@@ -335,6 +383,7 @@ fn verify_signed_session(
     Ok(Some(SessionUser {
         username: claims.sub,
         role: claims.role,
+        origin: claims.origin,
     }))
 }
 
@@ -410,25 +459,32 @@ pub async fn get_username(jar: &CookieJar) -> String {
     }
 }
 
-#[derive(Copy, Clone, Eq, PartialEq)]
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
 pub enum LoginResult {
     Admin,
     ReadOnly,
     Denied,
 }
 
-fn login_result_for_session(user: Option<SessionUser>, allow_anonymous: bool) -> LoginResult {
+/// Resolves a session's permissions and scope. Local sessions get the access configured in
+/// `lqusers.toml`; SSO sessions get the unscoped permissions of their mapped role, even if a
+/// local account has the same username.
+fn access_for_session(user: Option<SessionUser>, snapshot: &AuthSnapshot) -> SessionAccess {
     match user {
-        Some(SessionUser {
-            role: UserRole::Admin,
-            ..
-        }) => LoginResult::Admin,
-        Some(SessionUser {
-            role: UserRole::ReadOnly,
-            ..
-        }) => LoginResult::ReadOnly,
-        None if allow_anonymous => LoginResult::ReadOnly,
-        None => LoginResult::Denied,
+        Some(user) => {
+            let access = match user.origin {
+                SessionOrigin::Local => snapshot.user_access.get(&user.username).cloned(),
+                SessionOrigin::Sso => None,
+            };
+            SessionAccess::from_user_access(
+                access.unwrap_or_else(|| UserAccess::for_role(user.role)),
+            )
+            .with_username(user.username)
+        }
+        None if snapshot.allow_anonymous => {
+            SessionAccess::from_user_access(UserAccess::for_role(UserRole::ReadOnly))
+        }
+        None => SessionAccess::denied(),
     }
 }
 
@@ -452,48 +508,76 @@ pub async fn auth_layer(
         AuthBootstrapState::Ready => {}
     }
 
-    let login_result = match session_from_cookie(&jar, &snapshot) {
-        Ok(user) => login_result_for_session(user, snapshot.allow_anonymous),
+    let access = match session_from_cookie(&jar, &snapshot) {
+        Ok(user) => access_for_session(user, &snapshot),
         Err(status) => return (status, "Unable to validate session").into_response(),
     };
 
+    let login_result = access.login();
     match login_result {
         LoginResult::Admin | LoginResult::ReadOnly => {
             record_first_login_timestamp_if_needed();
             req.extensions_mut().insert(login_result);
+            req.extensions_mut().insert(access);
             next.run(req).await
         }
         LoginResult::Denied => Redirect::temporary("/login.html").into_response(),
     }
 }
 
-pub async fn login_from_token(token: &str) -> LoginResult {
+pub async fn access_from_token(token: &str) -> SessionAccess {
     let snapshot = auth_snapshot();
     if snapshot.bootstrap_state != AuthBootstrapState::Ready {
-        return LoginResult::Denied;
+        return SessionAccess::denied();
     }
 
     let key = match session_key() {
         Ok(key) => key,
         Err(e) => {
             warn!("Unable to load session key for websocket auth: {e}");
-            return LoginResult::Denied;
+            return SessionAccess::denied();
         }
     };
 
-    let login_result = match verify_signed_session(&key, token, &snapshot) {
-        Ok(user) => login_result_for_session(user, snapshot.allow_anonymous),
+    let access = match verify_signed_session(&key, token, &snapshot) {
+        Ok(user) => access_for_session(user, &snapshot),
         Err(e) => {
             warn!("Unable to verify websocket session token: {e}");
-            LoginResult::Denied
+            SessionAccess::denied()
         }
     };
 
-    if login_result != LoginResult::Denied {
+    if access.login() != LoginResult::Denied {
         record_first_login_timestamp_if_needed();
     }
 
-    login_result
+    access
+}
+
+/// Resolves a presented `/api/v1` bearer token to its stored record. Returns `None` for
+/// unknown, expired or malformed tokens, or when no users have been configured yet.
+pub fn api_token_from_bearer(presented: &str) -> Option<ApiToken> {
+    let snapshot = auth_snapshot();
+    if snapshot.bootstrap_state != AuthBootstrapState::Ready {
+        return None;
+    }
+    verify_api_token_in(&snapshot.api_tokens, presented, now_unix_secs())
+}
+
+/// Describes where a request came from, for the audit log: the peer address, preceded by
+/// the first `X-Forwarded-For` hop when the node manager sits behind a reverse proxy.
+pub fn client_source(peer: SocketAddr, headers: &HeaderMap) -> String {
+    let peer = peer.ip().to_canonical().to_string();
+    let forwarded = headers
+        .get("x-forwarded-for")
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.split(',').next())
+        .map(str::trim)
+        .filter(|value| !value.is_empty());
+    match forwarded {
+        Some(forwarded) => format!("{forwarded} (via {peer})"),
+        None => peer,
+    }
 }
 
 /// Invalidate the cached auth snapshot after user-management changes.
@@ -511,6 +595,9 @@ pub async fn refresh_cached_users() {
 pub struct LoginAttempt {
     pub username: String,
     pub password: String,
+    /// TOTP or recovery code, for users with two-factor authentication.
+    #[serde(default)]
+    pub totp: Option<String>,
 }
 
 pub async fn try_login(
@@ -542,6 +629,9 @@ pub async fn try_login(
         AuthBootstrapState::Ready => {}
     }
 
+    // One login at a time, so concurrent guesses can't each read a stale count of wrong
+    // second-factor codes from lqusers.toml
+    let _login = LOGIN_LOCK.lock();
     let mut users = WebUsers::load_or_create().map_err(|e| {
         warn!("Unable to load users during login: {e}");
         (
@@ -554,14 +644,43 @@ pub async fn try_login(
         )
     })?;
     let authenticated = users
-        .authenticate(&login.username, &login.password)
-        .map_err(|_| {
+        .authenticate_with_second_factor(&login.username, &login.password, login.totp.as_deref())
+        .map_err(|e| {
+            let (status, reason, message) = match e {
+                AuthenticationError::TotpRequired => (
+                    StatusCode::UNAUTHORIZED,
+                    "totp_required",
+                    "Enter the code from your authenticator app.".to_string(),
+                ),
+                AuthenticationError::InvalidTotp => (
+                    StatusCode::UNAUTHORIZED,
+                    "invalid_totp",
+                    "Invalid or already used two-factor code.".to_string(),
+                ),
+                AuthenticationError::TotpLockedOut { retry_after_secs } => (
+                    StatusCode::TOO_MANY_REQUESTS,
+                    "totp_locked",
+                    format!(
+                        "Too many wrong two-factor codes. Try again in {retry_after_secs} seconds."
+                    ),
+                ),
+                AuthenticationError::TotpEnrollmentRequired => (
+                    StatusCode::FORBIDDEN,
+                    "totp_enrollment_required",
+                    "Two-factor authentication is required for this account. Enroll with lqusers totp enroll.".to_string(),
+                ),
+                _ => (
+                    StatusCode::UNAUTHORIZED,
+                    "invalid_credentials",
+                    "Invalid username or password.".to_string(),
+                ),
+            };
             (
-                StatusCode::UNAUTHORIZED,
+                status,
                 Json(LoginResponse {
                     ok: false,
-                    reason: Some("invalid_credentials"),
-                    message: Some("Invalid username or password.".to_string()),
+                    reason: Some(reason),
+                    message: Some(message),
                 }),
             )
         })?;
@@ -578,16 +697,17 @@ pub async fn try_login(
             }),
         )
     })?;
-    let token = build_signed_session(&key, &authenticated).map_err(|status| {
-        (
-            status,
-            Json(LoginResponse {
-                ok: false,
-                reason: Some("session_error"),
-                message: Some("Unable to create session token.".to_string()),
-            }),
-        )
-    })?;
+    let token =
+        build_signed_session(&key, &authenticated, SessionOrigin::Local).map_err(|status| {
+            (
+                status,
+                Json(LoginResponse {
+                    ok: false,
+                    reason: Some("session_error"),
+                    message: Some("Unable to create session token.".to_string()),
+                }),
+            )
+        })?;
 
     record_first_login_timestamp_if_needed();
     Ok((
@@ -692,16 +812,17 @@ pub async fn first_user(
             }),
         )
     })?;
-    let token = build_signed_session(&key, &authenticated).map_err(|status| {
-        (
-            status,
-            Json(LoginResponse {
-                ok: false,
-                reason: Some("session_error"),
-                message: Some("Unable to create session token.".to_string()),
-            }),
-        )
-    })?;
+    let token =
+        build_signed_session(&key, &authenticated, SessionOrigin::Local).map_err(|status| {
+            (
+                status,
+                Json(LoginResponse {
+                    ok: false,
+                    reason: Some("session_error"),
+                    message: Some("Unable to create session token.".to_string()),
+                }),
+            )
+        })?;
 
     record_first_login_timestamp_if_needed();
     Ok((
@@ -713,3 +834,33 @@ pub async fn first_user(
         }),
     ))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sso_sessions_ignore_local_accounts_with_the_same_name() {
+        let snapshot = AuthSnapshot {
+            bootstrap_state: AuthBootstrapState::Ready,
+            auth_epoch: 1,
+            allow_anonymous: false,
+            api_tokens: Vec::new(),
+            user_access: HashMap::from([(
+                "admin".to_string(),
+                UserAccess::for_role(UserRole::Admin),
+            )]),
+        };
+        let session = |origin| SessionUser {
+            username: "admin".to_string(),
+            role: UserRole::ReadOnly,
+            origin,
+        };
+
+        let local = access_for_session(Some(session(SessionOrigin::Local)), &snapshot);
+        assert_eq!(local.login(), LoginResult::Admin);
+        let sso = access_for_session(Some(session(SessionOrigin::Sso)), &snapshot);
+        assert_eq!(sso.login(), LoginResult::ReadOnly);
+        assert_eq!(sso.username(), "admin");
+    }
+}
@@ -0,0 +1,254 @@
+//! Permission and `network.json` scope checks for node manager sessions.
+
+use super::LoginResult;
+use crate::shaped_devices_tracker::{NETWORK_JSON, SHAPED_DEVICES};
+use lqos_config::{Permission, UserAccess};
+use std::collections::HashSet;
+use std::net::IpAddr;
+
+const ANONYMOUS: &str = "Anonymous";
+
+/// What the current session may do, and which part of the network it may see.
+#[derive(Clone, Debug)]
+pub struct SessionAccess {
+    login: LoginResult,
+    access: UserAccess,
+    username: String,
+}
+
+impl SessionAccess {
+    /// A session that may do nothing.
+    pub fn denied() -> Self {
+        Self {
+            login: LoginResult::Denied,
+            access: UserAccess::default(),
+            username: ANONYMOUS.to_string(),
+        }
+    }
+
+    /// Resolves the coarse [`LoginResult`] for a user's effective access. Only unscoped users
+    /// with every permission count as administrators; users without `view` are denied.
+    pub fn from_user_access(access: UserAccess) -> Self {
+        let login = if !access.allows(Permission::View) {
+            LoginResult::Denied
+        } else if access.is_full_admin() {
+            LoginResult::Admin
+        } else {
+            LoginResult::ReadOnly
+        };
+        Self {
+            login,
+            access,
+            username: ANONYMOUS.to_string(),
+        }
+    }
+
+    /// Attaches the signed-in user's name, as recorded in the audit log.
+    pub fn with_username(mut self, username: impl Into<String>) -> Self {
+        self.username = username.into();
+        self
+    }
+
+    /// The signed-in user's name, or `Anonymous`.
+    pub fn username(&self) -> &str {
+        &self.username
+    }
+
+    /// The coarse login level, used by handlers that only distinguish administrators.
+    pub fn login(&self) -> LoginResult {
+        self.login
+    }
+
+    /// Is `permission` granted to this session?
+    pub fn allows(&self, permission: Permission) -> bool {
+        self.login != LoginResult::Denied && self.access.allows(permission)
+    }
+
+    /// The login level to pass to an administrator-only handler that implements `permission`:
+    /// `Admin` when the permission is granted, otherwise the session's own level.
+    pub fn login_for(&self, permission: Permission) -> LoginResult {
+        if self.allows(permission) {
+            LoginResult::Admin
+        } else {
+            self.login
+        }
+    }
+
+    /// Is the session limited to part of the network?
+    pub fn is_scoped(&self) -> bool {
+        self.access.is_scoped()
+    }
+
+    /// May this session list and change web users? Web accounts are not tied to part of the
+    /// network, so scoped sessions never may.
+    pub fn may_manage_users(&self) -> bool {
+        self.allows(Permission::ManageUsers) && !self.is_scoped()
+    }
+
+    /// May this session create, edit or delete an account with `target` access? It may only
+    /// hand out permissions it holds itself, and only full administrators may touch accounts
+    /// with every permission. A user manager is unscoped, so any target scope is narrower.
+    pub fn may_manage_account(&self, target: &UserAccess) -> bool {
+        if !self.may_manage_users() {
+            return false;
+        }
+        if self.login == LoginResult::Admin {
+            return true;
+        }
+        !target.is_full_admin()
+            && target
+                .permissions
+                .iter()
+                .all(|permission| self.access.allows(*permission))
+    }
+
+    /// The nodes this session may see, or `None` when it may see the whole network.
+    pub fn scope_filter(&self) -> Option<ScopeFilter> {
+        if !self.is_scoped() {
+            return None;
+        }
+        let reader = NETWORK_JSON.read();
+        let nodes = reader.get_nodes_when_ready();
+        let names: Vec<&str> = nodes.iter().map(|n| n.name.as_str()).collect();
+        let parents: Vec<&[usize]> = nodes.iter().map(|n| n.parents.as_slice()).collect();
+        Some(ScopeFilter::from_tree(&names, &parents, &self.access.scope))
+    }
+}
+
+/// The set of `network.json` node names visible to a scoped session: each scope root and
+/// everything below it.
+#[derive(Clone, Debug, Default)]
+pub struct ScopeFilter {
+    nodes: HashSet<String>,
+}
+
+impl ScopeFilter {
+    /// Builds the filter from node names and each node's ancestor indices (which include the
+    /// node itself, as in `NetworkJsonNode::parents`).
+    pub fn from_tree(names: &[&str], parents: &[&[usize]], scope: &[String]) -> Self {
+        let roots: HashSet<&str> = scope.iter().map(String::as_str).collect();
+        let nodes = names
+            .iter()
+            .zip(parents)
+            .filter(|(name, ancestors)| {
+                roots.contains(*name)
+                    || ancestors
+                        .iter()
+                        .any(|idx| names.get(*idx).is_some_and(|n| roots.contains(n)))
+            })
+            .map(|(name, _)| name.to_string())
+            .collect();
+        Self { nodes }
+    }
+
+    /// Is the named node (or a circuit whose parent node it is) visible?
+    pub fn allows_node(&self, name: &str) -> bool {
+        self.nodes.contains(name)
+    }
+
+    /// Is the circuit visible? Circuits are visible when their parent node is.
+    pub fn allows_circuit(&self, circuit_id: &str) -> bool {
+        let wanted = circuit_id.to_lowercase();
+        let wanted = wanted.trim();
+        SHAPED_DEVICES.load().devices.iter().any(|d| {
+            d.circuit_id.to_lowercase().trim() == wanted && self.allows_node(&d.parent_node)
+        })
+    }
+
+    /// Does the address belong to a shaped device in a visible circuit?
+    pub fn allows_ip(&self, ip: &IpAddr) -> bool {
+        let query = match ip {
+            IpAddr::V4(v4) => v4.to_ipv6_mapped(),
+            IpAddr::V6(v6) => *v6,
+        };
+        let reader = SHAPED_DEVICES.load();
+        reader
+            .trie
+            .longest_match(query)
+            .and_then(|(_, idx)| reader.devices.get(*idx))
+            .is_some_and(|device| self.allows_node(&device.parent_node))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn scope_covers_subtrees_only() {
+        // Root -> Region A -> Tower 1, Root -> Region B
+        let names = ["Root", "Region A", "Tower 1", "Region B"];
+        let parents: [&[usize]; 4] = [&[0], &[0, 1], &[0, 1, 2], &[0, 3]];
+        let filter = ScopeFilter::from_tree(&names, &parents, &["Region A".to_string()]);
+        assert!(filter.allows_node("Region A"));
+        assert!(filter.allows_node("Tower 1"));
+        assert!(!filter.allows_node("Root"));
+        assert!(!filter.allows_node("Region B"));
+    }
+
+    #[test]
+    fn only_unscoped_full_permissions_are_admin() {
+        let admin =
+            SessionAccess::from_user_access(UserAccess::for_role(lqos_config::UserRole::Admin));
+        assert_eq!(admin.login(), LoginResult::Admin);
+
+        let tech = SessionAccess::from_user_access(UserAccess {
+            permissions: vec![Permission::View, Permission::AdjustSpeeds],
+            scope: vec!["Tower 1".to_string()],
+        });
+        assert_eq!(tech.login(), LoginResult::ReadOnly);
+        assert_eq!(tech.login_for(Permission::AdjustSpeeds), LoginResult::Admin);
+        assert_eq!(tech.login_for(Permission::Reload), LoginResult::ReadOnly);
+
+        let blind = SessionAccess::from_user_access(UserAccess {
+            permissions: vec![Permission::Reload],
+            scope: Vec::new(),
+        });
+        assert_eq!(blind.login(), LoginResult::Denied);
+        assert!(!blind.allows(Permission::Reload));
+    }
+
+    fn user_manager(scope: Vec<String>) -> SessionAccess {
+        SessionAccess::from_user_access(UserAccess {
+            permissions: vec![
+                Permission::View,
+                Permission::PacketCapture,
+                Permission::ManageUsers,
+            ],
+            scope,
+        })
+    }
+
+    #[test]
+    fn scoped_sessions_cannot_manage_users() {
+        let manager = user_manager(vec!["Tower 1".to_string()]);
+        assert!(!manager.may_manage_users());
+        assert!(
+            !manager.may_manage_account(&UserAccess::for_role(lqos_config::UserRole::ReadOnly))
+        );
+    }
+
+    #[test]
+    fn user_managers_cannot_create_or_touch_administrators() {
+        let manager = user_manager(Vec::new());
+        assert!(manager.may_manage_users());
+        assert!(!manager.may_manage_account(&UserAccess::for_role(lqos_config::UserRole::Admin)));
+    }
+
+    #[test]
+    fn user_managers_only_grant_permissions_they_hold() {
+        let manager = user_manager(Vec::new());
+        assert!(manager.may_manage_account(&UserAccess::for_role(lqos_config::UserRole::ReadOnly)));
+        assert!(!manager.may_manage_account(&UserAccess {
+            permissions: vec![Permission::View, Permission::Reload],
+            scope: vec!["Tower 1".to_string()],
+        }));
+    }
+
+    #[test]
+    fn full_administrators_manage_any_account() {
+        let admin =
+            SessionAccess::from_user_access(UserAccess::for_role(lqos_config::UserRole::Admin));
+        assert!(admin.may_manage_account(&UserAccess::for_role(lqos_config::UserRole::Admin)));
+    }
+}
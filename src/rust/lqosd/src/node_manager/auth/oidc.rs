@@ -0,0 +1,682 @@
+//! OpenID Connect single sign-on (authorization code flow with PKCE).
+//!
+//! `/oidc/login` redirects to the identity provider, which sends the browser back to
+//! `/oidc/callback`; the callback's `state` must match a cookie set on the same browser. The
+//! code is exchanged directly with the provider's token endpoint over the back channel, so
+//! the ID token's issuer, audience, expiry and nonce are checked rather than its signature
+//! (OIDC Core 3.1.3.7). The result is an ordinary signed session cookie;
+//! local users keep working as break-glass accounts.
+
+use super::{
+    AuthBootstrapState, SessionOrigin, auth_snapshot, build_session_cookie, build_signed_session,
+    now_unix_secs, session_key,
+};
+use axum::Json;
+use axum::extract::Query;
+use axum::http::header;
+use axum::response::{IntoResponse, Redirect, Response};
+use axum_extra::extract::CookieJar;
+use axum_extra::extract::cookie::Cookie;
+use base64::Engine;
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use lqos_config::{AuthenticatedUser, OidcConfig, UserRole, load_config};
+use once_cell::sync::Lazy;
+use parking_lot::Mutex;
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::time::Duration;
+use tracing::{info, warn};
+
+/// How long a login may take between leaving for the IdP and returning.
+const PENDING_LOGIN_TTL_SECS: u64 = 600;
+/// Holds a login's `state` in the browser that started it, so a callback URL handed to
+/// someone else cannot sign them in to the sender's account.
+const STATE_COOKIE_NAME: &str = "OIDC-State";
+const STATE_COOKIE_PATH: &str = "/oidc";
+/// Cap on outstanding logins, so unauthenticated visitors cannot grow the map without bound.
+const MAX_PENDING_LOGINS: usize = 1024;
+const DISCOVERY_TTL_SECS: u64 = 3600;
+/// Allowed clock difference between the shaper and the IdP.
+const CLOCK_SKEW_SECS: u64 = 60;
+
+/// Endpoints read from the provider's discovery document.
+#[derive(Clone, Debug, Deserialize)]
+pub(super) struct ProviderMetadata {
+    issuer: String,
+    authorization_endpoint: String,
+    token_endpoint: String,
+}
+
+struct PendingLogin {
+    nonce: String,
+    code_verifier: String,
+    created: u64,
+}
+
+static PENDING_LOGINS: Lazy<Mutex<HashMap<String, PendingLogin>>> =
+    Lazy::new(|| Mutex::new(HashMap::new()));
+static DISCOVERY_CACHE: Lazy<Mutex<Option<(String, u64, ProviderMetadata)>>> =
+    Lazy::new(|| Mutex::new(None));
+
+/// Why an SSO login failed. The code is passed to the login page as `?sso_error=`.
+#[derive(Debug, PartialEq, Eq)]
+pub(super) enum OidcError {
+    Disabled,
+    NotReady,
+    Provider,
+    InvalidState,
+    InvalidToken,
+    NoRole,
+    Session,
+}
+
+impl OidcError {
+    fn code(&self) -> &'static str {
+        match self {
+            OidcError::Disabled => "disabled",
+            OidcError::NotReady => "first_run_required",
+            OidcError::Provider => "provider_error",
+            OidcError::InvalidState => "invalid_state",
+            OidcError::InvalidToken => "invalid_token",
+            OidcError::NoRole => "no_role",
+            OidcError::Session => "session_error",
+        }
+    }
+}
+
+fn random_token() -> String {
+    let mut bytes = [0u8; 32];
+    rand::thread_rng().fill_bytes(&mut bytes);
+    URL_SAFE_NO_PAD.encode(bytes)
+}
+
+fn pkce_challenge(verifier: &str) -> String {
+    URL_SAFE_NO_PAD.encode(Sha256::digest(verifier.as_bytes()))
+}
+
+/// Percent-encodes a value for a query string or form body.
+fn url_encode(value: &str) -> String {
+    let mut out = String::with_capacity(value.len());
+    for byte in value.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => {
+                out.push(byte as char)
+            }
+            _ => out.push_str(&format!("%{byte:02X}")),
+        }
+    }
+    out
+}
+
+fn encode_pairs(pairs: &[(&str, &str)]) -> String {
+    pairs
+        .iter()
+        .map(|(k, v)| format!("{}={}", url_encode(k), url_encode(v)))
+        .collect::<Vec<_>>()
+        .join("&")
+}
+
+fn http_client() -> Result<reqwest::Client, OidcError> {
+    reqwest::Client::builder()
+        .connect_timeout(Duration::from_secs(5))
+        .timeout(Duration::from_secs(10))
+        .build()
+        .map_err(|e| {
+            warn!("Unable to build OIDC HTTP client: {e}");
+            OidcError::Provider
+        })
+}
+
+/// Fetches (or returns the cached) discovery document for `issuer`.
+pub(super) async fn discover(issuer: &str) -> Result<ProviderMetadata, OidcError> {
+    let issuer = issuer.trim_end_matches('/');
+    let now = now_unix_secs();
+    if let Some((cached_issuer, fetched, metadata)) = &*DISCOVERY_CACHE.lock()
+        && cached_issuer == issuer
+        && now.saturating_sub(*fetched) < DISCOVERY_TTL_SECS
+    {
+        return Ok(metadata.clone());
+    }
+
+    let url = format!("{issuer}/.well-known/openid-configuration");
+    let metadata: ProviderMetadata = http_client()?
+        .get(&url)
+        .send()
+        .await
+        .and_then(|r| r.error_for_status())
+        .map_err(|e| {
+            warn!("OIDC discovery request to {url} failed: {e}");
+            OidcError::Provider
+        })?
+        .json()
+        .await
+        .map_err(|e| {
+            warn!("OIDC discovery document from {url} is invalid: {e}");
+            OidcError::Provider
+        })?;
+    if issuer.starts_with("https://") && !metadata.token_endpoint.starts_with("https://") {
+        warn!(
+            "OIDC token endpoint {} is not HTTPS; refusing to trust its ID tokens",
+            metadata.token_endpoint
+        );
+        return Err(OidcError::Provider);
+    }
+    if metadata.issuer.trim_end_matches('/') != issuer {
+        warn!(
+            "OIDC discovery issuer {} does not match configured issuer {issuer}",
+            metadata.issuer
+        );
+        return Err(OidcError::Provider);
+    }
+
+    *DISCOVERY_CACHE.lock() = Some((issuer.to_string(), now, metadata.clone()));
+    Ok(metadata)
+}
+
+fn authorization_url(
+    config: &OidcConfig,
+    metadata: &ProviderMetadata,
+    state: &str,
+    nonce: &str,
+    code_verifier: &str,
+) -> String {
+    let mut scopes = vec!["openid".to_string()];
+    for scope in &config.scopes {
+        if !scopes.contains(scope) {
+            scopes.push(scope.clone());
+        }
+    }
+    let scope = scopes.join(" ");
+    let challenge = pkce_challenge(code_verifier);
+    let separator = if metadata.authorization_endpoint.contains('?') {
+        '&'
+    } else {
+        '?'
+    };
+    format!(
+        "{}{separator}{}",
+        metadata.authorization_endpoint,
+        encode_pairs(&[
+            ("response_type", "code"),
+            ("client_id", &config.client_id),
+            ("redirect_uri", &config.redirect_url),
+            ("scope", &scope),
+            ("state", state),
+            ("nonce", nonce),
+            ("code_challenge", &challenge),
+            ("code_challenge_method", "S256"),
+        ])
+    )
+}
+
+#[derive(Deserialize)]
+struct TokenResponse {
+    id_token: String,
+}
+
+/// Exchanges an authorization code for the provider's ID token.
+pub(super) async fn exchange_code(
+    config: &OidcConfig,
+    metadata: &ProviderMetadata,
+    code: &str,
+    code_verifier: &str,
+) -> Result<String, OidcError> {
+    let mut pairs = vec![
+        ("grant_type", "authorization_code"),
+        ("code", code),
+        ("redirect_uri", config.redirect_url.as_str()),
+        ("client_id", config.client_id.as_str()),
+        ("code_verifier", code_verifier),
+    ];
+    if !config.client_secret.is_empty() {
+        pairs.push(("client_secret", config.client_secret.as_str()));
+    }
+    let response: TokenResponse = http_client()?
+        .post(&metadata.token_endpoint)
+        .header(
+            reqwest::header::CONTENT_TYPE,
+            "application/x-www-form-urlencoded",
+        )
+        .body(encode_pairs(&pairs))
+        .send()
+        .await
+        .and_then(|r| r.error_for_status())
+        .map_err(|e| {
+            warn!("OIDC token request failed: {e}");
+            OidcError::Provider
+        })?
+        .json()
+        .await
+        .map_err(|e| {
+            warn!("OIDC token response is invalid: {e}");
+            OidcError::Provider
+        })?;
+    Ok(response.id_token)
+}
+
+/// Identity extracted from a validated ID token.
+#[derive(Debug, PartialEq, Eq)]
+pub(super) struct OidcIdentity {
+    pub username: String,
+    pub role: UserRole,
+}
+
+fn claim_strings(value: Option<&Value>) -> Vec<String> {
+    match value {
+        Some(Value::String(s)) => vec![s.clone()],
+        Some(Value::Array(items)) => items
+            .iter()
+            .filter_map(|item| item.as_str().map(str::to_string))
+            .collect(),
+        _ => Vec::new(),
+    }
+}
+
+/// Looks up a claim, following `.` separators into nested objects
+/// (e.g. `realm_access.roles` for Keycloak).
+fn lookup_claim<'a>(claims: &'a Value, path: &str) -> Option<&'a Value> {
+    if let Some(value) = claims.get(path) {
+        return Some(value);
+    }
+    path.split('.')
+        .try_fold(claims, |value, segment| value.get(segment))
+}
+
+/// Checks the ID token claims and maps them to a username and role.
+pub(super) fn validate_id_token(
+    config: &OidcConfig,
+    metadata: &ProviderMetadata,
+    id_token: &str,
+    expected_nonce: &str,
+    now_unix: u64,
+) -> Result<OidcIdentity, OidcError> {
+    let payload = id_token
+        .split('.')
+        .nth(1)
+        .and_then(|p| URL_SAFE_NO_PAD.decode(p.trim_end_matches('=')).ok())
+        .ok_or(OidcError::InvalidToken)?;
+    let claims: Value = serde_json::from_slice(&payload).map_err(|_| OidcError::InvalidToken)?;
+
+    let issuer_ok = claims
+        .get("iss")
+        .and_then(Value::as_str)
+        .is_some_and(|iss| iss.trim_end_matches('/') == metadata.issuer.trim_end_matches('/'));
+    let audience_ok = claim_strings(claims.get("aud")).contains(&config.client_id);
+    let expiry_ok = claims
+        .get("exp")
+        .and_then(Value::as_u64)
+        .is_some_and(|exp| exp.saturating_add(CLOCK_SKEW_SECS) > now_unix);
+    let nonce_ok = claims.get("nonce").and_then(Value::as_str) == Some(expected_nonce);
+    if !(issuer_ok && audience_ok && expiry_ok && nonce_ok) {
+        return Err(OidcError::InvalidToken);
+    }
+
+    let username = [config.username_claim.as_str(), "email", "sub"]
+        .iter()
+        .find_map(|claim| {
+            lookup_claim(&claims, claim)
+                .and_then(Value::as_str)
+                .filter(|name| !name.trim().is_empty())
+        })
+        .ok_or(OidcError::InvalidToken)?
+        .to_string();
+    let role_values = claim_strings(lookup_claim(&claims, &config.role_claim));
+    let role = config
+        .role_for_claim_values(&role_values)
+        .ok_or(OidcError::NoRole)?;
+    Ok(OidcIdentity { username, role })
+}
+
+fn enabled_config() -> Result<OidcConfig, OidcError> {
+    let config = load_config().map_err(|_| OidcError::Disabled)?;
+    if !config.oidc.enabled {
+        return Err(OidcError::Disabled);
+    }
+    if auth_snapshot().bootstrap_state != AuthBootstrapState::Ready {
+        return Err(OidcError::NotReady);
+    }
+    Ok(config.oidc.clone())
+}
+
+fn login_error_redirect(error: OidcError) -> Response {
+    Redirect::temporary(&format!("/login.html?sso_error={}", error.code())).into_response()
+}
+
+#[derive(Serialize)]
+pub struct OidcStatus {
+    enabled: bool,
+    label: String,
+}
+
+/// Tells the login page whether to offer SSO.
+pub async fn oidc_status() -> Json<OidcStatus> {
+    let status = match load_config() {
+        Ok(config) if config.oidc.enabled => OidcStatus {
+            enabled: true,
+            label: config.oidc.button_label.clone(),
+        },
+        _ => OidcStatus {
+            enabled: false,
+            label: String::new(),
+        },
+    };
+    Json(status)
+}
+
+/// Starts an SSO login by redirecting to the provider.
+pub async fn oidc_login() -> Response {
+    let config = match enabled_config() {
+        Ok(config) => config,
+        Err(e) => return login_error_redirect(e),
+    };
+    let metadata = match discover(&config.issuer).await {
+        Ok(metadata) => metadata,
+        Err(e) => return login_error_redirect(e),
+    };
+
+    let state = random_token();
+    let nonce = random_token();
+    let code_verifier = random_token();
+    let url = authorization_url(&config, &metadata, &state, &nonce, &code_verifier);
+
+    let now = now_unix_secs();
+    let mut pending = PENDING_LOGINS.lock();
+    pending.retain(|_, login| now.saturating_sub(login.created) < PENDING_LOGIN_TTL_SECS);
+    if pending.len() >= MAX_PENDING_LOGINS {
+        warn!("Too many outstanding SSO logins; refusing a new one");
+        return login_error_redirect(OidcError::Provider);
+    }
+    let state_cookie = format!(
+        "{STATE_COOKIE_NAME}={state}; Path={STATE_COOKIE_PATH}; Max-Age={PENDING_LOGIN_TTL_SECS}; HttpOnly; SameSite=Lax"
+    );
+    pending.insert(
+        state,
+        PendingLogin {
+            nonce,
+            code_verifier,
+            created: now,
+        },
+    );
+    drop(pending);
+
+    ([(header::SET_COOKIE, state_cookie)], Redirect::to(&url)).into_response()
+}
+
+#[derive(Deserialize)]
+pub struct OidcCallback {
+    code: Option<String>,
+    state: Option<String>,
+    error: Option<String>,
+}
+
+/// Claims the pending login for a callback's `state`, which must also be the state stored
+/// in the browser's cookie.
+fn take_pending_login(
+    state: Option<&str>,
+    browser_state: Option<&str>,
+) -> Result<PendingLogin, OidcError> {
+    state
+        .filter(|state| Some(*state) == browser_state)
+        .and_then(|state| PENDING_LOGINS.lock().remove(state))
+        .filter(|login| now_unix_secs().saturating_sub(login.created) < PENDING_LOGIN_TTL_SECS)
+        .ok_or(OidcError::InvalidState)
+}
+
+async fn complete_login(
+    callback: OidcCallback,
+    browser_state: Option<&str>,
+) -> Result<OidcIdentity, OidcError> {
+    let config = enabled_config()?;
+    let pending = take_pending_login(callback.state.as_deref(), browser_state)?;
+    if let Some(error) = callback.error {
+        warn!("OIDC provider returned an error: {error}");
+        return Err(OidcError::Provider);
+    }
+    let code = callback.code.ok_or(OidcError::Provider)?;
+
+    let metadata = discover(&config.issuer).await?;
+    let id_token = exchange_code(&config, &metadata, &code, &pending.code_verifier).await?;
+    validate_id_token(
+        &config,
+        &metadata,
+        &id_token,
+        &pending.nonce,
+        now_unix_secs(),
+    )
+}
+
+/// Completes an SSO login and issues the session cookie.
+pub async fn oidc_callback(jar: CookieJar, Query(callback): Query<OidcCallback>) -> Response {
+    let browser_state = jar
+        .get(STATE_COOKIE_NAME)
+        .map(|cookie| cookie.value().to_string());
+    let mut state_cookie = Cookie::from(STATE_COOKIE_NAME);
+    state_cookie.set_path(STATE_COOKIE_PATH);
+    let jar = jar.remove(state_cookie);
+
+    let identity = match complete_login(callback, browser_state.as_deref()).await {
+        Ok(identity) => identity,
+        Err(e) => {
+            warn!("SSO login failed: {}", e.code());
+            return (jar, login_error_redirect(e)).into_response();
+        }
+    };
+
+    let authenticated = AuthenticatedUser {
+        username: identity.username,
+        role: identity.role,
+        auth_epoch: auth_snapshot().auth_epoch,
+        password_upgraded: false,
+    };
+    let token = match session_key()
+        .ok()
+        .and_then(|key| build_signed_session(&key, &authenticated, SessionOrigin::Sso).ok())
+    {
+        Some(token) => token,
+        None => return (jar, login_error_redirect(OidcError::Session)).into_response(),
+    };
+    info!(
+        "SSO login for {} as {}",
+        authenticated.username, authenticated.role
+    );
+    (
+        jar.add(build_session_cookie(token)),
+        Redirect::to("/index.html"),
+    )
+        .into_response()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::Router;
+    use axum::routing::{get, post};
+
+    fn config(issuer: &str) -> OidcConfig {
+        OidcConfig {
+            enabled: true,
+            issuer: issuer.to_string(),
+            client_id: "libreqos".to_string(),
+            client_secret: "secret".to_string(),
+            redirect_url: "https://shaper.example.com/oidc/callback".to_string(),
+            role_claim: "realm_access.roles".to_string(),
+            admin_values: vec!["noc-admins".to_string()],
+            read_only_values: vec!["noc".to_string()],
+            ..OidcConfig::default()
+        }
+    }
+
+    fn id_token(claims: Value) -> String {
+        format!(
+            "{}.{}.signature",
+            URL_SAFE_NO_PAD.encode(br#"{"alg":"RS256"}"#),
+            URL_SAFE_NO_PAD.encode(claims.to_string())
+        )
+    }
+
+    /// Runs a minimal IdP on localhost serving discovery and a token endpoint that checks the
+    /// code and PKCE verifier, then returns an ID token carrying `nonce`.
+    async fn mock_idp(verifier: String, nonce: &'static str) -> String {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0")
+            .await
+            .expect("bind mock IdP");
+        let issuer = format!("http://{}", listener.local_addr().expect("addr"));
+        let discovery = serde_json::json!({
+            "issuer": issuer,
+            "authorization_endpoint": format!("{issuer}/authorize"),
+            "token_endpoint": format!("{issuer}/token"),
+        });
+        let token = id_token(serde_json::json!({
+            "iss": issuer,
+            "aud": ["libreqos", "other"],
+            "exp": now_unix_secs() + 300,
+            "nonce": nonce,
+            "preferred_username": "alice",
+            "realm_access": { "roles": ["noc", "noc-admins"] },
+        }));
+        let app = Router::new()
+            .route(
+                "/.well-known/openid-configuration",
+                get(move || async move { Json(discovery) }),
+            )
+            .route(
+                "/token",
+                post(move |body: String| async move {
+                    let expected = format!("code_verifier={}", url_encode(&verifier));
+                    if body.contains("code=good-code") && body.contains(&expected) {
+                        Json(serde_json::json!({ "id_token": token })).into_response()
+                    } else {
+                        axum::http::StatusCode::BAD_REQUEST.into_response()
+                    }
+                }),
+            );
+        tokio::spawn(async move {
+            let _ = axum::serve(listener, app).await;
+        });
+        issuer
+    }
+
+    #[tokio::test]
+    async fn login_flow_against_mock_idp() {
+        let verifier = random_token();
+        let issuer = mock_idp(verifier.clone(), "n-1").await;
+        let cfg = config(&issuer);
+
+        let metadata = discover(&issuer).await.expect("discovery");
+        let url = authorization_url(&cfg, &metadata, "s-1", "n-1", &verifier);
+        assert!(url.starts_with(&format!("{issuer}/authorize?response_type=code")));
+        assert!(url.contains(&format!("code_challenge={}", pkce_challenge(&verifier))));
+
+        assert_eq!(
+            exchange_code(&cfg, &metadata, "bad-code", &verifier).await,
+            Err(OidcError::Provider)
+        );
+        let token = exchange_code(&cfg, &metadata, "good-code", &verifier)
+            .await
+            .expect("token exchange");
+
+        let now = now_unix_secs();
+        assert_eq!(
+            validate_id_token(&cfg, &metadata, &token, "n-1", now),
+            Ok(OidcIdentity {
+                username: "alice".to_string(),
+                role: UserRole::Admin,
+            })
+        );
+        assert_eq!(
+            validate_id_token(&cfg, &metadata, &token, "n-2", now),
+            Err(OidcError::InvalidToken)
+        );
+        assert_eq!(
+            validate_id_token(&cfg, &metadata, &token, "n-1", now + 600),
+            Err(OidcError::InvalidToken)
+        );
+    }
+
+    #[test]
+    fn callback_state_must_match_the_browser_cookie() {
+        let pending = || PendingLogin {
+            nonce: "n".to_string(),
+            code_verifier: "v".to_string(),
+            created: now_unix_secs(),
+        };
+        PENDING_LOGINS.lock().insert("s-2".to_string(), pending());
+        assert!(matches!(
+            take_pending_login(Some("s-2"), Some("other")),
+            Err(OidcError::InvalidState)
+        ));
+        assert!(matches!(
+            take_pending_login(Some("s-2"), None),
+            Err(OidcError::InvalidState)
+        ));
+        assert!(take_pending_login(Some("s-2"), Some("s-2")).is_ok());
+        assert!(matches!(
+            take_pending_login(Some("s-2"), Some("s-2")),
+            Err(OidcError::InvalidState)
+        ));
+    }
+
+    #[test]
+    fn unmapped_users_are_rejected() {
+        let cfg = config("https://idp.example.com");
+        let metadata = ProviderMetadata {
+            issuer: "https://idp.example.com".to_string(),
+            authorization_endpoint: "https://idp.example.com/auth".to_string(),
+            token_endpoint: "https://idp.example.com/token".to_string(),
+        };
+        let token = id_token(serde_json::json!({
+            "iss": "https://idp.example.com",
+            "aud": "libreqos",
+            "exp": 2_000,
+            "nonce": "n",
+            "email": "bob@example.com",
+            "realm_access": { "roles": ["sales"] },
+        }));
+        assert_eq!(
+            validate_id_token(&cfg, &metadata, &token, "n", 1_000),
+            Err(OidcError::NoRole)
+        );
+
+        let cfg = OidcConfig {
+            default_role: Some(UserRole::ReadOnly),
+            ..cfg
+        };
+        assert_eq!(
+            validate_id_token(&cfg, &metadata, &token, "n", 1_000),
+            Ok(OidcIdentity {
+                username: "bob@example.com".to_string(),
+                role: UserRole::ReadOnly,
+            })
+        );
+    }
+
+    #[test]
+    fn empty_preferred_claim_falls_back_to_email() {
+        let cfg = config("https://idp.example.com");
+        let metadata = ProviderMetadata {
+            issuer: "https://idp.example.com".to_string(),
+            authorization_endpoint: "https://idp.example.com/auth".to_string(),
+            token_endpoint: "https://idp.example.com/token".to_string(),
+        };
+        let token = id_token(serde_json::json!({
+            "iss": "https://idp.example.com",
+            "aud": "libreqos",
+            "exp": 2_000,
+            "nonce": "n",
+            "preferred_username": " ",
+            "email": "carol@example.com",
+            "sub": "8c1f",
+            "realm_access": { "roles": ["noc"] },
+        }));
+        assert_eq!(
+            validate_id_token(&cfg, &metadata, &token, "n", 1_000),
+            Ok(OidcIdentity {
+                username: "carol@example.com".to_string(),
+                role: UserRole::ReadOnly,
+            })
+        );
+    }
+}
@@ -0,0 +1,711 @@
+//! Federation view: this node manager polls peer `lqosd` instances over their `/api/v1`
+//! REST API and presents a combined overview, network tree and circuit search.
+//!
+//! A background thread polls every peer in `[federation]` each `poll_interval_seconds`
+//! (in parallel, so one slow peer does not hold up the rest) and keeps the latest result.
+//! A peer that fails a poll keeps its last tree but is marked unreachable and left out of
+//! the totals. Circuit searches are forwarded to the peers when asked. Peers are reached
+//! through [`PeerSource`] so tests can substitute simulated shapers.
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use arc_swap::ArcSwap;
+use lqos_config::{Config, FederationPeer};
+use lqos_utils::units::DownUpOrder;
+use lqos_utils::unix_time::unix_now;
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use tracing::{debug, warn};
+
+use super::api_v1::{ApiSite, ApiThroughput};
+use super::local_api::directories::{
+    self, CircuitDirectoryPage, CircuitDirectoryQuery, CircuitDirectoryRow,
+};
+use super::local_api::network_tree_lite::{self, NetworkTreeLiteNode};
+
+/// Latest poll result for each configured peer, in configuration order.
+static PEERS: Lazy<ArcSwap<Vec<PeerState>>> = Lazy::new(|| ArcSwap::from_pointee(Vec::new()));
+
+/// Most circuits returned by one federated search, per shaper.
+const SEARCH_LIMIT: usize = 50;
+
+/// Name of the synthetic node joining every shaper's tree.
+const FEDERATION_ROOT: &str = "Federation";
+
+/// Everything the federation view needs from one shaper.
+#[derive(Clone, Debug, Default)]
+pub(crate) struct PeerData {
+    pub throughput: ApiThroughput,
+    pub sites: Vec<(usize, NetworkTreeLiteNode)>,
+    pub urgent_issues: usize,
+}
+
+/// A shaper that can be polled and searched.
+pub(crate) trait PeerSource: Send + Sync {
+    fn name(&self) -> &str;
+    fn url(&self) -> &str;
+    fn fetch(&self) -> Result<PeerData, String>;
+    fn search_circuits(&self, search: &str) -> Result<Vec<CircuitDirectoryRow>, String>;
+}
+
+#[derive(Clone, Debug)]
+struct PeerState {
+    summary: FederatedShaper,
+    sites: Vec<(usize, NetworkTreeLiteNode)>,
+}
+
+/// One shaper's row in the federation overview.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct FederatedShaper {
+    pub name: String,
+    /// Base URL of the peer's node manager; `None` for this shaper.
+    pub url: Option<String>,
+    pub local: bool,
+    pub reachable: bool,
+    pub last_error: Option<String>,
+    /// Unix time of the last successful poll.
+    pub last_update_unix: u64,
+    pub bits_per_second: DownUpOrder<u64>,
+    pub shaped_bits_per_second: DownUpOrder<u64>,
+    pub packets_per_second: DownUpOrder<u64>,
+    /// Nodes in the shaper's network tree, excluding its root.
+    pub nodes: usize,
+    pub urgent_issues: usize,
+}
+
+/// Aggregated dashboard across this shaper and its reachable peers.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct FederationOverview {
+    pub enabled: bool,
+    pub shapers: Vec<FederatedShaper>,
+    pub reachable_shapers: usize,
+    pub total_bits_per_second: DownUpOrder<u64>,
+    pub total_shaped_bits_per_second: DownUpOrder<u64>,
+    pub total_packets_per_second: DownUpOrder<u64>,
+    pub total_urgent_issues: usize,
+}
+
+/// A node in the combined tree, tagged with the shaper that owns it.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct FederatedTreeNode {
+    pub index: usize,
+    pub shaper: String,
+    #[serde(flatten)]
+    pub node: NetworkTreeLiteNode,
+}
+
+/// A circuit found on one of the shapers.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct FederatedCircuitRow {
+    pub shaper: String,
+    /// Base URL of the peer's node manager; `None` for this shaper.
+    pub shaper_url: Option<String>,
+    #[serde(flatten)]
+    pub row: CircuitDirectoryRow,
+}
+
+/// Circuit search results from every shaper, plus the peers that could not be searched.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct FederatedCircuitSearch {
+    pub rows: Vec<FederatedCircuitRow>,
+    pub errors: Vec<String>,
+}
+
+/// Starts the peer polling thread. It idles while federation is disabled.
+pub fn start_federation_poller() {
+    let spawned = std::thread::Builder::new()
+        .name("federation".to_string())
+        .spawn(|| {
+            loop {
+                let interval = match lqos_config::load_config() {
+                    Ok(config) if config.federation.enabled => {
+                        let peers = http_peers(&config);
+                        let previous = PEERS.load_full();
+                        PEERS.store(Arc::new(poll_peers(&peers, &previous)));
+                        config.federation.poll_interval_seconds
+                    }
+                    Ok(config) => {
+                        PEERS.store(Arc::new(Vec::new()));
+                        config.federation.poll_interval_seconds
+                    }
+                    Err(e) => {
+                        warn!("Unable to load config for federation: {e:?}");
+                        60
+                    }
+                };
+                std::thread::sleep(Duration::from_secs(interval.max(1)));
+            }
+        });
+    if let Err(e) = spawned {
+        warn!("Unable to start federation poller: {e}");
+    }
+}
+
+/// Overview of this shaper and every peer.
+pub fn federation_overview() -> FederationOverview {
+    let enabled = lqos_config::load_config().is_ok_and(|c| c.federation.enabled);
+    let mut shapers = vec![local_state().summary];
+    shapers.extend(PEERS.load().iter().map(|peer| peer.summary.clone()));
+    build_overview(enabled, shapers)
+}
+
+/// The network trees of this shaper and every peer, joined under one root.
+pub fn federation_tree() -> Vec<FederatedTreeNode> {
+    let mut trees = vec![local_state()];
+    trees.extend(PEERS.load().iter().cloned());
+    combine_trees(&trees)
+}
+
+/// Searches circuits on this shaper and, when federation is enabled, on every peer.
+/// Blocks on network requests; call from a blocking context.
+pub fn federation_circuit_search(search: &str) -> FederatedCircuitSearch {
+    let local_rows = local_circuit_search(search);
+    let local_name = local_name();
+    let peers = match lqos_config::load_config() {
+        Ok(config) if config.federation.enabled => http_peers(&config),
+        _ => Vec::new(),
+    };
+    search_all(&local_name, local_rows, &peers, search)
+}
+
+fn build_overview(enabled: bool, shapers: Vec<FederatedShaper>) -> FederationOverview {
+    let mut overview = FederationOverview {
+        enabled,
+        shapers: Vec::new(),
+        reachable_shapers: 0,
+        total_bits_per_second: DownUpOrder::zeroed(),
+        total_shaped_bits_per_second: DownUpOrder::zeroed(),
+        total_packets_per_second: DownUpOrder::zeroed(),
+        total_urgent_issues: 0,
+    };
+    for shaper in shapers.iter().filter(|s| s.reachable) {
+        overview.reachable_shapers += 1;
+        overview
+            .total_bits_per_second
+            .checked_add(shaper.bits_per_second);
+        overview
+            .total_shaped_bits_per_second
+            .checked_add(shaper.shaped_bits_per_second);
+        overview
+            .total_packets_per_second
+            .checked_add(shaper.packets_per_second);
+        overview.total_urgent_issues += shaper.urgent_issues;
+    }
+    overview.shapers = shapers;
+    overview
+}
+
+fn local_name() -> String {
+    lqos_config::load_config()
+        .map(|c| c.node_name.clone())
+        .ok()
+        .filter(|name| !name.trim().is_empty())
+        .unwrap_or_else(|| "This shaper".to_string())
+}
+
+fn local_state() -> PeerState {
+    let data = PeerData {
+        throughput: ApiThroughput::from_response(crate::throughput_tracker::current_throughput())
+            .unwrap_or_default(),
+        sites: network_tree_lite::network_tree_lite_data(),
+        urgent_issues: crate::urgent::list().len(),
+    };
+    let mut state = PeerState::from_data(&local_name(), None, data);
+    state.summary.local = true;
+    state
+}
+
+fn local_circuit_search(search: &str) -> Vec<CircuitDirectoryRow> {
+    directories::circuit_directory_page(CircuitDirectoryQuery {
+        page: Some(0),
+        page_size: Some(SEARCH_LIMIT),
+        search: Some(search.to_string()),
+    })
+    .rows
+}
+
+impl PeerState {
+    fn from_data(name: &str, url: Option<String>, data: PeerData) -> Self {
+        Self {
+            summary: FederatedShaper {
+                name: name.to_string(),
+                url,
+                local: false,
+                reachable: true,
+                last_error: None,
+                last_update_unix: unix_now().unwrap_or_default(),
+                bits_per_second: data.throughput.bits_per_second,
+                shaped_bits_per_second: data.throughput.shaped_bits_per_second,
+                packets_per_second: data.throughput.packets_per_second,
+                nodes: data.sites.len().saturating_sub(1),
+                urgent_issues: data.urgent_issues,
+            },
+            sites: data.sites,
+        }
+    }
+
+    /// A failed poll: keep the last tree, but mark the peer unreachable with no traffic.
+    fn unreachable(peer: &dyn PeerSource, previous: Option<&PeerState>, error: String) -> Self {
+        let mut state = previous.cloned().unwrap_or_else(|| {
+            PeerState::from_data(
+                peer.name(),
+                Some(peer.url().to_string()),
+                PeerData::default(),
+            )
+        });
+        state.summary.reachable = false;
+        state.summary.last_error = Some(error);
+        state.summary.bits_per_second = DownUpOrder::zeroed();
+        state.summary.shaped_bits_per_second = DownUpOrder::zeroed();
+        state.summary.packets_per_second = DownUpOrder::zeroed();
+        if previous.is_none() {
+            state.summary.last_update_unix = 0;
+        }
+        state
+    }
+}
+
+fn poll_peers(peers: &[Box<dyn PeerSource>], previous: &[PeerState]) -> Vec<PeerState> {
+    std::thread::scope(|scope| {
+        let handles: Vec<_> = peers
+            .iter()
+            .map(|peer| scope.spawn(move || (peer, peer.fetch())))
+            .collect();
+        handles
+            .into_iter()
+            .filter_map(|handle| handle.join().ok())
+            .map(|(peer, result)| {
+                let last = previous.iter().find(|p| p.summary.name == peer.name());
+                match result {
+                    Ok(data) => {
+                        debug!("Federation peer {} polled", peer.name());
+                        PeerState::from_data(peer.name(), Some(peer.url().to_string()), data)
+                    }
+                    Err(e) => {
+                        if last.is_none_or(|p| p.summary.reachable) {
+                            warn!("Federation peer {} unreachable: {e}", peer.name());
+                        }
+                        PeerState::unreachable(peer.as_ref(), last, e)
+                    }
+                }
+            })
+            .collect()
+    })
+}
+
+fn search_all(
+    local_name: &str,
+    local_rows: Vec<CircuitDirectoryRow>,
+    peers: &[Box<dyn PeerSource>],
+    search: &str,
+) -> FederatedCircuitSearch {
+    let mut result = FederatedCircuitSearch {
+        rows: local_rows
+            .into_iter()
+            .map(|row| FederatedCircuitRow {
+                shaper: local_name.to_string(),
+                shaper_url: None,
+                row,
+            })
+            .collect(),
+        errors: Vec::new(),
+    };
+    let peer_results: Vec<_> = std::thread::scope(|scope| {
+        let handles: Vec<_> = peers
+            .iter()
+            .map(|peer| scope.spawn(move || (peer, peer.search_circuits(search))))
+            .collect();
+        handles
+            .into_iter()
+            .filter_map(|handle| handle.join().ok())
+            .collect()
+    });
+    for (peer, rows) in peer_results {
+        match rows {
+            Ok(rows) => result
+                .rows
+                .extend(
+                    rows.into_iter()
+                        .take(SEARCH_LIMIT)
+                        .map(|row| FederatedCircuitRow {
+                            shaper: peer.name().to_string(),
+                            shaper_url: Some(peer.url().to_string()),
+                            row,
+                        }),
+                ),
+            Err(e) => result.errors.push(format!("{}: {e}", peer.name())),
+        }
+    }
+    result
+}
+
+/// Joins each shaper's tree under a synthetic root. Each shaper's own root becomes a child
+/// named after the shaper, and its indexes are shifted past the previous shaper's.
+fn combine_trees(shapers: &[PeerState]) -> Vec<FederatedTreeNode> {
+    let mut combined = vec![FederatedTreeNode {
+        index: 0,
+        shaper: String::new(),
+        node: NetworkTreeLiteNode {
+            name: FEDERATION_ROOT.to_string(),
+            id: None,
+            is_virtual: true,
+            runtime_virtualized: false,
+            max_throughput: (0.0, 0.0),
+            current_throughput: (0, 0),
+            current_tcp_packets: (0, 0),
+            current_tcp_retransmit_packets: (0, 0),
+            current_retransmits: (0, 0),
+            rtts: Vec::new(),
+            qoo: (None, None),
+            parents: Vec::new(),
+            immediate_parent: None,
+            node_type: None,
+            latitude: None,
+            longitude: None,
+        },
+    }];
+
+    let mut offset = 1;
+    for shaper in shapers {
+        let name = &shaper.summary.name;
+        let span = shaper.sites.iter().map(|(i, _)| i + 1).max().unwrap_or(0);
+        for (index, node) in &shaper.sites {
+            let mut node = node.clone();
+            node.parents = std::iter::once(0)
+                .chain(node.parents.iter().map(|p| p + offset))
+                .collect();
+            node.immediate_parent = Some(node.immediate_parent.map_or(0, |p| p + offset));
+            if *index == 0 {
+                node.name = name.clone();
+                node.is_virtual = true;
+                let root = &mut combined[0].node;
+                root.current_throughput.0 += node.current_throughput.0;
+                root.current_throughput.1 += node.current_throughput.1;
+            }
+            combined.push(FederatedTreeNode {
+                index: index + offset,
+                shaper: name.clone(),
+                node,
+            });
+        }
+        offset += span;
+    }
+    combined.sort_by_key(|node| node.index);
+    combined
+}
+
+/// Builds an HTTP client for every configured peer. Peers whose client cannot be built
+/// (for example an unreadable CA file) are reported as unreachable.
+fn http_peers(config: &Config) -> Vec<Box<dyn PeerSource>> {
+    let timeout = Duration::from_secs(config.federation.request_timeout_seconds);
+    config
+        .federation
+        .peers
+        .iter()
+        .map(|peer| -> Box<dyn PeerSource> {
+            match HttpPeer::new(peer, timeout) {
+                Ok(peer) => Box::new(peer),
+                Err(error) => Box::new(BrokenPeer {
+                    name: peer.name.clone(),
+                    url: peer.url.clone(),
+                    error,
+                }),
+            }
+        })
+        .collect()
+}
+
+/// A peer reached over HTTPS with a bearer token.
+struct HttpPeer {
+    name: String,
+    url: String,
+    token: String,
+    client: reqwest::blocking::Client,
+}
+
+impl HttpPeer {
+    fn new(peer: &FederationPeer, timeout: Duration) -> Result<Self, String> {
+        let mut builder = reqwest::blocking::Client::builder()
+            .timeout(timeout)
+            .danger_accept_invalid_certs(!peer.verify_tls);
+        if let Some(path) = peer.ca_certificate.as_deref().filter(|p| !p.is_empty()) {
+            let pem = std::fs::read(path).map_err(|e| format!("CA certificate {path}: {e}"))?;
+            let certificate = reqwest::Certificate::from_pem(&pem)
+                .map_err(|e| format!("CA certificate {path}: {e}"))?;
+            builder = builder.add_root_certificate(certificate);
+        }
+        Ok(Self {
+            name: peer.name.clone(),
+            url: peer.url.trim_end_matches('/').to_string(),
+            token: peer.api_token.clone(),
+            client: builder.build().map_err(|e| e.to_string())?,
+        })
+    }
+
+    fn get<T: serde::de::DeserializeOwned>(
+        &self,
+        path: &str,
+        query: &[(&str, &str)],
+    ) -> Result<T, String> {
+        let url = reqwest::Url::parse_with_params(&format!("{}/api/v1{path}", self.url), query)
+            .map_err(|e| e.to_string())?;
+        let response = self
+            .client
+            .get(url)
+            .bearer_auth(&self.token)
+            .send()
+            .map_err(|e| e.to_string())?;
+        let status = response.status();
+        if !status.is_success() {
+            return Err(format!("{path}: HTTP {status}"));
+        }
+        response.json().map_err(|e| format!("{path}: {e}"))
+    }
+}
+
+/// The peer's `/api/v1/urgent` body; only the count is used.
+#[derive(Deserialize)]
+struct PeerUrgentList {
+    items: Vec<serde_json::Value>,
+}
+
+impl PeerSource for HttpPeer {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn url(&self) -> &str {
+        &self.url
+    }
+
+    fn fetch(&self) -> Result<PeerData, String> {
+        let throughput: ApiThroughput = self.get("/throughput", &[])?;
+        let sites: Vec<ApiSite> = self.get("/sites", &[])?;
+        let urgent: PeerUrgentList = self.get("/urgent", &[])?;
+        Ok(PeerData {
+            throughput,
+            sites: sites.into_iter().map(|s| (s.index, s.node)).collect(),
+            urgent_issues: urgent.items.len(),
+        })
+    }
+
+    fn search_circuits(&self, search: &str) -> Result<Vec<CircuitDirectoryRow>, String> {
+        let page_size = SEARCH_LIMIT.to_string();
+        let page: CircuitDirectoryPage = self.get(
+            "/circuits",
+            &[("search", search), ("page_size", page_size.as_str())],
+        )?;
+        Ok(page.rows)
+    }
+}
+
+/// A configured peer that could not be set up; every request reports why.
+struct BrokenPeer {
+    name: String,
+    url: String,
+    error: String,
+}
+
+impl PeerSource for BrokenPeer {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn url(&self) -> &str {
+        &self.url
+    }
+
+    fn fetch(&self) -> Result<PeerData, String> {
+        Err(self.error.clone())
+    }
+
+    fn search_circuits(&self, _search: &str) -> Result<Vec<CircuitDirectoryRow>, String> {
+        Err(self.error.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// An in-process shaper with a fixed tree and circuit list.
+    struct SimulatedPeer {
+        name: String,
+        online: bool,
+        sites: Vec<&'static str>,
+        circuits: Vec<&'static str>,
+    }
+
+    fn lite_node(name: &str, parents: Vec<usize>, throughput: u64) -> NetworkTreeLiteNode {
+        NetworkTreeLiteNode {
+            name: name.to_string(),
+            id: None,
+            is_virtual: false,
+            runtime_virtualized: false,
+            max_throughput: (1000.0, 1000.0),
+            current_throughput: (throughput, throughput / 10),
+            current_tcp_packets: (0, 0),
+            current_tcp_retransmit_packets: (0, 0),
+            current_retransmits: (0, 0),
+            rtts: Vec::new(),
+            qoo: (None, None),
+            immediate_parent: parents.last().copied(),
+            parents,
+            node_type: Some("site".to_string()),
+            latitude: None,
+            longitude: None,
+        }
+    }
+
+    impl SimulatedPeer {
+        fn new(name: &str, sites: Vec<&'static str>, circuits: Vec<&'static str>) -> Self {
+            Self {
+                name: name.to_string(),
+                online: true,
+                sites,
+                circuits,
+            }
+        }
+    }
+
+    impl PeerSource for SimulatedPeer {
+        fn name(&self) -> &str {
+            &self.name
+        }
+
+        fn url(&self) -> &str {
+            "https://sim.invalid"
+        }
+
+        fn fetch(&self) -> Result<PeerData, String> {
+            if !self.online {
+                return Err("connection refused".to_string());
+            }
+            let mut sites = vec![(0, lite_node("Root", Vec::new(), 3000))];
+            for (i, site) in self.sites.iter().enumerate() {
+                sites.push((i + 1, lite_node(site, vec![0], 1000)));
+            }
+            Ok(PeerData {
+                throughput: ApiThroughput {
+                    bits_per_second: DownUpOrder::new(24_000, 2_400),
+                    ..Default::default()
+                },
+                sites,
+                urgent_issues: 1,
+            })
+        }
+
+        fn search_circuits(&self, search: &str) -> Result<Vec<CircuitDirectoryRow>, String> {
+            if !self.online {
+                return Err("connection refused".to_string());
+            }
+            Ok(self
+                .circuits
+                .iter()
+                .filter(|c| c.to_lowercase().contains(&search.to_lowercase()))
+                .map(|c| CircuitDirectoryRow {
+                    circuit_id: format!("{}-{c}", self.name),
+                    circuit_name: c.to_string(),
+                    parent_node: self.sites.first().unwrap_or(&"").to_string(),
+                    sqm_override: None,
+                })
+                .collect())
+        }
+    }
+
+    fn boxed(peers: Vec<SimulatedPeer>) -> Vec<Box<dyn PeerSource>> {
+        peers
+            .into_iter()
+            .map(|p| Box::new(p) as Box<dyn PeerSource>)
+            .collect()
+    }
+
+    #[test]
+    fn overview_totals_only_reachable_peers() {
+        let west = || SimulatedPeer::new("west", vec!["Tower W"], Vec::new());
+        let east = || SimulatedPeer::new("east", vec!["Tower E1", "Tower E2"], Vec::new());
+        let first = poll_peers(&boxed(vec![west(), east()]), &[]);
+        assert!(first.iter().all(|p| p.summary.reachable));
+
+        let mut offline_west = west();
+        offline_west.online = false;
+        let peers = boxed(vec![offline_west, east()]);
+        let second = poll_peers(&peers, &first);
+        assert!(!second[0].summary.reachable);
+        assert_eq!(
+            second[0].summary.last_error.as_deref(),
+            Some("connection refused")
+        );
+        assert_eq!(second[0].sites.len(), 2, "last known tree is kept");
+
+        let overview = build_overview(true, second.iter().map(|p| p.summary.clone()).collect());
+        assert_eq!(overview.reachable_shapers, 1);
+        assert_eq!(
+            overview.total_bits_per_second,
+            DownUpOrder::new(24_000, 2_400)
+        );
+        assert_eq!(overview.total_urgent_issues, 1);
+        assert_eq!(overview.shapers[1].nodes, 2);
+    }
+
+    #[test]
+    fn trees_are_joined_under_one_root() {
+        let peers = boxed(vec![
+            SimulatedPeer::new("west", vec!["Tower W"], Vec::new()),
+            SimulatedPeer::new("east", vec!["Tower E1", "Tower E2"], Vec::new()),
+        ]);
+        let tree = combine_trees(&poll_peers(&peers, &[]));
+
+        let names: Vec<&str> = tree.iter().map(|n| n.node.name.as_str()).collect();
+        assert_eq!(
+            names,
+            vec![
+                FEDERATION_ROOT,
+                "west",
+                "Tower W",
+                "east",
+                "Tower E1",
+                "Tower E2"
+            ]
+        );
+        assert!(tree.iter().enumerate().all(|(i, n)| n.index == i));
+        assert_eq!(tree[3].node.immediate_parent, Some(0));
+        assert_eq!(tree[5].node.immediate_parent, Some(3));
+        assert_eq!(tree[5].node.parents, vec![0, 3]);
+        assert_eq!(tree[5].shaper, "east");
+        assert_eq!(tree[0].node.current_throughput, (6000, 600));
+    }
+
+    #[test]
+    fn circuit_search_spans_shapers_and_reports_failures() {
+        let mut down = SimulatedPeer::new("north", Vec::new(), vec!["Smith Farm"]);
+        down.online = false;
+        let peers = boxed(vec![
+            SimulatedPeer::new("west", vec!["Tower W"], vec!["Smith Home", "Jones"]),
+            down,
+        ]);
+        let local = vec![CircuitDirectoryRow {
+            circuit_id: "1".to_string(),
+            circuit_name: "Smith Office".to_string(),
+            parent_node: "Tower L".to_string(),
+            sqm_override: None,
+        }];
+        let result = search_all("local", local, &peers, "smith");
+
+        let found: Vec<(&str, &str)> = result
+            .rows
+            .iter()
+            .map(|r| (r.shaper.as_str(), r.row.circuit_name.as_str()))
+            .collect();
+        assert_eq!(
+            found,
+            vec![("local", "Smith Office"), ("west", "Smith Home")]
+        );
+        assert_eq!(
+            result.rows[1].shaper_url.as_deref(),
+            Some("https://sim.invalid")
+        );
+        assert_eq!(result.errors, vec!["north: connection refused".to_string()]);
+    }
+}
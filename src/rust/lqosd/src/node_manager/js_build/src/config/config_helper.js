@@ -62,6 +62,17 @@ function ensureOptionalConfigSections(config) {
         splynx.strategy = "ap_only";
     }
 
+    if (!config.change_control || typeof config.change_control !== "object") {
+        config.change_control = {};
+    }
+    const changeControl = config.change_control;
+    if (typeof changeControl.require_approval !== "boolean") changeControl.require_approval = false;
+    if (!Array.isArray(changeControl.auto_approve_sites)) changeControl.auto_approve_sites = [];
+    if (typeof changeControl.auto_approve_max_change_pct !== "number") {
+        changeControl.auto_approve_max_change_pct = 0;
+    }
+    if (typeof changeControl.auto_approve_sqm !== "boolean") changeControl.auto_approve_sqm = false;
+
     return config;
 }
 
@@ -457,6 +468,39 @@ export function deleteUser(payload, onComplete, onError) {
     );
 }
 
+export function getPendingChanges(onComplete, onError) {
+    sendWsRequest(
+        "GetPendingChanges",
+        { GetPendingChanges: {} },
+        (msg) => {
+            if (onComplete) onComplete(msg.data);
+        },
+        onError,
+    );
+}
+
+export function approvePendingChange(id, onComplete, onError) {
+    sendWsRequest(
+        "PendingChangeResult",
+        { ApprovePendingChange: { id } },
+        (msg) => {
+            if (onComplete) onComplete(msg);
+        },
+        onError,
+    );
+}
+
+export function rejectPendingChange(id, onComplete, onError) {
+    sendWsRequest(
+        "PendingChangeResult",
+        { RejectPendingChange: { id } },
+        (msg) => {
+            if (onComplete) onComplete(msg);
+        },
+        onError,
+    );
+}
+
 export function validNodeList(network_json) {
     let nodes = [];
 
@@ -482,6 +526,7 @@ export function renderConfigMenu(currentPage) {
         { href: "config_queues.html", icon: "fa-car", text: "Queues", id: "queues" },
         { href: "config_stormguard.html", icon: "fa-bolt", text: "StormGuard", id: "stormguard" },
         { href: "config_treeguard.html", icon: "fa-shield-halved", text: "TreeGuard", id: "treeguard" },
+        { href: "config_change_control.html", icon: "fa-clipboard-check", text: "Change Control", id: "change_control" },
         { href: "config_lts.html", icon: "fa-line-chart", text: "LibreQoS Insight", id: "lts" },
         { href: "config_iprange.html", icon: "fa-address-card", text: "IP Ranges", id: "iprange" },
         { href: "config_flows.html", icon: "fa-arrow-circle-down", text: "Flow Tracking", id: "flows" },
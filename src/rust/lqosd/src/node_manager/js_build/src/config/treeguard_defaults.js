@@ -40,6 +40,32 @@ export function defaultTreeguardConfig() {
             enabled: true,
             min_score: 70.0,
         },
+        rebalance: {
+            enabled: false,
+            imbalance_pct: 25,
+            min_hot_cpu_pct: 50,
+            sustain_minutes: 10,
+            hysteresis_pct: 3.0,
+            site_cooldown_minutes: 60,
+            max_moves_per_run: 1,
+            min_minutes_between_runs: 30,
+        },
+        adaptive_sqm: {
+            enabled: false,
+            profiles: [
+                "cake:diffserv4",
+                "cake:besteffort",
+                "cake:diffserv4:ack-filter",
+                "cake:besteffort:ack-filter",
+            ],
+            poor_qoo_score: 60.0,
+            min_util_pct: 50.0,
+            sustain_minutes: 5,
+            evaluation_minutes: 15,
+            min_improvement: 5.0,
+            retry_cooldown_minutes: 1440,
+            max_active_experiments: 8,
+        },
     };
 }
 
@@ -69,5 +95,13 @@ export function ensureTreeguardConfig(config) {
             ...defaults.qoo,
             ...(current.qoo || {}),
         },
+        rebalance: {
+            ...defaults.rebalance,
+            ...(current.rebalance || {}),
+        },
+        adaptive_sqm: {
+            ...defaults.adaptive_sqm,
+            ...(current.adaptive_sqm || {}),
+        },
     };
 }
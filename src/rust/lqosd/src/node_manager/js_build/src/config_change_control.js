@@ -0,0 +1,134 @@
+import {
+    approvePendingChange,
+    getPendingChanges,
+    loadConfig,
+    rejectPendingChange,
+    renderConfigMenu,
+    saveConfig,
+} from "./config/config_helper";
+
+function parseSites(raw) {
+    return raw
+        .split("\n")
+        .map((line) => line.trim())
+        .filter((line) => line.length > 0);
+}
+
+function setFormValues(cc) {
+    document.getElementById("requireApproval").checked = !!cc.require_approval;
+    document.getElementById("autoApproveSqm").checked = !!cc.auto_approve_sqm;
+    document.getElementById("autoApproveSites").value = (cc.auto_approve_sites || []).join("\n");
+    document.getElementById("autoApproveMaxChangePct").value = cc.auto_approve_max_change_pct ?? 0;
+}
+
+function updateConfigFromForm() {
+    const pct = Number(document.getElementById("autoApproveMaxChangePct").value);
+    if (!Number.isFinite(pct) || pct < 0 || pct > 100) {
+        return { ok: false, message: "Max bandwidth change must be between 0 and 100." };
+    }
+    window.config.change_control = {
+        require_approval: document.getElementById("requireApproval").checked,
+        auto_approve_sqm: document.getElementById("autoApproveSqm").checked,
+        auto_approve_sites: parseSites(document.getElementById("autoApproveSites").value),
+        auto_approve_max_change_pct: pct,
+    };
+    return { ok: true };
+}
+
+function cell(text) {
+    const td = document.createElement("td");
+    td.textContent = text;
+    return td;
+}
+
+function actionButton(label, className, onClick) {
+    const btn = document.createElement("button");
+    btn.type = "button";
+    btn.className = `btn btn-sm ${className} me-1`;
+    btn.textContent = label;
+    btn.addEventListener("click", onClick);
+    return btn;
+}
+
+function handleResult(msg) {
+    if (!msg || !msg.ok) {
+        alert(msg && msg.message ? msg.message : "Request failed");
+    }
+    loadPending();
+}
+
+function renderPending(data) {
+    const holder = document.getElementById("pendingChanges");
+    holder.innerHTML = "";
+    const changes = data?.changes || [];
+
+    if (!data?.require_approval && changes.length === 0) {
+        holder.innerHTML = '<div class="alert alert-info mb-0">Approval mode is off; automated changes are applied directly.</div>';
+        return;
+    }
+    if (changes.length === 0) {
+        holder.innerHTML = '<div class="alert alert-info mb-0">No changes are waiting for approval.</div>';
+        return;
+    }
+
+    const wrap = document.createElement("div");
+    wrap.className = "table-responsive lqos-table-wrap";
+    const table = document.createElement("table");
+    table.className = "lqos-table lqos-table-compact mb-0";
+    table.innerHTML = "<thead><tr><th>ID</th><th>Queued</th><th>Layer</th><th>Site</th><th>Change</th><th>Magnitude</th><th>Actions</th></tr></thead>";
+    const tbody = document.createElement("tbody");
+
+    changes.forEach((change) => {
+        const row = document.createElement("tr");
+        row.appendChild(cell(String(change.id)));
+        row.appendChild(cell(new Date(change.created_unix * 1000).toLocaleString()));
+        row.appendChild(cell(change.layer));
+        row.appendChild(cell(change.site || "-"));
+        row.appendChild(cell(change.summary));
+        row.appendChild(cell(change.magnitude_pct == null ? "-" : `${change.magnitude_pct.toFixed(1)}%`));
+        const actions = document.createElement("td");
+        actions.appendChild(actionButton("Approve", "btn-success", () => {
+            approvePendingChange(change.id, handleResult, () => alert("Failed to approve change"));
+        }));
+        actions.appendChild(actionButton("Reject", "btn-danger", () => {
+            if (confirm("Discard this pending change?")) {
+                rejectPendingChange(change.id, handleResult, () => alert("Failed to reject change"));
+            }
+        }));
+        row.appendChild(actions);
+        tbody.appendChild(row);
+    });
+
+    table.appendChild(tbody);
+    wrap.appendChild(table);
+    holder.appendChild(wrap);
+}
+
+function loadPending() {
+    getPendingChanges(renderPending, () => {
+        document.getElementById("pendingChanges").innerHTML =
+            '<div class="alert alert-danger mb-0">Failed to load pending changes</div>';
+    });
+}
+
+renderConfigMenu("change_control");
+
+loadConfig(() => {
+    if (!window.config) return;
+    setFormValues(window.config.change_control);
+
+    document.getElementById("saveButton").addEventListener("click", () => {
+        const res = updateConfigFromForm();
+        if (!res.ok) {
+            alert(res.message);
+            return;
+        }
+        saveConfig(() => {
+            alert("Configuration saved successfully!");
+            loadPending();
+        });
+    });
+    document.getElementById("refreshPending").addEventListener("click", loadPending);
+
+    loadPending();
+});
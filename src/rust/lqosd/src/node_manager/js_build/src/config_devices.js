@@ -659,14 +659,24 @@ function validateModalDevice(device) {
     }
 
     const sqm = device.sqm_override ? parseSqmOverride(device.sqm_override) : { down: "", up: "" };
-    const validSqm = (token) => token === "" || token === "cake" || token === "fq_codel" || token === "none";
+    const validSqm = (token) => token === "" || token === "cake" || token === "fq_codel" || token === "none"
+        || isValidCakeProfile(token);
     if (!validSqm(sqm.down) || !validSqm(sqm.up)) {
-        errors.push("SQM overrides must be blank, cake, fq_codel, or none.");
+        errors.push("SQM overrides must be blank, cake, cake:<options>, fq_codel, or none.");
     }
 
     return { valid: errors.length === 0, errors };
 }
 
+function isValidCakeProfile(token) {
+    if (!token.startsWith("cake:")) return false;
+    const options = token.slice(5).split(":");
+    const allowed = ["besteffort", "diffserv3", "diffserv4", "diffserv8", "ack-filter", "no-ack-filter"];
+    if (!options.every((option) => allowed.includes(option))) return false;
+    const ackModes = options.filter((option) => option.includes("ack-filter")).length;
+    return ackModes <= 1 && options.length - ackModes <= 1;
+}
+
 function setModalBusy(busy) {
     modal_busy = busy;
     $("#sdModalSave").prop("disabled", busy || topology_editor_locked);
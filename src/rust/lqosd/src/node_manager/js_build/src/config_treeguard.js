@@ -423,9 +423,104 @@ function validateConfig() {
     const minScore = parseFloat(document.getElementById("minScore").value);
     if (!validatePercent("Minimum QoO Score", minScore)) return false;
 
+    const rebalanceImbalancePct = parseInt(
+        document.getElementById("rebalanceImbalancePct").value,
+        10,
+    );
+    if (!validatePercent("Rebalance Imbalance Threshold", rebalanceImbalancePct)) return false;
+
+    const rebalanceMinHotCpuPct = parseInt(
+        document.getElementById("rebalanceMinHotCpuPct").value,
+        10,
+    );
+    if (!validatePercent("Rebalance Minimum Busiest CPU", rebalanceMinHotCpuPct)) return false;
+
+    const rebalanceHysteresisPct = parseFloat(
+        document.getElementById("rebalanceHysteresisPct").value,
+    );
+    if (!validatePercent("Rebalance Planner Hysteresis", rebalanceHysteresisPct)) return false;
+
+    const rebalanceSustainMinutes = parseInt(
+        document.getElementById("rebalanceSustainMinutes").value,
+        10,
+    );
+    if (!validateNonNegativeInt("Rebalance Sustain Duration", rebalanceSustainMinutes)) return false;
+
+    const rebalanceSiteCooldownMinutes = parseInt(
+        document.getElementById("rebalanceSiteCooldownMinutes").value,
+        10,
+    );
+    if (!validateNonNegativeInt("Rebalance Per-Site Cooldown", rebalanceSiteCooldownMinutes)) return false;
+
+    const rebalanceMaxMovesPerRun = parseInt(
+        document.getElementById("rebalanceMaxMovesPerRun").value,
+        10,
+    );
+    if (Number.isNaN(rebalanceMaxMovesPerRun) || rebalanceMaxMovesPerRun < 1) {
+        alert("Rebalance Max Moves Per Run must be at least 1");
+        return false;
+    }
+
+    const rebalanceMinMinutesBetweenRuns = parseInt(
+        document.getElementById("rebalanceMinMinutesBetweenRuns").value,
+        10,
+    );
+    if (!validateNonNegativeInt("Rebalance Minimum Time Between Runs", rebalanceMinMinutesBetweenRuns)) return false;
+
+    const adaptiveSqmProfiles = parseAdaptiveSqmProfiles();
+    if (adaptiveSqmProfiles.some((profile) => !profile.startsWith("cake:"))) {
+        alert("Adaptive SQM profiles must be CAKE profiles such as cake:besteffort:ack-filter");
+        return false;
+    }
+
+    const adaptivePoorQooScore = parseFloat(document.getElementById("adaptivePoorQooScore").value);
+    if (!validatePercent("Adaptive SQM Poor QoO Score", adaptivePoorQooScore)) return false;
+
+    const adaptiveMinUtilPct = parseFloat(document.getElementById("adaptiveMinUtilPct").value);
+    if (!validatePercent("Adaptive SQM Minimum Utilization", adaptiveMinUtilPct)) return false;
+
+    const adaptiveMinImprovement = parseFloat(
+        document.getElementById("adaptiveMinImprovement").value,
+    );
+    if (!validatePercent("Adaptive SQM Minimum Improvement", adaptiveMinImprovement)) return false;
+
+    const adaptiveSustainMinutes = parseInt(
+        document.getElementById("adaptiveSustainMinutes").value,
+        10,
+    );
+    if (!validateNonNegativeInt("Adaptive SQM Sustain Duration", adaptiveSustainMinutes)) return false;
+
+    const adaptiveEvaluationMinutes = parseInt(
+        document.getElementById("adaptiveEvaluationMinutes").value,
+        10,
+    );
+    if (Number.isNaN(adaptiveEvaluationMinutes) || adaptiveEvaluationMinutes < 1) {
+        alert("Adaptive SQM Evaluation Window must be at least 1 minute");
+        return false;
+    }
+
+    const adaptiveRetryCooldownMinutes = parseInt(
+        document.getElementById("adaptiveRetryCooldownMinutes").value,
+        10,
+    );
+    if (!validateNonNegativeInt("Adaptive SQM Retry Cooldown", adaptiveRetryCooldownMinutes)) return false;
+
+    const adaptiveMaxActiveExperiments = parseInt(
+        document.getElementById("adaptiveMaxActiveExperiments").value,
+        10,
+    );
+    if (!validateNonNegativeInt("Adaptive SQM Max Concurrent Experiments", adaptiveMaxActiveExperiments)) return false;
+
     return true;
 }
 
+function parseAdaptiveSqmProfiles() {
+    return document.getElementById("adaptiveProfiles").value
+        .split(",")
+        .map((profile) => profile.trim().toLowerCase())
+        .filter((profile) => profile.length > 0);
+}
+
 function updateConfig() {
     window.config.treeguard = {
         enabled: document.getElementById("enabled").checked,
@@ -489,6 +584,45 @@ function updateConfig() {
             enabled: document.getElementById("qooEnabled").checked,
             min_score: parseFloat(document.getElementById("minScore").value),
         },
+        rebalance: {
+            enabled: document.getElementById("rebalanceEnabled").checked,
+            imbalance_pct: parseInt(document.getElementById("rebalanceImbalancePct").value, 10),
+            min_hot_cpu_pct: parseInt(document.getElementById("rebalanceMinHotCpuPct").value, 10),
+            sustain_minutes: parseInt(document.getElementById("rebalanceSustainMinutes").value, 10),
+            hysteresis_pct: parseFloat(document.getElementById("rebalanceHysteresisPct").value),
+            site_cooldown_minutes: parseInt(
+                document.getElementById("rebalanceSiteCooldownMinutes").value,
+                10,
+            ),
+            max_moves_per_run: parseInt(
+                document.getElementById("rebalanceMaxMovesPerRun").value,
+                10,
+            ),
+            min_minutes_between_runs: parseInt(
+                document.getElementById("rebalanceMinMinutesBetweenRuns").value,
+                10,
+            ),
+        },
+        adaptive_sqm: {
+            enabled: document.getElementById("adaptiveEnabled").checked,
+            profiles: parseAdaptiveSqmProfiles(),
+            poor_qoo_score: parseFloat(document.getElementById("adaptivePoorQooScore").value),
+            min_util_pct: parseFloat(document.getElementById("adaptiveMinUtilPct").value),
+            sustain_minutes: parseInt(document.getElementById("adaptiveSustainMinutes").value, 10),
+            evaluation_minutes: parseInt(
+                document.getElementById("adaptiveEvaluationMinutes").value,
+                10,
+            ),
+            min_improvement: parseFloat(document.getElementById("adaptiveMinImprovement").value),
+            retry_cooldown_minutes: parseInt(
+                document.getElementById("adaptiveRetryCooldownMinutes").value,
+                10,
+            ),
+            max_active_experiments: parseInt(
+                document.getElementById("adaptiveMaxActiveExperiments").value,
+                10,
+            ),
+        },
     };
 }
 
@@ -506,6 +640,8 @@ Promise.all([
     const links = tg.links;
     const circuits = tg.circuits;
     const qoo = tg.qoo;
+    const rebalance = tg.rebalance;
+    const adaptiveSqm = tg.adaptive_sqm;
 
     document.getElementById("enabled").checked = tg.enabled;
     document.getElementById("dryRun").checked = tg.dry_run;
@@ -552,6 +688,25 @@ Promise.all([
     document.getElementById("qooEnabled").checked = qoo.enabled;
     document.getElementById("minScore").value = qoo.min_score;
 
+    document.getElementById("rebalanceEnabled").checked = rebalance.enabled;
+    document.getElementById("rebalanceImbalancePct").value = rebalance.imbalance_pct;
+    document.getElementById("rebalanceMinHotCpuPct").value = rebalance.min_hot_cpu_pct;
+    document.getElementById("rebalanceSustainMinutes").value = rebalance.sustain_minutes;
+    document.getElementById("rebalanceHysteresisPct").value = rebalance.hysteresis_pct;
+    document.getElementById("rebalanceSiteCooldownMinutes").value = rebalance.site_cooldown_minutes;
+    document.getElementById("rebalanceMaxMovesPerRun").value = rebalance.max_moves_per_run;
+    document.getElementById("rebalanceMinMinutesBetweenRuns").value = rebalance.min_minutes_between_runs;
+
+    document.getElementById("adaptiveEnabled").checked = adaptiveSqm.enabled;
+    document.getElementById("adaptiveProfiles").value = (adaptiveSqm.profiles || []).join(", ");
+    document.getElementById("adaptivePoorQooScore").value = adaptiveSqm.poor_qoo_score;
+    document.getElementById("adaptiveMinUtilPct").value = adaptiveSqm.min_util_pct;
+    document.getElementById("adaptiveSustainMinutes").value = adaptiveSqm.sustain_minutes;
+    document.getElementById("adaptiveEvaluationMinutes").value = adaptiveSqm.evaluation_minutes;
+    document.getElementById("adaptiveMinImprovement").value = adaptiveSqm.min_improvement;
+    document.getElementById("adaptiveRetryCooldownMinutes").value = adaptiveSqm.retry_cooldown_minutes;
+    document.getElementById("adaptiveMaxActiveExperiments").value = adaptiveSqm.max_active_experiments;
+
     [
         "enabled",
         "dryRun",
@@ -93,13 +93,15 @@ function loadUsers() {
 
         const tableWrap = $('<div class="table-responsive lqos-table-wrap">');
         const table = $('<table class="lqos-table lqos-table-compact mb-0">')
-            .append('<thead><tr><th>Username</th><th>Role</th><th>Actions</th></tr></thead>');
+            .append('<thead><tr><th>Username</th><th>Role</th><th>Scope</th><th>2FA</th><th>Actions</th></tr></thead>');
         const tbody = $('<tbody>');
         
         users.forEach(user => {
             const row = $('<tr>')
                 .append(`<td>${user.username}</td>`)
-                .append(`<td>${user.role}</td>`)
+                .append(`<td>${user.custom_role || user.role}</td>`)
+                .append(`<td>${user.scope && user.scope.length ? user.scope.join(', ') : 'All'}</td>`)
+                .append(`<td>${user.totp && user.totp.confirmed ? 'Enabled' : 'Off'}</td>`)
                 .append(`<td>
                     <button class="btn btn-sm btn-primary edit-user" data-username="${user.username}">
                         <i class="fa fa-edit"></i> Edit
@@ -260,6 +260,7 @@ export class TreeGuardStatusDashlet extends BaseDashlet {
         this.dryRunEl.textContent = data.dry_run ? "Yes" : "No";
 
         const cpuRaw = data.cpu_max_pct;
+        const spreadRaw = data.softirq_spread_pct;
         if (cpuRaw === null || cpuRaw === undefined) {
             updateProgressMetric(this.cpuMetric, {
                 value: 0,
@@ -276,7 +277,9 @@ export class TreeGuardStatusDashlet extends BaseDashlet {
                 max: 100,
                 text: `${cpu.toFixed(0)}%`,
                 bgClass: bg,
-                title: `CPU max: ${cpu.toFixed(0)}%`,
+                title: spreadRaw === null || spreadRaw === undefined
+                    ? `CPU max: ${cpu.toFixed(0)}%`
+                    : `CPU max: ${cpu.toFixed(0)}%, softirq spread: ${Number(spreadRaw).toFixed(0)}%`,
             });
         }
 
@@ -324,14 +327,16 @@ export class TreeGuardStatusDashlet extends BaseDashlet {
         const fqCodelCircuits = fqCodelCircuitsExact;
         const fqMax = Math.max(1, managedCircuits);
         const fqPct = managedCircuits > 0 ? clamp((fqCodelCircuits / fqMax) * 100, 0, 100) : 0;
+        const sqmExperiments = Math.max(0, Math.trunc(Number(data.sqm_experiments_active ?? 0) || 0));
+        const experimentsNote = sqmExperiments > 0 ? `, SQM experiments running: ${sqmExperiments}` : "";
         updateProgressMetric(this.fqCodelMetric, {
             value: fqCodelCircuits,
             max: fqMax,
             text: managedCircuits > 0 ? `${fqCodelCircuits} / ${managedCircuits}` : fqCodelCircuits.toString(),
             bgClass: "bg-warning",
             title: managedCircuits > 0
-                ? `${fqCodelCircuits} / ${managedCircuits} (${fqPct.toFixed(0)}%)`
-                : `${fqCodelCircuits}`,
+                ? `${fqCodelCircuits} / ${managedCircuits} (${fqPct.toFixed(0)}%)${experimentsNote}`
+                : `${fqCodelCircuits}${experimentsNote}`,
         });
 
         this.lastActionEl.innerHTML = "";
@@ -0,0 +1,194 @@
+import {clearDiv, simpleRow, theading} from "./helpers/builders";
+import {scaleNumber} from "./lq_js_common/helpers/scaling";
+import {get_ws_client} from "./pubsub/ws";
+
+const wsClient = get_ws_client();
+const listenOnce = (eventName, handler) => {
+   const wrapped = (msg) => {
+      wsClient.off(eventName, wrapped);
+      handler(msg);
+   };
+   wsClient.on(eventName, wrapped);
+};
+
+function wrapTable(table) {
+   const tableWrap = document.createElement("div");
+   tableWrap.classList.add("lqos-table-wrap");
+   tableWrap.appendChild(table);
+   return tableWrap;
+}
+
+function makeTable(headings) {
+   const table = document.createElement("table");
+   table.classList.add("lqos-table", "lqos-table-compact");
+   const thead = document.createElement("thead");
+   headings.forEach(([text, colspan]) => thead.appendChild(theading(text, colspan || 0)));
+   table.appendChild(thead);
+   const tbody = document.createElement("tbody");
+   table.appendChild(tbody);
+   return [table, tbody];
+}
+
+function emptyNote(target, text) {
+   const p = document.createElement("p");
+   p.classList.add("text-muted");
+   p.textContent = text;
+   target.appendChild(p);
+}
+
+function shaperLink(name, url, path) {
+   const td = document.createElement("td");
+   if (url) {
+      const a = document.createElement("a");
+      a.href = url.replace(/\/$/, "") + "/" + path;
+      a.target = "_blank";
+      a.rel = "noopener";
+      a.innerText = name;
+      td.appendChild(a);
+   } else {
+      td.innerText = name;
+   }
+   return td;
+}
+
+function statusCell(shaper) {
+   const td = document.createElement("td");
+   if (shaper.reachable) {
+      td.innerHTML = "<span class='text-success'><i class='fa fa-circle-check'></i> Online</span>";
+   } else {
+      const span = document.createElement("span");
+      span.classList.add("text-danger");
+      span.title = shaper.last_error || "";
+      span.innerText = "Unreachable";
+      td.appendChild(span);
+   }
+   return td;
+}
+
+function loadOverview() {
+   listenOnce("FederationOverview", (msg) => {
+      const data = msg && msg.data ? msg.data : null;
+      const target = document.getElementById("federationOverview");
+      clearDiv(target);
+      if (!data) {
+         emptyNote(target, "Federation data is unavailable.");
+         return;
+      }
+      if (!data.enabled) {
+         emptyNote(target, "Federation is disabled. Add peers under [federation] in /etc/lqos.conf to enable it.");
+      }
+
+      const [table, tbody] = makeTable([
+         ["Shaper"], ["Status"], ["Throughput", 2], ["Shaped", 2], ["Packets/s", 2], ["Nodes"], ["Urgent Issues"],
+      ]);
+      data.shapers.forEach((shaper) => {
+         const tr = document.createElement("tr");
+         tr.appendChild(shaperLink(shaper.name + (shaper.local ? " (this shaper)" : ""), shaper.url, "index.html"));
+         tr.appendChild(statusCell(shaper));
+         tr.appendChild(simpleRow(scaleNumber(shaper.bits_per_second.down) + "bps"));
+         tr.appendChild(simpleRow(scaleNumber(shaper.bits_per_second.up) + "bps"));
+         tr.appendChild(simpleRow(scaleNumber(shaper.shaped_bits_per_second.down) + "bps"));
+         tr.appendChild(simpleRow(scaleNumber(shaper.shaped_bits_per_second.up) + "bps"));
+         tr.appendChild(simpleRow(scaleNumber(shaper.packets_per_second.down, 0)));
+         tr.appendChild(simpleRow(scaleNumber(shaper.packets_per_second.up, 0)));
+         tr.appendChild(simpleRow(shaper.nodes));
+         tr.appendChild(simpleRow(shaper.urgent_issues));
+         tbody.appendChild(tr);
+      });
+
+      const total = document.createElement("tr");
+      total.classList.add("fw-bold");
+      total.appendChild(simpleRow("Total"));
+      total.appendChild(simpleRow(data.reachable_shapers + " of " + data.shapers.length + " online"));
+      total.appendChild(simpleRow(scaleNumber(data.total_bits_per_second.down) + "bps"));
+      total.appendChild(simpleRow(scaleNumber(data.total_bits_per_second.up) + "bps"));
+      total.appendChild(simpleRow(scaleNumber(data.total_shaped_bits_per_second.down) + "bps"));
+      total.appendChild(simpleRow(scaleNumber(data.total_shaped_bits_per_second.up) + "bps"));
+      total.appendChild(simpleRow(scaleNumber(data.total_packets_per_second.down, 0)));
+      total.appendChild(simpleRow(scaleNumber(data.total_packets_per_second.up, 0)));
+      total.appendChild(simpleRow(""));
+      total.appendChild(simpleRow(data.total_urgent_issues));
+      tbody.appendChild(total);
+      target.appendChild(wrapTable(table));
+   });
+   wsClient.send({ FederationOverview: {} });
+}
+
+function runSearch() {
+   const search = document.getElementById("federationSearch").value.trim();
+   const target = document.getElementById("federationSearchResults");
+   clearDiv(target);
+   if (search.length === 0) {
+      return;
+   }
+   target.innerHTML = "<i class='fa fa-spinner fa-spin'></i> Searching...";
+   listenOnce("FederationCircuitSearch", (msg) => {
+      const data = msg && msg.data ? msg.data : { rows: [], errors: [] };
+      target.innerHTML = "";
+      data.errors.forEach((error) => {
+         const p = document.createElement("p");
+         p.classList.add("text-warning");
+         p.innerText = "Not searched: " + error;
+         target.appendChild(p);
+      });
+      if (data.rows.length === 0) {
+         emptyNote(target, "No matching circuits.");
+         return;
+      }
+      const [table, tbody] = makeTable([["Shaper"], ["Circuit"], ["Parent Node"], ["Circuit ID"]]);
+      data.rows.forEach((row) => {
+         const tr = document.createElement("tr");
+         tr.appendChild(simpleRow(row.shaper));
+         const circuitPath = "circuit.html?id=" + encodeURIComponent(row.circuit_id);
+         const link = row.shaper_url
+            ? shaperLink(row.circuit_name, row.shaper_url, circuitPath)
+            : shaperLink(row.circuit_name, ".", circuitPath);
+         link.classList.add("redactable");
+         tr.appendChild(link);
+         tr.appendChild(simpleRow(row.parent_node, true));
+         tr.appendChild(simpleRow(row.circuit_id, true));
+         tbody.appendChild(tr);
+      });
+      target.appendChild(wrapTable(table));
+   });
+   wsClient.send({ FederationCircuitSearch: { search: search } });
+}
+
+function loadTree() {
+   listenOnce("FederationTree", (msg) => {
+      const nodes = msg && msg.data ? msg.data : [];
+      const target = document.getElementById("federationTree");
+      clearDiv(target);
+      if (nodes.length <= 1) {
+         emptyNote(target, "No network tree data yet.");
+         return;
+      }
+      const [table, tbody] = makeTable([["Node"], ["Shaper"], ["Type"], ["Throughput", 2]]);
+      nodes.forEach((node) => {
+         const tr = document.createElement("tr");
+         const name = document.createElement("td");
+         name.style.paddingLeft = (node.parents.length * 1.2) + "em";
+         name.innerText = node.name;
+         name.classList.add("redactable");
+         tr.appendChild(name);
+         tr.appendChild(simpleRow(node.shaper));
+         tr.appendChild(simpleRow(node.type || ""));
+         tr.appendChild(simpleRow(scaleNumber(node.current_throughput[0] * 8) + "bps"));
+         tr.appendChild(simpleRow(scaleNumber(node.current_throughput[1] * 8) + "bps"));
+         tbody.appendChild(tr);
+      });
+      target.appendChild(wrapTable(table));
+   });
+   wsClient.send({ FederationTree: {} });
+}
+
+document.getElementById("btnFederationSearch").addEventListener("click", runSearch);
+document.getElementById("federationSearch").addEventListener("keydown", (e) => {
+   if (e.key === "Enter") {
+      runSearch();
+   }
+});
+
+loadOverview();
+loadTree();
+setInterval(loadOverview, 10000);
@@ -14,6 +14,10 @@ $("#btnLogin").on('click', () => {
         username: username,
         password: password
     }
+    const totp = ($("#totp").val() || "").trim();
+    if (totp !== "") {
+        login.totp = totp;
+    }
 
     $.ajax({
         type: "POST",
@@ -35,8 +39,22 @@ $("#btnLogin").on('click', () => {
                 return;
             }
 
+            if (reason === "totp_required") {
+                $("#totpRow").removeClass("d-none");
+                $("#totp").focus();
+                return;
+            }
+
             if (reason === "auth_corrupt") {
                 $("#loginErrorText").text(response.message || "The auth file is corrupt and must be repaired before anyone can log in.");
+            } else if (reason === "invalid_totp") {
+                $("#totpRow").removeClass("d-none");
+                $("#loginErrorText").text(response.message || "Invalid two-factor code.");
+            } else if (reason === "totp_locked") {
+                $("#totpRow").removeClass("d-none");
+                $("#loginErrorText").text(response.message || "Too many wrong two-factor codes. Try again later.");
+            } else if (reason === "totp_enrollment_required") {
+                $("#loginErrorText").text(response.message || "Two-factor authentication is required for this account.");
             } else if (reason === "invalid_credentials") {
                 $("#loginErrorText").text(response.message || "Invalid username or password.");
             } else {
@@ -48,7 +66,7 @@ $("#btnLogin").on('click', () => {
 });
 
 // Add keypress handler for Enter key
-$('#username, #password').on('keypress', function(e) {
+$('#username, #password, #totp').on('keypress', function(e) {
     if (e.which === 13) {
         e.preventDefault();
         $('#btnLogin').click();
@@ -56,6 +74,28 @@ $('#username, #password').on('keypress', function(e) {
 });
 
 // Hide error when typing
-$('#username, #password').on('input', function() {
+$('#username, #password, #totp').on('input', function() {
     $("#loginError").fadeOut();
 });
+
+const SSO_ERRORS = {
+    provider_error: "The identity provider could not be reached or rejected the login.",
+    invalid_state: "The single sign-on session expired. Please try again.",
+    invalid_token: "The identity provider returned an invalid login.",
+    no_role: "Your account is not mapped to a LibreQoS role.",
+    session_error: "Unable to create session token.",
+};
+
+$.get("/oidc/status", (status) => {
+    if (status && status.enabled) {
+        $("#btnSso").text(status.label || "Sign in with SSO").removeClass("d-none");
+    }
+});
+
+const ssoError = new URLSearchParams(window.location.search).get("sso_error");
+if (ssoError === "first_run_required") {
+    window.location.href = "/first-run.html";
+} else if (ssoError) {
+    $("#loginErrorText").text(SSO_ERRORS[ssoError] || "Single sign-on failed.");
+    $("#loginError").removeClass("d-none").addClass("show");
+}
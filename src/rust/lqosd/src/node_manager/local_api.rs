@@ -1,3 +1,4 @@
+pub(crate) mod audit_log;
 pub(crate) mod circuit;
 pub(crate) mod circuit_activity;
 pub(crate) mod circuit_count;
@@ -16,7 +17,9 @@ pub mod lts;
 pub(crate) mod network_tree;
 pub(crate) mod network_tree_lite;
 pub(crate) mod node_rate_overrides;
+pub(crate) mod override_history;
 pub(crate) mod packet_analysis;
+pub(crate) mod pending_changes;
 pub(crate) mod reload_libreqos;
 pub(crate) mod scheduler;
 pub(crate) mod search;
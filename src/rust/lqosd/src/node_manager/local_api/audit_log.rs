@@ -0,0 +1,52 @@
+use crate::audit::{self, AuditQuery};
+use crate::node_manager::auth::LoginResult;
+use lqos_bus::AuditLogEntry;
+use serde::{Deserialize, Serialize};
+
+/// Entries returned when the WebUI does not ask for a specific number.
+const DEFAULT_LIMIT: usize = 500;
+
+/// Filters for the WebUI audit log view and export.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct AuditLogQuery {
+    /// Only entries at or after this Unix timestamp (seconds).
+    pub since_unix: Option<u64>,
+    /// Only entries made by this user.
+    pub user: Option<String>,
+    /// Only entries for this action.
+    pub action: Option<String>,
+    /// Maximum number of entries.
+    pub limit: Option<usize>,
+}
+
+impl From<AuditLogQuery> for AuditQuery {
+    fn from(query: AuditLogQuery) -> Self {
+        let non_empty = |value: Option<String>| value.filter(|v| !v.trim().is_empty());
+        AuditQuery {
+            since_unix: query.since_unix,
+            user: non_empty(query.user),
+            action: non_empty(query.action),
+            limit: query.limit.unwrap_or(DEFAULT_LIMIT),
+        }
+    }
+}
+
+/// Audit log view for the WebUI, newest first. Only administrators may read it.
+pub fn audit_log_data(
+    login: LoginResult,
+    query: AuditLogQuery,
+) -> Result<Vec<AuditLogEntry>, String> {
+    if login != LoginResult::Admin {
+        return Err("Unauthorized".to_string());
+    }
+    Ok(audit::query(&query.into()))
+}
+
+/// Audit log export for the WebUI as JSON lines, oldest first. Only administrators may
+/// export it.
+pub fn audit_log_export_data(login: LoginResult, query: AuditLogQuery) -> Result<String, String> {
+    if login != LoginResult::Admin {
+        return Err("Unauthorized".to_string());
+    }
+    Ok(audit::export_jsonl(&query.into()))
+}
@@ -1,9 +1,12 @@
-use crate::node_manager::auth::LoginResult;
+use crate::audit::{AuditActor, AuditEvent};
+use crate::node_manager::auth::{LoginResult, SessionAccess};
 use crate::shaped_devices_tracker::SHAPED_DEVICES;
 use axum::http::StatusCode;
 use default_net::get_interfaces;
-use lqos_bus::{BusRequest, bus_request};
-use lqos_config::{Config, ConfigShapedDevices, ShapedDevice, UserRole, WebUser, WebUsers};
+use lqos_config::{
+    Config, ConfigShapedDevices, ShapedDevice, UserAccess, UserRole, WebUser, WebUsers,
+    is_valid_sqm_direction_token,
+};
 use lqos_utils::hash_to_i64;
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
@@ -230,7 +233,7 @@ fn validate_sqm_override(raw: &Option<String>) -> Result<(), String> {
     let Some(token) = normalize_sqm_override(raw) else {
         return Ok(());
     };
-    let valid = |value: &str| matches!(value, "" | "cake" | "fq_codel" | "none");
+    let valid = |value: &str| value.is_empty() || is_valid_sqm_direction_token(value);
     if token.contains('/') {
         let mut parts = token.splitn(2, '/');
         let down = parts.next().unwrap_or("").trim();
@@ -242,7 +245,7 @@ fn validate_sqm_override(raw: &Option<String>) -> Result<(), String> {
         return Ok(());
     }
     Err(format!(
-        "Invalid SQM override '{token}'. Allowed values: cake, fq_codel, none, or directional down/up tokens."
+        "Invalid SQM override '{token}'. Allowed values: cake, cake:<options>, fq_codel, none, or directional down/up tokens."
     ))
 }
 
@@ -350,17 +353,19 @@ fn persist_shaped_devices(mut devices: Vec<ShapedDevice>) -> Result<(), String>
     Ok(())
 }
 
-pub async fn update_lqosd_config_data(
+pub fn update_lqosd_config_data(
     login: LoginResult,
     config: Config,
+    actor: &AuditActor,
 ) -> Result<(), StatusCode> {
     if login != LoginResult::Admin {
+        AuditEvent::new("update_config")
+            .target("/etc/lqos.conf")
+            .record(actor, false, "Unauthorized");
         return Err(StatusCode::FORBIDDEN);
     }
-    bus_request(vec![BusRequest::UpdateLqosdConfig(Box::new(config))])
-        .await
-        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
-    Ok(())
+    crate::program_control::update_lqosd_config(&config, actor)
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)
 }
 
 /// Persists both `network.json` and `ShapedDevices.csv` for administrative
@@ -420,6 +425,15 @@ pub fn get_shaped_device_data(
         .cloned())
 }
 
+/// Starts an audit event for a shaped-device edit, capturing the row as it was.
+pub fn shaped_device_audit_event(action: &'static str, device_id: &str) -> AuditEvent {
+    let event = AuditEvent::new(action).target(device_id);
+    match get_shaped_device_data(LoginResult::Admin, device_id.to_string()) {
+        Ok(Some(device)) => event.before(&device),
+        _ => event,
+    }
+}
+
 /// Creates one shaped device row for administrative callers.
 ///
 /// Returns an error string when the caller is unauthorized, when integration-
@@ -489,16 +503,31 @@ pub fn delete_shaped_device_data(login: LoginResult, device_id: String) -> Resul
     Ok(())
 }
 
-pub fn get_users_data(login: LoginResult) -> Result<Vec<WebUser>, StatusCode> {
-    if login != LoginResult::Admin {
+pub fn get_users_data(access: &SessionAccess) -> Result<Vec<WebUser>, StatusCode> {
+    if !access.may_manage_users() {
         return Err(StatusCode::FORBIDDEN);
     }
     let users = WebUsers::load_or_create().map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
-    Ok(users.get_users())
+    Ok(users.get_users().iter().map(WebUser::redacted).collect())
 }
 
-pub fn add_user_data(login: LoginResult, data: UserRequest) -> Result<String, StatusCode> {
-    if login != LoginResult::Admin {
+/// May `access` edit or delete the existing account `username`? Accounts that do not exist
+/// yet are always manageable; built-in administrators are left to full administrators.
+fn existing_account_manageable(access: &SessionAccess, users: &WebUsers, username: &str) -> bool {
+    let Some(user) = users
+        .get_users()
+        .into_iter()
+        .find(|u| u.username == username)
+    else {
+        return true;
+    };
+    let target = users.access_for_user(username).unwrap_or_default();
+    access.may_manage_account(&target)
+        && (user.role != UserRole::Admin || access.login() == LoginResult::Admin)
+}
+
+pub fn add_user_data(access: &SessionAccess, data: UserRequest) -> Result<String, StatusCode> {
+    if !access.may_manage_users() {
         return Err(StatusCode::FORBIDDEN);
     }
     if data.username.trim().is_empty() {
@@ -508,18 +537,31 @@ pub fn add_user_data(login: LoginResult, data: UserRequest) -> Result<String, St
         Some(p) if !p.is_empty() => p,
         _ => return Err(StatusCode::BAD_REQUEST),
     };
+    let role: UserRole = data.role.into();
     let mut users = WebUsers::load_or_create().map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    // Adding an existing name overwrites that account, so it must be manageable too.
+    if !access.may_manage_account(&UserAccess::for_role(role))
+        || !existing_account_manageable(access, &users, data.username.trim())
+    {
+        return Err(StatusCode::FORBIDDEN);
+    }
     users
-        .add_or_update_user(data.username.trim(), password, data.role.into())
+        .add_or_update_user(data.username.trim(), password, role)
         .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
     Ok(format!("User '{}' added", data.username))
 }
 
-pub fn update_user_data(login: LoginResult, data: UserRequest) -> Result<String, StatusCode> {
-    if login != LoginResult::Admin {
+pub fn update_user_data(access: &SessionAccess, data: UserRequest) -> Result<String, StatusCode> {
+    if !access.may_manage_users() {
         return Err(StatusCode::FORBIDDEN);
     }
     let mut users = WebUsers::load_or_create().map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    let requested_role: UserRole = data.role.clone().into();
+    if !access.may_manage_account(&UserAccess::for_role(requested_role))
+        || !existing_account_manageable(access, &users, &data.username)
+    {
+        return Err(StatusCode::FORBIDDEN);
+    }
     let all_users = users.get_users();
 
     // Prevent turning the last administrator into a non-admin account.
@@ -530,7 +572,6 @@ pub fn update_user_data(login: LoginResult, data: UserRequest) -> Result<String,
             .iter()
             .filter(|u| u.role == UserRole::Admin)
             .count();
-        let requested_role: UserRole = data.role.clone().into();
         if admin_count <= 1 && requested_role != UserRole::Admin {
             return Err(StatusCode::BAD_REQUEST);
         }
@@ -538,16 +579,19 @@ pub fn update_user_data(login: LoginResult, data: UserRequest) -> Result<String,
 
     let password = data.password.as_deref().filter(|p| !p.is_empty());
     users
-        .update_user_with_optional_password(&data.username, password, data.role.into())
+        .update_user_with_optional_password(&data.username, password, requested_role)
         .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
     Ok("User updated".to_string())
 }
 
-pub fn delete_user_data(login: LoginResult, username: String) -> Result<String, StatusCode> {
-    if login != LoginResult::Admin {
+pub fn delete_user_data(access: &SessionAccess, username: String) -> Result<String, StatusCode> {
+    if !access.may_manage_users() {
         return Err(StatusCode::FORBIDDEN);
     }
     let mut users = WebUsers::load_or_create().map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    if !existing_account_manageable(access, &users, &username) {
+        return Err(StatusCode::FORBIDDEN);
+    }
     let all_users = users.get_users();
 
     // Prevent deleting the final administrator account.
@@ -578,9 +622,30 @@ pub struct UserRequest {
 
 #[cfg(test)]
 mod tests {
-    use super::validate_network_json;
+    use super::{validate_network_json, validate_sqm_override};
     use serde_json::json;
 
+    #[test]
+    fn accepts_cake_profile_sqm_overrides() {
+        for token in [
+            "cake",
+            "CAKE:besteffort",
+            "cake:diffserv4/fq_codel",
+            "/cake:ack-filter",
+        ] {
+            assert!(
+                validate_sqm_override(&Some(token.to_string())).is_ok(),
+                "{token}"
+            );
+        }
+        for token in ["fq_pie", "cake:bogus", "cake/sfq"] {
+            assert!(
+                validate_sqm_override(&Some(token.to_string())).is_err(),
+                "{token}"
+            );
+        }
+    }
+
     #[test]
     fn accepts_unique_node_names() {
         let network = json!({
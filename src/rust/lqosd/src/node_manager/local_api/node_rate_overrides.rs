@@ -1,7 +1,7 @@
 use crate::node_manager::auth::LoginResult;
 use axum::http::StatusCode;
 use lqos_config::load_config;
-use lqos_overrides::{NetworkAdjustment, OverrideLayer, OverrideStore};
+use lqos_overrides::{ChangeAuthor, NetworkAdjustment, OverrideLayer, OverrideStore, SaveContext};
 use serde::{Deserialize, Serialize};
 use std::path::Path;
 
@@ -64,6 +64,7 @@ pub fn get_node_rate_override_data(
 /// Save or replace the operator-owned rate override for a tree node.
 pub fn set_node_rate_override_data(
     login: LoginResult,
+    username: &str,
     update: NodeRateOverrideUpdate,
 ) -> Result<NodeRateOverrideData, StatusCode> {
     if login != LoginResult::Admin {
@@ -93,7 +94,11 @@ pub fn set_node_rate_override_data(
         update.upload_bandwidth_mbps,
     );
     if changed {
-        OverrideStore::save_layer(OverrideLayer::Operator, &overrides)
+        let ctx = SaveContext::new(
+            webui_author(username),
+            format!("Set rate override for node '{}'", query.node_name),
+        );
+        OverrideStore::save_layer(OverrideLayer::Operator, &overrides, &ctx)
             .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
     }
 
@@ -103,6 +108,7 @@ pub fn set_node_rate_override_data(
 /// Remove the operator-owned rate override for a tree node.
 pub fn clear_node_rate_override_data(
     login: LoginResult,
+    username: &str,
     query: NodeRateOverrideQuery,
 ) -> Result<NodeRateOverrideData, StatusCode> {
     if login != LoginResult::Admin {
@@ -125,13 +131,23 @@ pub fn clear_node_rate_override_data(
         .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
     let removed = overrides.remove_site_bandwidth_override_count(Some(node_id), &query.node_name);
     if removed > 0 {
-        OverrideStore::save_layer(OverrideLayer::Operator, &overrides)
+        let ctx = SaveContext::new(
+            webui_author(username),
+            format!("Cleared rate override for node '{}'", query.node_name),
+        );
+        OverrideStore::save_layer(OverrideLayer::Operator, &overrides, &ctx)
             .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
     }
 
     build_node_rate_override_data(login, query)
 }
 
+fn webui_author(username: &str) -> ChangeAuthor {
+    ChangeAuthor::User {
+        name: username.to_string(),
+    }
+}
+
 fn build_node_rate_override_data(
     login: LoginResult,
     query: NodeRateOverrideQuery,
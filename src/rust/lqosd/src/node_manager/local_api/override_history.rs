@@ -0,0 +1,141 @@
+use crate::node_manager::auth::LoginResult;
+use lqos_bus::{OverrideDiffLine, OverrideHistoryEntry};
+use lqos_overrides::{
+    ChangeAuthor, DiffKind, HistoryEntry, OverrideDiffEntry, OverrideLayer, OverrideStore,
+    SaveContext,
+};
+
+pub(crate) fn parse_layer(layer: &str) -> Result<OverrideLayer, String> {
+    match layer.trim().to_ascii_lowercase().as_str() {
+        "operator" => Ok(OverrideLayer::Operator),
+        "stormguard" => Ok(OverrideLayer::Stormguard),
+        "treeguard" => Ok(OverrideLayer::Treeguard),
+        other => Err(format!(
+            "Unknown overrides layer '{other}' (expected operator, stormguard or treeguard)"
+        )),
+    }
+}
+
+fn layer_name(layer: OverrideLayer) -> &'static str {
+    match layer {
+        OverrideLayer::Operator => "operator",
+        OverrideLayer::Stormguard => "stormguard",
+        OverrideLayer::Treeguard => "treeguard",
+    }
+}
+
+fn to_bus_entry(entry: HistoryEntry) -> OverrideHistoryEntry {
+    OverrideHistoryEntry {
+        version: entry.version,
+        layer: layer_name(entry.layer).to_string(),
+        author: entry.author.to_string(),
+        timestamp_unix: entry.timestamp_unix,
+        reason: entry.reason,
+    }
+}
+
+fn to_bus_diff(entry: OverrideDiffEntry) -> OverrideDiffLine {
+    let kind = match entry.kind {
+        DiffKind::Added => "added",
+        DiffKind::Removed => "removed",
+        DiffKind::Changed => "changed",
+    };
+    OverrideDiffLine {
+        section: entry.section,
+        key: entry.key,
+        kind: kind.to_string(),
+        before: entry.before.map(|v| v.to_string()),
+        after: entry.after.map(|v| v.to_string()),
+    }
+}
+
+/// Recorded versions of an overrides layer, newest first.
+pub fn override_history(layer: &str) -> Result<Vec<OverrideHistoryEntry>, String> {
+    let layer = parse_layer(layer)?;
+    let entries = OverrideStore::list_history(layer).map_err(|e| e.to_string())?;
+    Ok(entries.into_iter().map(to_bus_entry).collect())
+}
+
+/// Differences between two versions of a layer (`to_version = None` is the current file).
+pub fn override_diff(
+    layer: &str,
+    from_version: u64,
+    to_version: Option<u64>,
+) -> Result<Vec<OverrideDiffLine>, String> {
+    let layer = parse_layer(layer)?;
+    let diff = OverrideStore::diff_history_versions(layer, from_version, to_version)
+        .map_err(|e| e.to_string())?;
+    Ok(diff.into_iter().map(to_bus_diff).collect())
+}
+
+/// Restore a layer to a recorded version, recording the rollback as a new version.
+pub fn rollback_override_layer(
+    layer: &str,
+    version: u64,
+    author: &str,
+    reason: &str,
+) -> Result<OverrideHistoryEntry, String> {
+    let layer = parse_layer(layer)?;
+    let name = if author.trim().is_empty() {
+        "unknown"
+    } else {
+        author.trim()
+    };
+    let ctx = SaveContext::new(
+        ChangeAuthor::User {
+            name: name.to_string(),
+        },
+        reason.trim(),
+    );
+    let entry = OverrideStore::rollback_layer(layer, version, &ctx).map_err(|e| e.to_string())?;
+    tracing::info!(
+        layer = layer_name(layer),
+        version,
+        new_version = entry.version,
+        "Rolled back overrides layer"
+    );
+    Ok(to_bus_entry(entry))
+}
+
+/// History view for the WebUI. Any signed-in user may read it.
+pub fn override_history_data(
+    login: LoginResult,
+    layer: &str,
+) -> Result<Vec<OverrideHistoryEntry>, String> {
+    if login == LoginResult::Denied {
+        return Err("Unauthorized".to_string());
+    }
+    override_history(layer)
+}
+
+/// Diff view for the WebUI. Any signed-in user may read it.
+pub fn override_diff_data(
+    login: LoginResult,
+    layer: &str,
+    from_version: u64,
+    to_version: Option<u64>,
+) -> Result<Vec<OverrideDiffLine>, String> {
+    if login == LoginResult::Denied {
+        return Err("Unauthorized".to_string());
+    }
+    override_diff(layer, from_version, to_version)
+}
+
+/// WebUI rollback, recorded under the signed-in user's name. Only administrators may restore
+/// a version.
+pub fn rollback_override_layer_data(
+    login: LoginResult,
+    username: &str,
+    layer: &str,
+    version: u64,
+    reason: &str,
+) -> Result<String, String> {
+    if login != LoginResult::Admin {
+        return Err("Unauthorized".to_string());
+    }
+    let entry = rollback_override_layer(layer, version, username, reason)?;
+    Ok(format!(
+        "Restored {} overrides to version {version} (recorded as version {})",
+        entry.layer, entry.version
+    ))
+}
@@ -1,7 +1,10 @@
+use crate::node_manager::auth::SessionAccess;
+use axum::Extension;
 use axum::body::Body;
 use axum::extract::Path;
-use axum::http::{HeaderMap, Request};
-use axum::response::IntoResponse;
+use axum::http::{HeaderMap, Request, StatusCode};
+use axum::response::{IntoResponse, Response};
+use lqos_config::Permission;
 use lqos_heimdall::n_second_pcap;
 use serde::Serialize;
 use std::net::IpAddr;
@@ -25,7 +28,14 @@ pub fn request_analysis_data(ip: &str) -> RequestAnalysisResult {
     RequestAnalysisResult::Fail
 }
 
-pub async fn pcap_dump(Path(id): Path<usize>, headers: HeaderMap) -> impl IntoResponse {
+pub async fn pcap_dump(
+    Extension(access): Extension<SessionAccess>,
+    Path(id): Path<usize>,
+    headers: HeaderMap,
+) -> Response {
+    if !access.allows(Permission::PacketCapture) {
+        return StatusCode::FORBIDDEN.into_response();
+    }
     let filename = n_second_pcap(id).expect("Could not determine pcap filename");
     let mut req = Request::new(Body::empty());
     *req.headers_mut() = headers;
@@ -33,4 +43,5 @@ pub async fn pcap_dump(Path(id): Path<usize>, headers: HeaderMap) -> impl IntoRe
         .try_call(req)
         .await
         .expect("ServeFile call failed")
+        .into_response()
 }
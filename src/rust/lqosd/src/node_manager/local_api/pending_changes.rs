@@ -0,0 +1,62 @@
+use crate::node_manager::auth::LoginResult;
+use axum::http::StatusCode;
+use lqos_overrides::{OverrideLayer, OverrideStore, PendingChange};
+use serde::{Deserialize, Serialize};
+use std::sync::atomic::Ordering;
+
+/// Change-control queue view for the approval page.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct PendingChangesData {
+    /// Whether `[change_control].require_approval` is currently enabled.
+    pub require_approval: bool,
+    /// Changes waiting for approval, oldest first.
+    pub changes: Vec<PendingChange>,
+}
+
+/// Load the pending change queue. Only administrators may review changes.
+pub fn get_pending_changes_data(login: LoginResult) -> Result<PendingChangesData, StatusCode> {
+    if login != LoginResult::Admin {
+        return Err(StatusCode::FORBIDDEN);
+    }
+    let require_approval = lqos_config::load_config()
+        .map(|config| config.change_control.require_approval)
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    let changes = OverrideStore::load_pending_changes().map_err(|e| {
+        tracing::warn!("Unable to load pending changes: {e}");
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+    Ok(PendingChangesData {
+        require_approval,
+        changes,
+    })
+}
+
+/// Approve a pending change, writing it to the owning overrides layer. StormGuard changes
+/// were held back from the live queues, so StormGuard is asked to replay its layer.
+pub fn approve_pending_change_data(login: LoginResult, id: u64) -> Result<String, StatusCode> {
+    if login != LoginResult::Admin {
+        return Err(StatusCode::FORBIDDEN);
+    }
+    let (change, _changed) = OverrideStore::approve_pending_change(id).map_err(|e| {
+        tracing::warn!("Unable to approve pending change {id}: {e}");
+        StatusCode::BAD_REQUEST
+    })?;
+    if change.layer == OverrideLayer::Stormguard {
+        lqos_stormguard::STORMGUARD_RELOAD_REQUESTED.store(true, Ordering::Relaxed);
+    }
+    tracing::info!(id, summary = %change.summary, "Approved pending override change");
+    Ok(format!("Approved: {}", change.summary))
+}
+
+/// Reject (discard) a pending change.
+pub fn reject_pending_change_data(login: LoginResult, id: u64) -> Result<String, StatusCode> {
+    if login != LoginResult::Admin {
+        return Err(StatusCode::FORBIDDEN);
+    }
+    let change = OverrideStore::reject_pending_change(id).map_err(|e| {
+        tracing::warn!("Unable to reject pending change {id}: {e}");
+        StatusCode::BAD_REQUEST
+    })?;
+    tracing::info!(id, summary = %change.summary, "Rejected pending override change");
+    Ok(format!("Rejected: {}", change.summary))
+}
@@ -1,16 +1,19 @@
+use crate::audit::{AuditActor, AuditEvent};
 use crate::node_manager::auth::LoginResult;
 use tokio::task::spawn_blocking;
 use tracing::info;
 
-pub async fn reload_libreqos_with_login(login: LoginResult) -> String {
+pub async fn reload_libreqos_with_login(login: LoginResult, actor: &AuditActor) -> String {
     info!("Reloading LibreQoS");
-    if let LoginResult::Admin = login {
-        let Ok(result) = spawn_blocking(lqos_config::load_libreqos).await else {
-            return "Failed to spawn blocking thread".to_string();
-        };
-        //println!("{:?}", result);
-        result.unwrap_or_else(|_| "Unable to reload LibreQoS".to_string())
+    let (ok, message) = if let LoginResult::Admin = login {
+        match spawn_blocking(lqos_config::load_libreqos).await {
+            Ok(Ok(message)) => (true, message),
+            Ok(Err(_)) => (false, "Unable to reload LibreQoS".to_string()),
+            Err(_) => (false, "Failed to spawn blocking thread".to_string()),
+        }
     } else {
-        "You must be an admin to reload LibreQoS".to_string()
-    }
+        (false, "You must be an admin to reload LibreQoS".to_string())
+    };
+    AuditEvent::new("reload_libreqos").record(actor, ok, message.as_str());
+    message
 }
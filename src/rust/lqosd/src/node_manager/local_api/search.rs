@@ -1,3 +1,4 @@
+use crate::node_manager::auth::ScopeFilter;
 use crate::shaped_devices_tracker::{NETWORK_JSON, SHAPED_DEVICES};
 use ip_network::IpNetwork;
 use serde::{Deserialize, Serialize};
@@ -70,6 +71,14 @@ pub enum SearchResult {
 }
 
 pub fn search_results(search: SearchRequest) -> Vec<SearchResult> {
+    search_results_in_scope(search, None)
+}
+
+/// Searches circuits, devices and sites, skipping anything outside `scope` when given.
+pub fn search_results_in_scope(
+    search: SearchRequest,
+    scope: Option<&ScopeFilter>,
+) -> Vec<SearchResult> {
     const MAX_RESULTS: usize = 50;
     let mut results: Vec<SearchResult> = Vec::new();
     let mut seen: HashSet<String> = HashSet::new(); // keys like "Device:<circuit_id>:<name>" or "Circuit:<id>" or "Site:<idx>"
@@ -78,6 +87,7 @@ pub fn search_results(search: SearchRequest) -> Vec<SearchResult> {
     let term_lc = raw_term.to_lowercase();
     let exact_ip: Option<IpAddr> = raw_term.parse::<IpAddr>().ok();
     let looks_like_ip_prefix = raw_term.contains('.') || raw_term.contains(':');
+    let in_scope = |node: &str| scope.is_none_or(|scope| scope.allows_node(node));
 
     // Helper to add results with de-dup and cap
     fn push_result(
@@ -110,6 +120,7 @@ pub fn search_results(search: SearchRequest) -> Vec<SearchResult> {
         };
         if let Some((net, &idx)) = sd_reader.trie.longest_match(query_v6)
             && let Some(dev) = sd_reader.devices.get(idx)
+            && in_scope(&dev.parent_node)
         {
             let name = format!("{} ({})", dev.device_name, pretty_net(&net));
             push_result(
@@ -150,6 +161,7 @@ pub fn search_results(search: SearchRequest) -> Vec<SearchResult> {
                         }
                         if ipv6_overlap(&n, query_v6)
                             && let Some(dev) = sd_reader.devices.get(idx)
+                            && in_scope(&dev.parent_node)
                         {
                             let name = format!("{} ({})", dev.device_name, pretty_net(&n));
                             push_result(
@@ -176,6 +188,7 @@ pub fn search_results(search: SearchRequest) -> Vec<SearchResult> {
                 let s = pretty_net(&n);
                 if s.starts_with(raw_term)
                     && let Some(dev) = sd_reader.devices.get(idx)
+                    && in_scope(&dev.parent_node)
                 {
                     let name = format!("{} ({})", dev.device_name, s);
                     push_result(
@@ -200,6 +213,9 @@ pub fn search_results(search: SearchRequest) -> Vec<SearchResult> {
             if results.len() >= MAX_RESULTS {
                 break;
             }
+            if !in_scope(&sd.parent_node) {
+                continue;
+            }
             let circuit_name_lc = sd.circuit_name.to_lowercase();
             if circuit_name_lc.contains(&term_lc) {
                 push_result(
@@ -238,7 +254,7 @@ pub fn search_results(search: SearchRequest) -> Vec<SearchResult> {
             if results.len() >= MAX_RESULTS {
                 break;
             }
-            if n.name.to_lowercase().contains(&term_lc) {
+            if n.name.to_lowercase().contains(&term_lc) && in_scope(&n.name) {
                 push_result(
                     &mut results,
                     &mut seen,
@@ -1,3 +1,4 @@
+use crate::node_manager::auth::ScopeFilter;
 use crate::shaped_devices_tracker::SHAPED_DEVICES;
 use lqos_config::ShapedDevice;
 use serde::{Deserialize, Deserializer, Serialize};
@@ -48,8 +49,12 @@ fn normalized_page_size(query: &ShapedDevicesPageQuery) -> usize {
         .clamp(1, MAX_SHAPED_DEVICES_PAGE_SIZE)
 }
 
-/// Returns one filtered, sorted page of shaped-device rows.
-pub fn shaped_devices_page(query: ShapedDevicesPageQuery) -> ShapedDevicesPage {
+/// Returns one filtered, sorted page of shaped-device rows. Scoped sessions only see devices
+/// attached below their `network.json` scope.
+pub fn shaped_devices_page(
+    query: ShapedDevicesPageQuery,
+    scope: Option<&ScopeFilter>,
+) -> ShapedDevicesPage {
     let page = query.page.unwrap_or(0);
     let page_size = normalized_page_size(&query);
     let search = query.search.as_deref().unwrap_or("").trim().to_lowercase();
@@ -58,6 +63,7 @@ pub fn shaped_devices_page(query: ShapedDevicesPageQuery) -> ShapedDevicesPage {
     let mut filtered: Vec<ShapedDevice> = devices
         .devices
         .iter()
+        .filter(|device| scope.is_none_or(|scope| scope.allows_node(&device.parent_node)))
         .filter(|device| {
             if search.is_empty() {
                 return true;
@@ -80,7 +80,8 @@ fn normalized_page_size(query: &TreeAttachedCircuitsQuery) -> usize {
         .clamp(1, MAX_ATTACHED_CIRCUITS_PAGE_SIZE)
 }
 
-fn resolve_node_name(query: &TreeAttachedCircuitsQuery) -> Option<String> {
+/// Resolves the queried node id or path to a `network.json` node name.
+pub(crate) fn resolve_node_name(query: &TreeAttachedCircuitsQuery) -> Option<String> {
     let reader = NETWORK_JSON.read();
     let nodes = reader.get_nodes_when_ready();
 
@@ -1,4 +1,5 @@
 use crate::lts2_sys::control_channel::ControlChannelCommand;
+use crate::node_manager::api_v1::api_v1_router;
 use crate::node_manager::local_api::local_api;
 use crate::node_manager::shaper_queries_actor::shaper_queries_actor;
 use crate::node_manager::{
@@ -14,6 +15,7 @@ use axum::response::Redirect;
 use axum::routing::{get, post};
 use lqos_bus::BusRequest;
 use lqos_config::load_config;
+use std::net::SocketAddr;
 use std::path::Path;
 use tokio::net::TcpListener;
 use tokio::sync::mpsc::Sender;
@@ -46,6 +48,9 @@ pub async fn spawn_webserver(
         .unwrap_or(":::9123".to_string());
     let listener = TcpListener::bind(&listen_address).await?;
 
+    // Poll federation peers, if any are configured
+    super::federation::start_federation_poller();
+
     // Setup shaper queries
     let shaper_tx = shaper_queries_actor(control_tx.clone()).await;
 
@@ -56,6 +61,9 @@ pub async fn spawn_webserver(
         .route("/first-run.html", get(auth::first_run_page))
         .route("/doLogin", post(auth::try_login))
         .route("/firstLogin", post(auth::first_user))
+        .route("/oidc/status", get(auth::oidc_status))
+        .route("/oidc/login", get(auth::oidc_login))
+        .route("/oidc/callback", get(auth::oidc_callback))
         .route("/health", get(health_check))
         // Backwards compatible aliases for historical misspellings.
         .route_service(
@@ -79,10 +87,16 @@ pub async fn spawn_webserver(
         .nest("/", static_routes()?)
         .nest("/local-api", local_api(shaper_tx))
         .fallback_service(ServeDir::new(static_path))
-        .layer(CorsLayer::very_permissive());
+        .layer(CorsLayer::very_permissive())
+        // Nested after the CORS layer so the API sets its own (read-only) CORS policy.
+        .nest("/api/v1", api_v1_router(bus_tx.clone()));
 
     info!("Webserver listening on: [{listen_address}]");
-    axum::serve(listener, router).await?;
+    axum::serve(
+        listener,
+        router.into_make_service_with_connect_info::<SocketAddr>(),
+    )
+    .await?;
     Ok(())
 }
 
@@ -78,10 +78,12 @@ pub(super) fn static_routes() -> Result<Router> {
         "config_wispgate.html",
         "config_stormguard.html",
         "config_treeguard.html",
+        "config_change_control.html",
         "stormguard_debug.html",
         "api.html",
         "cpu_weights.html",
         "cpu_tree.html",
+        "federation.html",
     ];
 
     // Iterate through pages and construct the router
@@ -8,16 +8,21 @@
 use std::collections::{BTreeMap, HashSet};
 use std::sync::Arc;
 
-use crate::node_manager::auth::{LoginResult, login_from_token};
+use crate::audit::{AuditActor, AuditEvent};
+use crate::node_manager::auth::{
+    LoginResult, ScopeFilter, SessionAccess, access_from_token, client_source,
+};
+use crate::node_manager::federation;
 use crate::node_manager::local_api::{
-    circuit, circuit_count, config, cpu_affinity, dashboard_themes, device_counts, directories,
-    ethernet_caps, executive, flow_explorer, flow_map, lts, network_tree, network_tree_lite,
-    node_rate_overrides, packet_analysis, reload_libreqos, scheduler, search, shaped_device_api,
-    shaped_devices_page, unknown_ips, urgent, warnings,
+    audit_log, circuit, circuit_count, config, cpu_affinity, dashboard_themes, device_counts,
+    directories, ethernet_caps, executive, flow_explorer, flow_map, lts, network_tree,
+    network_tree_lite, node_rate_overrides, override_history, packet_analysis, pending_changes,
+    reload_libreqos, scheduler, search, shaped_device_api, shaped_devices_page, unknown_ips,
+    urgent, warnings,
 };
 use crate::node_manager::shaper_queries_actor::ShaperQueryCommand;
 use crate::node_manager::ws::messages::{
-    WS_HANDSHAKE_REQUIREMENT, WsHello, WsRequest, WsResponse, encode_ws_message,
+    PrivateRequest, WS_HANDSHAKE_REQUIREMENT, WsHello, WsRequest, WsResponse, encode_ws_message,
 };
 use crate::node_manager::ws::publish_subscribe::PubSub;
 use crate::node_manager::ws::published_channels::PublishedChannels;
@@ -32,7 +37,7 @@ use crate::system_stats::SystemStats;
 use axum::{
     Extension, Router,
     extract::{
-        WebSocketUpgrade,
+        ConnectInfo, WebSocketUpgrade,
         ws::{Message, WebSocket},
     },
     http::{HeaderMap, StatusCode, header},
@@ -41,7 +46,9 @@ use axum::{
 };
 use futures_util::{SinkExt, StreamExt};
 use lqos_bus::BusRequest;
+use lqos_config::Permission;
 use serde_cbor::Value as CborValue;
+use std::net::SocketAddr;
 use tokio::sync::mpsc::Sender;
 use tracing::{info, warn};
 
@@ -53,6 +60,13 @@ mod ticker;
 
 const WS_VERSION: &str = include_str!("../../../../VERSION_STRING");
 const HANDSHAKE_TIMEOUT_SECS: u64 = 10;
+/// Pubsub channels that carry nothing about individual sites or circuits, and so are open to
+/// sessions limited to part of the network.
+const SCOPED_CHANNELS: &[PublishedChannels] = &[
+    PublishedChannels::Cadence,
+    PublishedChannels::Cpu,
+    PublishedChannels::Ram,
+];
 
 /// Provides an Axum router for the websocket system. Exposes a single /ws route that supports
 /// pubsub subscriptions and private commands.
@@ -91,6 +105,7 @@ async fn ws_handler(
         tokio::sync::mpsc::Sender<crate::lts2_sys::control_channel::ControlChannelCommand>,
     >,
     Extension(shaper_query): Extension<Sender<ShaperQueryCommand>>,
+    ConnectInfo(peer): ConnectInfo<SocketAddr>,
     headers: HeaderMap,
 ) -> impl IntoResponse {
     let has_cookie = headers.contains_key(header::COOKIE);
@@ -104,6 +119,7 @@ async fn ws_handler(
         .get(header::ACCEPT_LANGUAGE)
         .and_then(|h| h.to_str().ok())
         .map(|s| s.to_string());
+    let source = client_source(peer, &headers);
     ws.on_upgrade(move |socket| async move {
         handle_socket(
             socket,
@@ -112,6 +128,7 @@ async fn ws_handler(
             control_tx,
             shaper_query,
             browser_language,
+            source,
         )
         .await;
     })
@@ -124,6 +141,7 @@ async fn handle_socket(
     control_tx: tokio::sync::mpsc::Sender<crate::lts2_sys::control_channel::ControlChannelCommand>,
     shaper_query: Sender<ShaperQueryCommand>,
     browser_language: Option<String>,
+    source: String,
 ) {
     info!("Websocket connected");
 
@@ -141,7 +159,7 @@ async fn handle_socket(
     });
     let mut subscribed_channels = HashSet::new();
     let mut handshake_complete = false;
-    let mut login = LoginResult::Denied;
+    let mut access = SessionAccess::denied();
     let handshake_timeout =
         tokio::time::sleep(std::time::Duration::from_secs(HANDSHAKE_TIMEOUT_SECS));
     tokio::pin!(handshake_timeout);
@@ -178,8 +196,9 @@ async fn handle_socket(
                             &mut handshake_complete,
                             &mut WsRequestState {
                                 private_state: &mut private_state,
-                                login: &mut login,
+                                access: &mut access,
                                 shaper_query: shaper_query.clone(),
+                                source: &source,
                             },
                         )
                         .await;
@@ -205,6 +224,65 @@ async fn handle_socket(
     info!("Websocket disconnected");
 }
 
+/// Can a session limited to `scope` make this request? Shaper-wide requests are refused;
+/// requests about a circuit, node or address are allowed only inside the scope. Handlers
+/// that return lists filter them separately.
+fn scoped_request_permitted(request: &WsRequest, scope: &ScopeFilter) -> bool {
+    let circuit_ok = |circuit: &str| scope.allows_circuit(circuit);
+    let ip_ok = |ip: &str| {
+        ip.parse::<std::net::IpAddr>()
+            .is_ok_and(|ip| scope.allows_ip(&ip))
+    };
+    match request {
+        WsRequest::Subscribe { channel } => SCOPED_CHANNELS.contains(channel),
+        WsRequest::Unsubscribe { .. }
+        | WsRequest::HelloReply(_)
+        | WsRequest::AdminCheck
+        | WsRequest::DashletThemes
+        | WsRequest::DashletSave { .. }
+        | WsRequest::DashletGet { .. }
+        | WsRequest::DashletDelete { .. }
+        | WsRequest::Search { .. }
+        | WsRequest::NetworkTree
+        | WsRequest::ShapedDevicesPage { .. }
+        | WsRequest::ReloadLibreQoS => true,
+        WsRequest::GetUsers
+        | WsRequest::AddUser { .. }
+        | WsRequest::UpdateUser { .. }
+        | WsRequest::DeleteUser { .. } => false,
+        WsRequest::CircuitById { id } => circuit_ok(id),
+        WsRequest::CircuitDevices { circuit } | WsRequest::CircuitFlowSankey { circuit } => {
+            circuit_ok(circuit)
+        }
+        WsRequest::CircuitTopAsns { query } => circuit_ok(&query.circuit),
+        WsRequest::CircuitTrafficFlowsPage { query } => circuit_ok(&query.circuit),
+        WsRequest::SetCircuitRttExcluded { circuit_id, .. } => circuit_ok(circuit_id),
+        WsRequest::GetNodeRateOverride { query } | WsRequest::ClearNodeRateOverride { query } => {
+            scope.allows_node(&query.node_name)
+        }
+        WsRequest::SetNodeRateOverride { update } => scope.allows_node(&update.node_name),
+        WsRequest::RequestAnalysis { ip } => ip_ok(ip),
+        WsRequest::Private(command) => match command {
+            PrivateRequest::CircuitWatcher { circuit }
+            | PrivateRequest::CakeWatcher { circuit } => circuit_ok(circuit),
+            PrivateRequest::PingMonitor { ips } => ips.iter().all(|(ip, _)| ip_ok(ip)),
+            PrivateRequest::WatchTreeAttachedCircuits { query } => {
+                crate::node_manager::local_api::tree_attached_circuits::resolve_node_name(query)
+                    .is_some_and(|node| scope.allows_node(&node))
+            }
+            PrivateRequest::WatchCircuitMetrics { query } => {
+                query.circuit_ids.iter().all(|circuit| circuit_ok(circuit))
+            }
+            PrivateRequest::StopCircuitWatcher
+            | PrivateRequest::StopPingMonitorWatch
+            | PrivateRequest::StopTreeAttachedCircuitsWatch
+            | PrivateRequest::StopCircuitMetricsWatch => true,
+            PrivateRequest::Chatbot { .. } | PrivateRequest::ChatbotUserInput { .. } => false,
+        },
+        _ => false,
+    }
+}
+
 fn is_benign_recv_error(err: &axum::Error, handshake_complete: bool) -> bool {
     if !handshake_complete {
         return false;
@@ -215,8 +293,16 @@ fn is_benign_recv_error(err: &axum::Error, handshake_complete: bool) -> bool {
 
 struct WsRequestState<'a> {
     private_state: &'a mut single_user_channels::PrivateState,
-    login: &'a mut LoginResult,
+    access: &'a mut SessionAccess,
     shaper_query: Sender<ShaperQueryCommand>,
+    /// Client address, as recorded in the audit log.
+    source: &'a str,
+}
+
+impl WsRequestState<'_> {
+    fn audit_actor(&self) -> AuditActor {
+        AuditActor::web(self.access.username(), self.source)
+    }
 }
 
 async fn receive_channel_message(
@@ -270,12 +356,12 @@ async fn receive_channel_message(
                 return true;
             }
             let token = reply.token.trim();
-            let login_result = login_from_token(token).await;
-            if login_result == LoginResult::Denied {
+            let access = access_from_token(token).await;
+            if access.login() == LoginResult::Denied {
                 warn!("Websocket handshake token rejected");
                 return true;
             }
-            *request_state.login = login_result;
+            *request_state.access = access;
             *handshake_complete = true;
             info!("Websocket handshake completed");
             return false;
@@ -284,6 +370,16 @@ async fn receive_channel_message(
         return true;
     }
 
+    let scope = request_state.access.scope_filter();
+    if let Some(scope) = &scope
+        && !scoped_request_permitted(&request, scope)
+    {
+        let response = WsResponse::Error {
+            message: "Not available to users limited to part of the network".to_string(),
+        };
+        return send_ws_response(&tx, response).await;
+    }
+
     match request {
         WsRequest::Subscribe { channel } => {
             if !subscribed_channels.contains(&channel) {
@@ -380,7 +476,7 @@ async fn receive_channel_message(
         }
         WsRequest::ShapedDevicesPage { query } => {
             let response = WsResponse::ShapedDevicesPage {
-                data: shaped_devices_page::shaped_devices_page(query),
+                data: shaped_devices_page::shaped_devices_page(query, scope.as_ref()),
             };
             if send_ws_response(&tx, response).await {
                 return true;
@@ -411,9 +507,11 @@ async fn receive_channel_message(
             }
         }
         WsRequest::NetworkTree => {
-            let response = WsResponse::NetworkTree {
-                data: network_tree::network_tree_data(),
-            };
+            let mut data = network_tree::network_tree_data();
+            if let Some(scope) = &scope {
+                data.retain(|(_, node)| scope.allows_node(&node.name));
+            }
+            let response = WsResponse::NetworkTree { data };
             if send_ws_response(&tx, response).await {
                 return true;
             }
@@ -482,37 +580,39 @@ async fn receive_channel_message(
             circuit_id,
             excluded,
         } => {
-            if *request_state.login != crate::node_manager::auth::LoginResult::Admin {
-                let response = WsResponse::SetCircuitRttExcludedResult {
-                    ok: false,
-                    message: "Unauthorized".to_string(),
-                    circuit_id,
-                    excluded,
-                };
-                if send_ws_response(&tx, response).await {
-                    return true;
-                }
+            let (ok, message) = if !request_state.access.allows(Permission::ManageOverrides) {
+                (false, "Unauthorized".to_string())
             } else {
-                let (ok, message) =
-                    match crate::rtt_exclusions::set_excluded_circuit_id(&circuit_id, excluded) {
-                        Ok(_) => (true, "Ok".to_string()),
-                        Err(e) => (false, format!("{e:?}")),
-                    };
-                let response = WsResponse::SetCircuitRttExcludedResult {
-                    ok,
-                    message,
-                    circuit_id,
+                match crate::rtt_exclusions::set_excluded_circuit_id(
+                    &circuit_id,
                     excluded,
-                };
-                if send_ws_response(&tx, response).await {
-                    return true;
+                    request_state.access.username(),
+                ) {
+                    Ok(_) => (true, "Ok".to_string()),
+                    Err(e) => (false, format!("{e:?}")),
                 }
+            };
+            AuditEvent::new("set_circuit_rtt_excluded")
+                .target(circuit_id.as_str())
+                .after(&excluded)
+                .record(&request_state.audit_actor(), ok, message.as_str());
+            let response = WsResponse::SetCircuitRttExcludedResult {
+                ok,
+                message,
+                circuit_id,
+                excluded,
+            };
+            if send_ws_response(&tx, response).await {
+                return true;
             }
         }
         WsRequest::RequestAnalysis { ip } => {
-            let response = WsResponse::RequestAnalysisResult {
-                data: packet_analysis::request_analysis_data(&ip),
+            let data = if request_state.access.allows(Permission::PacketCapture) {
+                packet_analysis::request_analysis_data(&ip)
+            } else {
+                packet_analysis::RequestAnalysisResult::Fail
             };
+            let response = WsResponse::RequestAnalysisResult { data };
             if send_ws_response(&tx, response).await {
                 return true;
             }
@@ -664,14 +764,24 @@ async fn receive_channel_message(
             }
         }
         WsRequest::UrgentClear { id } => {
+            let mut event = AuditEvent::new("clear_urgent_issue").target(id.to_string());
+            if let Some(issue) = crate::urgent::get(id) {
+                event = event.before(&issue);
+            }
             let ok = urgent::urgent_clear_id(id);
+            let message = if ok { "Ok" } else { "Not found" };
+            event.record(&request_state.audit_actor(), ok, message);
             let response = WsResponse::UrgentClearResult { ok };
             if send_ws_response(&tx, response).await {
                 return true;
             }
         }
         WsRequest::UrgentClearAll => {
+            let cleared = urgent::urgent_status_data().count;
             urgent::urgent_clear_all_data();
+            AuditEvent::new("clear_all_urgent_issues")
+                .before(&cleared)
+                .record(&request_state.audit_actor(), true, "Ok");
             let response = WsResponse::UrgentClearAllResult { ok: true };
             if send_ws_response(&tx, response).await {
                 return true;
@@ -830,7 +940,7 @@ async fn receive_channel_message(
             body,
             commentor,
         } => {
-            if *request_state.login != LoginResult::Admin {
+            if request_state.access.login() != LoginResult::Admin {
                 if send_ws_response(
                     &tx,
                     WsResponse::Error {
@@ -910,7 +1020,7 @@ async fn receive_channel_message(
             commentor,
             body,
         } => {
-            if *request_state.login != LoginResult::Admin {
+            if request_state.access.login() != LoginResult::Admin {
                 if send_ws_response(
                     &tx,
                     WsResponse::Error {
@@ -991,20 +1101,28 @@ async fn receive_channel_message(
             }
         }
         WsRequest::Search { term } => {
-            let results = search::search_results(search::SearchRequest { term: term.clone() });
+            let results = search::search_results_in_scope(
+                search::SearchRequest { term: term.clone() },
+                scope.as_ref(),
+            );
             let response = WsResponse::SearchResults { term, results };
             if send_ws_response(&tx, response).await {
                 return true;
             }
         }
         WsRequest::ReloadLibreQoS => {
-            let message = reload_libreqos::reload_libreqos_with_login(*request_state.login).await;
+            let message = reload_libreqos::reload_libreqos_with_login(
+                request_state.access.login_for(Permission::Reload),
+                &request_state.audit_actor(),
+            )
+            .await;
             let response = WsResponse::ReloadResult { message };
             if send_ws_response(&tx, response).await {
                 return true;
             }
         }
-        WsRequest::LtsTrialConfig => match lts::lts_trial_config_data(*request_state.login) {
+        WsRequest::LtsTrialConfig => match lts::lts_trial_config_data(request_state.access.login())
+        {
             Ok(data) => {
                 let response = WsResponse::LtsTrialConfigResult { data };
                 if send_ws_response(&tx, response).await {
@@ -1402,13 +1520,13 @@ async fn receive_channel_message(
         }
         WsRequest::AdminCheck => {
             let response = WsResponse::AdminCheck {
-                ok: config::admin_check_data(*request_state.login),
+                ok: config::admin_check_data(request_state.access.login()),
             };
             if send_ws_response(&tx, response).await {
                 return true;
             }
         }
-        WsRequest::GetConfig => match config::get_config_data(*request_state.login) {
+        WsRequest::GetConfig => match config::get_config_data(request_state.access.login()) {
             Ok(data) => {
                 let response = WsResponse::GetConfig { data };
                 if send_ws_response(&tx, response).await {
@@ -1433,7 +1551,7 @@ async fn receive_channel_message(
             }
         },
         WsRequest::QooProfiles => {
-            if *request_state.login != crate::node_manager::auth::LoginResult::Admin {
+            if request_state.access.login() != LoginResult::Admin {
                 let response = WsResponse::Error {
                     message: "Unauthorized".to_string(),
                 };
@@ -1473,7 +1591,11 @@ async fn receive_channel_message(
             }
         }
         WsRequest::UpdateConfig { config: cfg } => {
-            let result = config::update_lqosd_config_data(*request_state.login, cfg).await;
+            let result = config::update_lqosd_config_data(
+                request_state.access.login(),
+                cfg,
+                &request_state.audit_actor(),
+            );
             let (ok, message) = match result {
                 Ok(()) => (true, "Ok".to_string()),
                 Err(StatusCode::FORBIDDEN) => (false, "Unauthorized".to_string()),
@@ -1485,7 +1607,11 @@ async fn receive_channel_message(
             }
         }
         WsRequest::UpdateNetworkJsonOnly { network_json } => {
-            let result = config::update_network_json_only_data(*request_state.login, network_json);
+            let result =
+                config::update_network_json_only_data(request_state.access.login(), network_json);
+            AuditEvent::new("update_network_json")
+                .target("network.json")
+                .record_result(&request_state.audit_actor(), &result);
             let (ok, message) = match result {
                 Ok(()) => (true, "Ok".to_string()),
                 Err(message) => (false, message),
@@ -1499,11 +1625,16 @@ async fn receive_channel_message(
             network_json,
             shaped_devices,
         } => {
+            let device_count = shaped_devices.len();
             let result = config::update_network_and_devices_data(
-                *request_state.login,
+                request_state.access.login(),
                 network_json,
                 shaped_devices,
             );
+            AuditEvent::new("update_network_and_devices")
+                .target("network.json, ShapedDevices.csv")
+                .after(&format!("{device_count} shaped devices"))
+                .record_result(&request_state.audit_actor(), &result);
             let (ok, message) = match result {
                 Ok(()) => (true, "Ok".to_string()),
                 Err(message) => (false, message),
@@ -1514,7 +1645,10 @@ async fn receive_channel_message(
             }
         }
         WsRequest::GetNodeRateOverride { query } => {
-            match node_rate_overrides::get_node_rate_override_data(*request_state.login, query) {
+            match node_rate_overrides::get_node_rate_override_data(
+                request_state.access.login_for(Permission::AdjustSpeeds),
+                query,
+            ) {
                 Ok(data) => {
                     let response = WsResponse::GetNodeRateOverride { data };
                     if send_ws_response(&tx, response).await {
@@ -1548,8 +1682,21 @@ async fn receive_channel_message(
             }
         }
         WsRequest::SetNodeRateOverride { update } => {
-            let result =
-                node_rate_overrides::set_node_rate_override_data(*request_state.login, update);
+            let login = request_state.access.login_for(Permission::AdjustSpeeds);
+            let event = AuditEvent::new("set_node_rate_override")
+                .target(update.node_name.as_str())
+                .before(&node_rate_override_summary(
+                    login,
+                    &update.node_id,
+                    &update.node_name,
+                ))
+                .after(&(update.download_bandwidth_mbps, update.upload_bandwidth_mbps));
+            let result = node_rate_overrides::set_node_rate_override_data(
+                login,
+                request_state.access.username(),
+                update,
+            );
+            event.record_result(&request_state.audit_actor(), &result);
             match result {
                 Ok(data) => {
                     let response = WsResponse::SetNodeRateOverrideResult {
@@ -1614,8 +1761,20 @@ async fn receive_channel_message(
             }
         }
         WsRequest::ClearNodeRateOverride { query } => {
-            let result =
-                node_rate_overrides::clear_node_rate_override_data(*request_state.login, query);
+            let login = request_state.access.login_for(Permission::AdjustSpeeds);
+            let event = AuditEvent::new("clear_node_rate_override")
+                .target(query.node_name.as_str())
+                .before(&node_rate_override_summary(
+                    login,
+                    query.node_id.as_deref().unwrap_or_default(),
+                    &query.node_name,
+                ));
+            let result = node_rate_overrides::clear_node_rate_override_data(
+                login,
+                request_state.access.username(),
+                query,
+            );
+            event.record_result(&request_state.audit_actor(), &result);
             match result {
                 Ok(data) => {
                     let response = WsResponse::ClearNodeRateOverrideResult {
@@ -1653,7 +1812,174 @@ async fn receive_channel_message(
                 }
             }
         }
-        WsRequest::ListNics => match config::list_nics_data(*request_state.login) {
+        WsRequest::GetPendingChanges => {
+            match pending_changes::get_pending_changes_data(
+                request_state.access.login_for(Permission::ManageOverrides),
+            ) {
+                Ok(data) => {
+                    let response = WsResponse::GetPendingChanges { data };
+                    if send_ws_response(&tx, response).await {
+                        return true;
+                    }
+                }
+                Err(StatusCode::FORBIDDEN) => {
+                    let response = WsResponse::Error {
+                        message: "Unauthorized".to_string(),
+                    };
+                    if send_ws_response(&tx, response).await {
+                        return true;
+                    }
+                }
+                Err(_) => {
+                    let response = WsResponse::Error {
+                        message: "Unable to load pending changes".to_string(),
+                    };
+                    if send_ws_response(&tx, response).await {
+                        return true;
+                    }
+                }
+            }
+        }
+        WsRequest::ApprovePendingChange { id } => {
+            let result = pending_changes::approve_pending_change_data(
+                request_state.access.login_for(Permission::ManageOverrides),
+                id,
+            );
+            let (ok, message) = match result {
+                Ok(message) => (true, message),
+                Err(StatusCode::FORBIDDEN) => (false, "Unauthorized".to_string()),
+                Err(_) => (false, format!("Pending change {id} was not found")),
+            };
+            AuditEvent::new("approve_pending_change")
+                .target(id.to_string())
+                .record(&request_state.audit_actor(), ok, message.as_str());
+            let response = WsResponse::PendingChangeResult { ok, message };
+            if send_ws_response(&tx, response).await {
+                return true;
+            }
+        }
+        WsRequest::RejectPendingChange { id } => {
+            let result = pending_changes::reject_pending_change_data(
+                request_state.access.login_for(Permission::ManageOverrides),
+                id,
+            );
+            let (ok, message) = match result {
+                Ok(message) => (true, message),
+                Err(StatusCode::FORBIDDEN) => (false, "Unauthorized".to_string()),
+                Err(_) => (false, format!("Pending change {id} was not found")),
+            };
+            AuditEvent::new("reject_pending_change")
+                .target(id.to_string())
+                .record(&request_state.audit_actor(), ok, message.as_str());
+            let response = WsResponse::PendingChangeResult { ok, message };
+            if send_ws_response(&tx, response).await {
+                return true;
+            }
+        }
+        WsRequest::GetOverrideHistory { layer } => {
+            let response =
+                match override_history::override_history_data(request_state.access.login(), &layer)
+                {
+                    Ok(data) => WsResponse::OverrideHistory { data },
+                    Err(message) => WsResponse::Error { message },
+                };
+            if send_ws_response(&tx, response).await {
+                return true;
+            }
+        }
+        WsRequest::GetOverrideDiff {
+            layer,
+            from_version,
+            to_version,
+        } => {
+            let response = match override_history::override_diff_data(
+                request_state.access.login(),
+                &layer,
+                from_version,
+                to_version,
+            ) {
+                Ok(data) => WsResponse::OverrideDiff { data },
+                Err(message) => WsResponse::Error { message },
+            };
+            if send_ws_response(&tx, response).await {
+                return true;
+            }
+        }
+        WsRequest::RollbackOverrideLayer {
+            layer,
+            version,
+            reason,
+        } => {
+            let (ok, message) = match override_history::rollback_override_layer_data(
+                request_state.access.login_for(Permission::ManageOverrides),
+                request_state.access.username(),
+                &layer,
+                version,
+                &reason,
+            ) {
+                Ok(message) => (true, message),
+                Err(message) => (false, message),
+            };
+            AuditEvent::new("rollback_override_layer")
+                .target(format!("{layer} v{version}"))
+                .after(&reason)
+                .record(&request_state.audit_actor(), ok, message.as_str());
+            let response = WsResponse::RollbackOverrideResult { ok, message };
+            if send_ws_response(&tx, response).await {
+                return true;
+            }
+        }
+        WsRequest::AuditLog { query } => {
+            let response = match audit_log::audit_log_data(request_state.access.login(), query) {
+                Ok(data) => WsResponse::AuditLog { data },
+                Err(message) => WsResponse::Error { message },
+            };
+            if send_ws_response(&tx, response).await {
+                return true;
+            }
+        }
+        WsRequest::AuditLogExport { query } => {
+            let response =
+                match audit_log::audit_log_export_data(request_state.access.login(), query) {
+                    Ok(jsonl) => WsResponse::AuditLogExport { jsonl },
+                    Err(message) => WsResponse::Error { message },
+                };
+            if send_ws_response(&tx, response).await {
+                return true;
+            }
+        }
+        WsRequest::FederationOverview => {
+            let response = WsResponse::FederationOverview {
+                data: federation::federation_overview(),
+            };
+            if send_ws_response(&tx, response).await {
+                return true;
+            }
+        }
+        WsRequest::FederationTree => {
+            let response = WsResponse::FederationTree {
+                data: federation::federation_tree(),
+            };
+            if send_ws_response(&tx, response).await {
+                return true;
+            }
+        }
+        WsRequest::FederationCircuitSearch { search } => {
+            let response = match tokio::task::spawn_blocking(move || {
+                federation::federation_circuit_search(&search)
+            })
+            .await
+            {
+                Ok(data) => WsResponse::FederationCircuitSearch { data },
+                Err(_) => WsResponse::Error {
+                    message: "Federated circuit search failed".to_string(),
+                },
+            };
+            if send_ws_response(&tx, response).await {
+                return true;
+            }
+        }
+        WsRequest::ListNics => match config::list_nics_data(request_state.access.login()) {
             Ok(data) => {
                 let response = WsResponse::ListNics { data };
                 if send_ws_response(&tx, response).await {
@@ -1694,7 +2020,7 @@ async fn receive_channel_message(
             }
         }
         WsRequest::GetShapedDevice { device_id } => {
-            match config::get_shaped_device_data(*request_state.login, device_id) {
+            match config::get_shaped_device_data(request_state.access.login(), device_id) {
                 Ok(device) => {
                     let response = WsResponse::GetShapedDeviceResult {
                         ok: device.is_some(),
@@ -1732,7 +2058,12 @@ async fn receive_channel_message(
             }
         }
         WsRequest::CreateShapedDevice { device } => {
-            match config::create_shaped_device_data(*request_state.login, device) {
+            let event = AuditEvent::new("create_shaped_device")
+                .target(device.device_id.as_str())
+                .after(&device);
+            let result = config::create_shaped_device_data(request_state.access.login(), device);
+            event.record_result(&request_state.audit_actor(), &result);
+            match result {
                 Ok(device) => {
                     let response = WsResponse::CreateShapedDeviceResult {
                         ok: true,
@@ -1758,35 +2089,45 @@ async fn receive_channel_message(
         WsRequest::UpdateShapedDevice {
             original_device_id,
             device,
-        } => match config::update_shaped_device_data(
-            *request_state.login,
-            original_device_id,
-            device,
-        ) {
-            Ok(device) => {
-                let response = WsResponse::UpdateShapedDeviceResult {
-                    ok: true,
-                    message: "Ok".to_string(),
-                    device: Some(device),
-                };
-                if send_ws_response(&tx, response).await {
-                    return true;
+        } => {
+            let event =
+                config::shaped_device_audit_event("update_shaped_device", &original_device_id)
+                    .after(&device);
+            let result = config::update_shaped_device_data(
+                request_state.access.login(),
+                original_device_id,
+                device,
+            );
+            event.record_result(&request_state.audit_actor(), &result);
+            match result {
+                Ok(device) => {
+                    let response = WsResponse::UpdateShapedDeviceResult {
+                        ok: true,
+                        message: "Ok".to_string(),
+                        device: Some(device),
+                    };
+                    if send_ws_response(&tx, response).await {
+                        return true;
+                    }
                 }
-            }
-            Err(message) => {
-                let response = WsResponse::UpdateShapedDeviceResult {
-                    ok: false,
-                    message,
-                    device: None,
-                };
-                if send_ws_response(&tx, response).await {
-                    return true;
+                Err(message) => {
+                    let response = WsResponse::UpdateShapedDeviceResult {
+                        ok: false,
+                        message,
+                        device: None,
+                    };
+                    if send_ws_response(&tx, response).await {
+                        return true;
+                    }
                 }
             }
-        },
+        }
         WsRequest::DeleteShapedDevice { device_id } => {
             let device_id_clone = device_id.clone();
-            match config::delete_shaped_device_data(*request_state.login, device_id) {
+            let event = config::shaped_device_audit_event("delete_shaped_device", &device_id);
+            let result = config::delete_shaped_device_data(request_state.access.login(), device_id);
+            event.record_result(&request_state.audit_actor(), &result);
+            match result {
                 Ok(()) => {
                     let response = WsResponse::DeleteShapedDeviceResult {
                         ok: true,
@@ -1833,7 +2174,7 @@ async fn receive_channel_message(
                 return true;
             }
         }
-        WsRequest::GetUsers => match config::get_users_data(*request_state.login) {
+        WsRequest::GetUsers => match config::get_users_data(request_state.access) {
             Ok(data) => {
                 let response = WsResponse::GetUsers { data };
                 if send_ws_response(&tx, response).await {
@@ -1862,8 +2203,11 @@ async fn receive_channel_message(
             password,
             role,
         } => {
+            let event = AuditEvent::new("add_user")
+                .target(username.as_str())
+                .after(&role);
             let result = config::add_user_data(
-                *request_state.login,
+                request_state.access,
                 config::UserRequest {
                     username,
                     password,
@@ -1879,6 +2223,7 @@ async fn receive_channel_message(
             if ok {
                 crate::node_manager::auth::refresh_cached_users().await;
             }
+            event.record(&request_state.audit_actor(), ok, message.as_str());
             let response = WsResponse::AddUserResult { ok, message };
             if send_ws_response(&tx, response).await {
                 return true;
@@ -1889,8 +2234,11 @@ async fn receive_channel_message(
             password,
             role,
         } => {
+            let event = AuditEvent::new("update_user")
+                .target(username.as_str())
+                .after(&role);
             let result = config::update_user_data(
-                *request_state.login,
+                request_state.access,
                 config::UserRequest {
                     username,
                     password,
@@ -1906,13 +2254,15 @@ async fn receive_channel_message(
             if ok {
                 crate::node_manager::auth::refresh_cached_users().await;
             }
+            event.record(&request_state.audit_actor(), ok, message.as_str());
             let response = WsResponse::UpdateUserResult { ok, message };
             if send_ws_response(&tx, response).await {
                 return true;
             }
         }
         WsRequest::DeleteUser { username } => {
-            let result = config::delete_user_data(*request_state.login, username);
+            let event = AuditEvent::new("delete_user").target(username.as_str());
+            let result = config::delete_user_data(request_state.access, username);
             let (ok, message) = match result {
                 Ok(message) => (true, message),
                 Err(StatusCode::FORBIDDEN) => (false, "Unauthorized".to_string()),
@@ -1922,6 +2272,7 @@ async fn receive_channel_message(
             if ok {
                 crate::node_manager::auth::refresh_cached_users().await;
             }
+            event.record(&request_state.audit_actor(), ok, message.as_str());
             let response = WsResponse::DeleteUserResult { ok, message };
             if send_ws_response(&tx, response).await {
                 return true;
@@ -1932,6 +2283,26 @@ async fn receive_channel_message(
     false
 }
 
+/// The stored override speeds of a node, as `(download, upload)` Mbps, for the audit log.
+fn node_rate_override_summary(
+    login: LoginResult,
+    node_id: &str,
+    node_name: &str,
+) -> Option<(Option<f32>, Option<f32>)> {
+    let query = node_rate_overrides::NodeRateOverrideQuery {
+        node_id: Some(node_id.to_string()).filter(|id| !id.is_empty()),
+        node_name: node_name.to_string(),
+    };
+    node_rate_overrides::get_node_rate_override_data(login, query)
+        .ok()
+        .map(|data| {
+            (
+                data.override_download_bandwidth_mbps,
+                data.override_upload_bandwidth_mbps,
+            )
+        })
+}
+
 fn is_benign_close_frame(frame: Option<&axum::extract::ws::CloseFrame<'_>>) -> bool {
     frame.is_some_and(|frame| matches!(frame.code, 1000 | 1001))
 }
@@ -1,5 +1,9 @@
 use crate::lts2_sys::control_channel::{SupportTicket, SupportTicketSummary};
 use crate::node_manager::WarningLevel;
+use crate::node_manager::federation::{
+    FederatedCircuitSearch, FederatedTreeNode, FederationOverview,
+};
+use crate::node_manager::local_api::audit_log::AuditLogQuery;
 use crate::node_manager::local_api::circuit::CircuitByIdData;
 use crate::node_manager::local_api::circuit_activity::{
     CircuitFlowSankeyRow, CircuitSummaryData, CircuitTopAsnsData, CircuitTopAsnsQuery,
@@ -20,6 +24,7 @@ use crate::node_manager::local_api::node_rate_overrides::{
     NodeRateOverrideData, NodeRateOverrideQuery, NodeRateOverrideUpdate,
 };
 use crate::node_manager::local_api::packet_analysis::RequestAnalysisResult;
+use crate::node_manager::local_api::pending_changes::PendingChangesData;
 use crate::node_manager::local_api::scheduler::{SchedulerDetails, SchedulerStatus};
 use crate::node_manager::local_api::search::SearchResult;
 use crate::node_manager::local_api::shaped_devices_page::{
@@ -50,7 +55,10 @@ use crate::throughput_tracker::TcpRetransmitTotal;
 use crate::throughput_tracker::flow_data::{
     AsnCountryListEntry, AsnListEntry, AsnProtocolListEntry,
 };
-use lqos_bus::{Circuit, FlowbeeSummaryData, QueueStoreTransit, StormguardDebugEntry};
+use lqos_bus::{
+    AuditLogEntry, Circuit, FlowbeeSummaryData, OverrideDiffLine, OverrideHistoryEntry,
+    QueueStoreTransit, StormguardDebugEntry,
+};
 use lqos_config::QooProfileInfo;
 use lqos_config::{Config, NetworkJsonTransport, ShapedDevice, WebUser};
 use lqos_utils::units::DownUpOrder;
@@ -190,6 +198,37 @@ pub enum WsRequest {
     ClearNodeRateOverride {
         query: NodeRateOverrideQuery,
     },
+    GetPendingChanges,
+    ApprovePendingChange {
+        id: u64,
+    },
+    RejectPendingChange {
+        id: u64,
+    },
+    GetOverrideHistory {
+        layer: String,
+    },
+    GetOverrideDiff {
+        layer: String,
+        from_version: u64,
+        to_version: Option<u64>,
+    },
+    RollbackOverrideLayer {
+        layer: String,
+        version: u64,
+        reason: String,
+    },
+    AuditLog {
+        query: AuditLogQuery,
+    },
+    AuditLogExport {
+        query: AuditLogQuery,
+    },
+    FederationOverview,
+    FederationTree,
+    FederationCircuitSearch {
+        search: String,
+    },
     ListNics,
     NetworkJson,
     AllShapedDevices,
@@ -468,6 +507,8 @@ pub struct TreeguardStatusData {
     pub paused_for_bakery_reload: bool,
     pub pause_reason: Option<String>,
     pub cpu_max_pct: Option<u8>,
+    pub softirq_spread_pct: Option<u8>,
+    pub sqm_experiments_active: usize,
     pub total_nodes: usize,
     pub total_circuits: usize,
     pub managed_nodes: usize,
@@ -673,6 +714,38 @@ pub enum WsResponse {
         message: String,
         data: NodeRateOverrideData,
     },
+    GetPendingChanges {
+        data: PendingChangesData,
+    },
+    PendingChangeResult {
+        ok: bool,
+        message: String,
+    },
+    OverrideHistory {
+        data: Vec<OverrideHistoryEntry>,
+    },
+    OverrideDiff {
+        data: Vec<OverrideDiffLine>,
+    },
+    RollbackOverrideResult {
+        ok: bool,
+        message: String,
+    },
+    AuditLog {
+        data: Vec<AuditLogEntry>,
+    },
+    AuditLogExport {
+        jsonl: String,
+    },
+    FederationOverview {
+        data: FederationOverview,
+    },
+    FederationTree {
+        data: Vec<FederatedTreeNode>,
+    },
+    FederationCircuitSearch {
+        data: FederatedCircuitSearch,
+    },
     GetUsers {
         data: Vec<WebUser>,
     },
@@ -0,0 +1,232 @@
+//! Delivers urgent issues to the sinks configured in `[notifications]`.
+//!
+//! `urgent::submit` hands every new or re-raised issue to [`notify`], which queues it for a
+//! background thread. That thread re-reads the configuration for each issue (so sink edits
+//! apply without a restart), applies each sink's severity and source filters, and sends at
+//! most one notification per sink and dedupe key every `min_interval_seconds`. Delivery
+//! failures are logged and otherwise ignored.
+
+mod email;
+mod syslog;
+mod webhook;
+
+use std::collections::HashMap;
+use std::sync::OnceLock;
+
+use crossbeam_channel::Sender;
+use lqos_bus::{UrgentIssue, UrgentSeverity, UrgentSource};
+use lqos_config::{Config, NotificationSeverity, NotificationSink, NotificationTarget};
+use lqos_utils::unix_time::unix_now;
+use tracing::{debug, info, warn};
+
+static NOTIFICATION_SENDER: OnceLock<Sender<UrgentIssue>> = OnceLock::new();
+
+/// Rate-limiter entries older than this are forgotten once the table grows large.
+const RATE_LIMIT_RETENTION_SECONDS: u64 = 24 * 60 * 60;
+const RATE_LIMIT_PRUNE_THRESHOLD: usize = 4096;
+
+/// Starts the notification delivery thread.
+pub fn start_notifications() {
+    let (tx, rx) = crossbeam_channel::bounded::<UrgentIssue>(1024);
+    if NOTIFICATION_SENDER.set(tx).is_err() {
+        warn!("Notification delivery already started");
+        return;
+    }
+    let spawned = std::thread::Builder::new()
+        .name("notifications".to_string())
+        .spawn(move || {
+            let mut limiter = RateLimiter::default();
+            while let Ok(issue) = rx.recv() {
+                deliver(&issue, &mut limiter);
+            }
+        });
+    if let Err(e) = spawned {
+        warn!("Unable to start notification delivery thread: {e}");
+    }
+}
+
+/// Queues an urgent issue for delivery to the configured sinks.
+pub fn notify(issue: &UrgentIssue) {
+    if let Some(tx) = NOTIFICATION_SENDER.get()
+        && tx.try_send(issue.clone()).is_err()
+    {
+        warn!("Notification queue is full; dropping {}", issue.code);
+    }
+}
+
+fn deliver(issue: &UrgentIssue, limiter: &mut RateLimiter) {
+    let Ok(config) = lqos_config::load_config() else {
+        warn!("Unable to load config; not sending notifications");
+        return;
+    };
+    let now = unix_now().unwrap_or_default();
+    let key = issue.dedupe_key.as_deref().unwrap_or(&issue.code);
+    for sink in config.notifications.sinks.iter() {
+        if !sink_accepts(sink, issue) || !limiter.allow(sink, key, now) {
+            continue;
+        }
+        match send(&config, sink, issue) {
+            Ok(()) => debug!("Sent {} to notification sink {}", issue.code, sink.name),
+            Err(e) => warn!("Notification sink {} failed: {e}", sink.name),
+        }
+    }
+}
+
+fn send(config: &Config, sink: &NotificationSink, issue: &UrgentIssue) -> Result<(), String> {
+    match &sink.target {
+        NotificationTarget::Webhook { url } => {
+            webhook::post(url, &webhook::generic_payload(config, issue))
+        }
+        NotificationTarget::Slack { url } => {
+            webhook::post(url, &webhook::slack_payload(config, issue))
+        }
+        NotificationTarget::Teams { url } => {
+            webhook::post(url, &webhook::teams_payload(config, issue))
+        }
+        NotificationTarget::Matrix { url } => {
+            webhook::post(url, &webhook::matrix_payload(config, issue))
+        }
+        NotificationTarget::Email { .. } => email::send(config, &sink.target, issue),
+        NotificationTarget::Syslog {
+            address,
+            protocol,
+            facility,
+        } => syslog::send(config, address, *protocol, *facility, issue),
+    }
+}
+
+/// Whether a sink is enabled and its severity and source filters let `issue` through.
+fn sink_accepts(sink: &NotificationSink, issue: &UrgentIssue) -> bool {
+    let severity = match issue.severity {
+        UrgentSeverity::Error => NotificationSeverity::Error,
+        UrgentSeverity::Warning => NotificationSeverity::Warning,
+    };
+    let source = source_name(issue.source);
+    sink.enabled
+        && severity >= sink.min_severity
+        && (sink.sources.is_empty() || sink.sources.iter().any(|s| s.eq_ignore_ascii_case(source)))
+}
+
+/// Name of an urgent-issue source as used in sink filters and messages.
+pub fn source_name(source: UrgentSource) -> &'static str {
+    match source {
+        UrgentSource::Scheduler => "scheduler",
+        UrgentSource::LibreQoS => "libreqos",
+        UrgentSource::API => "api",
+        UrgentSource::System => "system",
+    }
+}
+
+/// One-line summary shared by the chat, email and syslog sinks.
+fn summary_line(config: &Config, issue: &UrgentIssue) -> String {
+    let severity = match issue.severity {
+        UrgentSeverity::Error => "ERROR",
+        UrgentSeverity::Warning => "WARNING",
+    };
+    format!(
+        "[LibreQoS {}] {severity} {}: {}",
+        config.node_name, issue.code, issue.message
+    )
+}
+
+/// Remembers when each (sink, dedupe key) pair was last notified.
+#[derive(Default)]
+struct RateLimiter {
+    last_sent: HashMap<(String, String), u64>,
+}
+
+impl RateLimiter {
+    fn allow(&mut self, sink: &NotificationSink, key: &str, now: u64) -> bool {
+        let entry = (sink.name.clone(), key.to_string());
+        if let Some(last) = self.last_sent.get(&entry)
+            && now.saturating_sub(*last) < sink.min_interval_seconds
+        {
+            return false;
+        }
+        if self.last_sent.len() >= RATE_LIMIT_PRUNE_THRESHOLD {
+            self.last_sent
+                .retain(|_, last| now.saturating_sub(*last) < RATE_LIMIT_RETENTION_SECONDS);
+            info!(
+                "Pruned notification rate limiter to {} entries",
+                self.last_sent.len()
+            );
+        }
+        self.last_sent.insert(entry, now);
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn issue(source: UrgentSource, severity: UrgentSeverity) -> UrgentIssue {
+        UrgentIssue {
+            id: 1,
+            ts: 1_700_000_000,
+            source,
+            severity,
+            code: "TC_U16_OVERFLOW".to_string(),
+            message: "Too many queues".to_string(),
+            context: None,
+            dedupe_key: Some("TC_U16_OVERFLOW".to_string()),
+        }
+    }
+
+    fn sink(min_severity: NotificationSeverity, sources: &[&str]) -> NotificationSink {
+        NotificationSink {
+            name: "noc".to_string(),
+            enabled: true,
+            min_severity,
+            sources: sources.iter().map(|s| s.to_string()).collect(),
+            min_interval_seconds: 600,
+            target: NotificationTarget::Webhook {
+                url: "https://example.com/hook".to_string(),
+            },
+        }
+    }
+
+    #[test]
+    fn filters_by_severity_and_source() {
+        let errors_only = sink(NotificationSeverity::Error, &[]);
+        assert!(sink_accepts(
+            &errors_only,
+            &issue(UrgentSource::System, UrgentSeverity::Error)
+        ));
+        assert!(!sink_accepts(
+            &errors_only,
+            &issue(UrgentSource::System, UrgentSeverity::Warning)
+        ));
+
+        let scheduler = sink(NotificationSeverity::Warning, &["Scheduler"]);
+        assert!(sink_accepts(
+            &scheduler,
+            &issue(UrgentSource::Scheduler, UrgentSeverity::Warning)
+        ));
+        assert!(!sink_accepts(
+            &scheduler,
+            &issue(UrgentSource::API, UrgentSeverity::Error)
+        ));
+
+        let mut disabled = sink(NotificationSeverity::Warning, &[]);
+        disabled.enabled = false;
+        assert!(!sink_accepts(
+            &disabled,
+            &issue(UrgentSource::System, UrgentSeverity::Error)
+        ));
+    }
+
+    #[test]
+    fn rate_limits_per_sink_and_dedupe_key() {
+        let mut limiter = RateLimiter::default();
+        let noc = sink(NotificationSeverity::Warning, &[]);
+        let mut pager = noc.clone();
+        pager.name = "pager".to_string();
+
+        assert!(limiter.allow(&noc, "a", 1000));
+        assert!(!limiter.allow(&noc, "a", 1599));
+        assert!(limiter.allow(&noc, "b", 1599));
+        assert!(limiter.allow(&pager, "a", 1599));
+        assert!(limiter.allow(&noc, "a", 1600));
+    }
+}
@@ -0,0 +1,88 @@
+//! Email through an SMTP relay.
+
+use std::time::Duration;
+
+use lettre::message::Mailbox;
+use lettre::message::header::ContentType;
+use lettre::transport::smtp::authentication::Credentials;
+use lettre::{Message, SmtpTransport, Transport};
+use lqos_bus::UrgentIssue;
+use lqos_config::{Config, NotificationTarget, SmtpSecurity};
+
+use super::{source_name, summary_line};
+
+const TIMEOUT: Duration = Duration::from_secs(20);
+
+/// Sends `issue` to every recipient of an email sink.
+pub fn send(
+    config: &Config,
+    target: &NotificationTarget,
+    issue: &UrgentIssue,
+) -> Result<(), String> {
+    let NotificationTarget::Email {
+        smtp_server,
+        smtp_port,
+        security,
+        username,
+        password,
+        from,
+        to,
+    } = target
+    else {
+        return Err("Not an email sink".to_string());
+    };
+
+    let mut message = Message::builder()
+        .from(parse_mailbox(from)?)
+        .subject(summary_line(config, issue))
+        .header(ContentType::TEXT_PLAIN);
+    for recipient in to {
+        message = message.to(parse_mailbox(recipient)?);
+    }
+    let message = message
+        .body(body(config, issue))
+        .map_err(|e| e.to_string())?;
+
+    let mut transport = match security {
+        SmtpSecurity::Starttls => {
+            SmtpTransport::starttls_relay(smtp_server).map_err(|e| e.to_string())?
+        }
+        SmtpSecurity::Tls => SmtpTransport::relay(smtp_server).map_err(|e| e.to_string())?,
+        SmtpSecurity::None => SmtpTransport::builder_dangerous(smtp_server),
+    }
+    .port(*smtp_port)
+    .timeout(Some(TIMEOUT));
+    if !username.is_empty() {
+        transport = transport.credentials(Credentials::new(username.clone(), password.clone()));
+    }
+    transport
+        .build()
+        .send(&message)
+        .map(|_| ())
+        .map_err(|e| e.to_string())
+}
+
+fn parse_mailbox(address: &str) -> Result<Mailbox, String> {
+    address
+        .parse()
+        .map_err(|e| format!("Invalid address '{address}': {e}"))
+}
+
+fn body(config: &Config, issue: &UrgentIssue) -> String {
+    let mut body = format!(
+        "{}\n\nNode: {} ({})\nSource: {}\nSeverity: {:?}\nCode: {}\nTime (Unix): {}\n",
+        issue.message,
+        config.node_name,
+        config.node_id,
+        source_name(issue.source),
+        issue.severity,
+        issue.code,
+        issue.ts,
+    );
+    if let Some(context) = &issue.context {
+        body.push_str("\nContext:\n");
+        body.push_str(context);
+        body.push('\n');
+    }
+    body
+}
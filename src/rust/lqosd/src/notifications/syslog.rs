@@ -0,0 +1,129 @@
+//! RFC 5424 syslog over UDP, or TCP with RFC 6587 octet-counted framing.
+
+use std::io::Write;
+use std::net::{TcpStream, ToSocketAddrs, UdpSocket};
+use std::time::Duration;
+
+use chrono::{DateTime, SecondsFormat, Utc};
+use lqos_bus::{UrgentIssue, UrgentSeverity};
+use lqos_config::{Config, SyslogProtocol};
+
+use super::summary_line;
+
+const TIMEOUT: Duration = Duration::from_secs(5);
+const APP_NAME: &str = "lqosd";
+
+/// Sends `issue` to the collector at `address`.
+pub fn send(
+    config: &Config,
+    address: &str,
+    protocol: SyslogProtocol,
+    facility: u8,
+    issue: &UrgentIssue,
+) -> Result<(), String> {
+    let message = format_message(
+        facility,
+        issue,
+        &local_hostname(),
+        std::process::id(),
+        &summary_line(config, issue),
+    );
+    let target = address
+        .to_socket_addrs()
+        .map_err(|e| e.to_string())?
+        .next()
+        .ok_or_else(|| format!("Unable to resolve {address}"))?;
+    match protocol {
+        SyslogProtocol::Udp => {
+            let bind = if target.is_ipv4() {
+                "0.0.0.0:0"
+            } else {
+                "[::]:0"
+            };
+            let socket = UdpSocket::bind(bind).map_err(|e| e.to_string())?;
+            socket
+                .send_to(message.as_bytes(), target)
+                .map_err(|e| e.to_string())?;
+        }
+        SyslogProtocol::Tcp => {
+            let mut stream =
+                TcpStream::connect_timeout(&target, TIMEOUT).map_err(|e| e.to_string())?;
+            stream
+                .set_write_timeout(Some(TIMEOUT))
+                .map_err(|e| e.to_string())?;
+            let framed = format!("{} {message}", message.len());
+            stream
+                .write_all(framed.as_bytes())
+                .map_err(|e| e.to_string())?;
+        }
+    }
+    Ok(())
+}
+
+/// Builds an RFC 5424 message. The issue code is the MSGID, so collectors can filter on it.
+fn format_message(
+    facility: u8,
+    issue: &UrgentIssue,
+    hostname: &str,
+    pid: u32,
+    text: &str,
+) -> String {
+    let severity = match issue.severity {
+        UrgentSeverity::Error => 3,
+        UrgentSeverity::Warning => 4,
+    };
+    let priority = u16::from(facility) * 8 + severity;
+    let timestamp = DateTime::<Utc>::from_timestamp(issue.ts as i64, 0)
+        .map(|t| t.to_rfc3339_opts(SecondsFormat::Secs, true))
+        .unwrap_or_else(|| "-".to_string());
+    format!(
+        "<{priority}>1 {timestamp} {} {APP_NAME} {pid} {} - {text}",
+        header_field(hostname, 255),
+        header_field(&issue.code, 32),
+    )
+}
+
+/// RFC 5424 header fields are printable ASCII without spaces; empty fields are `-`.
+fn header_field(value: &str, max_len: usize) -> String {
+    let field: String = value
+        .chars()
+        .filter(|c| c.is_ascii_graphic())
+        .take(max_len)
+        .collect();
+    if field.is_empty() {
+        "-".to_string()
+    } else {
+        field
+    }
+}
+
+fn local_hostname() -> String {
+    std::fs::read_to_string("/proc/sys/kernel/hostname")
+        .map(|name| name.trim().to_string())
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use lqos_bus::UrgentSource;
+
+    #[test]
+    fn formats_rfc5424_header() {
+        let issue = UrgentIssue {
+            id: 7,
+            ts: 1_700_000_000,
+            source: UrgentSource::Scheduler,
+            severity: UrgentSeverity::Error,
+            code: "TC_U16_OVERFLOW".to_string(),
+            message: "Too many queues".to_string(),
+            context: None,
+            dedupe_key: None,
+        };
+        let message = format_message(16, &issue, "shaper 1", 4242, "Too many queues");
+        assert_eq!(
+            message,
+            "<131>1 2023-11-14T22:13:20Z shaper1 lqosd 4242 TC_U16_OVERFLOW - Too many queues"
+        );
+    }
+}
@@ -0,0 +1,88 @@
+//! JSON webhooks: generic, Slack, Microsoft Teams and Matrix (hookshot) payloads.
+
+use std::time::Duration;
+
+use lqos_bus::{UrgentIssue, UrgentSeverity};
+use lqos_config::Config;
+use serde_json::{Value, json};
+
+use super::{source_name, summary_line};
+
+const TIMEOUT: Duration = Duration::from_secs(10);
+
+/// POSTs `payload` to `url`, treating any non-2xx status as a failure.
+pub fn post(url: &str, payload: &Value) -> Result<(), String> {
+    let client = reqwest::blocking::Client::builder()
+        .timeout(TIMEOUT)
+        .build()
+        .map_err(|e| e.to_string())?;
+    let response = client
+        .post(url)
+        .json(payload)
+        .send()
+        .map_err(|e| e.to_string())?;
+    if response.status().is_success() {
+        Ok(())
+    } else {
+        Err(format!("HTTP {}", response.status()))
+    }
+}
+
+/// The issue as-is, plus the identity of the shaper that raised it.
+pub fn generic_payload(config: &Config, issue: &UrgentIssue) -> Value {
+    json!({
+        "node_id": config.node_id,
+        "node_name": config.node_name,
+        "source": source_name(issue.source),
+        "issue": issue,
+    })
+}
+
+/// Slack-compatible incoming webhook (also accepted by Mattermost and Rocket.Chat).
+pub fn slack_payload(config: &Config, issue: &UrgentIssue) -> Value {
+    json!({ "text": summary_line(config, issue) })
+}
+
+/// Microsoft Teams incoming webhook (legacy MessageCard format).
+pub fn teams_payload(config: &Config, issue: &UrgentIssue) -> Value {
+    let colour = match issue.severity {
+        UrgentSeverity::Error => "D13438",
+        UrgentSeverity::Warning => "F2C744",
+    };
+    json!({
+        "@type": "MessageCard",
+        "@context": "https://schema.org/extensions",
+        "summary": summary_line(config, issue),
+        "themeColor": colour,
+        "title": format!("LibreQoS {}: {}", config.node_name, issue.code),
+        "text": issue.message,
+        "sections": [{
+            "facts": [
+                { "name": "Severity", "value": format!("{:?}", issue.severity) },
+                { "name": "Source", "value": source_name(issue.source) },
+                { "name": "Node", "value": config.node_id },
+            ]
+        }],
+    })
+}
+
+/// Matrix hookshot generic webhook: plain text with an HTML rendering.
+pub fn matrix_payload(config: &Config, issue: &UrgentIssue) -> Value {
+    json!({
+        "text": summary_line(config, issue),
+        "html": format!(
+            "<b>LibreQoS {}</b> {:?} <code>{}</code>: {}",
+            escape_html(&config.node_name),
+            issue.severity,
+            escape_html(&issue.code),
+            escape_html(&issue.message)
+        ),
+    })
+}
+
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
@@ -1,4 +1,8 @@
+use crate::audit::{self, AuditActor, AuditEvent};
+use crate::stick;
 use lqos_bus::BusResponse;
+use lqos_config::Config;
+use tracing::error;
 
 pub fn reload_libre_qos() -> BusResponse {
     let result = lqos_config::load_libreqos();
@@ -7,3 +11,31 @@ pub fn reload_libre_qos() -> BusResponse {
         Err(..) => BusResponse::Fail("Unable to reload LibreQoS".to_string()),
     }
 }
+
+/// Stores and writes a new `/etc/lqos.conf`, refreshes state derived from it, and records
+/// which sections changed (not their values, which may hold secrets) in the audit log.
+pub fn update_lqosd_config(config: &Config, actor: &AuditActor) -> Result<(), String> {
+    let previous = lqos_config::load_config().ok();
+    let result = lqos_config::update_config(config).map_err(|e| {
+        error!("Error updating config: {:?}", e);
+        e.to_string()
+    });
+    if result.is_ok()
+        && let Ok(cfg) = lqos_config::load_config()
+    {
+        let _ = stick::recompute_stick_offset(&cfg);
+    }
+
+    let changed = previous
+        .map(|previous| audit::changed_sections(previous.as_ref(), config))
+        .unwrap_or_default();
+    let message = match &result {
+        Ok(()) => "Ok".to_string(),
+        Err(e) => e.clone(),
+    };
+    AuditEvent::new("update_config")
+        .target("/etc/lqos.conf")
+        .after(&changed)
+        .record(actor, result.is_ok(), message);
+    result
+}
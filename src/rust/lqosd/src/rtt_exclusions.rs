@@ -1,6 +1,6 @@
 use arc_swap::ArcSwap;
 use fxhash::FxHashSet;
-use lqos_overrides::OverrideFile;
+use lqos_overrides::{ChangeAuthor, OverrideFile, SaveContext};
 use lqos_utils::hash_to_i64;
 use once_cell::sync::Lazy;
 use std::sync::Arc;
@@ -38,12 +38,28 @@ pub fn is_excluded_circuit_id(circuit_id: &str) -> bool {
     is_excluded_hash(hash_to_i64(circuit_id))
 }
 
+/// The circuit IDs currently excluded, as listed in `lqos_overrides.json`.
+pub fn excluded_circuit_ids() -> anyhow::Result<Vec<String>> {
+    Ok(OverrideFile::load()?.rtt_excluded_circuits().to_vec())
+}
+
 /// Add/remove a circuit from the RTT exclusion list. Returns true if changed.
-pub fn set_excluded_circuit_id(circuit_id: &str, excluded: bool) -> anyhow::Result<bool> {
+/// `author` names who made the change in the overrides history.
+pub fn set_excluded_circuit_id(
+    circuit_id: &str,
+    excluded: bool,
+    author: &str,
+) -> anyhow::Result<bool> {
     let mut of = OverrideFile::load()?;
     let changed = of.set_circuit_rtt_excluded_return_changed(circuit_id, excluded);
     if changed {
-        of.save()?;
+        let verb = if excluded { "Exclude" } else { "Include" };
+        of.save(&SaveContext::new(
+            ChangeAuthor::User {
+                name: author.to_string(),
+            },
+            format!("{verb} circuit '{circuit_id}' in RTT aggregation"),
+        ))?;
     }
     store_from_override_file(&of);
     Ok(changed)
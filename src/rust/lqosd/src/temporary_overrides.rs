@@ -0,0 +1,346 @@
+//! Circuit speed overrides that remove themselves.
+//!
+//! `SetTemporaryCircuitSpeed` appends a `CircuitAdjustSpeed` entry to the operator overrides
+//! layer and records its expiry in `<lqos_directory>/temporary_overrides.json`. A background
+//! thread removes the entry again once it expires. Both changes reload LibreQoS, so the cap
+//! starts and lifts straight away rather than at the next scheduler refresh.
+
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use lqos_overrides::{ChangeAuthor, CircuitAdjustment, OverrideFile, SaveContext};
+use lqos_utils::unix_time::unix_now;
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use tracing::{info, warn};
+
+use crate::shaped_devices_tracker::SHAPED_DEVICES;
+
+const PERSIST_FILE: &str = "temporary_overrides.json";
+const CHECK_INTERVAL: Duration = Duration::from_secs(30);
+/// Longest override accepted: one week.
+const MAX_MINUTES: u32 = 7 * 24 * 60;
+const AUTHOR: &str = "bus";
+
+/// Serializes changes to the overrides file and the expiry list.
+static LOCK: Mutex<()> = Mutex::new(());
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+struct TemporaryOverride {
+    circuit_id: String,
+    download_mbps: f32,
+    upload_mbps: f32,
+    expires_unix: u64,
+}
+
+/// Starts the thread that removes expired overrides.
+pub fn start_expiry() {
+    let spawned = std::thread::Builder::new()
+        .name("temporary_overrides".to_string())
+        .spawn(|| {
+            loop {
+                if let Err(e) = expire_due(unix_now().unwrap_or_default()) {
+                    warn!("Unable to expire temporary speed overrides: {e}");
+                }
+                std::thread::sleep(CHECK_INTERVAL);
+            }
+        });
+    if let Err(e) = spawned {
+        warn!("Unable to start temporary override thread: {e}");
+    }
+}
+
+/// Where overrides are stored and how the shaper picks them up; replaced in tests.
+trait Backend {
+    fn load(&self) -> Result<OverrideFile, String>;
+    fn save(&self, overrides: &OverrideFile, reason: String) -> Result<(), String>;
+    /// Rebuilds the queues from the overrides. The scheduler only reads them on its next
+    /// refresh, so without this a cap would neither start nor lift until then.
+    fn reload(&self) -> Result<(), String>;
+}
+
+/// The operator overrides layer and a LibreQoS reload.
+struct Shaper;
+
+impl Backend for Shaper {
+    fn load(&self) -> Result<OverrideFile, String> {
+        OverrideFile::load().map_err(|e| e.to_string())
+    }
+
+    fn save(&self, overrides: &OverrideFile, reason: String) -> Result<(), String> {
+        overrides
+            .save(&SaveContext::new(
+                ChangeAuthor::User {
+                    name: AUTHOR.to_string(),
+                },
+                reason,
+            ))
+            .map_err(|e| e.to_string())
+    }
+
+    fn reload(&self) -> Result<(), String> {
+        lqos_config::load_libreqos()
+            .map(|_| ())
+            .map_err(|e| e.to_string())
+    }
+}
+
+/// Caps a circuit at `download_mbps`/`upload_mbps` for `minutes`, reloading LibreQoS so the
+/// cap is live when this returns. A second override of the same circuit replaces the first.
+pub fn set_circuit_speed(
+    circuit_id: &str,
+    download_mbps: f32,
+    upload_mbps: f32,
+    minutes: u32,
+) -> Result<(), String> {
+    let circuit_id = circuit_id.trim();
+    if circuit_id.is_empty() {
+        return Err("A circuit ID is required".to_string());
+    }
+    for rate in [download_mbps, upload_mbps] {
+        if !rate.is_finite() || rate <= 0.0 {
+            return Err(format!("{rate} is not a valid speed in Mbps"));
+        }
+    }
+    if minutes == 0 || minutes > MAX_MINUTES {
+        return Err(format!(
+            "Duration must be between 1 and {MAX_MINUTES} minutes"
+        ));
+    }
+
+    // Keep the committed rate at or below the new ceiling
+    let (min_down, min_up) = {
+        let shaped = SHAPED_DEVICES.load();
+        let Some(device) = shaped.devices.iter().find(|d| d.circuit_id == circuit_id) else {
+            return Err(format!(
+                "Circuit '{circuit_id}' is not in ShapedDevices.csv"
+            ));
+        };
+        (
+            device.download_min_mbps.min(download_mbps),
+            device.upload_min_mbps.min(upload_mbps),
+        )
+    };
+
+    let entry = TemporaryOverride {
+        circuit_id: circuit_id.to_string(),
+        download_mbps,
+        upload_mbps,
+        expires_unix: unix_now().unwrap_or_default() + u64::from(minutes) * 60,
+    };
+    set_in(&Shaper, &persist_path()?, entry, min_down, min_up, minutes)
+}
+
+fn set_in(
+    backend: &impl Backend,
+    path: &Path,
+    entry: TemporaryOverride,
+    min_down: f32,
+    min_up: f32,
+    minutes: u32,
+) -> Result<(), String> {
+    let _guard = LOCK.lock();
+    let mut pending = load_pending(path);
+    let mut overrides = backend.load()?;
+    if let Some(index) = pending
+        .iter()
+        .position(|p| p.circuit_id == entry.circuit_id)
+    {
+        remove_adjustment(&mut overrides, &pending.remove(index));
+    }
+    overrides.add_circuit_adjustment(CircuitAdjustment::CircuitAdjustSpeed {
+        circuit_id: entry.circuit_id.clone(),
+        min_download_bandwidth: Some(min_down),
+        max_download_bandwidth: Some(entry.download_mbps),
+        min_upload_bandwidth: Some(min_up),
+        max_upload_bandwidth: Some(entry.upload_mbps),
+    });
+    backend.save(
+        &overrides,
+        format!(
+            "Temporary speed override for circuit '{}': {}/{} Mbps for {minutes} minutes",
+            entry.circuit_id, entry.download_mbps, entry.upload_mbps
+        ),
+    )?;
+
+    // Track the expiry before reloading, so the override is removed even if the reload fails
+    pending.push(entry);
+    write_pending(path, &pending).map_err(|e| e.to_string())?;
+    backend.reload().map_err(|e| {
+        format!(
+            "The override was saved, but LibreQoS could not be reloaded ({e}); it takes effect on the next scheduler refresh"
+        )
+    })
+}
+
+/// Removes every override whose time is up.
+fn expire_due(now: u64) -> Result<(), String> {
+    expire_in(&Shaper, &persist_path()?, now)
+}
+
+fn expire_in(backend: &impl Backend, path: &Path, now: u64) -> Result<(), String> {
+    let _guard = LOCK.lock();
+    let (due, remaining): (Vec<_>, Vec<_>) = load_pending(path)
+        .into_iter()
+        .partition(|p| p.expires_unix <= now);
+    if due.is_empty() {
+        return Ok(());
+    }
+
+    let mut overrides = backend.load()?;
+    let removed: Vec<&str> = due
+        .iter()
+        .filter(|p| remove_adjustment(&mut overrides, p))
+        .map(|p| p.circuit_id.as_str())
+        .collect();
+    if removed.is_empty() {
+        return write_pending(path, &remaining).map_err(|e| e.to_string());
+    }
+    backend.save(
+        &overrides,
+        format!(
+            "Temporary speed override expired for circuit(s) {}",
+            removed.join(", ")
+        ),
+    )?;
+    info!("Expired temporary speed overrides: {}", removed.join(", "));
+    write_pending(path, &remaining).map_err(|e| e.to_string())?;
+    backend.reload()
+}
+
+/// Removes the adjustment added for `entry`, leaving any permanent adjustments of the same
+/// circuit in place. Returns false if the operator already removed it.
+fn remove_adjustment(overrides: &mut OverrideFile, entry: &TemporaryOverride) -> bool {
+    let index = overrides.circuit_adjustments().iter().rposition(|adj| {
+        matches!(
+            adj,
+            CircuitAdjustment::CircuitAdjustSpeed {
+                circuit_id,
+                max_download_bandwidth: Some(down),
+                max_upload_bandwidth: Some(up),
+                ..
+            } if *circuit_id == entry.circuit_id
+                && *down == entry.download_mbps
+                && *up == entry.upload_mbps
+        )
+    });
+    index.is_some_and(|index| overrides.remove_circuit_adjustment_by_index(index))
+}
+
+fn persist_path() -> Result<PathBuf, String> {
+    let config = lqos_config::load_config().map_err(|e| e.to_string())?;
+    Ok(Path::new(&config.lqos_directory).join(PERSIST_FILE))
+}
+
+fn load_pending(path: &Path) -> Vec<TemporaryOverride> {
+    let Ok(raw) = std::fs::read_to_string(path) else {
+        return Vec::new();
+    };
+    serde_json::from_str(&raw).unwrap_or_else(|e| {
+        warn!("Ignoring unreadable {path:?}: {e}");
+        Vec::new()
+    })
+}
+
+fn write_pending(path: &Path, pending: &[TemporaryOverride]) -> std::io::Result<()> {
+    let json = serde_json::to_vec(pending)?;
+    let tmp = path.with_extension("json.tmp");
+    std::fs::write(&tmp, json)?;
+    std::fs::rename(&tmp, path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::{Cell, RefCell};
+
+    fn speed(circuit_id: &str, down: f32, up: f32) -> CircuitAdjustment {
+        CircuitAdjustment::CircuitAdjustSpeed {
+            circuit_id: circuit_id.to_string(),
+            min_download_bandwidth: None,
+            max_download_bandwidth: Some(down),
+            min_upload_bandwidth: None,
+            max_upload_bandwidth: Some(up),
+        }
+    }
+
+    #[derive(Default)]
+    struct FakeShaper {
+        overrides: RefCell<OverrideFile>,
+        reloads: Cell<usize>,
+    }
+
+    impl Backend for FakeShaper {
+        fn load(&self) -> Result<OverrideFile, String> {
+            Ok(self.overrides.borrow().clone())
+        }
+
+        fn save(&self, overrides: &OverrideFile, _reason: String) -> Result<(), String> {
+            *self.overrides.borrow_mut() = overrides.clone();
+            Ok(())
+        }
+
+        fn reload(&self) -> Result<(), String> {
+            self.reloads.set(self.reloads.get() + 1);
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn setting_and_expiring_reload_the_shaper() {
+        let dir = std::env::temp_dir().join(format!(
+            "lqosd-temporary-overrides-test-{}",
+            std::process::id()
+        ));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join(PERSIST_FILE);
+        let shaper = FakeShaper::default();
+        let entry = TemporaryOverride {
+            circuit_id: "c1".to_string(),
+            download_mbps: 10.0,
+            upload_mbps: 2.0,
+            expires_unix: 100,
+        };
+
+        set_in(&shaper, &path, entry.clone(), 5.0, 1.0, 1).unwrap();
+        assert_eq!(shaper.reloads.get(), 1, "the cap is applied when set");
+        assert_eq!(shaper.overrides.borrow().circuit_adjustments().len(), 1);
+
+        expire_in(&shaper, &path, 99).unwrap();
+        assert_eq!(shaper.reloads.get(), 1, "nothing is due yet");
+
+        expire_in(&shaper, &path, 100).unwrap();
+        assert_eq!(shaper.reloads.get(), 2, "the cap is lifted when it expires");
+        assert!(shaper.overrides.borrow().circuit_adjustments().is_empty());
+        assert!(load_pending(&path).is_empty());
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn removes_only_the_temporary_adjustment() {
+        let mut overrides = OverrideFile::default();
+        overrides.add_circuit_adjustment(speed("c1", 100.0, 20.0));
+        overrides.add_circuit_adjustment(speed("c1", 10.0, 2.0));
+        let entry = TemporaryOverride {
+            circuit_id: "c1".to_string(),
+            download_mbps: 10.0,
+            upload_mbps: 2.0,
+            expires_unix: 0,
+        };
+
+        assert!(remove_adjustment(&mut overrides, &entry));
+        assert_eq!(overrides.circuit_adjustments().len(), 1);
+        assert!(matches!(
+            &overrides.circuit_adjustments()[0],
+            CircuitAdjustment::CircuitAdjustSpeed {
+                max_download_bandwidth: Some(down),
+                ..
+            } if *down == 100.0
+        ));
+        assert!(
+            !remove_adjustment(&mut overrides, &entry),
+            "already removed"
+        );
+    }
+}
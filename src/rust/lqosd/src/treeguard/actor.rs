@@ -9,16 +9,20 @@ use crate::shaped_devices_tracker::{NETWORK_JSON, SHAPED_DEVICES};
 use crate::system_stats::SystemStats;
 use crate::throughput_tracker::CIRCUIT_RTT_BUFFERS;
 use crate::treeguard::TreeguardError;
+use crate::treeguard::rebalance::{self, RebalanceMove, RebalanceSite, SoftirqSampler};
 use crate::treeguard::state::{
-    CircuitSqmState, CircuitState, LinkState, LinkStructuralIneligibleState,
-    LinkTopologyFingerprint, LinkVirtualState, is_sustained_idle, is_sustained_window,
+    AdaptiveSqmState, CircuitSqmState, CircuitState, Ewma, LinkState,
+    LinkStructuralIneligibleState, LinkTopologyFingerprint, LinkVirtualState, RebalanceState,
+    SqmExperiment, is_sustained_idle, is_sustained_window,
 };
 use crate::treeguard::{bakery, decisions, overrides};
 use crossbeam_channel::{Receiver, Sender};
 use fxhash::{FxHashMap, FxHashSet};
 use lqos_bakery::{BakeryRuntimeNodeOperationFailureReason, BakeryRuntimeNodeOperationStatus};
 use lqos_config::{NetworkJsonNode, ShapedDevice, load_config};
-use lqos_overrides::{NetworkAdjustment, OverrideFile, OverrideLayer, OverrideStore};
+use lqos_overrides::{
+    NetworkAdjustment, OverrideFile, OverrideLayer, OverrideStore, SubmitOutcome,
+};
 use lqos_utils::hash_to_i64;
 use lqos_utils::units::DownUpOrder;
 use lqos_utils::unix_time::{time_since_boot, unix_now};
@@ -50,6 +54,7 @@ const TREEGUARD_LOW_VALUE_THROUGHPUT_MBPS: f64 = 1.0;
 const TREEGUARD_MAX_AUTO_VIRTUALIZED_NODES: usize = 64;
 const TREEGUARD_TOP_LEVEL_VIRTUALIZATION_VALUE_BONUS: u64 = 1_000_000_000;
 const TREEGUARD_WARNING_DETAIL_LIMIT_PER_GROUP: usize = 3;
+const TREEGUARD_REBALANCE_CONFIRM_TIMEOUT_SECONDS: u64 = 5 * 60;
 
 struct TreeguardWarningGroupState {
     emitted: usize,
@@ -112,6 +117,11 @@ pub(crate) enum TreeguardCommand {
         /// One-shot reply channel. Side effect: sends a snapshot to the requester.
         reply: tokio::sync::oneshot::Sender<Vec<TreeguardActivityEntry>>,
     },
+    /// Re-virtualize nodes that were runtime-virtualized on the HA peer before a takeover.
+    AdoptRuntimeVirtualized {
+        /// Node names to virtualize.
+        nodes: Vec<String>,
+    },
 }
 
 /// Starts the TreeGuard actor.
@@ -153,6 +163,28 @@ pub(crate) fn is_runtime_virtualized_node(node_name: &str) -> bool {
         .is_some_and(|cache| cache.read().contains(node_name))
 }
 
+/// Returns the nodes TreeGuard currently has runtime-virtualized, sorted by name.
+pub(crate) fn runtime_virtualized_nodes() -> Vec<String> {
+    let mut nodes: Vec<String> = TREEGUARD_RUNTIME_VIRTUALIZED_NODES
+        .get()
+        .map(|cache| cache.read().iter().cloned().collect())
+        .unwrap_or_default();
+    nodes.sort();
+    nodes
+}
+
+/// Asks the TreeGuard actor to re-virtualize nodes carried over from the HA peer.
+///
+/// This function is not pure: it sends a message to the TreeGuard actor thread.
+pub(crate) fn adopt_runtime_virtualized_nodes(nodes: Vec<String>) -> bool {
+    let Some(sender) = TREEGUARD_SENDER.get() else {
+        return false;
+    };
+    sender
+        .try_send(TreeguardCommand::AdoptRuntimeVirtualized { nodes })
+        .is_ok()
+}
+
 /// Requests a status snapshot from the TreeGuard actor.
 ///
 /// This function is not pure: it sends a message to the TreeGuard actor thread.
@@ -207,7 +239,7 @@ fn treeguard_actor_loop(
         let timeout = next_tick.saturating_duration_since(Instant::now());
 
         match rx.recv_timeout(timeout) {
-            Ok(cmd) => handle_command(cmd, &status, &activity),
+            Ok(cmd) => handle_command(cmd, &status, &mut activity, &mut runtime_state),
             Err(crossbeam_channel::RecvTimeoutError::Timeout) => {
                 last_tick = Instant::now();
                 run_tick(
@@ -230,11 +262,13 @@ fn treeguard_actor_loop(
 
 /// Handles a command received by the actor.
 ///
-/// This function has side effects: it sends a snapshot reply over the provided one-shot channel.
+/// This function has side effects: it sends a snapshot reply over the provided one-shot channel,
+/// and may queue Bakery runtime virtualization.
 fn handle_command(
     cmd: TreeguardCommand,
     status: &TreeguardStatusData,
-    activity: &VecDeque<TreeguardActivityEntry>,
+    activity: &mut VecDeque<TreeguardActivityEntry>,
+    runtime_state: &mut TreeguardRuntimeState,
 ) {
     match cmd {
         TreeguardCommand::GetStatus { reply } => {
@@ -244,6 +278,57 @@ fn handle_command(
             let data: Vec<TreeguardActivityEntry> = activity.iter().cloned().rev().collect();
             let _ = reply.send(data);
         }
+        TreeguardCommand::AdoptRuntimeVirtualized { nodes } => {
+            adopt_runtime_virtualized(activity, runtime_state, nodes);
+        }
+    }
+}
+
+/// Queues live virtualization for nodes the HA peer had runtime-virtualized, so the tree matches
+/// what the peer was shaping. Completion is picked up by `reconcile_pending_link_operations`.
+///
+/// This function is not pure: it sends commands to Bakery and appends to the activity ring.
+fn adopt_runtime_virtualized(
+    activity: &mut VecDeque<TreeguardActivityEntry>,
+    runtime_state: &mut TreeguardRuntimeState,
+    nodes: Vec<String>,
+) {
+    let now_unix = unix_now().unwrap_or(0);
+    for node_name in nodes {
+        if runtime_state.runtime_virtualized_nodes.contains(&node_name)
+            || runtime_state
+                .pending_link_operations
+                .contains_key(&node_name)
+        {
+            continue;
+        }
+        let reason = "Runtime-virtualized on the HA peer before takeover";
+        match bakery::submit_node_virtualization_live(&node_name, true) {
+            Ok(()) => {
+                runtime_state.pending_link_operations.insert(
+                    node_name.clone(),
+                    PendingLinkOperation {
+                        target: LinkVirtualState::Virtual,
+                        reason: reason.to_string(),
+                    },
+                );
+                push_activity(
+                    activity,
+                    TreeguardActivityEntry {
+                        time: now_unix.to_string(),
+                        entity_type: "node".to_string(),
+                        entity_id: node_name,
+                        action: "virtualize_requested".to_string(),
+                        persisted: false,
+                        reason: format!("{reason}. Queued in Bakery for live virtualization."),
+                        ..Default::default()
+                    },
+                );
+            }
+            Err(e) => {
+                warn!("TreeGuard: unable to adopt runtime virtualization of '{node_name}': {e}");
+            }
+        }
     }
 }
 
@@ -254,6 +339,8 @@ fn empty_status_snapshot() -> TreeguardStatusData {
         paused_for_bakery_reload: false,
         pause_reason: None,
         cpu_max_pct: None,
+        softirq_spread_pct: None,
+        sqm_experiments_active: 0,
         total_nodes: 0,
         total_circuits: 0,
         managed_nodes: 0,
@@ -416,6 +503,7 @@ fn update_runtime_virtualized_cache(runtime_virtualized_nodes: &FxHashSet<String
 struct TreeguardRuntimeState {
     link_states: FxHashMap<String, LinkState>,
     circuit_states: FxHashMap<String, CircuitState>,
+    adaptive_sqm_states: FxHashMap<String, AdaptiveSqmState>,
     circuit_inventory: CircuitInventory,
     circuit_batch_cursor: usize,
     next_sqm_batch_id: u64,
@@ -427,6 +515,15 @@ struct TreeguardRuntimeState {
     duplicate_device_conflict_circuits: FxHashSet<String>,
     last_dry_run: Option<bool>,
     paused_for_bakery_reload: bool,
+    rebalance: RebalanceState,
+    pending_rebalance_moves: FxHashMap<String, PendingRebalanceMove>,
+    softirq_sampler: SoftirqSampler,
+}
+
+#[derive(Clone, Debug)]
+struct PendingRebalanceMove {
+    requested: RebalanceMove,
+    requested_unix: u64,
 }
 
 #[derive(Clone, Debug)]
@@ -794,6 +891,8 @@ fn run_tick(
         status.paused_for_bakery_reload = false;
         status.pause_reason = None;
         status.cpu_max_pct = None;
+        status.softirq_spread_pct = None;
+        status.sqm_experiments_active = 0;
         status.managed_nodes = 0;
         status.managed_circuits = 0;
         status.virtualized_nodes = 0;
@@ -814,6 +913,7 @@ fn run_tick(
     {
         runtime_state.link_states.clear();
         runtime_state.circuit_states.clear();
+        runtime_state.adaptive_sqm_states.clear();
         runtime_state.pending_link_operations.clear();
         push_activity(
             activity,
@@ -839,6 +939,7 @@ fn run_tick(
 
     let link_states = &mut runtime_state.link_states;
     let circuit_states = &mut runtime_state.circuit_states;
+    let adaptive_sqm_states = &mut runtime_state.adaptive_sqm_states;
     let runtime_virtualized_nodes = &mut runtime_state.runtime_virtualized_nodes;
     let pending_link_operations = &mut runtime_state.pending_link_operations;
     let link_virtualization_backoff_until_unix =
@@ -1597,9 +1698,9 @@ fn run_tick(
             }
         };
         if !removed.is_empty() {
-            match overrides::clear_device_overrides(&removed) {
-                Ok(changed) => {
-                    if changed {
+            match overrides::clear_device_overrides(None, &removed) {
+                Ok(outcome) => {
+                    if matches!(outcome, SubmitOutcome::Applied { changed: true }) {
                         push_activity(
                             activity,
                             TreeguardActivityEntry {
@@ -1624,12 +1725,18 @@ fn run_tick(
         managed_device_ids.clear();
         duplicate_device_conflict_circuits.clear();
         circuit_states.clear();
+        adaptive_sqm_states.clear();
         runtime_state.circuit_batch_cursor = 0;
     } else {
         circuit_states.retain(|circuit_id, _| {
             circuit_inventory.entries.contains_key(circuit_id)
                 && (tg.circuits.all_circuits || allowlisted_circuits.contains(circuit_id))
         });
+        adaptive_sqm_states.retain(|circuit_id, _| circuit_states.contains_key(circuit_id));
+        let mut active_sqm_experiments = adaptive_sqm_states
+            .values()
+            .filter(|state| state.experiment.is_some())
+            .count();
 
         let treeguard_device_ids_with_overrides: FxHashSet<String> = treeguard_overrides_snapshot
             .as_ref()
@@ -1647,9 +1754,9 @@ fn run_tick(
             .cloned()
             .collect();
         if !removed.is_empty() {
-            match overrides::clear_device_overrides(&removed) {
-                Ok(changed) => {
-                    if changed {
+            match overrides::clear_device_overrides(None, &removed) {
+                Ok(outcome) => {
+                    if matches!(outcome, SubmitOutcome::Applied { changed: true }) {
                         push_activity(
                             activity,
                             TreeguardActivityEntry {
@@ -1726,9 +1833,12 @@ fn run_tick(
                     );
                 }
                 if !entry.device_ids.is_empty() {
-                    match overrides::clear_device_overrides(&entry.device_ids) {
-                        Ok(changed) => {
-                            if changed {
+                    match overrides::clear_device_overrides(
+                        overrides::circuit_site(&entry.devices),
+                        &entry.device_ids,
+                    ) {
+                        Ok(outcome) => {
+                            if matches!(outcome, SubmitOutcome::Applied { changed: true }) {
                                 push_activity(
                                     activity,
                                     TreeguardActivityEntry {
@@ -1755,6 +1865,12 @@ fn run_tick(
                     }
                 }
                 circuit_states.remove(circuit_id);
+                if adaptive_sqm_states
+                    .remove(circuit_id)
+                    .is_some_and(|state| state.experiment.is_some())
+                {
+                    active_sqm_experiments = active_sqm_experiments.saturating_sub(1);
+                }
                 continue;
             }
 
@@ -1797,9 +1913,12 @@ fn run_tick(
                     "TreeGuard circuits: circuit '{circuit_id}' has operator SQM overrides; TreeGuard will not manage it."
                 ));
                 if !entry.device_ids.is_empty() {
-                    match overrides::clear_device_overrides(&entry.device_ids) {
-                        Ok(changed) => {
-                            if changed {
+                    match overrides::clear_device_overrides(
+                        overrides::circuit_site(&entry.devices),
+                        &entry.device_ids,
+                    ) {
+                        Ok(outcome) => {
+                            if matches!(outcome, SubmitOutcome::Applied { changed: true }) {
                                 push_activity(
                                     activity,
                                     TreeguardActivityEntry {
@@ -1840,6 +1959,64 @@ fn run_tick(
             }
 
             let live_rollup = live_snapshot.by_circuit_id.get(circuit_id);
+            let bps = live_rollup
+                .map(|rollup| rollup.bytes_per_second)
+                .unwrap_or(DownUpOrder { down: 0, up: 0 });
+            let qoo = live_rollup.map(|rollup| rollup.qoo).unwrap_or(DownUpOrder {
+                down: None,
+                up: None,
+            });
+
+            let adaptive_state = adaptive_sqm_states
+                .entry(circuit_id.to_string())
+                .or_insert_with(|| {
+                    initial_adaptive_sqm_state(
+                        &entry.devices,
+                        treeguard_overrides_snapshot.as_ref(),
+                    )
+                });
+            let cake_both_ways = |sqm: DownUpOrder<CircuitSqmState>| {
+                sqm.down == CircuitSqmState::Cake && sqm.up == CircuitSqmState::Cake
+            };
+            let desired_sqm = DownUpOrder {
+                down: state.down.desired,
+                up: state.up.desired,
+            };
+            if !cake_both_ways(desired_sqm) {
+                // CPU-saving switches replace any kept CAKE profile.
+                adaptive_state.active_profile = None;
+            } else if cake_both_ways(base_sqm)
+                && (tg.adaptive_sqm.enabled || adaptive_state.experiment.is_some())
+                && process_adaptive_sqm_tick(
+                    AdaptiveSqmTickContext {
+                        status,
+                        activity,
+                        now_unix,
+                        dry_run: tg.dry_run,
+                        circuit_id,
+                        circuit_entity_id: &entry.circuit_entity_id,
+                        circuit_label: &entry.circuit_label,
+                        devices: &entry.devices,
+                        cap_down: entry.cap_down,
+                        cap_up: entry.cap_up,
+                        bps,
+                        qoo,
+                        cfg: &tg.adaptive_sqm,
+                        persist_sqm_overrides: tg.circuits.persist_sqm_overrides,
+                        active_experiments: &mut active_sqm_experiments,
+                        circuit_change_budget_remaining: &mut circuit_change_budget_remaining,
+                    },
+                    adaptive_state,
+                    overrides::set_devices_sqm_override,
+                    |circuit_id, devices, token| {
+                        bakery::apply_circuit_sqm_override_live(circuit_id, devices, token)
+                    },
+                )
+            {
+                // Leave the circuit alone while a profile experiment is being measured.
+                continue;
+            }
+
             process_circuit_tick(
                 CircuitTickContext {
                     status,
@@ -1858,16 +2035,11 @@ fn run_tick(
                         || allowlisted_circuits.contains(circuit_id),
                     cap_down: entry.cap_down,
                     cap_up: entry.cap_up,
-                    bps: live_rollup
-                        .map(|rollup| rollup.bytes_per_second)
-                        .unwrap_or(DownUpOrder { down: 0, up: 0 }),
+                    bps,
                     last_rtt_seen_nanos: rtt_snapshot
                         .get(&entry.circuit_hash)
                         .map(|buf| buf.last_seen),
-                    qoo: live_rollup.map(|rollup| rollup.qoo).unwrap_or(DownUpOrder {
-                        down: None,
-                        up: None,
-                    }),
+                    qoo,
                     cpu_cfg: &tg.cpu,
                     circuits_cfg: &tg.circuits,
                     qoo_cfg: &tg.qoo,
@@ -1891,7 +2063,22 @@ fn run_tick(
         }
     }
 
+    run_rebalance_tick(
+        status,
+        activity,
+        now_unix,
+        &config,
+        &mut runtime_state.rebalance,
+        &mut runtime_state.pending_rebalance_moves,
+        &mut runtime_state.softirq_sampler,
+        runtime_virtualized_nodes,
+    );
+
     status.virtualized_nodes = runtime_virtualized_nodes.len();
+    status.sqm_experiments_active = adaptive_sqm_states
+        .values()
+        .filter(|state| state.experiment.is_some())
+        .count();
     let mut cake_circuits = 0usize;
     let mut mixed_sqm_circuits = 0usize;
     let mut fq_codel_circuits = 0usize;
@@ -1914,6 +2101,261 @@ fn run_tick(
     warning_limiter.flush(status);
 }
 
+/// Samples softirq load and moves top-level sites between queues when imbalance persists.
+///
+/// This function has side effects: it reads `/proc/stat`, may submit live moves to Bakery, may
+/// rewrite `planner_state.json`, and appends to the activity ring buffer.
+#[allow(clippy::too_many_arguments)]
+fn run_rebalance_tick(
+    status: &mut TreeguardStatusData,
+    activity: &mut VecDeque<TreeguardActivityEntry>,
+    now_unix: u64,
+    config: &lqos_config::Config,
+    rebalance_state: &mut RebalanceState,
+    pending_moves: &mut FxHashMap<String, PendingRebalanceMove>,
+    sampler: &mut SoftirqSampler,
+    runtime_virtualized_nodes: &FxHashSet<String>,
+) {
+    let tg = &config.treeguard;
+    let site_queues = bakery::top_level_site_queues();
+    confirm_pending_rebalance_moves(
+        status,
+        activity,
+        now_unix,
+        &config.lqos_directory,
+        &site_queues,
+        pending_moves,
+    );
+
+    if !tg.enabled || !tg.rebalance.enabled {
+        rebalance_state.imbalance_since_unix = None;
+        status.softirq_spread_pct = None;
+        return;
+    }
+    let Some(softirq_pct) = sampler.sample() else {
+        status.softirq_spread_pct = None;
+        return;
+    };
+
+    // Only queues that currently host top-level sites take part, unless the operator pinned the
+    // queue count.
+    let queue_count = config
+        .queues
+        .override_available_queues
+        .filter(|queues| *queues > 0)
+        .unwrap_or_else(|| site_queues.values().copied().max().unwrap_or(0));
+    let shaping_cpus = lqos_config::detect_shaping_cpus(config).shaping;
+    let queue_cpus = rebalance::queue_cpus(queue_count, &shaping_cpus, &softirq_pct);
+    let max_and_spread = rebalance::softirq_spread(&softirq_pct, &queue_cpus);
+    status.softirq_spread_pct = max_and_spread.map(|(_, spread)| spread);
+
+    if decisions::softirq_imbalanced(&tg.rebalance, max_and_spread) {
+        rebalance_state.imbalance_since_unix.get_or_insert(now_unix);
+    } else {
+        rebalance_state.imbalance_since_unix = None;
+    }
+    if !decisions::decide_rebalance(decisions::RebalanceTriggerInput {
+        now_unix,
+        softirq_max_and_spread_pct: max_and_spread,
+        cfg: &tg.rebalance,
+        state: rebalance_state,
+    }) {
+        return;
+    }
+    if !pending_moves.is_empty() {
+        return;
+    }
+    rebalance_state.last_run_unix = Some(now_unix);
+
+    let sites: Vec<RebalanceSite> = {
+        let reader = NETWORK_JSON.read();
+        reader
+            .get_nodes_when_ready()
+            .iter()
+            .filter(|node| node.name != "Root" && node.immediate_parent == Some(0))
+            .filter(|node| !runtime_virtualized_nodes.contains(&node.name))
+            .filter_map(|node| {
+                let queue = *site_queues.get(&hash_to_i64(&node.name))?;
+                Some(RebalanceSite {
+                    name: node.name.clone(),
+                    queue,
+                    weight: (node.current_throughput.get_down() as f64)
+                        + (node.current_throughput.get_up() as f64),
+                })
+            })
+            .collect()
+    };
+    let moves = rebalance::plan_rebalance_moves(rebalance::RebalancePlanInput {
+        now_unix,
+        sites: &sites,
+        queue_cpus: &queue_cpus,
+        softirq_pct: &softirq_pct,
+        last_move_unix: &rebalance_state.last_move_unix,
+        cfg: &tg.rebalance,
+    });
+    let (max_pct, spread_pct) = max_and_spread.unwrap_or_default();
+    if moves.is_empty() {
+        debug!(
+            "TreeGuard rebalance: softirq spread {}% (busiest CPU {}%) but no eligible top-level site moves",
+            spread_pct, max_pct
+        );
+        return;
+    }
+
+    for mv in moves {
+        let reason = format!(
+            "Softirq load spread {}% (busiest CPU {}%) for at least {} minutes; queue {} CPU {}%, queue {} CPU {}%",
+            spread_pct,
+            max_pct,
+            tg.rebalance.sustain_minutes,
+            mv.from_queue,
+            rebalance::queue_softirq_pct(&softirq_pct, &queue_cpus, mv.from_queue),
+            mv.to_queue,
+            rebalance::queue_softirq_pct(&softirq_pct, &queue_cpus, mv.to_queue),
+        );
+        if tg.dry_run {
+            rebalance_state
+                .last_move_unix
+                .insert(mv.name.clone(), now_unix);
+            push_activity(
+                activity,
+                TreeguardActivityEntry {
+                    time: now_unix.to_string(),
+                    entity_type: "node".to_string(),
+                    entity_id: mv.name.clone(),
+                    action: format!("would_rebalance_move:{}->{}", mv.from_queue, mv.to_queue),
+                    persisted: false,
+                    reason: format!("Dry-run. {reason}"),
+                    ..Default::default()
+                },
+            );
+            status.last_action_summary = Some(format!(
+                "Would move top-level site '{}' from queue {} to queue {}",
+                mv.name, mv.from_queue, mv.to_queue
+            ));
+            continue;
+        }
+
+        match bakery::submit_top_level_site_move_live(&mv.name, mv.to_queue) {
+            Ok(()) => {
+                rebalance_state
+                    .last_move_unix
+                    .insert(mv.name.clone(), now_unix);
+                push_activity(
+                    activity,
+                    TreeguardActivityEntry {
+                        time: now_unix.to_string(),
+                        entity_type: "node".to_string(),
+                        entity_id: mv.name.clone(),
+                        action: format!(
+                            "rebalance_move_requested:{}->{}",
+                            mv.from_queue, mv.to_queue
+                        ),
+                        persisted: false,
+                        reason: format!("{reason}. Queued in Bakery for a lossless live move."),
+                        ..Default::default()
+                    },
+                );
+                status.last_action_summary = Some(format!(
+                    "Queued move of top-level site '{}' from queue {} to queue {}",
+                    mv.name, mv.from_queue, mv.to_queue
+                ));
+                pending_moves.insert(
+                    mv.name.clone(),
+                    PendingRebalanceMove {
+                        requested: mv,
+                        requested_unix: now_unix,
+                    },
+                );
+            }
+            Err(e) => {
+                status.warnings.push(format!(
+                    "TreeGuard rebalance: unable to move top-level site '{}' to queue {}: {e}",
+                    mv.name, mv.to_queue
+                ));
+                push_activity(
+                    activity,
+                    TreeguardActivityEntry {
+                        time: now_unix.to_string(),
+                        entity_type: "node".to_string(),
+                        entity_id: mv.name.clone(),
+                        action: "rebalance_move_failed".to_string(),
+                        persisted: false,
+                        reason: format!("{reason}. Bakery submit failed: {e}"),
+                        ..Default::default()
+                    },
+                );
+            }
+        }
+    }
+}
+
+/// Confirms queued top-level site moves against Bakery's view of the tree.
+///
+/// Confirmed moves are written to `planner_state.json` so the next full reload keeps them; moves
+/// Bakery has not applied within `TREEGUARD_REBALANCE_CONFIRM_TIMEOUT_SECONDS` are dropped.
+///
+/// This function is not pure: it may rewrite `planner_state.json` and appends to the activity ring
+/// buffer.
+fn confirm_pending_rebalance_moves(
+    status: &mut TreeguardStatusData,
+    activity: &mut VecDeque<TreeguardActivityEntry>,
+    now_unix: u64,
+    lqos_directory: &str,
+    site_queues: &std::collections::HashMap<i64, u32>,
+    pending_moves: &mut FxHashMap<String, PendingRebalanceMove>,
+) {
+    if pending_moves.is_empty() {
+        return;
+    }
+    let mut confirmed = Vec::new();
+    pending_moves.retain(|name, pending| {
+        let target = pending.requested.to_queue;
+        if site_queues.get(&hash_to_i64(name)) == Some(&target) {
+            confirmed.push(pending.requested.clone());
+            push_activity(
+                activity,
+                TreeguardActivityEntry {
+                    time: now_unix.to_string(),
+                    entity_type: "node".to_string(),
+                    entity_id: name.clone(),
+                    action: format!("rebalance_moved:{}->{}", pending.requested.from_queue, target),
+                    persisted: true,
+                    reason: "Bakery moved the top-level site; recorded in planner state."
+                        .to_string(),
+                    ..Default::default()
+                },
+            );
+            return false;
+        }
+        if now_unix.saturating_sub(pending.requested_unix)
+            >= TREEGUARD_REBALANCE_CONFIRM_TIMEOUT_SECONDS
+        {
+            push_activity(
+                activity,
+                TreeguardActivityEntry {
+                    time: now_unix.to_string(),
+                    entity_type: "node".to_string(),
+                    entity_id: name.clone(),
+                    action: "rebalance_move_failed".to_string(),
+                    persisted: false,
+                    reason: format!(
+                        "Bakery did not move the top-level site to queue {target}; see Bakery activity for details."
+                    ),
+                    ..Default::default()
+                },
+            );
+            return false;
+        }
+        true
+    });
+    if let Err(e) = rebalance::record_planner_moves(lqos_directory, &confirmed, now_unix) {
+        status.warnings.push(format!(
+            "TreeGuard rebalance: unable to record moves in planner state: {e}"
+        ));
+    }
+}
+
 /// Applies TreeGuard backoff while Bakery is performing a structural full reload.
 ///
 /// This function is not pure: it mutates TreeGuard status/runtime state and may emit logs.
@@ -1931,7 +2373,7 @@ fn pause_for_bakery_reload(
         enabled,
         dry_run,
         lqos_bakery::full_reload_in_progress(),
-        lqos_bakery::bakery_reload_required_reason(),
+        lqos_bakery::bakery_reload_required_reason().or_else(crate::ha::standby_pause_reason),
     )
 }
 
@@ -1963,6 +2405,8 @@ fn pause_for_bakery_reload_with_flag(
         status.paused_for_bakery_reload = true;
         status.pause_reason = Some(pause_reason.clone());
         status.cpu_max_pct = None;
+        status.softirq_spread_pct = None;
+        status.sqm_experiments_active = 0;
         status.last_action_summary = Some(format!("Paused while {}", pause_reason));
         status
             .warnings
@@ -1979,7 +2423,7 @@ fn pause_for_bakery_reload_with_flag(
     if status
         .last_action_summary
         .as_deref()
-        .is_some_and(|summary| summary.starts_with("Paused while Bakery "))
+        .is_some_and(|summary| summary.starts_with("Paused while "))
     {
         status.last_action_summary = None;
     }
@@ -1995,8 +2439,8 @@ fn clear_legacy_treeguard_virtual_override(
     reason: &str,
 ) {
     match overrides::clear_node_virtual(node_name) {
-        Ok(changed) => {
-            if changed {
+        Ok(outcome) => {
+            if matches!(outcome, SubmitOutcome::Applied { changed: true }) {
                 push_activity(
                     activity,
                     TreeguardActivityEntry {
@@ -2811,8 +3255,8 @@ fn apply_circuit_sqm_change<P, C, L>(
     mut clear_override: C,
     mut live_apply: L,
 ) where
-    P: FnMut(&[String], &str) -> Result<bool, TreeguardError>,
-    C: FnMut(&[String]) -> Result<bool, TreeguardError>,
+    P: FnMut(Option<&str>, &[String], &str) -> Result<SubmitOutcome, TreeguardError>,
+    C: FnMut(Option<&str>, &[String]) -> Result<SubmitOutcome, TreeguardError>,
     L: FnMut(&str, &[lqos_config::ShapedDevice], &str) -> Result<(), TreeguardError>,
 {
     let CircuitSqmApplyContext {
@@ -2894,15 +3338,42 @@ fn apply_circuit_sqm_change<P, C, L>(
         .map(|device| device.device_id.clone())
         .collect();
     if persist_sqm_overrides {
+        let site = overrides::circuit_site(devices);
         let persist_result = if returning_to_base {
-            clear_override(&device_ids)
+            clear_override(site, &device_ids)
         } else {
-            persist_override(&device_ids, &token)
+            persist_override(site, &device_ids, &token)
         };
         match persist_result {
-            Ok(_) => {
+            Ok(SubmitOutcome::Applied { .. }) => {
                 persisted_ok = true;
             }
+            Ok(SubmitOutcome::Queued { id }) => {
+                // Change control is holding the override, so the live queue must keep its
+                // approved state. Leaving `state` untouched lets the next tick re-propose it;
+                // identical proposals reuse the same pending entry.
+                push_activity(
+                    activity,
+                    TreeguardActivityEntry {
+                        time: now_unix.to_string(),
+                        entity_type: "circuit".to_string(),
+                        entity_id: circuit_entity_id.to_string(),
+                        action: if returning_to_base {
+                            format!("clear_sqm_override_pending:{token}")
+                        } else {
+                            format!("set_sqm_override_pending:{token}")
+                        },
+                        persisted: false,
+                        reason: format!("Held for approval as pending change {id}"),
+                        batch_id: Some(batch_id.to_string()),
+                        batch_kind: Some("sqm".to_string()),
+                    },
+                );
+                status.last_action_summary = Some(format!(
+                    "SQM override for circuit '{circuit_label}' -> {token} is held for approval"
+                ));
+                return;
+            }
             Err(e) => {
                 status.warnings.push(format!(
                     "TreeGuard circuits: failed to {} SQM overrides for circuit '{circuit_id}': {e}",
@@ -3058,8 +3529,8 @@ fn process_circuit_tick<P, C, L>(
     live_apply: L,
 ) -> bool
 where
-    P: FnMut(&[String], &str) -> Result<bool, TreeguardError>,
-    C: FnMut(&[String]) -> Result<bool, TreeguardError>,
+    P: FnMut(Option<&str>, &[String], &str) -> Result<SubmitOutcome, TreeguardError>,
+    C: FnMut(Option<&str>, &[String]) -> Result<SubmitOutcome, TreeguardError>,
     L: FnMut(&str, &[lqos_config::ShapedDevice], &str) -> Result<(), TreeguardError>,
 {
     let CircuitTickContext {
@@ -3186,6 +3657,336 @@ where
     state.down.desired == CircuitSqmState::FqCodel || state.up.desired == CircuitSqmState::FqCodel
 }
 
+struct AdaptiveSqmTickContext<'a> {
+    status: &'a mut TreeguardStatusData,
+    activity: &'a mut VecDeque<TreeguardActivityEntry>,
+    now_unix: u64,
+    dry_run: bool,
+    circuit_id: &'a str,
+    circuit_entity_id: &'a str,
+    circuit_label: &'a str,
+    devices: &'a [lqos_config::ShapedDevice],
+    cap_down: f32,
+    cap_up: f32,
+    bps: DownUpOrder<u64>,
+    qoo: DownUpOrder<Option<f32>>,
+    cfg: &'a lqos_config::TreeguardAdaptiveSqmConfig,
+    persist_sqm_overrides: bool,
+    active_experiments: &'a mut usize,
+    circuit_change_budget_remaining: &'a mut usize,
+}
+
+/// Builds adaptive SQM state for a newly tracked circuit, recovering a kept CAKE profile from
+/// the TreeGuard override layer.
+///
+/// This function is pure: it has no side effects.
+fn initial_adaptive_sqm_state(
+    devices: &[lqos_config::ShapedDevice],
+    treeguard_overrides: Option<&OverrideFile>,
+) -> AdaptiveSqmState {
+    let active_profile = treeguard_overrides
+        .and_then(|overrides| find_circuit_override_token_in_overrides(devices, overrides))
+        .filter(|token| lqos_config::cake_profile_options(token).is_some());
+    AdaptiveSqmState {
+        active_profile,
+        ..Default::default()
+    }
+}
+
+/// Starts a retry cooldown once every configured profile has been tried.
+///
+/// This function is not pure: it mutates `state`.
+fn note_adaptive_sqm_profiles_exhausted(
+    state: &mut AdaptiveSqmState,
+    cfg: &lqos_config::TreeguardAdaptiveSqmConfig,
+    now_unix: u64,
+) {
+    let exhausted = cfg.profiles.iter().all(|profile| {
+        state.tried_profiles.contains(profile) || state.active_profile.as_ref() == Some(profile)
+    });
+    if exhausted {
+        state.retry_after_unix =
+            Some(now_unix.saturating_add(u64::from(cfg.retry_cooldown_minutes) * 60));
+    }
+}
+
+/// Samples loaded QoO for one circuit and starts, keeps, or reverts a CAKE profile experiment.
+///
+/// Returns true while an experiment is running on the circuit.
+///
+/// This function is not pure: it may live-apply SQM tokens, persist overrides, and append to the
+/// activity ring buffer.
+fn process_adaptive_sqm_tick<P, L>(
+    ctx: AdaptiveSqmTickContext<'_>,
+    state: &mut AdaptiveSqmState,
+    mut persist_override: P,
+    mut live_apply: L,
+) -> bool
+where
+    P: FnMut(Option<&str>, &[String], &str) -> Result<SubmitOutcome, TreeguardError>,
+    L: FnMut(&str, &[lqos_config::ShapedDevice], &str) -> Result<(), TreeguardError>,
+{
+    let AdaptiveSqmTickContext {
+        status,
+        activity,
+        now_unix,
+        dry_run,
+        circuit_id,
+        circuit_entity_id,
+        circuit_label,
+        devices,
+        cap_down,
+        cap_up,
+        bps,
+        qoo,
+        cfg,
+        persist_sqm_overrides,
+        active_experiments,
+        circuit_change_budget_remaining,
+    } = ctx;
+
+    if state.retry_after_unix.is_some_and(|t| now_unix >= t) {
+        state.retry_after_unix = None;
+        state.tried_profiles.clear();
+    }
+
+    let util_pct = |bytes_per_second: u64, cap_mbps: f32| {
+        ((bytes_per_second as f64 * 8.0) / 1_000_000.0) / cap_mbps as f64 * 100.0
+    };
+    let loaded = cap_down > 0.0
+        && cap_up > 0.0
+        && util_pct(bps.down, cap_down).max(util_pct(bps.up, cap_up)) >= cfg.min_util_pct as f64;
+    let worst_qoo = match (qoo.down, qoo.up) {
+        (Some(d), Some(u)) => Some(d.min(u)),
+        (Some(v), None) | (None, Some(v)) => Some(v),
+        (None, None) => None,
+    };
+    state.observe(now_unix, worst_qoo, loaded, cfg.poor_qoo_score);
+
+    let decision = decisions::decide_adaptive_sqm(decisions::AdaptiveSqmInput {
+        now_unix,
+        cfg,
+        active_experiments: *active_experiments,
+        state,
+    });
+
+    match decision {
+        decisions::AdaptiveSqmDecision::NoChange => {}
+        decisions::AdaptiveSqmDecision::Start { profile } => {
+            let baseline_qoo = state
+                .loaded_qoo_ewma
+                .current()
+                .map(|v| v as f32)
+                .unwrap_or_default();
+            if dry_run {
+                state.tried_profiles.push(profile.clone());
+                state.poor_since_unix = None;
+                note_adaptive_sqm_profiles_exhausted(state, cfg, now_unix);
+                push_activity(
+                    activity,
+                    TreeguardActivityEntry {
+                        time: now_unix.to_string(),
+                        entity_type: "circuit".to_string(),
+                        entity_id: circuit_entity_id.to_string(),
+                        action: format!("would_start_sqm_experiment:{profile}"),
+                        persisted: false,
+                        reason: "Dry-run".to_string(),
+                        ..Default::default()
+                    },
+                );
+                status.last_action_summary = Some(format!(
+                    "Would start SQM experiment on circuit '{circuit_label}' -> {profile}"
+                ));
+                return false;
+            }
+            if devices.is_empty()
+                || !try_consume_circuit_change_budget(circuit_change_budget_remaining)
+            {
+                return false;
+            }
+
+            let previous_token = state
+                .active_profile
+                .clone()
+                .unwrap_or_else(|| "/".to_string());
+            match live_apply(circuit_id, devices, &profile) {
+                Ok(()) => {
+                    state.tried_profiles.push(profile.clone());
+                    state.poor_since_unix = None;
+                    *active_experiments = active_experiments.saturating_add(1);
+                    push_activity(
+                        activity,
+                        TreeguardActivityEntry {
+                            time: now_unix.to_string(),
+                            entity_type: "circuit".to_string(),
+                            entity_id: circuit_entity_id.to_string(),
+                            action: format!("sqm_experiment_started:{profile}"),
+                            persisted: false,
+                            reason: format!(
+                                "Loaded QoO {baseline_qoo:.1} below {:.1} for {} minutes; evaluating for {} minutes",
+                                cfg.poor_qoo_score, cfg.sustain_minutes, cfg.evaluation_minutes
+                            ),
+                            ..Default::default()
+                        },
+                    );
+                    status.last_action_summary = Some(format!(
+                        "Started SQM experiment on circuit '{circuit_label}' -> {profile}"
+                    ));
+                    state.experiment = Some(SqmExperiment {
+                        profile,
+                        previous_token,
+                        started_unix: now_unix,
+                        baseline_qoo,
+                        ..Default::default()
+                    });
+                }
+                Err(e) => {
+                    if is_retryable_live_mutation_unavailable(&e).is_none() {
+                        // Don't retry a profile Bakery refused outright.
+                        state.tried_profiles.push(profile.clone());
+                        note_adaptive_sqm_profiles_exhausted(state, cfg, now_unix);
+                        status.warnings.push(format!(
+                            "TreeGuard adaptive SQM: failed to start experiment on circuit '{circuit_id}': {e}"
+                        ));
+                        push_activity(
+                            activity,
+                            TreeguardActivityEntry {
+                                time: now_unix.to_string(),
+                                entity_type: "circuit".to_string(),
+                                entity_id: circuit_entity_id.to_string(),
+                                action: format!("sqm_experiment_start_failed:{profile}"),
+                                persisted: false,
+                                reason: format!("Bakery live apply failed: {e}"),
+                                ..Default::default()
+                            },
+                        );
+                    }
+                }
+            }
+        }
+        decisions::AdaptiveSqmDecision::Keep { after_qoo } => {
+            let Some(experiment) = state.experiment.take() else {
+                return false;
+            };
+            *active_experiments = active_experiments.saturating_sub(1);
+            state.loaded_qoo_ewma = Ewma::default();
+            let previous_profile = state.active_profile.replace(experiment.profile.clone());
+
+            let mut persisted = false;
+            if persist_sqm_overrides {
+                let device_ids: Vec<String> = devices
+                    .iter()
+                    .map(|device| device.device_id.clone())
+                    .collect();
+                match persist_override(
+                    overrides::circuit_site(devices),
+                    &device_ids,
+                    &experiment.profile,
+                ) {
+                    Ok(SubmitOutcome::Applied { .. }) => persisted = true,
+                    Ok(SubmitOutcome::Queued { id }) => {
+                        // The experiment profile may only stay live once it is approved.
+                        if let Err(e) = live_apply(circuit_id, devices, &experiment.previous_token)
+                        {
+                            status.warnings.push(format!(
+                                "TreeGuard adaptive SQM: failed to restore circuit '{circuit_id}' while its profile awaits approval: {e}"
+                            ));
+                        }
+                        state.active_profile = previous_profile;
+                        push_activity(
+                            activity,
+                            TreeguardActivityEntry {
+                                time: now_unix.to_string(),
+                                entity_type: "circuit".to_string(),
+                                entity_id: circuit_entity_id.to_string(),
+                                action: format!("sqm_experiment_pending:{}", experiment.profile),
+                                persisted: false,
+                                reason: format!(
+                                    "Loaded QoO improved {:.1} -> {after_qoo:.1}; held for approval as pending change {id}",
+                                    experiment.baseline_qoo
+                                ),
+                                ..Default::default()
+                            },
+                        );
+                        status.last_action_summary = Some(format!(
+                            "SQM profile {} on circuit '{circuit_label}' is held for approval",
+                            experiment.profile
+                        ));
+                        note_adaptive_sqm_profiles_exhausted(state, cfg, now_unix);
+                        return false;
+                    }
+                    Err(e) => status.warnings.push(format!(
+                        "TreeGuard adaptive SQM: failed to persist profile for circuit '{circuit_id}': {e}"
+                    )),
+                }
+            }
+            note_adaptive_sqm_profiles_exhausted(state, cfg, now_unix);
+            push_activity(
+                activity,
+                TreeguardActivityEntry {
+                    time: now_unix.to_string(),
+                    entity_type: "circuit".to_string(),
+                    entity_id: circuit_entity_id.to_string(),
+                    action: format!("sqm_experiment_kept:{}", experiment.profile),
+                    persisted,
+                    reason: format!(
+                        "Loaded QoO improved {:.1} -> {after_qoo:.1}",
+                        experiment.baseline_qoo
+                    ),
+                    ..Default::default()
+                },
+            );
+            status.last_action_summary = Some(format!(
+                "Kept SQM profile {} on circuit '{circuit_label}'",
+                experiment.profile
+            ));
+        }
+        decisions::AdaptiveSqmDecision::Revert { after_qoo } => {
+            let Some(experiment) = state.experiment.take() else {
+                return false;
+            };
+            if let Err(e) = live_apply(circuit_id, devices, &experiment.previous_token) {
+                if is_retryable_live_mutation_unavailable(&e).is_some() {
+                    state.experiment = Some(experiment);
+                    return true;
+                }
+                status.warnings.push(format!(
+                    "TreeGuard adaptive SQM: failed to revert experiment on circuit '{circuit_id}': {e}"
+                ));
+            }
+            *active_experiments = active_experiments.saturating_sub(1);
+            state.loaded_qoo_ewma = Ewma::default();
+            note_adaptive_sqm_profiles_exhausted(state, cfg, now_unix);
+            let after = after_qoo.map_or_else(
+                || "no loaded samples".to_string(),
+                |score| format!("{score:.1}"),
+            );
+            push_activity(
+                activity,
+                TreeguardActivityEntry {
+                    time: now_unix.to_string(),
+                    entity_type: "circuit".to_string(),
+                    entity_id: circuit_entity_id.to_string(),
+                    action: format!("sqm_experiment_reverted:{}", experiment.profile),
+                    persisted: false,
+                    reason: format!(
+                        "Loaded QoO {:.1} -> {after}; restored {}",
+                        experiment.baseline_qoo,
+                        if experiment.previous_token == "/" {
+                            "base SQM"
+                        } else {
+                            experiment.previous_token.as_str()
+                        }
+                    ),
+                    ..Default::default()
+                },
+            );
+        }
+    }
+
+    state.experiment.is_some()
+}
+
 /// Removes entries older than one hour from a recent-changes ring buffer.
 ///
 /// This function is not pure: it mutates `recent_changes`.
@@ -3248,13 +4049,14 @@ fn update_above_since(
 #[cfg(test)]
 mod tests {
     use super::{
-        CircuitSqmApplyContext, CircuitSqmTransition, CircuitTickContext, LinkVirtualState,
-        PendingLinkVirtualizationDecision, TreeguardRuntimeState, apply_circuit_sqm_change,
-        apply_link_virtualization_decision, base_circuit_sqm_state, circuit_evaluation_batch_size,
-        circuit_sqm_transition_from_decision, clear_structural_ineligible_if_topology_changed,
-        collect_circuit_batch, empty_status_snapshot, latched_structural_ineligible_reason,
-        pause_for_bakery_reload_with_flag, process_circuit_tick, run_tick,
-        select_link_virtualization_candidates, treeguard_manages_circuit_direction,
+        AdaptiveSqmTickContext, CircuitSqmApplyContext, CircuitSqmTransition, CircuitTickContext,
+        LinkVirtualState, PendingLinkVirtualizationDecision, TreeguardRuntimeState,
+        apply_circuit_sqm_change, apply_link_virtualization_decision, base_circuit_sqm_state,
+        circuit_evaluation_batch_size, circuit_sqm_transition_from_decision,
+        clear_structural_ineligible_if_topology_changed, collect_circuit_batch,
+        empty_status_snapshot, latched_structural_ineligible_reason,
+        pause_for_bakery_reload_with_flag, process_adaptive_sqm_tick, process_circuit_tick,
+        run_tick, select_link_virtualization_candidates, treeguard_manages_circuit_direction,
         try_consume_circuit_change_budget,
     };
     use crate::node_manager::ws::messages::TreeguardActivityEntry;
@@ -3679,8 +4481,8 @@ mod tests {
                 changed_down: true,
                 changed_up: false,
             },
-            |_device_ids, _token| Ok(false),
-            |_device_ids| Ok(false),
+            |_site, _device_ids, _token| Ok(SubmitOutcome::Applied { changed: false }),
+            |_site, _device_ids| Ok(SubmitOutcome::Applied { changed: false }),
             |circuit_id, devices, token| {
                 bakery::apply_circuit_sqm_override_live_with_sender_and_snapshot(
                     circuit_id, devices, token, &tx, &queues,
@@ -3765,10 +4567,10 @@ mod tests {
                 changed_down: true,
                 changed_up: false,
             },
-            |_device_ids, _token| Ok(false),
-            |device_ids| {
+            |_site, _device_ids, _token| Ok(SubmitOutcome::Applied { changed: false }),
+            |_site, device_ids| {
                 cleared_device_ids = device_ids.to_vec();
-                Ok(true)
+                Ok(SubmitOutcome::Applied { changed: true })
             },
             |_circuit_id, _devices, token| {
                 live_token = Some(token.to_string());
@@ -3793,6 +4595,216 @@ mod tests {
         assert_eq!(last_activity.batch_kind.as_deref(), Some("sqm"));
     }
 
+    #[test]
+    fn actor_held_sqm_change_leaves_live_queue_and_state_alone() {
+        let devices = vec![ShapedDevice {
+            circuit_id: "circuit-1".to_string(),
+            circuit_name: "Circuit One".to_string(),
+            device_id: "device-1".to_string(),
+            ..ShapedDevice::default()
+        }];
+        let mut status = empty_status_snapshot();
+        let mut activity: VecDeque<TreeguardActivityEntry> = VecDeque::new();
+        let mut state = crate::treeguard::state::CircuitState::default();
+
+        apply_circuit_sqm_change(
+            CircuitSqmApplyContext {
+                status: &mut status,
+                activity: &mut activity,
+                now_unix: 1_000,
+                dry_run: false,
+                persist_sqm_overrides: true,
+                circuit_id: "circuit-1",
+                circuit_entity_id: "Circuit One (circuit-1)",
+                circuit_label: "Circuit One",
+                devices: &devices,
+                base_sqm: DownUpOrder {
+                    down: CircuitSqmState::Cake,
+                    up: CircuitSqmState::Cake,
+                },
+                batch_id: "sqm-test-held",
+            },
+            &mut state,
+            CircuitSqmTransition {
+                proposed_down: CircuitSqmState::FqCodel,
+                proposed_up: CircuitSqmState::Cake,
+                changed_down: true,
+                changed_up: false,
+            },
+            |_site, _device_ids, _token| Ok(SubmitOutcome::Queued { id: 7 }),
+            |_site, _device_ids| panic!("a set must not clear"),
+            |_circuit_id, _devices, _token| panic!("held changes must not be applied live"),
+        );
+
+        assert_eq!(state.down.desired, CircuitSqmState::Cake);
+        assert_eq!(state.down.last_change_unix, None);
+        let last_activity = activity.back().expect("activity should be recorded");
+        assert_eq!(
+            last_activity.action,
+            "set_sqm_override_pending:fq_codel/cake"
+        );
+        assert!(!last_activity.persisted);
+        assert_eq!(
+            last_activity.reason,
+            "Held for approval as pending change 7"
+        );
+    }
+
+    #[test]
+    fn adaptive_sqm_kept_profile_awaiting_approval_is_restored() {
+        let devices = vec![ShapedDevice {
+            circuit_id: "circuit-9".to_string(),
+            circuit_name: "Circuit Nine".to_string(),
+            device_id: "device-9".to_string(),
+            ..ShapedDevice::default()
+        }];
+        let cfg = lqos_config::TreeguardAdaptiveSqmConfig {
+            enabled: true,
+            ..Default::default()
+        };
+        let mut status = empty_status_snapshot();
+        let mut activity: VecDeque<TreeguardActivityEntry> = VecDeque::new();
+        let mut state = crate::treeguard::state::AdaptiveSqmState::default();
+        let mut active_experiments = 0usize;
+        let mut budget = 8usize;
+        let mut live_tokens: Vec<String> = Vec::new();
+        let loaded_bps = DownUpOrder {
+            down: 10_000_000,
+            up: 0,
+        };
+
+        let mut tick =
+            |now_unix: u64, qoo: f32, state: &mut crate::treeguard::state::AdaptiveSqmState| {
+                process_adaptive_sqm_tick(
+                    AdaptiveSqmTickContext {
+                        status: &mut status,
+                        activity: &mut activity,
+                        now_unix,
+                        dry_run: false,
+                        circuit_id: "circuit-9",
+                        circuit_entity_id: "Circuit Nine (circuit-9)",
+                        circuit_label: "Circuit Nine",
+                        devices: &devices,
+                        cap_down: 100.0,
+                        cap_up: 20.0,
+                        bps: loaded_bps,
+                        qoo: DownUpOrder {
+                            down: Some(qoo),
+                            up: None,
+                        },
+                        cfg: &cfg,
+                        persist_sqm_overrides: true,
+                        active_experiments: &mut active_experiments,
+                        circuit_change_budget_remaining: &mut budget,
+                    },
+                    state,
+                    |_site, _device_ids, _token| Ok(SubmitOutcome::Queued { id: 3 }),
+                    |_circuit_id, _devices, token| {
+                        live_tokens.push(token.to_string());
+                        Ok(())
+                    },
+                )
+            };
+
+        assert!(!tick(1_000, 40.0, &mut state));
+        assert!(tick(1_300, 40.0, &mut state));
+        assert!(tick(1_600, 90.0, &mut state));
+        assert!(!tick(2_200, 90.0, &mut state));
+
+        assert_eq!(
+            live_tokens,
+            vec!["cake:diffserv4".to_string(), "/".to_string()]
+        );
+        assert_eq!(active_experiments, 0);
+        assert!(state.active_profile.is_none());
+        let last_activity = activity.back().expect("activity should be recorded");
+        assert_eq!(
+            last_activity.action,
+            "sqm_experiment_pending:cake:diffserv4"
+        );
+        assert!(!last_activity.persisted);
+    }
+
+    #[test]
+    fn adaptive_sqm_tick_starts_and_reverts_unhelpful_profile() {
+        let devices = vec![ShapedDevice {
+            circuit_id: "circuit-9".to_string(),
+            circuit_name: "Circuit Nine".to_string(),
+            device_id: "device-9".to_string(),
+            ..ShapedDevice::default()
+        }];
+        let cfg = lqos_config::TreeguardAdaptiveSqmConfig {
+            enabled: true,
+            ..Default::default()
+        };
+        let mut status = empty_status_snapshot();
+        let mut activity: VecDeque<TreeguardActivityEntry> = VecDeque::new();
+        let mut state = crate::treeguard::state::AdaptiveSqmState::default();
+        let mut active_experiments = 0usize;
+        let mut budget = 8usize;
+        let mut live_tokens: Vec<String> = Vec::new();
+        // 80 Mbps of 100 Mbps down is "loaded" for the default 50% threshold.
+        let loaded_bps = DownUpOrder {
+            down: 10_000_000,
+            up: 0,
+        };
+
+        let mut tick =
+            |now_unix: u64, qoo: f32, state: &mut crate::treeguard::state::AdaptiveSqmState| {
+                process_adaptive_sqm_tick(
+                    AdaptiveSqmTickContext {
+                        status: &mut status,
+                        activity: &mut activity,
+                        now_unix,
+                        dry_run: false,
+                        circuit_id: "circuit-9",
+                        circuit_entity_id: "Circuit Nine (circuit-9)",
+                        circuit_label: "Circuit Nine",
+                        devices: &devices,
+                        cap_down: 100.0,
+                        cap_up: 20.0,
+                        bps: loaded_bps,
+                        qoo: DownUpOrder {
+                            down: Some(qoo),
+                            up: None,
+                        },
+                        cfg: &cfg,
+                        persist_sqm_overrides: true,
+                        active_experiments: &mut active_experiments,
+                        circuit_change_budget_remaining: &mut budget,
+                    },
+                    state,
+                    |_site, _device_ids, _token| panic!("reverted experiments must not persist"),
+                    |_circuit_id, _devices, token| {
+                        live_tokens.push(token.to_string());
+                        Ok(())
+                    },
+                )
+            };
+
+        assert!(!tick(1_000, 40.0, &mut state));
+        assert!(tick(1_300, 40.0, &mut state));
+        // No improvement after the evaluation window: restore the base SQM.
+        assert!(tick(1_600, 41.0, &mut state));
+        assert!(!tick(2_200, 42.0, &mut state));
+
+        assert_eq!(
+            live_tokens,
+            vec!["cake:diffserv4".to_string(), "/".to_string()]
+        );
+        assert_eq!(active_experiments, 0);
+        assert_eq!(state.tried_profiles, vec!["cake:diffserv4".to_string()]);
+        assert!(state.active_profile.is_none());
+        let actions: Vec<&str> = activity.iter().map(|entry| entry.action.as_str()).collect();
+        assert_eq!(
+            actions,
+            vec![
+                "sqm_experiment_started:cake:diffserv4",
+                "sqm_experiment_reverted:cake:diffserv4"
+            ]
+        );
+    }
+
     #[test]
     fn circuit_tick_snapshot_decides_and_applies_live_override() {
         let (tx, rx) = bounded(1);
@@ -3864,8 +4876,8 @@ mod tests {
                 deferred_circuit_sqm_changes: &mut deferred_circuit_sqm_changes,
             },
             &mut state,
-            |_device_ids, _token| Ok(false),
-            |_device_ids| Ok(false),
+            |_site, _device_ids, _token| Ok(SubmitOutcome::Applied { changed: false }),
+            |_site, _device_ids| Ok(SubmitOutcome::Applied { changed: false }),
             |circuit_id, devices, token| {
                 bakery::apply_circuit_sqm_override_live_with_sender_and_snapshot(
                     circuit_id, devices, token, &tx, &queues,
@@ -3973,8 +4985,8 @@ mod tests {
                 deferred_circuit_sqm_changes: &mut deferred_circuit_sqm_changes,
             },
             &mut state,
-            |_device_ids, _token| Ok(false),
-            |_device_ids| Ok(false),
+            |_site, _device_ids, _token| Ok(SubmitOutcome::Applied { changed: false }),
+            |_site, _device_ids| Ok(SubmitOutcome::Applied { changed: false }),
             |_circuit_id, _devices, _token| {
                 Err(TreeguardError::LiveMutationUnavailable {
                     details: "the shaping tree is not currently active".to_string(),
@@ -167,6 +167,37 @@ pub(crate) fn submit_node_virtualization_live(
         })
 }
 
+/// Submits a lossless live move of a top-level site (and its subtree) to another queue.
+///
+/// This function has side effects: it sends a command to the Bakery thread.
+pub(crate) fn submit_top_level_site_move_live(
+    node_name: &str,
+    target_queue: u32,
+) -> Result<(), TreeguardError> {
+    if let Some(details) = lqos_bakery::bakery_live_tree_mutation_blocker() {
+        return Err(TreeguardError::LiveMutationUnavailable { details });
+    }
+    let Some(sender) = lqos_bakery::BAKERY_SENDER.get() else {
+        return Err(TreeguardError::BakeryNotReady);
+    };
+
+    let site_hash = hash_to_i64(node_name);
+    sender
+        .send(BakeryCommands::TreeGuardMoveTopLevelSite {
+            site_hash,
+            target_queue,
+            reply: None,
+        })
+        .map_err(|e| TreeguardError::BakerySend {
+            details: e.to_string(),
+        })
+}
+
+/// Returns the current queue of each movable top-level site Bakery knows about, keyed by hash.
+pub(crate) fn top_level_site_queues() -> std::collections::HashMap<i64, u32> {
+    lqos_bakery::bakery_top_level_site_queues()
+}
+
 /// Returns the latest Bakery runtime-node operation snapshot for a node, if Bakery has processed one.
 pub(crate) fn node_virtualization_operation_status(
     node_name: &str,
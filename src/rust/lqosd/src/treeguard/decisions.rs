@@ -3,10 +3,12 @@
 //! Functions in this module should be pure: they must not perform I/O, mutate globals,
 //! or have side effects beyond returning a decision.
 
-use crate::treeguard::state::{CircuitSqmState, CircuitState, LinkState, LinkVirtualState};
+use crate::treeguard::state::{
+    AdaptiveSqmState, CircuitSqmState, CircuitState, LinkState, LinkVirtualState, RebalanceState,
+};
 use lqos_config::{
-    TreeguardCircuitsConfig, TreeguardCpuConfig, TreeguardCpuMode, TreeguardLinksConfig,
-    TreeguardQooConfig,
+    TreeguardAdaptiveSqmConfig, TreeguardCircuitsConfig, TreeguardCpuConfig, TreeguardCpuMode,
+    TreeguardLinksConfig, TreeguardQooConfig, TreeguardRebalanceConfig,
 };
 use lqos_utils::units::DownUpOrder;
 
@@ -75,6 +77,39 @@ pub struct CircuitSqmInput<'a> {
     pub state: &'a CircuitState,
 }
 
+/// Input to top-level CPU rebalancing decisions.
+#[derive(Clone, Copy, Debug)]
+pub struct RebalanceTriggerInput<'a> {
+    pub now_unix: u64,
+    /// Highest and spread (max - min) softirq load across shaping CPUs, if sampled.
+    pub softirq_max_and_spread_pct: Option<(u8, u8)>,
+    pub cfg: &'a TreeguardRebalanceConfig,
+    pub state: &'a RebalanceState,
+}
+
+/// An adaptive SQM decision for a single circuit.
+#[derive(Clone, Debug, PartialEq)]
+pub enum AdaptiveSqmDecision {
+    /// Nothing to do this tick.
+    NoChange,
+    /// Start an experiment with the given CAKE profile.
+    Start { profile: String },
+    /// The running experiment improved QoO; keep its profile.
+    Keep { after_qoo: f32 },
+    /// The running experiment did not help (or cannot be judged); restore the previous SQM.
+    Revert { after_qoo: Option<f32> },
+}
+
+/// Input to per-circuit adaptive SQM decisions.
+#[derive(Clone, Copy, Debug)]
+pub struct AdaptiveSqmInput<'a> {
+    pub now_unix: u64,
+    pub cfg: &'a TreeguardAdaptiveSqmConfig,
+    /// Number of circuits currently running an experiment.
+    pub active_experiments: usize,
+    pub state: &'a AdaptiveSqmState,
+}
+
 /// Returns true if CPU pressure permits taking CPU-saving actions.
 ///
 /// This function is pure: it has no side effects.
@@ -128,6 +163,106 @@ fn rate_limited(recent_changes: usize, max_changes_per_hour: u32) -> bool {
     recent_changes >= max_changes_per_hour as usize
 }
 
+/// Returns true if softirq load across shaping CPUs is currently imbalanced.
+///
+/// This function is pure: it has no side effects.
+pub fn softirq_imbalanced(
+    cfg: &TreeguardRebalanceConfig,
+    max_and_spread: Option<(u8, u8)>,
+) -> bool {
+    max_and_spread
+        .is_some_and(|(max, spread)| max >= cfg.min_hot_cpu_pct && spread >= cfg.imbalance_pct)
+}
+
+/// Decide whether a top-level CPU rebalance run should happen now.
+///
+/// A run requires the imbalance to have persisted for `sustain_minutes` and the previous run to
+/// be at least `min_minutes_between_runs` old.
+///
+/// This function is pure: it has no side effects.
+pub fn decide_rebalance(input: RebalanceTriggerInput<'_>) -> bool {
+    let RebalanceTriggerInput {
+        now_unix,
+        softirq_max_and_spread_pct,
+        cfg,
+        state,
+    } = input;
+    if !cfg.enabled || !softirq_imbalanced(cfg, softirq_max_and_spread_pct) {
+        return false;
+    }
+    let Some(since) = state.imbalance_since_unix else {
+        return false;
+    };
+    let sustain_secs = u64::from(cfg.sustain_minutes).saturating_mul(60);
+    if now_unix.saturating_sub(since) < sustain_secs {
+        return false;
+    }
+    !in_dwell_window(now_unix, state.last_run_unix, cfg.min_minutes_between_runs)
+}
+
+/// Decide whether to start, keep, or revert an adaptive SQM profile experiment.
+///
+/// A running experiment is judged after `evaluation_minutes`: it is kept only if its mean loaded
+/// QoO beats the baseline by at least `min_improvement`. A new experiment starts once QoO has
+/// stayed poor under load for `sustain_minutes`, using the first configured profile that has not
+/// been tried yet.
+///
+/// This function is pure: it has no side effects.
+pub fn decide_adaptive_sqm(input: AdaptiveSqmInput<'_>) -> AdaptiveSqmDecision {
+    let AdaptiveSqmInput {
+        now_unix,
+        cfg,
+        active_experiments,
+        state,
+    } = input;
+
+    if let Some(experiment) = state.experiment.as_ref() {
+        let after_qoo = experiment.mean_qoo();
+        if !cfg.enabled {
+            return AdaptiveSqmDecision::Revert { after_qoo };
+        }
+        if in_dwell_window(
+            now_unix,
+            Some(experiment.started_unix),
+            cfg.evaluation_minutes,
+        ) {
+            return AdaptiveSqmDecision::NoChange;
+        }
+        return match after_qoo {
+            Some(after) if after >= experiment.baseline_qoo + cfg.min_improvement => {
+                AdaptiveSqmDecision::Keep { after_qoo: after }
+            }
+            _ => AdaptiveSqmDecision::Revert { after_qoo },
+        };
+    }
+
+    if !cfg.enabled
+        || active_experiments >= cfg.max_active_experiments as usize
+        || state.retry_after_unix.is_some_and(|t| now_unix < t)
+        || state.loaded_qoo_ewma.current().is_none()
+    {
+        return AdaptiveSqmDecision::NoChange;
+    }
+    let Some(poor_since) = state.poor_since_unix else {
+        return AdaptiveSqmDecision::NoChange;
+    };
+    if in_dwell_window(now_unix, Some(poor_since), cfg.sustain_minutes) {
+        return AdaptiveSqmDecision::NoChange;
+    }
+
+    cfg.profiles
+        .iter()
+        .find(|profile| {
+            !state.tried_profiles.contains(profile)
+                && state.active_profile.as_ref() != Some(*profile)
+        })
+        .map_or(AdaptiveSqmDecision::NoChange, |profile| {
+            AdaptiveSqmDecision::Start {
+                profile: profile.clone(),
+            }
+        })
+}
+
 /// Decide whether to virtualize/unvirtualize a managed node.
 ///
 /// This function is pure: it has no side effects.
@@ -410,7 +545,7 @@ pub fn parse_directional_sqm_override(token: &str) -> DownUpOrder<Option<Circuit
         if t.is_empty() || t.eq_ignore_ascii_case("none") {
             return None;
         }
-        if t.eq_ignore_ascii_case("cake") {
+        if t.eq_ignore_ascii_case("cake") || t.to_ascii_lowercase().starts_with("cake:") {
             return Some(CircuitSqmState::Cake);
         }
         if t.eq_ignore_ascii_case("fq_codel") {
@@ -441,9 +576,137 @@ pub fn parse_directional_sqm_override(token: &str) -> DownUpOrder<Option<Circuit
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::treeguard::state::{CircuitDirectionState, CircuitState, LinkState};
+    use crate::treeguard::state::{CircuitDirectionState, CircuitState, LinkState, SqmExperiment};
     use std::collections::VecDeque;
 
+    #[test]
+    fn rebalance_requires_sustained_imbalance_and_run_spacing() {
+        let cfg = TreeguardRebalanceConfig {
+            enabled: true,
+            ..Default::default()
+        };
+        let now = 1_000_000u64;
+        let mut state = RebalanceState {
+            imbalance_since_unix: Some(now - 599),
+            ..Default::default()
+        };
+        let decide = |state: &RebalanceState, pct: Option<(u8, u8)>| {
+            decide_rebalance(RebalanceTriggerInput {
+                now_unix: now,
+                softirq_max_and_spread_pct: pct,
+                cfg: &cfg,
+                state,
+            })
+        };
+
+        // 9:59 of imbalance is not enough for the default 10 minutes.
+        assert!(!decide(&state, Some((80, 40))));
+        state.imbalance_since_unix = Some(now - 600);
+        assert!(decide(&state, Some((80, 40))));
+        // A cool busiest CPU or a small spread is not an imbalance.
+        assert!(!decide(&state, Some((40, 40))));
+        assert!(!decide(&state, Some((80, 10))));
+        assert!(!decide(&state, None));
+
+        state.last_run_unix = Some(now - 60);
+        assert!(!decide(&state, Some((80, 40))));
+    }
+
+    #[test]
+    fn adaptive_sqm_starts_after_sustained_poor_qoo() {
+        let cfg = TreeguardAdaptiveSqmConfig {
+            enabled: true,
+            ..Default::default()
+        };
+        let now = 1_000_000u64;
+        let mut state = AdaptiveSqmState::default();
+        state.observe(now - 299, Some(40.0), true, cfg.poor_qoo_score);
+        let decide = |state: &AdaptiveSqmState, active: usize| {
+            decide_adaptive_sqm(AdaptiveSqmInput {
+                now_unix: now,
+                cfg: &cfg,
+                active_experiments: active,
+                state,
+            })
+        };
+
+        // 4:59 of poor QoO is not enough for the default 5 minutes.
+        assert_eq!(decide(&state, 0), AdaptiveSqmDecision::NoChange);
+        state.poor_since_unix = Some(now - 300);
+        assert_eq!(
+            decide(&state, 0),
+            AdaptiveSqmDecision::Start {
+                profile: "cake:diffserv4".to_string()
+            }
+        );
+        // Tried profiles are skipped; the experiment budget is respected.
+        state.tried_profiles.push("cake:diffserv4".to_string());
+        assert_eq!(
+            decide(&state, 0),
+            AdaptiveSqmDecision::Start {
+                profile: "cake:besteffort".to_string()
+            }
+        );
+        assert_eq!(decide(&state, 8), AdaptiveSqmDecision::NoChange);
+        state.retry_after_unix = Some(now + 1);
+        assert_eq!(decide(&state, 0), AdaptiveSqmDecision::NoChange);
+
+        // An unloaded sample resets the poor window.
+        state.observe(now, Some(40.0), false, cfg.poor_qoo_score);
+        assert!(state.poor_since_unix.is_none());
+    }
+
+    #[test]
+    fn adaptive_sqm_keeps_only_profiles_that_improve_qoo() {
+        let cfg = TreeguardAdaptiveSqmConfig {
+            enabled: true,
+            ..Default::default()
+        };
+        let now = 1_000_000u64;
+        let mut state = AdaptiveSqmState {
+            experiment: Some(SqmExperiment {
+                profile: "cake:besteffort".to_string(),
+                previous_token: "/".to_string(),
+                started_unix: now - 900,
+                baseline_qoo: 50.0,
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+        let decide = |state: &AdaptiveSqmState| {
+            decide_adaptive_sqm(AdaptiveSqmInput {
+                now_unix: now,
+                cfg: &cfg,
+                active_experiments: 1,
+                state,
+            })
+        };
+
+        // No loaded samples: the experiment cannot be judged, so it is reverted.
+        assert_eq!(
+            decide(&state),
+            AdaptiveSqmDecision::Revert { after_qoo: None }
+        );
+        state.observe(now, Some(54.0), true, cfg.poor_qoo_score);
+        assert_eq!(
+            decide(&state),
+            AdaptiveSqmDecision::Revert {
+                after_qoo: Some(54.0)
+            }
+        );
+        state.observe(now, Some(62.0), true, cfg.poor_qoo_score);
+        assert_eq!(
+            decide(&state),
+            AdaptiveSqmDecision::Keep { after_qoo: 58.0 }
+        );
+
+        // Still inside the evaluation window.
+        if let Some(experiment) = state.experiment.as_mut() {
+            experiment.started_unix = now - 899;
+        }
+        assert_eq!(decide(&state), AdaptiveSqmDecision::NoChange);
+    }
+
     #[test]
     fn link_decision_requires_allowlist() {
         let cpu = TreeguardCpuConfig::default();
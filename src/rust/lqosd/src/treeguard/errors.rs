@@ -9,10 +9,6 @@ pub enum TreeguardError {
     #[error("failed to spawn TreeGuard actor thread: {0}")]
     SpawnThread(#[from] std::io::Error),
 
-    /// The overrides file could not be loaded.
-    #[error("failed to load overrides file: {details}")]
-    OverridesLoad { details: String },
-
     /// The overrides file could not be saved.
     #[error("failed to save overrides file: {details}")]
     OverridesSave { details: String },
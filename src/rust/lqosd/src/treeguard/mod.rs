@@ -2,14 +2,16 @@
 //!
 //! This module provides an actor-driven control loop that can:
 //! - monitor link utilization and RTT,
-//! - virtualize/unvirtualize selected network nodes, and
-//! - adjust per-circuit shaping behavior to reduce CPU load.
+//! - virtualize/unvirtualize selected network nodes,
+//! - adjust per-circuit shaping behavior to reduce CPU load, and
+//! - rebalance top-level sites across CPU queues when softirq load is uneven.
 
 pub(crate) mod actor;
 pub(crate) mod bakery;
 pub(crate) mod decisions;
 pub(crate) mod errors;
 pub(crate) mod overrides;
+pub(crate) mod rebalance;
 pub(crate) mod state;
 pub(crate) mod status;
 
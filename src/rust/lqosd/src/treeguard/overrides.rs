@@ -1,104 +1,96 @@
 //! Persistence helpers for TreeGuard.
 //!
-//! This module writes TreeGuard-owned changes to `lqos_overrides.treeguard.json`.
+//! This module writes TreeGuard-owned changes to `lqos_overrides.treeguard.json`. Writes are
+//! submitted through the change-control queue, so when approval is required they may be held
+//! in `lqos_overrides.pending.json` until an administrator approves them.
 
 use crate::treeguard::TreeguardError;
-use lqos_overrides::{OverrideLayer, OverrideStore};
+use lqos_overrides::{ChangeProposal, OverrideLayer, OverrideStore, ProposedChange, SubmitOutcome};
+
+/// Submits a TreeGuard change through change control.
+fn submit(
+    site: Option<String>,
+    summary: String,
+    change: ProposedChange,
+) -> Result<SubmitOutcome, TreeguardError> {
+    OverrideStore::submit_change(ChangeProposal {
+        layer: OverrideLayer::Treeguard,
+        site,
+        summary,
+        magnitude_pct: None,
+        change,
+    })
+    .map_err(|e| TreeguardError::OverridesSave {
+        details: e.to_string(),
+    })
+}
 
 /// Removes any node-virtualization overrides for a `network.json` node.
 ///
 /// This function is not pure: it reads and writes `lqos_overrides.treeguard.json`.
 ///
-/// Returns `Ok(true)` if the file was changed, `Ok(false)` if no change was needed.
-pub(crate) fn clear_node_virtual(node_name: &str) -> Result<bool, TreeguardError> {
-    let mut overrides = OverrideStore::load_layer(OverrideLayer::Treeguard).map_err(|e| {
-        TreeguardError::OverridesLoad {
-            details: e.to_string(),
-        }
-    })?;
-
-    let removed = overrides.remove_network_node_virtual_by_name_count(node_name);
-    if removed == 0 {
-        return Ok(false);
-    }
+/// Returns [`SubmitOutcome::Queued`] when the change is awaiting approval; nothing has been
+/// written yet in that case.
+pub(crate) fn clear_node_virtual(node_name: &str) -> Result<SubmitOutcome, TreeguardError> {
+    submit(
+        Some(node_name.to_string()),
+        format!("TreeGuard: clear virtual override on node '{node_name}'"),
+        ProposedChange::ClearNodeVirtual {
+            node_name: node_name.to_string(),
+        },
+    )
+}
 
-    OverrideStore::save_layer(OverrideLayer::Treeguard, &overrides).map_err(|e| {
-        TreeguardError::OverridesSave {
-            details: e.to_string(),
-        }
-    })?;
-    Ok(true)
+/// The `network.json` node a circuit hangs from, used as the change-control site of its SQM
+/// changes so `auto_approve_sites` can match them.
+pub(crate) fn circuit_site(devices: &[lqos_config::ShapedDevice]) -> Option<&str> {
+    devices.first().map(|device| device.parent_node.as_str())
 }
 
-/// Persists a per-device SQM override token for a list of devices.
+/// Persists a per-device SQM override token for a list of devices. `site` is the circuit's
+/// parent node, if known.
 ///
 /// This function is not pure: it reads and writes `lqos_overrides.treeguard.json`.
 ///
-/// Returns `Ok(true)` if the file was changed, `Ok(false)` if no change was needed.
+/// Returns [`SubmitOutcome::Queued`] when the change is awaiting approval; nothing has been
+/// written yet in that case.
 pub(crate) fn set_devices_sqm_override(
+    site: Option<&str>,
     device_ids: &[String],
     sqm_override: &str,
-) -> Result<bool, TreeguardError> {
-    let mut overrides = OverrideStore::load_layer(OverrideLayer::Treeguard).map_err(|e| {
-        TreeguardError::OverridesLoad {
-            details: e.to_string(),
-        }
-    })?;
-
-    let mut changed = false;
-    for device_id in device_ids {
-        if overrides.set_device_sqm_override_return_changed(
-            device_id.clone(),
-            Some(sqm_override.to_string()),
-        ) {
-            changed = true;
-        }
-        if overrides.remove_persistent_shaped_device_by_device_count(device_id) > 0 {
-            changed = true;
-        }
-    }
-
-    if !changed {
-        return Ok(false);
-    }
-
-    OverrideStore::save_layer(OverrideLayer::Treeguard, &overrides).map_err(|e| {
-        TreeguardError::OverridesSave {
-            details: e.to_string(),
-        }
-    })?;
-    Ok(true)
+) -> Result<SubmitOutcome, TreeguardError> {
+    submit(
+        site.map(str::to_string),
+        format!(
+            "TreeGuard: set SQM '{sqm_override}' on devices {}",
+            device_ids.join(", ")
+        ),
+        ProposedChange::SetDeviceSqm {
+            device_ids: device_ids.to_vec(),
+            sqm_override: sqm_override.to_string(),
+        },
+    )
 }
 
-/// Removes any persisted SQM override entries for a list of device IDs.
+/// Removes any persisted SQM override entries for a list of device IDs. `site` is the
+/// circuit's parent node, if known.
 ///
 /// This function is not pure: it reads and writes `lqos_overrides.treeguard.json`.
 ///
-/// Returns `Ok(true)` if the file was changed, `Ok(false)` if no change was needed.
-pub(crate) fn clear_device_overrides(device_ids: &[String]) -> Result<bool, TreeguardError> {
-    let mut overrides = OverrideStore::load_layer(OverrideLayer::Treeguard).map_err(|e| {
-        TreeguardError::OverridesLoad {
-            details: e.to_string(),
-        }
-    })?;
-
-    let mut removed_any = false;
-    for device_id in device_ids {
-        let removed_adjustments = overrides.remove_device_sqm_override_by_device_count(device_id);
-        let removed_devices = overrides.remove_persistent_shaped_device_by_device_count(device_id);
-        if removed_adjustments > 0 || removed_devices > 0 {
-            removed_any = true;
-        }
-    }
-
-    if !removed_any {
-        return Ok(false);
-    }
-
-    OverrideStore::save_layer(OverrideLayer::Treeguard, &overrides).map_err(|e| {
-        TreeguardError::OverridesSave {
-            details: e.to_string(),
-        }
-    })?;
-    Ok(true)
+/// Returns [`SubmitOutcome::Queued`] when the change is awaiting approval; nothing has been
+/// written yet in that case.
+pub(crate) fn clear_device_overrides(
+    site: Option<&str>,
+    device_ids: &[String],
+) -> Result<SubmitOutcome, TreeguardError> {
+    submit(
+        site.map(str::to_string),
+        format!(
+            "TreeGuard: clear SQM overrides on devices {}",
+            device_ids.join(", ")
+        ),
+        ProposedChange::ClearDeviceSqm {
+            device_ids: device_ids.to_vec(),
+        },
+    )
 }
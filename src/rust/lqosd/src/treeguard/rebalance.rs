@@ -0,0 +1,485 @@
+//! Top-level site CPU rebalancing for TreeGuard.
+//!
+//! TreeGuard samples per-CPU softirq load from `/proc/stat`. When shaping CPUs stay unevenly
+//! loaded, it asks the shared `StableGreedy` top-level planner for a better site-to-queue
+//! assignment and moves a small number of top-level sites live through Bakery.
+
+use fxhash::FxHashMap;
+use lqos_bakery::{queue_from_bin_name, shaping_cpu_for_queue, top_level_bin_name};
+use lqos_config::{
+    TopLevelPlannerItem, TopLevelPlannerMode, TopLevelPlannerParams, TreeguardRebalanceConfig,
+    plan_top_level_assignments,
+};
+use std::collections::BTreeMap;
+use std::path::Path;
+
+const PROC_STAT_PATH: &str = "/proc/stat";
+const PLANNER_STATE_FILENAME: &str = "planner_state.json";
+
+/// Cumulative softirq and total jiffies for a single CPU.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct CpuSoftirqTimes {
+    pub softirq: u64,
+    pub total: u64,
+}
+
+/// Parses per-CPU softirq counters from the contents of `/proc/stat`, keyed by CPU index.
+///
+/// The total only counts the `user nice system idle iowait irq softirq steal` columns, since
+/// guest time is already included in `user`/`nice`.
+///
+/// This function is pure: it has no side effects.
+pub fn parse_proc_stat_softirq(text: &str) -> BTreeMap<usize, CpuSoftirqTimes> {
+    let mut result = BTreeMap::new();
+    for line in text.lines() {
+        let mut fields = line.split_whitespace();
+        let Some(cpu) = fields
+            .next()
+            .and_then(|label| label.strip_prefix("cpu"))
+            .and_then(|index| index.parse::<usize>().ok())
+        else {
+            continue;
+        };
+        let values: Vec<u64> = fields
+            .take(8)
+            .map_while(|value| value.parse::<u64>().ok())
+            .collect();
+        if values.len() < 7 {
+            continue;
+        }
+        result.insert(
+            cpu,
+            CpuSoftirqTimes {
+                softirq: values[6],
+                total: values.iter().sum(),
+            },
+        );
+    }
+    result
+}
+
+/// Returns the softirq share (0-100) of each CPU between two `/proc/stat` samples.
+///
+/// CPUs missing from either sample, or without elapsed time, are omitted.
+///
+/// This function is pure: it has no side effects.
+pub fn softirq_pct_between(
+    previous: &BTreeMap<usize, CpuSoftirqTimes>,
+    current: &BTreeMap<usize, CpuSoftirqTimes>,
+) -> BTreeMap<usize, u8> {
+    current
+        .iter()
+        .filter_map(|(cpu, now)| {
+            let before = previous.get(cpu)?;
+            let total = now.total.checked_sub(before.total)?;
+            if total == 0 {
+                return None;
+            }
+            let softirq = now.softirq.saturating_sub(before.softirq).min(total);
+            let pct = (softirq as f64 * 100.0 / total as f64).round();
+            Some((*cpu, pct.clamp(0.0, 100.0) as u8))
+        })
+        .collect()
+}
+
+/// Maps each of the first `queue_count` queues to the CPU that services it, using Bakery's
+/// queue-to-CPU order over `shaping_cpus`. Queues whose CPU was not sampled are left out.
+///
+/// This function is pure: it has no side effects.
+pub fn queue_cpus(
+    queue_count: u32,
+    shaping_cpus: &[u32],
+    softirq_pct: &BTreeMap<usize, u8>,
+) -> BTreeMap<u32, usize> {
+    (1..=queue_count)
+        .filter_map(|queue| {
+            let cpu = shaping_cpu_for_queue(queue, shaping_cpus)? as usize;
+            softirq_pct.contains_key(&cpu).then_some((queue, cpu))
+        })
+        .collect()
+}
+
+/// Returns the softirq load (0-100) of the CPU servicing `queue`.
+///
+/// This function is pure: it has no side effects.
+pub fn queue_softirq_pct(
+    softirq_pct: &BTreeMap<usize, u8>,
+    queue_cpus: &BTreeMap<u32, usize>,
+    queue: u32,
+) -> u8 {
+    queue_cpus
+        .get(&queue)
+        .and_then(|cpu| softirq_pct.get(cpu))
+        .copied()
+        .unwrap_or(0)
+}
+
+/// Returns `(max, spread)` of softirq load across the CPUs that service queues.
+///
+/// This function is pure: it has no side effects.
+pub fn softirq_spread(
+    softirq_pct: &BTreeMap<usize, u8>,
+    queue_cpus: &BTreeMap<u32, usize>,
+) -> Option<(u8, u8)> {
+    let shaping = queue_cpus
+        .values()
+        .filter_map(|cpu| softirq_pct.get(cpu).copied());
+    let (min, max) = shaping.fold(None, |acc: Option<(u8, u8)>, pct| match acc {
+        None => Some((pct, pct)),
+        Some((min, max)) => Some((min.min(pct), max.max(pct))),
+    })?;
+    Some((max, max - min))
+}
+
+/// Samples `/proc/stat` and reports softirq load since the previous sample.
+#[derive(Debug, Default)]
+pub struct SoftirqSampler {
+    previous: Option<BTreeMap<usize, CpuSoftirqTimes>>,
+}
+
+impl SoftirqSampler {
+    /// Returns per-CPU softirq load since the last call, or `None` on the first call or if
+    /// `/proc/stat` cannot be read.
+    ///
+    /// This function is not pure: it reads `/proc/stat` and mutates `self`.
+    pub fn sample(&mut self) -> Option<BTreeMap<usize, u8>> {
+        let text = std::fs::read_to_string(PROC_STAT_PATH).ok()?;
+        let current = parse_proc_stat_softirq(&text);
+        let previous = self.previous.replace(current.clone())?;
+        Some(softirq_pct_between(&previous, &current))
+    }
+}
+
+/// A top-level site that may be moved between queues.
+#[derive(Clone, Debug, PartialEq)]
+pub struct RebalanceSite {
+    pub name: String,
+    pub queue: u32,
+    /// Relative load of the site, typically recent throughput in bytes/second.
+    pub weight: f64,
+}
+
+/// A planned move of a top-level site to another queue.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RebalanceMove {
+    pub name: String,
+    pub from_queue: u32,
+    pub to_queue: u32,
+}
+
+/// Input to [`plan_rebalance_moves`].
+#[derive(Clone, Copy, Debug)]
+pub struct RebalancePlanInput<'a> {
+    pub now_unix: u64,
+    pub sites: &'a [RebalanceSite],
+    /// Queues that may receive sites, and the CPU servicing each (see [`queue_cpus`]).
+    pub queue_cpus: &'a BTreeMap<u32, usize>,
+    pub softirq_pct: &'a BTreeMap<usize, u8>,
+    pub last_move_unix: &'a FxHashMap<String, u64>,
+    pub cfg: &'a TreeguardRebalanceConfig,
+}
+
+/// Plans top-level site moves that shift load from hotter queues to cooler ones.
+///
+/// Moves come from the `StableGreedy` planner, seeded with the current assignment. A move is kept
+/// only if the site is out of its cooldown and the target CPU has less softirq load than the
+/// source. At most `max_moves_per_run` moves are returned, heaviest sites first.
+///
+/// This function is pure: it has no side effects.
+pub fn plan_rebalance_moves(input: RebalancePlanInput<'_>) -> Vec<RebalanceMove> {
+    let RebalancePlanInput {
+        now_unix,
+        sites,
+        queue_cpus,
+        softirq_pct,
+        last_move_unix,
+        cfg,
+    } = input;
+    let sites: Vec<&RebalanceSite> = sites
+        .iter()
+        .filter(|site| queue_cpus.contains_key(&site.queue))
+        .collect();
+    if queue_cpus.len() < 2 || sites.len() < 2 {
+        return Vec::new();
+    }
+
+    let cooldown_seconds = u64::from(cfg.site_cooldown_minutes).saturating_mul(60);
+    let max_moves = cfg.max_moves_per_run as usize;
+    let bins: Vec<String> = queue_cpus.keys().copied().map(top_level_bin_name).collect();
+    let items: Vec<TopLevelPlannerItem> = sites
+        .iter()
+        .map(|site| TopLevelPlannerItem {
+            id: site.name.clone(),
+            weight: site.weight.max(1.0),
+        })
+        .collect();
+    let prev_assign: BTreeMap<String, String> = sites
+        .iter()
+        .map(|site| (site.name.clone(), top_level_bin_name(site.queue)))
+        .collect();
+    let last_change_ts: BTreeMap<String, f64> = sites
+        .iter()
+        .filter_map(|site| {
+            last_move_unix
+                .get(&site.name)
+                .map(|ts| (site.name.clone(), *ts as f64))
+        })
+        .collect();
+    let output = plan_top_level_assignments(
+        &items,
+        &bins,
+        &prev_assign,
+        &last_change_ts,
+        now_unix as f64,
+        &TopLevelPlannerParams {
+            mode: TopLevelPlannerMode::StableGreedy,
+            hysteresis_threshold: f64::from(cfg.hysteresis_pct) / 100.0,
+            cooldown_seconds: cooldown_seconds as f64,
+            move_budget_per_run: max_moves,
+        },
+    );
+
+    let cpu_pct = |queue: u32| queue_softirq_pct(softirq_pct, queue_cpus, queue);
+    // The planner falls back to a full greedy reassignment when everything shares a bin, so the
+    // cooldown and budget are enforced here as well.
+    let mut candidates: Vec<(&RebalanceSite, u32)> = output
+        .changed
+        .iter()
+        .filter_map(|name| {
+            let site = sites.iter().find(|site| &site.name == name)?;
+            let to_queue = output
+                .assignment
+                .get(name)
+                .and_then(|bin| queue_from_bin_name(bin))?;
+            Some((*site, to_queue))
+        })
+        .filter(|(site, to_queue)| {
+            *to_queue != site.queue && cpu_pct(*to_queue) < cpu_pct(site.queue)
+        })
+        .filter(|(site, _)| {
+            last_move_unix
+                .get(&site.name)
+                .is_none_or(|last| now_unix.saturating_sub(*last) >= cooldown_seconds)
+        })
+        .collect();
+    candidates.sort_by(|(a, _), (b, _)| {
+        b.weight
+            .partial_cmp(&a.weight)
+            .unwrap_or(std::cmp::Ordering::Equal)
+            .then_with(|| a.name.cmp(&b.name))
+    });
+    candidates
+        .into_iter()
+        .take(max_moves)
+        .map(|(site, to_queue)| RebalanceMove {
+            name: site.name.clone(),
+            from_queue: site.queue,
+            to_queue,
+        })
+        .collect()
+}
+
+/// Records completed moves in `planner_state.json` so the next full reload keeps them.
+///
+/// This function has side effects: it reads and rewrites `planner_state.json` in the LibreQoS
+/// directory.
+pub fn record_planner_moves(
+    lqos_directory: &str,
+    moves: &[RebalanceMove],
+    now_unix: u64,
+) -> anyhow::Result<()> {
+    if moves.is_empty() {
+        return Ok(());
+    }
+    let path = Path::new(lqos_directory).join(PLANNER_STATE_FILENAME);
+    let mut state = match std::fs::read_to_string(&path) {
+        Ok(raw) => serde_json::from_str::<serde_json::Value>(&raw)
+            .ok()
+            .filter(serde_json::Value::is_object)
+            .unwrap_or_else(|| serde_json::json!({})),
+        Err(_) => serde_json::json!({}),
+    };
+    apply_moves_to_planner_state(&mut state, moves, now_unix);
+
+    let tmp = path.with_extension("json.tmp");
+    std::fs::write(&tmp, serde_json::to_string_pretty(&state)?)?;
+    std::fs::rename(&tmp, &path)?;
+    Ok(())
+}
+
+/// Updates the `assignments` and `last_change_ts` maps of a planner state document.
+///
+/// This function is pure: it only mutates `state`.
+fn apply_moves_to_planner_state(
+    state: &mut serde_json::Value,
+    moves: &[RebalanceMove],
+    now_unix: u64,
+) {
+    let Some(root) = state.as_object_mut() else {
+        return;
+    };
+    for key in ["assignments", "last_change_ts"] {
+        if !root.get(key).is_some_and(serde_json::Value::is_object) {
+            root.insert(key.to_string(), serde_json::json!({}));
+        }
+    }
+    for mv in moves {
+        root["assignments"][mv.name.as_str()] = serde_json::json!(top_level_bin_name(mv.to_queue));
+        root["last_change_ts"][mv.name.as_str()] = serde_json::json!(now_unix as f64);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cfg() -> TreeguardRebalanceConfig {
+        TreeguardRebalanceConfig {
+            enabled: true,
+            ..Default::default()
+        }
+    }
+
+    fn site(name: &str, queue: u32, weight: f64) -> RebalanceSite {
+        RebalanceSite {
+            name: name.to_string(),
+            queue,
+            weight,
+        }
+    }
+
+    #[test]
+    fn parses_per_cpu_softirq_and_skips_aggregate_line() {
+        let text = "cpu  10 0 10 70 0 0 10 0 0 0\n\
+                    cpu0 1 0 1 7 0 0 1 0 0 0\n\
+                    cpu2 2 0 2 14 0 0 2 0 5 0\n\
+                    intr 12345\n";
+        let parsed = parse_proc_stat_softirq(text);
+        assert_eq!(parsed.len(), 2);
+        assert_eq!(
+            parsed.get(&0),
+            Some(&CpuSoftirqTimes {
+                softirq: 1,
+                total: 10
+            })
+        );
+        // Guest time is not double counted.
+        assert_eq!(parsed.get(&2).map(|t| t.total), Some(20));
+    }
+
+    #[test]
+    fn softirq_pct_uses_deltas_between_samples() {
+        let before = parse_proc_stat_softirq("cpu0 0 0 0 100 0 0 0 0\ncpu1 0 0 0 100 0 0 0 0\n");
+        let after = parse_proc_stat_softirq("cpu0 0 0 0 150 0 0 50 0\ncpu1 0 0 0 190 0 0 10 0\n");
+        let pct = softirq_pct_between(&before, &after);
+        assert_eq!(pct.get(&0), Some(&50));
+        assert_eq!(pct.get(&1), Some(&10));
+        assert_eq!(
+            softirq_spread(&pct, &queue_cpus(2, &[], &pct)),
+            Some((50, 40))
+        );
+        assert_eq!(
+            softirq_spread(&pct, &queue_cpus(1, &[], &pct)),
+            Some((50, 0))
+        );
+    }
+
+    #[test]
+    fn queues_follow_the_shaping_cpu_list() {
+        // CPUs 0 and 1 are excluded from shaping, so queue 1 runs on CPU 2.
+        let pct = BTreeMap::from([(0, 5), (1, 5), (2, 90), (3, 20)]);
+        let cpus = queue_cpus(3, &[2, 3], &pct);
+        assert_eq!(cpus, BTreeMap::from([(1, 2), (2, 3)]));
+        assert_eq!(queue_softirq_pct(&pct, &cpus, 1), 90);
+        assert_eq!(softirq_spread(&pct, &cpus), Some((90, 70)));
+
+        let sites = vec![
+            site("A", 1, 900.0),
+            site("B", 1, 800.0),
+            site("C", 2, 100.0),
+        ];
+        let moves = plan_rebalance_moves(RebalancePlanInput {
+            now_unix: 1_000_000,
+            sites: &sites,
+            queue_cpus: &cpus,
+            softirq_pct: &pct,
+            last_move_unix: &FxHashMap::default(),
+            cfg: &cfg(),
+        });
+        assert_eq!(moves.len(), 1);
+        assert_eq!((moves[0].from_queue, moves[0].to_queue), (1, 2));
+    }
+
+    #[test]
+    fn plans_move_from_hot_queue_to_cool_queue() {
+        let sites = vec![
+            site("A", 1, 900.0),
+            site("B", 1, 800.0),
+            site("C", 2, 100.0),
+        ];
+        let softirq = BTreeMap::from([(0, 90), (1, 20)]);
+        let moves = plan_rebalance_moves(RebalancePlanInput {
+            now_unix: 1_000_000,
+            sites: &sites,
+            queue_cpus: &queue_cpus(2, &[], &softirq),
+            softirq_pct: &softirq,
+            last_move_unix: &FxHashMap::default(),
+            cfg: &cfg(),
+        });
+        assert_eq!(moves.len(), 1);
+        assert_eq!(moves[0].from_queue, 1);
+        assert_eq!(moves[0].to_queue, 2);
+    }
+
+    #[test]
+    fn respects_site_cooldown_and_softirq_direction() {
+        let sites = vec![
+            site("A", 1, 900.0),
+            site("B", 1, 800.0),
+            site("C", 2, 100.0),
+        ];
+        let now = 1_000_000;
+        let mut recent = FxHashMap::default();
+        recent.insert("A".to_string(), now - 60);
+        recent.insert("B".to_string(), now - 60);
+        let softirq = BTreeMap::from([(0, 90), (1, 20)]);
+        let moves = plan_rebalance_moves(RebalancePlanInput {
+            now_unix: now,
+            sites: &sites,
+            queue_cpus: &queue_cpus(2, &[], &softirq),
+            softirq_pct: &softirq,
+            last_move_unix: &recent,
+            cfg: &cfg(),
+        });
+        assert!(moves.is_empty());
+
+        // Never move load onto a CPU that is already busier.
+        let inverted = BTreeMap::from([(0, 20), (1, 90)]);
+        let moves = plan_rebalance_moves(RebalancePlanInput {
+            now_unix: now,
+            sites: &sites,
+            queue_cpus: &queue_cpus(2, &[], &inverted),
+            softirq_pct: &inverted,
+            last_move_unix: &FxHashMap::default(),
+            cfg: &cfg(),
+        });
+        assert!(moves.is_empty());
+    }
+
+    #[test]
+    fn planner_state_records_assignment_and_timestamp() {
+        let mut state = serde_json::json!({"salt": "x", "assignments": {"A": "CpueQueue0"}});
+        apply_moves_to_planner_state(
+            &mut state,
+            &[RebalanceMove {
+                name: "A".to_string(),
+                from_queue: 1,
+                to_queue: 3,
+            }],
+            42,
+        );
+        assert_eq!(state["assignments"]["A"], "CpueQueue2");
+        assert_eq!(state["last_change_ts"]["A"], 42.0);
+        assert_eq!(state["salt"], "x");
+    }
+}
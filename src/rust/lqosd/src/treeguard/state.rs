@@ -3,6 +3,7 @@
 //! This module will hold per-node and per-circuit state such as dwell timers,
 //! last-seen timestamps, and smoothed telemetry.
 
+use fxhash::FxHashMap;
 use lqos_bakery::BakeryRuntimeNodeOperationFailureReason;
 use std::collections::VecDeque;
 
@@ -136,6 +137,89 @@ pub struct CircuitState {
     pub up: CircuitDirectionState,
 }
 
+/// Top-level CPU rebalancing tracking state.
+#[derive(Clone, Debug, Default)]
+pub struct RebalanceState {
+    /// When softirq load first became imbalanced (seconds since UNIX epoch), if currently imbalanced.
+    pub imbalance_since_unix: Option<u64>,
+    /// Last rebalance run (seconds since UNIX epoch), if any.
+    pub last_run_unix: Option<u64>,
+    /// Last move time (seconds since UNIX epoch) by top-level site name.
+    pub last_move_unix: FxHashMap<String, u64>,
+}
+
+/// An adaptive SQM profile experiment running on one circuit.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct SqmExperiment {
+    /// CAKE profile token under trial (e.g. `cake:besteffort`).
+    pub profile: String,
+    /// Live SQM token to restore if the profile does not help (`"/"` restores the base SQM).
+    pub previous_token: String,
+    /// When the experiment started (seconds since UNIX epoch).
+    pub started_unix: u64,
+    /// Loaded-QoO baseline measured before the experiment.
+    pub baseline_qoo: f32,
+    /// Sum of loaded-QoO samples taken during the experiment.
+    pub qoo_sum: f64,
+    /// Number of loaded-QoO samples taken during the experiment.
+    pub qoo_samples: u32,
+}
+
+impl SqmExperiment {
+    /// Returns the mean loaded QoO observed during the experiment, if any samples were taken.
+    ///
+    /// This function is pure: it has no side effects.
+    pub fn mean_qoo(&self) -> Option<f32> {
+        if self.qoo_samples == 0 {
+            None
+        } else {
+            Some((self.qoo_sum / f64::from(self.qoo_samples)) as f32)
+        }
+    }
+}
+
+/// Per-circuit adaptive SQM tracking state.
+#[derive(Clone, Debug, Default)]
+pub struct AdaptiveSqmState {
+    /// When loaded QoO first went below the poor threshold (seconds since UNIX epoch), if poor.
+    pub poor_since_unix: Option<u64>,
+    /// Smoothed QoO, sampled only while the circuit is loaded and no experiment is running.
+    pub loaded_qoo_ewma: Ewma,
+    /// The experiment currently running, if any.
+    pub experiment: Option<SqmExperiment>,
+    /// Profile kept by a previous experiment, if any.
+    pub active_profile: Option<String>,
+    /// Profiles already tried since the last retry cooldown.
+    pub tried_profiles: Vec<String>,
+    /// No new experiment starts before this time (seconds since UNIX epoch).
+    pub retry_after_unix: Option<u64>,
+}
+
+impl AdaptiveSqmState {
+    /// Records one QoO observation.
+    ///
+    /// Samples only count while the circuit is loaded; otherwise the poor-quality window resets.
+    ///
+    /// This function is not pure: it mutates `self`.
+    pub fn observe(&mut self, now_unix: u64, qoo: Option<f32>, loaded: bool, poor_score: f32) {
+        let (true, Some(score)) = (loaded, qoo) else {
+            self.poor_since_unix = None;
+            return;
+        };
+        if let Some(experiment) = self.experiment.as_mut() {
+            experiment.qoo_sum += f64::from(score);
+            experiment.qoo_samples = experiment.qoo_samples.saturating_add(1);
+            return;
+        }
+        self.loaded_qoo_ewma.update(f64::from(score), 0.1);
+        if score < poor_score {
+            self.poor_since_unix.get_or_insert(now_unix);
+        } else {
+            self.poor_since_unix = None;
+        }
+    }
+}
+
 /// Returns true if both directions have been idle for at least `idle_min_minutes`.
 ///
 /// This function is pure: it has no side effects.
@@ -26,6 +26,8 @@ pub async fn treeguard_status_snapshot() -> TreeguardStatusData {
             paused_for_bakery_reload: false,
             pause_reason: None,
             cpu_max_pct: None,
+            softirq_spread_pct: None,
+            sqm_experiments_active: 0,
             total_nodes: totals.total_nodes,
             total_circuits: totals.total_circuits,
             managed_nodes: 0,
@@ -64,6 +66,8 @@ pub async fn treeguard_status_snapshot() -> TreeguardStatusData {
         paused_for_bakery_reload: false,
         pause_reason: None,
         cpu_max_pct: None,
+        softirq_spread_pct: None,
+        sqm_experiments_active: 0,
         total_nodes: totals.total_nodes,
         total_circuits: totals.total_circuits,
         managed_nodes: if tg.links.all_nodes {
@@ -1,9 +1,16 @@
+//! Urgent issues shown prominently in the WebUI and sent to any configured notification
+//! sinks. When `notifications.persist_urgent_issues` is set, the list is saved to
+//! `<lqos_directory>/urgent_issues.json` after every change and restored at startup.
+
 use std::collections::VecDeque;
+use std::path::{Path, PathBuf};
 use std::sync::atomic::{AtomicU64, Ordering};
 
 use lqos_bus::{UrgentIssue, UrgentSeverity, UrgentSource};
 use parking_lot::Mutex;
+use tracing::{info, warn};
 
+use crate::notifications;
 use lqos_utils::unix_time::unix_now;
 
 static NEXT_ID: AtomicU64 = AtomicU64::new(0);
@@ -12,6 +19,7 @@ static URGENT: Mutex<VecDeque<UrgentIssue>> = Mutex::new(VecDeque::new());
 const MAX_ISSUES: usize = 100;
 const TTL_SECONDS: u64 = 24 * 60 * 60; // 24h
 const DEDUPE_WINDOW_SECONDS: u64 = 300; // 5 minutes
+const PERSIST_FILE: &str = "urgent_issues.json";
 
 fn now_unix() -> u64 {
     unix_now().unwrap_or_default()
@@ -54,6 +62,10 @@ pub fn submit(
         existing.ts = ts;
         existing.message = message;
         existing.context = context;
+        let issue = existing.clone();
+        persist(&guard);
+        drop(guard);
+        notifications::notify(&issue);
         return;
     }
 
@@ -67,8 +79,11 @@ pub fn submit(
         context,
         dedupe_key: Some(key),
     };
-    guard.push_back(issue);
+    guard.push_back(issue.clone());
     prune_expired(&mut guard);
+    persist(&guard);
+    drop(guard);
+    notifications::notify(&issue);
 }
 
 pub fn list() -> Vec<UrgentIssue> {
@@ -80,10 +95,15 @@ pub fn list() -> Vec<UrgentIssue> {
     v
 }
 
+pub fn get(id: u64) -> Option<UrgentIssue> {
+    URGENT.lock().iter().find(|i| i.id == id).cloned()
+}
+
 pub fn clear(id: u64) -> bool {
     let mut guard = URGENT.lock();
     if let Some(pos) = guard.iter().position(|i| i.id == id) {
         guard.remove(pos);
+        persist(&guard);
         true
     } else {
         false
@@ -93,4 +113,119 @@ pub fn clear(id: u64) -> bool {
 pub fn clear_all() {
     let mut guard = URGENT.lock();
     guard.clear();
+    persist(&guard);
+}
+
+/// Removes issues raised with `code` and `dedupe_key`, and tells notification sinks the
+/// condition has cleared with a `<code>_RESOLVED` notice. The notice is not stored.
+pub fn resolve(
+    source: UrgentSource,
+    severity: UrgentSeverity,
+    code: &str,
+    message: String,
+    context: Option<String>,
+    dedupe_key: &str,
+) {
+    {
+        let mut guard = URGENT.lock();
+        let before = guard.len();
+        guard.retain(|i| !(i.code == code && i.dedupe_key.as_deref() == Some(dedupe_key)));
+        if guard.len() != before {
+            persist(&guard);
+        }
+    }
+    notifications::notify(&UrgentIssue {
+        id: NEXT_ID.fetch_add(1, Ordering::Relaxed) + 1,
+        ts: now_unix(),
+        source,
+        severity,
+        code: format!("{code}_RESOLVED"),
+        message,
+        context,
+        dedupe_key: Some(format!("{dedupe_key}:resolved")),
+    });
+}
+
+/// Reloads issues saved by a previous run, dropping any that have expired. Restored issues
+/// are not re-sent to notification sinks.
+pub fn restore() {
+    let Some(path) = persist_path() else {
+        return;
+    };
+    let Ok(raw) = std::fs::read_to_string(&path) else {
+        return;
+    };
+    let issues: Vec<UrgentIssue> = match serde_json::from_str(&raw) {
+        Ok(issues) => issues,
+        Err(e) => {
+            warn!("Ignoring unreadable urgent issues file {path:?}: {e}");
+            return;
+        }
+    };
+    let mut guard = URGENT.lock();
+    guard.extend(issues);
+    guard.make_contiguous().sort_by_key(|i| i.ts);
+    prune_expired(&mut guard);
+    let max_id = guard.iter().map(|i| i.id).max().unwrap_or(0);
+    NEXT_ID.fetch_max(max_id, Ordering::Relaxed);
+    info!("Restored {} urgent issues from {path:?}", guard.len());
+}
+
+fn persist_path() -> Option<PathBuf> {
+    let config = lqos_config::load_config().ok()?;
+    config
+        .notifications
+        .persist_urgent_issues
+        .then(|| Path::new(&config.lqos_directory).join(PERSIST_FILE))
+}
+
+/// Saves the issue list if persistence is enabled. Called with the lock held so that
+/// concurrent writers cannot reorder saves.
+fn persist(issues: &VecDeque<UrgentIssue>) {
+    let Some(path) = persist_path() else {
+        return;
+    };
+    if let Err(e) = write_atomic(&path, issues) {
+        warn!("Unable to save urgent issues to {path:?}: {e}");
+    }
+}
+
+fn write_atomic(path: &Path, issues: &VecDeque<UrgentIssue>) -> std::io::Result<()> {
+    let json = serde_json::to_vec(issues)?;
+    let tmp = path.with_extension("json.tmp");
+    std::fs::write(&tmp, json)?;
+    std::fs::rename(&tmp, path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn persisted_issues_round_trip() {
+        let dir = std::env::temp_dir().join(format!("lqosd-urgent-test-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).expect("create temp dir");
+        let path = dir.join(PERSIST_FILE);
+
+        let issues: VecDeque<UrgentIssue> = (1..=3)
+            .map(|id| UrgentIssue {
+                id,
+                ts: 1_700_000_000 + id,
+                source: UrgentSource::System,
+                severity: UrgentSeverity::Warning,
+                code: format!("CODE_{id}"),
+                message: "message".to_string(),
+                context: None,
+                dedupe_key: Some(format!("CODE_{id}")),
+            })
+            .collect();
+        write_atomic(&path, &issues).expect("write");
+        assert!(!path.with_extension("json.tmp").exists());
+
+        let raw = std::fs::read_to_string(&path).expect("read");
+        let restored: Vec<UrgentIssue> = serde_json::from_str(&raw).expect("parse");
+        assert_eq!(restored, Vec::from(issues));
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
 }
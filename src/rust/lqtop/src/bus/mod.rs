@@ -17,8 +17,21 @@ pub enum BusMessage {
     DisableTopHosts,
     EnableLatencyHistogram(std::sync::mpsc::Sender<BusResponse>),
     DisableLatencyHistogram,
+    EnableNetworkTree(std::sync::mpsc::Sender<BusResponse>),
+    DisableNetworkTree,
+    EnableCircuits(std::sync::mpsc::Sender<BusResponse>),
+    /// Switch the circuit view between the circuit list (`None`) and one circuit's detail
+    SelectCircuit(Option<String>),
+    DisableCircuits,
+    EnableStatus(std::sync::mpsc::Sender<BusResponse>),
+    DisableStatus,
+    /// Send a one-off request the user has confirmed; the response goes to the sender
+    Action(BusRequest, std::sync::mpsc::Sender<BusResponse>),
 }
 
+/// Network maps, circuit lists and status are polled once per this many ticks
+const SLOW_POLL_TICKS: u64 = 10;
+
 /// The main loop for the bus.
 /// Spawns a separate task to handle the bus communication.
 pub async fn bus_loop(rx: Receiver<BusMessage>) {
@@ -40,11 +53,18 @@ async fn main_loop(mut rx: Receiver<BusMessage>) -> Result<()> {
     let mut collect_top_downloaders = None;
     let mut collect_top_flows = None;
     let mut collect_latency_histogram = None;
+    let mut collect_network_tree = None;
+    let mut collect_circuits = None;
+    let mut selected_circuit: Option<String> = None;
+    let mut collect_status = None;
+    let mut tick: u64 = 0;
 
     let mut bus_client = LibreqosBusClient::new().await?;
 
     loop {
         // See if there are any messages
+        let mut commands: Vec<BusRequest> = Vec::new();
+        let mut actions = Vec::new();
         while let Ok(msg) = rx.try_recv() {
             match msg {
                 BusMessage::EnableTotalThroughput(tx) => {
@@ -71,12 +91,44 @@ async fn main_loop(mut rx: Receiver<BusMessage>) -> Result<()> {
                 BusMessage::DisableLatencyHistogram => {
                     collect_latency_histogram = None;
                 }
+                BusMessage::EnableNetworkTree(tx) => {
+                    collect_network_tree = Some(tx);
+                    tick = 0;
+                }
+                BusMessage::DisableNetworkTree => {
+                    collect_network_tree = None;
+                }
+                BusMessage::EnableCircuits(tx) => {
+                    collect_circuits = Some(tx);
+                    selected_circuit = None;
+                    tick = 0;
+                }
+                BusMessage::SelectCircuit(circuit_id) => {
+                    if let Some(circuit_id) = &circuit_id {
+                        // Queue history is only gathered for watched circuits
+                        commands.push(BusRequest::WatchQueue(circuit_id.clone()));
+                    }
+                    selected_circuit = circuit_id;
+                    tick = 0;
+                }
+                BusMessage::DisableCircuits => {
+                    collect_circuits = None;
+                    selected_circuit = None;
+                }
+                BusMessage::EnableStatus(tx) => {
+                    collect_status = Some(tx);
+                    tick = 0;
+                }
+                BusMessage::DisableStatus => {
+                    collect_status = None;
+                }
+                BusMessage::Action(request, tx) => {
+                    actions.push((request, tx));
+                }
             }
         }
 
         // Perform actual bus collection
-        let mut commands: Vec<BusRequest> = Vec::new();
-
         if collect_total_throughput.is_some() {
             commands.push(BusRequest::GetCurrentThroughput);
         }
@@ -92,6 +144,30 @@ async fn main_loop(mut rx: Receiver<BusMessage>) -> Result<()> {
         if collect_latency_histogram.is_some() {
             commands.push(BusRequest::RttHistogram);
         }
+        if tick % SLOW_POLL_TICKS == 0 {
+            if collect_network_tree.is_some() || collect_status.is_some() {
+                commands.push(BusRequest::GetFullNetworkMap);
+            }
+            if collect_circuits.is_some() {
+                match &selected_circuit {
+                    Some(circuit_id) => {
+                        commands.push(BusRequest::GetCircuitById {
+                            circuit_id: circuit_id.clone(),
+                        });
+                        commands.push(BusRequest::GetRawQueueData(circuit_id.clone()));
+                    }
+                    None => commands.push(BusRequest::GetAllCircuits),
+                }
+                commands.push(BusRequest::GetRttExcludedCircuits);
+            }
+            if collect_status.is_some() {
+                commands.push(BusRequest::GetBakeryStats);
+                commands.push(BusRequest::GetStormguardStats);
+                commands.push(BusRequest::CheckSchedulerStatus);
+                commands.push(BusRequest::GetHaStatus);
+            }
+        }
+        tick += 1;
 
         // Send the requests and process replies
         for response in bus_client.request(commands).await? {
@@ -116,10 +192,40 @@ async fn main_loop(mut rx: Receiver<BusMessage>) -> Result<()> {
                         let _ = tx.send(response); // Ignoring the error, it's ok if the channel closed
                     }
                 }
+                BusResponse::NetworkMap(..) => {
+                    if let Some(tx) = &collect_network_tree {
+                        let _ = tx.send(response.clone());
+                    }
+                    if let Some(tx) = &collect_status {
+                        let _ = tx.send(response);
+                    }
+                }
+                BusResponse::CircuitData(..)
+                | BusResponse::RawQueueData(..)
+                | BusResponse::RttExcludedCircuits(..) => {
+                    if let Some(tx) = &collect_circuits {
+                        let _ = tx.send(response);
+                    }
+                }
+                BusResponse::BakeryActiveCircuits(..)
+                | BusResponse::StormguardStats(..)
+                | BusResponse::SchedulerStatus { .. }
+                | BusResponse::HaStatus(..) => {
+                    if let Some(tx) = &collect_status {
+                        let _ = tx.send(response);
+                    }
+                }
                 _ => {}
             }
         }
 
+        // Actions are sent on their own so that each reply reaches the widget that asked
+        for (request, tx) in actions {
+            for response in bus_client.request(vec![request]).await? {
+                let _ = tx.send(response);
+            }
+        }
+
         // Check if we should be quitting
         if SHOULD_EXIT.load(Ordering::Relaxed) {
             break;
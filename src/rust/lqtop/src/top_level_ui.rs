@@ -5,21 +5,47 @@
 //! It's designed to be the manager from which specific UI
 //! components are managed.
 
+use crate::widgets::circuits::Circuits;
 use crate::widgets::help::help_display;
 use crate::widgets::latency_histogram::LatencyHistogram;
+use crate::widgets::network_tree::NetworkTree;
+use crate::widgets::status::Status;
 use crate::{bus::BusMessage, widgets::*};
+use crossterm::event::KeyCode;
+use lqos_bus::BusResponse;
 use ratatui::prelude::*;
+use ratatui::widgets::Paragraph;
 use std::io::Stdout;
 use tokio::sync::mpsc::Sender;
 
 use self::{top_flows::TopFlows, top_hosts::TopHosts};
 
+/// A line of input the bottom bar is collecting
+enum Prompt {
+    /// Typing a filter; holds the filter to restore on Esc
+    Filter(String),
+    /// Typing the parameters of an action
+    Input {
+        prompt: String,
+        text: String,
+        parse: Box<dyn Fn(&str) -> Result<Action, String>>,
+    },
+    /// Waiting for `y` before sending an action
+    Confirm(Action),
+}
+
 pub struct TopUi {
     show_cpus: bool,
     show_throughput_sparkline: bool,
     bus_sender: Sender<BusMessage>,
     sparkline: NetworkSparkline,
     main_widget: Box<dyn TopWidget>,
+    filter: String,
+    prompt: Option<Prompt>,
+    /// The outcome of the last action, shown until the next keypress
+    message: Option<String>,
+    action_tx: std::sync::mpsc::Sender<BusResponse>,
+    action_rx: std::sync::mpsc::Receiver<BusResponse>,
 }
 
 impl TopUi {
@@ -27,16 +53,40 @@ impl TopUi {
     pub fn new(bus_sender: Sender<BusMessage>) -> Self {
         let mut main_widget = Box::new(TopHosts::new(bus_sender.clone()));
         main_widget.enable();
+        let (action_tx, action_rx) = std::sync::mpsc::channel();
         TopUi {
             show_cpus: true,
             show_throughput_sparkline: false,
             main_widget,
             bus_sender: bus_sender.clone(),
             sparkline: NetworkSparkline::new(bus_sender.clone()),
+            filter: String::new(),
+            prompt: None,
+            message: None,
+            action_tx,
+            action_rx,
         }
     }
 
+    /// True while the bottom bar is collecting input, so that every key belongs to it
+    pub fn prompting(&self) -> bool {
+        self.prompt.is_some()
+    }
+
+    fn switch_to(&mut self, mut widget: Box<dyn TopWidget>) {
+        self.main_widget.disable();
+        widget.set_filter(&self.filter);
+        widget.enable();
+        self.main_widget = widget;
+    }
+
     pub fn handle_keypress(&mut self, key: char) {
+        self.message = None;
+        if self.prompt.is_some() {
+            self.prompt_key(KeyCode::Char(key));
+            return;
+        }
+
         // Handle Mode Switches
         match key {
             'c' => self.show_cpus = !self.show_cpus,
@@ -48,22 +98,131 @@ impl TopUi {
                     self.sparkline.disable();
                 }
             }
-            'h' => {
-                self.main_widget.disable();
-                self.main_widget = Box::new(TopHosts::new(self.bus_sender.clone()));
-                self.main_widget.enable();
+            'h' => self.switch_to(Box::new(TopHosts::new(self.bus_sender.clone()))),
+            'f' => self.switch_to(Box::new(TopFlows::new(self.bus_sender.clone()))),
+            'l' => self.switch_to(Box::new(LatencyHistogram::new(self.bus_sender.clone()))),
+            't' => self.switch_to(Box::new(NetworkTree::new(self.bus_sender.clone()))),
+            'd' => self.switch_to(Box::new(Circuits::new(self.bus_sender.clone()))),
+            's' => self.switch_to(Box::new(Status::new(self.bus_sender.clone()))),
+            '/' => self.prompt = Some(Prompt::Filter(self.filter.clone())),
+            '<' => self.main_widget.sort(SortChange::Previous),
+            '>' => self.main_widget.sort(SortChange::Next),
+            'r' => self.main_widget.sort(SortChange::Reverse),
+            _ => match self.main_widget.action(key) {
+                Some(ActionPrompt::Confirm(action)) => self.prompt = Some(Prompt::Confirm(action)),
+                Some(ActionPrompt::Input { prompt, parse }) => {
+                    self.prompt = Some(Prompt::Input {
+                        prompt,
+                        text: String::new(),
+                        parse,
+                    })
+                }
+                None => self.main_widget.handle_key(KeyCode::Char(key)),
+            },
+        }
+    }
+
+    /// Pass navigation keys through to the main widget
+    pub fn handle_key(&mut self, key: KeyCode) {
+        self.message = None;
+        if self.prompt.is_some() {
+            self.prompt_key(key);
+        } else {
+            self.main_widget.handle_key(key);
+        }
+    }
+
+    fn prompt_key(&mut self, key: KeyCode) {
+        let Some(prompt) = self.prompt.take() else {
+            return;
+        };
+        self.prompt = match (prompt, key) {
+            (Prompt::Filter(previous), KeyCode::Esc) => {
+                self.set_filter(previous);
+                None
+            }
+            (Prompt::Filter(_), KeyCode::Enter) => None,
+            (Prompt::Filter(previous), KeyCode::Backspace) => {
+                let mut filter = self.filter.clone();
+                filter.pop();
+                self.set_filter(filter);
+                Some(Prompt::Filter(previous))
+            }
+            (Prompt::Filter(previous), KeyCode::Char(c)) => {
+                self.set_filter(format!("{}{}", self.filter, c.to_lowercase()));
+                Some(Prompt::Filter(previous))
+            }
+            (Prompt::Input { .. }, KeyCode::Esc) => {
+                self.message = Some("Cancelled".to_string());
+                None
+            }
+            (Prompt::Input { text, parse, .. }, KeyCode::Enter) => match parse(&text) {
+                Ok(action) => Some(Prompt::Confirm(action)),
+                Err(e) => {
+                    self.message = Some(e);
+                    None
+                }
+            },
+            (
+                Prompt::Input {
+                    prompt,
+                    mut text,
+                    parse,
+                },
+                KeyCode::Backspace,
+            ) => {
+                text.pop();
+                Some(Prompt::Input {
+                    prompt,
+                    text,
+                    parse,
+                })
+            }
+            (
+                Prompt::Input {
+                    prompt,
+                    mut text,
+                    parse,
+                },
+                KeyCode::Char(c),
+            ) => {
+                text.push(c);
+                Some(Prompt::Input {
+                    prompt,
+                    text,
+                    parse,
+                })
             }
-            'f' => {
-                self.main_widget.disable();
-                self.main_widget = Box::new(TopFlows::new(self.bus_sender.clone()));
-                self.main_widget.enable();
+            (Prompt::Confirm(action), KeyCode::Char('y' | 'Y')) => {
+                let _ = self
+                    .bus_sender
+                    .blocking_send(BusMessage::Action(action.request, self.action_tx.clone()));
+                self.message = Some("Sent...".to_string());
+                None
             }
-            'l' => {
-                self.main_widget.disable();
-                self.main_widget = Box::new(LatencyHistogram::new(self.bus_sender.clone()));
-                self.main_widget.enable();
+            (Prompt::Confirm(_), _) => {
+                self.message = Some("Cancelled".to_string());
+                None
             }
-            _ => {}
+            // Ignore other keys while typing
+            (prompt, _) => Some(prompt),
+        };
+    }
+
+    fn set_filter(&mut self, filter: String) {
+        self.main_widget.set_filter(&filter);
+        self.filter = filter;
+    }
+
+    /// The bottom bar: an open prompt, else the last action's outcome, else the filter
+    fn status_line(&self) -> Option<String> {
+        match &self.prompt {
+            Some(Prompt::Filter(_)) => Some(format!("Filter: {}_", self.filter)),
+            Some(Prompt::Input { prompt, text, .. }) => Some(format!("{prompt}{text}_")),
+            Some(Prompt::Confirm(action)) => Some(format!("{} [y/N]", action.question)),
+            None => self.message.clone().or_else(|| {
+                (!self.filter.is_empty()).then(|| format!("Filter: {} - [/] change", self.filter))
+            }),
         }
     }
 
@@ -105,6 +264,23 @@ impl TopUi {
         let final_region = constraints.len();
         constraints.push(Constraint::Fill(1));
 
+        while let Ok(response) = self.action_rx.try_recv() {
+            self.message = Some(match response {
+                BusResponse::Ack => "Done".to_string(),
+                BusResponse::Fail(e) => format!("Failed: {e}"),
+                BusResponse::PacketCollectionSession {
+                    session_id,
+                    countdown,
+                } => format!("Capture session {session_id} started ({countdown}s)"),
+                other => format!("{other:?}"),
+            });
+        }
+        let status_line = self.status_line();
+        let status_region = constraints.len();
+        if status_line.is_some() {
+            constraints.push(Constraint::Length(1));
+        }
+
         let main_layout = Layout::new(Direction::Vertical, constraints).split(frame.size());
 
         // Add Widgets
@@ -123,6 +299,13 @@ impl TopUi {
         self.main_widget.tick();
         self.main_widget.render_to_frame(frame);
 
+        if let Some(line) = status_line {
+            frame.render_widget(
+                Paragraph::new(line).style(Style::default().fg(Color::Yellow)),
+                main_layout[status_region],
+            );
+        }
+
         /*match self.main_widget {
             MainWidget::Hosts => {
                 frame.render_widget(top_hosts::hosts(), main_layout[final_region]);
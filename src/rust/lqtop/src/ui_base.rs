@@ -65,17 +65,14 @@ impl UiBase {
                     if key.kind == KeyEventKind::Press {
                         match key.code {
                             // Quit the program
-                            KeyCode::Char('q') => {
+                            KeyCode::Char('q') if !self.ui.prompting() => {
                                 self.quit_program();
                             }
-                            _ => {
-                                let char: Option<char> = match key.code {
-                                    KeyCode::Char(c) => Some(c),
-                                    _ => None,
-                                };
-                                if let Some(c) = char {
-                                    self.ui.handle_keypress(c);
-                                }
+                            KeyCode::Char(c) => {
+                                self.ui.handle_keypress(c);
+                            }
+                            code => {
+                                self.ui.handle_key(code);
                             }
                         }
                     }
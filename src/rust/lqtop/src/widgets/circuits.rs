@@ -0,0 +1,587 @@
+//! Circuit list, with a drill-down into one circuit's devices and CAKE tins.
+
+use super::network_tree::utilization_cell;
+use super::table_controls::{Selection, SortChange, SortKey, SortState, matches_filter};
+use super::{Action, ActionPrompt, TopWidget, capture_action};
+use crossterm::event::KeyCode;
+use lqos_bus::{BusRequest, BusResponse, Circuit, QueueStoreTransit};
+use lqos_utils::packet_scale::scale_bits;
+use ratatui::prelude::*;
+use ratatui::widgets::{Block, Borders, Cell, Row, Table};
+use std::collections::{HashMap, HashSet};
+
+/// CAKE `diffserv4` tin names, lowest priority first.
+const DIFFSERV4_TINS: [&str; 4] = ["Bulk", "Best Effort", "Video", "Voice"];
+
+/// Totals for one circuit in the list view.
+struct CircuitSummary {
+    circuit_id: String,
+    name: String,
+    parent_node: String,
+    ips: Vec<String>,
+    devices: usize,
+    bits_per_second: (u64, u64),
+    plan_mbps: (f32, f32),
+    worst_rtt: Option<f32>,
+}
+
+pub struct Circuits {
+    bus_link: tokio::sync::mpsc::Sender<crate::bus::BusMessage>,
+    rx: std::sync::mpsc::Receiver<BusResponse>,
+    tx: std::sync::mpsc::Sender<BusResponse>,
+    size: Rect,
+    summaries: Vec<CircuitSummary>,
+    filter: String,
+    sort: SortState<8>,
+    list: Selection,
+    /// Circuits lqosd leaves out of RTT tracking
+    excluded: HashSet<String>,
+    /// The circuit being drilled into, if any
+    selected: Option<String>,
+    devices: Vec<Circuit>,
+    device_selection: Selection,
+    queue: Option<Box<QueueStoreTransit>>,
+}
+
+impl TopWidget for Circuits {
+    fn enable(&mut self) {
+        self.bus_link
+            .blocking_send(crate::bus::BusMessage::EnableCircuits(self.tx.clone()))
+            .unwrap();
+    }
+
+    fn disable(&mut self) {
+        self.bus_link
+            .blocking_send(crate::bus::BusMessage::DisableCircuits)
+            .unwrap();
+    }
+
+    fn set_size(&mut self, size: Rect) {
+        self.size = size;
+    }
+
+    fn tick(&mut self) {
+        while let Ok(response) = self.rx.try_recv() {
+            match response {
+                BusResponse::CircuitData(circuits) => match &self.selected {
+                    Some(circuit_id) => {
+                        self.devices = circuits
+                            .into_iter()
+                            .filter(|c| c.circuit_id.as_ref() == Some(circuit_id))
+                            .collect();
+                        self.devices.sort_by(|a, b| {
+                            a.device_name.cmp(&b.device_name).then(a.ip.cmp(&b.ip))
+                        });
+                    }
+                    None => self.summaries = summarize(circuits),
+                },
+                BusResponse::RawQueueData(queue) => {
+                    if self.selected.is_some() {
+                        self.queue = queue;
+                    }
+                }
+                BusResponse::RttExcludedCircuits(circuit_ids) => {
+                    self.excluded = circuit_ids.into_iter().collect();
+                }
+                _ => {}
+            }
+        }
+    }
+
+    fn render_to_frame(&mut self, frame: &mut Frame) {
+        if self.selected.is_some() {
+            self.render_detail(frame);
+        } else {
+            self.render_list(frame);
+        }
+    }
+
+    fn handle_key(&mut self, key: KeyCode) {
+        let page = self.size.height.saturating_sub(2) as usize;
+        if self.selected.is_some() {
+            if matches!(key, KeyCode::Esc | KeyCode::Backspace | KeyCode::Left) {
+                self.select(None);
+            } else {
+                let keys = device_keys(&self.devices);
+                self.device_selection.handle_key(key, &keys, page);
+            }
+            return;
+        }
+        let keys = summary_keys(&self.visible());
+        if matches!(key, KeyCode::Enter | KeyCode::Right) {
+            if let Some(circuit_id) = self.list.selected(&keys) {
+                self.select(Some(circuit_id.to_string()));
+            }
+        } else {
+            self.list.handle_key(key, &keys, page);
+        }
+    }
+
+    fn set_filter(&mut self, filter: &str) {
+        self.filter = filter.to_string();
+    }
+
+    fn sort(&mut self, change: SortChange) {
+        self.sort.change(change);
+    }
+
+    fn action(&mut self, key: char) -> Option<ActionPrompt> {
+        if key == 'p' && self.selected.is_some() {
+            let keys = device_keys(&self.devices);
+            return self.device_selection.selected(&keys).map(capture_action);
+        }
+
+        let circuit_id = match &self.selected {
+            Some(circuit_id) => circuit_id.clone(),
+            None => {
+                let keys = summary_keys(&self.visible());
+                self.list.selected(&keys)?.to_string()
+            }
+        };
+        let name = self.circuit_name(&circuit_id);
+        match key {
+            'x' => {
+                let excluded = !self.excluded.contains(&circuit_id);
+                let question = if excluded {
+                    format!("Exclude {name} from RTT tracking?")
+                } else {
+                    format!("Track RTT for {name} again?")
+                };
+                Some(ActionPrompt::Confirm(Action {
+                    question,
+                    request: BusRequest::SetCircuitRttExcluded {
+                        circuit_id,
+                        excluded,
+                    },
+                }))
+            }
+            'o' => Some(ActionPrompt::Input {
+                prompt: format!("Temporary speed for {name} (down Mbps, up Mbps, minutes): "),
+                parse: Box::new(move |text: &str| parse_speed_override(&circuit_id, &name, text)),
+            }),
+            _ => None,
+        }
+    }
+}
+
+impl Circuits {
+    pub fn new(bus_link: tokio::sync::mpsc::Sender<crate::bus::BusMessage>) -> Self {
+        let (tx, rx) = std::sync::mpsc::channel::<BusResponse>();
+        Self {
+            bus_link,
+            rx,
+            tx,
+            size: Rect::default(),
+            summaries: Vec::new(),
+            filter: String::new(),
+            sort: SortState::new(5, true),
+            list: Selection::default(),
+            excluded: HashSet::new(),
+            selected: None,
+            devices: Vec::new(),
+            device_selection: Selection::default(),
+            queue: None,
+        }
+    }
+
+    fn select(&mut self, circuit_id: Option<String>) {
+        self.devices.clear();
+        self.device_selection = Selection::default();
+        self.queue = None;
+        self.selected = circuit_id.clone();
+        self.bus_link
+            .blocking_send(crate::bus::BusMessage::SelectCircuit(circuit_id))
+            .unwrap();
+    }
+
+    fn circuit_name(&self, circuit_id: &str) -> String {
+        self.summaries
+            .iter()
+            .find(|c| c.circuit_id == circuit_id)
+            .map(|c| c.name.clone())
+            .or_else(|| self.devices.first().and_then(|d| d.circuit_name.clone()))
+            .filter(|name| !name.is_empty())
+            .unwrap_or_else(|| circuit_id.to_string())
+    }
+
+    /// Circuits matching the filter (by name, parent, ID or IP), in display order
+    fn visible(&self) -> Vec<&CircuitSummary> {
+        let mut summaries: Vec<&CircuitSummary> = self
+            .summaries
+            .iter()
+            .filter(|c| {
+                let mut fields = vec![
+                    c.name.as_str(),
+                    c.parent_node.as_str(),
+                    c.circuit_id.as_str(),
+                ];
+                fields.extend(c.ips.iter().map(String::as_str));
+                matches_filter(&self.filter, &fields)
+            })
+            .collect();
+        self.sort.sort(&mut summaries, |c, column| match column {
+            0 => SortKey::Text(c.name.clone()),
+            1 => SortKey::Text(c.parent_node.clone()),
+            2 => SortKey::Number(c.devices as f64),
+            3 => SortKey::Number(utilization(c.bits_per_second.0, c.plan_mbps.0)),
+            4 => SortKey::Number(utilization(c.bits_per_second.1, c.plan_mbps.1)),
+            5 => SortKey::Number(c.bits_per_second.0 as f64),
+            6 => SortKey::Number(c.bits_per_second.1 as f64),
+            _ => SortKey::Number(c.worst_rtt.map_or(-1.0, f64::from)),
+        });
+        summaries
+    }
+
+    fn render_list(&mut self, frame: &mut Frame) {
+        let summaries = self.visible();
+        let keys = summary_keys(&summaries);
+        let rows: Vec<Row> = summaries
+            .iter()
+            .map(|c| {
+                let rtt = if self.excluded.contains(&c.circuit_id) {
+                    "excluded".to_string()
+                } else {
+                    format_ms(c.worst_rtt)
+                };
+                Row::new(vec![
+                    Cell::from(c.name.clone()),
+                    Cell::from(c.parent_node.clone()),
+                    Cell::from(c.devices.to_string()),
+                    utilization_cell(c.bits_per_second.0, c.plan_mbps.0 as f64),
+                    utilization_cell(c.bits_per_second.1, c.plan_mbps.1 as f64),
+                    Cell::from(scale_bits(c.bits_per_second.0)),
+                    Cell::from(scale_bits(c.bits_per_second.1)),
+                    Cell::from(rtt),
+                ])
+            })
+            .collect();
+        let widths = [
+            Constraint::Min(24),
+            Constraint::Length(20),
+            Constraint::Length(10),
+            Constraint::Length(16),
+            Constraint::Length(16),
+            Constraint::Length(14),
+            Constraint::Length(14),
+            Constraint::Length(10),
+        ];
+        let table = Table::new(rows, widths)
+            .header(header(self.sort.headings([
+                "Circuit",
+                "Parent",
+                "Devices",
+                "Down",
+                "Up",
+                "Down (bps)",
+                "Up (bps)",
+                "RTT",
+            ])))
+            .highlight_style(Style::default().add_modifier(Modifier::REVERSED))
+            .block(titled(
+                "Circuits - [Enter] details [x] RTT exclusion [o] temporary speed".to_string(),
+            ));
+        frame.render_stateful_widget(table, self.size, self.list.state(&keys));
+    }
+
+    fn render_detail(&mut self, frame: &mut Frame) {
+        let tins = self.queue.as_deref().and_then(latest_tins);
+        let tin_rows = tins.as_ref().map_or(1, |(down, up)| down.len() + up.len());
+        let layout = Layout::new(
+            Direction::Vertical,
+            [Constraint::Min(4), Constraint::Length(tin_rows as u16 + 3)],
+        )
+        .split(self.size);
+
+        let circuit_id = self.selected.clone().unwrap_or_default();
+        let name = self.circuit_name(&circuit_id);
+        let excluded = if self.excluded.contains(&circuit_id) {
+            " (RTT excluded)"
+        } else {
+            ""
+        };
+        let device_rows: Vec<Row> = self
+            .devices
+            .iter()
+            .map(|d| {
+                Row::new(vec![
+                    Cell::from(d.device_name.clone().unwrap_or_default()),
+                    Cell::from(d.ip.to_string()),
+                    Cell::from(scale_bits(d.bytes_per_second.down * 8)),
+                    Cell::from(scale_bits(d.bytes_per_second.up * 8)),
+                    Cell::from(format_ms(nanos_to_ms(d.rtt_current_p50_nanos.down))),
+                    Cell::from(format_ms(nanos_to_ms(d.rtt_current_p50_nanos.up))),
+                    Cell::from(format_percent(
+                        d.tcp_retransmit_sample.down.percent_0_to_100(),
+                    )),
+                    Cell::from(format_percent(
+                        d.tcp_retransmit_sample.up.percent_0_to_100(),
+                    )),
+                ])
+            })
+            .collect();
+        let widths = [
+            Constraint::Min(20),
+            Constraint::Length(26),
+            Constraint::Length(14),
+            Constraint::Length(14),
+            Constraint::Length(10),
+            Constraint::Length(10),
+            Constraint::Length(10),
+            Constraint::Length(10),
+        ];
+        let keys = device_keys(&self.devices);
+        let devices = Table::new(device_rows, widths)
+            .header(header([
+                "Device",
+                "IP",
+                "Down (bps)",
+                "Up (bps)",
+                "RTT Down",
+                "RTT Up",
+                "Retx Down",
+                "Retx Up",
+            ]))
+            .highlight_style(Style::default().add_modifier(Modifier::REVERSED))
+            .block(titled(format!(
+                "Circuit: {name}{excluded} - [Esc] back [p] capture packets [x] RTT exclusion [o] temporary speed"
+            )));
+        frame.render_stateful_widget(devices, layout[0], self.device_selection.state(&keys));
+
+        let mut rows = Vec::new();
+        if let Some((down, up)) = &tins {
+            for (direction, tins) in [("Down", down), ("Up", up)] {
+                for (i, tin) in tins.iter().enumerate() {
+                    let tin_name = if tins.len() == DIFFSERV4_TINS.len() {
+                        DIFFSERV4_TINS[i].to_string()
+                    } else {
+                        format!("Tin {i}")
+                    };
+                    rows.push(Row::new(vec![
+                        Cell::from(format!("{tin_name} {direction}")),
+                        Cell::from(tin.sent_bytes.to_string()),
+                        Cell::from(tin.backlog_bytes.to_string()),
+                        Cell::from(tin.drops.to_string()),
+                        Cell::from(tin.marks.to_string()),
+                        Cell::from(format!("{:.2} ms", tin.base_delay_us as f32 / 1000.0)),
+                    ]));
+                }
+            }
+        } else {
+            rows.push(Row::new(vec![Cell::from("No CAKE statistics yet")]));
+        }
+        let widths = [
+            Constraint::Min(18),
+            Constraint::Length(14),
+            Constraint::Length(14),
+            Constraint::Length(8),
+            Constraint::Length(8),
+            Constraint::Length(12),
+        ];
+        let kinds = self
+            .queue
+            .as_ref()
+            .map(|q| format!(" ({} / {})", q.kind_down, q.kind_up))
+            .unwrap_or_default();
+        let tins = Table::new(rows, widths)
+            .header(header([
+                "Tin",
+                "Sent (bytes)",
+                "Backlog (bytes)",
+                "Drops",
+                "Marks",
+                "Base Delay",
+            ]))
+            .block(titled(format!("Queue, last sample{kinds}")));
+        frame.render_widget(tins, layout[1]);
+    }
+}
+
+type Tins = Vec<lqos_bus::CakeDiffTinTransit>;
+
+/// The most recent download and upload tin samples, if the queue is CAKE and has history.
+fn latest_tins(queue: &QueueStoreTransit) -> Option<(Tins, Tins)> {
+    if queue.history.is_empty() {
+        return None;
+    }
+    let latest = (queue.history_head + queue.history.len() - 1) % queue.history.len();
+    let (down, up) = &queue.history[latest];
+    if down.tins.is_empty() && up.tins.is_empty() {
+        return None;
+    }
+    Some((down.tins.clone(), up.tins.clone()))
+}
+
+/// One row per circuit.
+fn summarize(circuits: Vec<Circuit>) -> Vec<CircuitSummary> {
+    let mut by_id: HashMap<String, CircuitSummary> = HashMap::new();
+    for device in circuits {
+        let Some(circuit_id) = device.circuit_id.clone() else {
+            continue;
+        };
+        let summary = by_id
+            .entry(circuit_id.clone())
+            .or_insert_with(|| CircuitSummary {
+                circuit_id,
+                name: device.circuit_name.clone().unwrap_or_default(),
+                parent_node: device.parent_node.clone().unwrap_or_default(),
+                ips: Vec::new(),
+                devices: 0,
+                bits_per_second: (0, 0),
+                plan_mbps: (device.plan.down, device.plan.up),
+                worst_rtt: None,
+            });
+        summary.devices += 1;
+        summary.ips.push(device.ip.to_string());
+        summary.bits_per_second.0 += device.bytes_per_second.down * 8;
+        summary.bits_per_second.1 += device.bytes_per_second.up * 8;
+        if let Some(rtt) = device.median_latency {
+            summary.worst_rtt = Some(summary.worst_rtt.map_or(rtt, |worst| worst.max(rtt)));
+        }
+    }
+    by_id.into_values().collect()
+}
+
+fn summary_keys(summaries: &[&CircuitSummary]) -> Vec<String> {
+    summaries.iter().map(|c| c.circuit_id.clone()).collect()
+}
+
+fn device_keys(devices: &[Circuit]) -> Vec<String> {
+    devices.iter().map(|d| d.ip.to_string()).collect()
+}
+
+/// Fraction of the plan in use, for sorting
+fn utilization(bits_per_second: u64, plan_mbps: f32) -> f64 {
+    if plan_mbps > 0.0 {
+        bits_per_second as f64 / (plan_mbps as f64 * 1_000_000.0)
+    } else {
+        0.0
+    }
+}
+
+/// Parses "down up minutes" into a temporary speed override.
+fn parse_speed_override(circuit_id: &str, name: &str, text: &str) -> Result<Action, String> {
+    let usage = "Enter download Mbps, upload Mbps and minutes, e.g. 50 10 60";
+    let parts: Vec<&str> = text.split_whitespace().collect();
+    let [down, up, minutes] = parts.as_slice() else {
+        return Err(usage.to_string());
+    };
+    let (Ok(down), Ok(up), Ok(minutes)) = (
+        down.parse::<f32>(),
+        up.parse::<f32>(),
+        minutes.parse::<u32>(),
+    ) else {
+        return Err(usage.to_string());
+    };
+    Ok(Action {
+        question: format!(
+            "Cap {name} at {down}/{up} Mbps for {minutes} minutes? (reloads LibreQoS to apply it)"
+        ),
+        request: BusRequest::SetTemporaryCircuitSpeed {
+            circuit_id: circuit_id.to_string(),
+            download_mbps: down,
+            upload_mbps: up,
+            minutes,
+        },
+    })
+}
+
+fn header<T: Into<Cell<'static>>, const N: usize>(headings: [T; N]) -> Row<'static> {
+    Row::new(headings).style(Style::default().fg(Color::White).bg(Color::Blue))
+}
+
+fn titled(title: String) -> Block<'static> {
+    Block::default()
+        .title(title)
+        .borders(Borders::NONE)
+        .style(Style::default().fg(Color::Green))
+}
+
+fn nanos_to_ms(nanos: Option<u64>) -> Option<f32> {
+    nanos.map(|n| n as f32 / 1_000_000.0)
+}
+
+fn format_ms(ms: Option<f32>) -> String {
+    ms.map(|ms| format!("{ms:.1} ms"))
+        .unwrap_or_else(|| "-".to_string())
+}
+
+fn format_percent(percent: Option<f64>) -> String {
+    percent
+        .map(|p| format!("{p:.2}%"))
+        .unwrap_or_else(|| "-".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use lqos_utils::units::DownUpOrder;
+
+    fn device(ip: &str, circuit_id: Option<&str>, bytes: u64, rtt: Option<f32>) -> Circuit {
+        Circuit {
+            ip: ip.parse().unwrap(),
+            bytes_per_second: DownUpOrder::new(bytes, bytes / 2),
+            median_latency: rtt,
+            rtt_current_p50_nanos: DownUpOrder::default(),
+            rtt_current_p95_nanos: DownUpOrder::default(),
+            rtt_total_p50_nanos: DownUpOrder::default(),
+            rtt_total_p95_nanos: DownUpOrder::default(),
+            qoo: DownUpOrder::default(),
+            tcp_retransmit_sample: DownUpOrder::default(),
+            circuit_id: circuit_id.map(str::to_string),
+            device_id: None,
+            parent_node: Some("Tower A".to_string()),
+            circuit_name: circuit_id.map(|id| format!("Customer {id}")),
+            device_name: None,
+            plan: DownUpOrder::new(100.0, 20.0),
+            last_seen_nanos: 0,
+        }
+    }
+
+    #[test]
+    fn summarize_totals_devices_by_circuit() {
+        let summaries = summarize(vec![
+            device("10.0.0.1", Some("1"), 1_000, Some(12.0)),
+            device("10.0.0.2", Some("1"), 3_000, Some(30.0)),
+            device("10.0.0.3", Some("2"), 500, None),
+            device("10.0.0.4", None, 9_000, Some(99.0)),
+        ]);
+        assert_eq!(summaries.len(), 2);
+
+        let one = summaries.iter().find(|c| c.circuit_id == "1").unwrap();
+        assert_eq!(one.name, "Customer 1");
+        assert_eq!(one.parent_node, "Tower A");
+        assert_eq!(one.devices, 2);
+        assert_eq!(one.bits_per_second, (32_000, 16_000));
+        assert_eq!(one.plan_mbps, (100.0, 20.0));
+        assert_eq!(one.worst_rtt, Some(30.0));
+
+        let two = summaries.iter().find(|c| c.circuit_id == "2").unwrap();
+        assert_eq!(two.ips, ["10.0.0.3"]);
+        assert_eq!(two.worst_rtt, None);
+    }
+
+    #[test]
+    fn utilization_is_a_share_of_the_plan() {
+        assert_eq!(utilization(50_000_000, 100.0), 0.5);
+        assert_eq!(utilization(50_000_000, 0.0), 0.0);
+    }
+
+    #[test]
+    fn speed_overrides_need_down_up_and_minutes() {
+        let action = parse_speed_override("1", "Customer 1", " 50 10  60 ").unwrap();
+        assert_eq!(
+            action.request,
+            BusRequest::SetTemporaryCircuitSpeed {
+                circuit_id: "1".to_string(),
+                download_mbps: 50.0,
+                upload_mbps: 10.0,
+                minutes: 60,
+            }
+        );
+        assert!(action.question.starts_with("Cap Customer 1 at 50/10 Mbps"));
+
+        assert!(parse_speed_override("1", "Customer 1", "50 10").is_err());
+        assert!(parse_speed_override("1", "Customer 1", "50 10 1.5").is_err());
+        assert!(parse_speed_override("1", "Customer 1", "fast 10 60").is_err());
+    }
+}
@@ -20,5 +20,12 @@ pub fn help_display() -> impl Widget {
     keyhelp('h', "Hosts", &mut span_buf);
     keyhelp('f', "Flows", &mut span_buf);
     keyhelp('l', "Latency Histogram", &mut span_buf);
+    keyhelp('t', "Tree", &mut span_buf);
+    keyhelp('d', "Circuits", &mut span_buf);
+    keyhelp('s', "Status", &mut span_buf);
+    keyhelp('/', "Filter", &mut span_buf);
+    keyhelp('<', "Sort", &mut span_buf);
+    keyhelp('>', "Sort", &mut span_buf);
+    keyhelp('r', "Reverse", &mut span_buf);
     Block::new().borders(Borders::NONE).title(span_buf)
 }
@@ -1,20 +1,53 @@
 mod cpu;
 mod stats_ringbuffer;
+mod table_controls;
 mod table_helper;
 pub use cpu::cpu_display;
 mod network_sparkline;
+use crossterm::event::KeyCode;
+use lqos_bus::BusRequest;
 pub use network_sparkline::*;
 use ratatui::{Frame, layout::Rect};
+pub mod circuits;
 pub mod help;
 pub mod latency_histogram;
+pub mod network_tree;
+pub mod status;
 pub mod top_flows;
 pub mod top_hosts;
+pub use table_controls::SortChange;
 
 pub enum MainWidget {
     Hosts,
     Flows,
 }
 
+/// An admin action, sent to lqosd only once the operator confirms it
+pub struct Action {
+    /// Shown at the `[y/N]` prompt
+    pub question: String,
+    pub request: BusRequest,
+}
+
+/// How a widget starts an admin action
+pub enum ActionPrompt {
+    /// Ask for confirmation
+    Confirm(Action),
+    /// Ask for a line of text, turn it into an action, then ask for confirmation
+    Input {
+        prompt: String,
+        parse: Box<dyn Fn(&str) -> Result<Action, String>>,
+    },
+}
+
+/// Starts a Heimdall packet capture on a host
+fn capture_action(ip: &str) -> ActionPrompt {
+    ActionPrompt::Confirm(Action {
+        question: format!("Start a Heimdall packet capture on {ip}?"),
+        request: BusRequest::GatherPacketData(ip.to_string()),
+    })
+}
+
 pub trait TopWidget {
     /// When the widget is enabled, this is called to setup the link to the bus
     fn enable(&mut self);
@@ -30,4 +63,18 @@ pub trait TopWidget {
 
     /// Render the widget
     fn render_to_frame(&mut self, frame: &mut Frame);
+
+    /// Receive a keypress that isn't a mode switch (arrows, Enter, Esc...)
+    fn handle_key(&mut self, _key: KeyCode) {}
+
+    /// Show only rows matching the lowercase `filter` (empty shows everything)
+    fn set_filter(&mut self, _filter: &str) {}
+
+    /// Change the sort column or direction
+    fn sort(&mut self, _change: SortChange) {}
+
+    /// The admin action bound to `key` for the highlighted row, if any
+    fn action(&mut self, _key: char) -> Option<ActionPrompt> {
+        None
+    }
 }
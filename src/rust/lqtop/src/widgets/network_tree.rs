@@ -0,0 +1,445 @@
+//! Collapsible view of the network.json tree, with live utilization bars.
+
+use super::TopWidget;
+use super::table_controls::matches_filter;
+use crossterm::event::KeyCode;
+use lqos_bus::BusResponse;
+use lqos_utils::packet_scale::scale_bits;
+use ratatui::prelude::*;
+use ratatui::widgets::{Block, Borders, Cell, Row, Table, TableState};
+use std::collections::{BTreeMap, HashSet};
+
+const BAR_WIDTH: usize = 10;
+
+struct TreeNode {
+    name: String,
+    node_type: Option<String>,
+    parent: Option<usize>,
+    children: Vec<usize>,
+    bits_per_second: (u64, u64),
+    max_mbps: (f64, f64),
+    median_rtt: Option<f32>,
+    circuits: u32,
+    runtime_virtualized: bool,
+}
+
+pub struct NetworkTree {
+    bus_link: tokio::sync::mpsc::Sender<crate::bus::BusMessage>,
+    rx: std::sync::mpsc::Receiver<BusResponse>,
+    tx: std::sync::mpsc::Sender<BusResponse>,
+    size: Rect,
+    nodes: BTreeMap<usize, TreeNode>,
+    expanded: HashSet<usize>,
+    selected: Option<usize>,
+    state: TableState,
+    filter: String,
+}
+
+impl TopWidget for NetworkTree {
+    fn enable(&mut self) {
+        self.bus_link
+            .blocking_send(crate::bus::BusMessage::EnableNetworkTree(self.tx.clone()))
+            .unwrap();
+    }
+
+    fn disable(&mut self) {
+        self.bus_link
+            .blocking_send(crate::bus::BusMessage::DisableNetworkTree)
+            .unwrap();
+    }
+
+    fn set_size(&mut self, size: Rect) {
+        self.size = size;
+    }
+
+    fn tick(&mut self) {
+        while let Ok(response) = self.rx.try_recv() {
+            if let BusResponse::NetworkMap(map) = response {
+                let nodes = map
+                    .into_iter()
+                    .map(|(index, node)| {
+                        let mut rtts = node.rtts;
+                        rtts.sort_by(|a, b| a.total_cmp(b));
+                        let max_mbps = node.effective_max_throughput.unwrap_or(node.max_throughput);
+                        (
+                            index,
+                            TreeNode {
+                                median_rtt: rtts.get(rtts.len() / 2).copied(),
+                                name: node.name,
+                                node_type: node.node_type,
+                                parent: node.immediate_parent.filter(|parent| *parent != index),
+                                children: Vec::new(),
+                                bits_per_second: (
+                                    node.current_throughput.0 * 8,
+                                    node.current_throughput.1 * 8,
+                                ),
+                                max_mbps,
+                                circuits: node.subtree_circuit_count,
+                                runtime_virtualized: node.runtime_virtualized,
+                            },
+                        )
+                    })
+                    .collect();
+                self.load(nodes);
+            }
+        }
+    }
+
+    fn render_to_frame(&mut self, frame: &mut Frame) {
+        let rows = self.visible_rows();
+        let position = self
+            .selected
+            .and_then(|selected| rows.iter().position(|(index, _)| *index == selected));
+        self.state
+            .select(position.or(if rows.is_empty() { None } else { Some(0) }));
+
+        let table_rows: Vec<Row> = rows
+            .iter()
+            .filter_map(|(index, depth)| {
+                let node = self.nodes.get(index)?;
+                let marker = if node.children.is_empty() {
+                    " "
+                } else if self.expanded.contains(index) {
+                    "▾"
+                } else {
+                    "▸"
+                };
+                let mut node_type = node.node_type.clone().unwrap_or_default();
+                if node.runtime_virtualized {
+                    node_type.push_str(" (virtual)");
+                }
+                Some(Row::new(vec![
+                    Cell::from(format!("{}{marker} {}", "  ".repeat(*depth), node.name)),
+                    Cell::from(node_type),
+                    utilization_cell(node.bits_per_second.0, node.max_mbps.0),
+                    utilization_cell(node.bits_per_second.1, node.max_mbps.1),
+                    Cell::from(scale_bits(node.bits_per_second.0)),
+                    Cell::from(scale_bits(node.bits_per_second.1)),
+                    Cell::from(
+                        node.median_rtt
+                            .map(|rtt| format!("{rtt:.1} ms"))
+                            .unwrap_or_else(|| "-".to_string()),
+                    ),
+                    Cell::from(node.circuits.to_string()),
+                ]))
+            })
+            .collect();
+
+        let header = Row::new(vec![
+            "Node",
+            "Type",
+            "Down",
+            "Up",
+            "Down (bps)",
+            "Up (bps)",
+            "RTT",
+            "Circuits",
+        ])
+        .style(Style::default().fg(Color::White).bg(Color::Blue));
+        let widths = [
+            Constraint::Min(30),
+            Constraint::Length(18),
+            Constraint::Length(BAR_WIDTH as u16 + 6),
+            Constraint::Length(BAR_WIDTH as u16 + 6),
+            Constraint::Length(14),
+            Constraint::Length(14),
+            Constraint::Length(10),
+            Constraint::Length(9),
+        ];
+        let table = Table::new(table_rows, widths)
+            .header(header)
+            .highlight_style(Style::default().add_modifier(Modifier::REVERSED))
+            .block(
+                Block::default()
+                    .title("Network Tree - [↑/↓] select [←/→] collapse/expand [Enter] toggle")
+                    .borders(Borders::NONE)
+                    .style(Style::default().fg(Color::Green)),
+            );
+        frame.render_stateful_widget(table, self.size, &mut self.state);
+    }
+
+    fn handle_key(&mut self, key: KeyCode) {
+        let rows = self.visible_rows();
+        if rows.is_empty() {
+            return;
+        }
+        let last = rows.len() - 1;
+        let page = self.size.height.saturating_sub(2).max(1) as usize;
+        let position = self
+            .selected
+            .and_then(|selected| rows.iter().position(|(index, _)| *index == selected))
+            .unwrap_or(0);
+        let index = rows[position].0;
+        let has_children = self
+            .nodes
+            .get(&index)
+            .is_some_and(|node| !node.children.is_empty());
+
+        let position = match key {
+            KeyCode::Up => position.saturating_sub(1),
+            KeyCode::Down => (position + 1).min(last),
+            KeyCode::PageUp => position.saturating_sub(page),
+            KeyCode::PageDown => (position + page).min(last),
+            KeyCode::Home => 0,
+            KeyCode::End => last,
+            KeyCode::Right if has_children => {
+                self.expanded.insert(index);
+                position
+            }
+            KeyCode::Enter | KeyCode::Char(' ') if has_children => {
+                if !self.expanded.remove(&index) {
+                    self.expanded.insert(index);
+                }
+                position
+            }
+            KeyCode::Left => {
+                if !self.expanded.remove(&index) {
+                    // Already collapsed: jump to the parent instead
+                    if let Some(parent) = self.nodes.get(&index).and_then(|node| node.parent) {
+                        self.selected = Some(parent);
+                    }
+                    return;
+                }
+                position
+            }
+            _ => return,
+        };
+        self.selected = Some(rows[position].0);
+    }
+
+    fn set_filter(&mut self, filter: &str) {
+        self.filter = filter.to_string();
+    }
+}
+
+impl NetworkTree {
+    pub fn new(bus_link: tokio::sync::mpsc::Sender<crate::bus::BusMessage>) -> Self {
+        let (tx, rx) = std::sync::mpsc::channel::<BusResponse>();
+        Self {
+            bus_link,
+            rx,
+            tx,
+            size: Rect::default(),
+            nodes: BTreeMap::new(),
+            expanded: HashSet::new(),
+            selected: None,
+            state: TableState::default(),
+            filter: String::new(),
+        }
+    }
+
+    /// Replaces the tree, linking children to parents by name. The roots start expanded.
+    fn load(&mut self, nodes: BTreeMap<usize, TreeNode>) {
+        let first_load = self.nodes.is_empty();
+        self.nodes = nodes;
+
+        let mut links: Vec<(usize, usize)> = self
+            .nodes
+            .iter()
+            .filter_map(|(index, node)| node.parent.map(|parent| (parent, *index)))
+            .collect();
+        links.sort_by(|a, b| self.nodes[&a.1].name.cmp(&self.nodes[&b.1].name));
+        for (parent, child) in links {
+            if let Some(parent) = self.nodes.get_mut(&parent) {
+                parent.children.push(child);
+            }
+        }
+
+        if first_load {
+            self.expanded = self.roots().into_iter().collect();
+        }
+    }
+
+    fn roots(&self) -> Vec<usize> {
+        self.nodes
+            .iter()
+            .filter(|(_, node)| {
+                node.parent
+                    .is_none_or(|parent| !self.nodes.contains_key(&parent))
+            })
+            .map(|(index, _)| *index)
+            .collect()
+    }
+
+    /// Nodes whose name or type matches the filter, plus their ancestors. `None` when
+    /// there is no filter.
+    fn filtered(&self) -> Option<HashSet<usize>> {
+        if self.filter.is_empty() {
+            return None;
+        }
+        let mut shown = HashSet::new();
+        for (index, node) in &self.nodes {
+            let node_type = node.node_type.as_deref().unwrap_or_default();
+            if !matches_filter(&self.filter, &[node.name.as_str(), node_type]) {
+                continue;
+            }
+            let mut next = Some(*index);
+            while let Some(index) = next {
+                if !shown.insert(index) {
+                    break;
+                }
+                next = self.nodes.get(&index).and_then(|node| node.parent);
+            }
+        }
+        Some(shown)
+    }
+
+    /// Node indices and depths of every row currently on screen, in display order. While
+    /// filtering, matches are shown with their ancestors expanded.
+    fn visible_rows(&self) -> Vec<(usize, usize)> {
+        let filtered = self.filtered();
+        let mut rows = Vec::new();
+        let mut stack: Vec<(usize, usize)> =
+            self.roots().into_iter().rev().map(|i| (i, 0)).collect();
+        while let Some((index, depth)) = stack.pop() {
+            if filtered
+                .as_ref()
+                .is_some_and(|shown| !shown.contains(&index))
+            {
+                continue;
+            }
+            rows.push((index, depth));
+            if (filtered.is_some() || self.expanded.contains(&index))
+                && let Some(node) = self.nodes.get(&index)
+            {
+                stack.extend(node.children.iter().rev().map(|child| (*child, depth + 1)));
+            }
+        }
+        rows
+    }
+}
+
+/// A fixed-width text bar showing `bits` as a share of `max_mbps`.
+pub(super) fn utilization_cell(bits: u64, max_mbps: f64) -> Cell<'static> {
+    match utilization_bar(bits, max_mbps) {
+        Some((bar, color)) => Cell::from(bar).style(Style::default().fg(color)),
+        None => Cell::from("-"),
+    }
+}
+
+/// The text and color of a utilization bar, or `None` without a known maximum.
+fn utilization_bar(bits: u64, max_mbps: f64) -> Option<(String, Color)> {
+    if max_mbps <= 0.0 {
+        return None;
+    }
+    let percent = bits as f64 / (max_mbps * 1_000_000.0) * 100.0;
+    let filled = (percent / 100.0 * BAR_WIDTH as f64)
+        .round()
+        .clamp(0.0, BAR_WIDTH as f64) as usize;
+    let color = if percent >= 90.0 {
+        Color::Red
+    } else if percent >= 70.0 {
+        Color::Yellow
+    } else {
+        Color::Green
+    };
+    let bar = format!(
+        "{}{} {percent:>3.0}%",
+        "█".repeat(filled),
+        "░".repeat(BAR_WIDTH - filled)
+    );
+    Some((bar, color))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A tree from `(index, name, parent)` triples.
+    fn tree(nodes: &[(usize, &str, Option<usize>)]) -> NetworkTree {
+        let (bus_link, _) = tokio::sync::mpsc::channel(1);
+        let mut tree = NetworkTree::new(bus_link);
+        tree.load(
+            nodes
+                .iter()
+                .map(|(index, name, parent)| {
+                    (
+                        *index,
+                        TreeNode {
+                            name: name.to_string(),
+                            node_type: Some("Site".to_string()),
+                            parent: *parent,
+                            children: Vec::new(),
+                            bits_per_second: (0, 0),
+                            max_mbps: (0.0, 0.0),
+                            median_rtt: None,
+                            circuits: 0,
+                            runtime_virtualized: false,
+                        },
+                    )
+                })
+                .collect(),
+        );
+        tree
+    }
+
+    fn visible_names(tree: &NetworkTree) -> Vec<String> {
+        tree.visible_rows()
+            .iter()
+            .map(|(index, depth)| format!("{}{}", "-".repeat(*depth), tree.nodes[index].name))
+            .collect()
+    }
+
+    fn sample() -> NetworkTree {
+        tree(&[
+            (0, "Root", None),
+            (1, "Tower B", Some(0)),
+            (2, "Tower A", Some(0)),
+            (3, "AP 1", Some(2)),
+        ])
+    }
+
+    #[test]
+    fn roots_start_expanded_with_children_sorted_by_name() {
+        let tree = sample();
+        assert_eq!(visible_names(&tree), ["Root", "-Tower A", "-Tower B"]);
+    }
+
+    #[test]
+    fn keys_expand_and_collapse_the_selected_node() {
+        let mut tree = sample();
+        tree.handle_key(KeyCode::Down);
+        assert_eq!(tree.selected, Some(2));
+
+        tree.handle_key(KeyCode::Right);
+        assert_eq!(
+            visible_names(&tree),
+            ["Root", "-Tower A", "--AP 1", "-Tower B"]
+        );
+
+        tree.handle_key(KeyCode::Left);
+        assert_eq!(visible_names(&tree), ["Root", "-Tower A", "-Tower B"]);
+        // Left on a collapsed node moves to its parent.
+        tree.handle_key(KeyCode::Left);
+        assert_eq!(tree.selected, Some(0));
+
+        tree.handle_key(KeyCode::Enter);
+        assert_eq!(visible_names(&tree), ["Root"]);
+        tree.handle_key(KeyCode::Enter);
+        assert_eq!(visible_names(&tree), ["Root", "-Tower A", "-Tower B"]);
+    }
+
+    #[test]
+    fn filter_shows_matches_with_their_ancestors() {
+        let mut tree = sample();
+        tree.set_filter("ap 1");
+        assert_eq!(visible_names(&tree), ["Root", "-Tower A", "--AP 1"]);
+    }
+
+    #[test]
+    fn utilization_bar_fills_and_colors_by_share_of_the_maximum() {
+        assert_eq!(utilization_bar(1_000, 0.0), None);
+        assert_eq!(
+            utilization_bar(50_000_000, 100.0),
+            Some(("█████░░░░░  50%".to_string(), Color::Green))
+        );
+        assert_eq!(
+            utilization_bar(75_000_000, 100.0).map(|(_, color)| color),
+            Some(Color::Yellow)
+        );
+        assert_eq!(
+            utilization_bar(250_000_000, 100.0),
+            Some(("██████████ 250%".to_string(), Color::Red))
+        );
+    }
+}
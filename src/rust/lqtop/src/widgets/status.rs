@@ -0,0 +1,252 @@
+//! Bakery, scheduler, HA, StormGuard and TreeGuard status at a glance.
+
+use super::TopWidget;
+use super::table_helper::TableHelper;
+use lqos_bus::{BusResponse, HaStatus};
+use ratatui::prelude::*;
+use ratatui::widgets::{Block, Borders, Paragraph};
+
+pub struct Status {
+    bus_link: tokio::sync::mpsc::Sender<crate::bus::BusMessage>,
+    rx: std::sync::mpsc::Receiver<BusResponse>,
+    tx: std::sync::mpsc::Sender<BusResponse>,
+    size: Rect,
+    bakery_circuits: Option<usize>,
+    scheduler: Option<(bool, Option<String>)>,
+    ha: Option<HaStatus>,
+    /// `(site, download Mbps, upload Mbps)`
+    stormguard: Vec<(String, u64, u64)>,
+    virtualized_nodes: Vec<String>,
+}
+
+impl TopWidget for Status {
+    fn enable(&mut self) {
+        self.bus_link
+            .blocking_send(crate::bus::BusMessage::EnableStatus(self.tx.clone()))
+            .unwrap();
+    }
+
+    fn disable(&mut self) {
+        self.bus_link
+            .blocking_send(crate::bus::BusMessage::DisableStatus)
+            .unwrap();
+    }
+
+    fn set_size(&mut self, size: Rect) {
+        self.size = size;
+    }
+
+    fn tick(&mut self) {
+        while let Ok(response) = self.rx.try_recv() {
+            match response {
+                BusResponse::BakeryActiveCircuits(count) => self.bakery_circuits = Some(count),
+                BusResponse::SchedulerStatus { running, error } => {
+                    self.scheduler = Some((running, error));
+                }
+                BusResponse::HaStatus(status) => self.ha = Some(status),
+                BusResponse::StormguardStats(mut sites) => {
+                    sites.sort_by(|a, b| a.0.cmp(&b.0));
+                    self.stormguard = sites;
+                }
+                BusResponse::NetworkMap(nodes) => {
+                    self.virtualized_nodes = nodes
+                        .into_iter()
+                        .filter(|(_, node)| node.runtime_virtualized)
+                        .map(|(_, node)| node.name)
+                        .collect();
+                    self.virtualized_nodes.sort();
+                }
+                _ => {}
+            }
+        }
+    }
+
+    fn render_to_frame(&mut self, frame: &mut Frame) {
+        let layout = Layout::new(
+            Direction::Vertical,
+            [Constraint::Length(4), Constraint::Fill(1)],
+        )
+        .split(self.size);
+        let columns = Layout::new(
+            Direction::Horizontal,
+            [Constraint::Percentage(60), Constraint::Percentage(40)],
+        )
+        .split(layout[1]);
+
+        let summary = vec![
+            Line::from(self.bakery_line()),
+            Line::from(self.scheduler_line()),
+            Line::from(self.ha_line()),
+        ];
+        frame.render_widget(
+            Paragraph::new(summary).block(
+                Block::default()
+                    .title("Status")
+                    .borders(Borders::NONE)
+                    .style(Style::default().fg(Color::Green)),
+            ),
+            layout[0],
+        );
+
+        let mut stormguard = TableHelper::new(["StormGuard Site", "Down (Mbps)", "Up (Mbps)"]);
+        for (site, down, up) in self.stormguard.iter() {
+            stormguard.add_row([site.clone(), down.to_string(), up.to_string()]);
+        }
+        frame.render_widget(stormguard.to_block(), columns[0]);
+
+        let mut treeguard = TableHelper::new(["TreeGuard Virtualized Node"]);
+        for node in self.virtualized_nodes.iter() {
+            treeguard.add_row([node.clone()]);
+        }
+        frame.render_widget(treeguard.to_block(), columns[1]);
+    }
+}
+
+impl Status {
+    pub fn new(bus_link: tokio::sync::mpsc::Sender<crate::bus::BusMessage>) -> Self {
+        let (tx, rx) = std::sync::mpsc::channel::<BusResponse>();
+        Self {
+            bus_link,
+            rx,
+            tx,
+            size: Rect::default(),
+            bakery_circuits: None,
+            scheduler: None,
+            ha: None,
+            stormguard: Vec::new(),
+            virtualized_nodes: Vec::new(),
+        }
+    }
+
+    fn bakery_line(&self) -> String {
+        match self.bakery_circuits {
+            Some(count) => format!("Bakery: {count} active circuits"),
+            None => "Bakery: waiting for lqosd".to_string(),
+        }
+    }
+
+    fn scheduler_line(&self) -> String {
+        match &self.scheduler {
+            Some((_, Some(error))) => format!("Scheduler: error - {error}"),
+            Some((true, None)) => "Scheduler: running".to_string(),
+            Some((false, None)) => "Scheduler: not running".to_string(),
+            None => "Scheduler: waiting for lqosd".to_string(),
+        }
+    }
+
+    fn ha_line(&self) -> String {
+        let Some(ha) = &self.ha else {
+            return "HA: waiting for lqosd".to_string();
+        };
+        if !ha.enabled {
+            return "HA: not configured".to_string();
+        }
+        let role = if ha.promoted {
+            format!("{} (promoted)", ha.role)
+        } else {
+            ha.role.clone()
+        };
+        let link = if ha.connected {
+            "connected"
+        } else {
+            "disconnected"
+        };
+        let mut line = format!(
+            "HA: {role}, {link} to {}",
+            ha.peer_name.as_deref().unwrap_or("peer")
+        );
+        if let Some(error) = &ha.last_error {
+            line.push_str(&format!(" - {error}"));
+        }
+        line
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn status() -> Status {
+        let (bus_link, _) = tokio::sync::mpsc::channel(1);
+        Status::new(bus_link)
+    }
+
+    fn ha(connected: bool, last_error: Option<&str>) -> HaStatus {
+        HaStatus {
+            enabled: true,
+            role: "standby".to_string(),
+            promoted: true,
+            connected,
+            peer_name: Some("shaper-a".to_string()),
+            last_contact_unix: None,
+            bakery_commands: 0,
+            ip_mappings: 0,
+            virtualized_nodes: 0,
+            stormguard_sites: 0,
+            last_error: last_error.map(str::to_string),
+        }
+    }
+
+    #[test]
+    fn lines_wait_for_lqosd() {
+        let status = status();
+        assert_eq!(status.bakery_line(), "Bakery: waiting for lqosd");
+        assert_eq!(status.scheduler_line(), "Scheduler: waiting for lqosd");
+        assert_eq!(status.ha_line(), "HA: waiting for lqosd");
+    }
+
+    #[test]
+    fn responses_update_the_summary() {
+        let mut status = status();
+        status
+            .tx
+            .send(BusResponse::BakeryActiveCircuits(42))
+            .unwrap();
+        status
+            .tx
+            .send(BusResponse::SchedulerStatus {
+                running: true,
+                error: None,
+            })
+            .unwrap();
+        status
+            .tx
+            .send(BusResponse::HaStatus(ha(true, None)))
+            .unwrap();
+        status
+            .tx
+            .send(BusResponse::StormguardStats(vec![
+                ("Tower B".to_string(), 10, 5),
+                ("Tower A".to_string(), 20, 8),
+            ]))
+            .unwrap();
+        status.tick();
+
+        assert_eq!(status.bakery_line(), "Bakery: 42 active circuits");
+        assert_eq!(status.scheduler_line(), "Scheduler: running");
+        assert_eq!(
+            status.ha_line(),
+            "HA: standby (promoted), connected to shaper-a"
+        );
+        assert_eq!(status.stormguard[0].0, "Tower A");
+    }
+
+    #[test]
+    fn errors_are_shown_in_the_summary() {
+        let mut status = status();
+        status.scheduler = Some((true, Some("boom".to_string())));
+        assert_eq!(status.scheduler_line(), "Scheduler: error - boom");
+
+        status.ha = Some(ha(false, Some("refused")));
+        assert_eq!(
+            status.ha_line(),
+            "HA: standby (promoted), disconnected to shaper-a - refused"
+        );
+
+        status.ha = Some(HaStatus {
+            enabled: false,
+            ..ha(false, None)
+        });
+        assert_eq!(status.ha_line(), "HA: not configured");
+    }
+}
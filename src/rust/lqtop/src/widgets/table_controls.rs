@@ -0,0 +1,132 @@
+//! Filtering, sorting and row selection shared by the table widgets.
+
+use crossterm::event::KeyCode;
+use ratatui::widgets::TableState;
+use std::cmp::Ordering;
+
+/// Keys that change the sort order: `<`, `>` and `r`
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SortChange {
+    Previous,
+    Next,
+    Reverse,
+}
+
+/// The value a row sorts by in one column
+pub enum SortKey {
+    Number(f64),
+    Text(String),
+}
+
+impl SortKey {
+    fn compare(&self, other: &SortKey) -> Ordering {
+        match (self, other) {
+            (SortKey::Number(a), SortKey::Number(b)) => a.total_cmp(b),
+            (SortKey::Text(a), SortKey::Text(b)) => a.to_lowercase().cmp(&b.to_lowercase()),
+            (SortKey::Number(_), SortKey::Text(_)) => Ordering::Less,
+            (SortKey::Text(_), SortKey::Number(_)) => Ordering::Greater,
+        }
+    }
+}
+
+/// The sorted column of an `N`-column table
+pub struct SortState<const N: usize> {
+    column: usize,
+    descending: bool,
+}
+
+impl<const N: usize> SortState<N> {
+    pub fn new(column: usize, descending: bool) -> Self {
+        Self { column, descending }
+    }
+
+    pub fn change(&mut self, change: SortChange) {
+        match change {
+            SortChange::Previous => self.column = (self.column + N - 1) % N,
+            SortChange::Next => self.column = (self.column + 1) % N,
+            SortChange::Reverse => self.descending = !self.descending,
+        }
+    }
+
+    /// Sorts `rows` by the current column; `key` gives a row's value in a column.
+    pub fn sort<T>(&self, rows: &mut [T], key: impl Fn(&T, usize) -> SortKey) {
+        rows.sort_by(|a, b| {
+            let ordering = key(a, self.column).compare(&key(b, self.column));
+            if self.descending {
+                ordering.reverse()
+            } else {
+                ordering
+            }
+        });
+    }
+
+    /// Column headings, with an arrow on the sorted column
+    pub fn headings(&self, headings: [&str; N]) -> [String; N] {
+        std::array::from_fn(|i| {
+            if i == self.column {
+                let arrow = if self.descending { "▼" } else { "▲" };
+                format!("{} {arrow}", headings[i])
+            } else {
+                headings[i].to_string()
+            }
+        })
+    }
+}
+
+/// True if `filter` is empty or appears (ignoring case) in any of `fields`.
+/// `filter` must already be lowercase.
+pub fn matches_filter(filter: &str, fields: &[&str]) -> bool {
+    filter.is_empty()
+        || fields
+            .iter()
+            .any(|field| field.to_lowercase().contains(filter))
+}
+
+/// The highlighted row, remembered by key so that it stays on the same item while the
+/// rows re-sort underneath it.
+#[derive(Default)]
+pub struct Selection {
+    key: Option<String>,
+    state: TableState,
+}
+
+impl Selection {
+    /// The key of the highlighted row, if it is still present in `keys`.
+    pub fn selected<'a>(&self, keys: &'a [String]) -> Option<&'a str> {
+        self.position(keys).map(|position| keys[position].as_str())
+    }
+
+    fn position(&self, keys: &[String]) -> Option<usize> {
+        match &self.key {
+            Some(key) => keys.iter().position(|k| k == key).or(Some(0)),
+            None => Some(0),
+        }
+        .filter(|_| !keys.is_empty())
+    }
+
+    /// Moves the highlight for arrow, page and home/end keys. Returns false for other keys.
+    pub fn handle_key(&mut self, key: KeyCode, keys: &[String], page: usize) -> bool {
+        let Some(position) = self.position(keys) else {
+            return false;
+        };
+        let last = keys.len() - 1;
+        let position = match key {
+            KeyCode::Up => position.saturating_sub(1),
+            KeyCode::Down => (position + 1).min(last),
+            KeyCode::PageUp => position.saturating_sub(page.max(1)),
+            KeyCode::PageDown => (position + page.max(1)).min(last),
+            KeyCode::Home => 0,
+            KeyCode::End => last,
+            _ => return false,
+        };
+        self.key = Some(keys[position].clone());
+        true
+    }
+
+    /// Table state for rendering rows in the order of `keys`
+    pub fn state(&mut self, keys: &[String]) -> &mut TableState {
+        let position = self.position(keys);
+        self.state.select(position);
+        &mut self.state
+    }
+}
@@ -1,7 +1,10 @@
-use super::{TopWidget, table_helper::TableHelper};
+use super::table_controls::{Selection, SortChange, SortKey, SortState, matches_filter};
+use super::{ActionPrompt, TopWidget, capture_action, table_helper::TableHelper};
+use crossterm::event::KeyCode;
 use lqos_bus::{BusResponse, FlowbeeSummaryData};
 use lqos_utils::packet_scale::scale_bits;
 use ratatui::prelude::*;
+use ratatui::widgets::{Block, Borders};
 
 pub struct TopFlows {
     bus_link: tokio::sync::mpsc::Sender<crate::bus::BusMessage>,
@@ -9,6 +12,9 @@ pub struct TopFlows {
     tx: std::sync::mpsc::Sender<BusResponse>,
     size: Rect,
     flows: Vec<FlowbeeSummaryData>,
+    filter: String,
+    sort: SortState<8>,
+    selection: Selection,
 }
 
 impl TopWidget for TopFlows {
@@ -37,7 +43,8 @@ impl TopWidget for TopFlows {
     }
 
     fn render_to_frame(&mut self, frame: &mut Frame) {
-        let mut t = TableHelper::new([
+        let flows = self.visible();
+        let mut t = TableHelper::new(self.sort.headings([
             "Src IP",
             "Dst IP",
             "Type",
@@ -46,8 +53,8 @@ impl TopWidget for TopFlows {
             "Retransmits",
             "RTT (ms)",
             "ASN",
-        ]);
-        for flow in self.flows.iter() {
+        ]));
+        for flow in flows.iter() {
             t.add_row([
                 flow.local_ip.to_string(),
                 flow.remote_ip.to_string(),
@@ -63,8 +70,42 @@ impl TopWidget for TopFlows {
                 flow.remote_asn_name.to_string(),
             ]);
         }
-        let table = t.to_block();
-        frame.render_widget(table, self.size);
+        let keys = keys(&flows);
+        let table = t
+            .to_table()
+            .highlight_style(Style::default().add_modifier(Modifier::REVERSED))
+            .block(
+                Block::default()
+                    .title("[p] capture packets on the local host")
+                    .borders(Borders::NONE)
+                    .style(Style::default().fg(Color::Green)),
+            );
+        frame.render_stateful_widget(table, self.size, self.selection.state(&keys));
+    }
+
+    fn handle_key(&mut self, key: KeyCode) {
+        let keys = keys(&self.visible());
+        let page = self.size.height.saturating_sub(2) as usize;
+        self.selection.handle_key(key, &keys, page);
+    }
+
+    fn set_filter(&mut self, filter: &str) {
+        self.filter = filter.to_string();
+    }
+
+    fn sort(&mut self, change: SortChange) {
+        self.sort.change(change);
+    }
+
+    fn action(&mut self, key: char) -> Option<ActionPrompt> {
+        let flows = self.visible();
+        let keys = keys(&flows);
+        let selected = self.selection.selected(&keys)?;
+        let flow = flows.iter().find(|f| flow_key(f) == selected)?;
+        match key {
+            'p' => Some(capture_action(&flow.local_ip)),
+            _ => None,
+        }
     }
 }
 
@@ -77,6 +118,52 @@ impl TopFlows {
             rx,
             size: Rect::default(),
             flows: Vec::new(),
+            filter: String::new(),
+            sort: SortState::new(3, true),
+            selection: Selection::default(),
         }
     }
+
+    /// Flows matching the filter (by IP, circuit or ASN), in display order
+    fn visible(&self) -> Vec<&FlowbeeSummaryData> {
+        let mut flows: Vec<&FlowbeeSummaryData> = self
+            .flows
+            .iter()
+            .filter(|f| {
+                matches_filter(
+                    &self.filter,
+                    &[
+                        f.local_ip.as_str(),
+                        f.remote_ip.as_str(),
+                        f.circuit_name.as_str(),
+                        f.circuit_id.as_str(),
+                        f.remote_asn_name.as_str(),
+                        format!("AS{}", f.remote_asn).as_str(),
+                    ],
+                )
+            })
+            .collect();
+        self.sort.sort(&mut flows, |f, column| match column {
+            0 => SortKey::Text(f.local_ip.clone()),
+            1 => SortKey::Text(f.remote_ip.clone()),
+            2 => SortKey::Text(f.analysis.clone()),
+            3 => SortKey::Number(f.bytes_sent.down as f64),
+            4 => SortKey::Number(f.bytes_sent.up as f64),
+            5 => SortKey::Number(f.tcp_retransmits.down as f64 + f.tcp_retransmits.up as f64),
+            6 => SortKey::Number(f.rtt_nanos.down as f64),
+            _ => SortKey::Text(f.remote_asn_name.clone()),
+        });
+        flows
+    }
+}
+
+fn flow_key(flow: &FlowbeeSummaryData) -> String {
+    format!(
+        "{}:{}-{}:{}/{:?}",
+        flow.local_ip, flow.src_port, flow.remote_ip, flow.dst_port, flow.ip_protocol
+    )
+}
+
+fn keys(flows: &[&FlowbeeSummaryData]) -> Vec<String> {
+    flows.iter().map(|f| flow_key(f)).collect()
 }
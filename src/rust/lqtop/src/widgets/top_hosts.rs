@@ -1,7 +1,10 @@
-use super::{TopWidget, table_helper::TableHelper};
+use super::table_controls::{Selection, SortChange, SortKey, SortState, matches_filter};
+use super::{ActionPrompt, TopWidget, capture_action, table_helper::TableHelper};
+use crossterm::event::KeyCode;
 use lqos_bus::{BusResponse, IpStats};
 use lqos_utils::packet_scale::{scale_bits, scale_packets};
 use ratatui::prelude::*;
+use ratatui::widgets::{Block, Borders};
 
 pub struct TopHosts {
     bus_link: tokio::sync::mpsc::Sender<crate::bus::BusMessage>,
@@ -9,6 +12,9 @@ pub struct TopHosts {
     tx: std::sync::mpsc::Sender<BusResponse>,
     size: Rect,
     stats: Vec<IpStats>,
+    filter: String,
+    sort: SortState<7>,
+    selection: Selection,
 }
 
 impl TopWidget for TopHosts {
@@ -37,7 +43,8 @@ impl TopWidget for TopHosts {
     }
 
     fn render_to_frame(&mut self, frame: &mut Frame) {
-        let mut t = TableHelper::new([
+        let hosts = self.visible();
+        let mut t = TableHelper::new(self.sort.headings([
             "IP Address",
             "Down (bps)",
             "Up (bps)",
@@ -45,9 +52,9 @@ impl TopWidget for TopHosts {
             "Up (pps)",
             "RTT",
             "TC Handle",
-        ]);
+        ]));
 
-        for host in self.stats.iter() {
+        for host in hosts.iter() {
             t.add_row([
                 host.ip_address.to_string(),
                 scale_bits(host.bits_per_second.down),
@@ -59,8 +66,39 @@ impl TopWidget for TopHosts {
             ]);
         }
 
-        let block = t.to_block();
-        frame.render_widget(block, self.size);
+        let keys = keys(&hosts);
+        let block = t
+            .to_table()
+            .highlight_style(Style::default().add_modifier(Modifier::REVERSED))
+            .block(
+                Block::default()
+                    .title("[p] capture packets")
+                    .borders(Borders::NONE)
+                    .style(Style::default().fg(Color::Green)),
+            );
+        frame.render_stateful_widget(block, self.size, self.selection.state(&keys));
+    }
+
+    fn handle_key(&mut self, key: KeyCode) {
+        let keys = keys(&self.visible());
+        let page = self.size.height.saturating_sub(2) as usize;
+        self.selection.handle_key(key, &keys, page);
+    }
+
+    fn set_filter(&mut self, filter: &str) {
+        self.filter = filter.to_string();
+    }
+
+    fn sort(&mut self, change: SortChange) {
+        self.sort.change(change);
+    }
+
+    fn action(&mut self, key: char) -> Option<ActionPrompt> {
+        let keys = keys(&self.visible());
+        match key {
+            'p' => self.selection.selected(&keys).map(capture_action),
+            _ => None,
+        }
     }
 }
 
@@ -73,6 +111,37 @@ impl TopHosts {
             tx,
             size: Rect::default(),
             stats: Vec::new(),
+            filter: String::new(),
+            sort: SortState::new(1, true),
+            selection: Selection::default(),
         }
     }
+
+    /// Hosts matching the filter, in display order
+    fn visible(&self) -> Vec<&IpStats> {
+        let mut hosts: Vec<&IpStats> = self
+            .stats
+            .iter()
+            .filter(|h| {
+                matches_filter(
+                    &self.filter,
+                    &[h.ip_address.as_str(), h.circuit_id.as_str()],
+                )
+            })
+            .collect();
+        self.sort.sort(&mut hosts, |h, column| match column {
+            0 => SortKey::Text(h.ip_address.clone()),
+            1 => SortKey::Number(h.bits_per_second.down as f64),
+            2 => SortKey::Number(h.bits_per_second.up as f64),
+            3 => SortKey::Number(h.packets_per_second.down as f64),
+            4 => SortKey::Number(h.packets_per_second.up as f64),
+            5 => SortKey::Number(h.median_tcp_rtt as f64),
+            _ => SortKey::Text(h.tc_handle.to_string()),
+        });
+        hosts
+    }
+}
+
+fn keys(hosts: &[&IpStats]) -> Vec<String> {
+    hosts.iter().map(|h| h.ip_address.clone()).collect()
 }
@@ -1,7 +1,7 @@
 use anyhow::Result;
-use clap::{Parser, Subcommand};
+use clap::{ArgGroup, Parser, Subcommand};
 use lqos_bus::{BusRequest, bus_request};
-use lqos_config::{UserRole, WebUsers};
+use lqos_config::{ApiTokenScope, Permission, UserRole, WebUsers};
 use std::process::exit;
 
 #[derive(Parser)]
@@ -34,6 +34,130 @@ enum Commands {
     },
     /// List users
     List,
+    /// Manage API tokens for the /api/v1 REST API
+    Token {
+        #[command(subcommand)]
+        command: TokenCommands,
+    },
+    /// Manage two-factor (TOTP) authentication
+    Totp {
+        #[command(subcommand)]
+        command: TotpCommands,
+    },
+    /// Manage custom roles (named permission sets)
+    Role {
+        #[command(subcommand)]
+        command: RoleCommands,
+    },
+    /// Assign a custom role and network.json scope to a user
+    Access {
+        /// Username
+        #[arg(long)]
+        username: String,
+
+        /// Custom role name. Omit to use only the user's built-in role.
+        #[arg(long)]
+        role: Option<String>,
+
+        /// network.json nodes the user is limited to (comma separated or repeated). Omit for
+        /// the whole network.
+        #[arg(long = "scope", value_delimiter = ',')]
+        scope: Vec<String>,
+    },
+}
+
+#[derive(Subcommand)]
+enum RoleCommands {
+    /// Create or replace a custom role
+    Define {
+        /// Role name, e.g. field-tech
+        #[arg(long)]
+        name: String,
+
+        /// Permissions: view, adjust-speeds, manage-overrides, reload, manage-users,
+        /// packet-capture (comma separated or repeated)
+        #[arg(long = "permission", value_delimiter = ',', required = true)]
+        permissions: Vec<Permission>,
+    },
+    /// List custom roles
+    List,
+    /// Remove a custom role that no user is assigned to
+    Remove {
+        /// Role name
+        name: String,
+    },
+}
+
+#[derive(Subcommand)]
+enum TotpCommands {
+    /// Start enrollment: prints a provisioning URI to add to an authenticator app
+    Enroll {
+        /// Username
+        username: String,
+    },
+    /// Finish enrollment with a code from the authenticator app; prints recovery codes
+    Confirm {
+        /// Username
+        username: String,
+
+        /// Current 6-digit code
+        code: String,
+    },
+    /// Remove a user's second factor (e.g. lost device)
+    Reset {
+        /// Username
+        username: String,
+    },
+    /// Require (or stop requiring) two-factor authentication for a role, a custom role or
+    /// everyone granted a permission
+    #[command(group(
+        ArgGroup::new("target")
+            .required(true)
+            .args(["role", "custom_role", "permission"])
+    ))]
+    Require {
+        /// Built-in role
+        #[arg(long)]
+        role: Option<UserRole>,
+
+        /// Custom role (see `role list`)
+        #[arg(long)]
+        custom_role: Option<String>,
+
+        /// Permission: view, adjust-speeds, manage-overrides, reload, manage-users or
+        /// packet-capture
+        #[arg(long)]
+        permission: Option<Permission>,
+
+        /// Stop requiring it
+        #[arg(long)]
+        off: bool,
+    },
+}
+
+#[derive(Subcommand)]
+enum TokenCommands {
+    /// Issue a new token. The token is printed once and cannot be shown again.
+    Add {
+        /// Label for the token, e.g. the integrating system
+        #[arg(long)]
+        name: String,
+
+        /// Scopes: read, devices, overrides, reload (comma separated or repeated)
+        #[arg(long = "scope", value_delimiter = ',', required = true)]
+        scopes: Vec<ApiTokenScope>,
+
+        /// Expire the token after this many days
+        #[arg(long)]
+        expires_days: Option<u64>,
+    },
+    /// List issued tokens
+    List,
+    /// Revoke a token by id
+    Revoke {
+        /// Token id (see `token list`)
+        id: String,
+    },
 }
 
 fn notify_auth_cache_invalidated() {
@@ -70,6 +194,126 @@ fn main() -> Result<()> {
             println!("All Users\n");
             users.print_users()?;
         }
+        Some(Commands::Token { command }) => match command {
+            TokenCommands::Add {
+                name,
+                scopes,
+                expires_days,
+            } => {
+                let expires_unix = expires_days.map(|days| {
+                    std::time::SystemTime::now()
+                        .duration_since(std::time::UNIX_EPOCH)
+                        .map(|d| d.as_secs())
+                        .unwrap_or(0)
+                        .saturating_add(days.saturating_mul(86_400))
+                });
+                let (token, secret) = users.create_api_token(&name, &scopes, expires_unix)?;
+                notify_auth_cache_invalidated();
+                println!("Created API token {} ({name}).", token.id);
+                println!("Store it now; it will not be shown again:\n\n{secret}");
+            }
+            TokenCommands::List => {
+                println!("{:<14} {:<24} {:<30} Expires", "ID", "Name", "Scopes");
+                for token in users.get_api_tokens() {
+                    let scopes: Vec<String> = token.scopes.iter().map(|s| s.to_string()).collect();
+                    let expires = token
+                        .expires_unix
+                        .map(|ts| ts.to_string())
+                        .unwrap_or_else(|| "never".to_string());
+                    println!(
+                        "{:<14} {:<24} {:<30} {expires}",
+                        token.id,
+                        token.name,
+                        scopes.join(",")
+                    );
+                }
+            }
+            TokenCommands::Revoke { id } => {
+                users.revoke_api_token(&id)?;
+                notify_auth_cache_invalidated();
+                println!("Revoked API token {id}.");
+            }
+        },
+        Some(Commands::Totp { command }) => match command {
+            TotpCommands::Enroll { username } => {
+                let provisioning = users.begin_totp_enrollment(&username)?;
+                println!("Add this account to an authenticator app, then run");
+                println!("`lqusers totp confirm {username} <code>`.\n");
+                println!("URI:    {}", provisioning.uri);
+                println!("Secret: {}\n", provisioning.secret);
+                println!(
+                    "To show a QR code: qrencode -t ansiutf8 '{}'",
+                    provisioning.uri
+                );
+            }
+            TotpCommands::Confirm { username, code } => {
+                let recovery_codes = users.confirm_totp_enrollment(&username, &code)?;
+                notify_auth_cache_invalidated();
+                println!("Two-factor authentication enabled for {username}.");
+                println!("Recovery codes (each works once; store them now):\n");
+                for code in recovery_codes {
+                    println!("  {code}");
+                }
+            }
+            TotpCommands::Reset { username } => {
+                users.reset_totp(&username)?;
+                notify_auth_cache_invalidated();
+                println!("Two-factor authentication removed for {username}.");
+            }
+            TotpCommands::Require {
+                role,
+                custom_role,
+                permission,
+                off,
+            } => {
+                let who = if let Some(role) = role {
+                    users.set_totp_required(role, !off)?;
+                    format!("{role} users")
+                } else if let Some(name) = custom_role {
+                    users.set_totp_required_for_custom_role(&name, !off)?;
+                    format!("members of {name}")
+                } else if let Some(permission) = permission {
+                    users.set_totp_required_for_permission(permission, !off)?;
+                    format!("users with the {permission} permission")
+                } else {
+                    unreachable!("clap requires one of --role, --custom-role or --permission");
+                };
+                notify_auth_cache_invalidated();
+                if off {
+                    println!("Two-factor authentication is optional for {who}.");
+                } else {
+                    println!("Two-factor authentication is required for {who}.");
+                }
+            }
+        },
+        Some(Commands::Role { command }) => match command {
+            RoleCommands::Define { name, permissions } => {
+                users.define_role(&name, &permissions)?;
+                notify_auth_cache_invalidated();
+                println!("Defined role {name}.");
+            }
+            RoleCommands::List => {
+                users.print_roles();
+            }
+            RoleCommands::Remove { name } => {
+                users.remove_role(&name)?;
+                notify_auth_cache_invalidated();
+                println!("Removed role {name}.");
+            }
+        },
+        Some(Commands::Access {
+            username,
+            role,
+            scope,
+        }) => {
+            users.set_user_access(&username, role.as_deref(), &scope)?;
+            notify_auth_cache_invalidated();
+            if scope.is_empty() {
+                println!("{username} may see the whole network.");
+            } else {
+                println!("{username} is limited to: {}", scope.join(", "));
+            }
+        }
         None => {
             println!("Run with --help to see instructions");
             exit(0);